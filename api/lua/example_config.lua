@@ -81,16 +81,37 @@ require("pinnacle").setup(function(pinnacle)
         window.get_focused():toggle_maximized()
     end)
 
+    -- mod_key + l toggles locking the pointer to the focused output
+    local pointer_locked = false
+    input.keybind({ mod_key }, keys.l, function()
+        pointer_locked = not pointer_locked
+        input.lock_pointer_to_output(pointer_locked and output.get_focused() or nil)
+    end)
+
     -- Tags ---------------------------------------------------------------------------
 
     local tags = { "1", "2", "3", "4", "5" }
 
-    output.connect_for_all(function(op)
-        -- Add tags 1, 2, 3, 4 and 5 on all monitors, and toggle tag 1 active by default
-
-        op:add_tags(tags)
-        -- Same as tag.add(op, "1", "2", "3", "4", "5")
-        tag.toggle({ name = "1", output = op })
+    output.connect_for_all(function(op, replay)
+        -- Add tags 1, 2, 3, 4 and 5 on all monitors, and toggle tag 1 active by default.
+        -- After a config reload, the tags that were active are toggled instead.
+
+        local tag_handles = tag.add_if_missing(op, tags)
+
+        local restored = false
+        if replay.is_replay then
+            for _, t in pairs(tag_handles) do
+                for _, name in pairs(replay.active_tags) do
+                    if t:name() == name then
+                        t:toggle()
+                        restored = true
+                    end
+                end
+            end
+        end
+        if not restored then
+            tag_handles[1]:toggle()
+        end
 
         -- Window rules
         -- Add your own window rules here. Below is an example.
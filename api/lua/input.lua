@@ -47,6 +47,43 @@ local input_module = {
     libinput = require("input.libinput"),
 }
 
+---@param edge "Press"|"Release"|"Both"
+---@param modifiers (Modifier)[]
+---@param key Keys|string
+---@param action fun()
+---@param group string?
+---@param description string?
+---@param layer string?
+---@param passthrough boolean?
+---@param force boolean?
+---@param repeats boolean?
+local function set_keybind(edge, modifiers, key, action, group, description, layer, passthrough, force, repeats)
+    table.insert(CallbackTable, action)
+
+    local k = {}
+
+    if type(key) == "string" then
+        k.String = key
+    else
+        k.Int = key
+    end
+
+    SendMsg({
+        SetKeybind = {
+            modifiers = modifiers,
+            key = k,
+            edge = edge,
+            callback_id = #CallbackTable,
+            group = group,
+            description = description,
+            layer = layer,
+            passthrough = passthrough or false,
+            force = force or false,
+            repeats = repeats or false,
+        },
+    })
+end
+
 ---Set a keybind. If called with an already existing keybind, it gets replaced.
 ---
 ---You must provide three arguments:
@@ -78,12 +115,210 @@ local input_module = {
 ---    process.spawn("Alacritty")
 ---end)
 ---```
+---
+---The keybind triggers when the key is pressed. Use `input.keybind_on` to trigger it on release.
 ---@param key Keys|string The key for the keybind.
 ---@param modifiers (Modifier)[] Which modifiers need to be pressed for the keybind to trigger.
 ---@param action fun() What to do.
 function input_module.keybind(modifiers, key, action)
-    table.insert(CallbackTable, action)
+    input_module.keybind_on("Press", modifiers, key, action)
+end
 
+---Set a keybind that triggers on the given key edge.
+---If called with an already existing keybind on the same edge, it gets replaced.
+---
+---This is the same as `input.keybind`, but takes an `edge` to choose whether the keybind triggers
+---on key press, key release, or both.
+---
+---Release keybinds only trigger if `modifiers` are still held when the key is released.
+---The key press is also kept from the focused window so it doesn't get a release without a press.
+---
+---### Example
+---
+---```lua
+--- -- Open Alacritty when `Super + Return` is released
+---input.keybind_on("Release", { "Super" }, input.keys.Return, function()
+---    process.spawn("Alacritty")
+---end)
+---```
+---@param edge "Press"|"Release"|"Both" Whether to trigger `action` on key press, release, or both.
+---@param modifiers (Modifier)[] Which modifiers need to be pressed for the keybind to trigger.
+---@param key Keys|string The key for the keybind.
+---@param action fun() What to do.
+function input_module.keybind_on(edge, modifiers, key, action)
+    set_keybind(edge, modifiers, key, action)
+end
+
+---Set a keybind with a group and description.
+---
+---This is the same as `input.keybind`, but also takes a `group`, like "Apps" or "Tags",
+---and a `description` of what the keybind does. These don't change how the keybind works,
+---but are returned by `input.keybinds` so you can show a list of your keybinds.
+---
+---### Example
+---
+---```lua
+---input.keybind_described({ "Super" }, input.keys.Return, "Apps", "Spawn terminal", function()
+---    process.spawn("Alacritty")
+---end)
+---```
+---@param modifiers (Modifier)[] Which modifiers need to be pressed for the keybind to trigger.
+---@param key Keys|string The key for the keybind.
+---@param group string What kind of keybind this is.
+---@param description string What the keybind does.
+---@param action fun() What to do.
+function input_module.keybind_described(modifiers, key, group, description, action)
+    set_keybind("Press", modifiers, key, action, group, description)
+end
+
+---Set a keybind that still sends the key to the focused window.
+---If called with an already existing keybind, it gets replaced.
+---
+---This is the same as `input.keybind`, but the key press isn't kept from the focused window,
+---so you can react to a key without taking it away. This is useful for things like
+---logging keys or toggling an overlay.
+---@param modifiers (Modifier)[] Which modifiers need to be pressed for the keybind to trigger.
+---@param key Keys|string The key for the keybind.
+---@param action fun() What to do.
+function input_module.keybind_passthrough(modifiers, key, action)
+    set_keybind("Press", modifiers, key, action, nil, nil, nil, true)
+end
+
+---Set a keybind that triggers even while the focused window inhibits keyboard shortcuts.
+---If called with an already existing keybind, it gets replaced.
+---
+---VM viewers and remote desktop clients can inhibit shortcuts while they're focused
+---so keys like Super go to them. No other keybinds trigger then, including the kill
+---and reload keybinds, so use this for a way out.
+---
+---These also trigger while a focused X11 app like a game has grabbed the keyboard.
+---The kill and reload keybinds work then too, see `x11_keyboard_grab` in `metaconfig.toml`.
+---@param modifiers (Modifier)[] Which modifiers need to be pressed for the keybind to trigger.
+---@param key Keys|string The key for the keybind.
+---@param action fun() What to do.
+function input_module.keybind_force(modifiers, key, action)
+    set_keybind("Press", modifiers, key, action, nil, nil, nil, false, true)
+end
+
+---Set a keybind whose action is run again while the key is held.
+---If called with an already existing keybind, it gets replaced.
+---
+---This is the same as `input.keybind`, but after the keyboard's repeat delay `action` is run
+---at its repeat rate, like a held key types repeatedly. Set these with `input.set_repeat_rate`.
+---This is useful for things like changing the volume or resizing windows.
+---
+---Repeating stops when the key or one of `modifiers` is released, another key is pressed,
+---or keyboard focus changes.
+---@param modifiers (Modifier)[] Which modifiers need to be pressed for the keybind to trigger.
+---@param key Keys|string The key for the keybind.
+---@param action fun() What to do.
+function input_module.keybind_repeating(modifiers, key, action)
+    set_keybind("Press", modifiers, key, action, nil, nil, nil, false, false, true)
+end
+
+---Set a keybind in a keybind layer.
+---If called with an already existing keybind in the same layer, it gets replaced.
+---
+---This is the same as `input.keybind`, but the keybind only triggers while `layer` is active.
+---Keybinds set with the other functions are in the default layer, which is only active
+---when no layer is entered. Enter a layer with `input.enter_bind_layer`.
+---
+---### Example
+---
+---```lua
+--- -- Super + r enters a resize mode where Escape leaves it again
+---input.keybind({ "Super" }, "r", function()
+---    input.enter_bind_layer("resize")
+---end)
+---input.layer_keybind("resize", {}, input.keys.Escape, function()
+---    input.leave_bind_layer()
+---end)
+---```
+---@param layer string The layer the keybind is in.
+---@param modifiers (Modifier)[] Which modifiers need to be pressed for the keybind to trigger.
+---@param key Keys|string The key for the keybind.
+---@param action fun() What to do.
+function input_module.layer_keybind(layer, modifiers, key, action)
+    set_keybind("Press", modifiers, key, action, nil, nil, layer)
+end
+
+---Make `layer` the active keybind layer.
+---
+---Only keybinds set with `input.layer_keybind` for `layer` trigger until another layer is entered
+---or `input.leave_bind_layer` is called. Key presses without a keybind in the layer are swallowed
+---unless `input.set_bind_layer_forwards_unbound` was called for it. The kill and reload keybinds,
+---media keys, and VT switching still work.
+---@param layer string
+function input_module.enter_bind_layer(layer)
+    SendMsg({
+        EnterBindLayer = {
+            layer = layer,
+        },
+    })
+end
+
+---Go back to the default keybind layer.
+function input_module.leave_bind_layer()
+    SendMsg({
+        EnterBindLayer = {},
+    })
+end
+
+---Set whether `layer` sends key presses it has no keybind for to the focused window
+---instead of swallowing them.
+---@param layer string
+---@param forward boolean
+function input_module.set_bind_layer_forwards_unbound(layer, forward)
+    SendMsg({
+        SetBindLayerForwardsUnbound = {
+            layer = layer,
+            forward = forward,
+        },
+    })
+end
+
+---Connect to the active keybind layer changing.
+---
+---`func` gets the layer that was entered and the one that was left,
+---where `nil` is the default layer. Use this to show which mode you're in.
+---@param func fun(layer: string?, previous: string?)
+function input_module.connect_bind_layer_changed(func)
+    ---@param args Args
+    table.insert(CallbackTable, function(args)
+        local changed = args.BindLayerChanged
+        func(changed.layer, changed.previous)
+    end)
+    SendMsg({
+        ConnectBindLayerChanged = {
+            callback_id = #CallbackTable,
+        },
+    })
+end
+
+---Get all keybinds your config has set, sorted by layer, group, and then key.
+---
+---`key` is the raw keysym and `key_name` is its xkbcommon name, like "Return" or "a".
+---@return { modifiers: Modifier[], key: integer, key_name: string, edge: "Press"|"Release"|"Both", group: string?, description: string?, layer: string?, passthrough: boolean, force: boolean, repeats: boolean }[]
+function input_module.keybinds()
+    local response = Request("GetKeybinds")
+    return response.RequestResponse.response.Keybinds.keybinds
+end
+
+---Remove a keybind set with `input.keybind` or `input.keybind_on`.
+---
+---### Example
+---
+---```lua
+---input.keybind({ "Super" }, input.keys.Return, function() end)
+---
+--- -- Later
+---input.remove_keybind({ "Super" }, input.keys.Return)
+---```
+---@param modifiers (Modifier)[] The modifiers of the keybind.
+---@param key Keys|string The key of the keybind.
+---@param edge ("Press"|"Release"|"Both")? The edge of the keybind. Defaults to `"Press"`.
+---@param layer string? The layer of the keybind. Defaults to the default layer.
+function input_module.remove_keybind(modifiers, key, edge, layer)
     local k = {}
 
     if type(key) == "string" then
@@ -93,10 +328,11 @@ function input_module.keybind(modifiers, key, action)
     end
 
     SendMsg({
-        SetKeybind = {
+        RemoveKeybind = {
             modifiers = modifiers,
             key = k,
-            callback_id = #CallbackTable,
+            edge = edge or "Press",
+            layer = layer,
         },
     })
 end
@@ -106,12 +342,23 @@ end
 ---The mousebind can happen either on button press or release, so you must specify
 ---which edge you desire.
 ---
+---It can also happen on the second press of a double click with the `"DoubleClick"` edge.
+---The first click still triggers `"Press"` binds. The second click triggers the `"DoubleClick"` bind
+---instead of the `"Press"` bind on the same modifiers and button. Release binds trigger on both releases.
+---The second press has to come within the interval set with `input.set_double_click_interval`,
+---without the pointer moving more than a few pixels.
+---
+---`action` gets the modifiers that were held when the mousebind triggered. Modifiers that are held
+---down or latched, like with sticky keys, count as held. Locked ones like Caps Lock don't.
+---
 ---@param modifiers (Modifier)[] The modifiers that need to be held for the mousebind to trigger.
----@param button MouseButton The button that needs to be pressed or released.
----@param edge "Press"|"Release" Whether or not to trigger `action` on button press or release.
----@param action fun() The function to run.
+---@param button MouseButton|integer The button that needs to be pressed or released, or a raw evdev button code.
+---@param edge "Press"|"Release"|"DoubleClick" Whether to trigger `action` on button press, release, or double click.
+---@param action fun(modifiers: (Modifier)[]) The function to run.
 function input_module.mousebind(modifiers, button, edge, action)
-    table.insert(CallbackTable, action)
+    table.insert(CallbackTable, function(args)
+        action(args and args.MousebindTriggered and args.MousebindTriggered.modifiers or {})
+    end)
 
     SendMsg({
         SetMousebind = {
@@ -123,9 +370,93 @@ function input_module.mousebind(modifiers, button, edge, action)
     })
 end
 
+---Set a scrollbind. If called with an already existing scrollbind, it gets replaced.
+---
+---`action` runs once per wheel click in `direction` while `modifiers` are held.
+---Continuous scrolling, like from a touchpad, is added up so a flick only runs `action`
+---a few times. Scrolling that matches a scrollbind isn't sent to the window under the pointer.
+---
+---### Example
+---
+---```lua
+---input.scrollbind({ "Super" }, "Down", function()
+---    print("Scrolled down while holding Super")
+---end)
+---```
+---@param modifiers (Modifier)[] The modifiers that need to be held for the scrollbind to trigger.
+---@param direction "Up"|"Down"|"Left"|"Right" The direction to scroll in.
+---@param action fun() The function to run.
+function input_module.scrollbind(modifiers, direction, action)
+    table.insert(CallbackTable, action)
+
+    SendMsg({
+        SetScrollbind = {
+            modifiers = modifiers,
+            direction = direction,
+            callback_id = #CallbackTable,
+        },
+    })
+end
+
+---Set a touchpad swipe bind. If called with an already existing swipe bind, it gets replaced.
+---
+---`action` runs when a swipe with `fingers` fingers ends, if it moved furthest in `direction`.
+---Swipes with a finger count that has a bind in any direction aren't sent to windows.
+---
+---### Example
+---
+---```lua
+---input.gesturebind(4, "Left", function()
+---    print("Swiped left with four fingers")
+---end)
+---```
+---@param fingers integer The number of fingers to swipe with.
+---@param direction "Up"|"Down"|"Left"|"Right" The direction to swipe in.
+---@param action fun() The function to run.
+function input_module.gesturebind(fingers, direction, action)
+    table.insert(CallbackTable, action)
+
+    SendMsg({
+        SetGesturebind = {
+            fingers = fingers,
+            direction = direction,
+            callback_id = #CallbackTable,
+        },
+    })
+end
+
+---Set a touchpad pinch bind. If called with an already existing pinch bind, it gets replaced.
+---
+---`action` runs when a pinch with `fingers` fingers ends, if it pinched in `direction`
+---by at least `threshold`. The threshold is how far the pinch's scale moved from 1.0,
+---so `0.2` triggers when the fingers end up 20% closer together or further apart.
+---Pinches with a finger count that has a bind in either direction aren't sent to windows.
+---
+---@param fingers integer The number of fingers to pinch with.
+---@param direction "In"|"Out" Whether the fingers move together or apart.
+---@param threshold number? How far the scale needs to move. Defaults to 0.2.
+---@param action fun() The function to run.
+function input_module.pinchbind(fingers, direction, threshold, action)
+    table.insert(CallbackTable, action)
+
+    SendMsg({
+        SetPinchbind = {
+            fingers = fingers,
+            direction = direction,
+            threshold = threshold or 0.2,
+            callback_id = #CallbackTable,
+        },
+    })
+end
+
 ---Set the xkbconfig for your keyboard.
 ---
 ---Fields not present will be set to their default values.
+---This can be called at any time, like from a keybind, and keybinds keep working
+---with the new layout.
+---
+---If xkbcommon can't build a keymap from these, like with a misspelled layout,
+---the keymap isn't changed and Pinnacle sends back a warning.
 ---
 ---Read `xkeyboard-config(7)` for more information.
 ---
@@ -144,4 +475,248 @@ function input_module.set_xkb_config(xkb_config)
     })
 end
 
+---Set how fast held keys repeat.
+---
+---Keys repeat `rate` times a second after being held for `delay` milliseconds.
+---Both need to be positive; otherwise Pinnacle ignores this.
+---
+---This stays set across config reloads.
+---
+---### Example
+---```lua
+---input.set_repeat_rate(25, 500)
+---```
+---@param rate integer Repeats per second.
+---@param delay integer Milliseconds to hold a key before it starts repeating.
+function input_module.set_repeat_rate(rate, delay)
+    SendMsg({
+        SetKeyboardRepeat = {
+            rate = rate,
+            delay = delay,
+        },
+    })
+end
+
+---Set the command that runs when `key` is pressed while default media keys are enabled.
+---
+---Passing an empty `command` removes the handler for `key`.
+---Keybinds always take precedence over these.
+---
+---### Example
+---```lua
+---input.set_media_key_command(input.keys.XF86AudioPlay, { "mpc", "toggle" })
+---```
+---@param key Keys|string The media key.
+---@param command string[] The command to run.
+function input_module.set_media_key_command(key, command)
+    local k = {}
+
+    if type(key) == "string" then
+        k.String = key
+    else
+        k.Int = key
+    end
+
+    SendMsg({
+        SetMediaKeyCommand = {
+            key = k,
+            command = command,
+        },
+    })
+end
+
+---Lock the pointer to `op`, or unlock it if `op` is nil.
+---
+---While locked, the pointer can't leave the output. It gets unlocked if the output is disconnected.
+---@param op OutputHandle?
+function input_module.lock_pointer_to_output(op)
+    SendMsg({
+        LockPointerToOutput = {
+            output_name = op and op:name(),
+        },
+    })
+end
+
+---Set how many pixels the pointer needs to be pushed past the edge of an output before it
+---crosses over to the next one.
+---
+---The default is 0, meaning the pointer crosses freely.
+---@param pixels integer
+function input_module.set_edge_resistance(pixels)
+    SendMsg({
+        SetEdgeResistance = {
+            pixels = pixels,
+        },
+    })
+end
+
+---Set the longest time between the two clicks of a double click.
+---
+---The default is 400 milliseconds.
+---@param interval_ms integer
+function input_module.set_double_click_interval(interval_ms)
+    SendMsg({
+        SetDoubleClickInterval = {
+            interval_ms = interval_ms,
+        },
+    })
+end
+
+---Set where the pointer goes when your config changes keyboard focus, like by focusing a window
+---or switching tags.
+---
+--- - `"None"`: Leave the pointer alone. This is the default.
+--- - `"CenterOnFocus"`: Move the pointer to the center of the focused window.
+---
+---This keeps the pointer on the same output as keyboard focus, so scrolling goes to the focused window.
+---Focus changes from clicks and scrollbinds never move the pointer, and neither do ones where the pointer
+---is already over the window. The pointer also stays put while it's locked to an output.
+---
+---### Example
+---```lua
+---input.set_cursor_warp("CenterOnFocus")
+---```
+---@param warp CursorWarp
+function input_module.set_cursor_warp(warp)
+    SendMsg({
+        SetCursorWarp = {
+            warp = warp,
+        },
+    })
+end
+
+---Set what long presses on touchscreens do.
+---
+---Touchscreens have no right click and no way to drag windows, so holding fingers still
+---does that instead:
+--- - Holding one finger right clicks what's under it.
+--- - Holding one finger on the edge of a window, or holding two fingers on it, starts moving
+---   the window. The finger drives the move, and lifting it drops the window.
+---
+---Lifting or moving a finger before the long press cancels it, as does a third finger.
+---
+---Fields left out use their defaults: `enabled` is true, `long_press_ms` is 500,
+---and `tolerance` is 8 pixels.
+---
+---### Example
+---```lua
+---input.set_touch_gestures({ long_press_ms = 700 })
+---```
+---@param gestures { enabled: boolean?, long_press_ms: integer?, tolerance: number? }
+function input_module.set_touch_gestures(gestures)
+    SendMsg({
+        SetTouchGestures = {
+            gestures = {
+                enabled = gestures.enabled ~= false,
+                long_press_ms = gestures.long_press_ms or 500,
+                tolerance = gestures.tolerance or 8.0,
+            },
+        },
+    })
+end
+
+---Multiply scroll amounts by `factor`.
+---
+---If `device_name` is provided, this only applies to the device with that name
+---and overrides the factor for every device. Otherwise, it applies to every device.
+---
+---Discrete scroll steps, like the notches on a mouse wheel, stay whole numbers:
+---with a factor of 1.5, every other notch scrolls twice.
+---
+---The default is 1.0.
+---@param factor number
+---@param device_name string?
+function input_module.set_scroll_factor(factor, device_name)
+    SendMsg({
+        SetScrollFactor = {
+            factor = factor,
+            device_name = device_name,
+        },
+    })
+end
+
+---Flip the direction of horizontal scrolling.
+---
+---This is independent of libinput's natural scrolling setting.
+---If `device_name` is provided, this only applies to the device with that name.
+---Otherwise, it applies to every device.
+---@param invert boolean
+---@param device_name string?
+function input_module.set_horizontal_invert(invert, device_name)
+    SendMsg({
+        SetHorizontalScrollInvert = {
+            invert = invert,
+            device_name = device_name,
+        },
+    })
+end
+
+---Get all connected input devices.
+---
+---Devices are only reported when running on a tty, as other backends don't expose them.
+---@return { name: string, scroll_factor: number, horizontal_invert: boolean }[]
+function input_module.devices()
+    local response = Request("GetInputDevices")
+    local devices = response.RequestResponse.response.InputDevices.devices
+
+    ---@type { name: string, scroll_factor: number, horizontal_invert: boolean }[]
+    local ret = {}
+    for _, device in pairs(devices) do
+        table.insert(ret, {
+            name = device.name,
+            scroll_factor = device.scroll_factor,
+            horizontal_invert = device.horizontal_scroll_invert,
+        })
+    end
+
+    return ret
+end
+
+---Connect a function to be run whenever an input device is added.
+---
+---This runs after the device gets your libinput settings, so you can change them here,
+---for example to turn off the touchpad when a mouse is plugged in.
+---Devices are only reported when running on a tty, as other backends don't expose them.
+---
+---### Example
+---```lua
+---input.connect_device_added(function(name, device_types)
+---    for _, device_type in pairs(device_types) do
+---        if device_type == "Pointer" then
+---            print("Mouse added: " .. name)
+---        end
+---    end
+---end)
+---```
+---@param func fun(name: string, device_types: DeviceType[]) The function that will be run with the new device's name and types.
+function input_module.connect_device_added(func)
+    ---@param args Args
+    table.insert(CallbackTable, function(args)
+        local device = args.DeviceAdded
+        func(device.name, device.device_types)
+    end)
+    SendMsg({
+        ConnectDeviceAdded = {
+            callback_id = #CallbackTable,
+        },
+    })
+end
+
+---Get the current media key mappings.
+---
+---This returns an array of tables with the raw keysym in `key` and its command in `command`.
+---@return { key: integer, command: string[] }[]
+function input_module.media_key_commands()
+    local response = Request("GetMediaKeyCommands")
+    local commands = response.RequestResponse.response.MediaKeyCommands.commands
+
+    ---@type { key: integer, command: string[] }[]
+    local ret = {}
+    for _, pair in pairs(commands) do
+        table.insert(ret, { key = pair[1], command = pair[2] })
+    end
+
+    return ret
+end
+
 return input_module
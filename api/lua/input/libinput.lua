@@ -17,6 +17,19 @@
 ---@field TapDragLockEnabled boolean?
 ---@field TapEnabled boolean?
 
+---@class DeviceMatcher
+---@field Name string? The device with exactly this name.
+---@field NameContains string? Devices whose name contains this.
+---@field Type DeviceType? Devices of this type.
+
+---@alias DeviceType
+---| "Keyboard"
+---| "Pointer" # Mice, trackballs, and other pointer devices that aren't touchpads.
+---| "Touchpad"
+---| "Touch" # Touchscreens.
+---| "Tablet"
+---| "Switch" # Switches, like laptop lids.
+
 ---@alias AccelProfile
 ---| "Flat" # Flat pointer acceleration.
 ---| "Adaptive" Adaptive pointer acceleration. This is the default for most devices.
@@ -208,4 +221,65 @@ function libinput.set_tap_drag_lock_enabled(enabled)
     })
 end
 
+---Set a libinput setting for only the devices `device` matches.
+---
+---A string matches devices whose name contains it. Settings for devices that aren't
+---connected are kept and applied when they are plugged in.
+---
+---When settings of the same kind apply to a device, the one with the most specific
+---matcher wins: `Name`, then `NameContains`, then `Type`, then settings for all devices.
+---
+---### Example
+---```lua
+----- Natural scrolling on the touchpad only
+---input.libinput.set_for_device("SynPS/2", { NaturalScrollEnabled = true })
+---
+----- Flat acceleration for every mouse
+---input.libinput.set_for_device({ Type = "Pointer" }, { AccelProfile = "Flat" })
+---```
+---@param device DeviceMatcher|string
+---@param setting LibinputSetting
+function libinput.set_for_device(device, setting)
+    if type(device) == "string" then
+        device = { NameContains = device }
+    end
+
+    SendMsg({
+        SetDeviceLibinputSetting = {
+            device = device,
+            setting = setting,
+        },
+    })
+end
+
+---Spread absolute pointer events from the devices `device` matches, like tablets and touchscreens,
+---over `op` only. Pass `nil` to spread them over every output again.
+---
+---By default, absolute devices cover every output together, so the left edge of a tablet is the
+---left edge of your leftmost output. If `op` gets disconnected, the devices cover every output
+---until it's back.
+---
+---A string matches devices whose name contains it. Like with `set_for_device`,
+---the most specific matcher wins.
+---
+---### Example
+---```lua
+------ Keep every tablet on the focused output
+---input.libinput.map_to_output({ Type = "Tablet" }, output.get_focused())
+---```
+---@param device DeviceMatcher|string
+---@param op OutputHandle?
+function libinput.map_to_output(device, op)
+    if type(device) == "string" then
+        device = { NameContains = device }
+    end
+
+    SendMsg({
+        MapDeviceToOutput = {
+            device = device,
+            output_name = op and op:name(),
+        },
+    })
+end
+
 return libinput
@@ -3,17 +3,47 @@
 ---@meta _
 
 ---@class _Msg
----@field SetKeybind { key: { Int: Keys?, String: string? }, modifiers: Modifier[], callback_id: integer }?
----@field SetMousebind { modifiers: (Modifier)[], button: integer, edge: "Press"|"Release", callback_id: integer }?
+---@field SetKeybind { key: { Int: Keys?, String: string? }, modifiers: Modifier[], edge: "Press"|"Release"|"Both", callback_id: integer, group: string?, description: string?, layer: string?, passthrough: boolean, force: boolean, repeats: boolean }?
+---@field RemoveKeybind { key: { Int: Keys?, String: string? }, modifiers: Modifier[], edge: "Press"|"Release"|"Both", layer: string? }?
+---@field EnterBindLayer { layer: string? }?
+---@field SetBindLayerForwardsUnbound { layer: string, forward: boolean }?
+---@field SetMousebind { modifiers: (Modifier)[], button: integer, edge: "Press"|"Release"|"DoubleClick", callback_id: integer }?
+---@field SetScrollbind { modifiers: (Modifier)[], direction: "Up"|"Down"|"Left"|"Right", callback_id: integer }?
+---@field SetGesturebind { fingers: integer, direction: "Up"|"Down"|"Left"|"Right", callback_id: integer }?
+---@field SetPinchbind { fingers: integer, direction: "In"|"Out", threshold: number, callback_id: integer }?
+---@field SetMediaKeysEnabled { enabled: boolean }?
+---@field SetMediaKeyCommand { key: { Int: Keys?, String: string? }, command: string[] }?
+---@field SetMoveOverride { modifiers: (Modifier)[]?, on_drop: MoveOverrideDrop }?
+---@field SetSnapThreshold { threshold: integer }?
+---@field LockPointerToOutput { output_name: OutputName? }?
+---@field SetEdgeResistance { pixels: integer }?
+---@field SetDoubleClickInterval { interval_ms: integer }?
+---@field SetCursorWarp { warp: CursorWarp }?
+---@field SetTouchGestures { gestures: TouchGestures }?
 --Windows
 ---@field CloseWindow { window_id: WindowId }?
 ---@field SetWindowSize { window_id: WindowId, width: integer?, height: integer? }?
+---@field MoveWindowBy { window_id: WindowId, dx: integer, dy: integer }?
+---@field ResizeWindowBy { window_id: WindowId, dx: integer, dy: integer }?
+---@field SetWindowGeometry { window_id: WindowId, x: integer, y: integer, width: integer, height: integer }?
+---@field SetFollowActiveTag { window_id: WindowId, follow: boolean }?
 ---@field MoveWindowToTag { window_id: WindowId, tag_id: TagId }?
 ---@field ToggleTagOnWindow { window_id: WindowId, tag_id: TagId }?
 ---@field ToggleFloating { window_id: WindowId }?
 ---@field ToggleFullscreen { window_id: WindowId }?
 ---@field ToggleMaximized { window_id: WindowId }?
+---@field SetFloating { window_id: WindowId, floating: boolean }?
+---@field SetFullscreen { window_id: WindowId, fullscreen: boolean }?
+---@field SetMaximized { window_id: WindowId, maximized: boolean }?
+---@field FlashWindow { window_id: WindowId, duration_ms: integer, color: number[] }?
+---@field RaiseWindow { window_id: WindowId }?
+---@field FocusWindow { window_id: WindowId }?
+---@field CycleWindowFocus { output_name: OutputName?, forward: boolean }?
+---@field CycleMruFocus { direction: CycleDirection }?
+---@field SwapWindowInDirection { direction: Direction }?
 ---@field AddWindowRule { cond: _WindowRuleCondition, rule: _WindowRule }?
+---@field AddStateRequestHandler { cond: _WindowRuleCondition, callback_id: integer }?
+---@field DecideStateRequest { token: integer, decision: StateRequestDecision }?
 ---@field WindowMoveGrab { button: integer }?
 ---@field WindowResizeGrab { button: integer }?
 --
@@ -25,41 +55,175 @@
 ---@field AddTags { output_name: string, tag_names: string[] }?
 ---@field RemoveTags { tag_ids: TagId[] }?
 ---@field SetLayout { tag_id: TagId, layout: Layout }?
+---@field SetMasterFactor { tag_id: TagId, master_factor: number }?
+---@field SetTagDefaultWindowMode { tag_id: TagId, floating_or_tiled: ("Floating"|"Tiled")? }?
+---@field SetTagIndex { tag_id: TagId, index: integer }?
 --Outputs
 ---@field ConnectForAllOutputs { callback_id: integer }?
+---@field SetHotplugDebounce { debounce_ms: integer }?
+---@field ConnectTagOrderChanged { callback_id: integer }?
+---@field ConnectTagActiveChanged { callback_id: integer }?
+---@field ConnectOutputConnected { callback_id: integer }?
+---@field ConnectOutputDisconnected { callback_id: integer }?
+---@field ConnectOutputModeChanged { callback_id: integer }?
+---@field ConnectLayoutApplied { callback_id: integer }?
+---@field ConnectKeyboardFocusChanged { callback_id: integer }?
+---@field ConnectPointerFocusChanged { callback_id: integer }?
+---@field ConnectWindowTitleChanged { callback_id: integer }?
+---@field ConnectWindowClassChanged { callback_id: integer }?
+---@field ConnectWindowOpened { callback_id: integer }?
+---@field ConnectWindowClosed { callback_id: integer }?
+---@field ConnectDeviceAdded { callback_id: integer }?
+---@field ConnectClientDisconnected { callback_id: integer }?
+---@field ConnectBindLayerChanged { callback_id: integer }?
 ---@field SetOutputLocation { output_name: OutputName, x: integer?, y: integer? }?
+---@field SetOutputRenderScale { output_name: OutputName, render_scale: number }?
+---@field SetOutputDefaultWindowMode { output_name: OutputName, floating_or_tiled: ("Floating"|"Tiled")? }?
 --Input
 ---@field SetXkbConfig XkbConfig?
+---@field SetKeyboardRepeat { rate: integer, delay: integer }?
 ---@field SetLibinputSetting LibinputSetting?
+---@field SetDeviceLibinputSetting { device: DeviceMatcher, setting: LibinputSetting }?
+---@field MapDeviceToOutput { device: DeviceMatcher, output_name: OutputName? }?
+---@field SetScrollFactor { factor: number, device_name: string? }?
+---@field SetHorizontalScrollInvert { invert: boolean, device_name: string? }?
+--Pinnacle
+---@field SetDoNotDisturb { enabled: boolean }?
+---@field SetDoNotDisturbNamespaces { namespaces: string[] }?
+---@field ConnectDoNotDisturbChanged { callback_id: integer }?
+---@field OnStartupComplete { callback_id: integer }?
+---@field SetDebugOverlay { flags: integer }?
+---@field SetGrabHints { enabled: boolean }?
+---@field SetUiFont { family: string, size: integer }?
+---@field Prompt { title: string, callback_id: integer }?
+---@field SetFlashOnFocus { enabled: boolean }?
+---@field SetFocusFollowsMovedWindow { policy: "Stay"|"SwitchTag"|"FocusNext" }?
+---@field SetAutoTidy { after_ms: integer, tag_name: string, enabled: boolean }?
+---@field SetApiFocusCountsAsActivity { counts: boolean }?
+---@field SetRememberWindowOutput { enabled: boolean }?
+---@field ForgetWindowPlacement { class: string }?
+---@field SetInsertPosition { position: InsertPosition }?
+---@field SetBackgroundThrottle { policy: BackgroundThrottle }?
+---@field SetSandboxPolicy { policy: SandboxPolicy }?
+---@field SetRaiseOnClick { enabled: boolean }?
+---@field ConnectReload { callback_id: integer }?
+---@field SetSlowRequestThreshold { threshold_ms: integer }?
 ---@field Request Request?
 
----@alias Msg _Msg | "Quit"
+---@alias Msg _Msg | "Quit" | "ReloadConfig" | "ConfigReady" | "OneOffClient" | "ApplyPreviousSession" | "CommitMruCycle"
+
+---@alias MoveOverrideDrop
+---| "ReturnToTile"
+---| "FloatOnOtherOutput"
+
+---@alias CycleDirection
+---| "Forward"
+---| "Backward"
+
+---@alias Direction
+---| "Left"
+---| "Right"
+---| "Up"
+---| "Down"
+
+---@alias InsertPosition
+---| "Append"
+---| "Prepend"
+---| "AfterFocused"
+---| "BeforeFocused"
+
+---@alias BackgroundThrottle
+---| "None" # Every window gets frame callbacks whenever its output repaints.
+---| { ThrottleOccluded: { fps: integer } } # Hidden and occluded windows get at most `fps` frame callbacks a second.
+---| "SuspendHidden" # Hidden windows get no frame callbacks. Occluded windows get them as usual.
+
+---@alias ReloadEvent
+---| "Started"
+---| "Finished"
+---| "TimedOut"
 
 ---@alias FullscreenOrMaximized
 ---| "Neither"
 ---| "Fullscreen"
 ---| "Maximized"
 
+---@alias CursorWarp
+---| "None"
+---| "CenterOnFocus"
+
+---@class TouchGestures
+---@field enabled boolean
+---@field long_press_ms integer
+---@field tolerance number
+
 --------------------------------------------------------------------------------------------
 
 ---@class __Request
 --Windows
 ---@field GetWindowProps { window_id: WindowId }?
+---@field WaitForWindow { condition: _WindowRuleCondition, timeout_ms: integer }?
+---@field GetInactiveWindows { inactive_ms: integer }?
 --Outputs
 ---@field GetOutputProps { output_name: string }?
 --Tags
 ---@field GetTagProps { tag_id: TagId }?
+---@field AddTags { output_name: string, tag_names: string[] }?
+--Pinnacle
+---@field MeasureText { text: string, size_px: integer? }?
 
----@alias _Request __Request | "GetWindows" | "GetOutputs" | "GetTags"
+---@alias _Request __Request | "GetWindows" | "GetPointerFocusedWindow" | "GetPreviousFocusedWindow" | "GetOrphanedWindows" | "GetOutputs" | "GetTags" | "GetMediaKeyCommands" | "GetInputDevices" | "GetKeybinds" | "GetDoNotDisturb" | "GetCompositorInfo" | "GetRecentDisconnects" | "GetApiStats" | "GetPreviousSession" | "DumpState"
 ---@alias Request { request_id: integer, request: _Request }
 
 ---@class IncomingMsg
----@field CallCallback { callback_id: integer, args: Args? }?
+---@field CallCallback { callback_id: integer, args: Args?, serial: integer }?
 ---@field RequestResponse { request_id: integer, response: RequestResponse }?
+---@field Warning { message: string }?
 
 ---@class Args
 ---@field Spawn { stdout: string?, stderr: string?, exit_code: integer?, exit_msg: string? }?
----@field ConnectForAllOutputs { output_name: string }?
+---@field ConnectForAllOutputs { output_name: string, is_replay: boolean?, tag_names: string[]?, active_tags: string[]? }?
+---@field TagOrderChanged { output_name: string }?
+---@field TagActiveChanged { tag_id: TagId, name: string, output_name: string?, active: boolean }?
+---@field OutputGeometry { output_name: string, x: integer, y: integer, width: integer, height: integer }?
+---@field LayoutApplied { output_name: string, tag_id: TagId, generation: integer, windows: [WindowId, integer, integer, integer, integer][] }?
+---@field WindowFocusChanged { window_id: WindowId? }?
+---@field WindowTitleChanged { window_id: WindowId, title: string? }?
+---@field WindowClassChanged { window_id: WindowId, class: string? }?
+---@field WindowOpened { window_id: WindowId, class: string?, title: string? }?
+---@field WindowClosed { window_id: WindowId, class: string?, title: string? }?
+---@field DeviceAdded { name: string, device_types: DeviceType[] }?
+---@field ClientDisconnected { disconnect: ClientDisconnect }?
+---@field BindLayerChanged { layer: string?, previous: string? }?
+---@field ReloadEvent { event: ReloadEvent }?
+---@field DoNotDisturbChanged { enabled: boolean }?
+---@field StateRequest { window_id: WindowId, token: integer, state: RequestedState }?
+---@field PromptFinished { text: string? }?
+---@field MousebindTriggered { modifiers: (Modifier)[] }?
+
+---@alias DisconnectReason
+---| "Clean" # The client closed its connection after closing its windows.
+---| "Crashed" # The client's connection closed while it still had windows open, like when it crashes or is killed.
+---| { ProtocolError: { interface: string, object_id: integer, code: integer, message: string } } # Pinnacle disconnected the client because it broke the Wayland protocol.
+
+---@class ClientDisconnect
+---@field pid integer? The client's process id, if it was known.
+---@field process_name string? The client's process name, like `firefox`, if it was known.
+---@field reason DisconnectReason Why the client disconnected.
+---@field window_ids WindowId[] The ids of the client's windows that were open when it disconnected.
+---@field seconds_ago integer How long ago the client disconnected.
+
+---@class Session
+---@field version integer The version of the format the session was saved in.
+---@field outputs { name: string, tags: { name: string, active: boolean, layout: Layout? }[] }[] Every output that was connected, with its tags in order.
+---@field windows SessionWindow[] Every window, from the bottom of the stack to the top. Windows that weren't shown come first.
+
+---@class SessionWindow
+---@field class string?
+---@field title string?
+---@field output_name string? The name of the output the window's tags were on.
+---@field tags string[] The names of the window's tags.
+---@field floating boolean Whether the window was floating.
+---@field geometry { x: integer, y: integer, width: integer, height: integer }? Where the window was floating, or would have floated if it was tiled.
 
 ---@alias WindowId integer | "None"
 ---@alias TagId integer | "None"
@@ -70,11 +234,23 @@
 --Windows
 ---@field Window { window_id: WindowId }?
 ---@field Windows { window_ids: WindowId[] }?
----@field WindowProps { size: integer[]?, loc: integer[]?, class: string?, title: string?, focused: boolean?, floating: boolean?, fullscreen_or_maximized: FullscreenOrMaximized? }?
+---@field WindowProps { size: integer[]?, loc: integer[]?, class: string?, title: string?, focused: boolean?, floating: boolean?, fullscreen_or_maximized: FullscreenOrMaximized?, resize_increments: integer[]?, seconds_since_activity: integer?, keyboard_shortcuts_inhibited: boolean?, group: integer?, sandbox_app_id: string? }?
 --Outputs
 ---@field Output { output_name: OutputName? }?
 ---@field Outputs { output_names: OutputName[] }?
----@field OutputProps { make: string?, model: string?, loc: integer[]?, res: integer[]?, refresh_rate: integer?, physical_size: integer[]?, focused: boolean?, tag_ids: integer[]? }?
+---@field OutputProps { make: string?, model: string?, loc: integer[]?, res: integer[]?, refresh_rate: integer?, physical_size: integer[]?, focused: boolean?, tag_ids: integer[]?, render_scale: number?, default_window_mode: ("Floating"|"Tiled")? }?
 --Tags
 ---@field Tags { tag_ids: TagId[] }?
----@field TagProps { active: boolean?, name: string?, output_name: string? }?
+---@field TagProps { active: boolean?, name: string?, output_name: string?, master_factor: number?, default_window_mode: ("Floating"|"Tiled")? }?
+--Pinnacle
+---@field DoNotDisturb { enabled: boolean }?
+---@field CompositorInfo { version: string, backend: "winit"|"udev", software_rendering: boolean, event_loop_wakeups: integer }?
+---@field TextSize { width: integer, height: integer }?
+---@field StateDump { dump: string }?
+---@field RecentDisconnects { disconnects: ClientDisconnect[] }?
+---@field PreviousSession { session: Session? }?
+---@field ApiStats { bucket_bounds_us: integer[], requests: { name: string, count: integer, total_us: integer, max_us: integer, buckets: integer[] }[] }?
+--Input
+---@field MediaKeyCommands { enabled: boolean, commands: [integer, string[]][] }?
+---@field InputDevices { devices: { name: string, scroll_factor: number, horizontal_scroll_invert: boolean }[] }?
+---@field Keybinds { keybinds: { modifiers: Modifier[], key: integer, key_name: string, edge: "Press"|"Release"|"Both", group: string?, description: string?, layer: string?, passthrough: boolean, force: boolean, repeats: boolean }[] }?
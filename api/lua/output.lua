@@ -60,12 +60,20 @@ function output_handle:tags()
     return output.tags(self)
 end
 
+---Get the tags on this output in the order they are displayed.
+---@return TagHandle[]
+---@see Output.tag_order — The corresponding module function
+function output_handle:tag_order()
+    return output.tag_order(self)
+end
+
 ---Add tags to this output.
 ---@param ... string The names of the tags you want to add. You can also pass in a table.
----@overload fun(self: self, tag_names: string[])
+---@return TagHandle[] tags The new tags, in the same order as their names.
+---@overload fun(self: self, tag_names: string[]): TagHandle[]
 ---@see Output.add_tags — The corresponding module function
 function output_handle:add_tags(...)
-    output.add_tags(self, ...)
+    return output.add_tags(self, ...)
 end
 
 ---Get this output's make.
@@ -118,6 +126,20 @@ function output_handle:focused()
     return output.focused(self)
 end
 
+---Get the fraction of this output's resolution it is rendered at.
+---@return number|nil
+---@see Output.render_scale — The corresponding module function
+function output_handle:render_scale()
+    return output.render_scale(self)
+end
+
+---Get whether new windows on this output float or tile when their tags don't say.
+---@return ("Floating"|"Tiled")|nil
+---@see Output.default_window_mode — The corresponding module function
+function output_handle:default_window_mode()
+    return output.default_window_mode(self)
+end
+
 ---Set this output's location.
 ---
 ---### Examples
@@ -143,6 +165,28 @@ function output_handle:set_loc(loc)
     output.set_loc(self, loc)
 end
 
+---Render this output at a fraction of its resolution and scale the result up.
+---
+---This can keep things smooth on weak GPUs at the cost of softer text.
+---Clients, input, and the cursor are unaffected; only the number of pixels rendered changes.
+---
+---`render_scale` is clamped between 0.5 and 1.0, where 1.0 renders at full resolution.
+---This currently only has an effect on the udev backend.
+---@param render_scale number
+---@see Output.set_render_scale — The corresponding module function
+function output_handle:set_render_scale(render_scale)
+    output.set_render_scale(self, render_scale)
+end
+
+---Set whether new windows on this output float or tile.
+---
+---See `Output.set_default_window_mode` for more information.
+---@param floating_or_tiled "Floating"|"Tiled"
+---@see Output.set_default_window_mode — The corresponding module function
+function output_handle:set_default_window_mode(floating_or_tiled)
+    output.set_default_window_mode(self, floating_or_tiled)
+end
+
 -- TODO: move this into own file or something ---------------------------------------------
 
 ---@alias AlignmentVertical
@@ -386,6 +430,12 @@ function output.get_focused()
     return create_output("")
 end
 
+---What an output had before the config was reloaded.
+---@class OutputReplay
+---@field is_replay boolean Whether the output was already set up before the config was reloaded. This is false when Pinnacle starts and when an output is plugged in.
+---@field tag_names string[] The names of the tags the output had before the reload, in order.
+---@field active_tags string[] The names of the tags that were active before the reload.
+
 ---Connect a function to be run on all current and future outputs.
 ---
 ---When called, `connect_for_all` will immediately run `func` with all currently connected outputs.
@@ -399,12 +449,20 @@ end
 ---Please note: this function will be run *after* Pinnacle processes your entire config.
 ---For example, if you define tags in `func` but toggle them directly after `connect_for_all`,
 ---nothing will happen as the tags haven't been added yet.
----@param func fun(output: OutputHandle) The function that will be run.
+---
+---Tags are cleared when the config reloads and `func` is run again with every connected output.
+---`replay` has the tags the output had before that, so `func` can put back the ones that were
+---active instead of switching away from what was being viewed.
+---@param func fun(output: OutputHandle, replay: OutputReplay) The function that will be run.
 function output.connect_for_all(func)
     ---@param args Args
     table.insert(CallbackTable, function(args)
         local args = args.ConnectForAllOutputs
-        func(create_output(args.output_name))
+        func(create_output(args.output_name), {
+            is_replay = args.is_replay or false,
+            tag_names = args.tag_names or {},
+            active_tags = args.active_tags or {},
+        })
     end)
     SendMsg({
         ConnectForAllOutputs = {
@@ -413,6 +471,101 @@ function output.connect_for_all(func)
     })
 end
 
+---Set how long to wait for more monitors after one is plugged in or unplugged.
+---
+---Docking stations connect several monitors one after another. Pinnacle waits until no
+---monitor has been plugged in or unplugged for `debounce_ms`, then sets them all up at once so
+---windows are only laid out once. A single monitor is set up after a short wait regardless.
+---
+---This defaults to 500 milliseconds and only has an effect when running in a tty.
+---@param debounce_ms integer
+function output.set_hotplug_debounce(debounce_ms)
+    SendMsg({
+        SetHotplugDebounce = {
+            debounce_ms = debounce_ms,
+        },
+    })
+end
+
+---Create an output handle from a name the compositor sent.
+---
+---This is used internally and shouldn't need to be called in your config.
+---@param name string
+---@return OutputHandle
+function output._create(name)
+    return create_output(name)
+end
+
+---Connect a function to be run whenever the order of tags on an output changes.
+---
+---Use `output.tag_order` to get the new order.
+---@param func fun(output: OutputHandle) The function that will be run with the output whose tags were reordered.
+function output.connect_tag_order_changed(func)
+    ---@param args Args
+    table.insert(CallbackTable, function(args)
+        local args = args.TagOrderChanged
+        func(create_output(args.output_name))
+    end)
+    SendMsg({
+        ConnectTagOrderChanged = {
+            callback_id = #CallbackTable,
+        },
+    })
+end
+
+---@alias OutputGeometry { x: integer, y: integer, width: integer, height: integer }
+
+---@param msg string
+---@param func fun(output: OutputHandle, geometry: OutputGeometry)
+local function connect_output_geometry(msg, func)
+    ---@param args Args
+    table.insert(CallbackTable, function(args)
+        local args = args.OutputGeometry
+        func(create_output(args.output_name), {
+            x = args.x,
+            y = args.y,
+            width = args.width,
+            height = args.height,
+        })
+    end)
+    SendMsg({
+        [msg] = {
+            callback_id = #CallbackTable,
+        },
+    })
+end
+
+---Connect a function to be run whenever an output is plugged in.
+---
+---Unlike `connect_for_all`, this is run every time, even if an output was plugged in to the same connector before.
+---It isn't run for outputs that were connected when the config started.
+---@param func fun(output: OutputHandle, geometry: OutputGeometry) The function that will be run with the output and its logical geometry.
+function output.connect_connected(func)
+    connect_output_geometry("ConnectOutputConnected", func)
+end
+
+---Connect a function to be run whenever an output is unplugged.
+---@param func fun(output: OutputHandle, geometry: OutputGeometry) The function that will be run with the output and its logical geometry from before it was unplugged.
+function output.connect_disconnected(func)
+    connect_output_geometry("ConnectOutputDisconnected", func)
+end
+
+---Connect a function to be run whenever an output's mode changes.
+---@param func fun(output: OutputHandle, geometry: OutputGeometry) The function that will be run with the output and its new logical geometry.
+function output.connect_mode_changed(func)
+    connect_output_geometry("ConnectOutputModeChanged", func)
+end
+
+---Get the specified output's tags in the order they are displayed.
+---
+---Tags are displayed in the order they were added unless moved with `tag.set_index`.
+---@param op OutputHandle|string The name of the output or an output object.
+---@return TagHandle[]
+---@see OutputHandle.tag_order — The corresponding object method
+function output.tag_order(op)
+    return output.tags(op)
+end
+
 ---Get the output the specified tag is on.
 ---@param tag TagHandle
 ---@return OutputHandle
@@ -556,6 +709,79 @@ function output.focused(op)
     return props.focused
 end
 
+---Get the fraction of the specified output's resolution it is rendered at.
+---@param op OutputHandle|string The name of the output or an output object.
+---@return number|nil
+---@see OutputHandle.render_scale — The corresponding object method
+function output.render_scale(op)
+    local op = create_output_from_params(op)
+
+    local response = Request({
+        GetOutputProps = {
+            output_name = op:name(),
+        },
+    })
+    local props = response.RequestResponse.response.OutputProps
+    return props.render_scale
+end
+
+---Render the specified output at a fraction of its resolution and scale the result up.
+---
+---`render_scale` is clamped between 0.5 and 1.0, where 1.0 renders at full resolution.
+---This currently only has an effect on the udev backend.
+---@param op OutputHandle|string The name of the output or an output object.
+---@param render_scale number
+---@see OutputHandle.set_render_scale — The corresponding object method
+function output.set_render_scale(op, render_scale)
+    local op = create_output_from_params(op)
+
+    SendMsg({
+        SetOutputRenderScale = {
+            output_name = op:name(),
+            render_scale = render_scale,
+        },
+    })
+end
+
+---Get whether new windows on the specified output float or tile when their tags don't say.
+---@param op OutputHandle|string The name of the output or an output object.
+---@return ("Floating"|"Tiled")|nil
+---@see OutputHandle.default_window_mode — The corresponding object method
+function output.default_window_mode(op)
+    local op = create_output_from_params(op)
+
+    local response = Request({
+        GetOutputProps = {
+            output_name = op:name(),
+        },
+    })
+    local props = response.RequestResponse.response.OutputProps
+    return props.default_window_mode
+end
+
+---Set whether new windows on the specified output float or tile.
+---
+---Window rules and tags with their own default take precedence over this.
+---New windows tile if nothing says otherwise.
+---
+---### Example
+---```lua
+---output.set_default_window_mode("eDP-1", "Floating") -- Float new windows on the laptop panel
+---```
+---@param op OutputHandle|string The name of the output or an output object.
+---@param floating_or_tiled "Floating"|"Tiled"
+---@see OutputHandle.set_default_window_mode — The corresponding object method
+function output.set_default_window_mode(op, floating_or_tiled)
+    local op = create_output_from_params(op)
+
+    SendMsg({
+        SetOutputDefaultWindowMode = {
+            output_name = op:name(),
+            floating_or_tiled = floating_or_tiled,
+        },
+    })
+end
+
 ---Get the specified output's tags.
 ---@param op OutputHandle|string The name of the output or an output object.
 ---@return TagHandle[]
@@ -570,13 +796,14 @@ end
 ---Add tags to the specified output.
 ---@param op OutputHandle|string The name of the output or an output object.
 ---@param ... string The names of the tags you want to add. You can also pass in a table.
----@overload fun(op: OutputHandle|string, tag_names: string[])
+---@return TagHandle[] tags The new tags, in the same order as their names.
+---@overload fun(op: OutputHandle|string, tag_names: string[]): TagHandle[]
 ---@see Tag.add — The called function
 ---@see OutputHandle.add_tags — The corresponding object method
 function output.add_tags(op, ...)
     local op = create_output_from_params(op)
 
-    require("tag").add(op, ...)
+    return require("tag").add(op, ...)
 end
 
 ---Set the specified output's location.
@@ -92,6 +92,479 @@ function pinnacle.quit()
     SendMsg("Quit")
 end
 
+---Restart your config.
+---
+---This kills the running config and starts it again.
+function pinnacle.reload_config()
+    SendMsg("ReloadConfig")
+end
+
+---Connect to config reloads starting and finishing.
+---
+---`func` gets `"Started"` when the old config is stopped, then `"Finished"` once the new config
+---has set everything up, or `"TimedOut"` if it didn't within 10 seconds.
+---Your config is killed when a reload starts, so it only sees the end of the reload that started it.
+---
+---Keybinds pressed with modifiers other than Shift during a reload are held,
+---then run once the new config is set up if it bound them.
+---@param func fun(event: ReloadEvent)
+function pinnacle.connect_reload(func)
+    table.insert(CallbackTable, function(args)
+        func(args.ReloadEvent.event)
+    end)
+    SendMsg({
+        ConnectReload = {
+            callback_id = #CallbackTable,
+        },
+    })
+end
+
+---Turn do not disturb mode on or off.
+---
+---While on, newly opened windows will not take keyboard focus, and new layer surfaces
+---in the top and overlay layers with a namespace set with `set_do_not_disturb_namespaces`
+---will be held back until do not disturb is turned off.
+---
+---Pinnacle doesn't support xdg-activation, so apps can't ask to be focused either.
+---@param enabled boolean
+function pinnacle.set_do_not_disturb(enabled)
+    SendMsg({
+        SetDoNotDisturb = {
+            enabled = enabled,
+        },
+    })
+end
+
+---Get whether or not do not disturb mode is on.
+---@return boolean
+function pinnacle.do_not_disturb()
+    local response = Request("GetDoNotDisturb")
+    return response.RequestResponse.response.DoNotDisturb.enabled
+end
+
+---Connect a function to be run whenever do not disturb mode is turned on or off.
+---
+---`func` gets whether do not disturb is now on.
+---
+--- ### Example
+---```lua
+---pinnacle.connect_do_not_disturb_changed(function(enabled)
+---    print("do not disturb is " .. (enabled and "on" or "off"))
+---end)
+---```
+---@param func fun(enabled: boolean)
+function pinnacle.connect_do_not_disturb_changed(func)
+    ---@param args Args
+    table.insert(CallbackTable, function(args)
+        func(args.DoNotDisturbChanged.enabled)
+    end)
+    SendMsg({
+        ConnectDoNotDisturbChanged = {
+            callback_id = #CallbackTable,
+        },
+    })
+end
+
+---Get information about the running compositor.
+---
+---`software_rendering` is true if Pinnacle is rendering without the GPU, either because
+---setting up GPU rendering failed or because it was started with `--force-software-renderer`.
+---
+---`event_loop_wakeups` is how many times Pinnacle's event loop has woken up since it started.
+---It should stay about the same while nothing is happening.
+---@return { version: string, backend: "winit"|"udev", software_rendering: boolean, event_loop_wakeups: integer }
+function pinnacle.compositor_info()
+    local response = Request("GetCompositorInfo")
+    return response.RequestResponse.response.CompositorInfo
+end
+
+---Get how long Pinnacle has taken to handle each kind of request, to find what's making your config slow.
+---
+---`requests` has a histogram for each kind of request. `buckets[i]` is how many requests took at most
+---`bucket_bounds_us[i]` microseconds but longer than the bucket before. The last bucket has everything slower.
+---@return { bucket_bounds_us: integer[], requests: { name: string, count: integer, total_us: integer, max_us: integer, buckets: integer[] }[] }
+function pinnacle.api_stats()
+    local response = Request("GetApiStats")
+    return response.RequestResponse.response.ApiStats
+end
+
+---Set how long Pinnacle can take to handle a request before it logs a warning about it.
+---
+---This is 10 milliseconds by default.
+---@param threshold_ms integer
+function pinnacle.set_slow_request_threshold(threshold_ms)
+    SendMsg({
+        SetSlowRequestThreshold = {
+            threshold_ms = threshold_ms,
+        },
+    })
+end
+
+---Get the session saved when Pinnacle last shut down, so you can respawn your apps.
+---
+---Returns nil if no session was saved or it couldn't be read.
+---@return Session?
+function pinnacle.previous_session()
+    local response = Request("GetPreviousSession")
+    return response.RequestResponse.response.PreviousSession.session
+end
+
+---Get a plain text description of Pinnacle's outputs, tags, and windows, for debugging.
+---
+---This is the same as the state dump in crash reports. Its format isn't stable, so don't parse it.
+---@return string
+function pinnacle.dump_state()
+    local response = Request("DumpState")
+    return response.RequestResponse.response.StateDump.dump
+end
+
+---Put windows from the previous session back where they were as they reappear.
+---
+---For the next minute, each new window is matched by class to a window from the previous
+---session, preferring one with the same title, and gets that window's tags and floating
+---geometry. Window rules still apply over this.
+---
+---Call this before respawning your apps.
+function pinnacle.apply_previous_session()
+    SendMsg("ApplyPreviousSession")
+end
+
+---Set the namespaces of layer surfaces that do not disturb mode will hold back,
+---like `"notifications"` for mako.
+---@param namespaces string[]
+function pinnacle.set_do_not_disturb_namespaces(namespaces)
+    SendMsg({
+        SetDoNotDisturbNamespaces = {
+            namespaces = namespaces,
+        },
+    })
+end
+
+---Run `func` once Pinnacle has finished starting up, or immediately if it already has.
+---
+---Startup is complete once every output connected at startup has been set up and XWayland has started.
+---Callbacks from `output.connect_for_all` for those outputs will have already run.
+---Use this to defer things like autostarting programs until the desktop is fully set up.
+---@param func fun()
+function pinnacle.on_startup_complete(func)
+    table.insert(CallbackTable, function(_)
+        func()
+    end)
+    SendMsg({
+        OnStartupComplete = {
+            callback_id = #CallbackTable,
+        },
+    })
+end
+
+---@alias DebugOverlayFlag
+---| "Geometry" Outline the geometry of windows and layer surfaces.
+---| "InputRegion" Outline the input region of every surface.
+---| "OpaqueRegion" Outline the opaque region of every surface.
+---| "Damage" Flash the rectangles that surfaces damage when they commit.
+
+---@type table<DebugOverlayFlag, integer>
+local debug_overlay_bits = {
+    Geometry = 1,
+    InputRegion = 2,
+    OpaqueRegion = 4,
+    Damage = 8,
+}
+
+---Set which annotations the debug overlay draws.
+---
+---The overlay draws translucent outlines over every visible surface and highlights the surface
+---with pointer focus, whose name is also logged when it changes.
+---Pass an empty table to turn it off.
+---
+---### Example
+---```lua
+---pinnacle.set_debug_overlay({ "Geometry", "InputRegion" })
+---```
+---@param flags DebugOverlayFlag[]
+function pinnacle.set_debug_overlay(flags)
+    local bits = 0
+    for _, flag in pairs(flags) do
+        bits = bits + debug_overlay_bits[flag]
+    end
+
+    SendMsg({
+        SetDebugOverlay = {
+            flags = bits,
+        },
+    })
+end
+
+---Show or hide a label next to the pointer while you resize or move a window.
+---
+---While resizing, the label shows the window's size, snapped to the window's
+---resize increments if it has any. While moving a floating window, it shows where the window is.
+---
+---This is on by default.
+---@param enabled boolean
+function pinnacle.set_grab_hints(enabled)
+    SendMsg({
+        SetGrabHints = {
+            enabled = enabled,
+        },
+    })
+end
+
+---Set the font of Pinnacle's own UI, like the grab hint, at `size` logical pixels.
+---
+---`family` is the name of an installed font family, like `"Noto Sans"`, or one of the generic
+---families `"sans-serif"`, `"serif"`, and `"monospace"`. Families that aren't installed fall
+---back to `"sans-serif"`.
+---
+---There is also a small built-in bitmap font named `"builtin"`, which is used when no fonts are
+---installed. Its pixels are drawn as squares, so its `size` is rounded to a multiple of 5.
+---
+---The default is `"sans-serif"` at 13.
+---@param family string
+---@param size integer
+function pinnacle.set_ui_font(family, size)
+    SendMsg({
+        SetUiFont = {
+            family = family,
+            size = size,
+        },
+    })
+end
+
+---Get the width and height in logical pixels that `text` is drawn at in the UI font.
+---
+---`size_px` is the size of the font, or the UI font's size if nil.
+---@param text string
+---@param size_px integer?
+---@return integer width, integer height
+function pinnacle.measure_text(text, size_px)
+    local response = Request({
+        MeasureText = {
+            text = text,
+            size_px = size_px,
+        },
+    })
+    local size = response.RequestResponse.response.TextSize
+    return size.width, size.height
+end
+
+---Ask for a line of text in a box titled `title` on the focused output.
+---
+---Key presses go to the box instead of keybinds or windows until Enter or Escape is pressed,
+---except for the kill and reload keybinds and VT switching.
+---`func` is called with the text on Enter, or nil on Escape. Only one prompt can be open at a time,
+---and asking for another while one is open calls `func` with nil right away.
+---
+---### Example
+---```lua
+---input.keybind({ "Super" }, "n", function()
+---    pinnacle.prompt("New tag:", function(name)
+---        local op = output.get_focused()
+---        if name and name ~= "" and op then
+---            tag.add(op, name)
+---        end
+---    end)
+---end)
+---```
+---@param title string
+---@param func fun(text: string?)
+function pinnacle.prompt(title, func)
+    table.insert(CallbackTable, function(args)
+        func(args.PromptFinished and args.PromptFinished.text)
+    end)
+    SendMsg({
+        Prompt = {
+            title = title,
+            callback_id = #CallbackTable,
+        },
+    })
+end
+
+---Flash a ring around windows when a keybind moves keyboard focus to them.
+---
+---This helps you see where focus went when you have a lot of similar windows.
+---Focus changes from clicking don't flash, since you already know where you clicked.
+---See `Window.flash` to flash a window yourself.
+---
+---This is off by default.
+---@param enabled boolean
+function pinnacle.set_flash_on_focus(enabled)
+    SendMsg({
+        SetFlashOnFocus = {
+            enabled = enabled,
+        },
+    })
+end
+
+---Set what happens to focus when you move the focused window to another tag.
+---
+--- - `"Stay"`: Leave focus alone. This is the default.
+--- - `"SwitchTag"`: Switch to the window's new tag and keep the window focused.
+--- - `"FocusNext"`: Stay on the current tag and focus the next window on it.
+---@param policy "Stay"|"SwitchTag"|"FocusNext"
+function pinnacle.set_focus_follows_moved_window(policy)
+    SendMsg({
+        SetFocusFollowsMovedWindow = {
+            policy = policy,
+        },
+    })
+end
+
+---Move windows that haven't been used for `after_ms` milliseconds to the tag named `to_tag`
+---on their output, or stop doing so if `enabled` is false.
+---
+---A window is used when it's focused, unfocused, or clicked. Pinnacle checks for inactive
+---windows every few seconds, so you don't need a timer in your config.
+---Windows on outputs without a tag named `to_tag` are left alone.
+---
+---### Example
+---```lua
+----- Move windows you haven't touched in an hour to the "stale" tag
+---pinnacle.auto_tidy(60 * 60 * 1000, "stale", true)
+---```
+---@param after_ms integer
+---@param to_tag string
+---@param enabled boolean
+function pinnacle.auto_tidy(after_ms, to_tag, enabled)
+    SendMsg({
+        SetAutoTidy = {
+            after_ms = after_ms,
+            tag_name = to_tag,
+            enabled = enabled,
+        },
+    })
+end
+
+---Remember where you move windows of each class, and open new windows of that class there.
+---
+---Moving a window with `window.move_to_tag` or dragging it to another output remembers its class's
+---output and tag. Window rules take priority over remembered placements, and moves made by window
+---rules aren't remembered. Placements are saved across restarts.
+---
+---This is off by default.
+---
+---### Example
+---```lua
+----- Slack will open wherever you last put it
+---pinnacle.remember_window_output(true)
+---```
+---@param enabled boolean
+function pinnacle.remember_window_output(enabled)
+    SendMsg({
+        SetRememberWindowOutput = {
+            enabled = enabled,
+        },
+    })
+end
+
+---Forget where windows of `class` were placed.
+---
+---New windows of that class will open like normal until you move one again.
+---@param class string
+function pinnacle.forget_window_placement(class)
+    SendMsg({
+        ForgetWindowPlacement = {
+            class = class,
+        },
+    })
+end
+
+---Set where new windows go in the layout.
+---
+---Tiled layouts place windows in order, with the first window on a tag being the master.
+---"AfterFocused" and "BeforeFocused" append the window when no window on its tags has focus.
+---Use the `insert_position` window rule to place specific windows somewhere else.
+---
+---This defaults to "Append".
+---
+---### Example
+---```lua
+----- New windows become the master, like in dwm
+---pinnacle.set_insert_position("Prepend")
+---```
+---@param position InsertPosition
+function pinnacle.set_insert_position(position)
+    SendMsg({
+        SetInsertPosition = {
+            position = position,
+        },
+    })
+end
+
+---Set how frame callbacks are throttled for windows that can't be seen.
+---
+---Windows are hidden when they're on tags that aren't active, and occluded when they're on an active tag
+---but completely covered by a single window above them. Translucent windows count as covering.
+---Clients draw when they get a frame callback, so throttling them saves battery.
+---
+---Some clients stop drawing altogether or time out when they don't get frame callbacks for a while,
+---so "SuspendHidden" can break them until they're shown again. Exempt those with the `never_throttle` window rule.
+---
+---This defaults to "None".
+---
+---### Example
+---```lua
+----- Let covered windows draw 5 times a second
+---pinnacle.set_background_throttle({ ThrottleOccluded = { fps = 5 } })
+---```
+---@param policy BackgroundThrottle
+function pinnacle.set_background_throttle(policy)
+    SendMsg({
+        SetBackgroundThrottle = {
+            policy = policy,
+        },
+    })
+end
+
+---@class SandboxPolicy
+---@field screen_capture boolean? Capturing the contents of outputs with screencopy. Defaults to false.
+---@field data_control boolean? Reading and setting the clipboard without focus with data control. Defaults to false.
+---@field foreign_toplevel boolean? Listing and controlling other apps' windows with foreign toplevel management. Defaults to true.
+---@field virtual_keyboard boolean? Typing into other apps with virtual keyboards. Defaults to true.
+---@field input_method boolean? Acting as an input method for other apps. Defaults to true.
+---@field input_inhibitor boolean? Taking all input away from other apps with the input inhibitor. Defaults to true.
+---@field gamma_control boolean? Setting the gamma of outputs with gamma control, like night light tools do. Defaults to false.
+
+---Set which privileged protocols sandboxed apps, like Flatpaks, are allowed to use.
+---
+---Apps are sandboxed when they connect through a socket their sandbox set up with the
+---security context protocol. Protocols they aren't allowed to use are hidden from them.
+---Apps that aren't sandboxed can use everything. Fields you leave out use their defaults.
+---
+---This only affects what apps see from now on, so apps that already started keep
+---what they were given. Set this before starting any sandboxed apps.
+---
+---### Example
+---```lua
+----- Don't let Flatpaks see what other windows are open either
+---pinnacle.set_sandbox_policy({ foreign_toplevel = false })
+---```
+---@param policy SandboxPolicy
+function pinnacle.set_sandbox_policy(policy)
+    SendMsg({
+        SetSandboxPolicy = {
+            policy = policy,
+        },
+    })
+end
+
+---Enable or disable Pinnacle's built-in media key handlers.
+---
+---When enabled, keys like `XF86AudioRaiseVolume` and `XF86MonBrightnessUp` will run commands
+---using `wpctl`, `brightnessctl`, and `playerctl` if they are installed.
+---These keys are not sent to the focused window.
+---
+---Any keybinds you set on the same keys will override the defaults.
+---You can change the commands with `input.set_media_key_command`.
+---@param enabled boolean
+function pinnacle.enable_default_media_keys(enabled)
+    SendMsg({
+        SetMediaKeysEnabled = {
+            enabled = enabled,
+        },
+    })
+end
+
 ---Configure Pinnacle. You should put mostly everything into the config_func to avoid invalid state.
 ---The function takes one argument: the `PinnacleModule` table, which is how you'll access all of the available config options.
 ---
@@ -145,6 +618,14 @@ function pinnacle.setup(config_func)
     ---@param data _Request
     ---@return IncomingMsg
     function Request(data)
+        return ReadMsg(SendRequest(data))
+    end
+
+    ---This is an internal global function used to send a request without waiting for its response.
+    ---Use `ReadMsg` with the returned id to get the response later.
+    ---@param data _Request
+    ---@return integer req_id
+    function SendRequest(data)
         local req_id = next_request_id()
         SendMsg({
             Request = {
@@ -152,7 +633,7 @@ function pinnacle.setup(config_func)
                 request = data,
             },
         })
-        return ReadMsg(req_id)
+        return req_id
     end
 
     ---This is an internal global function used to read messages sent from the server.
@@ -186,7 +667,9 @@ function pinnacle.setup(config_func)
             local inc_msg = msgpack.decode(msg_bytes)
             -- print(msg_bytes)
 
-            if req_id then
+            if inc_msg.Warning then
+                print("Pinnacle: " .. inc_msg.Warning.message)
+            elseif req_id then
                 if inc_msg.CallCallback then
                     unread_cb_msgs[inc_msg.CallCallback.callback_id] = inc_msg
                 elseif inc_msg.RequestResponse.request_id ~= req_id then
@@ -202,6 +685,9 @@ function pinnacle.setup(config_func)
 
     config_func(pinnacle)
 
+    -- Everything the config set up was sent before this, so the reload is done
+    SendMsg("ConfigReady")
+
     while true do
         for cb_id, inc_msg in pairs(unread_cb_msgs) do
             CallbackTable[inc_msg.CallCallback.callback_id](inc_msg.CallCallback.args)
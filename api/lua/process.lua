@@ -76,6 +76,32 @@ function process_module.spawn_once(command, callback)
     process_module.spawn(command, callback)
 end
 
+---Spawn a process and wait for a window that meets `cond` to open.
+---
+---This is useful for doing something with a window right after spawning it, like moving it to a tag.
+---See `window.wait_for` for details on how waiting works.
+---
+---### Example
+---```lua
+---local term = process.spawn_and_wait_window("alacritty", { class = "Alacritty" }, 5000)
+---if term then
+---    term:move_to_tag("5")
+---end
+---```
+---@param command string|string[] The command as one whole string or a table of each of its arguments
+---@param cond WindowRuleCondition
+---@param timeout_ms integer
+---@return WindowHandle|nil handle The window, or nil if none opened before the timeout.
+function process_module.spawn_and_wait_window(command, cond, timeout_ms)
+    local window = require("window")
+
+    -- Messages are handled in order, so sending the wait request first guarantees
+    -- the window can't map before the compositor knows about the wait.
+    local req_id = window._send_wait_request(cond, timeout_ms)
+    process_module.spawn(command)
+    return window._read_wait_response(req_id)
+end
+
 ---Set an environment variable for Pinnacle. All future processes spawned will have this env set.
 ---
 ---Note that this will only set the variable for Pinnacle the compositor, not the running Lua config process.
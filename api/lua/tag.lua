@@ -119,6 +119,41 @@ function tag_handle:set_layout(layout)
     tag.set_layout(self, layout)
 end
 
+---Set how much of the width the master window takes up in this tag's layout.
+---@param master_factor number From 0.1 to 0.9.
+---@see Tag.set_master_factor — The corresponding module function
+function tag_handle:set_master_factor(master_factor)
+    tag.set_master_factor(self, master_factor)
+end
+
+---Get how much of the width the master window takes up in this tag's layout.
+---@return number|nil master_factor The master factor, or nil if the tag doesn't exist.
+---@see Tag.master_factor — The corresponding module function
+function tag_handle:master_factor()
+    return tag.master_factor(self)
+end
+
+---Set whether new windows on this tag float or tile, overriding its output.
+---@param floating_or_tiled ("Floating"|"Tiled")? `nil` to go by the output again.
+---@see Tag.set_default_window_mode — The corresponding module function
+function tag_handle:set_default_window_mode(floating_or_tiled)
+    tag.set_default_window_mode(self, floating_or_tiled)
+end
+
+---Get whether new windows on this tag float or tile, if it overrides its output.
+---@return ("Floating"|"Tiled")|nil
+---@see Tag.default_window_mode — The corresponding module function
+function tag_handle:default_window_mode()
+    return tag.default_window_mode(self)
+end
+
+---Move this tag to `index` in its output's tag order.
+---@param index integer The new position, starting at 1.
+---@see Tag.set_index — The corresponding module function
+function tag_handle:set_index(index)
+    tag.set_index(self, index)
+end
+
 -----------------------------------------------------------
 
 ---Add tags to the specified output.
@@ -127,7 +162,8 @@ end
 ---```lua
 ---local op = output.get_by_name("DP-1")
 ---if op ~= nil then
----    tag.add(op, "1", "2", "3", "4", "5") -- Add tags with names 1-5
+---    local tags = tag.add(op, "1", "2", "3", "4", "5") -- Add tags with names 1-5
+---    tags[1]:toggle()
 ---end
 ---
 --- -- You can also pass in a table.
@@ -136,30 +172,86 @@ end
 ---```
 ---@param output OutputHandle The output you want these tags to be added to.
 ---@param ... string The names of the new tags you want to add.
----@overload fun(output: OutputHandle, tag_names: string[])
+---@return TagHandle[] tags The new tags, in the same order as their names.
+---@overload fun(output: OutputHandle, tag_names: string[]): TagHandle[]
 ---@see OutputHandle.add_tags — The corresponding object method
 function tag.add(output, ...)
     local varargs = { ... }
+
+    ---@type string[]
+    local tag_names
     if type(varargs[1]) == "string" then
-        local tag_names = varargs
+        tag_names = varargs
         tag_names["n"] = nil -- remove the length to make it a true array for serializing
-
-        SendMsg({
-            AddTags = {
-                output_name = output:name(),
-                tag_names = tag_names,
-            },
-        })
     else
-        local tag_names = varargs[1] --[=[@as string[]]=]
+        tag_names = varargs[1] --[=[@as string[]]=]
+    end
 
-        SendMsg({
-            AddTags = {
-                output_name = output:name(),
-                tag_names = tag_names,
-            },
-        })
+    local response = Request({
+        AddTags = {
+            output_name = output:name(),
+            tag_names = tag_names,
+        },
+    })
+
+    local tag_ids = response.RequestResponse.response.Tags.tag_ids
+
+    ---@type TagHandle[]
+    local tags = {}
+
+    for _, tag_id in pairs(tag_ids) do
+        table.insert(tags, create_tag(tag_id))
+    end
+
+    return tags
+end
+
+---Add tags to the specified output that it doesn't already have a tag with the name of.
+---
+---This lets `output.connect_for_all` be run more than once for the same output without adding the same tags again.
+---
+---### Example
+---```lua
+---local tags = tag.add_if_missing(op, { "1", "2", "3", "4", "5" })
+---tags[1]:toggle()
+---```
+---@param output OutputHandle The output you want these tags to be added to.
+---@param tag_names string[] The names of the tags.
+---@return TagHandle[] tags The tags with each of the names, in the same order.
+function tag.add_if_missing(output, tag_names)
+    local function by_name()
+        local tags = {}
+        for _, t in pairs(tag.get_on_output(output)) do
+            local name = t:name()
+            if name ~= nil and tags[name] == nil then
+                tags[name] = t
+            end
+        end
+        return tags
+    end
+
+    local existing = by_name()
+
+    ---@type string[]
+    local missing = {}
+    for _, name in pairs(tag_names) do
+        if existing[name] == nil then
+            table.insert(missing, name)
+        end
+    end
+
+    if #missing > 0 then
+        for i, t in ipairs(tag.add(output, missing)) do
+            existing[missing[i]] = t
+        end
+    end
+
+    ---@type TagHandle[]
+    local tags = {}
+    for _, name in pairs(tag_names) do
+        table.insert(tags, existing[name])
     end
+    return tags
 end
 
 ---Toggle a tag on the specified output. If the output isn't specified, toggle it on the currently focused output instead.
@@ -256,6 +348,81 @@ function tag.set_layout(t, layout)
     end
 end
 
+---Set how much of the width the master window takes up in a tag's layout, from 0.1 to 0.9.
+---
+---This applies to the "MasterStack", "Dwindle", and "Spiral" layouts. The default is 0.5.
+---Dragging the edge between the master window and the rest also changes this.
+---
+---### Example
+---```lua
+---tag.set_master_factor("1", 0.6) -- Give the master window of tag 1 on the focused output 60% of the width
+---```
+---@param t TagConstructor
+---@param master_factor number
+---@see TagHandle.set_master_factor — The corresponding object method
+function tag.set_master_factor(t, master_factor)
+    local t = tag.get(t)
+
+    if t then
+        SendMsg({
+            SetMasterFactor = {
+                tag_id = t:id(),
+                master_factor = master_factor,
+            },
+        })
+    end
+end
+
+---Set whether new windows on a tag float or tile, overriding its output's default.
+---
+---Window rules take precedence over this. It's kept across config reloads for tags with the
+---same name on the same output.
+---
+---### Example
+---```lua
+---tag.set_default_window_mode("3", "Floating") -- Float new windows on tag 3 on the focused output
+---tag.set_default_window_mode("3", nil)        -- Go by the output again
+---```
+---@param t TagConstructor
+---@param floating_or_tiled ("Floating"|"Tiled")?
+---@see TagHandle.set_default_window_mode — The corresponding object method
+function tag.set_default_window_mode(t, floating_or_tiled)
+    local t = tag.get(t)
+
+    if t then
+        SendMsg({
+            SetTagDefaultWindowMode = {
+                tag_id = t:id(),
+                floating_or_tiled = floating_or_tiled,
+            },
+        })
+    end
+end
+
+---Move a tag to `index` in its output's tag order.
+---
+---Indices past the end move the tag to the end. The new order is kept across config reloads.
+---
+---### Example
+---```lua
+---tag.set_index("3", 1) -- Move tag 3 on the focused output to the front
+---```
+---@param t TagConstructor
+---@param index integer The new position, starting at 1.
+---@see TagHandle.set_index — The corresponding object method
+function tag.set_index(t, index)
+    local t = tag.get(t)
+
+    if t then
+        SendMsg({
+            SetTagIndex = {
+                tag_id = t:id(),
+                index = math.max(index - 1, 0),
+            },
+        })
+    end
+end
+
 ---Get a tag with the specified name and optional output.
 ---
 ---If the output isn't specified, the focused one is used.
@@ -454,6 +621,34 @@ function tag.active(t)
     return active
 end
 
+---Get how much of the width the master window takes up in the specified tag's layout.
+---@param t TagHandle
+---@return number|nil
+---@see TagHandle.master_factor — The corresponding object method
+function tag.master_factor(t)
+    local response = Request({
+        GetTagProps = {
+            tag_id = t:id(),
+        },
+    })
+    local master_factor = response.RequestResponse.response.TagProps.master_factor
+    return master_factor
+end
+
+---Get whether new windows on the specified tag float or tile, if it overrides its output.
+---@param t TagHandle
+---@return ("Floating"|"Tiled")|nil
+---@see TagHandle.default_window_mode — The corresponding object method
+function tag.default_window_mode(t)
+    local response = Request({
+        GetTagProps = {
+            tag_id = t:id(),
+        },
+    })
+    local default_window_mode = response.RequestResponse.response.TagProps.default_window_mode
+    return default_window_mode
+end
+
 ---Get the output the specified tag is on.
 ---@param t TagHandle
 ---@return OutputHandle
@@ -463,6 +658,83 @@ function tag.output(t)
     return require("output").get_for_tag(t)
 end
 
+---Connect a function to be run whenever a tag is activated or deactivated.
+---
+---Switching to a tag runs `func` for every tag on its output whose active state changed.
+---
+--- ### Example
+---```lua
+----- Change the wallpaper for each tag
+---tag.connect_active_changed(function(t, name, op, active)
+---    if active then
+---        process.spawn({ "set-wallpaper", name })
+---    end
+---end)
+---```
+---@param func fun(t: TagHandle, name: string, output: OutputHandle|nil, active: boolean)
+function tag.connect_active_changed(func)
+    ---@param args Args
+    table.insert(CallbackTable, function(args)
+        local args = args.TagActiveChanged
+        local op = args.output_name and require("output")._create(args.output_name) or nil
+        func(create_tag(args.tag_id), args.name, op, args.active)
+    end)
+    SendMsg({
+        ConnectTagActiveChanged = {
+            callback_id = #CallbackTable,
+        },
+    })
+end
+
+---@class LayoutApplied
+---@field output OutputHandle The output that was laid out.
+---@field tag TagHandle The first active tag on the output.
+---@field generation integer A number that goes up by one with every layout, so older ones can be told apart.
+---@field windows { window: WindowHandle, x: integer, y: integer, width: integer, height: integer }[] The windows on the output's active tags and their geometries.
+
+---Connect a function to be run whenever windows on an output are laid out.
+---
+---This is called once the windows have been moved and resized, so the geometries are what's
+---on screen. Tools that draw over windows can use this instead of asking for every window's geometry.
+---
+--- ### Example
+---```lua
+---tag.connect_layout_applied(function(applied)
+---    print(#applied.windows .. " windows laid out on " .. applied.output:name())
+---end)
+---```
+---@param func fun(applied: LayoutApplied)
+function tag.connect_layout_applied(func)
+    ---@param args Args
+    table.insert(CallbackTable, function(args)
+        local args = args.LayoutApplied
+        local window = require("window")
+
+        local windows = {}
+        for _, laid_out in pairs(args.windows) do
+            table.insert(windows, {
+                window = window._create(laid_out[1]),
+                x = laid_out[2],
+                y = laid_out[3],
+                width = laid_out[4],
+                height = laid_out[5],
+            })
+        end
+
+        func({
+            output = require("output")._create(args.output_name),
+            tag = create_tag(args.tag_id),
+            generation = args.generation,
+            windows = windows,
+        })
+    end)
+    SendMsg({
+        ConnectLayoutApplied = {
+            callback_id = #CallbackTable,
+        },
+    })
+end
+
 ---@class LayoutCycler
 ---@field next fun(output: (OutputHandle|OutputName)?) Change the first active tag on `output` to its next layout. If `output` is empty, the focused output is used.
 ---@field prev fun(output: (OutputHandle|OutputName)?) Change the first active tag on `output` to its previous layout. If `output` is empty, the focused output is used.
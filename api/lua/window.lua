@@ -54,6 +54,38 @@ function window_handle:set_size(size)
     window.set_size(self, size)
 end
 
+---Move this window by `dx` and `dy` logical pixels.
+---
+---See `Window.move_by` for more information.
+---@param dx integer
+---@param dy integer
+---@see Window.move_by — The corresponding module function
+function window_handle:move_by(dx, dy)
+    window.move_by(self, dx, dy)
+end
+
+---Grow this window by `dx` and `dy` logical pixels, or shrink it with negative values.
+---
+---See `Window.resize_by` for more information.
+---@param dx integer
+---@param dy integer
+---@see Window.resize_by — The corresponding module function
+function window_handle:resize_by(dx, dy)
+    window.resize_by(self, dx, dy)
+end
+
+---Place this window at `x` and `y` with a size of `w` by `h`.
+---
+---See `Window.set_geometry` for more information.
+---@param x integer
+---@param y integer
+---@param w integer
+---@param h integer
+---@see Window.set_geometry — The corresponding module function
+function window_handle:set_geometry(x, y, w, h)
+    window.set_geometry(self, x, y, w, h)
+end
+
 ---Move this window to a tag, removing all other ones.
 ---
 ---See `Window.move_to_tag` for examples.
@@ -64,6 +96,15 @@ function window_handle:move_to_tag(t)
     window.move_to_tag(self, t)
 end
 
+---Make this window follow the active tags on its output, or stop it from doing so.
+---
+---See `Window.set_follow_active_tag` for more information.
+---@param follow boolean
+---@see Window.set_follow_active_tag — The corresponding module function
+function window_handle:set_follow_active_tag(follow)
+    window.set_follow_active_tag(self, follow)
+end
+
 ---Toggle the specified tag for this window.
 ---
 ---Note: toggling off all tags currently makes a window not respond to layouting.
@@ -86,6 +127,32 @@ function window_handle:close()
     window.close(self)
 end
 
+---Flash a ring around this window to help you find it.
+---
+---See `Window.flash` for more information.
+---@param duration_ms integer How long the ring takes to fade out.
+---@param color number[] The color the ring starts at, as `{ r, g, b, a }` from 0.0 to 1.0.
+---@see Window.flash — The corresponding module function
+function window_handle:flash(duration_ms, color)
+    window.flash(self, duration_ms, color)
+end
+
+---Raise this window to the top of the stack.
+---
+---See `Window.raise` for more information.
+---@see Window.raise — The corresponding module function
+function window_handle:raise()
+    window.raise(self)
+end
+
+---Give this window keyboard focus and raise it.
+---
+---See `Window.focus` for more information.
+---@see Window.focus — The corresponding module function
+function window_handle:focus()
+    window.focus(self)
+end
+
 ---Get this window's size.
 ---
 ---See `Window.size` for examples.
@@ -179,6 +246,33 @@ function window_handle:toggle_maximized()
     window.toggle_maximized(self)
 end
 
+---Make this window floating if `floating` is true, or tiled if it isn't.
+---
+---This does nothing if the window already is, so unlike `toggle_floating`
+---it's safe to use more than once.
+---@param floating boolean
+function window_handle:set_floating(floating)
+    window.set_floating(self, floating)
+end
+
+---Make this window fullscreen or not.
+---
+---This does nothing if the window already is. Making a maximized window fullscreen
+---unmaximizes it.
+---@param fullscreen boolean
+function window_handle:set_fullscreen(fullscreen)
+    window.set_fullscreen(self, fullscreen)
+end
+
+---Make this window maximized or not.
+---
+---This does nothing if the window already is. Making a fullscreen window maximized
+---unfullscreens it.
+---@param maximized boolean
+function window_handle:set_maximized(maximized)
+    window.set_maximized(self, maximized)
+end
+
 ---Get whether or not this window is focused.
 ---
 ---See `Window.focused` for examples.
@@ -188,6 +282,51 @@ function window_handle:focused()
     return window.focused(self)
 end
 
+---Get this window's resize increments.
+---
+---See `Window.resize_increments` for more information.
+---@return { w: integer, h: integer }|nil
+---@see Window.resize_increments — The corresponding module function
+function window_handle:resize_increments()
+    return window.resize_increments(self)
+end
+
+---Get how many seconds it's been since this window was last focused, unfocused, or clicked.
+---
+---See `Window.seconds_since_activity` for more information.
+---@return integer|nil
+---@see Window.seconds_since_activity — The corresponding module function
+function window_handle:seconds_since_activity()
+    return window.seconds_since_activity(self)
+end
+
+---Get whether this window is keeping keybinds from triggering.
+---
+---See `Window.keyboard_shortcuts_inhibited` for more information.
+---@return boolean|nil
+---@see Window.keyboard_shortcuts_inhibited — The corresponding module function
+function window_handle:keyboard_shortcuts_inhibited()
+    return window.keyboard_shortcuts_inhibited(self)
+end
+
+---Get the id of this window's group.
+---
+---See `Window.group` for more information.
+---@return integer|nil
+---@see Window.group — The corresponding module function
+function window_handle:group()
+    return window.group(self)
+end
+
+---Get the app id this window's sandbox gave it.
+---
+---See `Window.sandbox_app_id` for more information.
+---@return string|nil
+---@see Window.sandbox_app_id — The corresponding module function
+function window_handle:sandbox_app_id()
+    return window.sandbox_app_id(self)
+end
+
 -------------------------------------------------------------------
 
 ---Get all windows with the specified class (usually the name of the application).
@@ -240,6 +379,194 @@ function window.get_focused()
     return create_window("None")
 end
 
+---Get the window with keyboard focus.
+---
+---This is the same as `window.get_focused`.
+---@return WindowHandle handle A handle to the window with keyboard focus. If there are none, this returns a dummy handle that can still be used but will be ignored by the compositor.
+function window.get_keyboard_focused()
+    return window.get_focused()
+end
+
+---Get the window with pointer focus.
+---
+---This is the window Pinnacle is sending pointer events to, which is usually the one
+---under the pointer. There is none while moving or resizing a window, as those grabs
+---don't send pointer events to any window.
+---@return WindowHandle handle A handle to the window with pointer focus. If there are none, this returns a dummy handle that can still be used but will be ignored by the compositor.
+function window.get_pointer_focused()
+    local window_id =
+        Request("GetPointerFocusedWindow").RequestResponse.response.Window.window_id
+    return create_window(window_id or "None")
+end
+
+---Get the window that was focused before the focused one.
+---
+---Only windows on active tags are considered.
+---
+---### Example
+---```lua
+---input.keybind({ "Super" }, "grave", function()
+---    window.get_previous_focused():focus()
+---end)
+---```
+---@return WindowHandle handle A handle to the previously focused window. If there are none, this returns a dummy handle that can still be used but will be ignored by the compositor.
+function window.get_previous_focused()
+    local window_id =
+        Request("GetPreviousFocusedWindow").RequestResponse.response.Window.window_id
+    return create_window(window_id or "None")
+end
+
+---Connect a function to be run whenever the window with keyboard focus changes.
+---@param func fun(win: WindowHandle|nil) The function that will be run with the newly focused window, or nil if no window has keyboard focus.
+function window.connect_keyboard_focus_changed(func)
+    ---@param args Args
+    table.insert(CallbackTable, function(args)
+        local window_id = args.WindowFocusChanged.window_id
+        func(window_id and create_window(window_id) or nil)
+    end)
+    SendMsg({
+        ConnectKeyboardFocusChanged = {
+            callback_id = #CallbackTable,
+        },
+    })
+end
+
+---Connect a function to be run whenever the window with pointer focus changes.
+---
+---Moving between a window and its popups doesn't count as a change.
+---@param func fun(win: WindowHandle|nil) The function that will be run with the window the pointer is now over, or nil if it isn't over one.
+function window.connect_pointer_focus_changed(func)
+    ---@param args Args
+    table.insert(CallbackTable, function(args)
+        local window_id = args.WindowFocusChanged.window_id
+        func(window_id and create_window(window_id) or nil)
+    end)
+    SendMsg({
+        ConnectPointerFocusChanged = {
+            callback_id = #CallbackTable,
+        },
+    })
+end
+
+---Connect a function to be run whenever a window's title changes.
+---
+---This includes windows on inactive tags, so a bar can show what a hidden window is doing.
+---
+---### Example
+---```lua
+---window.connect_title_changed(function(win, title)
+---    print("A window's title is now " .. (title or "unset"))
+---end)
+---```
+---@param func fun(win: WindowHandle, title: string|nil) The function that will be run with the window and its new title, or nil if it unset it.
+function window.connect_title_changed(func)
+    ---@param args Args
+    table.insert(CallbackTable, function(args)
+        local changed = args.WindowTitleChanged
+        func(create_window(changed.window_id), changed.title)
+    end)
+    SendMsg({
+        ConnectWindowTitleChanged = {
+            callback_id = #CallbackTable,
+        },
+    })
+end
+
+---Connect a function to be run whenever a window's class changes.
+---@param func fun(win: WindowHandle, class: string|nil) The function that will be run with the window and its new class, or nil if it unset it.
+function window.connect_class_changed(func)
+    ---@param args Args
+    table.insert(CallbackTable, function(args)
+        local changed = args.WindowClassChanged
+        func(create_window(changed.window_id), changed.class)
+    end)
+    SendMsg({
+        ConnectWindowClassChanged = {
+            callback_id = #CallbackTable,
+        },
+    })
+end
+
+---Connect a function to be run whenever a window opens.
+---
+---This runs after window rules were applied, so the window is already on the tags and output they gave it.
+---
+---### Example
+---```lua
+----- Move mpv to tag 5 whenever it opens
+---window.connect_opened(function(win, class)
+---    if class == "mpv" then
+---        win:move_to_tag("5")
+---    end
+---end)
+---```
+---@param func fun(win: WindowHandle, class: string|nil, title: string|nil) The function that will be run with the window, its class, and its title.
+function window.connect_opened(func)
+    ---@param args Args
+    table.insert(CallbackTable, function(args)
+        local opened = args.WindowOpened
+        func(create_window(opened.window_id), opened.class, opened.title)
+    end)
+    SendMsg({
+        ConnectWindowOpened = {
+            callback_id = #CallbackTable,
+        },
+    })
+end
+
+---Connect a function to be run whenever a window closes.
+---
+---The window is gone by the time `func` runs, so its class and title are passed in as they last were.
+---@param func fun(win: WindowHandle, class: string|nil, title: string|nil) The function that will be run with the window, its last class, and its last title.
+function window.connect_closed(func)
+    ---@param args Args
+    table.insert(CallbackTable, function(args)
+        local closed = args.WindowClosed
+        func(create_window(closed.window_id), closed.class, closed.title)
+    end)
+    SendMsg({
+        ConnectWindowClosed = {
+            callback_id = #CallbackTable,
+        },
+    })
+end
+
+---Connect a function to be run whenever a client with windows disconnects.
+---
+---This is useful for finding out why a window suddenly disappeared.
+---Only clients that opened a window or broke the Wayland protocol are reported.
+---
+---### Example
+---```lua
+---window.connect_client_disconnected(function(disconnect)
+---    if disconnect.reason ~= "Clean" then
+---        print(tostring(disconnect.process_name) .. " disconnected")
+---    end
+---end)
+---```
+---@param func fun(disconnect: ClientDisconnect)
+function window.connect_client_disconnected(func)
+    ---@param args Args
+    table.insert(CallbackTable, function(args)
+        func(args.ClientDisconnected.disconnect)
+    end)
+    SendMsg({
+        ConnectClientDisconnected = {
+            callback_id = #CallbackTable,
+        },
+    })
+end
+
+---Get the clients that disconnected recently, oldest first.
+---
+---Pinnacle remembers the last 32, which is handy to include in bug reports.
+---See `connect_client_disconnected` for which clients are reported.
+---@return ClientDisconnect[]
+function window.recent_disconnects()
+    local response = Request("GetRecentDisconnects")
+    return response.RequestResponse.response.RecentDisconnects.disconnects
+end
+
 ---Get all windows.
 ---@return WindowHandle[]
 function window.get_all()
@@ -255,6 +582,157 @@ function window.get_all()
     return windows
 end
 
+---Get all windows that haven't been focused, unfocused, or clicked for at least `ms` milliseconds.
+---
+---The focused window is never inactive.
+---
+---### Example
+---```lua
+----- Windows you haven't touched in an hour
+---local stale = window.get_inactive_since(60 * 60 * 1000)
+---```
+---@param ms integer
+---@return WindowHandle[]
+function window.get_inactive_since(ms)
+    local window_ids = Request({
+        GetInactiveWindows = {
+            inactive_ms = ms,
+        },
+    }).RequestResponse.response.Windows.window_ids
+
+    ---@type WindowHandle[]
+    local windows = {}
+
+    for _, window_id in pairs(window_ids) do
+        table.insert(windows, create_window(window_id))
+    end
+
+    return windows
+end
+
+---Get all windows that have no live tags.
+---
+---These windows can't be seen until they get a tag. Pinnacle gives them tags again when tags
+---are added to the output they were on, but you can also move them to a tag yourself.
+---
+---### Example
+---```lua
+----- Bring lost windows to tag "1"
+---for _, win in pairs(window.get_orphaned()) do
+---    win:move_to_tag("1")
+---end
+---```
+---@return WindowHandle[]
+function window.get_orphaned()
+    local window_ids = Request("GetOrphanedWindows").RequestResponse.response.Windows.window_ids
+
+    ---@type WindowHandle[]
+    local windows = {}
+
+    for _, window_id in pairs(window_ids) do
+        table.insert(windows, create_window(window_id))
+    end
+
+    return windows
+end
+
+---Set whether focusing windows through the API counts as using them.
+---
+---This affects `window.get_inactive_since`, `window.seconds_since_activity`, and `pinnacle.auto_tidy`.
+---It's on by default, so focusing a window from a keybind keeps it from being tidied away.
+---@param counts boolean
+function window.set_api_focus_counts_as_activity(counts)
+    SendMsg({
+        SetApiFocusCountsAsActivity = {
+            counts = counts,
+        },
+    })
+end
+
+---Set whether clicking a window raises it to the top of the stack.
+---
+---When this is off, clicking a window only focuses it. Use `window.raise` to raise windows yourself.
+---It's on by default.
+---@param enabled boolean
+function window.set_raise_on_click(enabled)
+    SendMsg({
+        SetRaiseOnClick = {
+            enabled = enabled,
+        },
+    })
+end
+
+---Create a handle to the window with `window_id`.
+---
+---This is used internally and shouldn't need to be called in your config.
+---@param window_id WindowId
+---@return WindowHandle
+function window._from_id(window_id)
+    return create_window(window_id)
+end
+
+---Wait for a window that meets `cond` to open.
+---
+---This blocks until such a window opens or `timeout_ms` milliseconds pass.
+---Windows that are already open are not considered.
+---
+---If multiple waits are pending, a new window will only resolve the oldest one whose condition it meets.
+---
+---`cond` takes the same form as the `cond` in `window.rules.add`.
+---
+---### Example
+---```lua
+---local term = window.wait_for({ class = "Alacritty" }, 5000)
+---if term then
+---    term:move_to_tag("5")
+---end
+---```
+---@param cond WindowRuleCondition
+---@param timeout_ms integer
+---@return WindowHandle|nil handle The window, or nil if none opened before the timeout.
+function window.wait_for(cond, timeout_ms)
+    return window._read_wait_response(window._send_wait_request(cond, timeout_ms))
+end
+
+---Send a request to wait for a window without blocking.
+---
+---This is used internally and shouldn't need to be called in your config.
+---@param cond WindowRuleCondition
+---@param timeout_ms integer
+---@return integer req_id
+function window._send_wait_request(cond, timeout_ms)
+    return SendRequest({
+        WaitForWindow = {
+            condition = require("window_rules").convert_cond(cond),
+            timeout_ms = timeout_ms,
+        },
+    })
+end
+
+---Block until the wait request with `req_id` is responded to.
+---
+---This is used internally and shouldn't need to be called in your config.
+---@param req_id integer
+---@return WindowHandle|nil
+function window._read_wait_response(req_id)
+    local window_id = ReadMsg(req_id).RequestResponse.response.Window.window_id
+
+    if window_id == nil then
+        return nil
+    end
+
+    return create_window(window_id)
+end
+
+---Create a window handle from an id the compositor sent.
+---
+---This is used internally and shouldn't need to be called in your config.
+---@param window_id WindowId
+---@return WindowHandle
+function window._create(window_id)
+    return create_window(window_id)
+end
+
 ---Toggle the tag with the given name and (optional) output for the specified window.
 ---
 ---@param w WindowHandle
@@ -291,6 +769,26 @@ function window.move_to_tag(w, t)
     end
 end
 
+---Make a window follow the active tags on its output, or stop it from doing so.
+---
+---Whenever the active tags on the window's output change, it's moved onto them,
+---so it's tiled on whatever tag you're viewing. This is useful for something like
+---a music player you want everywhere. Fullscreen windows don't follow until they
+---aren't fullscreen anymore.
+---
+---Moving the window to a tag or toggling a tag on it stops it from following.
+---@param win WindowHandle
+---@param follow boolean
+---@see WindowHandle.set_follow_active_tag — The corresponding object method
+function window.set_follow_active_tag(win, follow)
+    SendMsg({
+        SetFollowActiveTag = {
+            window_id = win:id(),
+            follow = follow,
+        },
+    })
+end
+
 ---Toggle `win`'s floating status.
 ---
 ---When used on a floating window, this will change it to tiled, and vice versa.
@@ -336,6 +834,130 @@ function window.toggle_maximized(win)
     })
 end
 
+---Make `win` floating if `floating` is true, or tiled if it isn't.
+---
+---This does nothing if the window already is.
+---@param win WindowHandle
+---@param floating boolean
+function window.set_floating(win, floating)
+    SendMsg({
+        SetFloating = {
+            window_id = win:id(),
+            floating = floating,
+        },
+    })
+end
+
+---Make `win` fullscreen or not.
+---
+---This does nothing if the window already is. Making a maximized window fullscreen
+---unmaximizes it.
+---@param win WindowHandle
+---@param fullscreen boolean
+function window.set_fullscreen(win, fullscreen)
+    SendMsg({
+        SetFullscreen = {
+            window_id = win:id(),
+            fullscreen = fullscreen,
+        },
+    })
+end
+
+---Make `win` maximized or not.
+---
+---This does nothing if the window already is. Making a fullscreen window maximized
+---unfullscreens it.
+---@param win WindowHandle
+---@param maximized boolean
+function window.set_maximized(win, maximized)
+    SendMsg({
+        SetMaximized = {
+            window_id = win:id(),
+            maximized = maximized,
+        },
+    })
+end
+
+---Focus the next window on the active tags of `op`, wrapping around.
+---
+---Windows are cycled through in the order they are laid out.
+---@param op OutputHandle? The output to cycle focus on. Defaults to the focused output.
+function window.focus_next(op)
+    SendMsg({
+        CycleWindowFocus = {
+            output_name = op and op:name(),
+            forward = true,
+        },
+    })
+end
+
+---Focus the previous window on the active tags of `op`, wrapping around.
+---
+---Windows are cycled through in the order they are laid out.
+---@param op OutputHandle? The output to cycle focus on. Defaults to the focused output.
+function window.focus_prev(op)
+    SendMsg({
+        CycleWindowFocus = {
+            output_name = op and op:name(),
+            forward = false,
+        },
+    })
+end
+
+---Cycle through windows on active tags in the order they were last focused, like Alt-Tab.
+---
+---The first call starts a cycle from the focused window. Each call focuses and raises the
+---next window in the cycle without changing the order windows were focused in. The cycle is
+---committed, moving the focused window to the top of that order, with `window.cycle_commit`
+---or once the modifiers held when it started, other than Shift, are all released.
+---
+---### Example
+---```lua
+---input.keybind({ "Alt" }, "Tab", function()
+---    window.cycle_focus("Forward")
+---end)
+---input.keybind({ "Alt", "Shift" }, "Tab", function()
+---    window.cycle_focus("Backward")
+---end)
+---```
+---@param direction CycleDirection
+function window.cycle_focus(direction)
+    SendMsg({
+        CycleMruFocus = {
+            direction = direction,
+        },
+    })
+end
+
+---Commit the cycle started by `window.cycle_focus`, moving the focused window to the top of
+---the order windows were last focused in.
+---
+---This is only needed if no modifiers other than Shift were held when the cycle started.
+function window.cycle_commit()
+    SendMsg("CommitMruCycle")
+end
+
+---Swap the focused window with the closest window in `direction`.
+---
+---Tiled windows trade places in the layout, and floating windows trade geometries.
+---The closest window can be on another output, in which case the windows also trade tags,
+---moving the focused window to that output's focused tag.
+---
+---### Example
+---```lua
+---input.keybind({ "Super", "Shift" }, "h", function()
+---    window.swap_in_direction("Left")
+---end)
+---```
+---@param direction Direction
+function window.swap_in_direction(direction)
+    SendMsg({
+        SwapWindowInDirection = {
+            direction = direction,
+        },
+    })
+end
+
 ---Set the specified window's size.
 ---
 ---### Examples
@@ -360,6 +982,86 @@ function window.set_size(win, size)
     })
 end
 
+---Move a window by `dx` and `dy` logical pixels.
+---
+---This only moves floating windows that aren't fullscreen or maximized.
+---
+---### Example
+---```lua
+----- Nudge the focused window left
+---input.keybind({ "Super", "Shift" }, "h", function()
+---    local win = window.get_focused()
+---    if win then
+---        win:move_by(-20, 0)
+---    end
+---end)
+---```
+---@param win WindowHandle
+---@param dx integer
+---@param dy integer
+---@see WindowHandle.move_by — The corresponding object method
+function window.move_by(win, dx, dy)
+    SendMsg({
+        MoveWindowBy = {
+            window_id = win:id(),
+            dx = dx,
+            dy = dy,
+        },
+    })
+end
+
+---Grow a window by `dx` and `dy` logical pixels, or shrink it with negative values.
+---
+---The window's top left corner stays put, and its size is kept within the sizes it supports.
+---Tiled windows move the split between the master window and the rest by `dx` instead,
+---growing the window if it's the master. Fullscreen and maximized windows aren't resized.
+---
+---### Example
+---```lua
+----- Make the focused window narrower
+---input.keybind({ "Super", "Ctrl" }, "h", function()
+---    local win = window.get_focused()
+---    if win then
+---        win:resize_by(-20, 0)
+---    end
+---end)
+---```
+---@param win WindowHandle
+---@param dx integer
+---@param dy integer
+---@see WindowHandle.resize_by — The corresponding object method
+function window.resize_by(win, dx, dy)
+    SendMsg({
+        ResizeWindowBy = {
+            window_id = win:id(),
+            dx = dx,
+            dy = dy,
+        },
+    })
+end
+
+---Place a window at `x` and `y` with a size of `w` by `h`, all in logical pixels.
+---
+---The size is kept within the sizes the window supports.
+---Tiled windows get this geometry once they're floating.
+---@param win WindowHandle
+---@param x integer
+---@param y integer
+---@param w integer
+---@param h integer
+---@see WindowHandle.set_geometry — The corresponding object method
+function window.set_geometry(win, x, y, w, h)
+    SendMsg({
+        SetWindowGeometry = {
+            window_id = win:id(),
+            x = x,
+            y = y,
+            width = w,
+            height = h,
+        },
+    })
+end
+
 ---Close the specified window.
 ---
 ---This only sends a close *event* to the window and is the same as just clicking the X button in the titlebar.
@@ -382,6 +1084,66 @@ function window.close(win)
     })
 end
 
+---Raise the specified window to the top of the stack.
+---
+---Windows it stacks with, like its dialogs, are raised along with it.
+---
+---### Example
+---```lua
+---local win = window.get_focused()
+---if win ~= nil then
+---    window.raise(win) -- raise the currently focused window
+---end
+---```
+---@param win WindowHandle
+---@see WindowHandle.raise — The corresponding object method
+function window.raise(win)
+    SendMsg({
+        RaiseWindow = {
+            window_id = win:id(),
+        },
+    })
+end
+
+---Give a window keyboard focus and raise it.
+---
+---This does nothing if the window isn't on an active tag.
+---@param win WindowHandle
+---@see WindowHandle.focus — The corresponding object method
+function window.focus(win)
+    SendMsg({
+        FocusWindow = {
+            window_id = win:id(),
+        },
+    })
+end
+
+---Flash a ring around a window to help you find it.
+---
+---The ring starts at `color` and fades out over `duration_ms`. It follows the window if it moves.
+---
+---### Example
+---
+---```lua
+---local win = window.get_focused()
+---if win ~= nil then
+---    window.flash(win, 500, { 1.0, 0.5, 0.0, 1.0 }) -- flash an orange ring for half a second
+---end
+---```
+---@param win WindowHandle
+---@param duration_ms integer How long the ring takes to fade out.
+---@param color number[] The color the ring starts at, as `{ r, g, b, a }` from 0.0 to 1.0.
+---@see WindowHandle.flash — The corresponding object method
+function window.flash(win, duration_ms, color)
+    SendMsg({
+        FlashWindow = {
+            window_id = win:id(),
+            duration_ms = duration_ms,
+            color = color,
+        },
+    })
+end
+
 ---Get the specified window's size.
 ---
 ---### Example
@@ -557,6 +1319,96 @@ function window.focused(win)
     return focused
 end
 
+---Get the width and height increments this window wants to be resized in, like terminal cells.
+---
+---This is nil if the window doesn't use resize increments.
+---@param win WindowHandle
+---@return { w: integer, h: integer }|nil
+---@see WindowHandle.resize_increments — The corresponding object method
+function window.resize_increments(win)
+    local response = Request({
+        GetWindowProps = {
+            window_id = win:id(),
+        },
+    })
+    local inc = response.RequestResponse.response.WindowProps.resize_increments
+    if inc == nil then
+        return nil
+    else
+        return {
+            w = inc[1],
+            h = inc[2],
+        }
+    end
+end
+
+---Get how many seconds it's been since this window was last focused, unfocused, or clicked.
+---
+---This is 0 for the focused window.
+---@param win WindowHandle
+---@return integer|nil
+---@see WindowHandle.seconds_since_activity — The corresponding object method
+function window.seconds_since_activity(win)
+    local response = Request({
+        GetWindowProps = {
+            window_id = win:id(),
+        },
+    })
+    return response.RequestResponse.response.WindowProps.seconds_since_activity
+end
+
+---Get whether a window is keeping keybinds from triggering, like a VM viewer does
+---so keys go to the VM.
+---
+---This can only be true while the window is focused.
+---Keybinds set with `input.keybind_force` still trigger.
+---@param win WindowHandle
+---@return boolean|nil
+---@see WindowHandle.keyboard_shortcuts_inhibited — The corresponding object method
+function window.keyboard_shortcuts_inhibited(win)
+    local response = Request({
+        GetWindowProps = {
+            window_id = win:id(),
+        },
+    })
+    return response.RequestResponse.response.WindowProps.keyboard_shortcuts_inhibited
+end
+
+---Get the id of a window's group, which is the id of the first window that opened in it.
+---
+---Windows that share an X11 window group or an xdg parent chain, like an app and its
+---tool palettes and dialogs, are in the same group. Raising one raises them all.
+---
+---Use this id in the `group` window rule condition to match windows in the same group.
+---@param win WindowHandle
+---@return integer|nil
+---@see WindowHandle.group — The corresponding object method
+function window.group(win)
+    local response = Request({
+        GetWindowProps = {
+            window_id = win:id(),
+        },
+    })
+    return response.RequestResponse.response.WindowProps.group
+end
+
+---Get the app id a window's sandbox, like Flatpak, gave it.
+---
+---Unlike the class, this can't be set by the app itself, so use it in the `sandbox_app_id`
+---window rule condition to treat sandboxed apps differently.
+---This is nil for windows that aren't sandboxed.
+---@param win WindowHandle
+---@return string|nil
+---@see WindowHandle.sandbox_app_id — The corresponding object method
+function window.sandbox_app_id(win)
+    local response = Request({
+        GetWindowProps = {
+            window_id = win:id(),
+        },
+    })
+    return response.RequestResponse.response.WindowProps.sandbox_app_id
+end
+
 ---Begin a window move.
 ---
 ---This will start a window move grab with the provided button on the window the pointer
@@ -570,6 +1422,44 @@ function window.begin_move(button)
     })
 end
 
+---Set the modifiers that let a tiled window be dragged freely for one move.
+---
+---If these modifiers are held when a move starts, the tiled window floats until it's dropped,
+---without changing whether it's tiled. `on_drop` decides where it ends up.
+---Pass `nil` for `modifiers` to turn this off.
+---
+---The move still has to be started, so you'll likely want a mousebind with the same modifiers
+---that calls `window.begin_move`.
+---
+---### Example
+---
+---```lua
+---window.set_move_override({ "Super", "Shift" }, "ReturnToTile")
+---```
+---@param modifiers (Modifier)[]? The modifiers to hold, or `nil` to turn this off.
+---@param on_drop MoveOverrideDrop? Where the window ends up. Defaults to `"ReturnToTile"`.
+function window.set_move_override(modifiers, on_drop)
+    SendMsg({
+        SetMoveOverride = {
+            modifiers = modifiers,
+            on_drop = on_drop or "ReturnToTile",
+        },
+    })
+end
+
+---Set how close a window being moved has to get to an edge to snap to it, in logical pixels.
+---
+---Floating windows snap to the edges of outputs, the area layer surfaces like bars leave free,
+---and other floating windows. This is 10 by default. Set it to 0 to turn snapping off.
+---@param threshold integer
+function window.set_snap_threshold(threshold)
+    SendMsg({
+        SetSnapThreshold = {
+            threshold = threshold,
+        },
+    })
+end
+
 ---Begin a window resize.
 ---
 ---This will start a window resize grab with the provided button on the window the
@@ -84,6 +84,18 @@ local function convert_single_attrs(cond)
         -- stylua: ignore end
     end
 
+    if type(cond.group) == "number" then
+        -- stylua: ignore start
+        cond.group = { cond.group --[[@as integer]] }
+        -- stylua: ignore end
+    end
+
+    if type(cond.sandbox_app_id) == "string" then
+        -- stylua: ignore start
+        cond.sandbox_app_id = { cond.sandbox_app_id --[[@as string]] }
+        -- stylua: ignore end
+    end
+
     if cond.cond_any then
         local conds = {}
         if type(cond.cond_any[1]) == "table" then
@@ -115,6 +127,15 @@ local function convert_single_attrs(cond)
     return cond
 end
 
+---Convert `cond` into the form the compositor expects.
+---
+---This is used internally and shouldn't need to be called in your config.
+---@param cond WindowRuleCondition
+---@return _WindowRuleCondition
+function window_rules.convert_cond(cond)
+    return convert_tag_params(convert_single_attrs(cond))
+end
+
 ---Add one or more window rules.
 ---
 ---A window rule defines what properties a window will spawn with given certain conditions.
@@ -199,10 +220,8 @@ function window_rules.add(...)
     local rules = { ... }
 
     for _, rule in pairs(rules) do
-        rule.cond = convert_single_attrs(rule.cond)
-
         ---@diagnostic disable-next-line
-        rule.cond = convert_tag_params(rule.cond)
+        rule.cond = window_rules.convert_cond(rule.cond)
 
         if rule.rule.tags then
             local tags = {}
@@ -235,4 +254,56 @@ function window_rules.add(...)
     end
 end
 
+---@alias RequestedState
+---| "Fullscreen"
+---| "Maximized"
+---| "Minimized"
+
+---@alias StateRequestDecision
+---| "Allow" # Put the window in the state it asked for.
+---| "Deny" # Keep the window in its current state.
+---| { Modify: { state: FullscreenOrMaximized } } # Put the window in this state instead.
+
+---Decide what happens when a window that meets `cond` asks to be fullscreened, maximized, or minimized.
+---
+---`callback` gets the window and the state it asked for, and returns what to do with the request.
+---If it takes longer than a second to decide, the request will be allowed.
+---
+---If multiple handlers have conditions the window meets, only the first one added is called.
+---
+---`cond` takes the same form as the `cond` in `window.rules.add`.
+---
+---### Example
+---```lua
+--- -- Make Firefox maximize instead of going fullscreen
+---window.rules.on_state_request({ class = "firefox" }, function(win, state)
+---    if state == "Fullscreen" then
+---        return { Modify = { state = "Maximized" } }
+---    end
+---    return "Allow"
+---end)
+---```
+---@param cond WindowRuleCondition
+---@param callback fun(window: WindowHandle, state: RequestedState): StateRequestDecision
+function window_rules.on_state_request(cond, callback)
+    ---@param args Args
+    table.insert(CallbackTable, function(args)
+        local req = args.StateRequest
+        local decision = callback(require("window")._from_id(req.window_id), req.state)
+        SendMsg({
+            DecideStateRequest = {
+                token = req.token,
+                decision = decision,
+            },
+        })
+    end)
+
+    SendMsg({
+        AddStateRequestHandler = {
+            cond = window_rules.convert_cond(cond),
+            callback_id = #CallbackTable,
+        },
+    })
+end
+
 return window_rules
@@ -11,6 +11,8 @@
 ---@field class string[]? The window must have this class.
 ---@field title string[]? The window must have this title.
 ---@field tag TagId[]? The window must be on this tag.
+---@field group integer[]? The window must be in the group with this id.
+---@field sandbox_app_id string[]? The window's sandbox must have given it this app id.
 
 ---Conditions for window rules. Only one condition can be in the table.
 ---If you have more than one you need to check for, use `cond_any` or `cond_all`
@@ -21,6 +23,8 @@
 ---@field class (string|string[])? The window must have this class.
 ---@field title (string|string[])? The window must have this title.
 ---@field tag (TagConstructor|TagConstructor[])? The window must be on this tag.
+---@field group (integer|integer[])? The window must be in the group with this id. See `Window.group`.
+---@field sandbox_app_id (string|string[])? The window's sandbox, like Flatpak, must have given it this app id. See `Window.sandbox_app_id`.
 
 ---@class _WindowRule Attributes the window will be spawned with.
 ---@field output OutputName? The output this window will be spawned on. TODO:
@@ -29,6 +33,11 @@
 ---@field fullscreen_or_maximized FullscreenOrMaximized? Whether or not this window will be spawned fullscreen, maximized, or forced to neither.
 ---@field size { [1]: integer, [2]: integer }? The size the window will spawn with, with [1] being width and [2] being height. This must be a strictly positive integer; putting 0 will crash the compositor.
 ---@field location { [1]: integer, [2]: integer }? The location the window will spawn at. If the window spawns tiled, it will instead snap to this location when set to floating.
+---@field snap_to_increments boolean? Whether or not tiled layouts should round this window's size down to its resize increments, like terminal cells.
+---@field deny_fullscreen boolean? Whether or not requests from the window to make itself fullscreen will be denied.
+---@field follow_active_tag boolean? Whether or not this window will move onto whatever tags become active on its output.
+---@field insert_position InsertPosition? Where this window will go in the layout. Use "Prepend" to always open it as the master.
+---@field never_throttle boolean? Whether or not this window gets every frame callback regardless of `pinnacle.set_background_throttle`.
 
 ---@class WindowRule Attributes the window will be spawned with.
 ---@field output (Output|OutputName)? The output this window will be spawned on. TODO:
@@ -37,3 +46,8 @@
 ---@field fullscreen_or_maximized FullscreenOrMaximized? Whether or not this window will be spawned fullscreen, maximized, or forced to neither.
 ---@field size { [1]: integer, [2]: integer }? The size the window will spawn with, with [1] being width and [2] being height. This must be a strictly positive integer; putting 0 will crash the compositor.
 ---@field location { [1]: integer, [2]: integer }? The location the window will spawn at. If the window spawns tiled, it will instead snap to this location when set to floating.
+---@field snap_to_increments boolean? Whether or not tiled layouts should round this window's size down to its resize increments, like terminal cells.
+---@field deny_fullscreen boolean? Whether or not requests from the window to make itself fullscreen will be denied.
+---@field follow_active_tag boolean? Whether or not this window will move onto whatever tags become active on its output.
+---@field insert_position InsertPosition? Where this window will go in the layout. Use "Prepend" to always open it as the master.
+---@field never_throttle boolean? Whether or not this window gets every frame callback regardless of `pinnacle.set_background_throttle`.
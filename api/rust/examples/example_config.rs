@@ -2,10 +2,10 @@
 use pinnacle_api::prelude::*;
 use pinnacle_api::*;
 
-fn main() {
+fn main() -> anyhow::Result<()> {
     // Connect to the Pinnacle server.
     // This needs to be called before you start calling any config functions.
-    pinnacle_api::connect().unwrap();
+    pinnacle_api::connect()?;
 
     let mod_key = Modifier::Ctrl; // This is set to Ctrl to not conflict with your WM/DE keybinds.
 
@@ -104,14 +104,48 @@ fn main() {
         &mut callback_vec,
     );
 
+    // Hold back notifications while do not disturb is on.
+    pinnacle_api::set_do_not_disturb_namespaces(&["notifications"]);
+
+    input::keybind(
+        &[mod_key],
+        'n',
+        move |_| {
+            pinnacle_api::set_do_not_disturb(!pinnacle_api::do_not_disturb());
+        },
+        &mut callback_vec,
+    );
+
+    // Toggle locking the pointer to the focused output.
+    let mut pointer_locked = false;
+
+    input::keybind(
+        &[mod_key],
+        'l',
+        move |_| {
+            pointer_locked = !pointer_locked;
+            if pointer_locked {
+                input::lock_pointer_to_output(output::get_focused().as_ref());
+            } else {
+                input::lock_pointer_to_output(None);
+            }
+        },
+        &mut callback_vec,
+    );
+
     // Output stuff -------------------------------------------------------
 
     let tags = ["1", "2", "3", "4", "5"];
 
-    output::connect_for_all(
-        move |output, _| {
-            tag::add(&output, tags.as_slice());
-            tag::get("1", Some(&output)).unwrap().toggle();
+    output::connect_for_all_with_replay(
+        move |output, replay, _| {
+            // `tag::add_if_missing` returns handles to the tags, so there's no need to look them up
+            let tag_handles = tag::add_if_missing(&output, tags.as_slice());
+
+            // Keep viewing the same tags after a config reload, otherwise start on tag 1
+            for i in replay.tags_to_activate(tags.as_slice()) {
+                tag_handles[i].toggle();
+            }
         },
         &mut callback_vec,
     );
@@ -151,56 +185,14 @@ fn main() {
 
     // Keybinds for tags ------------------------------------------
 
-    for tag_name in tags.iter().map(|t| t.to_string()) {
-        // mod_key + 1-5 to switch to tag
-        let t = tag_name.clone();
-        let num = tag_name.chars().next().unwrap();
-        input::keybind(
-            &[mod_key],
-            num,
-            move |_| {
-                tag::get(&t, None).unwrap().switch_to();
-            },
-            &mut callback_vec,
-        );
-
-        // mod_key + Shift + 1-5 to toggle tag
-        let t = tag_name.clone();
-        input::keybind(
-            &[mod_key, Modifier::Shift],
-            num,
-            move |_| {
-                tag::get(&t, None).unwrap().toggle();
-            },
-            &mut callback_vec,
-        );
-
-        // mod_key + Alt + 1-5 to move focused window to tag
-        let t = tag_name.clone();
-        input::keybind(
-            &[mod_key, Modifier::Alt],
-            num,
-            move |_| {
-                if let Some(window) = window::get_focused() {
-                    window.move_to_tag(&tag::get(&t, None).unwrap());
-                }
-            },
-            &mut callback_vec,
-        );
-
-        // mod_key + Shift + Alt + 1-5 to toggle tag on focused window
-        let t = tag_name.clone();
-        input::keybind(
-            &[mod_key, Modifier::Shift, Modifier::Alt],
-            num,
-            move |_| {
-                if let Some(window) = window::get_focused() {
-                    window.toggle_tag(&tag::get(&t, None).unwrap());
-                }
-            },
-            &mut callback_vec,
-        );
-    }
+    // mod_key + 1-5 to switch to a tag,
+    // mod_key + Shift + 1-5 to toggle a tag,
+    // mod_key + Alt + 1-5 to move the focused window to a tag, and
+    // mod_key + Shift + Alt + 1-5 to toggle a tag on the focused window.
+    tag::setup_standard_binds(mod_key, tags.as_slice(), &mut callback_vec);
+
+    // Switch to the tag you move the focused window to so you can keep using it.
+    pinnacle_api::set_focus_follows_moved_window(FollowPolicy::SwitchTag);
 
     // At the very end of your config, you will need to start listening to Pinnacle in order for
     // your callbacks to be correctly called.
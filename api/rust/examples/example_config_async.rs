@@ -0,0 +1,157 @@
+// This is the same as `example_config.rs`, but uses the async API.
+// Run it with `cargo run --example example_config_async --features async`.
+
+use std::time::Duration;
+
+use pinnacle_api::prelude::*;
+use pinnacle_api::*;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> anyhow::Result<()> {
+    // Connect to the Pinnacle server without blocking.
+    r#async::connect().await?;
+
+    let mod_key = Modifier::Ctrl; // This is set to Ctrl to not conflict with your WM/DE keybinds.
+
+    let terminal = "alacritty";
+
+    process::set_env("MOZ_ENABLE_WAYLAND", "1");
+
+    let mut callback_vec = CallbackVec::new();
+
+    // Keybinds ------------------------------------------------------
+
+    input::mousebind(
+        &[mod_key],
+        MouseButton::Left,
+        MouseEdge::Press,
+        move |_| {
+            window::begin_move(MouseButton::Left);
+        },
+        &mut callback_vec,
+    );
+
+    input::mousebind(
+        &[mod_key],
+        MouseButton::Right,
+        MouseEdge::Press,
+        move |_| {
+            window::begin_resize(MouseButton::Right);
+        },
+        &mut callback_vec,
+    );
+
+    input::keybind(
+        &[mod_key, Modifier::Alt],
+        'q',
+        |_| pinnacle_api::quit(),
+        &mut callback_vec,
+    );
+
+    input::keybind(
+        &[mod_key, Modifier::Alt],
+        'c',
+        move |_| {
+            if let Some(window) = window::get_focused() {
+                window.close();
+            }
+        },
+        &mut callback_vec,
+    );
+
+    input::keybind(
+        &[mod_key],
+        xkbcommon::xkb::keysyms::KEY_Return,
+        move |_| {
+            process::spawn(vec![terminal]).unwrap();
+        },
+        &mut callback_vec,
+    );
+
+    // Callbacks can start async work with `r#async::spawn`.
+    // Here, a floating terminal is opened without blocking other keybinds while it starts up.
+    input::keybind(
+        &[mod_key, Modifier::Shift],
+        xkbcommon::xkb::keysyms::KEY_Return,
+        move |_| {
+            r#async::spawn(async move {
+                let condition = WindowRuleCondition::new().class(&["Alacritty"]);
+                match r#async::spawn_and_wait_window(vec![terminal], condition, 5000).await {
+                    Ok(window) => window.toggle_floating(),
+                    Err(err) => eprintln!("{err}"),
+                }
+            });
+        },
+        &mut callback_vec,
+    );
+
+    // Turn do not disturb on for ten minutes.
+    input::keybind(
+        &[mod_key],
+        'n',
+        move |_| {
+            r#async::spawn(async {
+                pinnacle_api::set_do_not_disturb(true);
+                tokio::time::sleep(Duration::from_secs(600)).await;
+                pinnacle_api::set_do_not_disturb(false);
+            });
+        },
+        &mut callback_vec,
+    );
+
+    // Output stuff -------------------------------------------------------
+
+    let tags = ["1", "2", "3", "4", "5"];
+
+    output::connect_for_all_with_replay(
+        move |output, replay, _| {
+            // `tag::add_if_missing` returns handles to the tags, so there's no need to look them up
+            let tag_handles = tag::add_if_missing(&output, tags.as_slice());
+
+            // Keep viewing the same tags after a config reload, otherwise start on tag 1
+            for i in replay.tags_to_activate(tags.as_slice()) {
+                tag_handles[i].toggle();
+            }
+        },
+        &mut callback_vec,
+    );
+
+    // Layouts -----------------------------------------------------------
+
+    let mut layout_cycler = tag::layout_cycler(&[
+        Layout::MasterStack,
+        Layout::Dwindle,
+        Layout::Spiral,
+        Layout::CornerTopLeft,
+        Layout::CornerTopRight,
+        Layout::CornerBottomLeft,
+        Layout::CornerBottomRight,
+    ]);
+
+    input::keybind(
+        &[mod_key],
+        xkbcommon::xkb::keysyms::KEY_space,
+        move |_| {
+            (layout_cycler.next)(None);
+        },
+        &mut callback_vec,
+    );
+
+    input::keybind(
+        &[mod_key, Modifier::Shift],
+        xkbcommon::xkb::keysyms::KEY_space,
+        move |_| {
+            (layout_cycler.prev)(None);
+        },
+        &mut callback_vec,
+    );
+
+    // Keybinds for tags ------------------------------------------
+
+    tag::setup_standard_binds(mod_key, tags.as_slice(), &mut callback_vec);
+
+    // At the very end of your config, run your callbacks as Pinnacle calls them.
+    //
+    // This will not return.
+    match r#async::run(callback_vec).await {}
+}
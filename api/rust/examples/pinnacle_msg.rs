@@ -0,0 +1,218 @@
+// A small command line client for sending one-off commands to Pinnacle from scripts
+// or other programs' keybinds, like `swaymsg`.
+//
+// Run it with `cargo run --example pinnacle_msg -- <command>`. See `USAGE` below for commands.
+//
+// Every command connects, sends what it needs to, and exits. Getters print JSON.
+
+use pinnacle_api::output::OutputHandle;
+use pinnacle_api::window::WindowHandle;
+use pinnacle_api::*;
+use serde_json::{json, Value};
+
+const USAGE: &str = "\
+Usage: pinnacle_msg [--output <name>] [--window-id <id>] <command>
+
+Commands:
+    focus-next           Focus the next window on the output
+    focus-prev           Focus the previous window on the output
+    move-to-tag <name>   Move the window to the tag called <name> on the output
+    switch-tag <name>    Switch to the tag called <name> on the output
+    spawn -- <cmd>...    Run <cmd>
+    reload-config        Restart the config
+    outputs              Print all outputs as JSON
+    tags                 Print the tags on the output, or all tags if no output is given, as JSON
+    windows              Print all windows as JSON
+    dump                 Print all outputs, tags, and windows as JSON
+
+Options:
+    --output <name>      The output to use instead of the focused one
+    --window-id <id>     The window to use instead of the focused one
+";
+
+enum Command {
+    FocusNext,
+    FocusPrev,
+    MoveToTag(String),
+    SwitchTag(String),
+    Spawn(Vec<String>),
+    ReloadConfig,
+    Outputs,
+    Tags,
+    Windows,
+    Dump,
+}
+
+struct Args {
+    output: Option<String>,
+    window_id: Option<u32>,
+    command: Command,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> anyhow::Result<Args> {
+    let mut output = None;
+    let mut window_id = None;
+
+    let command = loop {
+        let arg = args
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("no command given"))?;
+
+        match arg.as_str() {
+            "--output" => {
+                output = Some(
+                    args.next()
+                        .ok_or_else(|| anyhow::anyhow!("--output needs an output name"))?,
+                );
+            }
+            "--window-id" => {
+                let id = args
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("--window-id needs a window id"))?;
+                window_id = Some(id.parse()?);
+            }
+            _ => break arg,
+        }
+    };
+
+    let mut tag_name = || {
+        args.next()
+            .ok_or_else(|| anyhow::anyhow!("{command} needs a tag name"))
+    };
+
+    let command = match command.as_str() {
+        "focus-next" => Command::FocusNext,
+        "focus-prev" => Command::FocusPrev,
+        "move-to-tag" => Command::MoveToTag(tag_name()?),
+        "switch-tag" => Command::SwitchTag(tag_name()?),
+        "spawn" => {
+            let cmd = args.skip_while(|arg| arg == "--").collect::<Vec<_>>();
+            if cmd.is_empty() {
+                anyhow::bail!("spawn needs a command");
+            }
+            Command::Spawn(cmd)
+        }
+        "reload-config" => Command::ReloadConfig,
+        "outputs" => Command::Outputs,
+        "tags" => Command::Tags,
+        "windows" => Command::Windows,
+        "dump" => Command::Dump,
+        command => anyhow::bail!("unknown command `{command}`"),
+    };
+
+    Ok(Args {
+        output,
+        window_id,
+        command,
+    })
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{err}\n\n{USAGE}");
+            std::process::exit(1);
+        }
+    };
+
+    // Connecting as a one-off client leaves your config's connection alone.
+    pinnacle_api::connect_one_off()?;
+
+    let output = match &args.output {
+        Some(name) => Some(
+            output::get_by_name(name).ok_or_else(|| anyhow::anyhow!("no output named {name}"))?,
+        ),
+        None => None,
+    };
+
+    let target_window = || -> anyhow::Result<WindowHandle> {
+        match args.window_id {
+            Some(id) => {
+                window::get_by_id(id).ok_or_else(|| anyhow::anyhow!("no window with id {id}"))
+            }
+            None => window::get_focused().ok_or_else(|| anyhow::anyhow!("no window is focused")),
+        }
+    };
+
+    let target_tag = |name: &str| {
+        tag::get(name, output.as_ref())
+            .ok_or_else(|| anyhow::anyhow!("no tag named {name} on the output"))
+    };
+
+    match args.command {
+        Command::FocusNext => window::focus_next(output.as_ref()),
+        Command::FocusPrev => window::focus_prev(output.as_ref()),
+        Command::MoveToTag(name) => target_window()?.move_to_tag(&target_tag(&name)?),
+        Command::SwitchTag(name) => target_tag(&name)?.switch_to(),
+        Command::Spawn(cmd) => process::spawn(cmd.iter().map(String::as_str).collect())?,
+        Command::ReloadConfig => pinnacle_api::reload_config(),
+        Command::Outputs => print_json(outputs_json()),
+        Command::Tags => print_json(tags_json(output.as_ref())),
+        Command::Windows => print_json(windows_json()),
+        Command::Dump => print_json(json!({
+            "outputs": outputs_json(),
+            "tags": tags_json(None),
+            "windows": windows_json(),
+        })),
+    }
+
+    Ok(())
+}
+
+fn print_json(value: Value) {
+    println!("{value:#}");
+}
+
+fn outputs_json() -> Value {
+    output::get_all()
+        .map(|output| {
+            let props = output.properties();
+            json!({
+                "name": output.name(),
+                "make": props.make,
+                "model": props.model,
+                "loc": props.loc,
+                "res": props.res,
+                "refresh_rate": props.refresh_rate,
+                "physical_size": props.physical_size,
+                "focused": props.focused,
+                "render_scale": props.render_scale,
+            })
+        })
+        .collect()
+}
+
+fn tags_json(output: Option<&OutputHandle>) -> Value {
+    tag::get_all()
+        .filter_map(|tag| {
+            let props = tag.properties();
+            if output.is_some_and(|output| props.output.as_ref() != Some(output)) {
+                return None;
+            }
+            Some(json!({
+                "name": props.name,
+                "active": props.active,
+                "output": props.output.map(|output| output.name()),
+            }))
+        })
+        .collect()
+}
+
+fn windows_json() -> Value {
+    window::get_all()
+        .map(|window| {
+            let props = window.properties();
+            json!({
+                "id": window.id(),
+                "class": props.class,
+                "title": props.title,
+                "loc": props.loc,
+                "size": props.size,
+                "focused": props.focused,
+                "floating": props.floating,
+                "fullscreen_or_maximized": props.fullscreen_or_maximized,
+            })
+        })
+        .collect()
+}
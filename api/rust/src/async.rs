@@ -0,0 +1,333 @@
+//! An async version of the config API, built on [tokio](https://tokio.rs).
+//!
+//! This needs the `async` feature.
+//!
+//! Connect with [`connect`] and end your config with [`run`] instead of
+//! [`crate::connect`] and [`crate::listen`]. Everything else in this crate works the same,
+//! so you can keep calling its functions from callbacks.
+//!
+//! Callbacks are still plain closures, but they are run inside a
+//! [`LocalSet`](tokio::task::LocalSet), so they can start async work with [`spawn`]:
+//!
+//! ```no_run
+//! use pinnacle_api::prelude::*;
+//! use pinnacle_api::*;
+//!
+//! #[tokio::main(flavor = "current_thread")]
+//! async fn main() -> anyhow::Result<()> {
+//!     r#async::connect().await?;
+//!
+//!     let mut callback_vec = CallbackVec::new();
+//!
+//!     input::keybind(
+//!         &[Modifier::Ctrl],
+//!         'a',
+//!         |_| {
+//!             r#async::spawn(async {
+//!                 tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+//!                 process::spawn(vec!["alacritty"]).unwrap();
+//!             });
+//!         },
+//!         &mut callback_vec,
+//!     );
+//!
+//!     match r#async::run(callback_vec).await {}
+//! }
+//! ```
+//!
+//! Since the other functions in this crate use the same connection, messages from Pinnacle
+//! are read on a separate thread and routed to whatever is waiting on them: request responses
+//! to the task or thread that made the request, and callbacks to [`run`].
+
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    future::Future,
+    os::unix::net::UnixStream,
+    sync::{Condvar, Mutex, OnceLock},
+    time::Instant,
+};
+
+use tokio::sync::{mpsc, oneshot};
+
+use crate::{
+    msg::{Args, CallbackId, IncomingMsg, Msg, Request, RequestId, RequestResponse},
+    window::{rules::WindowRuleCondition, PendingWindowWait, WindowHandle},
+    CallbackVec, Error, CONNECT_ATTEMPTS, CONNECT_BACKOFF, STREAM,
+};
+
+type CallbackMsg = Result<(CallbackId, Option<Args>, u64), Error>;
+
+static ROUTER: OnceLock<Router> = OnceLock::new();
+
+/// Routes messages read from Pinnacle to whatever is waiting on them.
+pub(crate) struct Router {
+    state: Mutex<RouterState>,
+    response_arrived: Condvar,
+    /// Where callbacks are sent. This is taken by [`run`].
+    callbacks: Mutex<Option<mpsc::UnboundedReceiver<CallbackMsg>>>,
+}
+
+#[derive(Default)]
+struct RouterState {
+    /// Responses that nothing has waited on yet.
+    responses: HashMap<RequestId, RequestResponse>,
+    /// Tasks waiting on responses.
+    waiters: HashMap<RequestId, oneshot::Sender<RequestResponse>>,
+    disconnected: bool,
+}
+
+/// Get the router if connected with [`connect`].
+pub(crate) fn router() -> Option<&'static Router> {
+    ROUTER.get()
+}
+
+impl Router {
+    fn respond(&self, request_id: RequestId, response: RequestResponse) {
+        let mut state = self.state.lock().unwrap();
+        let response = match state.waiters.remove(&request_id) {
+            // If the waiter went away, keep the response in case it's waited on again
+            Some(waiter) => match waiter.send(response) {
+                Ok(()) => return,
+                Err(response) => response,
+            },
+            None => response,
+        };
+        state.responses.insert(request_id, response);
+        self.response_arrived.notify_all();
+    }
+
+    fn disconnect(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.disconnected = true;
+        // Dropping the senders wakes the waiters up
+        state.waiters.clear();
+        self.response_arrived.notify_all();
+    }
+
+    /// Block until the response to the request with `request_id` arrives.
+    pub(crate) fn wait_blocking(&self, request_id: RequestId) -> Result<RequestResponse, Error> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(response) = state.responses.remove(&request_id) {
+                return Ok(response);
+            }
+            if state.disconnected {
+                return Err(Error::Disconnected);
+            }
+            state = self.response_arrived.wait(state).unwrap();
+        }
+    }
+
+    async fn wait(&self, request_id: RequestId) -> Result<RequestResponse, Error> {
+        let receiver = {
+            let mut state = self.state.lock().unwrap();
+            if let Some(response) = state.responses.remove(&request_id) {
+                return Ok(response);
+            }
+            if state.disconnected {
+                return Err(Error::Disconnected);
+            }
+            let (sender, receiver) = oneshot::channel();
+            state.waiters.insert(request_id, sender);
+            receiver
+        };
+
+        receiver.await.map_err(|_| Error::Disconnected)
+    }
+}
+
+/// Connect to Pinnacle without blocking.
+///
+/// This is the async version of [`crate::connect`] and connects to the same socket.
+/// Once connected, end your config with [`run`] instead of [`crate::listen`].
+///
+/// # Errors
+/// Returns an error if the socket couldn't be connected to or if you are already connected.
+pub async fn connect() -> anyhow::Result<()> {
+    if STREAM.get().is_some() {
+        anyhow::bail!("already connected to Pinnacle");
+    }
+
+    let socket_path = crate::socket_path();
+
+    let mut backoff = CONNECT_BACKOFF;
+    let mut attempt = 1;
+    let stream = loop {
+        match tokio::net::UnixStream::connect(&socket_path).await {
+            Ok(stream) => break stream,
+            Err(_) if attempt < CONNECT_ATTEMPTS => {
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+                attempt += 1;
+            }
+            Err(err) => {
+                return Err(anyhow::Error::new(err).context(format!(
+                    "failed to connect to Pinnacle at {} after {CONNECT_ATTEMPTS} attempts",
+                    socket_path.display()
+                )));
+            }
+        }
+    };
+
+    // The sync functions write to the socket directly, so it needs to block.
+    let stream = stream.into_std()?;
+    stream.set_nonblocking(false)?;
+    let reader = stream.try_clone()?;
+
+    crate::set_stream(stream)?;
+
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let router = Router {
+        state: Mutex::new(RouterState::default()),
+        response_arrived: Condvar::new(),
+        callbacks: Mutex::new(Some(receiver)),
+    };
+    ROUTER
+        .set(router)
+        .map_err(|_| anyhow::anyhow!("already connected to Pinnacle"))?;
+
+    std::thread::Builder::new()
+        .name("pinnacle_api reader".to_string())
+        .spawn(move || read_loop(reader, sender))?;
+
+    Ok(())
+}
+
+/// Read messages from Pinnacle until the connection closes, routing them as they come in.
+fn read_loop(mut stream: UnixStream, callbacks: mpsc::UnboundedSender<CallbackMsg>) {
+    let router = ROUTER.get().expect("router wasn't set");
+
+    loop {
+        match crate::read_framed_msg(&mut stream) {
+            Ok(IncomingMsg::CallCallback {
+                callback_id,
+                args,
+                serial,
+            }) => {
+                // If `run` has stopped there's nothing to call the callback anyway
+                let _ = callbacks.send(Ok((callback_id, args, serial)));
+            }
+            Ok(IncomingMsg::RequestResponse {
+                request_id,
+                response,
+            }) => {
+                router.respond(request_id, response);
+            }
+            Ok(IncomingMsg::Warning { message }) => {
+                crate::warn(crate::Warning::Pinnacle { message });
+            }
+            Err(err) => {
+                router.disconnect();
+                let _ = callbacks.send(Err(err));
+                return;
+            }
+        }
+    }
+}
+
+/// Make a request to Pinnacle and wait for its response without blocking.
+pub(crate) async fn request(request: Request) -> Result<RequestResponse, Error> {
+    let name = crate::stats::variant_name(&request);
+    let start = Instant::now();
+
+    let request_id = crate::try_send_request(request)?;
+    let response = read_response(request_id).await?;
+
+    crate::stats::record_round_trip(name, start.elapsed());
+    Ok(response)
+}
+
+/// Wait for the response to the request with `request_id` without blocking.
+pub(crate) async fn read_response(request_id: RequestId) -> Result<RequestResponse, Error> {
+    router()
+        .expect("not connected with `r#async::connect`")
+        .wait(request_id)
+        .await
+}
+
+/// Begin running callbacks as Pinnacle calls them.
+///
+/// This is the async version of [`crate::listen`] and needs to be awaited at the very end of
+/// your config. Callbacks are run inside a [`LocalSet`](tokio::task::LocalSet),
+/// so they can use [`spawn`].
+///
+/// If Pinnacle exits, this exits the config process.
+///
+/// # Panics
+/// Panics if you didn't connect with [`connect`] or if this was already called.
+pub async fn run(callback_vec: CallbackVec<'_>) -> Infallible {
+    let receiver = router()
+        .expect("not connected with `r#async::connect`")
+        .callbacks
+        .lock()
+        .unwrap()
+        .take()
+        .expect("`run` was already called");
+
+    // Everything the config set up was sent before this, so the reload is done
+    crate::send_msg(Msg::ConfigReady);
+
+    tokio::task::LocalSet::new()
+        .run_until(dispatch(callback_vec, receiver))
+        .await
+}
+
+async fn dispatch(
+    mut callback_vec: CallbackVec<'_>,
+    mut receiver: mpsc::UnboundedReceiver<CallbackMsg>,
+) -> Infallible {
+    loop {
+        let (callback_id, args, serial) = match receiver.recv().await {
+            Some(Ok(callback)) => callback,
+            Some(Err(err)) => crate::handle_error(err),
+            None => crate::handle_error(Error::Disconnected),
+        };
+
+        crate::call_callback(&mut callback_vec, callback_id, args, serial);
+    }
+}
+
+/// Run `future` alongside your callbacks.
+///
+/// This is meant to be called from callbacks to do async work without holding up other
+/// callbacks. The future doesn't need to be `Send`.
+///
+/// # Panics
+/// Panics if called outside of [`run`].
+pub fn spawn<F>(future: F)
+where
+    F: Future<Output = ()> + 'static,
+{
+    tokio::task::spawn_local(future);
+}
+
+/// Wait for a window that meets `condition` to open without blocking.
+///
+/// This is the async version of [`crate::window::wait_for`].
+pub async fn wait_for_window(
+    condition: WindowRuleCondition,
+    timeout_ms: u64,
+) -> anyhow::Result<WindowHandle> {
+    let response = request(Request::WaitForWindow {
+        condition: condition.0,
+        timeout_ms,
+    })
+    .await?;
+
+    WindowHandle::from_wait_response(response, timeout_ms)
+}
+
+/// Spawn a process and wait for a window that meets `condition` to open without blocking.
+///
+/// This is the async version of [`crate::process::spawn_and_wait_window`].
+pub async fn spawn_and_wait_window(
+    command: Vec<&str>,
+    condition: WindowRuleCondition,
+    timeout_ms: u64,
+) -> anyhow::Result<WindowHandle> {
+    // See `process::spawn_and_wait_window` for why the wait request is sent first
+    let pending = PendingWindowWait::send(condition, timeout_ms);
+    crate::process::spawn(command)?;
+    pending.wait_async().await
+}
@@ -2,11 +2,17 @@
 
 pub mod libinput;
 
-use xkbcommon::xkb::Keysym;
+use std::marker::PhantomData;
+
+use anyhow::Context;
+use serde::{de::DeserializeOwned, Serialize};
+use xkbcommon::xkb::{self, Keysym};
 
 use crate::{
-    msg::{Args, CallbackId, KeyIntOrString, Msg},
-    send_msg, CallbackVec,
+    input::libinput::DeviceType,
+    msg::{Args, CallbackId, KeyIntOrString, Msg, Request, RequestResponse},
+    output::OutputHandle,
+    request, send_msg, CallbackVec,
 };
 
 /// Set a keybind.
@@ -22,36 +28,636 @@ use crate::{
 /// - `callback_vec`: Your [`CallbackVec`] to insert `action` into.
 ///
 /// `action` takes in a `&mut `[`CallbackVec`] for use in the closure.
+///
+/// The keybind triggers when the key is pressed. Use [`keybind_on`] to trigger it on release.
+///
+/// This returns a [`BindHandle`] that can remove the keybind later.
 pub fn keybind<'a, F>(
     modifiers: &[Modifier],
     key: impl Into<KeyIntOrString>,
+    action: F,
+    callback_vec: &mut CallbackVec<'a>,
+) -> BindHandle
+where
+    F: FnMut(&mut CallbackVec) + 'a,
+{
+    keybind_on(KeyEdge::Press, modifiers, key, action, callback_vec)
+}
+
+/// Set a keybind that triggers on the given key edge.
+/// If called with an already existing keybind on the same edge, it gets replaced.
+///
+/// This is the same as [`keybind`], but takes an `edge` to choose whether the keybind triggers
+/// on key press, key release, or both.
+///
+/// Release keybinds only trigger if `modifiers` are still held when the key is released.
+/// The key press is also kept from the focused window so it doesn't get a release
+/// without a press.
+pub fn keybind_on<'a, F>(
+    edge: KeyEdge,
+    modifiers: &[Modifier],
+    key: impl Into<KeyIntOrString>,
+    action: F,
+    callback_vec: &mut CallbackVec<'a>,
+) -> BindHandle
+where
+    F: FnMut(&mut CallbackVec) + 'a,
+{
+    set_keybind(
+        edge,
+        modifiers,
+        key.into(),
+        KeybindOptions::default(),
+        action,
+        callback_vec,
+    )
+}
+
+/// Set a keybind in a keybind layer.
+/// If called with an already existing keybind in the same layer, it gets replaced.
+///
+/// This is the same as [`keybind`], but the keybind only triggers while `layer` is active.
+/// Keybinds set with the other functions are in the default layer, which is only active
+/// when no layer is entered. Enter a layer with [`enter_bind_layer`].
+///
+/// ```no_run
+/// # use pinnacle_api::prelude::*;
+/// # use pinnacle_api::*;
+/// # let mut callback_vec = CallbackVec::new();
+/// // Super + r enters a resize mode where Escape leaves it again
+/// input::keybind(
+///     &[Modifier::Super],
+///     'r',
+///     |_| input::enter_bind_layer("resize"),
+///     &mut callback_vec,
+/// );
+/// input::layer_keybind(
+///     "resize",
+///     &[],
+///     xkbcommon::xkb::keysyms::KEY_Escape,
+///     |_| input::leave_bind_layer(),
+///     &mut callback_vec,
+/// );
+/// ```
+pub fn layer_keybind<'a, F>(
+    layer: &str,
+    modifiers: &[Modifier],
+    key: impl Into<KeyIntOrString>,
+    action: F,
+    callback_vec: &mut CallbackVec<'a>,
+) -> BindHandle
+where
+    F: FnMut(&mut CallbackVec) + 'a,
+{
+    set_keybind(
+        KeyEdge::Press,
+        modifiers,
+        key.into(),
+        KeybindOptions {
+            layer: Some(layer.to_string()),
+            ..Default::default()
+        },
+        action,
+        callback_vec,
+    )
+}
+
+/// Make `layer` the active keybind layer.
+///
+/// Only keybinds set with [`layer_keybind`] for `layer` trigger until another layer is entered
+/// or [`leave_bind_layer`] is called. Key presses without a keybind in the layer are swallowed
+/// unless [`set_bind_layer_forwards_unbound`] was called for it. The kill and reload keybinds,
+/// media keys, and VT switching still work.
+pub fn enter_bind_layer(layer: &str) {
+    send_msg(Msg::EnterBindLayer {
+        layer: Some(layer.to_string()),
+    });
+}
+
+/// Go back to the default keybind layer.
+pub fn leave_bind_layer() {
+    send_msg(Msg::EnterBindLayer { layer: None });
+}
+
+/// Set whether `layer` sends key presses it has no keybind for to the focused window
+/// instead of swallowing them.
+pub fn set_bind_layer_forwards_unbound(layer: &str, forward: bool) {
+    send_msg(Msg::SetBindLayerForwardsUnbound {
+        layer: layer.to_string(),
+        forward,
+    });
+}
+
+/// Connect to the active keybind layer changing.
+///
+/// `func` gets the layer that was entered and the one that was left,
+/// where `None` is the default layer. Use this to show which mode you're in.
+pub fn connect_bind_layer_changed<'a, F>(mut func: F, callback_vec: &mut CallbackVec<'a>)
+where
+    F: FnMut(Option<&str>, Option<&str>, &mut CallbackVec) + 'a,
+{
+    let closure = move |args: Option<Args>, callback_vec: &mut CallbackVec| {
+        if let Some(Args::BindLayerChanged { layer, previous }) = args {
+            func(layer.as_deref(), previous.as_deref(), callback_vec);
+        }
+    };
+
+    let callback_id = callback_vec.insert(closure);
+
+    send_msg(Msg::ConnectBindLayerChanged { callback_id });
+}
+
+/// Set a keybind with a group and description.
+///
+/// This is the same as [`keybind`], but also takes a `group`, like `"Apps"` or `"Tags"`,
+/// and a `description` of what the keybind does. These don't change how the keybind works,
+/// but are returned by [`get_keybinds`] so you can show a list of your keybinds.
+///
+/// ```no_run
+/// # use pinnacle_api::prelude::*;
+/// # use pinnacle_api::*;
+/// # let mut callback_vec = CallbackVec::new();
+/// input::keybind_described(
+///     &[Modifier::Super],
+///     xkbcommon::xkb::keysyms::KEY_Return,
+///     "Apps",
+///     "Spawn terminal",
+///     |_| {
+///         process::spawn(vec!["alacritty"]).unwrap();
+///     },
+///     &mut callback_vec,
+/// );
+/// ```
+pub fn keybind_described<'a, F>(
+    modifiers: &[Modifier],
+    key: impl Into<KeyIntOrString>,
+    group: &str,
+    description: &str,
+    action: F,
+    callback_vec: &mut CallbackVec<'a>,
+) -> BindHandle
+where
+    F: FnMut(&mut CallbackVec) + 'a,
+{
+    set_keybind(
+        KeyEdge::Press,
+        modifiers,
+        key.into(),
+        KeybindOptions {
+            group: Some(group.to_string()),
+            description: Some(description.to_string()),
+            ..Default::default()
+        },
+        action,
+        callback_vec,
+    )
+}
+
+/// Set a keybind that still sends the key to the focused window.
+/// If called with an already existing keybind, it gets replaced.
+///
+/// This is the same as [`keybind`], but the key press isn't kept from the focused window,
+/// so you can react to a key without taking it away. This is useful for things like
+/// logging keys or toggling an overlay.
+pub fn keybind_passthrough<'a, F>(
+    modifiers: &[Modifier],
+    key: impl Into<KeyIntOrString>,
+    action: F,
+    callback_vec: &mut CallbackVec<'a>,
+) -> BindHandle
+where
+    F: FnMut(&mut CallbackVec) + 'a,
+{
+    set_keybind(
+        KeyEdge::Press,
+        modifiers,
+        key.into(),
+        KeybindOptions {
+            passthrough: true,
+            ..Default::default()
+        },
+        action,
+        callback_vec,
+    )
+}
+
+/// Set a keybind that triggers even while the focused window inhibits keyboard shortcuts.
+/// If called with an already existing keybind, it gets replaced.
+///
+/// VM viewers and remote desktop clients can inhibit shortcuts while they're focused
+/// so keys like Super go to them. No other keybinds trigger then, including the kill
+/// and reload keybinds, so use this for a way out.
+///
+/// These also trigger while a focused X11 app like a game has grabbed the keyboard.
+/// The kill and reload keybinds work then too, see `x11_keyboard_grab` in `metaconfig.toml`.
+pub fn keybind_force<'a, F>(
+    modifiers: &[Modifier],
+    key: impl Into<KeyIntOrString>,
+    action: F,
+    callback_vec: &mut CallbackVec<'a>,
+) -> BindHandle
+where
+    F: FnMut(&mut CallbackVec) + 'a,
+{
+    set_keybind(
+        KeyEdge::Press,
+        modifiers,
+        key.into(),
+        KeybindOptions {
+            force: true,
+            ..Default::default()
+        },
+        action,
+        callback_vec,
+    )
+}
+
+/// Set a keybind whose callback is called again while the key is held.
+/// If called with an already existing keybind, it gets replaced.
+///
+/// This is the same as [`keybind`], but after the keyboard's repeat delay the callback
+/// is called at its repeat rate, like a held key types repeatedly. Set these with
+/// [`set_repeat_rate`]. This is useful for things like changing the volume or resizing
+/// windows.
+///
+/// Repeating stops when the key or one of `modifiers` is released, another key is pressed,
+/// or keyboard focus changes.
+pub fn keybind_repeating<'a, F>(
+    modifiers: &[Modifier],
+    key: impl Into<KeyIntOrString>,
+    action: F,
+    callback_vec: &mut CallbackVec<'a>,
+) -> BindHandle
+where
+    F: FnMut(&mut CallbackVec) + 'a,
+{
+    set_keybind(
+        KeyEdge::Press,
+        modifiers,
+        key.into(),
+        KeybindOptions {
+            repeats: true,
+            ..Default::default()
+        },
+        action,
+        callback_vec,
+    )
+}
+
+/// Options for [`set_keybind`] that most keybinds leave off.
+#[derive(Default)]
+struct KeybindOptions {
+    group: Option<String>,
+    description: Option<String>,
+    layer: Option<String>,
+    passthrough: bool,
+    force: bool,
+    repeats: bool,
+    data: Option<Vec<u8>>,
+}
+
+fn set_keybind<'a, F>(
+    edge: KeyEdge,
+    modifiers: &[Modifier],
+    key: KeyIntOrString,
+    options: KeybindOptions,
     mut action: F,
     callback_vec: &mut CallbackVec<'a>,
-) where
+) -> BindHandle
+where
     F: FnMut(&mut CallbackVec) + 'a,
 {
     let args_callback = move |_: Option<Args>, callback_vec: &mut CallbackVec<'_>| {
         action(callback_vec);
     };
 
-    let len = callback_vec.callbacks.len();
-    callback_vec.callbacks.push(Box::new(args_callback));
+    let callback_id = callback_vec.insert(args_callback);
+
+    bind_callback(edge, modifiers, key, options, callback_id, callback_vec)
+}
+
+/// Bind the already inserted callback with `callback_id` to a key.
+fn bind_callback(
+    edge: KeyEdge,
+    modifiers: &[Modifier],
+    key: KeyIntOrString,
+    options: KeybindOptions,
+    callback_id: CallbackId,
+    callback_vec: &mut CallbackVec,
+) -> BindHandle {
+    let keysym = key.to_keysym();
 
-    let key = key.into();
+    let KeybindOptions {
+        group,
+        description,
+        layer,
+        passthrough,
+        force,
+        repeats,
+        data,
+    } = options;
+
+    // Pinnacle replaces the old keybind, so its callback won't be called anymore
+    if let Some(old_callback_id) = callback_vec.keybinds.insert(
+        keybind_key(layer.as_deref(), modifiers, keysym, edge),
+        callback_id,
+    ) {
+        if old_callback_id != callback_id {
+            callback_vec.remove_keybind_callback(old_callback_id);
+        }
+    }
 
     let msg = Msg::SetKeybind {
         key,
         modifiers: modifiers.to_vec(),
-        callback_id: CallbackId(len as u32),
+        edge,
+        callback_id,
+        group,
+        description,
+        layer: layer.clone(),
+        passthrough,
+        force,
+        repeats,
+        data,
+    };
+
+    send_msg(msg);
+
+    BindHandle {
+        modifiers: modifiers.to_vec(),
+        key: keysym,
+        edge,
+        layer,
+        callback_id,
+    }
+}
+
+/// A callback that many keybinds can share, each with its own data of type `D`.
+///
+/// Create one with [`keybind_callback`] and bind it with [`keybind_with_data`].
+/// Removing or replacing the keybinds doesn't remove the callback, so it can be bound again.
+#[derive(Debug)]
+pub struct KeybindCallback<D> {
+    callback_id: CallbackId,
+    _data: PhantomData<fn(D)>,
+}
+
+impl<D> Clone for KeybindCallback<D> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<D> Copy for KeybindCallback<D> {}
+
+/// Create a callback for keybinds set with [`keybind_with_data`].
+///
+/// `action` gets the data of the keybind that was triggered, so one callback can do
+/// something different for each keybind without capturing anything.
+///
+/// If the data can't be deserialized into a `D`, `action` isn't called and a
+/// [`Warning::KeybindData`][crate::Warning::KeybindData] is raised instead.
+///
+/// ```no_run
+/// # use pinnacle_api::prelude::*;
+/// # use pinnacle_api::*;
+/// # let mut callback_vec = CallbackVec::new();
+/// let view_tag = input::keybind_callback(
+///     |tag_name: String, _| {
+///         if let Some(tag) = tag::get(&tag_name, None) {
+///             tag.switch_to();
+///         }
+///     },
+///     &mut callback_vec,
+/// );
+///
+/// for tag_name in ["1", "2", "3"] {
+///     let key = tag_name.chars().next().unwrap();
+///     input::keybind_with_data(
+///         &[Modifier::Super],
+///         key,
+///         &tag_name.to_string(),
+///         view_tag,
+///         &mut callback_vec,
+///     )?;
+/// }
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn keybind_callback<'a, D, F>(
+    mut action: F,
+    callback_vec: &mut CallbackVec<'a>,
+) -> KeybindCallback<D>
+where
+    D: DeserializeOwned,
+    F: FnMut(D, &mut CallbackVec) + 'a,
+{
+    let args_callback = move |args: Option<Args>, callback_vec: &mut CallbackVec<'_>| {
+        let Some(Args::KeybindData { data }) = args else {
+            return;
+        };
+
+        match rmp_serde::from_slice::<D>(&data) {
+            Ok(data) => action(data, callback_vec),
+            Err(err) => crate::warn(crate::Warning::KeybindData {
+                type_name: std::any::type_name::<D>(),
+                error: err.to_string(),
+            }),
+        }
+    };
+
+    let callback_id = callback_vec.insert(args_callback);
+    callback_vec.shared_callbacks.insert(callback_id);
+
+    KeybindCallback {
+        callback_id,
+        _data: PhantomData,
+    }
+}
+
+/// Set a keybind that calls a shared `callback` with `data`.
+/// If called with an already existing keybind, it gets replaced.
+///
+/// This is the same as [`keybind`], but instead of a closure per keybind, many keybinds
+/// share one [`KeybindCallback`] and tell it what to do with their `data`.
+/// This keeps callbacks from cloning things like tag names into every closure.
+///
+/// # Errors
+/// Returns an error if `data` can't be serialized. Nothing is sent to Pinnacle if so.
+pub fn keybind_with_data<D>(
+    modifiers: &[Modifier],
+    key: impl Into<KeyIntOrString>,
+    data: &D,
+    callback: KeybindCallback<D>,
+    callback_vec: &mut CallbackVec,
+) -> anyhow::Result<BindHandle>
+where
+    D: Serialize,
+{
+    let data = rmp_serde::to_vec_named(data).context("failed to serialize keybind data")?;
+
+    Ok(bind_callback(
+        KeyEdge::Press,
+        modifiers,
+        key.into(),
+        KeybindOptions {
+            data: Some(data),
+            ..Default::default()
+        },
+        callback.callback_id,
+        callback_vec,
+    ))
+}
+
+/// A keybind set with [`keybind`], [`keybind_on`], [`keybind_described`], [`keybind_str`],
+/// [`keybind_with_data`], or [`layer_keybind`].
+///
+/// Keep this around to remove the keybind later, for example to set a group of keybinds
+/// while in a mode and remove them when leaving it.
+#[derive(Debug, Clone)]
+pub struct BindHandle {
+    modifiers: Vec<Modifier>,
+    key: Keysym,
+    edge: KeyEdge,
+    layer: Option<String>,
+    callback_id: CallbackId,
+}
+
+impl BindHandle {
+    /// Remove this keybind and free its callback.
+    ///
+    /// This does nothing if the keybind was already removed or replaced by setting another
+    /// keybind with the same layer, modifiers, key, and edge.
+    pub fn unbind(self, callback_vec: &mut CallbackVec) {
+        let key = keybind_key(self.layer.as_deref(), &self.modifiers, self.key, self.edge);
+        if callback_vec.keybinds.get(&key) != Some(&self.callback_id) {
+            return;
+        }
+
+        callback_vec.keybinds.remove(&key);
+        callback_vec.remove_keybind_callback(self.callback_id);
+
+        send_msg(Msg::RemoveKeybind {
+            key: self.key.into(),
+            modifiers: self.modifiers,
+            edge: self.edge,
+            layer: self.layer,
+        });
+    }
+}
+
+/// What identifies a keybind: its layer, its modifiers as a bitmask, its keysym, and its edge.
+pub(crate) type KeybindKey = (Option<String>, u8, u32, KeyEdge);
+
+fn keybind_key(
+    layer: Option<&str>,
+    modifiers: &[Modifier],
+    key: Keysym,
+    edge: KeyEdge,
+) -> KeybindKey {
+    let mask = modifiers
+        .iter()
+        .fold(0, |mask, modifier| mask | 1 << *modifier as u8);
+    (layer.map(str::to_string), mask, key.raw(), edge)
+}
+
+/// Set a keybind from a human-readable chord like `"Super+Shift+Return"`.
+///
+/// This is the same as [`keybind`], but takes the modifiers and key as one string.
+/// The chord is made of any number of modifiers followed by a key, separated by `+`.
+///
+/// Modifiers are case-insensitive, can be written more than once, and can be any of:
+/// - `Shift`
+/// - `Ctrl` or `Control`
+/// - `Alt` or `Mod1`
+/// - `Super`, `Logo`, or `Mod4`
+///
+/// The key can be a single character like `a` or `+`, or the name of a keysym like `Return` or
+/// `XF86AudioRaiseVolume`. Keysym names are matched exactly first, then case-insensitively.
+///
+/// ```no_run
+/// # use pinnacle_api::*;
+/// # let mut callback_vec = CallbackVec::new();
+/// input::keybind_str(
+///     "Super+Shift+Return",
+///     |_| {
+///         process::spawn(vec!["alacritty"]).unwrap();
+///     },
+///     &mut callback_vec,
+/// )?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+///
+/// # Errors
+/// Returns an error if a modifier or the key is unknown. Nothing is sent to Pinnacle if so.
+pub fn keybind_str<'a, F>(
+    chord: &str,
+    action: F,
+    callback_vec: &mut CallbackVec<'a>,
+) -> anyhow::Result<BindHandle>
+where
+    F: FnMut(&mut CallbackVec) + 'a,
+{
+    let (modifiers, key) = parse_chord(chord)?;
+    Ok(keybind(&modifiers, key, action, callback_vec))
+}
+
+/// Parse a chord like `"Super+Shift+Return"` into its modifiers and key.
+fn parse_chord(chord: &str) -> anyhow::Result<(Vec<Modifier>, Keysym)> {
+    // The key is everything after the last `+`, unless the key is `+` itself
+    let (modifiers, key) = match chord.strip_suffix("++") {
+        Some(modifiers) => (modifiers, "+"),
+        None if chord == "+" => ("", "+"),
+        None => chord.rsplit_once('+').unwrap_or(("", chord)),
     };
 
-    send_msg(msg).unwrap();
+    let mut modifiers = modifiers
+        .split('+')
+        .filter(|modifier| !modifier.is_empty())
+        .map(|modifier| match modifier.to_lowercase().as_str() {
+            "shift" => Ok(Modifier::Shift),
+            "ctrl" | "control" => Ok(Modifier::Ctrl),
+            "alt" | "mod1" => Ok(Modifier::Alt),
+            "super" | "logo" | "mod4" => Ok(Modifier::Super),
+            _ => Err(anyhow::anyhow!(
+                "unknown modifier `{modifier}` in keybind `{chord}`"
+            )),
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    // A modifier written twice, maybe under two names, is still just held
+    let mut seen = Vec::new();
+    modifiers.retain(|modifier| {
+        let first = !seen.contains(modifier);
+        seen.push(*modifier);
+        first
+    });
+
+    let mut chars = key.chars();
+    let keysym = match (chars.next(), chars.next()) {
+        (None, _) => anyhow::bail!("keybind `{chord}` has no key"),
+        (Some(ch), None) => Keysym::from_char(ch),
+        _ => {
+            let keysym = xkb::keysym_from_name(key, xkb::KEYSYM_NO_FLAGS);
+            if keysym.raw() != xkb::keysyms::KEY_NoSymbol {
+                keysym
+            } else {
+                xkb::keysym_from_name(key, xkb::KEYSYM_CASE_INSENSITIVE)
+            }
+        }
+    };
+
+    if keysym.raw() == xkb::keysyms::KEY_NoSymbol {
+        anyhow::bail!("unknown key `{key}` in keybind `{chord}`");
+    }
+
+    Ok((modifiers, keysym))
 }
 
 /// Set a mousebind. If called with an already existing mousebind, it gets replaced.
 ///
 /// The mousebind can happen either on button press or release, so you must
-/// specify which edge you desire.
+/// specify which edge you desire. It can also happen on a double click; see
+/// [`MouseEdge::DoubleClick`] for how that works with press binds.
 ///
 /// `action` takes in a `&mut `[`CallbackVec`] for use in the closure.
 pub fn mousebind<'a, F>(
@@ -63,28 +669,212 @@ pub fn mousebind<'a, F>(
 ) where
     F: FnMut(&mut CallbackVec) + 'a,
 {
-    let args_callback = move |_: Option<Args>, callback_vec: &mut CallbackVec<'_>| {
-        action(callback_vec);
+    mousebind_with_modifiers(
+        modifiers,
+        button,
+        edge,
+        move |_, callback_vec| action(callback_vec),
+        callback_vec,
+    );
+}
+
+/// Set a mousebind whose `action` gets the modifiers that were held when it triggered.
+///
+/// Modifiers that are held down or latched, like with sticky keys, count as held.
+/// Locked ones like Caps Lock don't, so `action` gets the same modifiers the bind was set with.
+///
+/// See [`mousebind`] for more.
+///
+/// ```no_run
+/// # use pinnacle_api::prelude::*;
+/// # use pinnacle_api::*;
+/// # let mut callback_vec = CallbackVec::new();
+/// input::mousebind_with_modifiers(
+///     &[Modifier::Super],
+///     MouseButton::Left,
+///     MouseEdge::Press,
+///     |modifiers, _| println!("Clicked with {modifiers:?}"),
+///     &mut callback_vec,
+/// );
+/// ```
+pub fn mousebind_with_modifiers<'a, F>(
+    modifiers: &[Modifier],
+    button: MouseButton,
+    edge: MouseEdge,
+    mut action: F,
+    callback_vec: &mut CallbackVec<'a>,
+) where
+    F: FnMut(&[Modifier], &mut CallbackVec) + 'a,
+{
+    let args_callback = move |args: Option<Args>, callback_vec: &mut CallbackVec<'_>| {
+        let modifiers = match args {
+            Some(Args::MousebindTriggered { modifiers }) => modifiers,
+            _ => Vec::new(),
+        };
+        action(&modifiers, callback_vec);
     };
 
-    let len = callback_vec.callbacks.len();
-    callback_vec.callbacks.push(Box::new(args_callback));
+    let callback_id = callback_vec.insert(args_callback);
 
     let msg = Msg::SetMousebind {
         modifiers: modifiers.to_vec(),
-        button: button as u32,
+        button: button.into(),
         edge,
-        callback_id: CallbackId(len as u32),
+        callback_id,
     };
 
-    send_msg(msg).unwrap();
+    send_msg(msg);
+}
+
+/// Set a scrollbind. If called with an already existing scrollbind, it gets replaced.
+///
+/// `action` runs once per wheel click in `direction` while `modifiers` are held.
+/// Continuous scrolling, like from a touchpad, is added up so a flick only runs `action`
+/// a few times. Scrolling that matches a scrollbind isn't sent to the window under the pointer.
+///
+/// `action` takes in a `&mut `[`CallbackVec`] for use in the closure.
+///
+/// ```no_run
+/// # use pinnacle_api::prelude::*;
+/// # use pinnacle_api::*;
+/// # let mut callback_vec = CallbackVec::new();
+/// // Cycle layouts with Super + scroll
+/// let mut layout_cycler = tag::layout_cycler(&[Layout::MasterStack, Layout::Dwindle]);
+/// input::scrollbind(
+///     &[Modifier::Super],
+///     ScrollDirection::Down,
+///     move |_| (layout_cycler.next)(None),
+///     &mut callback_vec,
+/// );
+/// ```
+pub fn scrollbind<'a, F>(
+    modifiers: &[Modifier],
+    direction: ScrollDirection,
+    mut action: F,
+    callback_vec: &mut CallbackVec<'a>,
+) where
+    F: FnMut(&mut CallbackVec) + 'a,
+{
+    let args_callback = move |_: Option<Args>, callback_vec: &mut CallbackVec<'_>| {
+        action(callback_vec);
+    };
+
+    let callback_id = callback_vec.insert(args_callback);
+
+    let msg = Msg::SetScrollbind {
+        modifiers: modifiers.to_vec(),
+        direction,
+        callback_id,
+    };
+
+    send_msg(msg);
+}
+
+/// Set a touchpad swipe bind. If called with an already existing swipe bind, it gets replaced.
+///
+/// `action` runs when a swipe with `fingers` fingers ends, if it moved furthest in `direction`.
+/// Swipes with a finger count that has a bind in any direction aren't sent to windows.
+///
+/// `action` takes in a `&mut `[`CallbackVec`] for use in the closure.
+///
+/// ```no_run
+/// # use pinnacle_api::prelude::*;
+/// # use pinnacle_api::*;
+/// # let mut callback_vec = CallbackVec::new();
+/// input::gesturebind(
+///     4,
+///     SwipeDirection::Left,
+///     |_| println!("Swiped left with four fingers"),
+///     &mut callback_vec,
+/// );
+/// ```
+pub fn gesturebind<'a, F>(
+    fingers: u32,
+    direction: SwipeDirection,
+    mut action: F,
+    callback_vec: &mut CallbackVec<'a>,
+) where
+    F: FnMut(&mut CallbackVec) + 'a,
+{
+    let args_callback = move |_: Option<Args>, callback_vec: &mut CallbackVec<'_>| {
+        action(callback_vec);
+    };
+
+    let callback_id = callback_vec.insert(args_callback);
+
+    let msg = Msg::SetGesturebind {
+        fingers,
+        direction,
+        callback_id,
+    };
+
+    send_msg(msg);
+}
+
+/// Set a touchpad pinch bind. If called with an already existing pinch bind, it gets replaced.
+///
+/// `action` runs when a pinch with `fingers` fingers ends, if it pinched in `direction`
+/// by at least `threshold`. The threshold is how far the pinch's scale moved from 1.0,
+/// so `0.2` triggers when the fingers end up 20% closer together or further apart.
+/// Pinches with a finger count that has a bind in either direction aren't sent to windows.
+///
+/// `action` takes in a `&mut `[`CallbackVec`] for use in the closure.
+pub fn pinchbind<'a, F>(
+    fingers: u32,
+    direction: PinchDirection,
+    threshold: f64,
+    mut action: F,
+    callback_vec: &mut CallbackVec<'a>,
+) where
+    F: FnMut(&mut CallbackVec) + 'a,
+{
+    let args_callback = move |_: Option<Args>, callback_vec: &mut CallbackVec<'_>| {
+        action(callback_vec);
+    };
+
+    let callback_id = callback_vec.insert(args_callback);
+
+    let msg = Msg::SetPinchbind {
+        fingers,
+        direction,
+        threshold,
+        callback_id,
+    };
+
+    send_msg(msg);
 }
 
 /// Set the xkbconfig for your keyboard.
 ///
 /// Parameters set to `None` will be set to their default values.
+/// This can be called at any time, like from a keybind, and keybinds keep working
+/// with the new layout.
+///
+/// If xkbcommon can't build a keymap from these, like with a misspelled layout,
+/// the keymap isn't changed and Pinnacle sends back a [`Warning`](crate::Warning).
 ///
 /// Read `xkeyboard-config(7)` for more information.
+///
+/// # Example
+/// ```no_run
+/// use pinnacle_api::prelude::*;
+/// use pinnacle_api::*;
+///
+/// let mut callback_vec = CallbackVec::new();
+/// let mut german = false;
+///
+/// // Switch between US and German layouts
+/// input::keybind(
+///     &[Modifier::Super],
+///     xkbcommon::xkb::keysyms::KEY_space,
+///     move |_| {
+///         german = !german;
+///         let layout = if german { "de" } else { "us" };
+///         input::set_xkb_config(None, None, Some(layout), None, None);
+///     },
+///     &mut callback_vec,
+/// );
+/// ```
 pub fn set_xkb_config(
     rules: Option<&str>,
     model: Option<&str>,
@@ -100,26 +890,352 @@ pub fn set_xkb_config(
         options: options.map(|s| s.to_string()),
     };
 
-    send_msg(msg).unwrap();
+    send_msg(msg);
+}
+
+/// Set how fast held keys repeat.
+///
+/// Keys repeat `rate_hz` times a second after being held for `delay_ms` milliseconds.
+/// Both need to be positive; otherwise Pinnacle ignores this.
+///
+/// This stays set across config reloads.
+pub fn set_repeat_rate(rate_hz: i32, delay_ms: i32) {
+    let msg = Msg::SetKeyboardRepeat {
+        rate: rate_hz,
+        delay: delay_ms,
+    };
+
+    send_msg(msg);
+}
+
+/// Set the command that runs when `key` is pressed while default media keys are enabled.
+///
+/// Passing an empty `command` removes the handler for `key`.
+///
+/// Keybinds set with [`keybind`] always take precedence over these.
+/// See [`enable_default_media_keys`][crate::enable_default_media_keys].
+pub fn set_media_key_command(key: impl Into<KeyIntOrString>, command: &[&str]) {
+    let msg = Msg::SetMediaKeyCommand {
+        key: key.into(),
+        command: command.iter().map(|s| s.to_string()).collect(),
+    };
+
+    send_msg(msg);
+}
+
+/// Lock the pointer to `output`, or unlock it if `None`.
+///
+/// While locked, the pointer can't leave the output. It gets unlocked if the output is
+/// disconnected.
+pub fn lock_pointer_to_output(output: Option<&OutputHandle>) {
+    let msg = Msg::LockPointerToOutput {
+        output_name: output.map(|op| op.0.clone()),
+    };
+
+    send_msg(msg);
+}
+
+/// Set how many pixels the pointer needs to be pushed past the edge of an output before it
+/// crosses over to the next one.
+///
+/// The default is 0, meaning the pointer crosses freely.
+pub fn set_edge_resistance(pixels: u32) {
+    let msg = Msg::SetEdgeResistance { pixels };
+
+    send_msg(msg);
+}
+
+/// Set the longest time between the two clicks of a [`MouseEdge::DoubleClick`].
+///
+/// The default is 400 milliseconds.
+pub fn set_double_click_interval(interval_ms: u64) {
+    let msg = Msg::SetDoubleClickInterval { interval_ms };
+
+    send_msg(msg);
+}
+
+/// Where the pointer goes when your config focuses a window.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum CursorWarp {
+    /// Leave the pointer alone. This is the default.
+    #[default]
+    None,
+    /// Move the pointer to the center of the focused window.
+    CenterOnFocus,
+}
+
+/// Set where the pointer goes when your config changes keyboard focus, like by focusing a window
+/// or switching tags.
+///
+/// This keeps the pointer on the same output as keyboard focus, so scrolling goes to the focused
+/// window. Focus changes from clicks and scrollbinds never move the pointer, and neither do ones
+/// where the pointer is already over the window. The pointer also stays put while it's locked
+/// to an output.
+///
+/// # Example
+/// ```no_run
+/// use pinnacle_api::input::CursorWarp;
+///
+/// pinnacle_api::input::set_cursor_warp(CursorWarp::CenterOnFocus);
+/// ```
+pub fn set_cursor_warp(warp: CursorWarp) {
+    let msg = Msg::SetCursorWarp { warp };
+
+    send_msg(msg);
+}
+
+/// What long presses on touchscreens do, for [`set_touch_gestures`].
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TouchGestures {
+    /// Whether long presses do anything. This is `true` by default.
+    pub enabled: bool,
+    /// How long fingers need to be held, in milliseconds. This is 500 by default.
+    pub long_press_ms: u64,
+    /// How far a finger can move while being held, in logical pixels. This is 8 by default.
+    pub tolerance: f64,
+}
+
+impl Default for TouchGestures {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            long_press_ms: 500,
+            tolerance: 8.0,
+        }
+    }
+}
+
+/// Set what long presses on touchscreens do.
+///
+/// Touchscreens have no right click and no way to drag windows, so holding fingers still
+/// does that instead:
+/// - Holding one finger right clicks what's under it.
+/// - Holding one finger on the edge of a window, or holding two fingers on it, starts moving
+///   the window. The finger drives the move, and lifting it drops the window.
+///
+/// Lifting or moving a finger before the long press cancels it, as does a third finger.
+///
+/// # Example
+/// ```no_run
+/// use pinnacle_api::input::TouchGestures;
+///
+/// pinnacle_api::input::set_touch_gestures(TouchGestures {
+///     long_press_ms: 700,
+///     ..Default::default()
+/// });
+/// ```
+pub fn set_touch_gestures(gestures: TouchGestures) {
+    let msg = Msg::SetTouchGestures { gestures };
+
+    send_msg(msg);
+}
+
+/// Multiply scroll amounts by `factor`.
+///
+/// If `device_name` is `Some`, this only applies to the device with that name
+/// and overrides the factor for every device. Otherwise, it applies to every device.
+///
+/// Discrete scroll steps, like the notches on a mouse wheel, stay whole numbers:
+/// with a factor of `1.5`, every other notch scrolls twice.
+///
+/// The default is `1.0`.
+pub fn set_scroll_factor(factor: f64, device_name: Option<&str>) {
+    let msg = Msg::SetScrollFactor {
+        factor,
+        device_name: device_name.map(|name| name.to_string()),
+    };
+
+    send_msg(msg);
+}
+
+/// Flip the direction of horizontal scrolling.
+///
+/// This is independent of libinput's natural scrolling setting.
+/// If `device_name` is `Some`, this only applies to the device with that name.
+/// Otherwise, it applies to every device.
+pub fn set_horizontal_invert(invert: bool, device_name: Option<&str>) {
+    let msg = Msg::SetHorizontalScrollInvert {
+        invert,
+        device_name: device_name.map(|name| name.to_string()),
+    };
+
+    send_msg(msg);
+}
+
+/// A connected input device.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputDevice {
+    /// The device's name.
+    pub name: String,
+    /// How much scroll amounts from this device are multiplied by.
+    pub scroll_factor: f64,
+    /// Whether horizontal scrolling from this device is flipped.
+    pub horizontal_invert: bool,
+}
+
+/// Get all connected input devices.
+///
+/// Devices are only reported when running on a tty, as other backends don't expose them.
+pub fn devices() -> Vec<InputDevice> {
+    let RequestResponse::InputDevices { devices } = request(Request::GetInputDevices) else {
+        unreachable!()
+    };
+
+    devices
+        .into_iter()
+        .map(|device| InputDevice {
+            name: device.name,
+            scroll_factor: device.scroll_factor,
+            horizontal_invert: device.horizontal_scroll_invert,
+        })
+        .collect()
+}
+
+/// Connect a function to be run whenever an input device is added.
+///
+/// `func` takes in three parameters:
+/// - `0`: The new device's name.
+/// - `1`: The types of the device. Some devices, like keyboards with a touchpad,
+///   are more than one.
+/// - `2`: A `&mut `[`CallbackVec`] for use in the closure.
+///
+/// This runs after the device gets your libinput settings, so you can change them here,
+/// for example to turn off the touchpad when a mouse is plugged in.
+/// Devices are only reported when running on a tty, as other backends don't expose them.
+///
+/// ```no_run
+/// use pinnacle_api::input::{self, libinput::DeviceType};
+/// # let mut callback_vec = pinnacle_api::CallbackVec::new();
+///
+/// input::connect_device_added(
+///     |name, device_types, _| {
+///         if device_types.contains(&DeviceType::Pointer) {
+///             println!("Mouse added: {name}");
+///         }
+///     },
+///     &mut callback_vec,
+/// );
+/// ```
+pub fn connect_device_added<'a, F>(mut func: F, callback_vec: &mut CallbackVec<'a>)
+where
+    F: FnMut(&str, &[DeviceType], &mut CallbackVec) + 'a,
+{
+    let closure = move |args: Option<Args>, callback_vec: &mut CallbackVec| {
+        if let Some(Args::DeviceAdded { name, device_types }) = args {
+            func(&name, &device_types, callback_vec);
+        }
+    };
+
+    let callback_id = callback_vec.insert(closure);
+
+    send_msg(Msg::ConnectDeviceAdded { callback_id });
+}
+
+/// A keybind set by your config, from [`get_keybinds`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keybind {
+    /// The modifiers that need to be held.
+    pub modifiers: Vec<Modifier>,
+    /// The key.
+    pub key: Keysym,
+    /// The xkbcommon name of the key, like `Return` or `a`.
+    pub key_name: String,
+    /// Whether the keybind triggers on key press, release, or both.
+    pub edge: KeyEdge,
+    /// The group given to [`keybind_described`].
+    pub group: Option<String>,
+    /// The description given to [`keybind_described`].
+    pub description: Option<String>,
+    /// The layer given to [`layer_keybind`], or `None` for the default layer.
+    pub layer: Option<String>,
+    /// Whether this was set with [`keybind_passthrough`].
+    pub passthrough: bool,
+    /// Whether this was set with [`keybind_force`].
+    pub force: bool,
+    /// Whether this was set with [`keybind_repeating`].
+    pub repeats: bool,
+}
+
+/// Get all keybinds your config has set, sorted by layer, group, and then key.
+///
+/// This is useful for showing a list of your keybinds. Set a group and description
+/// with [`keybind_described`] to make it more helpful.
+pub fn get_keybinds() -> Vec<Keybind> {
+    let RequestResponse::Keybinds { keybinds } = request(Request::GetKeybinds) else {
+        unreachable!()
+    };
+
+    keybinds
+        .into_iter()
+        .map(|keybind| Keybind {
+            modifiers: keybind.modifiers,
+            key: Keysym::from(keybind.key),
+            key_name: keybind.key_name,
+            edge: keybind.edge,
+            group: keybind.group,
+            description: keybind.description,
+            layer: keybind.layer,
+            passthrough: keybind.passthrough,
+            force: keybind.force,
+            repeats: keybind.repeats,
+        })
+        .collect()
+}
+
+/// Get the current media key mappings as pairs of keysyms and the commands they run.
+///
+/// This returns the mappings even if default media keys are disabled.
+pub fn media_key_commands() -> Vec<(Keysym, Vec<String>)> {
+    let RequestResponse::MediaKeyCommands {
+        enabled: _,
+        commands,
+    } = request(Request::GetMediaKeyCommands)
+    else {
+        unreachable!()
+    };
+
+    commands
+        .into_iter()
+        .map(|(raw, command)| (Keysym::from(raw), command))
+        .collect()
 }
 
 /// A mouse button.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MouseButton {
     /// The left mouse button.
-    Left = 0x110,
+    Left,
     /// The right mouse button.
     Right,
     /// The middle mouse button, pressed usually by clicking the scroll wheel.
     Middle,
-    ///
+    /// The side mouse button, usually the back thumb button.
     Side,
-    ///
+    /// The extra mouse button, usually the forward thumb button.
     Extra,
-    ///
+    /// The forward mouse button.
     Forward,
-    ///
+    /// The back mouse button.
     Back,
+    /// Any other button, given by its raw evdev code.
+    ///
+    /// Run `libinput debug-events` and press the button to find its code.
+    Other(u32),
+}
+
+impl From<MouseButton> for u32 {
+    fn from(button: MouseButton) -> Self {
+        match button {
+            MouseButton::Left => 0x110,
+            MouseButton::Right => 0x111,
+            MouseButton::Middle => 0x112,
+            MouseButton::Side => 0x113,
+            MouseButton::Extra => 0x114,
+            MouseButton::Forward => 0x115,
+            MouseButton::Back => 0x116,
+            MouseButton::Other(code) => code,
+        }
+    }
 }
 
 /// The edge on which you want things to trigger.
@@ -129,6 +1245,77 @@ pub enum MouseEdge {
     Press,
     /// Actions will be triggered on button release.
     Release,
+    /// Actions will be triggered on the second press of a double click.
+    ///
+    /// The first click still triggers [`MouseEdge::Press`] binds. The second click triggers
+    /// the `DoubleClick` bind instead of the `Press` bind on the same modifiers and button.
+    /// Release binds trigger on both releases.
+    ///
+    /// The second press has to come within the interval set with [`set_double_click_interval`],
+    /// without the pointer moving more than a few pixels.
+    DoubleClick,
+}
+
+/// The direction of a scroll.
+#[derive(Debug, Hash, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ScrollDirection {
+    /// Scrolling up.
+    Up,
+    /// Scrolling down.
+    Down,
+    /// Scrolling left.
+    Left,
+    /// Scrolling right.
+    Right,
+}
+
+/// The direction of a touchpad swipe.
+#[derive(Debug, Hash, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum SwipeDirection {
+    /// Swiping up.
+    Up,
+    /// Swiping down.
+    Down,
+    /// Swiping left.
+    Left,
+    /// Swiping right.
+    Right,
+}
+
+/// The direction of a touchpad pinch.
+#[derive(Debug, Hash, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum PinchDirection {
+    /// Fingers moving together.
+    In,
+    /// Fingers moving apart.
+    Out,
+}
+
+/// The key edge on which you want a keybind to trigger.
+#[derive(Debug, Hash, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum KeyEdge {
+    /// The keybind will be triggered on key press.
+    Press,
+    /// The keybind will be triggered on key release.
+    Release,
+    /// The keybind will be triggered on both key press and key release.
+    Both,
+}
+
+impl KeyIntOrString {
+    /// Convert this to a keysym the same way Pinnacle does.
+    fn to_keysym(&self) -> Keysym {
+        match self {
+            KeyIntOrString::Int(num) => Keysym::from(*num),
+            KeyIntOrString::String(s) => {
+                let mut chars = s.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(ch), None) => Keysym::from_char(ch),
+                    _ => xkb::keysym_from_name(s, xkb::KEYSYM_NO_FLAGS),
+                }
+            }
+        }
+    }
 }
 
 impl From<char> for KeyIntOrString {
@@ -163,3 +1350,72 @@ pub enum Modifier {
     /// This is also known as the Windows key, meta, or Mod4 for those coming from Xorg.
     Super,
 }
+
+#[cfg(test)]
+mod tests {
+    use xkbcommon::xkb::keysyms;
+
+    use super::*;
+
+    fn parse(chord: &str) -> (Vec<Modifier>, u32) {
+        let (modifiers, keysym) = parse_chord(chord).unwrap();
+        (modifiers, keysym.raw())
+    }
+
+    #[test]
+    fn parses_valid_chords() {
+        assert_eq!(
+            parse("Super+Shift+Return"),
+            (vec![Modifier::Super, Modifier::Shift], keysyms::KEY_Return)
+        );
+        assert_eq!(parse("Ctrl+a"), (vec![Modifier::Ctrl], keysyms::KEY_a));
+        assert_eq!(parse("q"), (vec![], keysyms::KEY_q));
+        assert_eq!(
+            parse("XF86AudioRaiseVolume"),
+            (vec![], keysyms::KEY_XF86AudioRaiseVolume)
+        );
+    }
+
+    #[test]
+    fn parses_plus_as_key() {
+        assert_eq!(parse("+"), (vec![], keysyms::KEY_plus));
+        assert_eq!(parse("Ctrl++"), (vec![Modifier::Ctrl], keysyms::KEY_plus));
+    }
+
+    #[test]
+    fn modifier_aliases() {
+        assert_eq!(parse("Control+a").0, [Modifier::Ctrl]);
+        assert_eq!(parse("Mod1+a").0, [Modifier::Alt]);
+        assert_eq!(parse("Logo+a").0, [Modifier::Super]);
+        assert_eq!(parse("Mod4+a").0, [Modifier::Super]);
+    }
+
+    #[test]
+    fn unknown_names_are_errors() {
+        assert!(parse_chord("Hyper+a").is_err());
+        assert!(parse_chord("Super+NotAKey").is_err());
+        assert!(parse_chord("Super+").is_err());
+        assert!(parse_chord("").is_err());
+    }
+
+    #[test]
+    fn duplicate_modifiers_are_held_once() {
+        assert_eq!(parse("Super+Super+a").0, [Modifier::Super]);
+        assert_eq!(
+            parse("Ctrl+Shift+Control+a").0,
+            [Modifier::Ctrl, Modifier::Shift]
+        );
+    }
+
+    #[test]
+    fn case_handling() {
+        // Modifiers are case-insensitive
+        assert_eq!(parse("SUPER+shift+a").0, [Modifier::Super, Modifier::Shift]);
+        // Keysym names are matched exactly first, then case-insensitively
+        assert_eq!(parse("Super+return").1, keysyms::KEY_Return);
+        assert_eq!(parse("Super+RETURN").1, keysyms::KEY_Return);
+        // Single characters keep their case
+        assert_eq!(parse("Super+A").1, keysyms::KEY_A);
+        assert_eq!(parse("Super+a").1, keysyms::KEY_a);
+    }
+}
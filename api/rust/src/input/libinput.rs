@@ -1,13 +1,111 @@
 //! Libinput settings.
 
-use crate::{msg::Msg, send_msg};
+use crate::{msg::Msg, output::OutputHandle, send_msg};
 
 /// Set a libinput setting.
 ///
 /// This takes a [`LibinputSetting`] containing what you want set.
 pub fn set(setting: LibinputSetting) {
     let msg = Msg::SetLibinputSetting(setting);
-    send_msg(msg).unwrap();
+    send_msg(msg);
+}
+
+/// Set a libinput setting for only the devices `device` matches.
+///
+/// A string matches devices whose name contains it. Settings for devices that aren't
+/// connected are kept and applied when they are plugged in.
+///
+/// When settings of the same kind apply to a device, the one with the most specific
+/// matcher wins: an exact [`Name`](DeviceMatcher::Name), then
+/// [`NameContains`](DeviceMatcher::NameContains), then [`Type`](DeviceMatcher::Type),
+/// then settings from [`set`].
+///
+/// # Example
+/// ```no_run
+/// use pinnacle_api::input::libinput::{self, DeviceMatcher, DeviceType, LibinputSetting};
+///
+/// // Natural scrolling on the touchpad only
+/// libinput::set_for_device("SynPS/2", LibinputSetting::NaturalScrollEnabled(true));
+///
+/// // Flat acceleration for every mouse
+/// libinput::set_for_device(
+///     DeviceMatcher::Type(DeviceType::Pointer),
+///     LibinputSetting::AccelProfile(libinput::AccelProfile::Flat),
+/// );
+/// ```
+pub fn set_for_device(device: impl Into<DeviceMatcher>, setting: LibinputSetting) {
+    let msg = Msg::SetDeviceLibinputSetting {
+        device: device.into(),
+        setting,
+    };
+    send_msg(msg);
+}
+
+/// Spread absolute pointer events from the devices `device` matches, like tablets and
+/// touchscreens, over `output` only. Pass `None` to spread them over every output again.
+///
+/// By default, absolute devices cover every output together, so the left edge of a tablet
+/// is the left edge of your leftmost output. If `output` gets disconnected, the devices cover
+/// every output until it's back.
+///
+/// Like with [`set_for_device`], the most specific matcher wins.
+///
+/// # Example
+/// ```no_run
+/// use pinnacle_api::{
+///     input::libinput::{self, DeviceMatcher, DeviceType},
+///     output,
+/// };
+///
+/// // Keep every tablet on the focused output
+/// libinput::map_to_output(DeviceMatcher::Type(DeviceType::Tablet), output::get_focused().as_ref());
+/// ```
+pub fn map_to_output(device: impl Into<DeviceMatcher>, output: Option<&OutputHandle>) {
+    let msg = Msg::MapDeviceToOutput {
+        device: device.into(),
+        output_name: output.map(|op| op.0.clone()),
+    };
+    send_msg(msg);
+}
+
+/// Which devices a setting from [`set_for_device`] applies to.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub enum DeviceMatcher {
+    /// The device with exactly this name.
+    Name(String),
+    /// Devices whose name contains this.
+    NameContains(String),
+    /// Devices of this type.
+    Type(DeviceType),
+}
+
+impl From<&str> for DeviceMatcher {
+    fn from(value: &str) -> Self {
+        Self::NameContains(value.to_string())
+    }
+}
+
+impl From<DeviceType> for DeviceMatcher {
+    fn from(value: DeviceType) -> Self {
+        Self::Type(value)
+    }
+}
+
+/// The kind of an input device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum DeviceType {
+    /// Keyboards.
+    Keyboard,
+    /// Mice, trackballs, and other pointer devices that aren't touchpads.
+    Pointer,
+    /// Touchpads.
+    Touchpad,
+    /// Touchscreens.
+    Touch,
+    /// Drawing tablets.
+    Tablet,
+    /// Switches, like laptop lids.
+    Switch,
 }
 
 /// The acceleration profile.
@@ -4,10 +4,14 @@
 
 #![warn(missing_docs)]
 
+#[cfg(feature = "async")]
+pub mod r#async;
 pub mod input;
 mod msg;
 pub mod output;
 pub mod process;
+pub mod session;
+pub mod stats;
 pub mod tag;
 pub mod window;
 
@@ -21,9 +25,13 @@ pub use xkbcommon;
 /// prevent your config file from being cluttered with imports.
 pub mod prelude {
     pub use crate::input::libinput::*;
+    pub use crate::input::KeyEdge;
     pub use crate::input::Modifier;
     pub use crate::input::MouseButton;
     pub use crate::input::MouseEdge;
+    pub use crate::input::PinchDirection;
+    pub use crate::input::ScrollDirection;
+    pub use crate::input::SwipeDirection;
     pub use crate::output::AlignmentHorizontal;
     pub use crate::output::AlignmentVertical;
     pub use crate::tag::Layout;
@@ -31,69 +39,251 @@ pub mod prelude {
     pub use crate::window::rules::WindowRuleCondition;
     pub use crate::window::FloatingOrTiled;
     pub use crate::window::FullscreenOrMaximized;
+    pub use crate::window::MoveOverrideDrop;
+    pub use crate::BackgroundThrottle;
+    pub use crate::FollowPolicy;
+    pub use crate::InsertPosition;
+    pub use crate::ReloadEvent;
 }
 
 use std::{
-    collections::{hash_map::Entry, HashMap},
-    convert::Infallible,
+    collections::{HashMap, HashSet, VecDeque},
     io::{Read, Write},
     os::unix::net::UnixStream,
     path::PathBuf,
-    sync::{atomic::AtomicU32, Mutex, OnceLock},
+    sync::{
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        Mutex, OnceLock,
+    },
+    time::{Duration, Instant},
 };
 
+use anyhow::Context;
+
 use msg::{Args, CallbackId, IncomingMsg, Msg, Request, RequestResponse};
 
 use crate::msg::RequestId;
 
 static STREAM: OnceLock<Mutex<UnixStream>> = OnceLock::new();
 lazy_static::lazy_static! {
-    static ref UNREAD_CALLBACK_MSGS: Mutex<HashMap<CallbackId, IncomingMsg>> = Mutex::new(HashMap::new());
+    static ref UNREAD_CALLBACK_MSGS: Mutex<VecDeque<IncomingMsg>> = Mutex::new(VecDeque::new());
     static ref UNREAD_REQUEST_MSGS: Mutex<HashMap<RequestId, IncomingMsg>> = Mutex::new(HashMap::new());
 }
 
 static REQUEST_ID_COUNTER: AtomicU32 = AtomicU32::new(0);
 
-fn send_msg(msg: Msg) -> anyhow::Result<()> {
-    let mut msg = rmp_serde::encode::to_vec_named(&msg)?;
-    let mut msg_len = (msg.len() as u32).to_ne_bytes();
+static LAST_DELIVERED_SERIAL: AtomicU64 = AtomicU64::new(0);
+
+#[allow(clippy::type_complexity)]
+static WARNING_HANDLER: Mutex<Option<Box<dyn Fn(&Warning) + Send + Sync>>> = Mutex::new(None);
+
+/// Errors from communicating with Pinnacle.
+#[derive(Debug)]
+pub enum Error {
+    /// Reading from or writing to the socket failed.
+    Io(std::io::Error),
+    /// A message from Pinnacle couldn't be decoded.
+    Decode(rmp_serde::decode::Error),
+    /// The connection to Pinnacle was closed, most likely because Pinnacle exited.
+    Disconnected,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "failed to communicate with Pinnacle: {err}"),
+            Error::Decode(err) => write!(f, "failed to decode message from Pinnacle: {err}"),
+            Error::Disconnected => write!(f, "lost connection to Pinnacle"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::Decode(err) => Some(err),
+            Error::Disconnected => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        use std::io::ErrorKind;
+        match err.kind() {
+            ErrorKind::UnexpectedEof
+            | ErrorKind::BrokenPipe
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::NotConnected => Error::Disconnected,
+            _ => Error::Io(err),
+        }
+    }
+}
+
+impl From<rmp_serde::decode::Error> for Error {
+    fn from(err: rmp_serde::decode::Error) -> Self {
+        match err {
+            rmp_serde::decode::Error::InvalidMarkerRead(err)
+            | rmp_serde::decode::Error::InvalidDataRead(err) => err.into(),
+            err => Error::Decode(err),
+        }
+    }
+}
+
+/// Something unexpected that happened while talking to Pinnacle that your config
+/// may want to know about, but that doesn't stop it from running.
+///
+/// These are printed to stderr unless you set your own handler with [`set_warning_handler`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum Warning {
+    /// Callbacks were sent by Pinnacle but never delivered, so `serial` came right after
+    /// `last` instead of `last + 1`.
+    ///
+    /// State you keep from callbacks may be out of date. Query Pinnacle for it again.
+    MissedCallbacks {
+        /// The serial of the last callback that was delivered.
+        last: u64,
+        /// The serial of the callback that was just delivered.
+        serial: u64,
+    },
+    /// A callback with `serial` was delivered after one with the later serial `last`.
+    CallbackOutOfOrder {
+        /// The serial of the latest callback that was delivered.
+        last: u64,
+        /// The serial of the callback that was just delivered.
+        serial: u64,
+    },
+    /// Pinnacle warned about something this client did, like sending too many messages.
+    ///
+    /// If this keeps happening, Pinnacle will disconnect the client.
+    Pinnacle {
+        /// What Pinnacle said.
+        message: String,
+    },
+    /// The data attached to a keybind couldn't be deserialized into the type its
+    /// [`KeybindCallback`][input::KeybindCallback] takes, so the callback wasn't called.
+    KeybindData {
+        /// The type the callback takes.
+        type_name: &'static str,
+        /// Why deserializing failed.
+        error: String,
+    },
+}
 
-    let mut stream = STREAM.get().unwrap().lock().unwrap();
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Warning::MissedCallbacks { last, serial } => write!(
+                f,
+                "missed {} callback(s) between serials {last} and {serial}",
+                serial - last - 1
+            ),
+            Warning::CallbackOutOfOrder { last, serial } => {
+                write!(f, "callback {serial} was delivered after callback {last}")
+            }
+            Warning::Pinnacle { message } => write!(f, "Pinnacle: {message}"),
+            Warning::KeybindData { type_name, error } => {
+                write!(f, "keybind data isn't a valid `{type_name}`: {error}")
+            }
+        }
+    }
+}
+
+/// Set what to do with [`Warning`]s. This replaces the default handler that prints them to stderr.
+pub fn set_warning_handler(handler: impl Fn(&Warning) + Send + Sync + 'static) {
+    *WARNING_HANDLER.lock().unwrap() = Some(Box::new(handler));
+}
 
-    stream.write_all(msg_len.as_mut_slice())?;
-    stream.write_all(msg.as_mut_slice())?;
+fn warn(warning: Warning) {
+    match WARNING_HANDLER.lock().unwrap().as_ref() {
+        Some(handler) => handler(&warning),
+        None => eprintln!("pinnacle_api: {warning}"),
+    }
+}
+
+/// Handle an error that happened while talking to Pinnacle.
+///
+/// Config functions can't do anything useful once the connection is gone,
+/// so if Pinnacle exited the config exits with it instead of panicking.
+fn handle_error<T>(err: Error) -> T {
+    match err {
+        Error::Disconnected => {
+            eprintln!("pinnacle_api: {err}, exiting");
+            std::process::exit(0);
+        }
+        err => panic!("pinnacle_api: {err}"),
+    }
+}
+
+/// Send a message to Pinnacle, exiting if the connection was lost.
+fn send_msg(msg: Msg) {
+    try_send_msg(msg).unwrap_or_else(handle_error)
+}
+
+fn try_send_msg(msg: Msg) -> Result<(), Error> {
+    let msg = encode_msg(&msg);
+
+    let mut stream = STREAM
+        .get()
+        .expect("not connected to Pinnacle; call `connect` first")
+        .lock()
+        .unwrap();
+
+    stream.write_all(msg.as_slice())?;
 
     Ok(())
 }
 
-fn read_msg(request_id: Option<RequestId>) -> IncomingMsg {
+/// Encode `msg` with its length prepended, ready to be written to the socket.
+fn encode_msg(msg: &Msg) -> Vec<u8> {
+    let msg = rmp_serde::encode::to_vec_named(msg).expect("failed to encode message");
+    let msg_len = (msg.len() as u32).to_ne_bytes();
+
+    [msg_len.as_slice(), msg.as_slice()].concat()
+}
+
+/// Read one length-prefixed message from `stream`.
+fn read_framed_msg(stream: &mut impl Read) -> Result<IncomingMsg, Error> {
+    let mut msg_len_bytes = [0u8; 4];
+    stream.read_exact(msg_len_bytes.as_mut_slice())?;
+
+    let msg_len = u32::from_ne_bytes(msg_len_bytes);
+    let mut msg_bytes = vec![0u8; msg_len as usize];
+    stream.read_exact(msg_bytes.as_mut_slice())?;
+
+    Ok(rmp_serde::from_slice(msg_bytes.as_slice())?)
+}
+
+fn read_msg(request_id: Option<RequestId>) -> Result<IncomingMsg, Error> {
     loop {
         if let Some(request_id) = request_id {
             if let Some(msg) = UNREAD_REQUEST_MSGS.lock().unwrap().remove(&request_id) {
-                return msg;
+                return Ok(msg);
             }
         }
 
-        let mut stream = STREAM.get().unwrap().lock().unwrap();
-        let mut msg_len_bytes = [0u8; 4];
-        stream.read_exact(msg_len_bytes.as_mut_slice()).unwrap();
-
-        let msg_len = u32::from_ne_bytes(msg_len_bytes);
-        let mut msg_bytes = vec![0u8; msg_len as usize];
-        stream.read_exact(msg_bytes.as_mut_slice()).unwrap();
+        let mut stream = STREAM
+            .get()
+            .expect("not connected to Pinnacle; call `connect` first")
+            .lock()
+            .unwrap();
+        let incoming_msg = read_framed_msg(&mut *stream)?;
+        // The warning handler may send messages of its own
+        drop(stream);
 
-        let incoming_msg: IncomingMsg = rmp_serde::from_slice(msg_bytes.as_slice()).unwrap();
+        if let IncomingMsg::Warning { message } = incoming_msg {
+            warn(Warning::Pinnacle { message });
+            continue;
+        }
 
         if let Some(request_id) = request_id {
             match &incoming_msg {
-                IncomingMsg::CallCallback {
-                    callback_id,
-                    args: _,
-                } => {
-                    UNREAD_CALLBACK_MSGS
-                        .lock()
-                        .unwrap()
-                        .insert(*callback_id, incoming_msg);
+                IncomingMsg::CallCallback { .. } => {
+                    // Keep every callback in the order it came in so none are lost while waiting
+                    UNREAD_CALLBACK_MSGS.lock().unwrap().push_back(incoming_msg);
                 }
                 IncomingMsg::RequestResponse {
                     request_id: req_id,
@@ -105,102 +295,868 @@ fn read_msg(request_id: Option<RequestId>) -> IncomingMsg {
                             .unwrap()
                             .insert(*req_id, incoming_msg);
                     } else {
-                        return incoming_msg;
+                        return Ok(incoming_msg);
                     }
                 }
+                IncomingMsg::Warning { .. } => unreachable!(),
             }
         } else {
-            return incoming_msg;
+            return Ok(incoming_msg);
         }
     }
 }
 
+/// Make a request and wait for its response, exiting if the connection was lost.
 fn request(request: Request) -> RequestResponse {
-    use std::sync::atomic::Ordering;
-    let request_id = REQUEST_ID_COUNTER.fetch_add(1, Ordering::Relaxed);
+    try_request(request).unwrap_or_else(handle_error)
+}
+
+fn try_request(request: Request) -> Result<RequestResponse, Error> {
+    let name = stats::variant_name(&request);
+    let start = Instant::now();
+
+    let request_id = try_send_request(request)?;
+    let response = try_read_response(request_id)?;
+
+    stats::record_round_trip(name, start.elapsed());
+    Ok(response)
+}
+
+/// Send a request without waiting for its response, exiting if the connection was lost.
+///
+/// The response must be read later with [`try_read_response`].
+fn send_request(request: Request) -> RequestId {
+    try_send_request(request).unwrap_or_else(handle_error)
+}
+
+fn try_send_request(request: Request) -> Result<RequestId, Error> {
+    let request_id = RequestId(REQUEST_ID_COUNTER.fetch_add(1, Ordering::Relaxed));
 
     let msg = Msg::Request {
-        request_id: RequestId(request_id),
+        request_id,
         request,
     };
-    send_msg(msg).unwrap(); // TODO: propogate
+    try_send_msg(msg)?;
+
+    Ok(request_id)
+}
+
+/// Block until the response to the request with `request_id` arrives.
+fn try_read_response(request_id: RequestId) -> Result<RequestResponse, Error> {
+    // Messages are read on another thread when connected with `r#async::connect`
+    #[cfg(feature = "async")]
+    if let Some(router) = r#async::router() {
+        return router.wait_blocking(request_id);
+    }
 
     let IncomingMsg::RequestResponse {
         request_id: _,
         response,
-    } = read_msg(Some(RequestId(request_id)))
+    } = read_msg(Some(request_id))?
     else {
         unreachable!()
     };
 
-    response
+    Ok(response)
 }
 
+/// How many times [`connect`] tries to connect before giving up.
+const CONNECT_ATTEMPTS: u32 = 5;
+
+/// How long [`connect`] waits after its first failed attempt. This doubles after every attempt.
+const CONNECT_BACKOFF: Duration = Duration::from_millis(50);
+
 /// Connect to Pinnacle. This needs to be called before you begin calling config functions.
 ///
 /// This will open up a connection to the Unix socket at `$PINNACLE_SOCKET`,
-/// which should be set when you start the compositor.
+/// which should be set when you start the compositor. If it isn't set,
+/// `/tmp/pinnacle_socket` is used.
+///
+/// Pinnacle may still be creating the socket when it starts your config, so connecting is
+/// retried a few times with a short backoff.
+///
+/// # Errors
+/// Returns an error if the socket couldn't be connected to or if you are already connected.
 pub fn connect() -> anyhow::Result<()> {
-    STREAM
-        .set(Mutex::new(
-            UnixStream::connect(PathBuf::from(
-                std::env::var("PINNACLE_SOCKET").unwrap_or("/tmp/pinnacle_socket".to_string()),
-            ))
-            .unwrap(),
-        ))
-        .unwrap();
+    if STREAM.get().is_some() {
+        anyhow::bail!("already connected to Pinnacle");
+    }
 
+    let socket_path = socket_path();
+
+    let mut backoff = CONNECT_BACKOFF;
+    let mut attempt = 1;
+    let stream = loop {
+        match UnixStream::connect(&socket_path) {
+            Ok(stream) => break stream,
+            Err(_) if attempt < CONNECT_ATTEMPTS => {
+                std::thread::sleep(backoff);
+                backoff *= 2;
+                attempt += 1;
+            }
+            Err(err) => {
+                return Err(err).with_context(|| {
+                    format!(
+                        "failed to connect to Pinnacle at {} after {CONNECT_ATTEMPTS} attempts",
+                        socket_path.display()
+                    )
+                });
+            }
+        }
+    };
+
+    set_stream(stream)
+}
+
+/// Connect to Pinnacle as a one-off client.
+///
+/// This is for scripts and tools that send a few messages and exit instead of running a config,
+/// like the `pinnacle_msg` example. Connecting like this doesn't replace your config's connection,
+/// so your config keeps running.
+///
+/// One-off clients can't use callbacks, so don't call [`listen`] or use functions that take a
+/// [`CallbackVec`] after connecting with this.
+///
+/// # Errors
+/// Returns an error if the socket couldn't be connected to or if you are already connected.
+pub fn connect_one_off() -> anyhow::Result<()> {
+    connect()?;
+    try_send_msg(Msg::OneOffClient)?;
     Ok(())
 }
 
+/// The path to Pinnacle's socket, from `$PINNACLE_SOCKET` or `/tmp/pinnacle_socket`.
+fn socket_path() -> PathBuf {
+    PathBuf::from(std::env::var("PINNACLE_SOCKET").unwrap_or("/tmp/pinnacle_socket".to_string()))
+}
+
+fn set_stream(stream: UnixStream) -> anyhow::Result<()> {
+    STREAM
+        .set(Mutex::new(stream))
+        .map_err(|_| anyhow::anyhow!("already connected to Pinnacle"))
+}
+
 /// Begin listening for messages coming from Pinnacle.
 ///
 /// This needs to be called at the very end of your `setup` function.
-pub fn listen(mut callback_vec: CallbackVec) -> Infallible {
+///
+/// If Pinnacle exits, this exits the config process.
+///
+/// If you connected with `r#async::connect` from the `async` feature, use `r#async::run` instead.
+pub fn listen(mut callback_vec: CallbackVec) -> ! {
+    // Everything the config set up was sent before this, so the reload is done
+    send_msg(Msg::ConfigReady);
+
     loop {
-        let mut unread_callback_msgs = UNREAD_CALLBACK_MSGS.lock().unwrap();
+        // Don't hold the lock while calling, as callbacks that make requests push to this
+        let unread_callback_msg = UNREAD_CALLBACK_MSGS.lock().unwrap().pop_front();
 
-        for cb_id in unread_callback_msgs.keys().copied().collect::<Vec<_>>() {
-            let Entry::Occupied(entry) = unread_callback_msgs.entry(cb_id) else {
-                unreachable!();
-            };
-            let IncomingMsg::CallCallback { callback_id, args } = entry.remove() else {
-                unreachable!();
-            };
+        let incoming_msg = match unread_callback_msg {
+            Some(msg) => msg,
+            None => read_msg(None).unwrap_or_else(handle_error),
+        };
 
-            // Take the callback out and replace it with a dummy callback
-            // to allow callback_vec to be used mutably below.
-            let mut callback = std::mem::replace(
-                &mut callback_vec.callbacks[callback_id.0 as usize],
-                Box::new(|_, _| {}),
-            );
+        let IncomingMsg::CallCallback {
+            callback_id,
+            args,
+            serial,
+        } = incoming_msg
+        else {
+            unreachable!();
+        };
 
-            callback(args, &mut callback_vec);
+        call_callback(&mut callback_vec, callback_id, args, serial);
+    }
+}
 
-            // Put it back.
-            callback_vec.callbacks[callback_id.0 as usize] = callback;
-        }
+fn call_callback(
+    callback_vec: &mut CallbackVec,
+    callback_id: CallbackId,
+    args: Option<Args>,
+    serial: u64,
+) {
+    callback_vec.context = deliver_serial(serial);
 
-        let incoming_msg = read_msg(None);
+    // Take the callback out to allow callback_vec to be used mutably below.
+    // If it's not there, it was removed and Pinnacle sent this before finding out.
+    let Some(mut callback) = callback_vec.callbacks[callback_id.0 as usize].take() else {
+        return;
+    };
 
-        let IncomingMsg::CallCallback { callback_id, args } = incoming_msg else {
-            unreachable!();
-        };
+    callback(args, callback_vec);
 
-        let mut callback = std::mem::replace(
-            &mut callback_vec.callbacks[callback_id.0 as usize],
-            Box::new(|_, _| {}),
-        );
+    // Put it back, unless the callback removed itself.
+    if !callback_vec.free_ids.contains(&callback_id) {
+        callback_vec.callbacks[callback_id.0 as usize].get_or_insert(callback);
+    }
+}
 
-        callback(args, &mut callback_vec);
+/// Record that the callback with `serial` is being delivered, warning if it isn't the one
+/// right after the last.
+fn deliver_serial(serial: u64) -> CallbackContext {
+    let last = LAST_DELIVERED_SERIAL.fetch_max(serial, Ordering::Relaxed);
 
-        callback_vec.callbacks[callback_id.0 as usize] = callback;
+    let (context, warning) = check_serial(last, serial);
+    if let Some(warning) = warning {
+        warn(warning);
     }
+
+    context
+}
+
+/// Check that `serial` comes right after `last`, the latest serial that was delivered,
+/// getting the warning to raise if it doesn't.
+fn check_serial(last: u64, serial: u64) -> (CallbackContext, Option<Warning>) {
+    if serial <= last {
+        return (
+            CallbackContext { serial, missed: 0 },
+            Some(Warning::CallbackOutOfOrder { last, serial }),
+        );
+    }
+
+    let missed = serial - last - 1;
+    let warning = (missed > 0).then_some(Warning::MissedCallbacks { last, serial });
+
+    (CallbackContext { serial, missed }, warning)
+}
+
+/// Get the serial of the latest callback Pinnacle delivered to your config.
+///
+/// Pinnacle gives every callback it sends a serial one higher than the last, starting at 1.
+/// This is 0 if no callbacks have been delivered yet.
+pub fn last_delivered_serial() -> u64 {
+    LAST_DELIVERED_SERIAL.load(Ordering::Relaxed)
 }
 
 /// Quit Pinnacle.
 pub fn quit() {
-    send_msg(Msg::Quit).unwrap();
+    send_msg(Msg::Quit);
+}
+
+/// Restart your config.
+///
+/// This kills the running config and starts it again, so if called from your config,
+/// nothing after this will run.
+pub fn reload_config() {
+    send_msg(Msg::ReloadConfig);
+}
+
+/// Something that happened to a config reload, from [`connect_reload`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ReloadEvent {
+    /// The old config was stopped and a new one is starting.
+    Started,
+    /// The new config called [`listen`] and has set everything up.
+    Finished,
+    /// The new config didn't call [`listen`] within 10 seconds, so Pinnacle stopped waiting.
+    TimedOut,
+}
+
+/// Connect to config reloads starting and finishing.
+///
+/// This is meant for one-off clients like bars, which keep running across reloads and can
+/// refresh what they show once the new config is done. Your config is killed when a reload
+/// starts, so it only ever sees [`ReloadEvent::Finished`] or [`ReloadEvent::TimedOut`]
+/// for the reload that started it.
+///
+/// Keybinds pressed with modifiers other than Shift while a reload is in progress are held,
+/// then run once the new config calls [`listen`] if it bound them.
+///
+/// # Example
+/// ```no_run
+/// use pinnacle_api::{CallbackVec, ReloadEvent};
+///
+/// pinnacle_api::connect_one_off().unwrap();
+///
+/// let mut callback_vec = CallbackVec::new();
+///
+/// pinnacle_api::connect_reload(
+///     |event, _| {
+///         if event == ReloadEvent::Finished {
+///             println!("config reloaded");
+///         }
+///     },
+///     &mut callback_vec,
+/// );
+///
+/// pinnacle_api::listen(callback_vec);
+/// ```
+pub fn connect_reload<'a, F>(mut func: F, callback_vec: &mut CallbackVec<'a>)
+where
+    F: FnMut(ReloadEvent, &mut CallbackVec) + 'a,
+{
+    let closure = move |args: Option<Args>, callback_vec: &mut CallbackVec| {
+        if let Some(Args::ReloadEvent { event }) = args {
+            func(event, callback_vec);
+        }
+    };
+
+    let callback_id = callback_vec.insert(closure);
+
+    send_msg(Msg::ConnectReload { callback_id });
+}
+
+/// Turn do not disturb mode on or off.
+///
+/// While on, newly opened windows will not take keyboard focus, and new layer surfaces
+/// in the top and overlay layers with a namespace set with [`set_do_not_disturb_namespaces`]
+/// will be held back until do not disturb is turned off.
+///
+///Pinnacle doesn't support xdg-activation, so apps can't ask to be focused either.
+pub fn set_do_not_disturb(enabled: bool) {
+    send_msg(Msg::SetDoNotDisturb { enabled });
+}
+
+/// Get whether or not do not disturb mode is on.
+pub fn do_not_disturb() -> bool {
+    let RequestResponse::DoNotDisturb { enabled } = request(Request::GetDoNotDisturb) else {
+        unreachable!()
+    };
+
+    enabled
+}
+
+/// Connect a function to be run whenever do not disturb mode is turned on or off.
+///
+/// `func` gets whether do not disturb is now on.
+///
+/// # Example
+/// ```no_run
+/// use pinnacle_api::CallbackVec;
+///
+/// let mut callback_vec = CallbackVec::new();
+///
+/// pinnacle_api::connect_do_not_disturb_changed(
+///     |enabled, _| {
+///         println!("do not disturb is {}", if enabled { "on" } else { "off" });
+///     },
+///     &mut callback_vec,
+/// );
+/// ```
+pub fn connect_do_not_disturb_changed<'a, F>(mut func: F, callback_vec: &mut CallbackVec<'a>)
+where
+    F: FnMut(bool, &mut CallbackVec) + 'a,
+{
+    let closure = move |args: Option<Args>, callback_vec: &mut CallbackVec| {
+        if let Some(Args::DoNotDisturbChanged { enabled }) = args {
+            func(enabled, callback_vec);
+        }
+    };
+
+    let callback_id = callback_vec.insert(closure);
+
+    send_msg(Msg::ConnectDoNotDisturbChanged { callback_id });
+}
+
+/// Information about the running compositor, from [`compositor_info`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompositorInfo {
+    /// Pinnacle's version.
+    pub version: String,
+    /// The backend Pinnacle is running on, either `"winit"` or `"udev"`.
+    pub backend: String,
+    /// Whether Pinnacle is rendering without the GPU, either because setting up
+    /// GPU rendering failed or because it was started with `--force-software-renderer`.
+    pub software_rendering: bool,
+    /// How many times Pinnacle's event loop has woken up since it started.
+    ///
+    /// This should stay about the same while nothing is happening, so comparing it
+    /// a few seconds apart shows whether something keeps waking Pinnacle up.
+    pub event_loop_wakeups: u64,
+}
+
+/// Get information about the running compositor.
+pub fn compositor_info() -> CompositorInfo {
+    let RequestResponse::CompositorInfo {
+        version,
+        backend,
+        software_rendering,
+        event_loop_wakeups,
+    } = request(Request::GetCompositorInfo)
+    else {
+        unreachable!()
+    };
+
+    CompositorInfo {
+        version,
+        backend,
+        software_rendering,
+        event_loop_wakeups,
+    }
+}
+
+/// Get how long requests to Pinnacle have taken, to find what's making your config slow.
+///
+/// This has how long Pinnacle took to handle each kind of request, and how long the requests
+/// this config made took to come back. Requests that wait for something, like
+/// [`window::wait_for`], aren't counted in round trips.
+pub fn api_stats() -> stats::ApiStats {
+    let RequestResponse::ApiStats {
+        bucket_bounds_us,
+        requests,
+    } = request(Request::GetApiStats)
+    else {
+        unreachable!()
+    };
+
+    stats::ApiStats {
+        handling: requests
+            .into_iter()
+            .map(|props| stats::RequestTimes::from_props(props, &bucket_bounds_us))
+            .collect(),
+        round_trips: stats::round_trips(),
+    }
+}
+
+/// Set how long Pinnacle can take to handle a request before it logs a warning about it.
+///
+/// This is 10 milliseconds by default.
+pub fn set_slow_request_threshold(threshold: Duration) {
+    send_msg(Msg::SetSlowRequestThreshold {
+        threshold_ms: threshold.as_millis() as u64,
+    });
+}
+
+/// Get the session saved when Pinnacle last shut down, so you can respawn your apps.
+///
+/// Returns `None` if no session was saved or it couldn't be read.
+pub fn previous_session() -> Option<session::Session> {
+    let RequestResponse::PreviousSession { session } = request(Request::GetPreviousSession) else {
+        unreachable!()
+    };
+
+    session
+}
+
+/// Get a plain text description of Pinnacle's outputs, tags, and windows, for debugging.
+///
+/// This is the same as the state dump in crash reports. Its format isn't stable, so don't parse it.
+pub fn dump_state() -> String {
+    let RequestResponse::StateDump { dump } = request(Request::DumpState) else {
+        unreachable!()
+    };
+
+    dump
+}
+
+/// Put windows from the previous session back where they were as they reappear.
+///
+/// For the next minute, each new window is matched by class to a window from the previous
+/// session, preferring one with the same title, and gets that window's tags and floating
+/// geometry. Window rules still apply over this.
+///
+/// Call this before respawning your apps.
+pub fn apply_previous_session() {
+    send_msg(Msg::ApplyPreviousSession);
+}
+
+/// Set the namespaces of layer surfaces that do not disturb mode will hold back,
+/// like `"notifications"` for mako.
+pub fn set_do_not_disturb_namespaces(namespaces: &[&str]) {
+    let msg = Msg::SetDoNotDisturbNamespaces {
+        namespaces: namespaces.iter().map(|s| s.to_string()).collect(),
+    };
+
+    send_msg(msg);
+}
+
+/// Run `action` once Pinnacle has finished starting up, or immediately if it already has.
+///
+/// Startup is complete once every output connected at startup has been set up and
+/// XWayland has started. Callbacks from [`output::connect_for_all`] for those outputs
+/// will have already run. Use this to defer things like autostarting programs until
+/// the desktop is fully set up.
+pub fn on_startup_complete<'a, F>(mut action: F, callback_vec: &mut CallbackVec<'a>)
+where
+    F: FnMut(&mut CallbackVec) + 'a,
+{
+    let args_callback = move |_: Option<Args>, callback_vec: &mut CallbackVec<'_>| {
+        action(callback_vec);
+    };
+
+    let callback_id = callback_vec.insert(args_callback);
+
+    let msg = Msg::OnStartupComplete { callback_id };
+
+    send_msg(msg);
+}
+
+/// Enable or disable Pinnacle's built-in media key handlers.
+///
+/// When enabled, keys like `XF86AudioRaiseVolume` and `XF86MonBrightnessUp` will run
+/// commands using `wpctl`, `brightnessctl`, and `playerctl` if they are installed.
+/// These keys are not sent to the focused window.
+///
+/// Any keybinds you set on the same keys will override the defaults.
+/// You can change the commands with [`input::set_media_key_command`].
+pub fn enable_default_media_keys(enabled: bool) {
+    send_msg(Msg::SetMediaKeysEnabled { enabled });
+}
+
+/// Annotations for the debug overlay to draw.
+///
+/// Combine flags with `|`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DebugOverlayFlags(u32);
+
+impl DebugOverlayFlags {
+    /// Draw nothing, turning the debug overlay off.
+    pub const NONE: Self = Self(0);
+    /// Outline the geometry of windows and layer surfaces.
+    pub const GEOMETRY: Self = Self(1);
+    /// Outline the input region of every surface.
+    pub const INPUT_REGION: Self = Self(1 << 1);
+    /// Outline the opaque region of every surface.
+    pub const OPAQUE_REGION: Self = Self(1 << 2);
+    /// Flash the rectangles that surfaces damage when they commit.
+    pub const DAMAGE: Self = Self(1 << 3);
+    /// Draw every annotation.
+    pub const ALL: Self = Self(0b1111);
+
+    /// Returns whether all flags in `other` are set in `self`.
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for DebugOverlayFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for DebugOverlayFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Set which annotations the debug overlay draws.
+///
+/// The overlay draws translucent outlines over every visible surface and highlights the surface
+/// with pointer focus, whose name is also logged when it changes.
+/// Pass [`DebugOverlayFlags::NONE`] to turn it off.
+///
+/// # Example
+/// ```no_run
+/// use pinnacle_api::DebugOverlayFlags;
+///
+/// pinnacle_api::set_debug_overlay(DebugOverlayFlags::GEOMETRY | DebugOverlayFlags::INPUT_REGION);
+/// ```
+pub fn set_debug_overlay(flags: DebugOverlayFlags) {
+    send_msg(Msg::SetDebugOverlay { flags });
+}
+
+/// Show or hide a label next to the pointer while you resize or move a window.
+///
+/// While resizing, the label shows the window's size, snapped to the window's
+/// resize increments if it has any. While moving a floating window, it shows where the window is.
+///
+/// This is on by default.
+pub fn set_grab_hints(enabled: bool) {
+    send_msg(Msg::SetGrabHints { enabled });
+}
+
+/// Set the font of Pinnacle's own UI, like the grab hint, at `size` logical pixels.
+///
+/// `family` is the name of an installed font family, like `"Noto Sans"`, or one of the generic
+/// families `"sans-serif"`, `"serif"`, and `"monospace"`. Families that aren't installed fall
+/// back to `"sans-serif"`.
+///
+/// There is also a small built-in bitmap font named `"builtin"`, which is used when no fonts are
+/// installed. Its pixels are drawn as squares, so its `size` is rounded to a multiple of 5.
+///
+/// The default is `"sans-serif"` at 13.
+pub fn set_ui_font(family: &str, size: i32) {
+    send_msg(Msg::SetUiFont {
+        family: family.to_string(),
+        size,
+    });
+}
+
+/// Get the width and height in logical pixels that `text` is drawn at in the UI font.
+///
+/// `size_px` is the size of the font, or `None` for the UI font's size.
+/// Use this to line up things you draw with Pinnacle's own text.
+pub fn measure_text(text: &str, size_px: Option<i32>) -> (i32, i32) {
+    let RequestResponse::TextSize { width, height } = request(Request::MeasureText {
+        text: text.to_string(),
+        size_px,
+    }) else {
+        unreachable!()
+    };
+
+    (width, height)
+}
+
+/// Ask for a line of text in a box titled `title` on the focused output.
+///
+/// Key presses go to the box instead of keybinds or windows until Enter or Escape is pressed,
+/// except for the kill and reload keybinds and VT switching. `func` is called with the text on
+/// Enter, or `None` on Escape. Only one prompt can be open at a time, and asking for another
+/// while one is open calls `func` with `None` right away.
+///
+/// # Example
+/// ```no_run
+/// use pinnacle_api::{input::Modifier, output, tag, CallbackVec};
+///
+/// let mut callback_vec = CallbackVec::new();
+///
+/// pinnacle_api::input::keybind(
+///     &[Modifier::Super],
+///     'n',
+///     |callback_vec| {
+///         pinnacle_api::prompt(
+///             "New tag:",
+///             |name, _| {
+///                 let (Some(name), Some(output)) = (name, output::get_focused()) else {
+///                     return;
+///                 };
+///                 if !name.is_empty() {
+///                     tag::add(&output, &[name.as_str()]);
+///                 }
+///             },
+///             callback_vec,
+///         );
+///     },
+///     &mut callback_vec,
+/// );
+/// ```
+pub fn prompt<'a, F>(title: &str, mut func: F, callback_vec: &mut CallbackVec<'a>)
+where
+    F: FnMut(Option<String>, &mut CallbackVec) + 'a,
+{
+    let closure = move |args: Option<Args>, callback_vec: &mut CallbackVec| {
+        if let Some(Args::PromptFinished { text }) = args {
+            func(text, callback_vec);
+        }
+    };
+
+    let callback_id = callback_vec.insert(closure);
+
+    send_msg(Msg::Prompt {
+        title: title.to_string(),
+        callback_id,
+    });
+}
+
+/// Flash a ring around windows when a keybind moves keyboard focus to them.
+///
+/// This helps you see where focus went when you have a lot of similar windows.
+/// Focus changes from clicking don't flash, since you already know where you clicked.
+/// See [`WindowHandle::flash`][crate::window::WindowHandle::flash] to flash a window yourself.
+///
+/// This is off by default.
+pub fn set_flash_on_focus(enabled: bool) {
+    send_msg(Msg::SetFlashOnFocus { enabled });
+}
+
+/// What to do with focus when you move the focused window to another tag.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum FollowPolicy {
+    /// Leave focus alone. This is the default.
+    #[default]
+    Stay,
+    /// Switch to the window's new tag and keep the window focused.
+    SwitchTag,
+    /// Stay on the current tag and focus the next window on it.
+    FocusNext,
+}
+
+/// Set what happens to focus when you move the focused window to another tag with
+/// [`WindowHandle::move_to_tag`](window::WindowHandle::move_to_tag).
+///
+/// # Example
+/// ```no_run
+/// use pinnacle_api::FollowPolicy;
+///
+/// // Go with windows to the tags you send them to
+/// pinnacle_api::set_focus_follows_moved_window(FollowPolicy::SwitchTag);
+/// ```
+pub fn set_focus_follows_moved_window(policy: FollowPolicy) {
+    send_msg(Msg::SetFocusFollowsMovedWindow { policy });
+}
+
+/// Move windows that haven't been used for `after` to the tag named `to_tag` on their output,
+/// or stop doing so if `enabled` is false.
+///
+/// A window is used when it's focused, unfocused, or clicked. Pinnacle checks for inactive
+/// windows every few seconds, so you don't need a timer in your config.
+/// Windows on outputs without a tag named `to_tag` are left alone.
+///
+/// # Example
+/// ```no_run
+/// use std::time::Duration;
+///
+/// // Move windows you haven't touched in an hour to the "stale" tag
+/// pinnacle_api::auto_tidy(Duration::from_secs(3600), "stale", true);
+/// ```
+pub fn auto_tidy(after: Duration, to_tag: &str, enabled: bool) {
+    send_msg(Msg::SetAutoTidy {
+        after_ms: after.as_millis() as u64,
+        tag_name: to_tag.to_string(),
+        enabled,
+    });
+}
+
+/// Remember where you move windows of each class, and open new windows of that class there.
+///
+/// Moving a window with [`WindowHandle::move_to_tag`](window::WindowHandle::move_to_tag) or
+/// dragging it to another output remembers its class's output and tag. Window rules
+/// take priority over remembered placements, and moves made by window rules aren't remembered.
+/// Placements are saved across restarts.
+///
+/// This is off by default.
+///
+/// # Example
+/// ```no_run
+/// // Slack will open wherever you last put it
+/// pinnacle_api::remember_window_output(true);
+/// ```
+pub fn remember_window_output(enabled: bool) {
+    send_msg(Msg::SetRememberWindowOutput { enabled });
+}
+
+/// Forget where windows of `class` were placed.
+///
+/// New windows of that class will open like normal until you move one again.
+pub fn forget_window_placement(class: &str) {
+    send_msg(Msg::ForgetWindowPlacement {
+        class: class.to_string(),
+    });
+}
+
+/// Where new windows go in the layout.
+///
+/// Tiled layouts place windows in order, with the first window on a tag being the master.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum InsertPosition {
+    /// After every other window. This is the default.
+    #[default]
+    Append,
+    /// Before every other window, making the new window the master.
+    Prepend,
+    /// Right after the focused window.
+    AfterFocused,
+    /// Right before the focused window, taking its place.
+    BeforeFocused,
+}
+
+/// Set where new windows go in the layout.
+///
+/// [`InsertPosition::AfterFocused`] and [`InsertPosition::BeforeFocused`] append the window
+/// when no window on its tags has focus. Use
+/// [`WindowRule::insert_position`](window::rules::WindowRule::insert_position)
+/// to place specific windows somewhere else.
+///
+/// # Example
+/// ```no_run
+/// use pinnacle_api::InsertPosition;
+///
+/// // New windows become the master, like in dwm
+/// pinnacle_api::set_insert_position(InsertPosition::Prepend);
+/// ```
+pub fn set_insert_position(position: InsertPosition) {
+    send_msg(Msg::SetInsertPosition { position });
+}
+
+/// How frame callbacks are throttled for windows that can't be seen.
+///
+/// Windows are hidden when they're on tags that aren't active, and occluded when they're on an
+/// active tag but completely covered by a single window above them. Translucent windows count as
+/// covering.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum BackgroundThrottle {
+    /// Every window gets frame callbacks whenever its output repaints. This is the default.
+    #[default]
+    None,
+    /// Hidden and occluded windows get at most `fps` frame callbacks a second.
+    ThrottleOccluded {
+        /// The most frame callbacks a second hidden and occluded windows get.
+        fps: u32,
+    },
+    /// Hidden windows get no frame callbacks. Occluded windows get them as usual.
+    SuspendHidden,
+}
+
+/// Set how frame callbacks are throttled for windows that can't be seen.
+///
+/// Clients draw when they get a frame callback, so windows that animate in the background
+/// use power even though nothing they draw is shown. Throttling them saves battery.
+///
+/// Some clients stop drawing altogether or time out when they don't get frame callbacks for a
+/// while, so [`BackgroundThrottle::SuspendHidden`] can break them until they're shown again.
+/// Exempt those with [`WindowRule::never_throttle`](window::rules::WindowRule::never_throttle).
+///
+/// # Example
+/// ```no_run
+/// use pinnacle_api::BackgroundThrottle;
+///
+/// // Let covered windows draw 5 times a second
+/// pinnacle_api::set_background_throttle(BackgroundThrottle::ThrottleOccluded { fps: 5 });
+/// ```
+pub fn set_background_throttle(policy: BackgroundThrottle) {
+    send_msg(Msg::SetBackgroundThrottle { policy });
+}
+
+/// Which privileged protocols sandboxed apps, like Flatpaks, are allowed to use.
+///
+/// Apps are sandboxed when they connect through a socket their sandbox set up with the
+/// security context protocol. Protocols they aren't allowed to use are hidden from them.
+/// Apps that aren't sandboxed can use everything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SandboxPolicy {
+    /// Capturing the contents of outputs with screencopy. This is off by default.
+    pub screen_capture: bool,
+    /// Reading and setting the clipboard without focus with data control.
+    /// This is off by default.
+    pub data_control: bool,
+    /// Listing and controlling other apps' windows with foreign toplevel management.
+    pub foreign_toplevel: bool,
+    /// Typing into other apps with virtual keyboards.
+    pub virtual_keyboard: bool,
+    /// Acting as an input method for other apps.
+    pub input_method: bool,
+    /// Taking all input away from other apps with the input inhibitor.
+    pub input_inhibitor: bool,
+    /// Setting the gamma of outputs with gamma control, like night light tools do.
+    /// This is off by default.
+    pub gamma_control: bool,
+}
+
+impl Default for SandboxPolicy {
+    fn default() -> Self {
+        Self {
+            screen_capture: false,
+            data_control: false,
+            foreign_toplevel: true,
+            virtual_keyboard: true,
+            input_method: true,
+            input_inhibitor: true,
+            gamma_control: false,
+        }
+    }
+}
+
+/// Set which privileged protocols sandboxed apps are allowed to use.
+///
+/// This only affects what apps see from now on, so apps that already started keep
+/// what they were given. Set this before starting any sandboxed apps.
+///
+/// # Example
+/// ```no_run
+/// use pinnacle_api::SandboxPolicy;
+///
+/// // Don't let Flatpaks see what other windows are open either
+/// pinnacle_api::set_sandbox_policy(SandboxPolicy {
+///     foreign_toplevel: false,
+///     ..Default::default()
+/// });
+/// ```
+pub fn set_sandbox_policy(policy: SandboxPolicy) {
+    send_msg(Msg::SetSandboxPolicy { policy });
 }
 
 /// A wrapper around a vector that holds all of your callbacks.
@@ -222,8 +1178,16 @@ pub fn quit() {
 /// `CallbackVec`.
 #[derive(Default)]
 pub struct CallbackVec<'a> {
+    /// Callbacks by id. Removed callbacks leave a `None` until their id is reused.
     #[allow(clippy::type_complexity)]
-    pub(crate) callbacks: Vec<Box<dyn FnMut(Option<Args>, &mut CallbackVec) + 'a>>,
+    callbacks: Vec<Option<Box<dyn FnMut(Option<Args>, &mut CallbackVec) + 'a>>>,
+    /// Ids of removed callbacks that can be reused.
+    free_ids: Vec<CallbackId>,
+    /// The callback ids of keybinds, to remove the old callback when a keybind is replaced.
+    pub(crate) keybinds: HashMap<input::KeybindKey, CallbackId>,
+    /// Callbacks shared by keybinds with data, which stay around when those keybinds go away.
+    pub(crate) shared_callbacks: HashSet<CallbackId>,
+    pub(crate) context: CallbackContext,
 }
 
 impl<'a> CallbackVec<'a> {
@@ -231,4 +1195,135 @@ impl<'a> CallbackVec<'a> {
     pub fn new() -> Self {
         Default::default()
     }
+
+    /// Insert `callback`, returning the id Pinnacle should call it with.
+    pub(crate) fn insert(
+        &mut self,
+        callback: impl FnMut(Option<Args>, &mut CallbackVec) + 'a,
+    ) -> CallbackId {
+        match self.free_ids.pop() {
+            Some(callback_id) => {
+                self.callbacks[callback_id.0 as usize] = Some(Box::new(callback));
+                callback_id
+            }
+            None => {
+                self.callbacks.push(Some(Box::new(callback)));
+                CallbackId(self.callbacks.len() as u32 - 1)
+            }
+        }
+    }
+
+    /// Remove the callback with `callback_id` so its id can be reused.
+    pub(crate) fn remove(&mut self, callback_id: CallbackId) {
+        if let Some(callback) = self.callbacks.get_mut(callback_id.0 as usize) {
+            *callback = None;
+            if !self.free_ids.contains(&callback_id) {
+                self.free_ids.push(callback_id);
+            }
+        }
+    }
+
+    /// Remove the callback of a keybind that was replaced or removed, unless it's shared.
+    pub(crate) fn remove_keybind_callback(&mut self, callback_id: CallbackId) {
+        if !self.shared_callbacks.contains(&callback_id) {
+            self.remove(callback_id);
+        }
+    }
+
+    /// Get the context of the callback being run.
+    ///
+    /// Use this in a callback to check if callbacks were missed before it.
+    pub fn context(&self) -> CallbackContext {
+        self.context
+    }
+}
+
+/// Information about the delivery of a callback, from [`CallbackVec::context`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CallbackContext {
+    /// The callback's serial. See [`last_delivered_serial`].
+    pub serial: u64,
+    /// How many callbacks were missed right before this one.
+    ///
+    /// If this isn't 0, state you keep from callbacks may be out of date.
+    pub missed: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deliver `serials` in order, getting the context and warning of each.
+    fn deliver(serials: &[u64]) -> Vec<(CallbackContext, Option<Warning>)> {
+        let mut last = 0;
+        serials
+            .iter()
+            .map(|&serial| {
+                let checked = check_serial(last, serial);
+                last = last.max(serial);
+                checked
+            })
+            .collect()
+    }
+
+    #[test]
+    fn adjacent_serials_have_no_gap() {
+        for (context, warning) in deliver(&[1, 2, 3, 4]) {
+            assert_eq!(context.missed, 0);
+            assert!(warning.is_none());
+        }
+    }
+
+    #[test]
+    fn dropped_callback_is_detected() {
+        let delivered = deliver(&[1, 2, 4, 5]);
+
+        let (context, warning) = &delivered[2];
+        assert_eq!(
+            *context,
+            CallbackContext {
+                serial: 4,
+                missed: 1
+            }
+        );
+        assert!(matches!(
+            warning,
+            Some(Warning::MissedCallbacks { last: 2, serial: 4 })
+        ));
+
+        // Delivery goes back to normal after the gap
+        assert_eq!(delivered[3].0.missed, 0);
+        assert!(delivered[3].1.is_none());
+    }
+
+    #[test]
+    fn first_serial_after_connecting() {
+        let (context, warning) = check_serial(0, 1);
+        assert_eq!(context.missed, 0);
+        assert!(warning.is_none());
+
+        // Callbacks sent before the first one that was delivered were missed
+        let (context, warning) = check_serial(0, 3);
+        assert_eq!(context.missed, 2);
+        assert!(matches!(
+            warning,
+            Some(Warning::MissedCallbacks { last: 0, serial: 3 })
+        ));
+    }
+
+    #[test]
+    fn repeated_and_late_serials_are_out_of_order() {
+        let delivered = deliver(&[1, 2, 2, 5, 3]);
+
+        assert!(matches!(
+            delivered[2].1,
+            Some(Warning::CallbackOutOfOrder { last: 2, serial: 2 })
+        ));
+        assert!(matches!(
+            delivered[4].1,
+            Some(Warning::CallbackOutOfOrder { last: 5, serial: 3 })
+        ));
+        // Late callbacks aren't counted as missing anything
+        assert_eq!(delivered[4].0.missed, 0);
+    }
 }
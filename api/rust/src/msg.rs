@@ -1,10 +1,21 @@
 use std::num::NonZeroU32;
 
 use crate::{
-    input::{libinput::LibinputSetting, Modifier, MouseEdge},
+    input::{
+        libinput::{DeviceMatcher, DeviceType, LibinputSetting},
+        CursorWarp, KeyEdge, Modifier, MouseEdge, PinchDirection, ScrollDirection, SwipeDirection,
+        TouchGestures,
+    },
     output::OutputName,
+    session::Session,
     tag::{Layout, TagId},
-    window::{FloatingOrTiled, FullscreenOrMaximized, WindowId},
+    window::{
+        rules::{RequestedState, StateRequestDecision},
+        CycleDirection, Direction, FloatingOrTiled, FullscreenOrMaximized, MoveOverrideDrop,
+        WindowId,
+    },
+    BackgroundThrottle, DebugOverlayFlags, FollowPolicy, InsertPosition, ReloadEvent,
+    SandboxPolicy,
 };
 
 #[derive(Debug, Hash, PartialEq, Eq, serde::Serialize, serde::Deserialize, Clone, Copy)]
@@ -27,6 +38,12 @@ pub(crate) struct WindowRuleCondition {
     /// This condition is met when the tag matches.
     #[serde(default)]
     pub tag: Option<Vec<TagId>>,
+    /// This condition is met when the window is in the group with this id.
+    #[serde(default)]
+    pub group: Option<Vec<u32>>,
+    /// This condition is met when the window's sandbox gave it this app id.
+    #[serde(default)]
+    pub sandbox_app_id: Option<Vec<String>>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, serde::Serialize)]
@@ -50,6 +67,21 @@ pub(crate) struct WindowRule {
     /// when set to floating.
     #[serde(default)]
     pub location: Option<(i32, i32)>,
+    /// Make tiled layouts round the window's size down to its resize increments.
+    #[serde(default)]
+    pub snap_to_increments: Option<bool>,
+    /// Deny any requests from the client to fullscreen the window.
+    #[serde(default)]
+    pub deny_fullscreen: Option<bool>,
+    /// Make the window follow the active tags on its output.
+    #[serde(default)]
+    pub follow_active_tag: Option<bool>,
+    /// Set where the window goes in the layout.
+    #[serde(default)]
+    pub insert_position: Option<InsertPosition>,
+    /// Exempt the window from the background throttle.
+    #[serde(default)]
+    pub never_throttle: Option<bool>,
 }
 
 #[derive(Debug, Hash, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -61,7 +93,28 @@ pub(crate) enum Msg {
     SetKeybind {
         key: KeyIntOrString,
         modifiers: Vec<Modifier>,
+        edge: KeyEdge,
         callback_id: CallbackId,
+        group: Option<String>,
+        description: Option<String>,
+        layer: Option<String>,
+        passthrough: bool,
+        force: bool,
+        repeats: bool,
+        data: Option<Vec<u8>>,
+    },
+    RemoveKeybind {
+        key: KeyIntOrString,
+        modifiers: Vec<Modifier>,
+        edge: KeyEdge,
+        layer: Option<String>,
+    },
+    EnterBindLayer {
+        layer: Option<String>,
+    },
+    SetBindLayerForwardsUnbound {
+        layer: String,
+        forward: bool,
     },
     SetMousebind {
         modifiers: Vec<Modifier>,
@@ -69,6 +122,51 @@ pub(crate) enum Msg {
         edge: MouseEdge,
         callback_id: CallbackId,
     },
+    SetScrollbind {
+        modifiers: Vec<Modifier>,
+        direction: ScrollDirection,
+        callback_id: CallbackId,
+    },
+    SetGesturebind {
+        fingers: u32,
+        direction: SwipeDirection,
+        callback_id: CallbackId,
+    },
+    SetPinchbind {
+        fingers: u32,
+        direction: PinchDirection,
+        threshold: f64,
+        callback_id: CallbackId,
+    },
+    SetMediaKeysEnabled {
+        enabled: bool,
+    },
+    SetMediaKeyCommand {
+        key: KeyIntOrString,
+        command: Vec<String>,
+    },
+    SetMoveOverride {
+        modifiers: Option<Vec<Modifier>>,
+        on_drop: MoveOverrideDrop,
+    },
+    SetSnapThreshold {
+        threshold: u32,
+    },
+    LockPointerToOutput {
+        output_name: Option<OutputName>,
+    },
+    SetEdgeResistance {
+        pixels: u32,
+    },
+    SetDoubleClickInterval {
+        interval_ms: u64,
+    },
+    SetCursorWarp {
+        warp: CursorWarp,
+    },
+    SetTouchGestures {
+        gestures: TouchGestures,
+    },
 
     // Window management
     CloseWindow {
@@ -81,6 +179,27 @@ pub(crate) enum Msg {
         #[serde(default)]
         height: Option<i32>,
     },
+    MoveWindowBy {
+        window_id: WindowId,
+        dx: i32,
+        dy: i32,
+    },
+    ResizeWindowBy {
+        window_id: WindowId,
+        dx: i32,
+        dy: i32,
+    },
+    SetWindowGeometry {
+        window_id: WindowId,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+    },
+    SetFollowActiveTag {
+        window_id: WindowId,
+        follow: bool,
+    },
     MoveWindowToTag {
         window_id: WindowId,
         tag_id: TagId,
@@ -98,10 +217,52 @@ pub(crate) enum Msg {
     ToggleMaximized {
         window_id: WindowId,
     },
+    SetFloating {
+        window_id: WindowId,
+        floating: bool,
+    },
+    SetFullscreen {
+        window_id: WindowId,
+        fullscreen: bool,
+    },
+    SetMaximized {
+        window_id: WindowId,
+        maximized: bool,
+    },
+    FlashWindow {
+        window_id: WindowId,
+        duration_ms: u64,
+        color: [f32; 4],
+    },
+    RaiseWindow {
+        window_id: WindowId,
+    },
+    FocusWindow {
+        window_id: WindowId,
+    },
+    CycleWindowFocus {
+        output_name: Option<OutputName>,
+        forward: bool,
+    },
+    CycleMruFocus {
+        direction: CycleDirection,
+    },
+    CommitMruCycle,
+    SwapWindowInDirection {
+        direction: Direction,
+    },
     AddWindowRule {
         cond: WindowRuleCondition,
         rule: WindowRule,
     },
+    AddStateRequestHandler {
+        cond: WindowRuleCondition,
+        callback_id: CallbackId,
+    },
+    DecideStateRequest {
+        token: u32,
+        decision: StateRequestDecision,
+    },
     WindowMoveGrab {
         button: u32,
     },
@@ -116,11 +277,6 @@ pub(crate) enum Msg {
     SwitchToTag {
         tag_id: TagId,
     },
-    AddTags {
-        /// The name of the output you want these tags on.
-        output_name: OutputName,
-        tag_names: Vec<String>,
-    },
     // TODO:
     RemoveTags {
         /// The name of the output you want these tags removed from.
@@ -130,11 +286,75 @@ pub(crate) enum Msg {
         tag_id: TagId,
         layout: Layout,
     },
+    SetMasterFactor {
+        tag_id: TagId,
+        master_factor: f64,
+    },
+    SetTagDefaultWindowMode {
+        tag_id: TagId,
+        floating_or_tiled: Option<FloatingOrTiled>,
+    },
+    SetTagIndex {
+        tag_id: TagId,
+        index: usize,
+    },
 
     // Output management
     ConnectForAllOutputs {
         callback_id: CallbackId,
     },
+    SetHotplugDebounce {
+        debounce_ms: u64,
+    },
+    ConnectTagOrderChanged {
+        callback_id: CallbackId,
+    },
+    ConnectTagActiveChanged {
+        callback_id: CallbackId,
+    },
+    ConnectOutputConnected {
+        callback_id: CallbackId,
+    },
+    ConnectOutputDisconnected {
+        callback_id: CallbackId,
+    },
+    ConnectOutputModeChanged {
+        callback_id: CallbackId,
+    },
+    ConnectLayoutApplied {
+        callback_id: CallbackId,
+    },
+    ConnectKeyboardFocusChanged {
+        callback_id: CallbackId,
+    },
+    ConnectPointerFocusChanged {
+        callback_id: CallbackId,
+    },
+    /// Call a callback whenever a window's title changes.
+    ConnectWindowTitleChanged {
+        callback_id: CallbackId,
+    },
+    /// Call a callback whenever a window's class changes.
+    ConnectWindowClassChanged {
+        callback_id: CallbackId,
+    },
+    /// Call a callback whenever a window opens.
+    ConnectWindowOpened {
+        callback_id: CallbackId,
+    },
+    /// Call a callback whenever a window closes.
+    ConnectWindowClosed {
+        callback_id: CallbackId,
+    },
+    ConnectDeviceAdded {
+        callback_id: CallbackId,
+    },
+    ConnectClientDisconnected {
+        callback_id: CallbackId,
+    },
+    ConnectBindLayerChanged {
+        callback_id: CallbackId,
+    },
     SetOutputLocation {
         output_name: OutputName,
         #[serde(default)]
@@ -142,6 +362,14 @@ pub(crate) enum Msg {
         #[serde(default)]
         y: Option<i32>,
     },
+    SetOutputRenderScale {
+        output_name: OutputName,
+        render_scale: f32,
+    },
+    SetOutputDefaultWindowMode {
+        output_name: OutputName,
+        floating_or_tiled: Option<FloatingOrTiled>,
+    },
 
     // Process management
     /// Spawn a program with an optional callback.
@@ -156,8 +384,83 @@ pub(crate) enum Msg {
     },
 
     // Pinnacle management
+    OneOffClient,
     /// Quit the compositor.
     Quit,
+    ReloadConfig,
+    /// Sent by the config once it has registered everything, finishing a reload.
+    ConfigReady,
+    ConnectReload {
+        callback_id: CallbackId,
+    },
+    SetSlowRequestThreshold {
+        threshold_ms: u64,
+    },
+    ApplyPreviousSession,
+    SetDoNotDisturb {
+        enabled: bool,
+    },
+    SetDoNotDisturbNamespaces {
+        namespaces: Vec<String>,
+    },
+    ConnectDoNotDisturbChanged {
+        callback_id: CallbackId,
+    },
+    OnStartupComplete {
+        callback_id: CallbackId,
+    },
+    SetDebugOverlay {
+        flags: DebugOverlayFlags,
+    },
+    SetGrabHints {
+        enabled: bool,
+    },
+    SetUiFont {
+        family: String,
+        size: i32,
+    },
+    Prompt {
+        title: String,
+        callback_id: CallbackId,
+    },
+    SetFlashOnFocus {
+        enabled: bool,
+    },
+    SetFocusFollowsMovedWindow {
+        policy: FollowPolicy,
+    },
+    /// Move windows that haven't been used for `after_ms` to the tag named `tag_name`
+    /// on their output, or stop if `enabled` is false.
+    SetAutoTidy {
+        after_ms: u64,
+        tag_name: String,
+        enabled: bool,
+    },
+    /// Whether focus changes made through the API count as window activity.
+    SetApiFocusCountsAsActivity {
+        counts: bool,
+    },
+    /// Remember where windows of each class are moved and open new ones there.
+    SetRememberWindowOutput {
+        enabled: bool,
+    },
+    ForgetWindowPlacement {
+        class: String,
+    },
+    /// Whether clicking a window raises it as well as focusing it.
+    SetRaiseOnClick {
+        enabled: bool,
+    },
+    /// Where new windows go in the layout.
+    SetInsertPosition {
+        position: InsertPosition,
+    },
+    SetBackgroundThrottle {
+        policy: BackgroundThrottle,
+    },
+    SetSandboxPolicy {
+        policy: SandboxPolicy,
+    },
 
     // Input management
     SetXkbConfig {
@@ -172,8 +475,29 @@ pub(crate) enum Msg {
         #[serde(default)]
         options: Option<String>,
     },
+    /// Repeat held keys `rate` times a second after holding them for `delay` milliseconds.
+    SetKeyboardRepeat {
+        rate: i32,
+        delay: i32,
+    },
 
     SetLibinputSetting(LibinputSetting),
+    SetDeviceLibinputSetting {
+        device: DeviceMatcher,
+        setting: LibinputSetting,
+    },
+    MapDeviceToOutput {
+        device: DeviceMatcher,
+        output_name: Option<OutputName>,
+    },
+    SetScrollFactor {
+        factor: f64,
+        device_name: Option<String>,
+    },
+    SetHorizontalScrollInvert {
+        invert: bool,
+        device_name: Option<String>,
+    },
 
     Request {
         request_id: RequestId,
@@ -187,13 +511,51 @@ pub(crate) enum Msg {
 pub(crate) enum Request {
     // Windows
     GetWindows,
-    GetWindowProps { window_id: WindowId },
+    GetWindowProps {
+        window_id: WindowId,
+    },
+    WaitForWindow {
+        condition: WindowRuleCondition,
+        timeout_ms: u64,
+    },
+    /// Get windows that haven't been used for at least `inactive_ms`.
+    GetInactiveWindows {
+        inactive_ms: u64,
+    },
+    /// Get windows that have no live tags.
+    GetOrphanedWindows,
+    GetPointerFocusedWindow,
+    GetPreviousFocusedWindow,
     // Outputs
     GetOutputs,
-    GetOutputProps { output_name: String },
+    GetOutputProps {
+        output_name: String,
+    },
     // Tags
     GetTags,
-    GetTagProps { tag_id: TagId },
+    GetTagProps {
+        tag_id: TagId,
+    },
+    /// Add tags and respond with their ids, in the same order as `tag_names`.
+    AddTags {
+        output_name: OutputName,
+        tag_names: Vec<String>,
+    },
+    // Input
+    GetMediaKeyCommands,
+    GetInputDevices,
+    GetKeybinds,
+    // Pinnacle
+    GetDoNotDisturb,
+    GetCompositorInfo,
+    GetRecentDisconnects,
+    GetApiStats,
+    GetPreviousSession,
+    MeasureText {
+        text: String,
+        size_px: Option<i32>,
+    },
+    DumpState,
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
@@ -217,6 +579,103 @@ pub enum Args {
     },
     ConnectForAllOutputs {
         output_name: String,
+        #[serde(default)]
+        is_replay: bool,
+        #[serde(default)]
+        tag_names: Vec<String>,
+        #[serde(default)]
+        active_tags: Vec<String>,
+    },
+    TagOrderChanged {
+        output_name: String,
+    },
+    /// A tag was activated or deactivated.
+    TagActiveChanged {
+        tag_id: TagId,
+        name: String,
+        output_name: Option<String>,
+        active: bool,
+    },
+    /// An output connected, disconnected, or changed modes, depending on what was connected.
+    OutputGeometry {
+        output_name: String,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+    },
+    /// Windows on an output were laid out, as `(window_id, x, y, width, height)`.
+    LayoutApplied {
+        output_name: String,
+        tag_id: TagId,
+        generation: u64,
+        windows: Vec<(WindowId, i32, i32, i32, i32)>,
+    },
+    /// The window with keyboard or pointer focus changed, depending on what was connected.
+    WindowFocusChanged {
+        window_id: Option<WindowId>,
+    },
+    /// A window's title changed.
+    WindowTitleChanged {
+        window_id: WindowId,
+        title: Option<String>,
+    },
+    /// A window's class changed.
+    WindowClassChanged {
+        window_id: WindowId,
+        class: Option<String>,
+    },
+    /// A window opened.
+    WindowOpened {
+        window_id: WindowId,
+        class: Option<String>,
+        title: Option<String>,
+    },
+    /// A window closed.
+    WindowClosed {
+        window_id: WindowId,
+        class: Option<String>,
+        title: Option<String>,
+    },
+    /// An input device was added.
+    DeviceAdded {
+        name: String,
+        device_types: Vec<DeviceType>,
+    },
+    /// A Wayland client with windows disconnected.
+    ClientDisconnected {
+        disconnect: ClientDisconnectProps,
+    },
+    /// The active keybind layer changed.
+    BindLayerChanged {
+        layer: Option<String>,
+        previous: Option<String>,
+    },
+    /// A config reload started or finished.
+    ReloadEvent {
+        event: ReloadEvent,
+    },
+    /// Do not disturb mode was turned on or off.
+    DoNotDisturbChanged {
+        enabled: bool,
+    },
+    /// A window requested to be put in `state`. Respond with the `token`.
+    StateRequest {
+        window_id: WindowId,
+        token: u32,
+        state: RequestedState,
+    },
+    /// A keybind with data attached was triggered.
+    KeybindData {
+        data: Vec<u8>,
+    },
+    /// A mousebind was triggered with these modifiers counted as held.
+    MousebindTriggered {
+        modifiers: Vec<Modifier>,
+    },
+    /// A prompt was finished, or cancelled if `text` is `None`.
+    PromptFinished {
+        text: Option<String>,
     },
 }
 
@@ -226,11 +685,15 @@ pub(crate) enum IncomingMsg {
         callback_id: CallbackId,
         #[serde(default)]
         args: Option<Args>,
+        serial: u64,
     },
     RequestResponse {
         request_id: RequestId,
         response: RequestResponse,
     },
+    Warning {
+        message: String,
+    },
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
@@ -249,6 +712,16 @@ pub(crate) enum RequestResponse {
         focused: Option<bool>,
         floating: Option<bool>,
         fullscreen_or_maximized: Option<FullscreenOrMaximized>,
+        #[serde(default)]
+        resize_increments: Option<(i32, i32)>,
+        #[serde(default)]
+        seconds_since_activity: Option<u64>,
+        #[serde(default)]
+        keyboard_shortcuts_inhibited: Option<bool>,
+        #[serde(default)]
+        group: Option<u32>,
+        #[serde(default)]
+        sandbox_app_id: Option<String>,
     },
     Output {
         output_name: Option<String>,
@@ -272,6 +745,9 @@ pub(crate) enum RequestResponse {
         /// Whether the output is focused or not.
         focused: Option<bool>,
         tag_ids: Option<Vec<TagId>>,
+        render_scale: Option<f32>,
+        #[serde(default)]
+        default_window_mode: Option<FloatingOrTiled>,
     },
     Tags {
         tag_ids: Vec<TagId>,
@@ -280,5 +756,105 @@ pub(crate) enum RequestResponse {
         active: Option<bool>,
         name: Option<String>,
         output_name: Option<String>,
+        #[serde(default)]
+        master_factor: Option<f64>,
+        #[serde(default)]
+        default_window_mode: Option<FloatingOrTiled>,
+    },
+    DoNotDisturb {
+        enabled: bool,
+    },
+    CompositorInfo {
+        version: String,
+        backend: String,
+        software_rendering: bool,
+        #[serde(default)]
+        event_loop_wakeups: u64,
+    },
+    RecentDisconnects {
+        disconnects: Vec<ClientDisconnectProps>,
+    },
+    ApiStats {
+        bucket_bounds_us: Vec<u64>,
+        requests: Vec<RequestStatsProps>,
+    },
+    PreviousSession {
+        session: Option<Session>,
+    },
+    TextSize {
+        width: i32,
+        height: i32,
+    },
+    StateDump {
+        dump: String,
+    },
+    MediaKeyCommands {
+        enabled: bool,
+        commands: Vec<(u32, Vec<String>)>,
+    },
+    InputDevices {
+        devices: Vec<InputDeviceProps>,
+    },
+    Keybinds {
+        keybinds: Vec<KeybindProps>,
     },
 }
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub(crate) struct InputDeviceProps {
+    pub name: String,
+    pub scroll_factor: f64,
+    pub horizontal_scroll_invert: bool,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub(crate) struct KeybindProps {
+    pub modifiers: Vec<Modifier>,
+    pub key: u32,
+    pub key_name: String,
+    pub edge: KeyEdge,
+    pub group: Option<String>,
+    pub description: Option<String>,
+    pub layer: Option<String>,
+    pub passthrough: bool,
+    pub force: bool,
+    pub repeats: bool,
+}
+
+/// Why a Wayland client disconnected.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub(crate) enum ClientDisconnectReason {
+    /// The client closed its connection after closing its windows.
+    Clean,
+    /// The client's connection closed while it still had windows open.
+    /// This is what happens when it crashes or is killed.
+    Crashed,
+    /// Pinnacle disconnected the client because it broke the Wayland protocol.
+    ProtocolError {
+        interface: String,
+        object_id: u32,
+        code: u32,
+        message: String,
+    },
+}
+
+/// How long one kind of request has taken.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub(crate) struct RequestStatsProps {
+    pub name: String,
+    pub count: u64,
+    pub total_us: u64,
+    pub max_us: u64,
+    pub buckets: Vec<u64>,
+}
+
+/// A Wayland client that disconnected.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub(crate) struct ClientDisconnectProps {
+    pub pid: Option<i32>,
+    pub process_name: Option<String>,
+    pub reason: ClientDisconnectReason,
+    /// The client's windows that were open when it disconnected.
+    pub window_ids: Vec<WindowId>,
+    pub seconds_ago: u64,
+}
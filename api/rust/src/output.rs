@@ -1,9 +1,12 @@
 //! Output management.
 
+use std::time::Duration;
+
 use crate::{
-    msg::{Args, CallbackId, Msg, Request, RequestResponse},
+    msg::{Args, Msg, Request, RequestResponse},
     request, send_msg,
     tag::TagHandle,
+    window::FloatingOrTiled,
     CallbackVec,
 };
 
@@ -75,19 +78,222 @@ where
     F: FnMut(OutputHandle, &mut CallbackVec) + 'a,
 {
     let args_callback = move |args: Option<Args>, callback_vec: &mut CallbackVec<'_>| {
-        if let Some(Args::ConnectForAllOutputs { output_name }) = args {
+        if let Some(Args::ConnectForAllOutputs { output_name, .. }) = args {
             func(OutputHandle(OutputName(output_name)), callback_vec);
         }
     };
 
-    let len = callback_vec.callbacks.len();
-    callback_vec.callbacks.push(Box::new(args_callback));
+    let callback_id = callback_vec.insert(args_callback);
+
+    let msg = Msg::ConnectForAllOutputs { callback_id };
+
+    send_msg(msg);
+}
+
+/// What an output had before the config was reloaded, given to functions connected with
+/// [`connect_for_all_with_replay`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Replay {
+    /// Whether the output was already set up before the config was reloaded.
+    ///
+    /// This is `false` when the compositor starts and when an output is plugged in.
+    pub is_replay: bool,
+    /// The names of the tags the output had before the reload, in order.
+    pub tag_names: Vec<String>,
+    /// The names of the tags that were active before the reload.
+    pub active_tags: Vec<String>,
+}
+
+impl Replay {
+    /// Get the indices of the tags in `names` to make active: the ones that were active before
+    /// the reload, or the first one if none of them were or the output wasn't set up before.
+    pub fn tags_to_activate(&self, names: &[&str]) -> Vec<usize> {
+        let restored = names
+            .iter()
+            .enumerate()
+            .filter(|(_, name)| self.is_replay && self.active_tags.iter().any(|tag| tag == *name))
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+
+        if restored.is_empty() && !names.is_empty() {
+            vec![0]
+        } else {
+            restored
+        }
+    }
+}
 
-    let msg = Msg::ConnectForAllOutputs {
-        callback_id: CallbackId(len as u32),
+/// Like [`connect_for_all`], but `func` is also told what the output had before a config reload.
+///
+/// Tags are cleared when the config reloads and `func` is run again with every connected output,
+/// so setting a tag active there switches away from whatever was being viewed. Use the
+/// [`Replay`] to put back the tags that were active instead.
+///
+/// `func` takes in three parameters:
+/// - `0`: An [`OutputHandle`] you can act on.
+/// - `1`: The [`Replay`] with the output's tags from before the reload.
+/// - `2`: A `&mut `[`CallbackVec`] for use in the closure.
+///
+/// # Example
+/// ```no_run
+/// use pinnacle_api::{output, tag, CallbackVec};
+///
+/// let mut callback_vec = CallbackVec::new();
+///
+/// output::connect_for_all_with_replay(
+///     |output, replay, _| {
+///         let names = ["1", "2", "3", "4", "5"];
+///         let tags = tag::add_if_missing(&output, &names);
+///
+///         // Put back the tags that were active before a reload, or start on tag 1
+///         for i in replay.tags_to_activate(&names) {
+///             tags[i].toggle();
+///         }
+///     },
+///     &mut callback_vec,
+/// );
+/// ```
+pub fn connect_for_all_with_replay<'a, F>(mut func: F, callback_vec: &mut CallbackVec<'a>)
+where
+    F: FnMut(OutputHandle, Replay, &mut CallbackVec) + 'a,
+{
+    let args_callback = move |args: Option<Args>, callback_vec: &mut CallbackVec<'_>| {
+        if let Some(Args::ConnectForAllOutputs {
+            output_name,
+            is_replay,
+            tag_names,
+            active_tags,
+        }) = args
+        {
+            let replay = Replay {
+                is_replay,
+                tag_names,
+                active_tags,
+            };
+            func(OutputHandle(OutputName(output_name)), replay, callback_vec);
+        }
     };
 
-    send_msg(msg).unwrap();
+    let callback_id = callback_vec.insert(args_callback);
+
+    send_msg(Msg::ConnectForAllOutputs { callback_id });
+}
+
+/// Set how long to wait for more monitors after one is plugged in or unplugged.
+///
+/// Docking stations connect several monitors one after another. Pinnacle waits until no
+/// monitor has been plugged in or unplugged for `debounce`, then sets them all up at once so
+/// windows are only laid out once. A single monitor is set up after a short wait regardless.
+///
+/// This defaults to 500 milliseconds and only has an effect when running in a tty.
+pub fn set_hotplug_debounce(debounce: Duration) {
+    send_msg(Msg::SetHotplugDebounce {
+        debounce_ms: debounce.as_millis() as u64,
+    });
+}
+
+/// Connect a function to be run whenever the order of tags on an output changes.
+///
+/// `func` takes in two parameters:
+/// - `0`: An [`OutputHandle`] for the output whose tags were reordered.
+/// - `1`: A `&mut `[`CallbackVec`] for use in the closure.
+///
+/// Use [`tag_order`] to get the new order.
+pub fn connect_tag_order_changed<'a, F>(mut func: F, callback_vec: &mut CallbackVec<'a>)
+where
+    F: FnMut(OutputHandle, &mut CallbackVec) + 'a,
+{
+    let args_callback = move |args: Option<Args>, callback_vec: &mut CallbackVec<'_>| {
+        if let Some(Args::TagOrderChanged { output_name }) = args {
+            func(OutputHandle(OutputName(output_name)), callback_vec);
+        }
+    };
+
+    let callback_id = callback_vec.insert(args_callback);
+
+    let msg = Msg::ConnectTagOrderChanged { callback_id };
+
+    send_msg(msg);
+}
+
+/// Connect a function to be run whenever an output is plugged in.
+///
+/// Unlike [`connect_for_all`], this is run every time, even if an output was plugged in to the
+/// same connector before. It isn't run for outputs that were connected when the config started.
+///
+/// `func` takes in three parameters:
+/// - `0`: An [`OutputHandle`] for the output that was plugged in.
+/// - `1`: Its logical geometry as `(x, y, width, height)`.
+/// - `2`: A `&mut `[`CallbackVec`] for use in the closure.
+pub fn connect_connected<'a, F>(func: F, callback_vec: &mut CallbackVec<'a>)
+where
+    F: FnMut(OutputHandle, (i32, i32, i32, i32), &mut CallbackVec) + 'a,
+{
+    let callback_id = callback_vec.insert(output_geometry_callback(func));
+
+    send_msg(Msg::ConnectOutputConnected { callback_id });
+}
+
+/// Connect a function to be run whenever an output is unplugged.
+///
+/// `func` takes in three parameters:
+/// - `0`: An [`OutputHandle`] for the output that was unplugged.
+/// - `1`: Its logical geometry as `(x, y, width, height)` from before it was unplugged.
+/// - `2`: A `&mut `[`CallbackVec`] for use in the closure.
+pub fn connect_disconnected<'a, F>(func: F, callback_vec: &mut CallbackVec<'a>)
+where
+    F: FnMut(OutputHandle, (i32, i32, i32, i32), &mut CallbackVec) + 'a,
+{
+    let callback_id = callback_vec.insert(output_geometry_callback(func));
+
+    send_msg(Msg::ConnectOutputDisconnected { callback_id });
+}
+
+/// Connect a function to be run whenever an output's mode changes.
+///
+/// `func` takes in three parameters:
+/// - `0`: An [`OutputHandle`] for the output whose mode changed.
+/// - `1`: Its new logical geometry as `(x, y, width, height)`.
+/// - `2`: A `&mut `[`CallbackVec`] for use in the closure.
+pub fn connect_mode_changed<'a, F>(func: F, callback_vec: &mut CallbackVec<'a>)
+where
+    F: FnMut(OutputHandle, (i32, i32, i32, i32), &mut CallbackVec) + 'a,
+{
+    let callback_id = callback_vec.insert(output_geometry_callback(func));
+
+    send_msg(Msg::ConnectOutputModeChanged { callback_id });
+}
+
+fn output_geometry_callback<'a, F>(
+    mut func: F,
+) -> impl FnMut(Option<Args>, &mut CallbackVec<'_>) + 'a
+where
+    F: FnMut(OutputHandle, (i32, i32, i32, i32), &mut CallbackVec) + 'a,
+{
+    move |args: Option<Args>, callback_vec: &mut CallbackVec<'_>| {
+        if let Some(Args::OutputGeometry {
+            output_name,
+            x,
+            y,
+            width,
+            height,
+        }) = args
+        {
+            func(
+                OutputHandle(OutputName(output_name)),
+                (x, y, width, height),
+                callback_vec,
+            );
+        }
+    }
+}
+
+/// Get the tags on `output` in the order they are displayed.
+///
+/// Tags are displayed in the order they were added unless moved with
+/// [`TagHandle::set_index`]. Reordered tags keep their order across config reloads.
+pub fn tag_order(output: &OutputHandle) -> Vec<TagHandle> {
+    output.properties().tags
 }
 
 /// An output handle.
@@ -122,6 +328,14 @@ pub struct OutputProperties {
     pub focused: Option<bool>,
     /// The tags on this output.
     pub tags: Vec<TagHandle>,
+    /// The fraction of this output's resolution it is rendered at.
+    ///
+    /// See [`OutputHandle::set_render_scale`].
+    pub render_scale: Option<f32>,
+    /// Whether new windows on this output float or tile when their tags don't say.
+    ///
+    /// See [`OutputHandle::set_default_window_mode`].
+    pub default_window_mode: Option<FloatingOrTiled>,
 }
 
 impl OutputHandle {
@@ -142,6 +356,8 @@ impl OutputHandle {
             physical_size,
             focused,
             tag_ids,
+            render_scale,
+            default_window_mode,
         } = request(Request::GetOutputProps {
             output_name: self.0 .0.clone(),
         })
@@ -162,12 +378,16 @@ impl OutputHandle {
                 .into_iter()
                 .map(TagHandle)
                 .collect(),
+            render_scale,
+            default_window_mode,
         }
     }
 
-    /// Add tags with the given `names` to this output.
-    pub fn add_tags(&self, names: &[&str]) {
-        crate::tag::add(self, names);
+    /// Add tags with the given `names` to this output, returning handles to them.
+    ///
+    /// See [`tag::add`](crate::tag::add).
+    pub fn add_tags(&self, names: &[&str]) -> Vec<TagHandle> {
+        crate::tag::add(self, names)
     }
 
     /// Set this output's location in the global space.
@@ -178,7 +398,47 @@ impl OutputHandle {
             y,
         };
 
-        send_msg(msg).unwrap();
+        send_msg(msg);
+    }
+
+    /// Render this output at a fraction of its resolution and scale the result up.
+    ///
+    /// This can keep things smooth on weak GPUs at the cost of softer text.
+    /// Clients, input, and the cursor are unaffected; only the number of pixels
+    /// rendered changes.
+    ///
+    /// `render_scale` is clamped between 0.5 and 1.0, where 1.0 renders at full resolution.
+    /// This currently only has an effect on the udev backend.
+    pub fn set_render_scale(&self, render_scale: f32) {
+        let msg = Msg::SetOutputRenderScale {
+            output_name: self.0.clone(),
+            render_scale,
+        };
+
+        send_msg(msg);
+    }
+
+    /// Set whether new windows on this output float or tile.
+    ///
+    /// Window rules and tags with their own default, set with
+    /// [`TagHandle::set_default_window_mode`], take precedence over this.
+    /// New windows tile if nothing says otherwise.
+    ///
+    /// ```no_run
+    /// # use pinnacle_api::output;
+    /// # use pinnacle_api::window::FloatingOrTiled;
+    /// // Float new windows on the laptop panel
+    /// if let Some(op) = output::get_by_name("eDP-1") {
+    ///     op.set_default_window_mode(FloatingOrTiled::Floating);
+    /// }
+    /// ```
+    pub fn set_default_window_mode(&self, floating_or_tiled: FloatingOrTiled) {
+        let msg = Msg::SetOutputDefaultWindowMode {
+            output_name: self.0.clone(),
+            floating_or_tiled: Some(floating_or_tiled),
+        };
+
+        send_msg(msg);
     }
 
     /// Set this output's location to the right of `other`.
@@ -299,3 +559,66 @@ pub enum AlignmentVertical {
     /// Align the outputs such that the bottom edges are in line.
     Bottom,
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const NAMES: [&str; 5] = ["1", "2", "3", "4", "5"];
+
+    fn replay(active_tags: &[&str]) -> Replay {
+        Replay {
+            is_replay: true,
+            tag_names: NAMES.iter().map(|name| name.to_string()).collect(),
+            active_tags: active_tags.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn active_tag_survives_reload() {
+        assert_eq!(replay(&["4"]).tags_to_activate(&NAMES), vec![3]);
+        assert_eq!(replay(&["2", "5"]).tags_to_activate(&NAMES), vec![1, 4]);
+    }
+
+    #[test]
+    fn new_outputs_start_on_the_first_tag() {
+        assert_eq!(Replay::default().tags_to_activate(&NAMES), vec![0]);
+    }
+
+    #[test]
+    fn outputs_without_a_remaining_active_tag_start_on_the_first_tag() {
+        // Nothing was active, or the active tag is no longer in the config
+        assert_eq!(replay(&[]).tags_to_activate(&NAMES), vec![0]);
+        assert_eq!(replay(&["web"]).tags_to_activate(&NAMES), vec![0]);
+    }
+
+    #[test]
+    fn no_tags_means_nothing_to_activate() {
+        assert!(replay(&["4"]).tags_to_activate(&[]).is_empty());
+    }
+
+    #[test]
+    fn replay_args_are_optional() {
+        // Sent by a Pinnacle from before replays
+        let msg = rmp_serde::to_vec_named(&json!({
+            "ConnectForAllOutputs": { "output_name": "DP-1" }
+        }))
+        .unwrap();
+
+        let Args::ConnectForAllOutputs {
+            output_name,
+            is_replay,
+            tag_names,
+            active_tags,
+        } = rmp_serde::from_slice(&msg).unwrap()
+        else {
+            panic!("wrong args");
+        };
+        assert_eq!(output_name, "DP-1");
+        assert!(!is_replay);
+        assert!(tag_names.is_empty());
+        assert!(active_tags.is_empty());
+    }
+}
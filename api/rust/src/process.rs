@@ -1,8 +1,10 @@
 //! Process management.
 
 use crate::{
-    msg::{Args, CallbackId, Msg},
-    send_msg, CallbackVec,
+    msg::{Args, Msg},
+    send_msg, try_send_msg,
+    window::{rules::WindowRuleCondition, PendingWindowWait, WindowHandle},
+    CallbackVec,
 };
 
 /// Spawn a process.
@@ -16,7 +18,39 @@ pub fn spawn(command: Vec<&str>) -> anyhow::Result<()> {
         callback_id: None,
     };
 
-    send_msg(msg)
+    try_send_msg(msg)?;
+
+    Ok(())
+}
+
+/// Spawn a process and wait for a window that meets `condition` to open.
+///
+/// This is useful for doing something with a window right after spawning it, like moving it
+/// to a tag. See [`crate::window::wait_for`] for details on how waiting works.
+///
+/// The wait is registered before the process is spawned, so the window can't be missed.
+///
+/// # Example
+/// ```no_run
+/// use pinnacle_api::process;
+/// use pinnacle_api::window::rules::WindowRuleCondition;
+///
+/// let window = process::spawn_and_wait_window(
+///     vec!["alacritty"],
+///     WindowRuleCondition::new().class(&["Alacritty"]),
+///     5000,
+/// );
+/// ```
+pub fn spawn_and_wait_window(
+    command: Vec<&str>,
+    condition: WindowRuleCondition,
+    timeout_ms: u64,
+) -> anyhow::Result<WindowHandle> {
+    // Messages are handled in order, so sending the wait request first guarantees
+    // the window can't map before the compositor knows about the wait.
+    let pending = PendingWindowWait::send(condition, timeout_ms);
+    spawn(command)?;
+    pending.wait()
 }
 
 /// Spawn a process with an optional callback for its stdout, stderr, and exit information.
@@ -49,15 +83,16 @@ where
         }
     };
 
-    let len = callback_vec.callbacks.len();
-    callback_vec.callbacks.push(Box::new(args_callback));
+    let callback_id = callback_vec.insert(args_callback);
 
     let msg = Msg::Spawn {
         command: command.into_iter().map(|s| s.to_string()).collect(),
-        callback_id: Some(CallbackId(len as u32)),
+        callback_id: Some(callback_id),
     };
 
-    send_msg(msg)
+    try_send_msg(msg)?;
+
+    Ok(())
 }
 
 /// Set an environment variable for Pinnacle. All future processes spawned will have this env set.
@@ -71,5 +106,5 @@ pub fn set_env(key: &str, value: &str) {
         value: value.to_string(),
     };
 
-    send_msg(msg).unwrap();
+    send_msg(msg);
 }
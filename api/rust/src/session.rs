@@ -0,0 +1,76 @@
+//! Restoring your session after Pinnacle restarts.
+//!
+//! When Pinnacle shuts down, it saves your tags and where your windows were.
+//! [`previous_session`][crate::previous_session] gets that so your config can respawn your apps,
+//! and [`apply_previous_session`][crate::apply_previous_session] puts their windows back where
+//! they were as they reappear.
+
+use crate::tag::Layout;
+
+/// The session saved when Pinnacle last shut down.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Session {
+    /// The version of the format the session was saved in.
+    pub version: u32,
+    /// Every output that was connected, with its tags.
+    pub outputs: Vec<SessionOutput>,
+    /// Every window, from the bottom of the stack to the top.
+    ///
+    /// Windows that weren't shown, like ones on inactive tags, come first.
+    pub windows: Vec<SessionWindow>,
+}
+
+/// An output and its tags.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SessionOutput {
+    /// The output's connector name, like `"DP-1"`.
+    pub name: String,
+    /// The output's tags, in order.
+    pub tags: Vec<SessionTag>,
+}
+
+/// A tag on an output.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SessionTag {
+    /// The tag's name.
+    pub name: String,
+    /// Whether the tag was active.
+    pub active: bool,
+    /// The tag's layout.
+    pub layout: Option<Layout>,
+}
+
+/// A window and where it was.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SessionWindow {
+    /// The window's class.
+    pub class: Option<String>,
+    /// The window's title.
+    pub title: Option<String>,
+    /// The name of the output the window's tags were on.
+    pub output_name: Option<String>,
+    /// The names of the window's tags.
+    pub tags: Vec<String>,
+    /// Whether the window was floating.
+    pub floating: bool,
+    /// Where the window was floating, or would have floated if it was tiled.
+    pub geometry: Option<SessionGeometry>,
+}
+
+/// A window's floating geometry, in logical pixels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SessionGeometry {
+    /// The x coordinate of the window's top left corner.
+    pub x: i32,
+    /// The y coordinate of the window's top left corner.
+    pub y: i32,
+    /// The window's width.
+    pub width: i32,
+    /// The window's height.
+    pub height: i32,
+}
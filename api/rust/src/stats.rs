@@ -0,0 +1,143 @@
+//! How long requests to Pinnacle take.
+//!
+//! If your config feels slow, [`api_stats`][crate::api_stats] shows which requests the time
+//! goes to, both in Pinnacle and on the way there and back.
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Write},
+    sync::Mutex,
+    time::Duration,
+};
+
+use crate::msg::RequestStatsProps;
+
+/// The upper bounds of the round trip histogram buckets, in microseconds.
+///
+/// There's one more bucket for everything slower than the last bound.
+const BUCKET_BOUNDS_US: [u64; 10] = [
+    50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 100_000,
+];
+
+lazy_static::lazy_static! {
+    static ref ROUND_TRIPS: Mutex<HashMap<String, RequestStatsProps>> = Mutex::new(HashMap::new());
+}
+
+/// How long requests to Pinnacle have taken, from [`api_stats`][crate::api_stats].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiStats {
+    /// How long Pinnacle took to handle each kind of request, from every client.
+    pub handling: Vec<RequestTimes>,
+    /// How long each kind of request took from being sent to its response being read,
+    /// from this config only.
+    pub round_trips: Vec<RequestTimes>,
+}
+
+/// How long one kind of request has taken.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestTimes {
+    /// The name of the request, like `"GetWindowProps"`.
+    pub name: String,
+    /// How many of these requests there have been.
+    pub count: u64,
+    /// How long all of them took together.
+    pub total: Duration,
+    /// How long the slowest one took.
+    pub max: Duration,
+    /// A histogram of how long they took.
+    ///
+    /// Each bucket has its upper bound and how many requests took at most that long but
+    /// longer than the previous bucket's bound. The last bucket has no upper bound.
+    pub buckets: Vec<(Option<Duration>, u64)>,
+}
+
+impl RequestTimes {
+    /// Get how long one of these requests took on average.
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+
+        Duration::from_nanos((self.total.as_nanos() / self.count as u128) as u64)
+    }
+
+    pub(crate) fn from_props(props: RequestStatsProps, bucket_bounds_us: &[u64]) -> Self {
+        let bounds = bucket_bounds_us
+            .iter()
+            .map(|bound| Some(Duration::from_micros(*bound)))
+            .chain(std::iter::repeat(None));
+
+        Self {
+            name: props.name,
+            count: props.count,
+            total: Duration::from_micros(props.total_us),
+            max: Duration::from_micros(props.max_us),
+            buckets: bounds.zip(props.buckets).collect(),
+        }
+    }
+}
+
+/// Get how long this config's requests have taken to come back, sorted by name.
+pub(crate) fn round_trips() -> Vec<RequestTimes> {
+    let round_trips = ROUND_TRIPS.lock().expect("round trips mutex was poisoned");
+
+    let mut times = round_trips
+        .values()
+        .map(|props| RequestTimes::from_props(props.clone(), &BUCKET_BOUNDS_US))
+        .collect::<Vec<_>>();
+
+    times.sort_by(|a, b| a.name.cmp(&b.name));
+    times
+}
+
+/// Record that the request called `name` took `elapsed` to come back.
+pub(crate) fn record_round_trip(name: String, elapsed: Duration) {
+    let mut round_trips = ROUND_TRIPS.lock().expect("round trips mutex was poisoned");
+
+    let props = round_trips
+        .entry(name.clone())
+        .or_insert_with(|| RequestStatsProps {
+            name,
+            count: 0,
+            total_us: 0,
+            max_us: 0,
+            buckets: vec![0; BUCKET_BOUNDS_US.len() + 1],
+        });
+
+    let micros = elapsed.as_micros() as u64;
+    let bucket = BUCKET_BOUNDS_US
+        .iter()
+        .position(|bound| micros <= *bound)
+        .unwrap_or(BUCKET_BOUNDS_US.len());
+
+    props.count += 1;
+    props.total_us += micros;
+    props.max_us = props.max_us.max(micros);
+    props.buckets[bucket] += 1;
+}
+
+/// Get the name of the enum variant `value` is from its `Debug` output.
+///
+/// Formatting stops as soon as the name is read, so fields aren't formatted.
+pub(crate) fn variant_name(value: &impl Debug) -> String {
+    struct NameWriter(String);
+
+    impl Write for NameWriter {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            let end = s
+                .find(|c: char| !c.is_alphanumeric() && c != '_')
+                .unwrap_or(s.len());
+            self.0.push_str(&s[..end]);
+
+            if end < s.len() {
+                Err(fmt::Error)
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    let mut writer = NameWriter(String::new());
+    let _ = write!(writer, "{value:?}");
+    writer.0
+}
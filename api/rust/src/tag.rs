@@ -1,13 +1,19 @@
 //! Tag management.
 
+mod binds;
+
 use std::collections::HashMap;
 
 use crate::{
-    msg::{Msg, Request, RequestResponse},
+    msg::{Args, Msg, Request, RequestResponse},
     output::{OutputHandle, OutputName},
     request, send_msg,
+    window::{FloatingOrTiled, WindowHandle},
+    CallbackVec,
 };
 
+pub use binds::{setup_standard_binds, RegisteredBind, StandardBindAction, StandardBinds};
+
 /// Get a tag by its name and output. If `output` is `None`, the currently focused output will
 /// be used instead.
 ///
@@ -32,15 +38,190 @@ pub fn get_all() -> impl Iterator<Item = TagHandle> {
     tag_ids.into_iter().map(TagHandle)
 }
 
-// TODO: return taghandles here
-/// Add tags with the names from `names` to `output`.
-pub fn add(output: &OutputHandle, names: &[&str]) {
-    let msg = Msg::AddTags {
+/// Add tags with the names from `names` to `output`, returning handles to them
+/// in the same order.
+///
+/// The tags exist by the time this returns, so the handles can be used right away.
+///
+/// # Example
+/// ```no_run
+/// use pinnacle_api::*;
+///
+/// let mut callback_vec = CallbackVec::new();
+///
+/// output::connect_for_all(
+///     |output, _| {
+///         let tags = tag::add(&output, &["1", "2", "3"]);
+///         tags[0].toggle();
+///     },
+///     &mut callback_vec,
+/// );
+/// ```
+pub fn add(output: &OutputHandle, names: &[&str]) -> Vec<TagHandle> {
+    let RequestResponse::Tags { tag_ids } = request(Request::AddTags {
         output_name: output.0.clone(),
         tag_names: names.iter().map(|s| s.to_string()).collect(),
+    }) else {
+        unreachable!()
+    };
+
+    tag_ids.into_iter().map(TagHandle).collect()
+}
+
+/// Add tags with the names from `names` to `output` that it doesn't already have, returning
+/// handles to the tags with all of the names.
+///
+/// This lets a function connected with [`connect_for_all`] be run more than once for the same
+/// output without adding the same tags again. The handles are in the order of `names`.
+///
+/// [`connect_for_all`]: crate::output::connect_for_all
+pub fn add_if_missing(output: &OutputHandle, names: &[&str]) -> Vec<TagHandle> {
+    let existing = output
+        .properties()
+        .tags
+        .into_iter()
+        .filter_map(|tag| Some((tag.properties().name?, tag)))
+        .collect::<Vec<_>>();
+
+    let missing = names
+        .iter()
+        .copied()
+        .filter(|name| !existing.iter().any(|(existing, _)| existing == name))
+        .collect::<Vec<_>>();
+    let mut added = add(output, &missing).into_iter();
+
+    names
+        .iter()
+        .filter_map(
+            |name| match existing.iter().find(|(existing, _)| existing == name) {
+                Some((_, tag)) => Some(TagHandle(tag.0)),
+                None => added.next(),
+            },
+        )
+        .collect()
+}
+
+/// Connect a function to be run whenever a tag is activated or deactivated.
+///
+/// Switching to a tag runs `func` for every tag on its output whose active state changed.
+///
+/// `func` takes in five parameters:
+/// - `0`: The tag that was activated or deactivated.
+/// - `1`: The tag's name.
+/// - `2`: The output the tag is on, if any.
+/// - `3`: Whether the tag is now active.
+/// - `4`: A `&mut `[`CallbackVec`] for use in the closure.
+///
+/// # Example
+/// ```no_run
+/// use pinnacle_api::{process, tag, CallbackVec};
+///
+/// let mut callback_vec = CallbackVec::new();
+///
+/// // Change the wallpaper for each tag
+/// tag::connect_active_changed(
+///     |_, name, _, active, _| {
+///         if active {
+///             let _ = process::spawn(vec!["set-wallpaper", &name]);
+///         }
+///     },
+///     &mut callback_vec,
+/// );
+/// ```
+pub fn connect_active_changed<'a, F>(mut func: F, callback_vec: &mut CallbackVec<'a>)
+where
+    F: FnMut(TagHandle, String, Option<OutputHandle>, bool, &mut CallbackVec) + 'a,
+{
+    let args_callback = move |args: Option<Args>, callback_vec: &mut CallbackVec<'_>| {
+        if let Some(Args::TagActiveChanged {
+            tag_id,
+            name,
+            output_name,
+            active,
+        }) = args
+        {
+            let output = output_name.map(|name| OutputHandle(OutputName(name)));
+            func(TagHandle(tag_id), name, output, active, callback_vec);
+        }
+    };
+
+    let callback_id = callback_vec.insert(args_callback);
+
+    send_msg(Msg::ConnectTagActiveChanged { callback_id });
+}
+
+/// Where the windows on an output were laid out, given to functions connected with
+/// [`connect_layout_applied`].
+pub struct LayoutApplied {
+    /// The output that was laid out.
+    pub output: OutputHandle,
+    /// The first active tag on the output.
+    pub tag: TagHandle,
+    /// A number that goes up by one with every layout, so older ones can be told apart.
+    pub generation: u64,
+    /// The windows on the output's active tags and their geometries as `(x, y, width, height)`.
+    pub windows: Vec<(WindowHandle, (i32, i32, i32, i32))>,
+}
+
+/// Connect a function to be run whenever windows on an output are laid out.
+///
+/// This is called once the windows have been moved and resized, so the geometries are what's
+/// on screen. Tools that draw over windows can connect with [`connect_one_off`] and use this
+/// instead of asking for every window's geometry.
+///
+/// `func` takes in two parameters:
+/// - `0`: The [`LayoutApplied`] with the windows and their geometries.
+/// - `1`: A `&mut `[`CallbackVec`] for use in the closure.
+///
+/// # Example
+/// ```no_run
+/// use pinnacle_api::CallbackVec;
+///
+/// pinnacle_api::connect_one_off().unwrap();
+///
+/// let mut callback_vec = CallbackVec::new();
+///
+/// pinnacle_api::tag::connect_layout_applied(
+///     |applied, _| {
+///         println!("{} windows laid out", applied.windows.len());
+///     },
+///     &mut callback_vec,
+/// );
+///
+/// pinnacle_api::listen(callback_vec);
+/// ```
+///
+/// [`connect_one_off`]: crate::connect_one_off
+pub fn connect_layout_applied<'a, F>(mut func: F, callback_vec: &mut CallbackVec<'a>)
+where
+    F: FnMut(LayoutApplied, &mut CallbackVec) + 'a,
+{
+    let args_callback = move |args: Option<Args>, callback_vec: &mut CallbackVec<'_>| {
+        if let Some(Args::LayoutApplied {
+            output_name,
+            tag_id,
+            generation,
+            windows,
+        }) = args
+        {
+            let applied = LayoutApplied {
+                output: OutputHandle(OutputName(output_name)),
+                tag: TagHandle(tag_id),
+                generation,
+                windows: windows
+                    .into_iter()
+                    .map(|(window_id, x, y, width, height)| {
+                        (WindowHandle(window_id), (x, y, width, height))
+                    })
+                    .collect(),
+            };
+            func(applied, callback_vec);
+        }
     };
 
-    send_msg(msg).unwrap();
+    let callback_id = callback_vec.insert(args_callback);
+
+    send_msg(Msg::ConnectLayoutApplied { callback_id });
 }
 
 /// Create a `LayoutCycler` to cycle layouts on tags.
@@ -144,6 +325,10 @@ pub struct TagProperties {
     pub name: Option<String>,
     /// The output the tag is on.
     pub output: Option<OutputHandle>,
+    /// How much of the width the master window takes up in the tag's layout.
+    pub master_factor: Option<f64>,
+    /// Whether new windows on the tag float or tile, if it overrides its output.
+    pub default_window_mode: Option<FloatingOrTiled>,
 }
 
 impl TagHandle {
@@ -153,6 +338,8 @@ impl TagHandle {
             active,
             name,
             output_name,
+            master_factor,
+            default_window_mode,
         } = request(Request::GetTagProps { tag_id: self.0 })
         else {
             unreachable!()
@@ -162,19 +349,21 @@ impl TagHandle {
             active,
             name,
             output: output_name.map(|name| OutputHandle(OutputName(name))),
+            master_factor,
+            default_window_mode,
         }
     }
 
     /// Toggle this tag.
     pub fn toggle(&self) {
         let msg = Msg::ToggleTag { tag_id: self.0 };
-        send_msg(msg).unwrap();
+        send_msg(msg);
     }
 
     /// Switch to this tag, deactivating all others on its output.
     pub fn switch_to(&self) {
         let msg = Msg::SwitchToTag { tag_id: self.0 };
-        send_msg(msg).unwrap();
+        send_msg(msg);
     }
 
     /// Set this tag's [`Layout`].
@@ -184,7 +373,48 @@ impl TagHandle {
             layout,
         };
 
-        send_msg(msg).unwrap()
+        send_msg(msg)
+    }
+
+    /// Set how much of the width the master window takes up in this tag's layout,
+    /// from 0.1 to 0.9.
+    ///
+    /// This applies to [`Layout::MasterStack`], [`Layout::Dwindle`], and [`Layout::Spiral`].
+    /// The default is 0.5. Dragging the edge between the master window and the rest
+    /// also changes this.
+    pub fn set_master_factor(&self, master_factor: f64) {
+        let msg = Msg::SetMasterFactor {
+            tag_id: self.0,
+            master_factor,
+        };
+
+        send_msg(msg)
+    }
+
+    /// Set whether new windows on this tag float or tile, overriding its output's
+    /// [default][OutputHandle::set_default_window_mode].
+    ///
+    /// Pass `None` to go by the output again. Window rules take precedence over this.
+    /// This is kept across config reloads for tags with the same name on the same output.
+    pub fn set_default_window_mode(&self, floating_or_tiled: Option<FloatingOrTiled>) {
+        let msg = Msg::SetTagDefaultWindowMode {
+            tag_id: self.0,
+            floating_or_tiled,
+        };
+
+        send_msg(msg)
+    }
+
+    /// Move this tag to `index` in its output's tag order.
+    ///
+    /// Indices past the end move the tag to the end.
+    pub fn set_index(&self, index: usize) {
+        let msg = Msg::SetTagIndex {
+            tag_id: self.0,
+            index,
+        };
+
+        send_msg(msg)
     }
 }
 
@@ -206,3 +436,67 @@ pub enum Layout {
     /// One main corner window in the bottom right with a column of windows on the left and a row on the top.
     CornerBottomRight,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        os::unix::net::UnixStream,
+    };
+
+    use serde_json::{json, Value};
+
+    use crate::msg::{IncomingMsg, RequestId};
+
+    use super::*;
+
+    /// Read the next message sent to the fake Pinnacle on the other end of `stream`.
+    fn read_sent(stream: &mut UnixStream) -> Value {
+        let mut len = [0u8; 4];
+        stream.read_exact(&mut len).unwrap();
+        let mut msg = vec![0u8; u32::from_ne_bytes(len) as usize];
+        stream.read_exact(&mut msg).unwrap();
+        rmp_serde::from_slice(&msg).unwrap()
+    }
+
+    fn respond(stream: &mut UnixStream, msg: &IncomingMsg) {
+        let msg = rmp_serde::encode::to_vec_named(msg).unwrap();
+        stream.write_all(&(msg.len() as u32).to_ne_bytes()).unwrap();
+        stream.write_all(&msg).unwrap();
+    }
+
+    #[test]
+    fn added_tags_are_usable_right_away() {
+        let (config, mut pinnacle) = UnixStream::pair().unwrap();
+        crate::set_stream(config).unwrap();
+
+        let fake_pinnacle = std::thread::spawn(move || {
+            let add = read_sent(&mut pinnacle);
+            assert_eq!(
+                add["Request"]["request"],
+                json!({ "AddTags": { "output_name": "DP-1", "tag_names": ["1", "2"] } })
+            );
+            let request_id = add["Request"]["request_id"].as_u64().unwrap() as u32;
+
+            respond(
+                &mut pinnacle,
+                &IncomingMsg::RequestResponse {
+                    request_id: RequestId(request_id),
+                    response: RequestResponse::Tags {
+                        tag_ids: vec![TagId::Some(7), TagId::Some(8)],
+                    },
+                },
+            );
+
+            [read_sent(&mut pinnacle), read_sent(&mut pinnacle)]
+        });
+
+        let tags = add(&OutputHandle(OutputName("DP-1".to_string())), &["1", "2"]);
+        tags[0].toggle();
+        tags[1].switch_to();
+
+        let [toggle, switch_to] = fake_pinnacle.join().unwrap();
+        assert_eq!(toggle, json!({ "ToggleTag": { "tag_id": 7 } }));
+        assert_eq!(switch_to, json!({ "SwitchToTag": { "tag_id": 8 } }));
+    }
+}
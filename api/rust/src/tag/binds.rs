@@ -0,0 +1,312 @@
+//! Standard keybinds for tags.
+
+use crate::{
+    input::{self, Modifier},
+    window, CallbackVec,
+};
+
+/// Register the standard set of keybinds for every tag in `tag_names`.
+///
+/// For each tag, the key that is bound is the first character of its name. The binds are:
+/// - `mod_key + key`: Switch to the tag
+/// - `mod_key + Shift + key`: Toggle the tag
+/// - `mod_key + Alt + key`: Move the focused window to the tag
+/// - `mod_key + Shift + Alt + key`: Toggle the tag on the focused window
+///
+/// Use [`StandardBinds`] if you want to change the modifiers.
+pub fn setup_standard_binds(
+    mod_key: Modifier,
+    tag_names: &[&str],
+    callback_vec: &mut CallbackVec,
+) -> Vec<RegisteredBind> {
+    StandardBinds::new(mod_key).register(tag_names, callback_vec)
+}
+
+/// The action a standard tag bind performs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum StandardBindAction {
+    /// Switch to the tag, deactivating all others on its output.
+    Switch,
+    /// Toggle the tag.
+    Toggle,
+    /// Move the focused window to the tag.
+    MoveWindow,
+    /// Toggle the tag on the focused window.
+    ToggleWindow,
+}
+
+/// A keybind registered by [`StandardBinds::register`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisteredBind {
+    /// The modifiers that need to be held.
+    pub modifiers: Vec<Modifier>,
+    /// The key that needs to be pressed.
+    pub key: char,
+    /// The name of the tag this bind acts on.
+    pub tag_name: String,
+    /// What this bind does.
+    pub action: StandardBindAction,
+}
+
+/// A builder for the standard set of keybinds for tags.
+///
+/// Every action is bound by default with the modifiers described in [`setup_standard_binds`].
+/// Set an action's modifiers with its method, or use [`StandardBinds::without`] to not bind it.
+///
+/// # Example
+/// ```no_run
+/// use pinnacle_api::prelude::*;
+/// use pinnacle_api::tag::{StandardBindAction, StandardBinds};
+/// # let mut callback_vec = pinnacle_api::CallbackVec::new();
+///
+/// StandardBinds::new(Modifier::Super)
+///     .without(StandardBindAction::Toggle)
+///     .move_window(&[Modifier::Super, Modifier::Ctrl])
+///     .register(&["1", "2", "3"], &mut callback_vec);
+/// ```
+#[derive(Debug, Clone)]
+pub struct StandardBinds {
+    switch: Option<Vec<Modifier>>,
+    toggle: Option<Vec<Modifier>>,
+    move_window: Option<Vec<Modifier>>,
+    toggle_window: Option<Vec<Modifier>>,
+}
+
+impl StandardBinds {
+    /// Create a new `StandardBinds` with the default modifiers based on `mod_key`.
+    pub fn new(mod_key: Modifier) -> Self {
+        Self {
+            switch: Some(vec![mod_key]),
+            toggle: Some(vec![mod_key, Modifier::Shift]),
+            move_window: Some(vec![mod_key, Modifier::Alt]),
+            toggle_window: Some(vec![mod_key, Modifier::Shift, Modifier::Alt]),
+        }
+    }
+
+    /// Set the modifiers for switching to a tag.
+    pub fn switch(mut self, modifiers: &[Modifier]) -> Self {
+        self.switch = Some(modifiers.to_vec());
+        self
+    }
+
+    /// Set the modifiers for toggling a tag.
+    pub fn toggle(mut self, modifiers: &[Modifier]) -> Self {
+        self.toggle = Some(modifiers.to_vec());
+        self
+    }
+
+    /// Set the modifiers for moving the focused window to a tag.
+    pub fn move_window(mut self, modifiers: &[Modifier]) -> Self {
+        self.move_window = Some(modifiers.to_vec());
+        self
+    }
+
+    /// Set the modifiers for toggling a tag on the focused window.
+    pub fn toggle_window(mut self, modifiers: &[Modifier]) -> Self {
+        self.toggle_window = Some(modifiers.to_vec());
+        self
+    }
+
+    /// Don't bind `action` at all.
+    pub fn without(mut self, action: StandardBindAction) -> Self {
+        match action {
+            StandardBindAction::Switch => self.switch = None,
+            StandardBindAction::Toggle => self.toggle = None,
+            StandardBindAction::MoveWindow => self.move_window = None,
+            StandardBindAction::ToggleWindow => self.toggle_window = None,
+        }
+        self
+    }
+
+    /// Compute the binds that [`StandardBinds::register`] would register, without registering them.
+    ///
+    /// Tags with empty names are skipped.
+    pub fn binds(&self, tag_names: &[&str]) -> Vec<RegisteredBind> {
+        let actions = [
+            (&self.switch, StandardBindAction::Switch),
+            (&self.toggle, StandardBindAction::Toggle),
+            (&self.move_window, StandardBindAction::MoveWindow),
+            (&self.toggle_window, StandardBindAction::ToggleWindow),
+        ];
+
+        let mut binds = Vec::new();
+
+        for tag_name in tag_names {
+            let Some(key) = tag_name.chars().next() else {
+                continue;
+            };
+
+            for (modifiers, action) in actions.iter() {
+                let Some(modifiers) = modifiers else {
+                    continue;
+                };
+
+                binds.push(RegisteredBind {
+                    modifiers: modifiers.clone(),
+                    key,
+                    tag_name: tag_name.to_string(),
+                    action: *action,
+                });
+            }
+        }
+
+        binds
+    }
+
+    /// Register the keybinds for every tag in `tag_names`, returning what was registered.
+    pub fn register(
+        self,
+        tag_names: &[&str],
+        callback_vec: &mut CallbackVec,
+    ) -> Vec<RegisteredBind> {
+        let binds = self.binds(tag_names);
+
+        // One callback for every bind, which gets the tag and action from the bind's data
+        let callback = input::keybind_callback(
+            |(tag_name, action): (String, StandardBindAction), _| {
+                let Some(tag) = super::get(&tag_name, None) else {
+                    return;
+                };
+
+                match action {
+                    StandardBindAction::Switch => tag.switch_to(),
+                    StandardBindAction::Toggle => tag.toggle(),
+                    StandardBindAction::MoveWindow => {
+                        if let Some(window) = window::get_focused() {
+                            window.move_to_tag(&tag);
+                        }
+                    }
+                    StandardBindAction::ToggleWindow => {
+                        if let Some(window) = window::get_focused() {
+                            window.toggle_tag(&tag);
+                        }
+                    }
+                }
+            },
+            callback_vec,
+        );
+
+        for bind in binds.iter() {
+            input::keybind_with_data(
+                &bind.modifiers,
+                bind.key,
+                &(bind.tag_name.clone(), bind.action),
+                callback,
+                callback_vec,
+            )
+            .expect("a tag name and action always serialize");
+        }
+
+        binds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bind(modifiers: &[Modifier], key: char, action: StandardBindAction) -> RegisteredBind {
+        RegisteredBind {
+            modifiers: modifiers.to_vec(),
+            key,
+            tag_name: key.to_string(),
+            action,
+        }
+    }
+
+    #[test]
+    fn default_binds() {
+        let binds = StandardBinds::new(Modifier::Super).binds(&["1", "2"]);
+
+        let mut expected = Vec::new();
+        for key in ['1', '2'] {
+            expected.extend([
+                bind(&[Modifier::Super], key, StandardBindAction::Switch),
+                bind(
+                    &[Modifier::Super, Modifier::Shift],
+                    key,
+                    StandardBindAction::Toggle,
+                ),
+                bind(
+                    &[Modifier::Super, Modifier::Alt],
+                    key,
+                    StandardBindAction::MoveWindow,
+                ),
+                bind(
+                    &[Modifier::Super, Modifier::Shift, Modifier::Alt],
+                    key,
+                    StandardBindAction::ToggleWindow,
+                ),
+            ]);
+        }
+
+        assert_eq!(binds, expected);
+    }
+
+    #[test]
+    fn without_skips_actions() {
+        let binds = StandardBinds::new(Modifier::Ctrl)
+            .without(StandardBindAction::Toggle)
+            .without(StandardBindAction::ToggleWindow)
+            .binds(&["1"]);
+
+        assert_eq!(
+            binds,
+            [
+                bind(&[Modifier::Ctrl], '1', StandardBindAction::Switch),
+                bind(
+                    &[Modifier::Ctrl, Modifier::Alt],
+                    '1',
+                    StandardBindAction::MoveWindow
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn custom_modifiers() {
+        let binds = StandardBinds::new(Modifier::Super)
+            .switch(&[Modifier::Alt])
+            .move_window(&[Modifier::Super, Modifier::Ctrl])
+            .binds(&["1"]);
+
+        let modifiers_for = |action| {
+            binds
+                .iter()
+                .find(|bind| bind.action == action)
+                .map(|bind| bind.modifiers.clone())
+        };
+
+        assert_eq!(
+            modifiers_for(StandardBindAction::Switch),
+            Some(vec![Modifier::Alt])
+        );
+        assert_eq!(
+            modifiers_for(StandardBindAction::MoveWindow),
+            Some(vec![Modifier::Super, Modifier::Ctrl])
+        );
+        assert_eq!(
+            modifiers_for(StandardBindAction::Toggle),
+            Some(vec![Modifier::Super, Modifier::Shift])
+        );
+    }
+
+    #[test]
+    fn binds_first_char_and_skips_empty_names() {
+        let binds = StandardBinds::new(Modifier::Super)
+            .without(StandardBindAction::Toggle)
+            .without(StandardBindAction::MoveWindow)
+            .without(StandardBindAction::ToggleWindow)
+            .binds(&["", "web", ""]);
+
+        assert_eq!(
+            binds,
+            [RegisteredBind {
+                modifiers: vec![Modifier::Super],
+                key: 'w',
+                tag_name: "web".to_string(),
+                action: StandardBindAction::Switch,
+            }]
+        );
+    }
+}
@@ -2,13 +2,22 @@
 
 pub mod rules;
 
+use std::time::Duration;
+
 use crate::{
-    input::MouseButton,
-    msg::{Msg, Request, RequestResponse},
-    request, send_msg,
+    input::{Modifier, MouseButton},
+    msg::{
+        Args, ClientDisconnectProps, ClientDisconnectReason, Msg, Request, RequestId,
+        RequestResponse,
+    },
+    output::OutputHandle,
+    request, send_msg, send_request,
     tag::TagHandle,
+    try_read_response, CallbackVec,
 };
 
+use self::rules::WindowRuleCondition;
+
 /// A unique identifier for each window.
 #[derive(Debug, Hash, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub(crate) enum WindowId {
@@ -25,10 +34,347 @@ pub fn get_by_class(class: &str) -> impl Iterator<Item = WindowHandle> + '_ {
 }
 
 /// Get the currently focused window, or `None` if there isn't one.
+///
+/// This is the window with keyboard focus. See [`get_pointer_focused`] for the one
+/// with pointer focus.
 pub fn get_focused() -> Option<WindowHandle> {
     get_all().find(|win| win.properties().focused.is_some_and(|focused| focused))
 }
 
+/// Get the window with keyboard focus, or `None` if there isn't one.
+///
+/// This is the same as [`get_focused`].
+pub fn get_keyboard_focused() -> Option<WindowHandle> {
+    get_focused()
+}
+
+/// Get the window with pointer focus, or `None` if there isn't one.
+///
+/// This is the window Pinnacle is sending pointer events to, which is usually the one
+/// under the pointer. It's `None` while moving or resizing a window, as those grabs
+/// don't send pointer events to any window.
+pub fn get_pointer_focused() -> Option<WindowHandle> {
+    let RequestResponse::Window { window_id } = request(Request::GetPointerFocusedWindow) else {
+        unreachable!()
+    };
+
+    window_id.map(WindowHandle)
+}
+
+/// Get the window that was focused before the focused one, or `None` if there isn't one.
+///
+/// Only windows on active tags are considered. This makes jumping back to the last window
+/// a one-liner:
+///
+/// ```no_run
+/// # use pinnacle_api::window;
+/// if let Some(win) = window::get_previous_focused() {
+///     win.focus();
+/// }
+/// ```
+pub fn get_previous_focused() -> Option<WindowHandle> {
+    let RequestResponse::Window { window_id } = request(Request::GetPreviousFocusedWindow) else {
+        unreachable!()
+    };
+
+    window_id.map(WindowHandle)
+}
+
+/// Connect a function to be run whenever the window with keyboard focus changes.
+///
+/// `func` takes in two parameters:
+/// - `0`: The newly focused window, or `None` if no window has keyboard focus.
+/// - `1`: A `&mut `[`CallbackVec`] for use in the closure.
+pub fn connect_keyboard_focus_changed<'a, F>(func: F, callback_vec: &mut CallbackVec<'a>)
+where
+    F: FnMut(Option<WindowHandle>, &mut CallbackVec) + 'a,
+{
+    let callback_id = callback_vec.insert(focus_changed_callback(func));
+
+    send_msg(Msg::ConnectKeyboardFocusChanged { callback_id });
+}
+
+/// Connect a function to be run whenever the window with pointer focus changes.
+///
+/// `func` takes in two parameters:
+/// - `0`: The window the pointer is now over, or `None` if it isn't over one.
+/// - `1`: A `&mut `[`CallbackVec`] for use in the closure.
+///
+/// Moving between a window and its popups doesn't count as a change.
+/// See [`get_pointer_focused`] for what happens during grabs.
+pub fn connect_pointer_focus_changed<'a, F>(func: F, callback_vec: &mut CallbackVec<'a>)
+where
+    F: FnMut(Option<WindowHandle>, &mut CallbackVec) + 'a,
+{
+    let callback_id = callback_vec.insert(focus_changed_callback(func));
+
+    send_msg(Msg::ConnectPointerFocusChanged { callback_id });
+}
+
+/// Connect a function to be run whenever a window's title changes.
+///
+/// This includes windows on inactive tags, so a bar can show what a hidden window is doing.
+///
+/// `func` takes in three parameters:
+/// - `0`: The window whose title changed.
+/// - `1`: Its new title, or `None` if it unset it.
+/// - `2`: A `&mut `[`CallbackVec`] for use in the closure.
+pub fn connect_title_changed<'a, F>(mut func: F, callback_vec: &mut CallbackVec<'a>)
+where
+    F: FnMut(WindowHandle, Option<String>, &mut CallbackVec) + 'a,
+{
+    let closure = move |args: Option<Args>, callback_vec: &mut CallbackVec| {
+        if let Some(Args::WindowTitleChanged { window_id, title }) = args {
+            func(WindowHandle(window_id), title, callback_vec);
+        }
+    };
+
+    let callback_id = callback_vec.insert(closure);
+
+    send_msg(Msg::ConnectWindowTitleChanged { callback_id });
+}
+
+/// Connect a function to be run whenever a window's class changes.
+///
+/// `func` takes in three parameters:
+/// - `0`: The window whose class changed.
+/// - `1`: Its new class, or `None` if it unset it.
+/// - `2`: A `&mut `[`CallbackVec`] for use in the closure.
+pub fn connect_class_changed<'a, F>(mut func: F, callback_vec: &mut CallbackVec<'a>)
+where
+    F: FnMut(WindowHandle, Option<String>, &mut CallbackVec) + 'a,
+{
+    let closure = move |args: Option<Args>, callback_vec: &mut CallbackVec| {
+        if let Some(Args::WindowClassChanged { window_id, class }) = args {
+            func(WindowHandle(window_id), class, callback_vec);
+        }
+    };
+
+    let callback_id = callback_vec.insert(closure);
+
+    send_msg(Msg::ConnectWindowClassChanged { callback_id });
+}
+
+/// Connect a function to be run whenever a window opens.
+///
+/// This runs after window rules were applied, so the window is already on the tags and output
+/// they gave it.
+///
+/// `func` takes in four parameters:
+/// - `0`: The window that opened.
+/// - `1`: Its class, or `None` if it hasn't set one.
+/// - `2`: Its title, or `None` if it hasn't set one.
+/// - `3`: A `&mut `[`CallbackVec`] for use in the closure.
+///
+/// # Example
+/// ```no_run
+/// use pinnacle_api::{tag, window, CallbackVec};
+///
+/// let mut callback_vec = CallbackVec::new();
+///
+/// // Move mpv to tag 5 whenever it opens
+/// window::connect_opened(
+///     |window, class, _title, _| {
+///         if class.as_deref() == Some("mpv") {
+///             if let Some(tag) = tag::get("5", None) {
+///                 window.move_to_tag(&tag);
+///             }
+///         }
+///     },
+///     &mut callback_vec,
+/// );
+/// ```
+pub fn connect_opened<'a, F>(mut func: F, callback_vec: &mut CallbackVec<'a>)
+where
+    F: FnMut(WindowHandle, Option<String>, Option<String>, &mut CallbackVec) + 'a,
+{
+    let closure = move |args: Option<Args>, callback_vec: &mut CallbackVec| {
+        if let Some(Args::WindowOpened {
+            window_id,
+            class,
+            title,
+        }) = args
+        {
+            func(WindowHandle(window_id), class, title, callback_vec);
+        }
+    };
+
+    let callback_id = callback_vec.insert(closure);
+
+    send_msg(Msg::ConnectWindowOpened { callback_id });
+}
+
+/// Connect a function to be run whenever a window closes.
+///
+/// The window is gone by the time `func` runs, so its class and title are passed in as they
+/// last were.
+///
+/// `func` takes in four parameters:
+/// - `0`: The window that closed.
+/// - `1`: Its last class, or `None` if it never set one.
+/// - `2`: Its last title, or `None` if it never set one.
+/// - `3`: A `&mut `[`CallbackVec`] for use in the closure.
+pub fn connect_closed<'a, F>(mut func: F, callback_vec: &mut CallbackVec<'a>)
+where
+    F: FnMut(WindowHandle, Option<String>, Option<String>, &mut CallbackVec) + 'a,
+{
+    let closure = move |args: Option<Args>, callback_vec: &mut CallbackVec| {
+        if let Some(Args::WindowClosed {
+            window_id,
+            class,
+            title,
+        }) = args
+        {
+            func(WindowHandle(window_id), class, title, callback_vec);
+        }
+    };
+
+    let callback_id = callback_vec.insert(closure);
+
+    send_msg(Msg::ConnectWindowClosed { callback_id });
+}
+
+fn focus_changed_callback<'a, F>(mut func: F) -> impl FnMut(Option<Args>, &mut CallbackVec<'_>) + 'a
+where
+    F: FnMut(Option<WindowHandle>, &mut CallbackVec) + 'a,
+{
+    move |args: Option<Args>, callback_vec: &mut CallbackVec<'_>| {
+        if let Some(Args::WindowFocusChanged { window_id }) = args {
+            func(window_id.map(WindowHandle), callback_vec);
+        }
+    }
+}
+
+/// Why a client disconnected, from [`ClientDisconnect`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// The client closed its connection after closing its windows.
+    Clean,
+    /// The client's connection closed while it still had windows open.
+    /// This is what happens when it crashes or is killed.
+    Crashed,
+    /// Pinnacle disconnected the client because it broke the Wayland protocol.
+    ProtocolError {
+        /// The interface of the object the error was on, like `xdg_surface`.
+        interface: String,
+        /// The id of the object the error was on.
+        object_id: u32,
+        /// The error code, specific to `interface`.
+        code: u32,
+        /// A description of the error.
+        message: String,
+    },
+}
+
+/// A Wayland client that disconnected.
+///
+/// Only clients that opened a window or broke the Wayland protocol are reported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientDisconnect {
+    /// The client's process id, if it was known.
+    pub pid: Option<i32>,
+    /// The client's process name, like `firefox`, if it was known.
+    pub process_name: Option<String>,
+    /// Why the client disconnected.
+    pub reason: DisconnectReason,
+    /// The ids of the client's windows that were open when it disconnected.
+    /// See [`WindowHandle::id`].
+    pub window_ids: Vec<u32>,
+    /// How long ago the client disconnected, in seconds.
+    pub seconds_ago: u64,
+}
+
+impl From<ClientDisconnectProps> for ClientDisconnect {
+    fn from(props: ClientDisconnectProps) -> Self {
+        let reason = match props.reason {
+            ClientDisconnectReason::Clean => DisconnectReason::Clean,
+            ClientDisconnectReason::Crashed => DisconnectReason::Crashed,
+            ClientDisconnectReason::ProtocolError {
+                interface,
+                object_id,
+                code,
+                message,
+            } => DisconnectReason::ProtocolError {
+                interface,
+                object_id,
+                code,
+                message,
+            },
+        };
+
+        Self {
+            pid: props.pid,
+            process_name: props.process_name,
+            reason,
+            window_ids: props
+                .window_ids
+                .into_iter()
+                .filter_map(|id| WindowHandle(id).id())
+                .collect(),
+            seconds_ago: props.seconds_ago,
+        }
+    }
+}
+
+/// Connect a function to be run whenever a client with windows disconnects.
+///
+/// `func` takes in two parameters:
+/// - `0`: The [`ClientDisconnect`] saying which client it was and why it disconnected.
+/// - `1`: A `&mut `[`CallbackVec`] for use in the closure.
+///
+/// This is useful for finding out why a window suddenly disappeared.
+///
+/// ```no_run
+/// use pinnacle_api::window::{self, DisconnectReason};
+/// # let mut callback_vec = pinnacle_api::CallbackVec::new();
+///
+/// window::connect_client_disconnected(
+///     |disconnect, _| {
+///         if disconnect.reason != DisconnectReason::Clean {
+///             eprintln!("{:?} disconnected: {:?}", disconnect.process_name, disconnect.reason);
+///         }
+///     },
+///     &mut callback_vec,
+/// );
+/// ```
+pub fn connect_client_disconnected<'a, F>(mut func: F, callback_vec: &mut CallbackVec<'a>)
+where
+    F: FnMut(&ClientDisconnect, &mut CallbackVec) + 'a,
+{
+    let closure = move |args: Option<Args>, callback_vec: &mut CallbackVec| {
+        if let Some(Args::ClientDisconnected { disconnect }) = args {
+            func(&ClientDisconnect::from(disconnect), callback_vec);
+        }
+    };
+
+    let callback_id = callback_vec.insert(closure);
+
+    send_msg(Msg::ConnectClientDisconnected { callback_id });
+}
+
+/// Get the clients that disconnected recently, oldest first.
+///
+/// Pinnacle remembers the last 32, which is handy to include in bug reports.
+/// See [`connect_client_disconnected`] for which clients are reported.
+pub fn recent_disconnects() -> Vec<ClientDisconnect> {
+    let RequestResponse::RecentDisconnects { disconnects } = request(Request::GetRecentDisconnects)
+    else {
+        unreachable!()
+    };
+
+    disconnects
+        .into_iter()
+        .map(ClientDisconnect::from)
+        .collect()
+}
+
+/// Get the window with the given `id`, or `None` if it doesn't exist.
+///
+/// See [`WindowHandle::id`].
+pub fn get_by_id(id: u32) -> Option<WindowHandle> {
+    get_all().find(|win| win.id() == Some(id))
+}
+
 /// Get all windows.
 pub fn get_all() -> impl Iterator<Item = WindowHandle> {
     let RequestResponse::Windows { window_ids } = request(Request::GetWindows) else {
@@ -38,16 +384,246 @@ pub fn get_all() -> impl Iterator<Item = WindowHandle> {
     window_ids.into_iter().map(WindowHandle)
 }
 
+/// Get all windows that haven't been focused, unfocused, or clicked for at least `duration`.
+///
+/// The focused window is never inactive.
+/// See [`set_api_focus_counts_as_activity`] for whether focusing windows from your config counts.
+pub fn get_inactive_since(duration: Duration) -> impl Iterator<Item = WindowHandle> {
+    let RequestResponse::Windows { window_ids } = request(Request::GetInactiveWindows {
+        inactive_ms: duration.as_millis() as u64,
+    }) else {
+        unreachable!()
+    };
+
+    window_ids.into_iter().map(WindowHandle)
+}
+
+/// Get all windows that have no live tags.
+///
+/// These windows can't be seen until they get a tag. Pinnacle gives them tags again when tags
+/// are added to the output they were on, but you can also move them to a tag yourself.
+pub fn get_orphaned() -> impl Iterator<Item = WindowHandle> {
+    let RequestResponse::Windows { window_ids } = request(Request::GetOrphanedWindows) else {
+        unreachable!()
+    };
+
+    window_ids.into_iter().map(WindowHandle)
+}
+
+/// Set whether focusing windows through the API counts as using them.
+///
+/// This affects [`get_inactive_since`], [`WindowProperties::seconds_since_activity`],
+/// and [`auto_tidy`][crate::auto_tidy]. It's on by default, so focusing a window
+/// from a keybind keeps it from being tidied away.
+pub fn set_api_focus_counts_as_activity(counts: bool) {
+    send_msg(Msg::SetApiFocusCountsAsActivity { counts });
+}
+
+/// Set whether clicking a window raises it to the top of the stack.
+///
+/// When this is off, clicking a window only focuses it. Use [`WindowHandle::raise`] to raise
+/// windows yourself. It's on by default.
+pub fn set_raise_on_click(enabled: bool) {
+    send_msg(Msg::SetRaiseOnClick { enabled });
+}
+
+/// Focus the next window on the active tags of `output`, wrapping around.
+///
+/// Windows are cycled through in the order they are laid out.
+/// If `output` is `None`, the focused output is used.
+pub fn focus_next(output: Option<&OutputHandle>) {
+    send_msg(Msg::CycleWindowFocus {
+        output_name: output.map(|output| output.0.clone()),
+        forward: true,
+    });
+}
+
+/// Focus the previous window on the active tags of `output`, wrapping around.
+///
+/// Windows are cycled through in the order they are laid out.
+/// If `output` is `None`, the focused output is used.
+pub fn focus_prev(output: Option<&OutputHandle>) {
+    send_msg(Msg::CycleWindowFocus {
+        output_name: output.map(|output| output.0.clone()),
+        forward: false,
+    });
+}
+
+/// Which way to cycle through recently focused windows.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize)]
+pub enum CycleDirection {
+    /// To less recently focused windows.
+    Forward,
+    /// To more recently focused windows.
+    Backward,
+}
+
+/// Cycle through windows on active tags in the order they were last focused, like Alt-Tab.
+///
+/// The first call starts a cycle from the focused window. Each call focuses and raises the
+/// next window in the cycle without changing the order windows were focused in. The cycle is
+/// committed, moving the focused window to the top of that order, with [`cycle_commit`] or
+/// once the modifiers held when it started, other than Shift, are all released.
+///
+/// ```no_run
+/// # use pinnacle_api::prelude::*;
+/// # use pinnacle_api::*;
+/// # use pinnacle_api::window::CycleDirection;
+/// # let mut callback_vec = CallbackVec::new();
+/// input::keybind(
+///     &[Modifier::Alt],
+///     xkbcommon::xkb::keysyms::KEY_Tab,
+///     |_| window::cycle_focus(CycleDirection::Forward),
+///     &mut callback_vec,
+/// );
+/// input::keybind(
+///     &[Modifier::Alt, Modifier::Shift],
+///     xkbcommon::xkb::keysyms::KEY_Tab,
+///     |_| window::cycle_focus(CycleDirection::Backward),
+///     &mut callback_vec,
+/// );
+/// ```
+pub fn cycle_focus(direction: CycleDirection) {
+    send_msg(Msg::CycleMruFocus { direction });
+}
+
+/// Commit the cycle started by [`cycle_focus`], moving the focused window to the top of the
+/// order windows were last focused in.
+///
+/// This is only needed if no modifiers other than Shift were held when the cycle started.
+pub fn cycle_commit() {
+    send_msg(Msg::CommitMruCycle);
+}
+
+/// A direction to look for a neighboring window in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize)]
+pub enum Direction {
+    /// To the left.
+    Left,
+    /// To the right.
+    Right,
+    /// Above.
+    Up,
+    /// Below.
+    Down,
+}
+
+/// Swap the focused window with the closest window in `direction`.
+///
+/// Tiled windows trade places in the layout, and floating windows trade geometries.
+/// The closest window can be on another output, in which case the windows also trade tags,
+/// moving the focused window to that output's focused tag.
+///
+/// ```no_run
+/// # use pinnacle_api::window::{self, Direction};
+/// window::swap_in_direction(Direction::Left);
+/// ```
+pub fn swap_in_direction(direction: Direction) {
+    send_msg(Msg::SwapWindowInDirection { direction });
+}
+
+/// Wait for a window that meets `condition` to open.
+///
+/// This blocks until such a window opens or `timeout_ms` milliseconds pass,
+/// in which case an error is returned. Windows that are already open are not considered.
+///
+/// If multiple waits are pending, a new window will only resolve the oldest one
+/// whose condition it meets.
+pub fn wait_for(condition: WindowRuleCondition, timeout_ms: u64) -> anyhow::Result<WindowHandle> {
+    PendingWindowWait::send(condition, timeout_ms).wait()
+}
+
+/// A request to wait for a window that has been sent but not yet responded to.
+pub(crate) struct PendingWindowWait {
+    request_id: RequestId,
+    timeout_ms: u64,
+}
+
+impl PendingWindowWait {
+    /// Send a request to wait for a window without blocking.
+    pub(crate) fn send(condition: WindowRuleCondition, timeout_ms: u64) -> Self {
+        let request_id = send_request(Request::WaitForWindow {
+            condition: condition.0,
+            timeout_ms,
+        });
+
+        Self {
+            request_id,
+            timeout_ms,
+        }
+    }
+
+    /// Block until the compositor responds with a window or the wait times out.
+    pub(crate) fn wait(self) -> anyhow::Result<WindowHandle> {
+        let response = try_read_response(self.request_id)?;
+        WindowHandle::from_wait_response(response, self.timeout_ms)
+    }
+
+    /// Wait for the compositor to respond without blocking.
+    #[cfg(feature = "async")]
+    pub(crate) async fn wait_async(self) -> anyhow::Result<WindowHandle> {
+        let response = crate::r#async::read_response(self.request_id).await?;
+        WindowHandle::from_wait_response(response, self.timeout_ms)
+    }
+}
+
 /// Begin a window move.
 ///
 /// This will start a window move grab with the provided button on the window the pointer
 /// is currently hovering over. Once `button` is let go, the move will end.
 pub fn begin_move(button: MouseButton) {
     let msg = Msg::WindowMoveGrab {
-        button: button as u32,
+        button: button.into(),
     };
 
-    send_msg(msg).unwrap();
+    send_msg(msg);
+}
+
+/// What happens to a tiled window dragged with the move override when it's dropped.
+///
+/// See [`set_move_override`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, serde::Serialize)]
+pub enum MoveOverrideDrop {
+    /// Put the window back in its tile.
+    #[default]
+    ReturnToTile,
+    /// Keep the window floating where it was dropped if that's on another output,
+    /// otherwise put it back in its tile.
+    FloatOnOtherOutput,
+}
+
+/// Set the modifiers that let a tiled window be dragged freely for one move.
+///
+/// If these modifiers are held when a move starts, the tiled window floats until it's dropped,
+/// without changing whether it's tiled. `on_drop` decides where it ends up.
+/// Pass `None` for `modifiers` to turn this off.
+///
+/// The move still has to be started, so you'll likely want a mousebind with the same modifiers
+/// that calls [`begin_move`].
+///
+/// ```no_run
+/// # use pinnacle_api::prelude::*;
+/// # use pinnacle_api::*;
+/// window::set_move_override(
+///     Some(&[Modifier::Super, Modifier::Shift]),
+///     MoveOverrideDrop::ReturnToTile,
+/// );
+/// ```
+pub fn set_move_override(modifiers: Option<&[Modifier]>, on_drop: MoveOverrideDrop) {
+    let msg = Msg::SetMoveOverride {
+        modifiers: modifiers.map(|modifiers| modifiers.to_vec()),
+        on_drop,
+    };
+
+    send_msg(msg);
+}
+
+/// Set how close a window being moved has to get to an edge to snap to it, in logical pixels.
+///
+/// Floating windows snap to the edges of outputs, the area layer surfaces like bars leave free,
+/// and other floating windows. This is 10 by default. Set it to 0 to turn snapping off.
+pub fn set_snap_threshold(threshold: u32) {
+    send_msg(Msg::SetSnapThreshold { threshold });
 }
 
 /// Begin a window resize.
@@ -56,15 +632,15 @@ pub fn begin_move(button: MouseButton) {
 /// pointer is currently hovering over. Once `button` is let go, the resize will end.
 pub fn begin_resize(button: MouseButton) {
     let msg = Msg::WindowResizeGrab {
-        button: button as u32,
+        button: button.into(),
     };
 
-    send_msg(msg).unwrap();
+    send_msg(msg);
 }
 
 /// A handle to a window.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub struct WindowHandle(WindowId);
+pub struct WindowHandle(pub(crate) WindowId);
 
 /// Properties of a window, retrieved through [`WindowHandle::properties`].
 #[derive(Debug)]
@@ -83,12 +659,62 @@ pub struct WindowProperties {
     pub floating: Option<bool>,
     /// Whether the window is fullscreen, maximized, or neither.
     pub fullscreen_or_maximized: Option<FullscreenOrMaximized>,
+    /// The width and height increments the window wants to be resized in, like terminal cells.
+    ///
+    /// This is `None` if the window doesn't use resize increments.
+    pub resize_increments: Option<(i32, i32)>,
+    /// How many seconds it's been since the window was last focused, unfocused, or clicked.
+    ///
+    /// This is 0 for the focused window.
+    pub seconds_since_activity: Option<u64>,
+    /// Whether the window is keeping keybinds from triggering, like a VM viewer does
+    /// so keys go to the VM.
+    ///
+    /// This can only be true while the window is focused. Keybinds set with
+    /// [`input::keybind_force`][crate::input::keybind_force] still trigger.
+    pub keyboard_shortcuts_inhibited: Option<bool>,
+    /// The id of the window's group, which is the id of the first window that opened in it.
+    ///
+    /// Windows that share an X11 window group or an xdg parent chain, like an app and its
+    /// tool palettes and dialogs, are in the same group. Raising one raises them all.
+    pub group: Option<u32>,
+    /// The app id the window's sandbox, like Flatpak, gave it.
+    ///
+    /// Unlike [`class`](WindowProperties::class), this can't be set by the app itself,
+    /// so match on it to treat sandboxed apps differently. This is `None` for windows
+    /// that aren't sandboxed.
+    pub sandbox_app_id: Option<String>,
 }
 
 impl WindowHandle {
+    /// Get the window from the response to a request to wait for a window.
+    pub(crate) fn from_wait_response(
+        response: RequestResponse,
+        timeout_ms: u64,
+    ) -> anyhow::Result<Self> {
+        let RequestResponse::Window { window_id } = response else {
+            unreachable!()
+        };
+
+        window_id
+            .map(WindowHandle)
+            .ok_or_else(|| anyhow::anyhow!("no matching window opened within {timeout_ms}ms"))
+    }
+
+    /// Get this window's id.
+    ///
+    /// This stays the same for as long as the window is open.
+    /// It's `None` if this handle doesn't refer to a valid window.
+    pub fn id(&self) -> Option<u32> {
+        match self.0 {
+            WindowId::None => None,
+            WindowId::Some(id) => Some(id),
+        }
+    }
+
     /// Toggle this window between floating and tiled.
     pub fn toggle_floating(&self) {
-        send_msg(Msg::ToggleFloating { window_id: self.0 }).unwrap();
+        send_msg(Msg::ToggleFloating { window_id: self.0 });
     }
 
     /// Toggle this window's fullscreen status.
@@ -97,7 +723,7 @@ impl WindowHandle {
     /// If used while fullscreen, it becomes unfullscreen.
     /// If used while maximized, it becomes fullscreen.
     pub fn toggle_fullscreen(&self) {
-        send_msg(Msg::ToggleFullscreen { window_id: self.0 }).unwrap();
+        send_msg(Msg::ToggleFullscreen { window_id: self.0 });
     }
 
     /// Toggle this window's maximized status.
@@ -106,7 +732,40 @@ impl WindowHandle {
     /// If used while maximized, it becomes unmaximized.
     /// If used while fullscreen, it becomes maximized.
     pub fn toggle_maximized(&self) {
-        send_msg(Msg::ToggleMaximized { window_id: self.0 }).unwrap();
+        send_msg(Msg::ToggleMaximized { window_id: self.0 });
+    }
+
+    /// Make this window floating if `floating` is true, or tiled if it isn't.
+    ///
+    /// This does nothing if the window already is, so unlike [`toggle_floating`][Self::toggle_floating]
+    /// it's safe to use more than once.
+    pub fn set_floating(&self, floating: bool) {
+        send_msg(Msg::SetFloating {
+            window_id: self.0,
+            floating,
+        });
+    }
+
+    /// Make this window fullscreen or not.
+    ///
+    /// This does nothing if the window already is. Making a maximized window fullscreen
+    /// unmaximizes it.
+    pub fn set_fullscreen(&self, fullscreen: bool) {
+        send_msg(Msg::SetFullscreen {
+            window_id: self.0,
+            fullscreen,
+        });
+    }
+
+    /// Make this window maximized or not.
+    ///
+    /// This does nothing if the window already is. Making a fullscreen window maximized
+    /// unfullscreens it.
+    pub fn set_maximized(&self, maximized: bool) {
+        send_msg(Msg::SetMaximized {
+            window_id: self.0,
+            maximized,
+        });
     }
 
     /// Set this window's size. None parameters will be ignored.
@@ -115,13 +774,107 @@ impl WindowHandle {
             window_id: self.0,
             width,
             height,
-        })
-        .unwrap();
+        });
+    }
+
+    /// Move this window by `dx` and `dy` logical pixels.
+    ///
+    /// This only moves floating windows that aren't fullscreen or maximized.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use pinnacle_api::input::{keybind, Modifier};
+    /// use pinnacle_api::window;
+    ///
+    /// # let mut callback_vec = pinnacle_api::CallbackVec::default();
+    /// // Nudge the focused window left
+    /// keybind(&[Modifier::Super, Modifier::Shift], 'h', |_| {
+    ///     if let Some(win) = window::get_focused() {
+    ///         win.move_by(-20, 0);
+    ///     }
+    /// }, &mut callback_vec);
+    /// ```
+    pub fn move_by(&self, dx: i32, dy: i32) {
+        send_msg(Msg::MoveWindowBy {
+            window_id: self.0,
+            dx,
+            dy,
+        });
+    }
+
+    /// Grow this window by `dx` and `dy` logical pixels, or shrink it with negative values.
+    ///
+    /// The window's top left corner stays put, and its size is kept within the sizes it
+    /// supports. Tiled windows move the split between the master window and the rest by `dx`
+    /// instead, growing the window if it's the master. Fullscreen and maximized windows aren't
+    /// resized.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use pinnacle_api::input::{keybind, Modifier};
+    /// use pinnacle_api::window;
+    ///
+    /// # let mut callback_vec = pinnacle_api::CallbackVec::default();
+    /// // Make the focused window narrower
+    /// keybind(&[Modifier::Super, Modifier::Ctrl], 'h', |_| {
+    ///     if let Some(win) = window::get_focused() {
+    ///         win.resize_by(-20, 0);
+    ///     }
+    /// }, &mut callback_vec);
+    /// ```
+    pub fn resize_by(&self, dx: i32, dy: i32) {
+        send_msg(Msg::ResizeWindowBy {
+            window_id: self.0,
+            dx,
+            dy,
+        });
+    }
+
+    /// Place this window at `x` and `y` with a size of `w` by `h`, all in logical pixels.
+    ///
+    /// The size is kept within the sizes the window supports. Tiled windows get this geometry
+    /// once they're floating.
+    pub fn set_geometry(&self, x: i32, y: i32, w: i32, h: i32) {
+        send_msg(Msg::SetWindowGeometry {
+            window_id: self.0,
+            x,
+            y,
+            width: w,
+            height: h,
+        });
     }
 
     /// Send a close event to this window.
     pub fn close(&self) {
-        send_msg(Msg::CloseWindow { window_id: self.0 }).unwrap();
+        send_msg(Msg::CloseWindow { window_id: self.0 });
+    }
+
+    /// Flash a ring around this window to help you find it.
+    ///
+    /// The ring starts at `color`, which is RGBA with each channel from 0.0 to 1.0,
+    /// and fades out over `duration_ms`. It follows the window if it moves.
+    pub fn flash(&self, duration_ms: u64, color: [f32; 4]) {
+        send_msg(Msg::FlashWindow {
+            window_id: self.0,
+            duration_ms,
+            color,
+        });
+    }
+
+    /// Raise this window to the top of the stack.
+    ///
+    /// Windows it stacks with, like its dialogs, are raised along with it.
+    pub fn raise(&self) {
+        send_msg(Msg::RaiseWindow { window_id: self.0 });
+    }
+
+    /// Give this window keyboard focus and raise it.
+    ///
+    /// This does nothing if the window isn't on an active tag.
+    pub fn focus(&self) {
+        send_msg(Msg::FocusWindow { window_id: self.0 });
     }
 
     /// Get this window's [`WindowProperties`].
@@ -134,6 +887,11 @@ impl WindowHandle {
             focused,
             floating,
             fullscreen_or_maximized,
+            resize_increments,
+            seconds_since_activity,
+            keyboard_shortcuts_inhibited,
+            group,
+            sandbox_app_id,
         } = request(Request::GetWindowProps { window_id: self.0 })
         else {
             unreachable!()
@@ -147,6 +905,11 @@ impl WindowHandle {
             focused,
             floating,
             fullscreen_or_maximized,
+            resize_increments,
+            seconds_since_activity,
+            keyboard_shortcuts_inhibited,
+            group,
+            sandbox_app_id,
         }
     }
 
@@ -157,7 +920,22 @@ impl WindowHandle {
             tag_id: tag.0,
         };
 
-        send_msg(msg).unwrap();
+        send_msg(msg);
+    }
+
+    /// Make this window follow the active tags on its output, or stop it from doing so.
+    ///
+    /// Whenever the active tags on the window's output change, it's moved onto them,
+    /// so it's tiled on whatever tag you're viewing. This is useful for something like
+    /// a music player you want everywhere. Fullscreen windows don't follow until they
+    /// aren't fullscreen anymore.
+    ///
+    /// Moving the window to a tag or toggling a tag on it stops it from following.
+    pub fn set_follow_active_tag(&self, follow: bool) {
+        send_msg(Msg::SetFollowActiveTag {
+            window_id: self.0,
+            follow,
+        });
     }
 
     /// Move this window to `tag`.
@@ -169,12 +947,12 @@ impl WindowHandle {
             tag_id: tag.0,
         };
 
-        send_msg(msg).unwrap();
+        send_msg(msg);
     }
 }
 
 /// Whether or not a window is floating or tiled.
-#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum FloatingOrTiled {
     /// The window is floating.
     ///
@@ -2,9 +2,15 @@
 
 use std::num::NonZeroU32;
 
-use crate::{msg::Msg, output::OutputHandle, send_msg, tag::TagHandle};
+use crate::{
+    msg::{Args, Msg},
+    output::OutputHandle,
+    send_msg,
+    tag::TagHandle,
+    CallbackVec, InsertPosition,
+};
 
-use super::{FloatingOrTiled, FullscreenOrMaximized};
+use super::{FloatingOrTiled, FullscreenOrMaximized, WindowHandle};
 
 /// Add a window rule.
 pub fn add(cond: WindowRuleCondition, rule: WindowRule) {
@@ -13,7 +19,87 @@ pub fn add(cond: WindowRuleCondition, rule: WindowRule) {
         rule: rule.0,
     };
 
-    send_msg(msg).unwrap();
+    send_msg(msg);
+}
+
+/// A state a window asked to be put in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum RequestedState {
+    /// The window wants to be fullscreen.
+    Fullscreen,
+    /// The window wants to be maximized.
+    Maximized,
+    /// The window wants to be minimized.
+    Minimized,
+}
+
+/// What to do with a window's [`RequestedState`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum StateRequestDecision {
+    /// Put the window in the state it asked for.
+    Allow,
+    /// Keep the window in its current state.
+    Deny,
+    /// Put the window in this state instead.
+    Modify {
+        /// The state to put the window in.
+        state: FullscreenOrMaximized,
+    },
+}
+
+/// Decide what happens when a window that meets `cond` asks to be fullscreened, maximized,
+/// or minimized.
+///
+/// `callback` gets the window and the state it asked for, and returns what to do with the request.
+/// If it takes longer than a second to decide, the request will be allowed.
+///
+/// If multiple handlers have conditions the window meets, only the first one added is called.
+///
+/// # Example
+/// ```no_run
+/// use pinnacle_api::prelude::*;
+/// use pinnacle_api::window::rules::{self, RequestedState, StateRequestDecision, WindowRuleCondition};
+/// # let mut callback_vec = pinnacle_api::CallbackVec::new();
+///
+/// // Make Firefox maximize instead of going fullscreen
+/// rules::on_state_request(
+///     WindowRuleCondition::new().class(&["firefox"]),
+///     |_window, state, _| match state {
+///         RequestedState::Fullscreen => StateRequestDecision::Modify {
+///             state: FullscreenOrMaximized::Maximized,
+///         },
+///         _ => StateRequestDecision::Allow,
+///     },
+///     &mut callback_vec,
+/// );
+/// ```
+pub fn on_state_request<'a, F>(
+    cond: WindowRuleCondition,
+    mut callback: F,
+    callback_vec: &mut CallbackVec<'a>,
+) where
+    F: FnMut(WindowHandle, RequestedState, &mut CallbackVec) -> StateRequestDecision + 'a,
+{
+    let args_callback = move |args: Option<Args>, callback_vec: &mut CallbackVec<'_>| {
+        if let Some(Args::StateRequest {
+            window_id,
+            token,
+            state,
+        }) = args
+        {
+            let decision = callback(WindowHandle(window_id), state, callback_vec);
+            send_msg(Msg::DecideStateRequest { token, decision });
+        }
+    };
+
+    let callback_id = callback_vec.insert(args_callback);
+
+    let msg = Msg::AddStateRequestHandler {
+        cond: cond.0,
+        callback_id,
+    };
+
+    send_msg(msg);
 }
 
 /// A window rule.
@@ -73,11 +159,58 @@ impl WindowRule {
         self.0.location = Some((x, y));
         self
     }
+
+    /// This rule will make tiled layouts round the window's size down to its resize increments,
+    /// giving the leftover space to a neighboring window.
+    ///
+    /// This is useful for terminals to prevent gaps from partial cells.
+    /// Windows that don't have resize increments are unaffected.
+    pub fn snap_to_increments(mut self, snap: bool) -> Self {
+        self.0.snap_to_increments = Some(snap);
+        self
+    }
+
+    /// This rule will deny any requests from the window to make itself fullscreen.
+    ///
+    /// You can still fullscreen the window from your config.
+    /// Use [`on_state_request`] if you need to decide per request.
+    pub fn deny_fullscreen(mut self, deny: bool) -> Self {
+        self.0.deny_fullscreen = Some(deny);
+        self
+    }
+
+    /// This rule will make the window follow the active tags on its output.
+    ///
+    /// See [`WindowHandle::set_follow_active_tag`](crate::window::WindowHandle::set_follow_active_tag)
+    /// for more information.
+    pub fn follow_active_tag(mut self, follow: bool) -> Self {
+        self.0.follow_active_tag = Some(follow);
+        self
+    }
+
+    /// This rule will put windows at `position` in the layout instead of where
+    /// [`set_insert_position`](crate::set_insert_position) says.
+    ///
+    /// Use [`InsertPosition::Prepend`] to always open a window as the master.
+    pub fn insert_position(mut self, position: InsertPosition) -> Self {
+        self.0.insert_position = Some(position);
+        self
+    }
+
+    /// This rule will exempt the window from the background throttle, so it gets every frame
+    /// callback even while hidden or covered.
+    ///
+    /// Use this for apps that misbehave without frame callbacks.
+    /// See [`set_background_throttle`](crate::set_background_throttle).
+    pub fn never_throttle(mut self, never: bool) -> Self {
+        self.0.never_throttle = Some(never);
+        self
+    }
 }
 
 /// A condition for a [`WindowRule`] to apply to a window.
 #[derive(Default, Debug)]
-pub struct WindowRuleCondition(crate::msg::WindowRuleCondition);
+pub struct WindowRuleCondition(pub(crate) crate::msg::WindowRuleCondition);
 
 impl WindowRuleCondition {
     /// Create a new, empty `WindowRuleCondition`.
@@ -132,4 +265,32 @@ impl WindowRuleCondition {
         self.0.tag = Some(tags.iter().map(|tag| tag.0).collect());
         self
     }
+
+    /// This condition requires that the window is in the group with the given id.
+    ///
+    /// Get a window's group id from its [`WindowProperties`][crate::window::WindowProperties].
+    ///
+    /// When used in a top level condition or inside of [`WindowRuleCondition::all`],
+    /// *all* groups must match (this is impossible).
+    ///
+    /// When used in [`WindowRuleCondition::any`], at least one of the
+    /// provided groups must match.
+    pub fn group(mut self, groups: &[u32]) -> Self {
+        self.0.group = Some(groups.to_vec());
+        self
+    }
+
+    /// This condition requires that the window's sandbox, like Flatpak, gave it the given app id.
+    ///
+    /// Windows that aren't sandboxed never match this.
+    ///
+    /// When used in a top level condition or inside of [`WindowRuleCondition::all`],
+    /// *all* app ids must match (this is impossible).
+    ///
+    /// When used in [`WindowRuleCondition::any`], at least one of the
+    /// provided app ids must match.
+    pub fn sandbox_app_id(mut self, app_ids: &[&str]) -> Self {
+        self.0.sandbox_app_id = Some(app_ids.iter().map(|s| s.to_string()).collect());
+        self
+    }
 }
@@ -35,13 +35,16 @@
 //! For an example, look at the Lua implementation in the repository.
 
 pub mod handlers;
+pub mod limits;
 pub mod msg;
+pub mod stats;
 
 use std::{
     io::{self, Read, Write},
     os::unix::net::{UnixListener, UnixStream},
+    panic::AssertUnwindSafe,
     path::Path,
-    sync::{Arc, Mutex},
+    sync::{atomic::AtomicU64, Arc, Mutex},
 };
 
 use anyhow::Context;
@@ -51,41 +54,86 @@ use smithay::reexports::calloop::{
 };
 use sysinfo::{ProcessRefreshKind, RefreshKind, SystemExt};
 
-use self::msg::{Msg, OutgoingMsg};
+use crate::window::{state_request::PendingStateRequest, wait::WindowWait};
+
+use self::{
+    limits::{ClientLimits, ClientUsages},
+    msg::{Msg, OutgoingMsg},
+    stats::ApiStats,
+};
 
 pub const SOCKET_NAME: &str = "pinnacle_socket";
 
-/// Handle a config process.
+/// A message from a client, along with where it came from.
+pub struct ClientMsg {
+    /// The message, or `None` once the client has closed its stream.
+    pub msg: Option<Msg>,
+    /// The stream the message came from. Requests are responded to through this.
+    pub stream: Arc<Mutex<UnixStream>>,
+    /// Whether the client identified itself as a one-off client with [`Msg::OneOffClient`].
+    ///
+    /// One-off clients never become the config, so they don't replace its stream.
+    pub one_off: bool,
+}
+
+/// Handle a client.
 ///
-/// `stream` is the incoming stream where messages will be received,
-/// and `sender` sends decoded messages to the main state's handler.
+/// `stream` is the incoming stream where messages will be received, `client_stream` is the
+/// same stream used to respond to the client, and `sender` sends decoded messages to the
+/// main state's handler.
 fn handle_client(
     mut stream: UnixStream,
-    sender: Sender<Msg>,
+    client_stream: Arc<Mutex<UnixStream>>,
+    sender: Sender<ClientMsg>,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    let mut one_off = false;
+
+    // Let the main state know the client is gone so it can drop anything waiting on it
+    let closed = |one_off| {
+        let _ = sender.send(ClientMsg {
+            msg: None,
+            stream: client_stream.clone(),
+            one_off,
+        });
+    };
+
     loop {
         let mut len_marker_bytes = [0u8; 4];
         if let Err(err) = stream.read_exact(&mut len_marker_bytes) {
-            if err.kind() == io::ErrorKind::UnexpectedEof {
-                tracing::warn!("stream closed: {}", err);
-                stream.shutdown(std::net::Shutdown::Both)?;
-                break Ok(());
-            }
+            closed(one_off);
+            return close_client(&stream, err);
         };
 
         let len_marker = u32::from_ne_bytes(len_marker_bytes);
         let mut msg_bytes = vec![0u8; len_marker as usize];
 
         if let Err(err) = stream.read_exact(msg_bytes.as_mut_slice()) {
-            if err.kind() == io::ErrorKind::UnexpectedEof {
-                tracing::warn!("stream closed: {}", err);
-                stream.shutdown(std::net::Shutdown::Both)?;
-                break Ok(());
-            }
+            closed(one_off);
+            return close_client(&stream, err);
         };
         let msg: Msg = rmp_serde::from_slice(msg_bytes.as_slice())?; // TODO: handle error
 
-        sender.send(msg)?;
+        if let Msg::OneOffClient = msg {
+            one_off = true;
+        }
+
+        sender.send(ClientMsg {
+            msg: Some(msg),
+            stream: client_stream.clone(),
+            one_off,
+        })?;
+    }
+}
+
+/// Shut down a client's stream after reading from it failed.
+fn close_client(stream: &UnixStream, err: io::Error) -> Result<(), Box<dyn std::error::Error>> {
+    stream.shutdown(std::net::Shutdown::Both)?;
+
+    if err.kind() == io::ErrorKind::UnexpectedEof {
+        tracing::warn!("stream closed: {}", err);
+        Ok(())
+    } else {
+        Err(err.into())
     }
 }
 
@@ -94,13 +142,13 @@ pub struct PinnacleSocketSource {
     /// The socket listener
     socket: Generic<UnixListener>,
     /// The sender that will send messages from clients to the main event loop.
-    sender: Sender<Msg>,
+    sender: Sender<ClientMsg>,
 }
 
 impl PinnacleSocketSource {
     /// Create a loop source that listens for connections to the provided `socket_dir`.
     /// This will also set PINNACLE_SOCKET for use in API implementations.
-    pub fn new(sender: Sender<Msg>, socket_dir: &Path) -> anyhow::Result<Self> {
+    pub fn new(sender: Sender<ClientMsg>, socket_dir: &Path) -> anyhow::Result<Self> {
         tracing::debug!("Creating socket source for dir {socket_dir:?}");
 
         let system = sysinfo::System::new_with_specifics(
@@ -190,8 +238,38 @@ pub fn send_to_client(
     Ok(())
 }
 
+/// Handle a client on its own thread with `handle`, so it doesn't block the main one.
+///
+/// If `handle` panics, the panic hook has already written a crash report, so the panic is
+/// turned into a disconnect. The stream is shut down so the client doesn't hang, and
+/// `disconnected` is run so the main state can drop anything waiting on the client.
+pub(crate) fn spawn_client_thread(
+    stream: UnixStream,
+    handle: impl FnOnce(UnixStream) -> Result<(), Box<dyn std::error::Error>> + Send + 'static,
+    disconnected: impl FnOnce() + Send + 'static,
+) -> io::Result<std::thread::JoinHandle<()>> {
+    let panic_stream = stream.try_clone()?;
+
+    std::thread::Builder::new()
+        .name("api client".to_string())
+        .spawn(move || {
+            let result = std::panic::catch_unwind(AssertUnwindSafe(|| handle(stream)));
+
+            match result {
+                Ok(Ok(())) => (),
+                Ok(Err(err)) => tracing::error!("handle_client errored: {err}"),
+                Err(_) => {
+                    tracing::error!("handle_client panicked, disconnecting the client");
+                    let _ = panic_stream.shutdown(std::net::Shutdown::Both);
+                    disconnected();
+                }
+            }
+        })
+}
+
 impl EventSource for PinnacleSocketSource {
-    type Event = UnixStream;
+    /// Clients are handled on their own threads, so there's nothing to give to the callback.
+    type Event = ();
 
     type Metadata = ();
 
@@ -203,7 +281,7 @@ impl EventSource for PinnacleSocketSource {
         &mut self,
         readiness: calloop::Readiness,
         token: calloop::Token,
-        mut callback: F,
+        _callback: F,
     ) -> Result<calloop::PostAction, Self::Error>
     where
         F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
@@ -212,18 +290,21 @@ impl EventSource for PinnacleSocketSource {
             .process_events(readiness, token, |_readiness, listener| {
                 while let Ok((stream, _sock_addr)) = listener.accept() {
                     let sender = self.sender.clone();
-                    let callback_stream = stream.try_clone()?;
-
-                    callback(callback_stream, &mut ());
-
-                    // Handle the client in another thread as to not block the main one.
-                    //
-                    // No idea if this is even needed or if it's premature optimization.
-                    std::thread::spawn(move || {
-                        if let Err(err) = handle_client(stream, sender) {
-                            tracing::error!("handle_client errored: {err}");
-                        }
-                    });
+                    let client_stream = Arc::new(Mutex::new(stream.try_clone()?));
+                    let closed_stream = client_stream.clone();
+                    let closed_sender = self.sender.clone();
+
+                    spawn_client_thread(
+                        stream,
+                        move |stream| handle_client(stream, client_stream, sender),
+                        move || {
+                            let _ = closed_sender.send(ClientMsg {
+                                msg: None,
+                                stream: closed_stream,
+                                one_off: false,
+                            });
+                        },
+                    )?;
                 }
 
                 Ok(PostAction::Continue)
@@ -253,14 +334,86 @@ impl EventSource for PinnacleSocketSource {
 
 pub struct ApiState {
     // TODO: this may not need to be in an arc mutex because of the move to async
-    /// The stream API messages are being sent through.
+    /// The stream of the config, which callbacks are sent through.
+    ///
+    /// This is set to the stream of the first client other than a one-off client
+    /// that sends a message.
     pub stream: Option<Arc<Mutex<UnixStream>>>,
     /// A token used to remove the socket source from the event loop on config restart.
     pub socket_token: Option<RegistrationToken>,
     /// The sending channel used to send API messages received from the socket source to a handler.
-    pub tx_channel: Sender<Msg>,
+    pub tx_channel: Sender<ClientMsg>,
     /// A channel used to ping the future in the event loop to drop and kill the child.
     pub kill_channel: Option<async_channel::Sender<()>>,
     /// A receiving channel that will cause the config process to be dropped and thus killed.
     pub future_channel: Option<async_channel::Receiver<()>>,
+    /// Requests waiting for a window that matches a condition to open.
+    pub window_waits: Vec<WindowWait>,
+    /// Client state requests waiting on a decision from the config.
+    pub pending_state_requests: Vec<PendingStateRequest>,
+    /// The token that will be given to the next state request sent to the config.
+    pub next_state_request_token: u32,
+    /// The serial of the last callback sent to the config.
+    ///
+    /// This is shared with futures that call callbacks and is replaced when a new config connects.
+    pub callback_serial: Arc<AtomicU64>,
+    /// Limits on what clients can send, from the metaconfig.
+    pub client_limits: ClientLimits,
+    /// What each client has sent, to check against `client_limits`.
+    pub client_usages: ClientUsages,
+    /// How long requests have taken to handle.
+    pub stats: ApiStats,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use calloop::{channel::Event, EventLoop};
+
+    use super::*;
+
+    #[test]
+    fn event_loop_blocks_while_idle() {
+        let dir = std::env::temp_dir().join(format!("pinnacle-idle-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // The same API sources the backends run without a timeout
+        let mut event_loop = EventLoop::<u32>::try_new().unwrap();
+        let (sender, receiver) = calloop::channel::channel::<ClientMsg>();
+        event_loop
+            .handle()
+            .insert_source(receiver, |event, _, msgs| {
+                if let Event::Msg(_) = event {
+                    *msgs += 1;
+                }
+            })
+            .unwrap();
+        event_loop
+            .handle()
+            .insert_source(
+                PinnacleSocketSource::new(sender, &dir).unwrap(),
+                |_, _, _| (),
+            )
+            .unwrap();
+
+        let mut msgs = 0;
+        let idle_time = Duration::from_millis(200);
+        let start = Instant::now();
+        event_loop.dispatch(Some(idle_time), &mut msgs).unwrap();
+        assert!(start.elapsed() >= idle_time);
+        assert_eq!(msgs, 0);
+
+        // A client connecting and disconnecting wakes the loop up
+        drop(UnixStream::connect(dir.join(SOCKET_NAME)).unwrap());
+        let start = Instant::now();
+        while msgs == 0 && start.elapsed() < Duration::from_secs(5) {
+            event_loop
+                .dispatch(Some(Duration::from_secs(5)), &mut msgs)
+                .unwrap();
+        }
+        assert_eq!(msgs, 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
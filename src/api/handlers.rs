@@ -1,4 +1,9 @@
-use std::ffi::OsString;
+use std::{
+    ffi::OsString,
+    os::unix::net::UnixStream,
+    sync::{atomic::AtomicU64, Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 use async_process::Stdio;
 use futures_lite::{AsyncBufReadExt, StreamExt};
@@ -7,58 +12,182 @@ use smithay::{
     input::keyboard::XkbConfig,
     reexports::wayland_protocols::xdg::shell::server::xdg_toplevel::ResizeEdge,
     utils::{Point, Rectangle, SERIAL_COUNTER},
-    wayland::{compositor, shell::xdg::XdgToplevelSurfaceData},
+    wayland::{
+        compositor, keyboard_shortcuts_inhibit::KeyboardShortcutsInhibitorSeat,
+        shell::xdg::XdgToplevelSurfaceData,
+    },
 };
 
 use crate::{
-    api::msg::{
-        Args, CallbackId, KeyIntOrString, Msg, OutgoingMsg, Request, RequestId, RequestResponse,
+    api::{
+        limits::LimitCheck,
+        msg::{
+            Args, CallbackId, InputDeviceProps, KeybindProps, ModifierMask, Msg, OutgoingMsg,
+            Request, RequestId, RequestResponse,
+        },
+        stats::BUCKET_BOUNDS_US,
+        ClientMsg,
     },
+    backend::Backend,
     config::ConnectorSavedState,
     focus::FocusTarget,
-    tag::Tag,
-    window::WindowElement,
+    grab::move_grab::MoveOverride,
+    input::{gesture::Pinchbind, libinput::LibinputSetting, Keybind},
+    output::OutputName,
+    window::{placement::MoveOrigin, state_request::StateRequestHandler, WindowElement},
 };
 
 use crate::state::{State, WithState};
 
 impl State {
+    /// Handle a message from a client.
+    ///
+    /// If the client isn't a one-off client and isn't the config yet, it becomes the config,
+    /// replacing the old one.
+    pub fn handle_client_msg(&mut self, client_msg: ClientMsg) {
+        let ClientMsg {
+            msg,
+            stream,
+            one_off,
+        } = client_msg;
+
+        let Some(msg) = msg else {
+            self.cancel_prompt_from(&stream);
+            self.clear_window_waits_from(&stream);
+            return;
+        };
+
+        if self.is_draining_stream(&stream) {
+            tracing::trace!("Dropping {msg:?} from replaced config");
+            return;
+        }
+
+        if let LimitCheck::Drop = self.check_client_limits(&msg, &stream, one_off) {
+            return;
+        }
+
+        if !one_off {
+            self.set_config_stream(&stream);
+        }
+
+        self.activity_state.handling_api_msg = true;
+        self.handle_msg(msg, stream);
+        self.activity_state.handling_api_msg = false;
+    }
+
+    /// Make `stream` the stream of the config, shutting down the old one.
+    fn set_config_stream(&mut self, stream: &Arc<Mutex<UnixStream>>) {
+        if self
+            .api_state
+            .stream
+            .as_ref()
+            .is_some_and(|old_stream| Arc::ptr_eq(old_stream, stream))
+        {
+            return;
+        }
+
+        if let Some(old_stream) = self.api_state.stream.replace(stream.clone()) {
+            if let Err(err) = old_stream
+                .lock()
+                .expect("Couldn't lock old stream")
+                .shutdown(std::net::Shutdown::Both)
+            {
+                tracing::warn!("Failed to shut down old config stream: {err}");
+            }
+
+            // Any pending waits belonged to the old client
+            self.clear_window_waits_from(&old_stream);
+        }
+
+        // Any pending requests belonged to the old client
+        self.allow_pending_state_requests();
+        self.api_state.callback_serial = Arc::new(AtomicU64::new(0));
+
+        if let Some(message) = self.backend.software_rendering_warning() {
+            if let Err(err) = crate::api::send_to_client(
+                &mut stream.lock().expect("Couldn't lock stream"),
+                &OutgoingMsg::Warning { message },
+            ) {
+                tracing::warn!("Failed to send warning to client: {err}");
+            }
+        }
+    }
+
     /// Handle a client message.
-    pub fn handle_msg(&mut self, msg: Msg) {
+    ///
+    /// `stream` is the stream of the client that sent it.
+    pub fn handle_msg(&mut self, msg: Msg, stream: Arc<Mutex<UnixStream>>) {
         tracing::trace!("Got {msg:?}");
 
         match msg {
             Msg::SetKeybind {
                 key,
                 modifiers,
+                edge,
                 callback_id,
+                group,
+                description,
+                layer,
+                passthrough,
+                force,
+                repeats,
+                data,
             } => {
-                let key = match key {
-                    KeyIntOrString::Int(num) => {
-                        tracing::info!("set keybind: {:?}, raw {}", modifiers, num);
-                        num
-                    }
-                    KeyIntOrString::String(s) => {
-                        if s.chars().count() == 1 {
-                            let Some(ch) = s.chars().next() else { unreachable!() };
-                            let raw = xkbcommon::xkb::Keysym::from_char(ch).raw();
-                            tracing::info!("set keybind: {:?}, {:?} (raw {})", modifiers, ch, raw);
-                            raw
-                        } else {
-                            let raw = xkbcommon::xkb::keysym_from_name(
-                                &s,
-                                xkbcommon::xkb::KEYSYM_NO_FLAGS,
-                            )
-                            .raw();
-                            tracing::info!("set keybind: {:?}, {:?}", modifiers, raw);
-                            raw
-                        }
-                    }
-                };
+                let key = key.to_keysym();
+                tracing::info!(
+                    "set keybind: {:?}, {:?} (raw {}) on {:?} in layer {:?}",
+                    modifiers,
+                    key,
+                    key.raw(),
+                    edge,
+                    layer
+                );
 
-                self.input_state
-                    .keybinds
-                    .insert((modifiers.into(), key.into()), callback_id);
+                self.input_state.keybinds.insert(
+                    (layer, modifiers.into(), key, edge),
+                    Keybind {
+                        callback_id,
+                        group,
+                        description,
+                        passthrough,
+                        force,
+                        repeats,
+                        data,
+                    },
+                );
+            }
+            Msg::RemoveKeybind {
+                key,
+                modifiers,
+                edge,
+                layer,
+            } => {
+                let key = key.to_keysym();
+                tracing::info!(
+                    "remove keybind: {:?}, {:?} on {:?} in layer {:?}",
+                    modifiers,
+                    key,
+                    edge,
+                    layer
+                );
+
+                self.input_state.keybinds.remove(&(
+                    layer,
+                    ModifierMask::from(modifiers),
+                    key,
+                    edge,
+                ));
+            }
+            Msg::EnterBindLayer { layer } => {
+                self.enter_bind_layer(layer);
+            }
+            Msg::SetBindLayerForwardsUnbound { layer, forward } => {
+                let forward_unbound = &mut self.input_state.bind_layer.forward_unbound;
+                if forward {
+                    forward_unbound.insert(layer);
+                } else {
+                    forward_unbound.remove(&layer);
+                }
             }
             Msg::SetMousebind {
                 modifiers,
@@ -71,15 +200,77 @@ impl State {
                     .mousebinds
                     .insert((modifiers.into(), button, edge), callback_id);
             }
+            Msg::SetScrollbind {
+                modifiers,
+                direction,
+                callback_id,
+            } => {
+                self.input_state
+                    .scrollbinds
+                    .insert((modifiers.into(), direction), callback_id);
+            }
+            Msg::SetGesturebind {
+                fingers,
+                direction,
+                callback_id,
+            } => {
+                self.input_state
+                    .gesture
+                    .swipebinds
+                    .insert((fingers, direction), callback_id);
+            }
+            Msg::SetPinchbind {
+                fingers,
+                direction,
+                threshold,
+                callback_id,
+            } => {
+                self.input_state.gesture.pinchbinds.insert(
+                    (fingers, direction),
+                    Pinchbind {
+                        callback_id,
+                        threshold: threshold.abs(),
+                    },
+                );
+            }
+            Msg::SetMediaKeysEnabled { enabled } => {
+                self.input_state.media_keys.enabled = enabled;
+            }
+            Msg::SetMediaKeyCommand { key, command } => {
+                let key = key.to_keysym();
+                if command.is_empty() {
+                    self.input_state.media_keys.commands.remove(&key);
+                } else {
+                    self.input_state.media_keys.commands.insert(key, command);
+                }
+            }
+            Msg::SetMoveOverride { modifiers, on_drop } => {
+                self.move_override = modifiers.map(|modifiers| MoveOverride {
+                    modifiers: modifiers.into(),
+                    on_drop,
+                });
+            }
+            Msg::LockPointerToOutput { output_name } => {
+                self.lock_pointer_to_output(output_name);
+            }
+            Msg::SetSnapThreshold { threshold } => {
+                self.snap_threshold = threshold;
+            }
+            Msg::SetEdgeResistance { pixels } => {
+                self.input_state.pointer_barrier.edge_resistance = pixels as f64;
+            }
+            Msg::SetDoubleClickInterval { interval_ms } => {
+                self.input_state.double_click.interval = Duration::from_millis(interval_ms);
+            }
+            Msg::SetCursorWarp { warp } => {
+                self.input_state.cursor_warp.warp = warp;
+            }
+            Msg::SetTouchGestures { gestures } => {
+                self.input_state.touch.gestures = gestures;
+            }
             Msg::CloseWindow { window_id } => {
                 if let Some(window) = window_id.window(self) {
-                    match window {
-                        WindowElement::Wayland(window) => window.toplevel().send_close(),
-                        WindowElement::X11(surface) => {
-                            surface.close().expect("failed to close x11 win");
-                        }
-                        WindowElement::X11OverrideRedirect(_) => (),
-                    }
+                    window.close();
                 }
             }
 
@@ -87,7 +278,9 @@ impl State {
                 command,
                 callback_id,
             } => {
-                self.handle_spawn(command, callback_id);
+                let spawn_token =
+                    callback_id.and_then(|_| self.api_state.client_usages.spawn_token(&stream));
+                self.handle_spawn(command, callback_id, spawn_token);
             }
             Msg::SetEnv { key, value } => std::env::set_var(key, value),
 
@@ -126,15 +319,80 @@ impl State {
                     self.schedule_render(&output);
                 }
             }
+            Msg::MoveWindowBy { window_id, dx, dy } => {
+                let Some(window) = window_id.window(self) else { return };
+                let is_floating = window.with_state(|state| {
+                    state.floating_or_tiled.is_floating()
+                        && state.fullscreen_or_maximized.is_neither()
+                });
+                if !is_floating {
+                    return;
+                }
+
+                let mut geo = self.window_floating_geometry(&window);
+                geo.loc += Point::from((dx, dy));
+                self.set_window_floating_geometry(&window, geo);
+            }
+            Msg::ResizeWindowBy { window_id, dx, dy } => {
+                let Some(window) = window_id.window(self) else { return };
+                let (floating_or_tiled, fullscreen_or_maximized) = window
+                    .with_state(|state| (state.floating_or_tiled, state.fullscreen_or_maximized));
+                if !fullscreen_or_maximized.is_neither() {
+                    return;
+                }
+
+                if floating_or_tiled.is_tiled() {
+                    crate::grab::layout_resize::resize_split_by(self, &window, dx);
+                    return;
+                }
+
+                let mut geo = self.window_floating_geometry(&window);
+                // Sizes can't be negative, so the deltas can't be turned into one
+                geo.size.w += dx;
+                geo.size.h += dy;
+                self.set_window_floating_geometry(&window, geo);
+            }
+            Msg::SetWindowGeometry {
+                window_id,
+                x,
+                y,
+                width,
+                height,
+            } => {
+                let Some(window) = window_id.window(self) else { return };
+                self.set_window_floating_geometry(
+                    &window,
+                    Rectangle::from_loc_and_size((x, y), (width.max(1), height.max(1))),
+                );
+            }
             Msg::MoveWindowToTag { window_id, tag_id } => {
                 let Some(window) = window_id.window(self) else { return };
                 let Some(tag) = tag_id.tag(self) else { return };
+
+                let old_output = window.output(self);
+                let was_focused = old_output
+                    .as_ref()
+                    .and_then(|op| self.focused_window(op))
+                    .is_some_and(|focused| focused == window);
+
                 window.with_state(|state| {
                     state.tags = vec![tag.clone()];
+                    state.follow_active_tag = false;
                 });
+                self.window_moved(&window, MoveOrigin::User);
+
                 let Some(output) = tag.output(self) else { return };
                 self.update_windows(&output);
                 self.schedule_render(&output);
+
+                if let Some(old_output) = old_output.as_ref().filter(|op| *op != &output) {
+                    self.update_windows(old_output);
+                    self.schedule_render(old_output);
+                }
+
+                if was_focused {
+                    self.follow_moved_window(&window, &tag, old_output.as_ref());
+                }
             }
             Msg::ToggleTagOnWindow { window_id, tag_id } => {
                 let Some(window) = window_id.window(self) else { return };
@@ -146,12 +404,18 @@ impl State {
                     } else {
                         state.tags.push(tag.clone());
                     }
+                    state.follow_active_tag = false;
                 });
+                self.mark_if_orphaned(&window, self.tag_home(&tag));
 
                 let Some(output) = tag.output(self) else { return };
                 self.update_windows(&output);
                 self.schedule_render(&output);
             }
+            Msg::SetFollowActiveTag { window_id, follow } => {
+                let Some(window) = window_id.window(self) else { return };
+                self.set_follow_active_tag(&window, follow);
+            }
             Msg::ToggleFloating { window_id } => {
                 let Some(window) = window_id.window(self) else { return };
                 window.toggle_floating();
@@ -181,9 +445,93 @@ impl State {
                 self.update_windows(&output);
                 self.schedule_render(&output);
             }
+            Msg::SetFloating {
+                window_id,
+                floating,
+            } => {
+                let Some(window) = window_id.window(self) else { return };
+                window.set_floating(floating);
+
+                let Some(output) = window.output(self) else { return };
+                self.update_windows(&output);
+                self.schedule_render(&output);
+            }
+            Msg::SetFullscreen {
+                window_id,
+                fullscreen,
+            } => {
+                let Some(window) = window_id.window(self) else { return };
+                window.set_fullscreen(fullscreen);
+
+                let Some(output) = window.output(self) else { return };
+                self.update_windows(&output);
+                self.schedule_render(&output);
+            }
+            Msg::SetMaximized {
+                window_id,
+                maximized,
+            } => {
+                let Some(window) = window_id.window(self) else { return };
+                window.set_maximized(maximized);
+
+                let Some(output) = window.output(self) else { return };
+                self.update_windows(&output);
+                self.schedule_render(&output);
+            }
+            Msg::FlashWindow {
+                window_id,
+                duration_ms,
+                color,
+            } => {
+                let Some(window) = window_id.window(self) else { return };
+                self.flash_window(&window, Duration::from_millis(duration_ms), color);
+            }
+            Msg::RaiseWindow { window_id } => {
+                let Some(window) = window_id.window(self) else { return };
+                self.raise_window(&window);
+
+                let Some(output) = window.output(self) else { return };
+                self.schedule_render(&output);
+            }
+            Msg::FocusWindow { window_id } => {
+                let Some(window) = window_id.window(self) else { return };
+                let Some(output) = window.output(self) else { return };
+
+                self.focus_state.set_focus(window);
+                self.update_focus(&output);
+                self.schedule_render(&output);
+            }
+            Msg::CycleWindowFocus {
+                output_name,
+                forward,
+            } => {
+                let output = output_name
+                    .and_then(|output_name| output_name.output(self))
+                    .or_else(|| self.focus_state.focused_output.clone());
+                if let Some(output) = output {
+                    self.cycle_focus(&output, forward);
+                }
+            }
+            Msg::CycleMruFocus { direction } => {
+                self.cycle_mru_focus(direction);
+            }
+            Msg::CommitMruCycle => {
+                self.commit_mru_cycle();
+            }
+            Msg::SwapWindowInDirection { direction } => {
+                self.swap_in_direction(direction);
+            }
             Msg::AddWindowRule { cond, rule } => {
                 self.config.window_rules.push((cond, rule));
             }
+            Msg::AddStateRequestHandler { cond, callback_id } => {
+                self.config
+                    .state_request_handlers
+                    .push(StateRequestHandler { cond, callback_id });
+            }
+            Msg::DecideStateRequest { token, decision } => {
+                self.decide_state_request(token, decision);
+            }
             Msg::WindowMoveGrab { button } => {
                 // TODO: in the future, there may be movable layer surfaces
                 let Some((FocusTarget::Window(window), _)) =
@@ -267,6 +615,7 @@ impl State {
                 tracing::debug!("ToggleTag");
                 if let Some(tag) = tag_id.tag(self) {
                     tag.set_active(!tag.active());
+                    self.signal_tag_active_changed(&tag);
                     if let Some(output) = tag.output(self) {
                         self.update_windows(&output);
                         self.update_focus(&output);
@@ -276,61 +625,13 @@ impl State {
             }
             Msg::SwitchToTag { tag_id } => {
                 let Some(tag) = tag_id.tag(self) else { return };
-                let Some(output) = tag.output(self) else { return };
-                output.with_state(|state| {
-                    for op_tag in state.tags.iter_mut() {
-                        op_tag.set_active(false);
-                    }
-                    tag.set_active(true);
-                });
-                self.update_windows(&output);
-                self.update_focus(&output);
-                self.schedule_render(&output);
+                self.switch_to_tag(&tag);
             }
             Msg::AddTags {
                 output_name,
                 tag_names,
             } => {
-                let new_tags = tag_names.into_iter().map(Tag::new).collect::<Vec<_>>();
-                if let Some(saved_state) = self.config.connector_saved_states.get_mut(&output_name)
-                {
-                    let mut tags = saved_state.tags.clone();
-                    tags.extend(new_tags.clone());
-                    saved_state.tags = tags;
-                } else {
-                    self.config.connector_saved_states.insert(
-                        output_name.clone(),
-                        ConnectorSavedState {
-                            tags: new_tags.clone(),
-                            ..Default::default()
-                        },
-                    );
-                }
-
-                if let Some(output) = self
-                    .space
-                    .outputs()
-                    .find(|output| output.name() == output_name.0)
-                {
-                    output.with_state(|state| {
-                        state.tags.extend(new_tags.clone());
-                        tracing::debug!("tags added, are now {:?}", state.tags);
-                    });
-
-                    // replace tags that windows have that are the same id
-                    // (this should only happen on config reload)
-                    for tag in new_tags {
-                        for window in self.windows.iter() {
-                            window.with_state(|state| {
-                                for win_tag in state.tags.iter_mut() {
-                                    if win_tag.id() == tag.id() {
-                                        *win_tag = tag.clone();
-                                    }
-                                }
-                            });
-                        }
-                    }
-                }
+                self.add_tags(output_name, tag_names);
             }
             Msg::RemoveTags { tag_ids } => {
                 let tags = tag_ids
@@ -339,13 +640,34 @@ impl State {
                     .collect::<Vec<_>>();
 
                 for tag in tags {
+                    let home = self.tag_home(&tag);
+                    let output = tag.output(self);
+
                     for saved_state in self.config.connector_saved_states.values_mut() {
                         saved_state.tags.retain(|tg| tg != &tag);
                     }
-                    let Some(output) = tag.output(self) else { continue };
-                    output.with_state(|state| {
-                        state.tags.retain(|tg| tg != &tag);
-                    });
+                    if let Some(output) = output.as_ref() {
+                        output.with_state(|state| {
+                            state.tags.retain(|tg| tg != &tag);
+                        });
+                    }
+
+                    // Windows only keep live tags, and ones left without any are orphaned
+                    let windows = self
+                        .windows
+                        .iter()
+                        .filter(|win| win.with_state(|state| state.tags.contains(&tag)))
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    for win in windows.iter() {
+                        win.with_state(|state| state.tags.retain(|tg| tg != &tag));
+                        self.mark_if_orphaned(win, home.clone());
+                    }
+
+                    if let Some(output) = output {
+                        self.update_windows(&output);
+                        self.schedule_render(&output);
+                    }
                 }
             }
             Msg::SetLayout { tag_id, layout } => {
@@ -354,6 +676,26 @@ impl State {
                 let Some(output) = tag.output(self) else { return };
                 self.update_windows(&output);
             }
+            Msg::SetMasterFactor {
+                tag_id,
+                master_factor,
+            } => {
+                let Some(tag) = tag_id.tag(self) else { return };
+                tag.set_master_factor(master_factor);
+                let Some(output) = tag.output(self) else { return };
+                self.update_windows(&output);
+            }
+            Msg::SetTagDefaultWindowMode {
+                tag_id,
+                floating_or_tiled,
+            } => {
+                let Some(tag) = tag_id.tag(self) else { return };
+                self.set_tag_default_floating_or_tiled(&tag, floating_or_tiled);
+            }
+            Msg::SetTagIndex { tag_id, index } => {
+                let Some(tag) = tag_id.tag(self) else { return };
+                self.set_tag_index(&tag, index);
+            }
 
             Msg::ConnectForAllOutputs { callback_id } => {
                 let stream = self
@@ -363,20 +705,87 @@ impl State {
                     .expect("stream doesn't exist");
 
                 for output in self.space.outputs() {
-                    crate::api::send_to_client(
+                    // Outputs the previous config set up are replayed with the tags they had
+                    let previous_tags = self
+                        .config
+                        .previous_tags
+                        .get(&OutputName(output.name()))
+                        .cloned();
+                    let is_replay = previous_tags.is_some();
+                    let previous_tags = previous_tags.unwrap_or_default();
+
+                    if let Err(err) = crate::api::send_to_client(
                         &mut stream.lock().expect("couldn't lock stream"),
-                        &OutgoingMsg::CallCallback {
+                        &OutgoingMsg::call_callback(
                             callback_id,
-                            args: Some(Args::ConnectForAllOutputs {
+                            Some(Args::ConnectForAllOutputs {
                                 output_name: output.name(),
+                                is_replay,
+                                tag_names: previous_tags.names,
+                                active_tags: previous_tags.active,
                             }),
-                        },
-                    )
-                    .expect("Send to client failed");
+                            &self.api_state.callback_serial,
+                        ),
+                    ) {
+                        tracing::warn!("Failed to send callback to client: {err}");
+                    }
                 }
 
                 self.config.output_callback_ids.push(callback_id);
             }
+            Msg::SetHotplugDebounce { debounce_ms } => {
+                if let Backend::Udev(udev) = &mut self.backend {
+                    udev.hotplug.debounce = Duration::from_millis(debounce_ms);
+                }
+            }
+            Msg::ConnectTagOrderChanged { callback_id } => {
+                self.config.tag_order_callback_ids.push(callback_id);
+            }
+            Msg::ConnectTagActiveChanged { callback_id } => {
+                self.config.tag_active_callback_ids.push(callback_id);
+            }
+            Msg::ConnectOutputConnected { callback_id } => {
+                self.config.output_connected_callback_ids.push(callback_id);
+            }
+            Msg::ConnectOutputDisconnected { callback_id } => {
+                self.config
+                    .output_disconnected_callback_ids
+                    .push(callback_id);
+            }
+            Msg::ConnectOutputModeChanged { callback_id } => {
+                self.config
+                    .output_mode_changed_callback_ids
+                    .push(callback_id);
+            }
+            Msg::ConnectKeyboardFocusChanged { callback_id } => {
+                self.config.keyboard_focus_callback_ids.push(callback_id);
+            }
+            Msg::ConnectPointerFocusChanged { callback_id } => {
+                self.config.pointer_focus_callback_ids.push(callback_id);
+            }
+            Msg::ConnectWindowTitleChanged { callback_id } => {
+                self.config.window_title_callback_ids.push(callback_id);
+            }
+            Msg::ConnectWindowClassChanged { callback_id } => {
+                self.config.window_class_callback_ids.push(callback_id);
+            }
+            Msg::ConnectWindowOpened { callback_id } => {
+                self.config.window_opened_callback_ids.push(callback_id);
+            }
+            Msg::ConnectWindowClosed { callback_id } => {
+                self.config.window_closed_callback_ids.push(callback_id);
+            }
+            Msg::ConnectDeviceAdded { callback_id } => {
+                self.config.device_added_callback_ids.push(callback_id);
+            }
+            Msg::ConnectBindLayerChanged { callback_id } => {
+                self.config.bind_layer_callback_ids.push(callback_id);
+            }
+            Msg::ConnectClientDisconnected { callback_id } => {
+                self.config
+                    .client_disconnected_callback_ids
+                    .push(callback_id);
+            }
             Msg::SetOutputLocation { output_name, x, y } => {
                 if let Some(saved_state) = self.config.connector_saved_states.get_mut(&output_name)
                 {
@@ -408,12 +817,88 @@ impl State {
                 self.space.map_output(&output, loc);
                 tracing::debug!("Mapping output {} to {loc:?}", output.name());
                 self.update_windows(&output);
+
+                let windows = self.windows.clone();
+                self.update_window_outputs(windows.iter());
+                self.reclamp_pointer();
+            }
+            Msg::SetOutputRenderScale {
+                output_name,
+                render_scale,
+            } => {
+                let Some(output) = output_name.output(self) else { return };
+                self.set_output_render_scale(&output, render_scale as f64);
+            }
+            Msg::SetOutputDefaultWindowMode {
+                output_name,
+                floating_or_tiled,
+            } => {
+                let Some(output) = output_name.output(self) else { return };
+                self.set_output_default_floating_or_tiled(&output, floating_or_tiled);
             }
 
             Msg::Quit => {
                 tracing::info!("Quitting Pinnacle");
                 self.loop_signal.stop();
             }
+            Msg::SetDoNotDisturb { enabled } => {
+                self.set_do_not_disturb(enabled);
+            }
+            Msg::SetDoNotDisturbNamespaces { namespaces } => {
+                self.do_not_disturb.held_namespaces = namespaces;
+            }
+            Msg::ConnectDoNotDisturbChanged { callback_id } => {
+                self.config.do_not_disturb_callback_ids.push(callback_id);
+            }
+            Msg::OnStartupComplete { callback_id } => {
+                self.on_startup_complete(callback_id);
+            }
+            Msg::SetDebugOverlay { flags } => {
+                self.set_debug_overlay(flags);
+            }
+            Msg::SetGrabHints { enabled } => {
+                self.set_grab_hints(enabled);
+            }
+            Msg::SetUiFont { family, size } => {
+                self.set_ui_font(family, size);
+            }
+            Msg::Prompt { title, callback_id } => {
+                self.start_prompt(title, callback_id, stream);
+            }
+            Msg::SetFlashOnFocus { enabled } => {
+                self.set_flash_on_focus(enabled);
+            }
+            Msg::SetFocusFollowsMovedWindow { policy } => {
+                self.focus_state.follow_moved_window = policy;
+            }
+            Msg::SetAutoTidy {
+                after_ms,
+                tag_name,
+                enabled,
+            } => {
+                self.set_auto_tidy(Duration::from_millis(after_ms), tag_name, enabled);
+            }
+            Msg::SetApiFocusCountsAsActivity { counts } => {
+                self.activity_state.count_api_focus = counts;
+            }
+            Msg::SetRememberWindowOutput { enabled } => {
+                self.placement_memory.enabled = enabled;
+            }
+            Msg::ForgetWindowPlacement { class } => {
+                self.forget_window_placement(&class);
+            }
+            Msg::SetRaiseOnClick { enabled } => {
+                self.raise_on_click = enabled;
+            }
+            Msg::SetBackgroundThrottle { policy } => {
+                self.background_throttle = policy;
+            }
+            Msg::SetInsertPosition { position } => {
+                self.insert_position = position;
+            }
+            Msg::SetSandboxPolicy { policy } => {
+                crate::handlers::security_context::set_sandbox_policy(policy);
+            }
 
             Msg::SetXkbConfig {
                 rules,
@@ -430,38 +915,106 @@ impl State {
                     options,
                 };
                 if let Some(kb) = self.seat.get_keyboard() {
+                    // Keybinds are matched against the keysyms of each key event,
+                    // so they pick up the new keymap without being set again
                     if let Err(err) = kb.set_xkb_config(self, new_config) {
                         tracing::error!("Failed to set xkbconfig: {err}");
+                        // The old keymap stays, so tell the config instead of failing silently
+                        if let Err(err) = crate::api::send_to_client(
+                            &mut stream.lock().expect("Couldn't lock stream"),
+                            &OutgoingMsg::Warning {
+                                message: format!("Failed to set xkb config: {err}"),
+                            },
+                        ) {
+                            tracing::warn!("Failed to send xkb config error to client: {err}");
+                        }
                     }
                 }
             }
+            Msg::SetKeyboardRepeat { rate, delay } => {
+                if rate <= 0 || delay <= 0 {
+                    tracing::warn!(
+                        "Ignoring invalid keyboard repeat rate {rate} and delay {delay}; both must be positive"
+                    );
+                    return;
+                }
+                // The keyboard lives as long as the seat, so this stays across config reloads
+                if let Some(kb) = self.seat.get_keyboard() {
+                    kb.change_repeat_info(rate, delay);
+                }
+                // Repeating keybinds use the same rate and delay
+                self.input_state.key_repeat.rate = rate;
+                self.input_state.key_repeat.delay = delay;
+            }
 
             Msg::SetLibinputSetting(setting) => {
-                for device in self.input_state.libinput_devices.iter_mut() {
-                    // We're just gonna indiscriminately apply everything and ignore errors
-                    setting.apply_to_device(device);
-                }
+                self.set_libinput_setting(None, setting);
+            }
+            Msg::SetDeviceLibinputSetting { device, setting } => {
+                self.set_libinput_setting(Some(device), setting);
+            }
+            Msg::MapDeviceToOutput {
+                device,
+                output_name,
+            } => {
+                self.set_libinput_setting(Some(device), LibinputSetting::MapToOutput(output_name));
+            }
+            Msg::SetScrollFactor {
+                factor,
+                device_name,
+            } => {
+                self.input_state.scroll.set_factor(factor, device_name);
+            }
+            Msg::SetHorizontalScrollInvert {
+                invert,
+                device_name,
+            } => {
+                self.input_state
+                    .scroll
+                    .set_horizontal_invert(invert, device_name);
+            }
 
-                self.input_state.libinput_settings.push(setting);
+            Msg::OneOffClient => (),
+            Msg::ReloadConfig => {
+                self.start_config(crate::config::get_config_dir())
+                    .expect("failed to restart config");
+            }
+            Msg::ConfigReady => {
+                self.config_ready(&stream);
+            }
+            Msg::ConnectLayoutApplied { callback_id } => {
+                self.connect_layout_applied(stream, callback_id);
+            }
+            Msg::ConnectReload { callback_id } => {
+                self.connect_reload(stream, callback_id);
+            }
+            Msg::SetSlowRequestThreshold { threshold_ms } => {
+                self.api_state.stats.slow_request_threshold = Duration::from_millis(threshold_ms);
+            }
+            Msg::ApplyPreviousSession => {
+                self.apply_previous_session();
             }
 
             Msg::Request {
                 request_id,
                 request,
             } => {
-                self.handle_request(request_id, request);
+                let name = request.name();
+                let start = Instant::now();
+                self.handle_request(request_id, request, stream);
+                self.record_request_time(name, start.elapsed());
             }
         }
     }
 
-    /// Handle a client request.
-    fn handle_request(&mut self, request_id: RequestId, request: Request) {
-        let stream = self
-            .api_state
-            .stream
-            .clone() // clone due to use of self below
-            .expect("Stream doesn't exist");
-        let mut stream = stream.lock().expect("Couldn't lock stream");
+    /// Handle a client request, responding through `client_stream`.
+    fn handle_request(
+        &mut self,
+        request_id: RequestId,
+        request: Request,
+        client_stream: Arc<Mutex<UnixStream>>,
+    ) {
+        let mut stream = client_stream.lock().expect("Couldn't lock stream");
 
         match request {
             Request::GetWindows => {
@@ -471,14 +1024,15 @@ impl State {
                     .map(|win| win.with_state(|state| state.id))
                     .collect::<Vec<_>>();
 
-                crate::api::send_to_client(
+                if let Err(err) = crate::api::send_to_client(
                     &mut stream,
                     &OutgoingMsg::RequestResponse {
                         request_id,
                         response: RequestResponse::Windows { window_ids },
                     },
-                )
-                .expect("Couldn't send to client");
+                ) {
+                    tracing::warn!("Dropping response to request {request_id:?}: {err}");
+                }
             }
             Request::GetWindowProps { window_id } => {
                 let window = window_id.window(self);
@@ -526,7 +1080,30 @@ impl State {
                     .as_ref()
                     .map(|win| win.with_state(|state| state.fullscreen_or_maximized));
 
-                crate::api::send_to_client(
+                let resize_increments = window
+                    .as_ref()
+                    .and_then(|win| win.resize_increments())
+                    .map(|increments| (increments.inc.w, increments.inc.h));
+
+                let seconds_since_activity = window
+                    .as_ref()
+                    .map(|win| self.window_inactive_duration(win).as_secs());
+
+                let keyboard_shortcuts_inhibited = window.as_ref().map(|win| {
+                    win.wl_surface()
+                        .and_then(|surface| {
+                            self.seat.keyboard_shortcuts_inhibitor_for_surface(&surface)
+                        })
+                        .is_some_and(|inhibitor| inhibitor.is_active())
+                });
+
+                let group = window.as_ref().and_then(|win| self.window_group_id(win));
+
+                let sandbox_app_id = window
+                    .as_ref()
+                    .and_then(|win| self.window_sandbox_app_id(win));
+
+                if let Err(err) = crate::api::send_to_client(
                     &mut stream,
                     &OutgoingMsg::RequestResponse {
                         request_id,
@@ -538,10 +1115,113 @@ impl State {
                             focused,
                             floating,
                             fullscreen_or_maximized,
+                            resize_increments,
+                            seconds_since_activity,
+                            keyboard_shortcuts_inhibited,
+                            group,
+                            sandbox_app_id,
                         },
                     },
-                )
-                .expect("failed to send to client");
+                ) {
+                    tracing::warn!("Dropping response to request {request_id:?}: {err}");
+                }
+            }
+            Request::AddTags {
+                output_name,
+                tag_names,
+            } => {
+                let tag_ids = self
+                    .add_tags(output_name, tag_names)
+                    .iter()
+                    .map(|tag| tag.id())
+                    .collect::<Vec<_>>();
+
+                if let Err(err) = crate::api::send_to_client(
+                    &mut stream,
+                    &OutgoingMsg::RequestResponse {
+                        request_id,
+                        response: RequestResponse::Tags { tag_ids },
+                    },
+                ) {
+                    tracing::warn!("Dropping response to request {request_id:?}: {err}");
+                }
+            }
+            Request::GetPointerFocusedWindow => {
+                let window_id = self
+                    .pointer_focused_window()
+                    .map(|win| win.with_state(|state| state.id));
+
+                if let Err(err) = crate::api::send_to_client(
+                    &mut stream,
+                    &OutgoingMsg::RequestResponse {
+                        request_id,
+                        response: RequestResponse::Window { window_id },
+                    },
+                ) {
+                    tracing::warn!("Dropping response to request {request_id:?}: {err}");
+                }
+            }
+            Request::GetPreviousFocusedWindow => {
+                let window_id = self
+                    .previous_focused_window()
+                    .map(|win| win.with_state(|state| state.id));
+
+                if let Err(err) = crate::api::send_to_client(
+                    &mut stream,
+                    &OutgoingMsg::RequestResponse {
+                        request_id,
+                        response: RequestResponse::Window { window_id },
+                    },
+                ) {
+                    tracing::warn!("Dropping response to request {request_id:?}: {err}");
+                }
+            }
+            Request::GetInactiveWindows { inactive_ms } => {
+                let window_ids = self
+                    .windows_inactive_for(Duration::from_millis(inactive_ms))
+                    .iter()
+                    .map(|win| win.with_state(|state| state.id))
+                    .collect::<Vec<_>>();
+
+                if let Err(err) = crate::api::send_to_client(
+                    &mut stream,
+                    &OutgoingMsg::RequestResponse {
+                        request_id,
+                        response: RequestResponse::Windows { window_ids },
+                    },
+                ) {
+                    tracing::warn!("Dropping response to request {request_id:?}: {err}");
+                }
+            }
+            Request::GetOrphanedWindows => {
+                let window_ids = self
+                    .orphaned_windows()
+                    .iter()
+                    .map(|win| win.with_state(|state| state.id))
+                    .collect::<Vec<_>>();
+
+                if let Err(err) = crate::api::send_to_client(
+                    &mut stream,
+                    &OutgoingMsg::RequestResponse {
+                        request_id,
+                        response: RequestResponse::Windows { window_ids },
+                    },
+                ) {
+                    tracing::warn!("Dropping response to request {request_id:?}: {err}");
+                }
+            }
+            Request::WaitForWindow {
+                condition,
+                timeout_ms,
+            } => {
+                // The wait might be responded to right away
+                drop(stream);
+                self.add_window_wait(
+                    request_id,
+                    condition,
+                    Duration::from_millis(timeout_ms),
+                    client_stream.clone(),
+                );
             }
             Request::GetOutputs => {
                 let output_names = self
@@ -550,14 +1230,15 @@ impl State {
                     .map(|output| output.name())
                     .collect::<Vec<_>>();
 
-                crate::api::send_to_client(
+                if let Err(err) = crate::api::send_to_client(
                     &mut stream,
                     &OutgoingMsg::RequestResponse {
                         request_id,
                         response: RequestResponse::Outputs { output_names },
                     },
-                )
-                .expect("failed to send to client");
+                ) {
+                    tracing::warn!("Dropping response to request {request_id:?}: {err}");
+                }
             }
             Request::GetOutputProps { output_name } => {
                 let output = self
@@ -604,7 +1285,15 @@ impl State {
                     })
                 });
 
-                crate::api::send_to_client(
+                let render_scale = output
+                    .as_ref()
+                    .map(|output| output.with_state(|state| state.render_scale as f32));
+
+                let default_window_mode = output
+                    .as_ref()
+                    .and_then(|output| output.with_state(|state| state.default_floating_or_tiled));
+
+                if let Err(err) = crate::api::send_to_client(
                     &mut stream,
                     &OutgoingMsg::RequestResponse {
                         request_id,
@@ -617,10 +1306,13 @@ impl State {
                             physical_size,
                             focused,
                             tag_ids,
+                            render_scale,
+                            default_window_mode,
                         },
                     },
-                )
-                .expect("failed to send to client");
+                ) {
+                    tracing::warn!("Dropping response to request {request_id:?}: {err}");
+                }
             }
             Request::GetTags => {
                 let tag_ids = self
@@ -630,14 +1322,15 @@ impl State {
                     .map(|tag| tag.id())
                     .collect::<Vec<_>>();
 
-                crate::api::send_to_client(
+                if let Err(err) = crate::api::send_to_client(
                     &mut stream,
                     &OutgoingMsg::RequestResponse {
                         request_id,
                         response: RequestResponse::Tags { tag_ids },
                     },
-                )
-                .expect("failed to send to client");
+                ) {
+                    tracing::warn!("Dropping response to request {request_id:?}: {err}");
+                }
             }
             Request::GetTagProps { tag_id } => {
                 let tag = tag_id.tag(self);
@@ -649,8 +1342,11 @@ impl State {
 
                 let active = tag.as_ref().map(|tag| tag.active());
                 let name = tag.as_ref().map(|tag| tag.name());
+                let master_factor = tag.as_ref().map(|tag| tag.master_factor());
+                let default_window_mode =
+                    tag.as_ref().and_then(|tag| tag.default_floating_or_tiled());
 
-                crate::api::send_to_client(
+                if let Err(err) = crate::api::send_to_client(
                     &mut stream,
                     &OutgoingMsg::RequestResponse {
                         request_id,
@@ -658,17 +1354,213 @@ impl State {
                             active,
                             name,
                             output_name,
+                            master_factor,
+                            default_window_mode,
                         },
                     },
-                )
-                .expect("failed to send to client");
+                ) {
+                    tracing::warn!("Dropping response to request {request_id:?}: {err}");
+                }
+            }
+            Request::GetDoNotDisturb => {
+                if let Err(err) = crate::api::send_to_client(
+                    &mut stream,
+                    &OutgoingMsg::RequestResponse {
+                        request_id,
+                        response: RequestResponse::DoNotDisturb {
+                            enabled: self.do_not_disturb.enabled,
+                        },
+                    },
+                ) {
+                    tracing::warn!("Dropping response to request {request_id:?}: {err}");
+                }
+            }
+            Request::GetCompositorInfo => {
+                let backend = if self.backend.is_winit() { "winit" } else { "udev" };
+
+                if let Err(err) = crate::api::send_to_client(
+                    &mut stream,
+                    &OutgoingMsg::RequestResponse {
+                        request_id,
+                        response: RequestResponse::CompositorInfo {
+                            version: env!("CARGO_PKG_VERSION").to_string(),
+                            backend: backend.to_string(),
+                            software_rendering: self.backend.software_rendering().is_some(),
+                            event_loop_wakeups: self.event_loop_wakeups,
+                        },
+                    },
+                ) {
+                    tracing::warn!("Dropping response to request {request_id:?}: {err}");
+                }
+            }
+            Request::GetApiStats => {
+                if let Err(err) = crate::api::send_to_client(
+                    &mut stream,
+                    &OutgoingMsg::RequestResponse {
+                        request_id,
+                        response: RequestResponse::ApiStats {
+                            bucket_bounds_us: BUCKET_BOUNDS_US.to_vec(),
+                            requests: self.request_stats(),
+                        },
+                    },
+                ) {
+                    tracing::warn!("Dropping response to request {request_id:?}: {err}");
+                }
+            }
+            Request::DumpState => {
+                if let Err(err) = crate::api::send_to_client(
+                    &mut stream,
+                    &OutgoingMsg::RequestResponse {
+                        request_id,
+                        response: RequestResponse::StateDump {
+                            dump: self.dump_state(),
+                        },
+                    },
+                ) {
+                    tracing::warn!("Dropping response to request {request_id:?}: {err}");
+                }
+            }
+            Request::GetPreviousSession => {
+                if let Err(err) = crate::api::send_to_client(
+                    &mut stream,
+                    &OutgoingMsg::RequestResponse {
+                        request_id,
+                        response: RequestResponse::PreviousSession {
+                            session: self.session_state.previous.clone(),
+                        },
+                    },
+                ) {
+                    tracing::warn!("Dropping response to request {request_id:?}: {err}");
+                }
+            }
+            Request::GetRecentDisconnects => {
+                if let Err(err) = crate::api::send_to_client(
+                    &mut stream,
+                    &OutgoingMsg::RequestResponse {
+                        request_id,
+                        response: RequestResponse::RecentDisconnects {
+                            disconnects: self.recent_disconnects(),
+                        },
+                    },
+                ) {
+                    tracing::warn!("Dropping response to request {request_id:?}: {err}");
+                }
+            }
+            Request::MeasureText { text, size_px } => {
+                let size = self
+                    .ui_font
+                    .measure(&text, size_px.unwrap_or(self.ui_font.size));
+
+                if let Err(err) = crate::api::send_to_client(
+                    &mut stream,
+                    &OutgoingMsg::RequestResponse {
+                        request_id,
+                        response: RequestResponse::TextSize {
+                            width: size.w,
+                            height: size.h,
+                        },
+                    },
+                ) {
+                    tracing::warn!("Dropping response to request {request_id:?}: {err}");
+                }
+            }
+            Request::GetMediaKeyCommands => {
+                let media_keys = &self.input_state.media_keys;
+                let commands = media_keys
+                    .commands
+                    .iter()
+                    .map(|(keysym, command)| (keysym.raw(), command.clone()))
+                    .collect::<Vec<_>>();
+
+                if let Err(err) = crate::api::send_to_client(
+                    &mut stream,
+                    &OutgoingMsg::RequestResponse {
+                        request_id,
+                        response: RequestResponse::MediaKeyCommands {
+                            enabled: media_keys.enabled,
+                            commands,
+                        },
+                    },
+                ) {
+                    tracing::warn!("Dropping response to request {request_id:?}: {err}");
+                }
+            }
+            Request::GetInputDevices => {
+                let scroll = &self.input_state.scroll;
+                let devices = self
+                    .input_state
+                    .libinput_devices
+                    .iter()
+                    .map(|device| InputDeviceProps {
+                        name: device.name().to_string(),
+                        scroll_factor: scroll.factor(device.name()),
+                        horizontal_scroll_invert: scroll.horizontal_invert(device.name()),
+                    })
+                    .collect::<Vec<_>>();
+
+                if let Err(err) = crate::api::send_to_client(
+                    &mut stream,
+                    &OutgoingMsg::RequestResponse {
+                        request_id,
+                        response: RequestResponse::InputDevices { devices },
+                    },
+                ) {
+                    tracing::warn!("Dropping response to request {request_id:?}: {err}");
+                }
+            }
+            Request::GetKeybinds => {
+                let mut keybinds = self
+                    .input_state
+                    .keybinds
+                    .iter()
+                    .map(|((layer, modifiers, key, edge), keybind)| KeybindProps {
+                        modifiers: modifiers.values(),
+                        key: key.raw(),
+                        key_name: xkbcommon::xkb::keysym_get_name(*key),
+                        edge: *edge,
+                        group: keybind.group.clone(),
+                        description: keybind.description.clone(),
+                        layer: layer.clone(),
+                        passthrough: keybind.passthrough,
+                        force: keybind.force,
+                        repeats: keybind.repeats,
+                    })
+                    .collect::<Vec<_>>();
+
+                // The keybinds are in a hashmap, so sort them to keep the order the same
+                // between requests
+                keybinds.sort_by(|a, b| {
+                    (&a.layer, &a.group, &a.key_name, a.key).cmp(&(
+                        &b.layer,
+                        &b.group,
+                        &b.key_name,
+                        b.key,
+                    ))
+                });
+
+                if let Err(err) = crate::api::send_to_client(
+                    &mut stream,
+                    &OutgoingMsg::RequestResponse {
+                        request_id,
+                        response: RequestResponse::Keybinds { keybinds },
+                    },
+                ) {
+                    tracing::warn!("Dropping response to request {request_id:?}: {err}");
+                }
             }
         }
     }
 
     // Welcome to indentation hell
     /// Handle a received spawn command by spawning the command and hooking up any callbacks.
-    pub fn handle_spawn(&self, command: Vec<String>, callback_id: Option<CallbackId>) {
+    ///
+    /// `spawn_token` is held until the process exits, so it counts toward the client's callbacks.
+    pub fn handle_spawn(
+        &self,
+        command: Vec<String>,
+        callback_id: Option<CallbackId>,
+        spawn_token: Option<Arc<()>>,
+    ) {
         let mut command = command.into_iter();
         let Some(program) = command.next() else {
             // TODO: notify that command was nothing
@@ -718,6 +1610,9 @@ impl State {
             let stream_out = self.api_state.stream.clone().expect("Stream doesn't exist");
             let stream_err = stream_out.clone();
             let stream_exit = stream_out.clone();
+            let serial_out = self.api_state.callback_serial.clone();
+            let serial_err = serial_out.clone();
+            let serial_exit = serial_out.clone();
 
             if let Some(stdout) = stdout {
                 let future = async move {
@@ -725,21 +1620,23 @@ impl State {
                     while let Some(line) = reader.next().await {
                         match line {
                             Ok(line) => {
-                                let msg = OutgoingMsg::CallCallback {
+                                let msg = OutgoingMsg::call_callback(
                                     callback_id,
-                                    args: Some(Args::Spawn {
+                                    Some(Args::Spawn {
                                         stdout: Some(line),
                                         stderr: None,
                                         exit_code: None,
                                         exit_msg: None,
                                     }),
-                                };
+                                    &serial_out,
+                                );
 
-                                crate::api::send_to_client(
+                                if let Err(err) = crate::api::send_to_client(
                                     &mut stream_out.lock().expect("Couldn't lock stream"),
                                     &msg,
-                                )
-                                .expect("Send to client failed"); // TODO: notify instead of crash
+                                ) {
+                                    tracing::warn!("Failed to send callback to client: {err}");
+                                }
                             }
                             // TODO: possibly break on err?
                             Err(err) => tracing::warn!("read err: {err}"),
@@ -759,21 +1656,23 @@ impl State {
                     while let Some(line) = reader.next().await {
                         match line {
                             Ok(line) => {
-                                let msg = OutgoingMsg::CallCallback {
+                                let msg = OutgoingMsg::call_callback(
                                     callback_id,
-                                    args: Some(Args::Spawn {
+                                    Some(Args::Spawn {
                                         stdout: None,
                                         stderr: Some(line),
                                         exit_code: None,
                                         exit_msg: None,
                                     }),
-                                };
+                                    &serial_err,
+                                );
 
-                                crate::api::send_to_client(
+                                if let Err(err) = crate::api::send_to_client(
                                     &mut stream_err.lock().expect("Couldn't lock stream"),
                                     &msg,
-                                )
-                                .expect("Send to client failed"); // TODO: notify instead of crash
+                                ) {
+                                    tracing::warn!("Failed to send callback to client: {err}");
+                                }
                             }
                             Err(err) => tracing::warn!("read err: {err}"),
                         }
@@ -786,23 +1685,26 @@ impl State {
             }
 
             let future = async move {
+                let _spawn_token = spawn_token;
                 match child.status().await {
                     Ok(exit_status) => {
-                        let msg = OutgoingMsg::CallCallback {
+                        let msg = OutgoingMsg::call_callback(
                             callback_id,
-                            args: Some(Args::Spawn {
+                            Some(Args::Spawn {
                                 stdout: None,
                                 stderr: None,
                                 exit_code: exit_status.code(),
                                 exit_msg: Some(exit_status.to_string()),
                             }),
-                        };
+                            &serial_exit,
+                        );
 
-                        crate::api::send_to_client(
+                        if let Err(err) = crate::api::send_to_client(
                             &mut stream_exit.lock().expect("Couldn't lock stream"),
                             &msg,
-                        )
-                        .expect("Send to client failed"); // TODO: notify instead of crash
+                        ) {
+                            tracing::warn!("Failed to send callback to client: {err}");
+                        }
                     }
                     Err(err) => {
                         tracing::warn!("child wait() err: {err}");
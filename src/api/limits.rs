@@ -0,0 +1,620 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Limits on what clients can send, so a buggy config can't take down the session.
+//!
+//! Messages over a limit get a warning sent back. Messages that register something, like
+//! keybinds, are also dropped. If a client keeps going over its limits, it's disconnected,
+//! and if it was the config, the fallback config is started in its place.
+//!
+//! The limits can be changed in the `client_limits` table of `metaconfig.toml`
+//! and turned off with `--no-client-limits`.
+
+use std::{
+    collections::{HashMap, HashSet},
+    os::unix::net::UnixStream,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{input::KeybindKey, state::State};
+
+use super::msg::{
+    ModifierMask, MouseEdge, Msg, OutgoingMsg, PinchDirection, Request, ScrollDirection,
+    SwipeDirection,
+};
+
+static LIMITS_DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Turn off client limits for the rest of the session.
+pub fn disable_limits() {
+    LIMITS_DISABLED.store(true, Ordering::Relaxed);
+}
+
+/// How long to wait between warnings to the same client.
+const WARNING_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Limits on what a client can send.
+#[derive(Debug, Clone, Copy, serde::Deserialize)]
+#[serde(default)]
+pub struct ClientLimits {
+    /// How many messages a client can send every second.
+    pub messages_per_second: u32,
+    /// How many keybinds a client can have set at once.
+    pub keybinds: usize,
+    /// How many callbacks a client can have registered at once, like keybinds, mousebinds,
+    /// and running processes it wants output from.
+    pub callbacks: usize,
+    /// How many window waits can be pending at once. Each one holds a timer
+    /// and a request waiting for a response.
+    pub window_waits: usize,
+    /// How many messages a client can send over its limits before it's disconnected.
+    pub max_violations: u32,
+}
+
+impl Default for ClientLimits {
+    fn default() -> Self {
+        Self {
+            messages_per_second: 2000,
+            keybinds: 1000,
+            callbacks: 10000,
+            window_waits: 256,
+            max_violations: 1000,
+        }
+    }
+}
+
+/// A bind a client set. Setting a bind with the same key replaces it, whichever client does it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum BindKey {
+    Key(KeybindKey),
+    Mouse(ModifierMask, u32, MouseEdge),
+    Scroll(ModifierMask, ScrollDirection),
+    Swipe(u32, SwipeDirection),
+    Pinch(u32, PinchDirection),
+}
+
+/// What a client has sent so far.
+#[derive(Debug)]
+pub struct ClientUsage {
+    /// The client's stream. When this is the only reference left, the client is gone.
+    stream: Arc<Mutex<UnixStream>>,
+    /// When the current one second window for counting messages started.
+    window_start: Instant,
+    /// How many messages were sent in the current window.
+    messages_in_window: u32,
+    /// The binds the client set that haven't been removed or replaced since.
+    binds: HashSet<BindKey>,
+    /// Cloned into processes the client spawned with a callback until they exit,
+    /// so every other reference is a running process.
+    spawns: Arc<()>,
+    /// How many other callbacks the client registered. These stay until it disconnects.
+    connections: usize,
+    /// How many messages the client sent over its limits.
+    violations: u32,
+    last_warning: Option<Instant>,
+    /// Whether the client was disconnected for going over its limits.
+    disconnected: bool,
+}
+
+impl ClientUsage {
+    fn new(stream: Arc<Mutex<UnixStream>>) -> Self {
+        Self {
+            stream,
+            window_start: Instant::now(),
+            messages_in_window: 0,
+            binds: HashSet::new(),
+            spawns: Arc::new(()),
+            connections: 0,
+            violations: 0,
+            last_warning: None,
+            disconnected: false,
+        }
+    }
+
+    /// How many callbacks the client has registered right now.
+    fn callbacks(&self) -> usize {
+        self.binds.len() + self.connections + Arc::strong_count(&self.spawns) - 1
+    }
+
+    /// How many keybinds the client has set right now.
+    fn keybinds(&self) -> usize {
+        self.binds
+            .iter()
+            .filter(|bind| matches!(bind, BindKey::Key(_)))
+            .count()
+    }
+}
+
+/// Usage for every connected client.
+#[derive(Debug, Default)]
+pub struct ClientUsages {
+    /// Usages keyed by the address of the client's stream
+    usages: HashMap<usize, ClientUsage>,
+}
+
+impl ClientUsages {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the usage of the client with `stream`, starting one if it's new.
+    fn usage_mut(&mut self, stream: &Arc<Mutex<UnixStream>>) -> &mut ClientUsage {
+        let key = Arc::as_ptr(stream) as usize;
+
+        if !self.usages.contains_key(&key) {
+            // Forget clients that have disconnected
+            self.usages
+                .retain(|_, usage| Arc::strong_count(&usage.stream) > 1);
+        }
+
+        self.usages
+            .entry(key)
+            .or_insert_with(|| ClientUsage::new(stream.clone()))
+    }
+
+    /// Count `msg` from the client with `stream` against `limits`.
+    ///
+    /// Returns the limit it went over, if any, and whether it should be dropped.
+    /// Messages that are dropped don't count as registering anything.
+    fn count(
+        &mut self,
+        msg: &Msg,
+        stream: &Arc<Mutex<UnixStream>>,
+        limits: &ClientLimits,
+        window_wait_count: usize,
+        now: Instant,
+    ) -> (Option<String>, bool) {
+        let bind = msg.bind_key();
+        let usage = self.usage_mut(stream);
+
+        if now.duration_since(usage.window_start) >= Duration::from_secs(1) {
+            usage.window_start = now;
+            usage.messages_in_window = 0;
+        }
+        usage.messages_in_window += 1;
+
+        // Setting a bind the client already has only replaces its callback
+        let replaces_own_bind = bind.as_ref().is_some_and(|bind| usage.binds.contains(bind));
+
+        // Going over the message rate only warns, as dropping requests
+        // would leave the client waiting on a response forever
+        let (violation, drop) = if usage.messages_in_window > limits.messages_per_second {
+            (
+                Some(format!(
+                    "sent more than {} messages in a second",
+                    limits.messages_per_second
+                )),
+                false,
+            )
+        } else if msg.registers_callback()
+            && !replaces_own_bind
+            && usage.callbacks() >= limits.callbacks
+        {
+            (
+                Some(format!(
+                    "registered more than {} callbacks",
+                    limits.callbacks
+                )),
+                true,
+            )
+        } else if matches!(msg, Msg::SetKeybind { .. })
+            && !replaces_own_bind
+            && usage.keybinds() >= limits.keybinds
+        {
+            (
+                Some(format!("set more than {} keybinds", limits.keybinds)),
+                true,
+            )
+        } else if matches!(
+            msg,
+            Msg::Request {
+                request: Request::WaitForWindow { .. },
+                ..
+            }
+        ) && window_wait_count >= limits.window_waits
+        {
+            (
+                Some(format!(
+                    "waited for more than {} windows at once",
+                    limits.window_waits
+                )),
+                true,
+            )
+        } else {
+            (None, false)
+        };
+
+        if !drop {
+            self.record(msg, bind, stream);
+        }
+
+        (violation, drop)
+    }
+
+    /// Record what `msg` from the client with `stream` registered or removed.
+    fn record(&mut self, msg: &Msg, bind: Option<BindKey>, stream: &Arc<Mutex<UnixStream>>) {
+        let Some(bind) = bind else {
+            // Running processes are counted with spawn tokens instead
+            if msg.registers_callback() && !matches!(msg, Msg::Spawn { .. }) {
+                self.usage_mut(stream).connections += 1;
+            }
+            return;
+        };
+
+        // The bind replaces or removes whichever client's bind had the same key
+        for usage in self.usages.values_mut() {
+            usage.binds.remove(&bind);
+        }
+
+        if msg.registers_callback() {
+            self.usage_mut(stream).binds.insert(bind);
+        }
+    }
+
+    /// Forget every client's binds. Call this when all binds are cleared.
+    pub fn forget_binds(&mut self) {
+        for usage in self.usages.values_mut() {
+            usage.binds.clear();
+        }
+    }
+
+    /// Get a token that counts a process spawned with a callback toward the callbacks of the
+    /// client with `stream` until it's dropped.
+    ///
+    /// Returns `None` if the client's usage isn't tracked, like when limits are off.
+    pub fn spawn_token(&self, stream: &Arc<Mutex<UnixStream>>) -> Option<Arc<()>> {
+        self.usages
+            .get(&(Arc::as_ptr(stream) as usize))
+            .map(|usage| usage.spawns.clone())
+    }
+}
+
+/// What to do with a message after checking it against the limits.
+pub enum LimitCheck {
+    /// Handle the message.
+    Allow,
+    /// Drop the message. The client was warned.
+    Drop,
+}
+
+impl Msg {
+    /// Whether this message registers a callback.
+    fn registers_callback(&self) -> bool {
+        matches!(
+            self,
+            Msg::SetKeybind { .. }
+                | Msg::SetMousebind { .. }
+                | Msg::SetScrollbind { .. }
+                | Msg::SetGesturebind { .. }
+                | Msg::SetPinchbind { .. }
+                | Msg::AddStateRequestHandler { .. }
+                | Msg::ConnectForAllOutputs { .. }
+                | Msg::ConnectTagOrderChanged { .. }
+                | Msg::ConnectTagActiveChanged { .. }
+                | Msg::ConnectDoNotDisturbChanged { .. }
+                | Msg::ConnectOutputConnected { .. }
+                | Msg::ConnectOutputDisconnected { .. }
+                | Msg::ConnectOutputModeChanged { .. }
+                | Msg::ConnectLayoutApplied { .. }
+                | Msg::ConnectKeyboardFocusChanged { .. }
+                | Msg::ConnectPointerFocusChanged { .. }
+                | Msg::ConnectWindowOpened { .. }
+                | Msg::ConnectWindowClosed { .. }
+                | Msg::ConnectDeviceAdded { .. }
+                | Msg::ConnectClientDisconnected { .. }
+                | Msg::ConnectBindLayerChanged { .. }
+                | Msg::OnStartupComplete { .. }
+                | Msg::Prompt { .. }
+                | Msg::Spawn {
+                    callback_id: Some(_),
+                    ..
+                }
+        )
+    }
+
+    /// The bind this message sets or removes.
+    fn bind_key(&self) -> Option<BindKey> {
+        let bind = match self {
+            Msg::SetKeybind {
+                key,
+                modifiers,
+                edge,
+                layer,
+                ..
+            }
+            | Msg::RemoveKeybind {
+                key,
+                modifiers,
+                edge,
+                layer,
+            } => BindKey::Key((
+                layer.clone(),
+                modifiers.as_slice().into(),
+                key.to_keysym(),
+                *edge,
+            )),
+            Msg::SetMousebind {
+                modifiers,
+                button,
+                edge,
+                ..
+            } => BindKey::Mouse(modifiers.as_slice().into(), *button, *edge),
+            Msg::SetScrollbind {
+                modifiers,
+                direction,
+                ..
+            } => BindKey::Scroll(modifiers.as_slice().into(), *direction),
+            Msg::SetGesturebind {
+                fingers, direction, ..
+            } => BindKey::Swipe(*fingers, *direction),
+            Msg::SetPinchbind {
+                fingers, direction, ..
+            } => BindKey::Pinch(*fingers, *direction),
+            _ => return None,
+        };
+
+        Some(bind)
+    }
+}
+
+impl State {
+    /// Check `msg` from the client with `stream` against the client limits.
+    ///
+    /// This warns the client if it went over a limit and disconnects it if it went over
+    /// too many times.
+    pub fn check_client_limits(
+        &mut self,
+        msg: &Msg,
+        stream: &Arc<Mutex<UnixStream>>,
+        one_off: bool,
+    ) -> LimitCheck {
+        if LIMITS_DISABLED.load(Ordering::Relaxed) {
+            return LimitCheck::Allow;
+        }
+
+        let limits = self.api_state.client_limits;
+        let window_wait_count = self.api_state.window_waits.len();
+
+        // Messages that were already sent when the client was disconnected
+        if self.api_state.client_usages.usage_mut(stream).disconnected {
+            return LimitCheck::Drop;
+        }
+
+        let now = Instant::now();
+        let (violation, drop) =
+            self.api_state
+                .client_usages
+                .count(msg, stream, &limits, window_wait_count, now);
+
+        let Some(violation) = violation else {
+            return LimitCheck::Allow;
+        };
+
+        let usage = self.api_state.client_usages.usage_mut(stream);
+        usage.violations += 1;
+
+        if usage.violations > limits.max_violations {
+            usage.disconnected = true;
+            tracing::error!(
+                "Disconnecting client that went over its limits too many times: {violation}"
+            );
+
+            if let Err(err) = stream
+                .lock()
+                .expect("Couldn't lock stream")
+                .shutdown(std::net::Shutdown::Both)
+            {
+                tracing::warn!("Failed to shut down client stream: {err}");
+            }
+
+            let is_config = !one_off
+                && self
+                    .api_state
+                    .stream
+                    .as_ref()
+                    .is_some_and(|config_stream| Arc::ptr_eq(config_stream, stream));
+
+            if is_config {
+                tracing::error!("Starting the fallback config");
+                if let Err(err) =
+                    self.start_config(crate::XDG_BASE_DIRS.get_data_home().join("lua"))
+                {
+                    tracing::error!("Failed to start the fallback config: {err}");
+                }
+            }
+
+            return LimitCheck::Drop;
+        }
+
+        tracing::warn!("Client {violation}");
+
+        if !usage
+            .last_warning
+            .is_some_and(|last| now.duration_since(last) < WARNING_INTERVAL)
+        {
+            usage.last_warning = Some(now);
+            let message = format!(
+                "{violation}; {} more messages over the limits will disconnect this client",
+                limits.max_violations - usage.violations
+            );
+            // The client may be too busy to read this, so don't crash if it can't be sent
+            if let Err(err) = super::send_to_client(
+                &mut stream.lock().expect("Couldn't lock stream"),
+                &OutgoingMsg::Warning { message },
+            ) {
+                tracing::warn!("Failed to send warning to client: {err}");
+            }
+        }
+
+        if drop {
+            // Don't leave the client waiting on a response to a dropped request
+            if let Msg::Request { request_id, .. } = msg {
+                crate::window::wait::respond_to_window_wait(stream, *request_id, None);
+            }
+            LimitCheck::Drop
+        } else {
+            LimitCheck::Allow
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::msg::{CallbackId, KeyEdge, KeyIntOrString, Modifier};
+
+    use super::*;
+
+    fn client() -> Arc<Mutex<UnixStream>> {
+        let (stream, _) = UnixStream::pair().expect("Couldn't create stream pair");
+        Arc::new(Mutex::new(stream))
+    }
+
+    fn set_keybind(key: &str) -> Msg {
+        Msg::SetKeybind {
+            key: KeyIntOrString::String(key.to_string()),
+            modifiers: vec![Modifier::Super],
+            edge: KeyEdge::Press,
+            callback_id: CallbackId(0),
+            group: None,
+            description: None,
+            layer: None,
+            passthrough: false,
+            force: false,
+            repeats: false,
+            data: None,
+        }
+    }
+
+    fn remove_keybind(key: &str) -> Msg {
+        Msg::RemoveKeybind {
+            key: KeyIntOrString::String(key.to_string()),
+            modifiers: vec![Modifier::Super],
+            edge: KeyEdge::Press,
+            layer: None,
+        }
+    }
+
+    fn connect() -> Msg {
+        Msg::ConnectDoNotDisturbChanged {
+            callback_id: CallbackId(0),
+        }
+    }
+
+    fn limits(keybinds: usize, callbacks: usize) -> ClientLimits {
+        ClientLimits {
+            keybinds,
+            callbacks,
+            ..Default::default()
+        }
+    }
+
+    /// Send `msg` and get whether it was dropped.
+    fn send(
+        usages: &mut ClientUsages,
+        msg: Msg,
+        stream: &Arc<Mutex<UnixStream>>,
+        limits: &ClientLimits,
+    ) -> bool {
+        usages.count(&msg, stream, limits, 0, Instant::now()).1
+    }
+
+    #[test]
+    fn removing_keybinds_frees_callbacks() {
+        let mut usages = ClientUsages::new();
+        let stream = client();
+        let limits = limits(2, 2);
+
+        assert!(!send(&mut usages, set_keybind("a"), &stream, &limits));
+        assert!(!send(&mut usages, set_keybind("b"), &stream, &limits));
+        assert!(send(&mut usages, set_keybind("c"), &stream, &limits));
+
+        assert!(!send(&mut usages, remove_keybind("a"), &stream, &limits));
+        assert!(!send(&mut usages, set_keybind("c"), &stream, &limits));
+    }
+
+    #[test]
+    fn replacing_a_keybind_counts_once() {
+        let mut usages = ClientUsages::new();
+        let stream = client();
+        let limits = limits(1, 1);
+
+        for _ in 0..10 {
+            assert!(!send(&mut usages, set_keybind("a"), &stream, &limits));
+        }
+        assert!(send(&mut usages, connect(), &stream, &limits));
+    }
+
+    #[test]
+    fn keybinds_are_counted_per_client() {
+        let mut usages = ClientUsages::new();
+        let first = client();
+        let second = client();
+        let limits = limits(1, 10);
+
+        assert!(!send(&mut usages, set_keybind("a"), &first, &limits));
+        assert!(send(&mut usages, set_keybind("b"), &first, &limits));
+        assert!(!send(&mut usages, set_keybind("b"), &second, &limits));
+    }
+
+    #[test]
+    fn replacing_another_clients_keybind_takes_it_over() {
+        let mut usages = ClientUsages::new();
+        let first = client();
+        let second = client();
+        let limits = limits(1, 10);
+
+        assert!(!send(&mut usages, set_keybind("a"), &first, &limits));
+        assert!(!send(&mut usages, set_keybind("a"), &second, &limits));
+
+        // The first client no longer has a keybind set
+        assert!(!send(&mut usages, set_keybind("b"), &first, &limits));
+        assert!(send(&mut usages, set_keybind("c"), &second, &limits));
+    }
+
+    #[test]
+    fn dropped_messages_register_nothing() {
+        let mut usages = ClientUsages::new();
+        let stream = client();
+        let limits = limits(10, 1);
+
+        assert!(!send(&mut usages, connect(), &stream, &limits));
+        assert!(send(&mut usages, set_keybind("a"), &stream, &limits));
+
+        // Removing the dropped keybind doesn't free anything
+        assert!(!send(&mut usages, remove_keybind("a"), &stream, &limits));
+        assert!(send(&mut usages, set_keybind("a"), &stream, &limits));
+    }
+
+    #[test]
+    fn spawns_count_until_they_exit() {
+        let mut usages = ClientUsages::new();
+        let stream = client();
+        let limits = limits(10, 1);
+        let spawn = Msg::Spawn {
+            command: vec!["true".to_string()],
+            callback_id: Some(CallbackId(0)),
+        };
+
+        assert!(!send(&mut usages, spawn, &stream, &limits));
+        let token = usages.spawn_token(&stream);
+        assert!(token.is_some());
+        assert!(send(&mut usages, set_keybind("a"), &stream, &limits));
+
+        drop(token);
+        assert!(!send(&mut usages, set_keybind("a"), &stream, &limits));
+    }
+
+    #[test]
+    fn forgetting_binds_frees_callbacks() {
+        let mut usages = ClientUsages::new();
+        let stream = client();
+        let limits = limits(1, 1);
+
+        assert!(!send(&mut usages, set_keybind("a"), &stream, &limits));
+        usages.forget_binds();
+        assert!(!send(&mut usages, set_keybind("b"), &stream, &limits));
+    }
+}
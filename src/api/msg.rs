@@ -3,15 +3,31 @@
 // The MessagePack format for these is a one-element map where the element's key is the enum name and its
 // value is a map of the enum's values
 
+use std::sync::atomic::{AtomicU64, Ordering};
+
 use smithay::input::keyboard::ModifiersState;
+use xkbcommon::xkb::Keysym;
 
 use crate::{
-    input::libinput::LibinputSetting,
-    layout::Layout,
+    config::reload::ReloadEvent,
+    focus::{mru::CycleDirection, FollowPolicy},
+    handlers::security_context::SandboxPolicy,
+    input::{
+        cursor_warp::CursorWarp,
+        libinput::{DeviceMatcher, DeviceType, LibinputSetting},
+        touch::TouchGestures,
+    },
+    layout::{applied::LaidOutWindow, Layout},
     output::OutputName,
+    render::debug_overlay::DebugOverlayFlags,
+    session::Session,
     tag::TagId,
     window::{
-        rules::{WindowRule, WindowRuleCondition},
+        insert_position::InsertPosition,
+        rules::{FloatingOrTiled, WindowRule, WindowRuleCondition},
+        state_request::{RequestedState, StateRequestDecision},
+        swap::Direction,
+        throttle::BackgroundThrottle,
         window_state::{FullscreenOrMaximized, WindowId},
     },
 };
@@ -25,10 +41,78 @@ pub enum KeyIntOrString {
     String(String),
 }
 
+impl KeyIntOrString {
+    /// Convert this into a [`Keysym`].
+    ///
+    /// Single characters are converted directly, while longer strings
+    /// are looked up by their xkbcommon name.
+    pub fn to_keysym(&self) -> Keysym {
+        match self {
+            KeyIntOrString::Int(num) => Keysym::from(*num),
+            KeyIntOrString::String(s) => {
+                if s.chars().count() == 1 {
+                    let Some(ch) = s.chars().next() else { unreachable!() };
+                    Keysym::from_char(ch)
+                } else {
+                    xkbcommon::xkb::keysym_from_name(s, xkbcommon::xkb::KEYSYM_NO_FLAGS)
+                }
+            }
+        }
+    }
+}
+
 #[derive(Debug, Hash, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq)]
 pub enum MouseEdge {
     Press,
     Release,
+    /// The second press of a double click.
+    DoubleClick,
+}
+
+#[derive(Debug, Hash, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ScrollDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// What happens to a tiled window dragged with the move override when it's dropped.
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MoveOverrideDrop {
+    /// Put the window back in its tile.
+    #[default]
+    ReturnToTile,
+    /// Keep the window floating where it was dropped if that's on another output,
+    /// otherwise put it back in its tile.
+    FloatOnOtherOutput,
+}
+
+#[derive(Debug, Hash, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum SwipeDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+#[derive(Debug, Hash, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum PinchDirection {
+    /// Fingers moving together.
+    In,
+    /// Fingers moving apart.
+    Out,
+}
+
+#[derive(Debug, Hash, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum KeyEdge {
+    Press,
+    Release,
+    Both,
+}
+
+fn default_key_edge() -> KeyEdge {
+    KeyEdge::Press
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -37,7 +121,50 @@ pub enum Msg {
     SetKeybind {
         key: KeyIntOrString,
         modifiers: Vec<Modifier>,
+        #[serde(default = "default_key_edge")]
+        edge: KeyEdge,
         callback_id: CallbackId,
+        /// What kind of keybind this is, like "Apps" or "Tags", for listing keybinds.
+        #[serde(default)]
+        group: Option<String>,
+        /// What this keybind does, for listing keybinds.
+        #[serde(default)]
+        description: Option<String>,
+        /// The layer this keybind is in, or `None` for the default layer.
+        #[serde(default)]
+        layer: Option<String>,
+        /// Whether the key is still sent to the focused window after calling the callback.
+        #[serde(default)]
+        passthrough: bool,
+        /// Whether this keybind triggers even while the focused surface inhibits shortcuts.
+        #[serde(default)]
+        force: bool,
+        /// Whether the callback is called again at the keyboard's repeat rate while the key
+        /// is held. Passthrough keybinds don't repeat; the focused window repeats the key.
+        #[serde(default)]
+        repeats: bool,
+        /// MessagePack data the config attached to this keybind, sent back with its callback.
+        ///
+        /// This lets keybinds share one callback that does something different for each.
+        #[serde(default)]
+        data: Option<Vec<u8>>,
+    },
+    RemoveKeybind {
+        key: KeyIntOrString,
+        modifiers: Vec<Modifier>,
+        #[serde(default = "default_key_edge")]
+        edge: KeyEdge,
+        #[serde(default)]
+        layer: Option<String>,
+    },
+    /// Make `layer` the active keybind layer, or the default layer if `None`.
+    EnterBindLayer {
+        layer: Option<String>,
+    },
+    /// Set whether `layer` sends key presses it has no keybind for to the focused window.
+    SetBindLayerForwardsUnbound {
+        layer: String,
+        forward: bool,
     },
     SetMousebind {
         modifiers: Vec<Modifier>,
@@ -45,6 +172,66 @@ pub enum Msg {
         edge: MouseEdge,
         callback_id: CallbackId,
     },
+    SetScrollbind {
+        modifiers: Vec<Modifier>,
+        direction: ScrollDirection,
+        callback_id: CallbackId,
+    },
+    /// Bind a touchpad swipe with `fingers` fingers.
+    SetGesturebind {
+        fingers: u32,
+        direction: SwipeDirection,
+        callback_id: CallbackId,
+    },
+    /// Bind a touchpad pinch with `fingers` fingers.
+    SetPinchbind {
+        fingers: u32,
+        direction: PinchDirection,
+        /// How far the scale needs to move away from 1.0 for the bind to trigger.
+        threshold: f64,
+        callback_id: CallbackId,
+    },
+    /// Enable or disable the built-in media key handlers.
+    SetMediaKeysEnabled {
+        enabled: bool,
+    },
+    /// Set the command a media key runs. An empty command removes the key's handler.
+    SetMediaKeyCommand {
+        key: KeyIntOrString,
+        command: Vec<String>,
+    },
+    /// Set the modifiers that let a tiled window be dragged freely for one move grab,
+    /// or turn this off if `None`.
+    SetMoveOverride {
+        modifiers: Option<Vec<Modifier>>,
+        #[serde(default)]
+        on_drop: MoveOverrideDrop,
+    },
+    /// Snap windows being moved to edges within `threshold` logical pixels,
+    /// or don't snap if it's 0.
+    SetSnapThreshold {
+        threshold: u32,
+    },
+    /// Lock the pointer to an output, or unlock it if `None`.
+    LockPointerToOutput {
+        output_name: Option<OutputName>,
+    },
+    /// Set how many pixels the pointer needs to be pushed past an output's edge to cross it.
+    SetEdgeResistance {
+        pixels: u32,
+    },
+    /// Set the longest time between the clicks of a double click.
+    SetDoubleClickInterval {
+        interval_ms: u64,
+    },
+    /// Set where the pointer goes when the config focuses a window.
+    SetCursorWarp {
+        warp: CursorWarp,
+    },
+    /// Set what long presses on touchscreens do.
+    SetTouchGestures {
+        gestures: TouchGestures,
+    },
 
     // Window management
     CloseWindow {
@@ -57,6 +244,32 @@ pub enum Msg {
         #[serde(default)]
         height: Option<i32>,
     },
+    /// Move a floating window by `dx` and `dy` logical pixels.
+    MoveWindowBy {
+        window_id: WindowId,
+        dx: i32,
+        dy: i32,
+    },
+    /// Grow a floating window by `dx` and `dy` logical pixels, or move the master split
+    /// by `dx` for a tiled one.
+    ResizeWindowBy {
+        window_id: WindowId,
+        dx: i32,
+        dy: i32,
+    },
+    /// Set the geometry a window has while floating.
+    SetWindowGeometry {
+        window_id: WindowId,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+    },
+    /// Make a window move onto whatever tags become active on its output.
+    SetFollowActiveTag {
+        window_id: WindowId,
+        follow: bool,
+    },
     MoveWindowToTag {
         window_id: WindowId,
         tag_id: TagId,
@@ -74,10 +287,62 @@ pub enum Msg {
     ToggleMaximized {
         window_id: WindowId,
     },
+    SetFloating {
+        window_id: WindowId,
+        floating: bool,
+    },
+    SetFullscreen {
+        window_id: WindowId,
+        fullscreen: bool,
+    },
+    SetMaximized {
+        window_id: WindowId,
+        maximized: bool,
+    },
+    /// Flash a ring of `color` around the window that fades out over `duration_ms`.
+    FlashWindow {
+        window_id: WindowId,
+        duration_ms: u64,
+        color: [f32; 4],
+    },
+    /// Raise the window and the rest of its group to the top of the stack.
+    RaiseWindow {
+        window_id: WindowId,
+    },
+    /// Give the window keyboard focus.
+    FocusWindow {
+        window_id: WindowId,
+    },
+    /// Focus the next or previous window on the active tags of the output named `output_name`,
+    /// or the focused output if `None`.
+    CycleWindowFocus {
+        #[serde(default)]
+        output_name: Option<OutputName>,
+        forward: bool,
+    },
+    /// Focus the next window in the cycle through recently focused windows,
+    /// starting one if needed.
+    CycleMruFocus {
+        direction: CycleDirection,
+    },
+    /// Commit the cycle through recently focused windows.
+    CommitMruCycle,
+    /// Swap the focused window with its neighbor in `direction`.
+    SwapWindowInDirection {
+        direction: Direction,
+    },
     AddWindowRule {
         cond: WindowRuleCondition,
         rule: WindowRule,
     },
+    AddStateRequestHandler {
+        cond: WindowRuleCondition,
+        callback_id: CallbackId,
+    },
+    DecideStateRequest {
+        token: u32,
+        decision: StateRequestDecision,
+    },
     WindowMoveGrab {
         button: u32,
     },
@@ -92,6 +357,10 @@ pub enum Msg {
     SwitchToTag {
         tag_id: TagId,
     },
+    /// Add tags without responding.
+    ///
+    /// Deprecated: use [`Request::AddTags`], which responds with the new tags.
+    /// This will be removed in the next release.
     AddTags {
         /// The name of the output you want these tags on.
         output_name: OutputName,
@@ -105,11 +374,88 @@ pub enum Msg {
         tag_id: TagId,
         layout: Layout,
     },
+    /// Set how much of the width the master window takes up in the tag's layout.
+    SetMasterFactor {
+        tag_id: TagId,
+        master_factor: f64,
+    },
+    /// Set whether new windows on the tag float or tile, or `None` to go by its output.
+    SetTagDefaultWindowMode {
+        tag_id: TagId,
+        #[serde(default)]
+        floating_or_tiled: Option<FloatingOrTiled>,
+    },
+    /// Move a tag to `index` in its output's tag order.
+    SetTagIndex {
+        tag_id: TagId,
+        index: usize,
+    },
 
     // Output management
     ConnectForAllOutputs {
         callback_id: CallbackId,
     },
+    /// Set how long udev needs to be quiet before output hotplugs are applied together.
+    SetHotplugDebounce {
+        debounce_ms: u64,
+    },
+    /// Call a callback whenever the order of tags on an output changes.
+    ConnectTagOrderChanged {
+        callback_id: CallbackId,
+    },
+    /// Call a callback whenever a tag is activated or deactivated.
+    ConnectTagActiveChanged {
+        callback_id: CallbackId,
+    },
+    /// Call a callback whenever an output is plugged in.
+    ConnectOutputConnected {
+        callback_id: CallbackId,
+    },
+    /// Call a callback whenever an output is unplugged.
+    ConnectOutputDisconnected {
+        callback_id: CallbackId,
+    },
+    /// Call a callback whenever an output's mode changes.
+    ConnectOutputModeChanged {
+        callback_id: CallbackId,
+    },
+    /// Call a callback with where windows are every time an output is laid out.
+    ///
+    /// One-off clients can use this too.
+    ConnectLayoutApplied {
+        callback_id: CallbackId,
+    },
+    ConnectKeyboardFocusChanged {
+        callback_id: CallbackId,
+    },
+    ConnectPointerFocusChanged {
+        callback_id: CallbackId,
+    },
+    /// Call a callback whenever a window's title changes.
+    ConnectWindowTitleChanged {
+        callback_id: CallbackId,
+    },
+    /// Call a callback whenever a window's class changes.
+    ConnectWindowClassChanged {
+        callback_id: CallbackId,
+    },
+    /// Call a callback whenever a window opens.
+    ConnectWindowOpened {
+        callback_id: CallbackId,
+    },
+    /// Call a callback whenever a window closes.
+    ConnectWindowClosed {
+        callback_id: CallbackId,
+    },
+    ConnectDeviceAdded {
+        callback_id: CallbackId,
+    },
+    ConnectClientDisconnected {
+        callback_id: CallbackId,
+    },
+    ConnectBindLayerChanged {
+        callback_id: CallbackId,
+    },
     SetOutputLocation {
         output_name: OutputName,
         #[serde(default)]
@@ -117,6 +463,18 @@ pub enum Msg {
         #[serde(default)]
         y: Option<i32>,
     },
+    /// Render the output at `render_scale` times its resolution and scale the result up.
+    SetOutputRenderScale {
+        output_name: OutputName,
+        render_scale: f32,
+    },
+    /// Set whether new windows on the output float or tile when their tags don't say,
+    /// or `None` to tile them.
+    SetOutputDefaultWindowMode {
+        output_name: OutputName,
+        #[serde(default)]
+        floating_or_tiled: Option<FloatingOrTiled>,
+    },
 
     // Process management
     /// Spawn a program with an optional callback.
@@ -131,8 +489,105 @@ pub enum Msg {
     },
 
     // Pinnacle management
+    /// Sent first by clients that send a few messages and exit instead of running a config.
+    ///
+    /// These clients don't replace the config's stream, so callbacks can't be used with them.
+    OneOffClient,
     /// Quit the compositor.
     Quit,
+    /// Restart the config.
+    ReloadConfig,
+    /// Sent by the config once it has registered everything, finishing a reload.
+    ConfigReady,
+    /// Call a callback whenever a config reload starts or finishes.
+    ///
+    /// One-off clients can use this too, and keep getting called across reloads.
+    ConnectReload {
+        callback_id: CallbackId,
+    },
+    /// Log requests that take longer than `threshold_ms` to handle.
+    SetSlowRequestThreshold {
+        threshold_ms: u64,
+    },
+    /// Put windows from the previous session back where they were as they reappear.
+    ApplyPreviousSession,
+    /// Turn do not disturb mode on or off.
+    SetDoNotDisturb {
+        enabled: bool,
+    },
+    /// Set the namespaces of layer surfaces that will be held while in do not disturb mode.
+    SetDoNotDisturbNamespaces {
+        namespaces: Vec<String>,
+    },
+    /// Call a callback whenever do not disturb mode is turned on or off.
+    ConnectDoNotDisturbChanged {
+        callback_id: CallbackId,
+    },
+    /// Call a callback once startup completes, or immediately if it already has.
+    OnStartupComplete {
+        callback_id: CallbackId,
+    },
+    /// Set which annotations the debug overlay draws.
+    SetDebugOverlay {
+        flags: DebugOverlayFlags,
+    },
+    /// Show or hide the window size or location next to the pointer while resizing or moving.
+    SetGrabHints {
+        enabled: bool,
+    },
+    /// Set the font of Pinnacle's own UI. Missing families fall back to sans-serif.
+    SetUiFont {
+        family: String,
+        size: i32,
+    },
+    /// Ask the user for a line of text in a box on the focused output.
+    ///
+    /// One-off clients can use this too.
+    Prompt {
+        title: String,
+        callback_id: CallbackId,
+    },
+    /// Flash a ring around windows when a keybind moves keyboard focus to them.
+    SetFlashOnFocus {
+        enabled: bool,
+    },
+    SetFocusFollowsMovedWindow {
+        policy: FollowPolicy,
+    },
+    /// Move windows that haven't been used for `after_ms` to the tag named `tag_name`
+    /// on their output, or stop if `enabled` is false.
+    SetAutoTidy {
+        after_ms: u64,
+        tag_name: String,
+        enabled: bool,
+    },
+    /// Whether focus changes made through the API count as window activity.
+    SetApiFocusCountsAsActivity {
+        counts: bool,
+    },
+    /// Remember where windows of each class are moved and open new ones there.
+    SetRememberWindowOutput {
+        enabled: bool,
+    },
+    ForgetWindowPlacement {
+        class: String,
+    },
+    /// Whether clicking a window raises it as well as focusing it.
+    SetRaiseOnClick {
+        enabled: bool,
+    },
+    /// Where new windows go in the layout.
+    SetInsertPosition {
+        position: InsertPosition,
+    },
+    /// How frame callbacks are throttled for windows that can't be seen.
+    SetBackgroundThrottle {
+        policy: BackgroundThrottle,
+    },
+    /// Set which privileged protocols sandboxed clients can use.
+    SetSandboxPolicy {
+        policy: SandboxPolicy,
+    },
 
     // Input management
     SetXkbConfig {
@@ -147,8 +602,37 @@ pub enum Msg {
         #[serde(default)]
         options: Option<String>,
     },
+    /// Repeat held keys `rate` times a second after holding them for `delay` milliseconds.
+    SetKeyboardRepeat {
+        rate: i32,
+        delay: i32,
+    },
 
     SetLibinputSetting(LibinputSetting),
+    /// Set a libinput setting for only the devices `device` matches.
+    SetDeviceLibinputSetting {
+        device: DeviceMatcher,
+        setting: LibinputSetting,
+    },
+    /// Spread absolute pointer events from the devices `device` matches over one output,
+    /// or every output if `None`.
+    MapDeviceToOutput {
+        device: DeviceMatcher,
+        output_name: Option<OutputName>,
+    },
+    /// Multiply scroll amounts by `factor` for the device named `device_name`,
+    /// or every device if `None`.
+    SetScrollFactor {
+        factor: f64,
+        #[serde(default)]
+        device_name: Option<String>,
+    },
+    /// Flip horizontal scrolling for the device named `device_name`, or every device if `None`.
+    SetHorizontalScrollInvert {
+        invert: bool,
+        #[serde(default)]
+        device_name: Option<String>,
+    },
 
     Request {
         request_id: RequestId,
@@ -156,8 +640,8 @@ pub enum Msg {
     },
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
-pub struct RequestId(u32);
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct RequestId(pub u32);
 
 #[allow(clippy::enum_variant_names)]
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
@@ -165,13 +649,60 @@ pub struct RequestId(u32);
 pub enum Request {
     // Windows
     GetWindows,
-    GetWindowProps { window_id: WindowId },
+    GetWindowProps {
+        window_id: WindowId,
+    },
+    WaitForWindow {
+        condition: WindowRuleCondition,
+        timeout_ms: u64,
+    },
+    /// Get windows that haven't been used for at least `inactive_ms`.
+    GetInactiveWindows {
+        inactive_ms: u64,
+    },
+    /// Get windows that have no live tags.
+    GetOrphanedWindows,
+    GetPointerFocusedWindow,
+    /// Get the window that was focused before the focused one.
+    GetPreviousFocusedWindow,
     // Outputs
     GetOutputs,
-    GetOutputProps { output_name: String },
+    GetOutputProps {
+        output_name: String,
+    },
     // Tags
     GetTags,
-    GetTagProps { tag_id: TagId },
+    GetTagProps {
+        tag_id: TagId,
+    },
+    /// Add tags and respond with their ids, in the same order as `tag_names`.
+    AddTags {
+        output_name: OutputName,
+        tag_names: Vec<String>,
+    },
+    // Input
+    GetMediaKeyCommands,
+    GetInputDevices,
+    GetKeybinds,
+    // Pinnacle
+    GetDoNotDisturb,
+    GetCompositorInfo,
+    GetRecentDisconnects,
+    /// Get how long each kind of request has taken to handle.
+    GetApiStats,
+    /// Get the session saved when Pinnacle last shut down.
+    GetPreviousSession,
+    /// Get the size `text` is drawn at in the UI font, at a font size of `size_px` or
+    /// the UI font's size.
+    MeasureText {
+        text: String,
+        #[serde(default)]
+        size_px: Option<i32>,
+    },
+    /// Get a plain text description of the outputs, tags, and windows, for debugging.
+    ///
+    /// Crash reports include the same dump.
+    DumpState,
 }
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone, serde::Serialize, serde::Deserialize)]
@@ -208,19 +739,35 @@ impl From<&[Modifier]> for ModifierMask {
     }
 }
 
+/// The modifiers that count for matching binds: the ones held down or latched.
+///
+/// Locked modifiers are left out, so a bind still matches with Caps Lock or Num Lock on,
+/// even with keymap options like `caps:shiftlock` that make Caps Lock lock Shift.
+/// Latched modifiers, like ones set by sticky keys, count as if they were held.
+///
+/// This reads the keyboard's own state, which grabs like an input method's don't change,
+/// so binds see the same modifiers no matter what has grabbed the keyboard.
 impl From<ModifiersState> for ModifierMask {
     fn from(state: ModifiersState) -> Self {
+        // The core modifiers always have these indices in xkb keymaps
+        const SHIFT: u32 = 1 << 0;
+        const CTRL: u32 = 1 << 2;
+        const ALT: u32 = 1 << 3;
+        const SUPER: u32 = 1 << 6;
+
+        let active = state.serialized.depressed | state.serialized.latched;
+
         let mut mask: u8 = 0b0000_0000;
-        if state.shift {
+        if active & SHIFT != 0 {
             mask |= Modifier::Shift as u8;
         }
-        if state.ctrl {
+        if active & CTRL != 0 {
             mask |= Modifier::Ctrl as u8;
         }
-        if state.alt {
+        if active & ALT != 0 {
             mask |= Modifier::Alt as u8;
         }
-        if state.logo {
+        if active & SUPER != 0 {
             mask |= Modifier::Super as u8;
         }
         Self(mask)
@@ -228,7 +775,6 @@ impl From<ModifiersState> for ModifierMask {
 }
 
 impl ModifierMask {
-    #[allow(dead_code)]
     pub fn values(self) -> Vec<Modifier> {
         let mut res = Vec::<Modifier>::new();
         if self.0 & Modifier::Shift as u8 == Modifier::Shift as u8 {
@@ -254,11 +800,28 @@ pub enum OutgoingMsg {
         callback_id: CallbackId,
         #[serde(default)]
         args: Option<Args>,
+        /// Increases by one with every callback sent to a client, starting at 1,
+        /// so the client can tell if it missed any.
+        serial: u64,
     },
     RequestResponse {
         request_id: RequestId,
         response: RequestResponse,
     },
+    /// Something the client did wrong that it should know about, like going over its limits.
+    Warning { message: String },
+}
+
+impl OutgoingMsg {
+    /// Create a message that calls the callback with `callback_id`,
+    /// taking the next serial from `serial`.
+    pub fn call_callback(callback_id: CallbackId, args: Option<Args>, serial: &AtomicU64) -> Self {
+        Self::CallCallback {
+            callback_id,
+            args,
+            serial: serial.fetch_add(1, Ordering::Relaxed) + 1,
+        }
+    }
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
@@ -276,6 +839,106 @@ pub enum Args {
     },
     ConnectForAllOutputs {
         output_name: String,
+        /// Whether the output was already set up by the config before it was reloaded
+        is_replay: bool,
+        /// The names of the tags the output had before the reload
+        tag_names: Vec<String>,
+        /// The names of the tags that were active before the reload
+        active_tags: Vec<String>,
+    },
+    TagOrderChanged {
+        output_name: String,
+    },
+    /// A tag was activated or deactivated.
+    TagActiveChanged {
+        tag_id: TagId,
+        name: String,
+        output_name: Option<String>,
+        active: bool,
+    },
+    /// An output connected, disconnected, or changed modes, depending on what was connected.
+    OutputGeometry {
+        output_name: String,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+    },
+    /// The windows on the active tags of an output were laid out.
+    LayoutApplied {
+        output_name: String,
+        /// The first active tag on the output
+        tag_id: TagId,
+        /// Goes up by one with every layout sent, so older ones can be told apart
+        generation: u64,
+        /// Every window on the active tags with where it is, in layout order
+        windows: Vec<LaidOutWindow>,
+    },
+    /// The window with keyboard or pointer focus changed, depending on what was connected.
+    WindowFocusChanged {
+        window_id: Option<WindowId>,
+    },
+    /// A window's title changed.
+    WindowTitleChanged {
+        window_id: WindowId,
+        title: Option<String>,
+    },
+    /// A window's class changed.
+    WindowClassChanged {
+        window_id: WindowId,
+        class: Option<String>,
+    },
+    /// A window opened, after window rules were applied.
+    WindowOpened {
+        window_id: WindowId,
+        class: Option<String>,
+        title: Option<String>,
+    },
+    /// A window closed. Its class and title are the last ones it had.
+    WindowClosed {
+        window_id: WindowId,
+        class: Option<String>,
+        title: Option<String>,
+    },
+    /// An input device was added.
+    DeviceAdded {
+        name: String,
+        device_types: Vec<DeviceType>,
+    },
+    /// A Wayland client with windows disconnected.
+    ClientDisconnected {
+        disconnect: ClientDisconnectProps,
+    },
+    /// The active keybind layer changed. `None` is the default layer.
+    BindLayerChanged {
+        layer: Option<String>,
+        previous: Option<String>,
+    },
+    /// A config reload started or finished.
+    ReloadEvent {
+        event: ReloadEvent,
+    },
+    /// Do not disturb mode was turned on or off.
+    DoNotDisturbChanged {
+        enabled: bool,
+    },
+    /// A window requested to be put in `state`. Respond with the `token`.
+    StateRequest {
+        window_id: WindowId,
+        token: u32,
+        state: RequestedState,
+    },
+    /// A keybind with data attached was triggered.
+    KeybindData {
+        data: Vec<u8>,
+    },
+    /// A mousebind was triggered with these modifiers counted as held.
+    MousebindTriggered {
+        modifiers: Vec<Modifier>,
+    },
+    /// A prompt was finished. `text` is `None` if it was cancelled.
+    PromptFinished {
+        text: Option<String>,
     },
 }
 
@@ -295,6 +958,21 @@ pub enum RequestResponse {
         focused: Option<bool>,
         floating: Option<bool>,
         fullscreen_or_maximized: Option<FullscreenOrMaximized>,
+        /// The width and height increments the window wants to be resized in, if any.
+        #[serde(default)]
+        resize_increments: Option<(i32, i32)>,
+        /// How many seconds it's been since the window was last used.
+        #[serde(default)]
+        seconds_since_activity: Option<u64>,
+        /// Whether the window is keeping keybinds from triggering while it's focused.
+        #[serde(default)]
+        keyboard_shortcuts_inhibited: Option<bool>,
+        /// The id of the window's group, which is the id of the first window that opened in it.
+        #[serde(default)]
+        group: Option<u32>,
+        /// The app id the window's sandbox gave it, if it's sandboxed.
+        #[serde(default)]
+        sandbox_app_id: Option<String>,
     },
     Output {
         output_name: Option<String>,
@@ -318,6 +996,10 @@ pub enum RequestResponse {
         /// Whether the output is focused or not.
         focused: Option<bool>,
         tag_ids: Option<Vec<TagId>>,
+        /// The fraction of the output's resolution it is rendered at.
+        render_scale: Option<f32>,
+        /// Whether new windows on the output float or tile when their tags don't say.
+        default_window_mode: Option<FloatingOrTiled>,
     },
     Tags {
         tag_ids: Vec<TagId>,
@@ -326,5 +1008,164 @@ pub enum RequestResponse {
         active: Option<bool>,
         name: Option<String>,
         output_name: Option<String>,
+        master_factor: Option<f64>,
+        /// Whether new windows on the tag float or tile, if it overrides its output.
+        default_window_mode: Option<FloatingOrTiled>,
+    },
+    DoNotDisturb {
+        enabled: bool,
+    },
+    CompositorInfo {
+        version: String,
+        backend: String,
+        software_rendering: bool,
+        /// How many times the event loop has woken up since Pinnacle started.
+        event_loop_wakeups: u64,
+    },
+    RecentDisconnects {
+        disconnects: Vec<ClientDisconnectProps>,
+    },
+    ApiStats {
+        /// The upper bounds of the histogram buckets in microseconds.
+        /// The last bucket has everything slower.
+        bucket_bounds_us: Vec<u64>,
+        requests: Vec<RequestStatsProps>,
     },
+    PreviousSession {
+        /// `None` if no session was saved or it couldn't be read.
+        session: Option<Session>,
+    },
+    /// The size of text in logical pixels.
+    TextSize {
+        width: i32,
+        height: i32,
+    },
+    StateDump {
+        dump: String,
+    },
+    MediaKeyCommands {
+        enabled: bool,
+        /// Pairs of raw keysyms and the commands they run.
+        commands: Vec<(u32, Vec<String>)>,
+    },
+    InputDevices {
+        devices: Vec<InputDeviceProps>,
+    },
+    Keybinds {
+        keybinds: Vec<KeybindProps>,
+    },
+}
+
+/// Properties of a connected input device.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct InputDeviceProps {
+    pub name: String,
+    /// How much scroll amounts from this device are multiplied by.
+    pub scroll_factor: f64,
+    /// Whether horizontal scrolling from this device is flipped.
+    pub horizontal_scroll_invert: bool,
+}
+
+/// A keybind set by the config.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct KeybindProps {
+    pub modifiers: Vec<Modifier>,
+    /// The raw keysym.
+    pub key: u32,
+    /// The xkbcommon name of the keysym.
+    pub key_name: String,
+    pub edge: KeyEdge,
+    pub group: Option<String>,
+    pub description: Option<String>,
+    /// The keybind layer, or `None` for the default layer.
+    pub layer: Option<String>,
+    /// Whether the key is still sent to the focused window.
+    pub passthrough: bool,
+    /// Whether this keybind triggers even while the focused surface inhibits shortcuts.
+    pub force: bool,
+    /// Whether the callback is called again while the key is held.
+    pub repeats: bool,
+}
+
+/// Why a Wayland client disconnected.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum ClientDisconnectReason {
+    /// The client closed its connection after closing its windows.
+    Clean,
+    /// The client's connection closed while it still had windows open.
+    /// This is what happens when it crashes or is killed.
+    Crashed,
+    /// Pinnacle disconnected the client because it broke the Wayland protocol.
+    ProtocolError {
+        interface: String,
+        object_id: u32,
+        code: u32,
+        message: String,
+    },
+}
+
+/// How long one kind of request has taken to handle.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RequestStatsProps {
+    pub name: String,
+    pub count: u64,
+    pub total_us: u64,
+    pub max_us: u64,
+    /// How many requests took as long as each bucket in the histogram.
+    pub buckets: Vec<u64>,
+}
+
+/// A Wayland client that disconnected.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ClientDisconnectProps {
+    pub pid: Option<i32>,
+    pub process_name: Option<String>,
+    pub reason: ClientDisconnectReason,
+    /// The client's windows that were open when it disconnected.
+    pub window_ids: Vec<WindowId>,
+    pub seconds_ago: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHIFT: u32 = 1 << 0;
+    const LOCK: u32 = 1 << 1;
+    const CTRL: u32 = 1 << 2;
+    const SUPER: u32 = 1 << 6;
+
+    fn mask(depressed: u32, latched: u32, locked: u32) -> Vec<Modifier> {
+        let mut state = ModifiersState::default();
+        state.serialized.depressed = depressed;
+        state.serialized.latched = latched;
+        state.serialized.locked = locked;
+        ModifierMask::from(state).values()
+    }
+
+    #[test]
+    fn held_modifiers_count() {
+        assert_eq!(mask(SUPER, 0, 0), [Modifier::Super]);
+        assert_eq!(mask(SHIFT | CTRL, 0, 0), [Modifier::Shift, Modifier::Ctrl]);
+    }
+
+    #[test]
+    fn latched_modifiers_count() {
+        assert_eq!(mask(0, SHIFT, 0), [Modifier::Shift]);
+        assert_eq!(mask(CTRL, SUPER, 0), [Modifier::Ctrl, Modifier::Super]);
+    }
+
+    #[test]
+    fn locked_modifiers_dont_count() {
+        // Caps Lock
+        assert_eq!(mask(SUPER, 0, LOCK), [Modifier::Super]);
+        // Caps Lock with `caps:shiftlock`
+        assert_eq!(mask(SUPER, 0, SHIFT | LOCK), [Modifier::Super]);
+        assert!(mask(0, 0, SHIFT).is_empty());
+    }
+
+    #[test]
+    fn locked_and_held_modifier_counts() {
+        assert_eq!(mask(SHIFT, 0, SHIFT), [Modifier::Shift]);
+    }
 }
@@ -0,0 +1,140 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! How long requests from clients take to handle.
+//!
+//! Every request is timed where it's dispatched, so new requests are covered without doing
+//! anything. Times are kept per kind of request in a histogram with fixed buckets, and
+//! requests that take longer than the slow request threshold are logged.
+//!
+//! Configs can get the histograms with `Request::GetApiStats` to see which requests make
+//! them feel slow.
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Write},
+    time::Duration,
+};
+
+use crate::state::State;
+
+use super::msg::{Request, RequestStatsProps};
+
+/// How long a request can take before it's logged, unless the config changes it.
+pub const DEFAULT_SLOW_REQUEST_THRESHOLD: Duration = Duration::from_millis(10);
+
+/// The upper bounds of the histogram buckets, in microseconds.
+///
+/// There's one more bucket for everything slower than the last bound.
+pub const BUCKET_BOUNDS_US: [u64; 10] = [
+    50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 100_000,
+];
+
+#[derive(Debug)]
+pub struct ApiStats {
+    /// Requests taking longer than this are logged.
+    pub slow_request_threshold: Duration,
+    requests: HashMap<String, RequestTimes>,
+}
+
+impl Default for ApiStats {
+    fn default() -> Self {
+        Self {
+            slow_request_threshold: DEFAULT_SLOW_REQUEST_THRESHOLD,
+            requests: HashMap::new(),
+        }
+    }
+}
+
+/// How long one kind of request has taken.
+#[derive(Debug, Default)]
+struct RequestTimes {
+    count: u64,
+    total: Duration,
+    max: Duration,
+    buckets: [u64; BUCKET_BOUNDS_US.len() + 1],
+}
+
+impl RequestTimes {
+    fn record(&mut self, elapsed: Duration) {
+        let micros = elapsed.as_micros() as u64;
+        let bucket = BUCKET_BOUNDS_US
+            .iter()
+            .position(|bound| micros <= *bound)
+            .unwrap_or(BUCKET_BOUNDS_US.len());
+
+        self.count += 1;
+        self.total += elapsed;
+        self.max = self.max.max(elapsed);
+        self.buckets[bucket] += 1;
+    }
+}
+
+impl Request {
+    /// Get the name of this kind of request, like `GetWindowProps`.
+    pub fn name(&self) -> String {
+        variant_name(self)
+    }
+}
+
+impl State {
+    /// Record that the request called `name` took `elapsed` to handle.
+    pub fn record_request_time(&mut self, name: String, elapsed: Duration) {
+        let stats = &mut self.api_state.stats;
+
+        if elapsed > stats.slow_request_threshold {
+            tracing::warn!(
+                "Handling {name} took {:.1}ms, over the {}ms slow request threshold",
+                elapsed.as_secs_f64() * 1000.0,
+                stats.slow_request_threshold.as_millis()
+            );
+        }
+
+        stats.requests.entry(name).or_default().record(elapsed);
+    }
+
+    /// Get how long each kind of request has taken, sorted by name.
+    pub fn request_stats(&self) -> Vec<RequestStatsProps> {
+        let mut stats = self
+            .api_state
+            .stats
+            .requests
+            .iter()
+            .map(|(name, times)| RequestStatsProps {
+                name: name.clone(),
+                count: times.count,
+                total_us: times.total.as_micros() as u64,
+                max_us: times.max.as_micros() as u64,
+                buckets: times.buckets.to_vec(),
+            })
+            .collect::<Vec<_>>();
+
+        stats.sort_by(|a, b| a.name.cmp(&b.name));
+        stats
+    }
+}
+
+/// Get the name of the enum variant `value` is from its `Debug` output.
+///
+/// Formatting stops as soon as the name is read, so fields aren't formatted.
+fn variant_name(value: &impl Debug) -> String {
+    struct NameWriter(String);
+
+    impl Write for NameWriter {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            let end = s
+                .find(|c: char| !c.is_alphanumeric() && c != '_')
+                .unwrap_or(s.len());
+            self.0.push_str(&s[..end]);
+
+            if end < s.len() {
+                Err(fmt::Error)
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    let mut writer = NameWriter(String::new());
+    let _ = write!(writer, "{value:?}");
+    writer.0
+}
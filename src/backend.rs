@@ -29,7 +29,7 @@ use smithay::{
 
 use crate::{
     state::{State, SurfaceDmabufFeedback},
-    window::WindowElement,
+    window::{throttle::BackgroundThrottle, WindowElement},
 };
 
 use self::{udev::Udev, winit::Winit};
@@ -59,6 +59,20 @@ impl Backend {
         }
     }
 
+    /// Get why rendering is done in software instead of on the GPU, if it is.
+    pub fn software_rendering(&self) -> Option<&str> {
+        match self {
+            Backend::Winit(_) => None,
+            Backend::Udev(udev) => udev.software_rendering.as_deref(),
+        }
+    }
+
+    /// Get the warning a config gets when it connects while rendering is done in software.
+    pub fn software_rendering_warning(&self) -> Option<String> {
+        self.software_rendering()
+            .map(|reason| format!("Pinnacle is rendering in software because {reason}"))
+    }
+
     /// Returns `true` if the backend is [`Winit`].
     ///
     /// [`Winit`]: Backend::Winit
@@ -93,6 +107,7 @@ pub fn post_repaint(
     space: &Space<WindowElement>,
     dmabuf_feedback: Option<SurfaceDmabufFeedback<'_>>,
     time: Duration,
+    background_throttle: BackgroundThrottle,
 ) {
     // let throttle = Some(Duration::from_secs(1));
     let throttle = Some(Duration::ZERO);
@@ -115,7 +130,13 @@ pub fn post_repaint(
         });
 
         if space.outputs_for_element(window).contains(output) {
-            window.send_frame(output, time, throttle, surface_primary_scanout_output);
+            window.send_background_frame(
+                output,
+                time,
+                space,
+                background_throttle,
+                surface_primary_scanout_output,
+            );
             if let Some(dmabuf_feedback) = dmabuf_feedback {
                 window.send_dmabuf_feedback(
                     output,
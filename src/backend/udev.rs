@@ -3,7 +3,8 @@
 use std::{
     collections::{HashMap, HashSet},
     ffi::OsString,
-    os::fd::FromRawFd,
+    os::fd::{FromRawFd, OwnedFd},
+    panic::AssertUnwindSafe,
     path::Path,
     time::Duration,
 };
@@ -26,8 +27,13 @@ use smithay::{
         egl::{self, EGLDevice, EGLDisplay},
         libinput::{LibinputInputBackend, LibinputSessionInterface},
         renderer::{
-            damage::{self},
-            element::{texture::TextureBuffer, RenderElement, RenderElementStates},
+            damage::{self, OutputDamageTracker},
+            element::{
+                surface::WaylandSurfaceRenderElement,
+                texture::{TextureBuffer, TextureRenderBuffer, TextureRenderElement},
+                utils::RescaleRenderElement,
+                Kind, RenderElement, RenderElementStates, Wrap,
+            },
             gles::{GlesRenderer, GlesTexture},
             multigpu::{gbm::GbmGlesBackend, GpuManager, MultiRenderer, MultiTexture},
             Bind, ExportMem, ImportDma, ImportEgl, ImportMemWl, Offscreen, Renderer,
@@ -51,7 +57,7 @@ use smithay::{
         ash::vk::ExtPhysicalDeviceDrmFn,
         calloop::{EventLoop, LoopHandle, RegistrationToken},
         drm::{
-            control::{connector, crtc, ModeTypeFlags},
+            control::{connector, crtc, Device as ControlDevice, ModeTypeFlags},
             Device,
         },
         input::Libinput,
@@ -64,7 +70,9 @@ use smithay::{
             backend::GlobalId, protocol::wl_surface::WlSurface, Display, DisplayHandle,
         },
     },
-    utils::{Clock, DeviceFd, IsAlive, Logical, Monotonic, Point, Transform},
+    utils::{
+        Clock, DeviceFd, IsAlive, Logical, Monotonic, Physical, Point, Rectangle, Size, Transform,
+    },
     wayland::dmabuf::{DmabufFeedback, DmabufFeedbackBuilder, DmabufGlobal, DmabufState},
 };
 use smithay_drm_extras::{
@@ -76,14 +84,23 @@ use crate::{
     api::msg::{Args, OutgoingMsg},
     backend::Backend,
     config::ConnectorSavedState,
+    handlers::{gamma_control::GammaControlState, screencopy::ScreencopyState},
     output::OutputName,
-    render::{pointer::PointerElement, take_presentation_feedback},
+    render::{
+        debug_overlay::DebugOverlay, grab_hint::GrabHint, pointer::PointerElement,
+        prompt_box::PromptBox, take_presentation_feedback, window_flash::WindowFlashState,
+        OutputRenderElements,
+    },
     state::{CalloopData, State, SurfaceDmabufFeedback, WithState},
-    window::WindowElement,
+    window::{throttle::BackgroundThrottle, WindowElement},
 };
 
+use self::hotplug::HotplugState;
+
 use super::BackendData;
 
+pub mod hotplug;
+
 const SUPPORTED_FORMATS: &[Fourcc] = &[
     Fourcc::Abgr2101010,
     Fourcc::Argb2101010,
@@ -117,6 +134,10 @@ pub struct Udev {
     pointer_images: Vec<(xcursor::parser::Image, TextureBuffer<MultiTexture>)>,
     pointer_element: PointerElement<MultiTexture>,
     pointer_image: crate::cursor::Cursor,
+    /// Why rendering is done in software instead of on the GPU, if it is.
+    pub(super) software_rendering: Option<String>,
+    /// Connector changes waiting to be applied together
+    pub hotplug: HotplugState,
 }
 
 impl Backend {
@@ -155,6 +176,49 @@ impl Udev {
     }
 }
 
+impl Udev {
+    /// Get the DRM device and CRTC `output` is on.
+    fn drm_crtc(&self, output: &Output) -> Option<(&DrmDevice, crtc::Handle)> {
+        let UdevOutputData { device_id, crtc } = output.user_data().get()?;
+        let device = self.backends.get(device_id)?;
+        Some((&device.drm, *crtc))
+    }
+
+    /// Get how many entries each of the gamma ramps of `output`'s CRTC has,
+    /// or `None` if its gamma can't be set.
+    pub fn gamma_size(&self, output: &Output) -> Option<u32> {
+        let (drm, crtc) = self.drm_crtc(output)?;
+        let size = drm.get_crtc(crtc).ok()?.gamma_length();
+        (size > 0).then_some(size)
+    }
+
+    /// Get the red, green, and blue gamma ramps of `output`'s CRTC one after another.
+    pub fn gamma(&self, output: &Output) -> anyhow::Result<Vec<u16>> {
+        let (drm, crtc) = self.drm_crtc(output).context("output has no crtc")?;
+        let size = drm.get_crtc(crtc)?.gamma_length() as usize;
+
+        let mut ramps = vec![0; size * 3];
+        let (red, rest) = ramps.split_at_mut(size);
+        let (green, blue) = rest.split_at_mut(size);
+        drm.get_gamma(crtc, red, green, blue)?;
+
+        Ok(ramps)
+    }
+
+    /// Set the gamma ramps of `output`'s CRTC to `ramps`, which are red, green,
+    /// and blue one after another.
+    pub fn set_gamma(&self, output: &Output, ramps: &[u16]) -> anyhow::Result<()> {
+        let (drm, crtc) = self.drm_crtc(output).context("output has no crtc")?;
+
+        let size = ramps.len() / 3;
+        let (red, rest) = ramps.split_at(size);
+        let (green, blue) = rest.split_at(size);
+        drm.set_gamma(crtc, red, green, blue)?;
+
+        Ok(())
+    }
+}
+
 impl State {
     /// Switch the tty.
     ///
@@ -235,7 +299,41 @@ impl BackendData for Udev {
     }
 }
 
-pub fn run_udev() -> anyhow::Result<()> {
+/// Get the name of the output on `connector`, like `DP-1`.
+fn connector_name(connector: &connector::Info) -> String {
+    format!(
+        "{}-{}",
+        connector.interface().as_str(),
+        connector.interface_id()
+    )
+}
+
+/// Run `f` with Mesa set to render in software with llvmpipe.
+///
+/// Mesa picks its driver when a gbm device or EGL display is created, so only those need to be
+/// created in `f`. The environment is put back afterwards so clients Pinnacle spawns don't
+/// render in software too.
+fn with_software_rendering<T>(f: impl FnOnce() -> T) -> T {
+    const VARS: [&str; 2] = ["LIBGL_ALWAYS_SOFTWARE", "GBM_ALWAYS_SOFTWARE"];
+
+    let previous = VARS.map(std::env::var_os);
+    for var in VARS {
+        std::env::set_var(var, "1");
+    }
+
+    let ret = f();
+
+    for (var, previous) in VARS.into_iter().zip(previous) {
+        match previous {
+            Some(value) => std::env::set_var(var, value),
+            None => std::env::remove_var(var),
+        }
+    }
+
+    ret
+}
+
+pub fn run_udev(ready_fd: Option<OwnedFd>, force_software_renderer: bool) -> anyhow::Result<()> {
     let mut event_loop = EventLoop::try_new()?;
     let display = Display::new()?;
 
@@ -260,6 +358,11 @@ pub fn run_udev() -> anyhow::Result<()> {
         });
     tracing::info!("Using {} as primary gpu.", primary_gpu);
 
+    let software_rendering = force_software_renderer.then(|| {
+        tracing::info!("Rendering in software because --force-software-renderer was passed");
+        "--force-software-renderer was passed".to_string()
+    });
+
     let gpu_manager = GpuManager::new(GbmGlesBackend::default())?;
 
     let data = Udev {
@@ -273,6 +376,8 @@ pub fn run_udev() -> anyhow::Result<()> {
         pointer_image: crate::cursor::Cursor::load(),
         pointer_images: Vec::new(),
         pointer_element: PointerElement::default(),
+        software_rendering,
+        hotplug: HotplugState::default(),
     };
 
     let display_handle = display.handle();
@@ -284,6 +389,8 @@ pub fn run_udev() -> anyhow::Result<()> {
         event_loop.handle(),
     )?;
 
+    state.startup.ready_fd = ready_fd;
+
     // Initialize the udev backend
     let udev_backend = UdevBackend::new(state.seat.name())?;
 
@@ -297,6 +404,10 @@ pub fn run_udev() -> anyhow::Result<()> {
         }
     }
 
+    // Every output connected at startup has been set up by now. If none were,
+    // startup will complete when one gets plugged in.
+    state.startup.outputs_initialized = true;
+
     let udev = state.backend.udev_mut();
 
     event_loop
@@ -313,7 +424,8 @@ pub fn run_udev() -> anyhow::Result<()> {
             }
             UdevEvent::Changed { device_id } => {
                 if let Ok(node) = DrmNode::from_dev_id(device_id) {
-                    data.state.device_changed(node)
+                    data.state.device_changed(node);
+                    data.state.schedule_connector_changes();
                 }
             }
             // GPU disconnected
@@ -339,7 +451,7 @@ pub fn run_udev() -> anyhow::Result<()> {
     let insert_ret = event_loop
         .handle()
         .insert_source(libinput_backend, move |event, _, data| {
-            data.state.apply_libinput_settings(&event);
+            data.state.handle_libinput_device_event(&event);
             data.state.process_input_event(event);
         });
 
@@ -396,11 +508,11 @@ pub fn run_udev() -> anyhow::Result<()> {
 
                     for (node, connectors) in connectors {
                         for (connector, crtc) in connectors {
-                            data.state
-                                .connector_disconnected(node, connector.clone(), crtc);
-                            data.state.connector_connected(node, connector, crtc);
+                            udev.hotplug.disconnected(node, connector.clone(), crtc);
+                            udev.hotplug.connected(node, connector, crtc);
                         }
                     }
+                    data.state.apply_connector_changes();
                     // for output in data.state.space.outputs().cloned().collect::<Vec<_>>() {
                     //     data.state.schedule_render(&output);
                     // }
@@ -486,6 +598,9 @@ pub fn run_udev() -> anyhow::Result<()> {
         .create_global_with_default_feedback::<State>(&display_handle, &default_feedback);
     udev.dmabuf_state = Some((dmabuf_state, global));
 
+    // Gamma is set on CRTCs, so only this backend can support gamma control
+    GammaControlState::create_global(&display_handle);
+
     let gpu_manager = &mut udev.gpu_manager;
     udev.backends.values_mut().for_each(|backend_data| {
         // Update the per drm surface dmabuf feedback
@@ -509,24 +624,38 @@ pub fn run_udev() -> anyhow::Result<()> {
         |_| {},
     ) {
         tracing::error!("Failed to start XWayland: {err}");
+        state.startup.xwayland_done = true;
     }
 
-    event_loop.run(
-        Some(Duration::from_micros(((1.0 / 144.0) * 1000000.0) as u64)),
-        &mut CalloopData {
-            state,
-            display_handle,
-        },
-        |data| {
+    state.check_startup_complete();
+
+    let mut data = CalloopData {
+        state,
+        display_handle,
+    };
+
+    // Rendering is driven by vblanks and scheduled renders, so there's no need to wake up
+    // until something happens
+    let run = std::panic::catch_unwind(AssertUnwindSafe(|| {
+        event_loop.run(None, &mut data, |data| {
+            data.state.event_loop_wakeups += 1;
             data.state.space.refresh();
             data.state.popup_manager.cleanup();
+            data.state.refresh_foreign_toplevels();
             data.display_handle
                 .flush_clients()
                 .expect("failed to flush_clients");
 
-            data.state.focus_state.fix_up_focus(&mut data.state.space);
-        },
-    )?;
+            data.state.fix_up_focus();
+        })
+    }));
+
+    match run {
+        Ok(result) => result?,
+        Err(_) => crate::crash::abort_after_panic(data),
+    }
+
+    data.state.save_session();
 
     Ok(())
 }
@@ -551,6 +680,8 @@ enum DeviceAddError {
     GbmDevice(std::io::Error),
     #[error("Failed to access drm node: {0}")]
     DrmNode(CreateDrmNodeError),
+    #[error("Failed to create EGLDisplay: {0}")]
+    EglDisplay(egl::Error),
     #[error("Failed to add device to GpuManager: {0}")]
     AddNode(egl::Error),
 }
@@ -660,6 +791,8 @@ struct RenderSurface {
     compositor: GbmDrmCompositor,
     dmabuf_feedback: Option<DrmSurfaceDmabufFeedback>,
     render_state: RenderState,
+    /// Where the output is rendered to when its render scale is below 1.0.
+    scaled_target: Option<ScaledRenderTarget>,
 }
 
 impl Drop for RenderSurface {
@@ -719,6 +852,187 @@ where
         })
 }
 
+/// An offscreen buffer an output is rendered to at a reduced resolution before being scaled
+/// up to the output's size.
+struct ScaledRenderTarget {
+    buffer: TextureRenderBuffer<GlesTexture>,
+    damage_tracker: OutputDamageTracker,
+    /// The size of the buffer.
+    size: Size<i32, Physical>,
+    /// The output scale the damage tracker was created with.
+    scale: f64,
+    /// The age of the buffer's contents, which is 0 until it has been rendered to.
+    age: usize,
+}
+
+impl ScaledRenderTarget {
+    fn new(
+        renderer: &mut UdevRenderer<'_, '_>,
+        size: Size<i32, Physical>,
+        scale: f64,
+    ) -> Result<Self, SwapBuffersError> {
+        let texture = Offscreen::<GlesTexture>::create_buffer(
+            renderer,
+            Fourcc::Abgr8888,
+            (size.w, size.h).into(),
+        )
+        .map_err(Into::<SwapBuffersError>::into)?;
+
+        let gles_renderer: &mut GlesRenderer = renderer.as_mut();
+        let buffer =
+            TextureRenderBuffer::from_texture(&*gles_renderer, texture, 1, Transform::Normal, None);
+
+        Ok(Self {
+            buffer,
+            damage_tracker: OutputDamageTracker::new(size, scale, Transform::Normal),
+            size,
+            scale,
+            age: 0,
+        })
+    }
+}
+
+/// Render everything but the pointer into `target` at `render_scale` times the output's
+/// resolution.
+///
+/// Returns the pointer elements along with an element that scales the buffer up to the
+/// output's size. The pointer is left out of the buffer so it stays sharp and can still go on
+/// the cursor plane.
+fn render_scaled<'a, 'b>(
+    target: &mut Option<ScaledRenderTarget>,
+    renderer: &mut UdevRenderer<'a, 'b>,
+    output: &Output,
+    elements: Vec<
+        OutputRenderElements<
+            UdevRenderer<'a, 'b>,
+            WaylandSurfaceRenderElement<UdevRenderer<'a, 'b>>,
+        >,
+    >,
+    render_scale: f64,
+    clear_color: [f32; 4],
+) -> Result<
+    Vec<OutputRenderElements<UdevRenderer<'a, 'b>, TextureRenderElement<GlesTexture>>>,
+    SwapBuffersError,
+> {
+    let scale = output.current_scale().fractional_scale();
+    let output_size = output
+        .current_mode()
+        .map(|mode| output.current_transform().transform_size(mode.size))
+        .unwrap_or_default();
+    let size = Size::<i32, Physical>::from((
+        (output_size.w as f64 * render_scale).round() as i32,
+        (output_size.h as f64 * render_scale).round() as i32,
+    ));
+
+    let target = match target {
+        Some(target) if target.size == size && target.scale == scale => target,
+        _ => target.insert(ScaledRenderTarget::new(renderer, size, scale)?),
+    };
+
+    let mut pointer_elements = Vec::new();
+    let mut scaled_elements = Vec::new();
+    for element in elements {
+        match element {
+            OutputRenderElements::Pointer(element) => {
+                pointer_elements.push(OutputRenderElements::Pointer(element));
+            }
+            element => scaled_elements.push(RescaleRenderElement::from_element(
+                element,
+                (0, 0).into(),
+                render_scale,
+            )),
+        }
+    }
+
+    let age = target.age;
+    let damage_tracker = &mut target.damage_tracker;
+    target
+        .buffer
+        .render()
+        .draw(|texture| {
+            renderer
+                .bind(texture.clone())
+                .map_err(damage::Error::Rendering)?;
+            let result =
+                damage_tracker.render_output(renderer, age, &scaled_elements, clear_color)?;
+
+            // The buffer isn't transformed, so its physical and buffer coordinates are the same
+            Ok(result
+                .damage
+                .unwrap_or_default()
+                .into_iter()
+                .map(|rect| {
+                    Rectangle::from_loc_and_size(
+                        (rect.loc.x, rect.loc.y),
+                        (rect.size.w, rect.size.h),
+                    )
+                })
+                .collect())
+        })
+        .map_err(|err| match err {
+            damage::Error::Rendering(err) => err.into(),
+            _ => unreachable!(),
+        })?;
+    target.age = 1;
+
+    let logical_size = Size::<i32, Logical>::from((
+        (output_size.w as f64 / scale).round() as i32,
+        (output_size.h as f64 / scale).round() as i32,
+    ));
+
+    let frame = TextureRenderElement::from_texture_render_buffer(
+        (0.0, 0.0),
+        &target.buffer,
+        None,
+        None,
+        Some(logical_size),
+        Kind::Unspecified,
+    );
+
+    // Elements render from top to bottom, so the pointer goes first
+    pointer_elements.push(OutputRenderElements::from(Wrap::from(frame)));
+
+    Ok(pointer_elements)
+}
+
+/// Create a gbm device and renderer for the GPU at `node`, rendering in software if
+/// [`Udev::software_rendering`] is set.
+///
+/// Returns the gbm device and the render node the renderer was added for.
+fn add_render_node(
+    udev: &mut Udev,
+    node: DrmNode,
+    fd: &DrmDeviceFd,
+) -> Result<(GbmDevice<DrmDeviceFd>, DrmNode), DeviceAddError> {
+    if udev.software_rendering.is_some() {
+        with_software_rendering(|| create_render_node(udev, node, fd))
+    } else {
+        create_render_node(udev, node, fd)
+    }
+}
+
+fn create_render_node(
+    udev: &mut Udev,
+    node: DrmNode,
+    fd: &DrmDeviceFd,
+) -> Result<(GbmDevice<DrmDeviceFd>, DrmNode), DeviceAddError> {
+    // The gbm device is created here too as it picks software rendering when it's created
+    let gbm = GbmDevice::new(fd.clone()).map_err(DeviceAddError::GbmDevice)?;
+
+    let display = EGLDisplay::new(gbm.clone()).map_err(DeviceAddError::EglDisplay)?;
+    let render_node = EGLDevice::device_for_display(&display)
+        .ok()
+        .and_then(|x| x.try_get_render_node().ok().flatten())
+        .unwrap_or(node);
+
+    udev.gpu_manager
+        .as_mut()
+        .add_node(render_node, gbm.clone())
+        .map_err(DeviceAddError::AddNode)?;
+
+    Ok((gbm, render_node))
+}
+
 impl State {
     /// A GPU was plugged in.
     fn device_added(&mut self, node: DrmNode, path: &Path) -> Result<(), DeviceAddError> {
@@ -737,7 +1051,19 @@ impl State {
 
         let (drm, notifier) =
             DrmDevice::new(fd.clone(), true).map_err(DeviceAddError::DrmDevice)?;
-        let gbm = GbmDevice::new(fd).map_err(DeviceAddError::GbmDevice)?;
+
+        let mut fell_back = false;
+        let (gbm, render_node) = match add_render_node(udev, node, &fd) {
+            Ok(ret) => ret,
+            Err(err) if udev.software_rendering.is_none() => {
+                tracing::error!("Failed to set up GPU rendering: {err}");
+                tracing::warn!("Falling back to software rendering. This will be slow.");
+                udev.software_rendering = Some(format!("GPU rendering failed: {err}"));
+                fell_back = true;
+                add_render_node(udev, node, &fd)?
+            }
+            Err(err) => return Err(err),
+        };
 
         let registration_token = self
             .loop_handle
@@ -759,18 +1085,6 @@ impl State {
             })
             .expect("failed to insert drm notifier into event loop");
 
-        let render_node = EGLDevice::device_for_display(
-            &EGLDisplay::new(gbm.clone()).expect("failed to create EGLDisplay"),
-        )
-        .ok()
-        .and_then(|x| x.try_get_render_node().ok().flatten())
-        .unwrap_or(node);
-
-        udev.gpu_manager
-            .as_mut()
-            .add_node(render_node, gbm.clone())
-            .map_err(DeviceAddError::AddNode)?;
-
         udev.backends.insert(
             node,
             UdevBackendData {
@@ -784,25 +1098,39 @@ impl State {
         );
 
         self.device_changed(node);
+        self.apply_connector_changes();
+
+        // A config that connected before this GPU was added hasn't been told yet
+        if let (true, Some(stream), Some(message)) = (
+            fell_back,
+            self.api_state.stream.as_ref(),
+            self.backend.software_rendering_warning(),
+        ) {
+            if let Err(err) = crate::api::send_to_client(
+                &mut stream.lock().expect("Couldn't lock stream"),
+                &OutgoingMsg::Warning { message },
+            ) {
+                tracing::warn!("Failed to send warning to client: {err}");
+            }
+        }
 
         Ok(())
     }
 
     /// A display was plugged in.
+    ///
+    /// Returns the new output, if one was set up. It isn't laid out yet, and the config's
+    /// output callbacks haven't run for it.
     // TODO: better edid info from cosmic-comp
     fn connector_connected(
         &mut self,
         node: DrmNode,
         connector: connector::Info,
         crtc: crtc::Handle,
-    ) {
+    ) -> Option<Output> {
         let udev = self.backend.udev_mut();
 
-        let device = if let Some(device) = udev.backends.get_mut(&node) {
-            device
-        } else {
-            return;
-        };
+        let device = udev.backends.get_mut(&node)?;
 
         let mut renderer = udev
             .gpu_manager
@@ -839,15 +1167,11 @@ impl State {
             Ok(surface) => surface,
             Err(err) => {
                 tracing::warn!("Failed to create drm surface: {}", err);
-                return;
+                return None;
             }
         };
 
-        let output_name = format!(
-            "{}-{}",
-            connector.interface().as_str(),
-            connector.interface_id()
-        );
+        let output_name = connector_name(&connector);
 
         let (make, model) = EdidInfo::for_connector(&device.drm, connector.handle())
             .map(|info| (info.manufacturer, info.model))
@@ -860,7 +1184,7 @@ impl State {
                 .get::<UdevOutputData>()
                 .is_some_and(|op_id| op_id.crtc == crtc)
         }) {
-            return;
+            return None;
         }
 
         let output = Output::new(
@@ -909,7 +1233,7 @@ impl State {
                 Ok(driver) => driver,
                 Err(err) => {
                     tracing::warn!("Failed to query drm driver: {}", err);
-                    return;
+                    return None;
                 }
             };
 
@@ -930,6 +1254,11 @@ impl State {
                 planes.overlay = vec![];
             }
 
+            // Software rendered buffers can't be scanned out from overlay planes
+            if udev.software_rendering.is_some() {
+                planes.overlay = vec![];
+            }
+
             match DrmCompositor::new(
                 &output,
                 surface,
@@ -944,7 +1273,7 @@ impl State {
                 Ok(compositor) => compositor,
                 Err(err) => {
                     tracing::warn!("Failed to create drm compositor: {}", err);
-                    return;
+                    return None;
                 }
             }
         };
@@ -964,6 +1293,7 @@ impl State {
             compositor,
             dmabuf_feedback,
             render_state: RenderState::Idle,
+            scaled_target: None,
         };
 
         device.surfaces.insert(crtc, surface);
@@ -982,34 +1312,49 @@ impl State {
             self.space.map_output(&output, *loc);
 
             output.with_state(|state| state.tags = tags.clone());
-        } else {
-            // Run any output callbacks
-            let clone = output.clone();
-            self.schedule(
-                |dt| dt.state.api_state.stream.is_some(),
-                move |dt| {
-                    let stream = dt
-                        .state
-                        .api_state
-                        .stream
-                        .as_ref()
-                        .expect("stream doesn't exist");
-                    let mut stream = stream.lock().expect("couldn't lock stream");
+            self.adopt_orphans(&output);
+        }
+
+        Some(output)
+    }
+
+    /// Run the config's output callbacks for each of `outputs` in order once the config
+    /// is connected.
+    fn run_output_callbacks(&mut self, outputs: Vec<Output>) {
+        if outputs.is_empty() {
+            return;
+        }
+
+        self.schedule(
+            |dt| dt.state.api_state.stream.is_some(),
+            move |dt| {
+                let stream = dt
+                    .state
+                    .api_state
+                    .stream
+                    .as_ref()
+                    .expect("stream doesn't exist");
+                let mut stream = stream.lock().expect("couldn't lock stream");
+                for output in outputs.iter() {
                     for callback_id in dt.state.config.output_callback_ids.iter() {
                         crate::api::send_to_client(
                             &mut stream,
-                            &OutgoingMsg::CallCallback {
-                                callback_id: *callback_id,
-                                args: Some(Args::ConnectForAllOutputs {
-                                    output_name: clone.name(),
+                            &OutgoingMsg::call_callback(
+                                *callback_id,
+                                Some(Args::ConnectForAllOutputs {
+                                    output_name: output.name(),
+                                    is_replay: false,
+                                    tag_names: Vec::new(),
+                                    active_tags: Vec::new(),
                                 }),
-                            },
+                                &dt.state.api_state.callback_serial,
+                            ),
                         )
                         .expect("Send to client failed");
                     }
-                },
-            );
-        }
+                }
+            },
+        );
     }
 
     /// A display was unplugged.
@@ -1051,10 +1396,13 @@ impl State {
                     tags: output.with_state(|state| state.tags.clone()),
                 },
             );
-            self.space.unmap_output(&output);
+            self.remove_output(&output);
         }
     }
 
+    /// Find the connectors on a GPU that changed and queue the changes up.
+    ///
+    /// They're applied with [`State::apply_connector_changes`].
     fn device_changed(&mut self, node: DrmNode) {
         let udev = self.backend.udev_mut();
 
@@ -1070,13 +1418,13 @@ impl State {
                     connector,
                     crtc: Some(crtc),
                 } => {
-                    self.connector_connected(node, connector, crtc);
+                    udev.hotplug.connected(node, connector, crtc);
                 }
                 DrmScanEvent::Disconnected {
                     connector,
                     crtc: Some(crtc),
                 } => {
-                    self.connector_disconnected(node, connector, crtc);
+                    udev.hotplug.disconnected(node, connector, crtc);
                 }
                 _ => {}
             }
@@ -1099,9 +1447,12 @@ impl State {
                 .collect::<Vec<_>>()
         };
 
+        let udev = self.backend.udev_mut();
         for (connector, crtc) in crtcs {
-            self.connector_disconnected(node, connector, crtc);
+            udev.hotplug.disconnected(node, connector, crtc);
         }
+        // The outputs need to go before the GPU they're on
+        self.apply_connector_changes();
 
         tracing::debug!("Surfaces dropped");
 
@@ -1203,9 +1554,13 @@ impl State {
             self.schedule_render(&output);
         } else {
             for window in self.windows.iter() {
-                window.send_frame(&output, self.clock.now(), Some(Duration::ZERO), |_, _| {
-                    Some(output.clone())
-                });
+                window.send_background_frame(
+                    &output,
+                    self.clock.now(),
+                    &self.space,
+                    self.background_throttle,
+                    |_, _| Some(output.clone()),
+                );
             }
         }
     }
@@ -1298,13 +1653,25 @@ impl State {
             &pointer_image,
             &mut udev.pointer_element,
             self.pointer_location,
+            &self.debug_overlay,
+            &self.grab_hint,
+            &self.prompt_box,
+            &self.window_flash,
+            &mut self.screencopy_state,
+            &self.loop_handle,
             &self.clock,
+            self.background_throttle,
         );
 
         match result {
             Ok(true) => surface.render_state = RenderState::WaitingForVblank { dirty: false },
             Ok(false) | Err(_) => surface.render_state = RenderState::Idle,
         }
+
+        // Keep rendering until damage flashes fade out
+        if self.debug_overlay.is_flashing_damage() {
+            self.schedule_render(output);
+        }
     }
 }
 
@@ -1339,7 +1706,15 @@ fn render_surface(
     pointer_element: &mut PointerElement<MultiTexture>,
     pointer_location: Point<f64, Logical>,
 
+    debug_overlay: &DebugOverlay,
+    grab_hint: &GrabHint,
+    prompt_box: &PromptBox,
+    window_flash: &WindowFlashState,
+    screencopy: &mut ScreencopyState,
+    loop_handle: &LoopHandle<'static, CalloopData>,
+
     clock: &Clock<Monotonic>,
+    background_throttle: BackgroundThrottle,
 ) -> Result<bool, SwapBuffersError> {
     use smithay::reexports::wayland_protocols::xdg::shell::server::xdg_toplevel;
 
@@ -1378,7 +1753,7 @@ fn render_surface(
     if !pending_wins.is_empty() {
         tracing::debug!("Skipping frame, waiting on {pending_wins:?}");
         for win in windows.iter() {
-            win.send_frame(output, clock.now(), Some(Duration::ZERO), |_, _| {
+            win.send_background_frame(output, clock.now(), space, background_throttle, |_, _| {
                 Some(output.clone())
             });
         }
@@ -1407,14 +1782,53 @@ fn render_surface(
         // input_method,
         pointer_element,
         Some(pointer_image),
+        debug_overlay,
+        grab_hint,
+        prompt_box,
+        window_flash,
     );
 
-    let res = render_frame::<_, _, GlesTexture>(
-        &mut surface.compositor,
+    let clear_color = [0.6, 0.6, 0.6, 1.0];
+
+    // Copies are drawn at the output's full size, so this happens before any scaling
+    screencopy.render_pending(
         renderer,
+        output,
         &output_render_elements,
-        [0.6, 0.6, 0.6, 1.0],
-    )?;
+        clear_color,
+        clock.now().into(),
+        loop_handle,
+    );
+
+    let render_scale = output.with_state(|state| state.render_scale);
+
+    let res = if render_scale < 1.0 {
+        let scaled_render_elements = render_scaled(
+            &mut surface.scaled_target,
+            renderer,
+            output,
+            output_render_elements,
+            render_scale,
+            clear_color,
+        )?;
+
+        render_frame::<_, _, GlesTexture>(
+            &mut surface.compositor,
+            renderer,
+            &scaled_render_elements,
+            clear_color,
+        )?
+    } else {
+        // Free the offscreen buffer if the output was scaled before
+        surface.scaled_target = None;
+
+        render_frame::<_, _, GlesTexture>(
+            &mut surface.compositor,
+            renderer,
+            &output_render_elements,
+            clear_color,
+        )?
+    };
 
     let time = clock.now();
 
@@ -1434,6 +1848,7 @@ fn render_surface(
                 scanout_feedback: &feedback.scanout_feedback,
             }),
         time.into(),
+        background_throttle,
     );
 
     if res.rendered {
@@ -0,0 +1,457 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Batching output hotplugs.
+//!
+//! Docking stations connect several monitors over a second or two, each with its own udev
+//! event. Setting up each output as its event comes in runs the config's output callbacks
+//! and lays windows out over and over, so connector changes are collected until udev has
+//! been quiet for the debounce time and then applied together. Disconnected outputs are
+//! removed first, then connected ones are set up in order of name, and every output is laid
+//! out once at the end.
+//!
+//! A lone change that isn't part of a burst is applied once udev has been quiet for
+//! [`QUIET_PERIOD`] so plugging in one monitor isn't held up by the debounce. A change that
+//! comes within the debounce time of the one before it is part of a burst, so the monitors
+//! after the first one of a docking station are still set up together.
+
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    hash::Hash,
+    time::{Duration, Instant},
+};
+
+use smithay::{
+    backend::drm::DrmNode,
+    reexports::{
+        calloop::{
+            timer::{TimeoutAction, Timer},
+            RegistrationToken,
+        },
+        drm::control::{connector, crtc},
+    },
+};
+
+use crate::{output::signals::OutputSignal, state::State};
+
+/// How long udev needs to be quiet before connector changes are applied, unless the config
+/// changes it.
+pub const DEFAULT_HOTPLUG_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// How long udev needs to be quiet before a single connector change is applied.
+const QUIET_PERIOD: Duration = Duration::from_millis(100);
+
+/// A connector on a GPU.
+pub type ConnectorChange = (DrmNode, connector::Info, crtc::Handle);
+
+#[derive(Debug)]
+pub struct HotplugState {
+    /// How long udev needs to be quiet before connector changes are applied
+    pub debounce: Duration,
+    changes: ConnectorChanges<(DrmNode, connector::Handle), ConnectorChange>,
+    /// The timer that applies `changes`
+    timer: Option<RegistrationToken>,
+    /// When the last connector change came in
+    last_change: Option<Instant>,
+    /// Whether the last connector change came within the debounce time of the one before it
+    in_burst: bool,
+}
+
+impl Default for HotplugState {
+    fn default() -> Self {
+        Self {
+            debounce: DEFAULT_HOTPLUG_DEBOUNCE,
+            changes: ConnectorChanges::default(),
+            timer: None,
+            last_change: None,
+            in_burst: false,
+        }
+    }
+}
+
+impl HotplugState {
+    pub fn connected(&mut self, node: DrmNode, connector: connector::Info, crtc: crtc::Handle) {
+        self.note_change(Instant::now());
+        self.changes
+            .connected((node, connector.handle()), (node, connector, crtc));
+    }
+
+    pub fn disconnected(&mut self, node: DrmNode, connector: connector::Info, crtc: crtc::Handle) {
+        self.note_change(Instant::now());
+        self.changes
+            .disconnected((node, connector.handle()), (node, connector, crtc));
+    }
+
+    fn note_change(&mut self, now: Instant) {
+        self.in_burst = self
+            .last_change
+            .is_some_and(|last_change| now.duration_since(last_change) < self.debounce);
+        self.last_change = Some(now);
+    }
+}
+
+/// Connector changes that haven't been applied yet, collapsed to one per connector.
+///
+/// `K` identifies a connector and `C` is what's known about it when it changes.
+#[derive(Debug)]
+struct ConnectorChanges<K, C> {
+    changes: HashMap<K, PendingConnector<C>>,
+}
+
+impl<K, C> Default for ConnectorChanges<K, C> {
+    fn default() -> Self {
+        Self {
+            changes: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PendingConnector<C> {
+    /// The connector as it was before it changed, if it needs to be disconnected first
+    disconnected: Option<C>,
+    /// The connector as it is now, if it's connected
+    connected: Option<C>,
+}
+
+impl<C> Default for PendingConnector<C> {
+    fn default() -> Self {
+        Self {
+            disconnected: None,
+            connected: None,
+        }
+    }
+}
+
+impl<K: Hash + Eq, C> ConnectorChanges<K, C> {
+    fn connected(&mut self, key: K, connector: C) {
+        let pending = self.changes.entry(key).or_default();
+        pending.connected = Some(connector);
+    }
+
+    fn disconnected(&mut self, key: K, connector: C) {
+        match self.changes.entry(key) {
+            Entry::Occupied(mut entry) => {
+                let pending = entry.get_mut();
+                let was_connected = pending.connected.take().is_some();
+                // A connector that was only connected in this batch was never set up,
+                // so there's nothing to disconnect. Otherwise the first disconnect is
+                // the only one that matters.
+                if was_connected && pending.disconnected.is_none() {
+                    entry.remove();
+                } else if pending.disconnected.is_none() {
+                    pending.disconnected = Some(connector);
+                }
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(PendingConnector {
+                    disconnected: Some(connector),
+                    connected: None,
+                });
+            }
+        }
+    }
+
+    /// How long udev needs to be quiet before these changes are applied, or `None` if
+    /// there aren't any.
+    ///
+    /// `in_burst` is whether the last change came within `debounce` of the one before it.
+    fn delay(&self, debounce: Duration, in_burst: bool) -> Option<Duration> {
+        match self.changes.len() {
+            0 => None,
+            1 if !in_burst => Some(QUIET_PERIOD.min(debounce)),
+            _ => Some(debounce),
+        }
+    }
+
+    /// Take the connectors to disconnect and the ones to connect, each in order of `name`.
+    fn take<N: Ord>(&mut self, mut name: impl FnMut(&C) -> N) -> (Vec<C>, Vec<C>) {
+        let mut disconnects = Vec::new();
+        let mut connects = Vec::new();
+
+        for (_, pending) in self.changes.drain() {
+            disconnects.extend(pending.disconnected);
+            connects.extend(pending.connected);
+        }
+
+        disconnects.sort_by_cached_key(&mut name);
+        connects.sort_by_cached_key(&mut name);
+
+        (disconnects, connects)
+    }
+}
+
+impl State {
+    /// Apply pending connector changes once udev goes quiet.
+    ///
+    /// Call this after every udev event, since each one pushes the changes back.
+    pub(super) fn schedule_connector_changes(&mut self) {
+        let hotplug = &mut self.backend.udev_mut().hotplug;
+        if let Some(timer) = hotplug.timer.take() {
+            self.loop_handle.remove(timer);
+        }
+
+        let Some(delay) = hotplug.changes.delay(hotplug.debounce, hotplug.in_burst) else {
+            return;
+        };
+
+        let timer = self
+            .loop_handle
+            .insert_source(Timer::from_duration(delay), |_, _, data| {
+                data.state.backend.udev_mut().hotplug.timer = None;
+                data.state.apply_connector_changes();
+                TimeoutAction::Drop
+            });
+
+        match timer {
+            Ok(timer) => self.backend.udev_mut().hotplug.timer = Some(timer),
+            Err(err) => {
+                tracing::error!("Failed to insert hotplug timer, applying changes now: {err}");
+                self.apply_connector_changes();
+            }
+        }
+    }
+
+    /// Apply pending connector changes right away.
+    pub(super) fn apply_connector_changes(&mut self) {
+        let hotplug = &mut self.backend.udev_mut().hotplug;
+        if let Some(timer) = hotplug.timer.take() {
+            self.loop_handle.remove(timer);
+        }
+
+        let (disconnects, connects) = hotplug
+            .changes
+            .take(|(_, connector, _)| super::connector_name(connector));
+        if disconnects.is_empty() && connects.is_empty() {
+            return;
+        }
+
+        tracing::info!(
+            "Applying {} output disconnects and {} output connects",
+            disconnects.len(),
+            connects.len()
+        );
+
+        for (node, connector, crtc) in disconnects {
+            self.connector_disconnected(node, connector, crtc);
+        }
+
+        let connected = connects
+            .into_iter()
+            .filter_map(|(node, connector, crtc)| self.connector_connected(node, connector, crtc))
+            .collect::<Vec<_>>();
+
+        // Outputs that were plugged in before got their tags back,
+        // so only new ones go through the config
+        let new_outputs = connected
+            .iter()
+            .filter(|output| {
+                !self
+                    .config
+                    .connector_saved_states
+                    .contains_key(&crate::output::OutputName(output.name()))
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        self.run_output_callbacks(new_outputs);
+
+        for output in connected.iter() {
+            self.update_windows(output);
+            self.schedule_render(output);
+            self.signal_output(output, OutputSignal::Connected);
+        }
+
+        let windows = self.windows.clone();
+        self.update_window_outputs(windows.iter());
+
+        // The pointer may have moved anywhere while there were no outputs
+        self.reclamp_pointer();
+
+        self.check_startup_complete();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A udev event, `ms` milliseconds into the replay.
+    #[derive(Debug, Clone, Copy)]
+    enum Event {
+        Connected(u64, &'static str),
+        Disconnected(u64, &'static str),
+    }
+
+    use Event::{Connected, Disconnected};
+
+    /// The changes applied at once, `ms` milliseconds into the replay.
+    #[derive(Debug, PartialEq, Eq)]
+    struct Applied {
+        ms: u64,
+        disconnects: Vec<&'static str>,
+        connects: Vec<&'static str>,
+    }
+
+    /// Replay `events` against the debounce, returning each batch of changes that's applied.
+    fn replay(events: &[Event], debounce: Duration) -> Vec<Applied> {
+        let mut changes = ConnectorChanges::default();
+        let mut deadline: Option<u64> = None;
+        let mut last_ms: Option<u64> = None;
+        let mut applied = Vec::new();
+
+        let mut apply = |changes: &mut ConnectorChanges<_, _>, ms| {
+            let (disconnects, connects) = changes.take(|name: &&str| *name);
+            applied.push(Applied {
+                ms,
+                disconnects,
+                connects,
+            });
+        };
+
+        for event in events {
+            let (Connected(ms, name) | Disconnected(ms, name)) = *event;
+
+            if let Some(due) = deadline.filter(|due| *due <= ms) {
+                apply(&mut changes, due);
+            }
+
+            match event {
+                Connected(..) => changes.connected(name, name),
+                Disconnected(..) => changes.disconnected(name, name),
+            }
+
+            let in_burst =
+                last_ms.is_some_and(|last_ms| ms - last_ms < debounce.as_millis() as u64);
+            last_ms = Some(ms);
+
+            deadline = changes
+                .delay(debounce, in_burst)
+                .map(|delay| ms + delay.as_millis() as u64);
+        }
+
+        if let Some(due) = deadline {
+            apply(&mut changes, due);
+        }
+
+        applied
+    }
+
+    #[test]
+    fn docking_storm_after_the_first_monitor_is_applied_together() {
+        // Three monitors come up over two seconds, with one of them flapping. Only the first
+        // one can't be told apart from plugging in a single monitor.
+        let events = [
+            Connected(0, "DP-3"),
+            Connected(450, "DP-1"),
+            Disconnected(700, "DP-1"),
+            Connected(900, "DP-1"),
+            Connected(1350, "DP-2"),
+            Disconnected(1800, "eDP-1"),
+            Connected(2000, "eDP-1"),
+        ];
+
+        assert_eq!(
+            replay(&events, DEFAULT_HOTPLUG_DEBOUNCE),
+            vec![
+                Applied {
+                    ms: 100,
+                    disconnects: vec![],
+                    connects: vec!["DP-3"],
+                },
+                Applied {
+                    ms: 2500,
+                    disconnects: vec!["eDP-1"],
+                    connects: vec!["DP-1", "DP-2", "eDP-1"],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn simultaneous_storm_is_applied_once() {
+        let events = [
+            Connected(0, "DP-3"),
+            Connected(20, "DP-1"),
+            Connected(60, "DP-2"),
+        ];
+
+        assert_eq!(
+            replay(&events, DEFAULT_HOTPLUG_DEBOUNCE),
+            vec![Applied {
+                ms: 560,
+                disconnects: vec![],
+                connects: vec!["DP-1", "DP-2", "DP-3"],
+            }]
+        );
+    }
+
+    #[test]
+    fn single_hotplug_isnt_held_up() {
+        assert_eq!(
+            replay(&[Connected(0, "HDMI-A-1")], DEFAULT_HOTPLUG_DEBOUNCE),
+            vec![Applied {
+                ms: QUIET_PERIOD.as_millis() as u64,
+                disconnects: vec![],
+                connects: vec!["HDMI-A-1"],
+            }]
+        );
+    }
+
+    #[test]
+    fn quiet_gaps_split_batches() {
+        let events = [
+            Connected(0, "DP-1"),
+            Connected(50, "DP-2"),
+            Disconnected(3000, "DP-2"),
+        ];
+
+        assert_eq!(
+            replay(&events, DEFAULT_HOTPLUG_DEBOUNCE),
+            vec![
+                Applied {
+                    ms: 550,
+                    disconnects: vec![],
+                    connects: vec!["DP-1", "DP-2"],
+                },
+                Applied {
+                    ms: 3100,
+                    disconnects: vec!["DP-2"],
+                    connects: vec![],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn connecting_and_disconnecting_in_one_batch_only_disconnects_the_old_output() {
+        let mut changes = ConnectorChanges::default();
+        changes.disconnected("DP-1", "old DP-1");
+        changes.connected("DP-1", "new DP-1");
+        changes.disconnected("DP-1", "new DP-1");
+
+        let (disconnects, connects) = changes.take(|name: &&str| *name);
+        assert_eq!(disconnects, vec!["old DP-1"]);
+        assert!(connects.is_empty());
+    }
+
+    #[test]
+    fn connectors_only_connected_in_the_batch_are_dropped() {
+        let mut changes = ConnectorChanges::default();
+        changes.connected("DP-1", "DP-1");
+        changes.disconnected("DP-1", "DP-1");
+        assert_eq!(changes.delay(DEFAULT_HOTPLUG_DEBOUNCE, false), None);
+
+        let (disconnects, connects) = changes.take(|name: &&str| *name);
+        assert!(disconnects.is_empty());
+        assert!(connects.is_empty());
+    }
+
+    #[test]
+    fn short_debounce_caps_the_quiet_period() {
+        let mut changes = ConnectorChanges::default();
+        assert_eq!(changes.delay(DEFAULT_HOTPLUG_DEBOUNCE, false), None);
+
+        changes.connected("DP-1", ());
+        let debounce = Duration::from_millis(20);
+        assert_eq!(changes.delay(debounce, false), Some(debounce));
+    }
+}
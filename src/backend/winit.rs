@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use std::{ffi::OsString, time::Duration};
+use std::{ffi::OsString, os::fd::OwnedFd, panic::AssertUnwindSafe, time::Duration};
 
 use smithay::{
     backend::{
@@ -34,6 +34,7 @@ use smithay::{
 };
 
 use crate::{
+    output::signals::OutputSignal,
     render::{pointer::PointerElement, take_presentation_feedback},
     state::{CalloopData, State, WithState},
     window::WindowElement,
@@ -41,11 +42,20 @@ use crate::{
 
 use super::{Backend, BackendData};
 
+/// How often winit events are polled for while nothing is happening.
+///
+/// Winit can't wake the event loop up by itself, so it has to be polled. This is slower than
+/// the refresh rate so an idle window doesn't keep Pinnacle awake. The first input after
+/// being idle can take this long to show up.
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
 pub struct Winit {
     pub backend: WinitGraphicsBackend<GlesRenderer>,
     pub damage_tracker: OutputDamageTracker,
     pub dmabuf_state: (DmabufState, DmabufGlobal, Option<DmabufFeedback>),
     pub full_redraw: u8,
+    /// Whether the window needs to be rendered on the next poll.
+    pub render_scheduled: bool,
 }
 
 impl BackendData for Winit {
@@ -68,7 +78,7 @@ impl Backend {
 }
 
 /// Start Pinnacle as a window in a graphical environment.
-pub fn run_winit() -> anyhow::Result<()> {
+pub fn run_winit(ready_fd: Option<OwnedFd>) -> anyhow::Result<()> {
     let mut event_loop: EventLoop<CalloopData> = EventLoop::try_new()?;
 
     let display: Display<State> = Display::new()?;
@@ -165,12 +175,14 @@ pub fn run_winit() -> anyhow::Result<()> {
             damage_tracker: OutputDamageTracker::from_output(&output),
             dmabuf_state,
             full_redraw: 0,
+            render_scheduled: true,
         }),
         display,
         event_loop.get_signal(),
         evt_loop_handle,
     )?;
 
+    state.startup.ready_fd = ready_fd;
     state.focus_state.focused_output = Some(output.clone());
 
     let winit = state.backend.winit_mut();
@@ -182,6 +194,7 @@ pub fn run_winit() -> anyhow::Result<()> {
         .update_formats(winit.backend.renderer().shm_formats());
 
     state.space.map_output(&output, (0, 0));
+    state.startup.outputs_initialized = true;
 
     if let Err(err) = state.xwayland.start(
         state.loop_handle.clone(),
@@ -191,38 +204,48 @@ pub fn run_winit() -> anyhow::Result<()> {
         |_| {},
     ) {
         tracing::error!("Failed to start XWayland: {err}");
+        state.startup.xwayland_done = true;
     }
 
+    state.check_startup_complete();
+
     let insert_ret =
         state
             .loop_handle
             .insert_source(Timer::immediate(), move |_instant, _metadata, data| {
                 let state = &mut data.state;
 
-                let result = winit_evt_loop.dispatch_new_events(|event| match event {
-                    WinitEvent::Resized {
-                        size,
-                        scale_factor: _,
-                    } => {
-                        output.change_current_state(
-                            Some(smithay::output::Mode {
-                                size,
-                                refresh: 144_000,
-                            }),
-                            None,
-                            None,
-                            None,
-                        );
-                        layer_map_for_output(&output).arrange();
-                        state.update_windows(&output);
-                        // state.re_layout(&output);
-                    }
-                    WinitEvent::Focus(_) => {}
-                    WinitEvent::Input(input_evt) => {
-                        state.process_input_event(input_evt);
-                    }
-                    WinitEvent::Refresh => {
-                        state.render_winit_window(&output);
+                let mut had_events = false;
+
+                let result = winit_evt_loop.dispatch_new_events(|event| {
+                    had_events = true;
+                    match event {
+                        WinitEvent::Resized {
+                            size,
+                            scale_factor: _,
+                        } => {
+                            output.change_current_state(
+                                Some(smithay::output::Mode {
+                                    size,
+                                    refresh: 144_000,
+                                }),
+                                None,
+                                None,
+                                None,
+                            );
+                            layer_map_for_output(&output).arrange();
+                            state.update_windows(&output);
+                            state.schedule_render(&output);
+                            state.signal_output(&output, OutputSignal::ModeChanged);
+                            // state.re_layout(&output);
+                        }
+                        WinitEvent::Focus(_) => {}
+                        WinitEvent::Input(input_evt) => {
+                            state.process_input_event(input_evt);
+                        }
+                        WinitEvent::Refresh => {
+                            state.schedule_render(&output);
+                        }
                     }
                 });
 
@@ -233,35 +256,61 @@ pub fn run_winit() -> anyhow::Result<()> {
                     }
                 };
 
-                state.render_winit_window(&output);
+                let winit = state.backend.winit_mut();
+                let should_render = winit.render_scheduled || winit.full_redraw > 0;
+                if should_render {
+                    state.render_winit_window(&output);
+                }
 
-                TimeoutAction::ToDuration(Duration::from_micros(((1.0 / 144.0) * 1000000.0) as u64))
+                // Keep polling at the refresh rate while things are happening
+                if had_events || should_render {
+                    let refresh_interval = output
+                        .current_mode()
+                        .map(|mode| Duration::from_secs_f64(1000.0 / mode.refresh as f64))
+                        .unwrap_or(IDLE_POLL_INTERVAL);
+                    TimeoutAction::ToDuration(refresh_interval)
+                } else {
+                    TimeoutAction::ToDuration(IDLE_POLL_INTERVAL)
+                }
             });
     if let Err(err) = insert_ret {
         anyhow::bail!("Failed to insert winit events into event loop: {err}");
     }
 
-    event_loop.run(
-        Some(Duration::from_micros(((1.0 / 144.0) * 1000000.0) as u64)),
-        &mut CalloopData {
-            display_handle,
-            state,
-        },
-        |data| {
+    let mut data = CalloopData {
+        display_handle,
+        state,
+    };
+
+    // The timer above wakes the loop up to poll winit, so nothing else needs to
+    let run = std::panic::catch_unwind(AssertUnwindSafe(|| {
+        event_loop.run(None, &mut data, |data| {
+            data.state.event_loop_wakeups += 1;
             data.state.space.refresh();
             data.state.popup_manager.cleanup();
+            data.state.refresh_foreign_toplevels();
             data.display_handle
                 .flush_clients()
                 .expect("failed to flush client buffers");
-        },
-    )?;
+        })
+    }));
+
+    match run {
+        Ok(result) => result?,
+        Err(_) => crate::crash::abort_after_panic(data),
+    }
+
+    data.state.save_session();
 
     Ok(())
 }
 
 impl State {
     fn render_winit_window(&mut self, output: &Output) {
+        self.fix_up_focus();
+
         let winit = self.backend.winit_mut();
+        winit.render_scheduled = false;
 
         let pending_wins = self
             .windows
@@ -301,10 +350,11 @@ impl State {
             let op_clone = output.clone();
             self.loop_handle.insert_idle(move |dt| {
                 for win in dt.state.windows.iter() {
-                    win.send_frame(
+                    win.send_background_frame(
                         &op_clone,
                         dt.state.clock.now(),
-                        Some(Duration::ZERO),
+                        &dt.state.space,
+                        dt.state.background_throttle,
                         surface_primary_scanout_output,
                     );
                 }
@@ -317,8 +367,6 @@ impl State {
         let full_redraw = &mut winit.full_redraw;
         *full_redraw = full_redraw.saturating_sub(1);
 
-        self.focus_state.fix_up_focus(&mut self.space);
-
         if let CursorImageStatus::Surface(surface) = &self.cursor_status {
             if !surface.alive() {
                 self.cursor_status = CursorImageStatus::default_named();
@@ -341,6 +389,10 @@ impl State {
             // self.seat.input_method(),
             &mut pointer_element,
             None,
+            &self.debug_overlay,
+            &self.grab_hint,
+            &self.prompt_box,
+            &self.window_flash,
         );
 
         let render_res = winit.backend.bind().and_then(|_| {
@@ -375,6 +427,15 @@ impl State {
 
                 let time = self.clock.now();
 
+                self.screencopy_state.render_pending(
+                    winit.backend.renderer(),
+                    output,
+                    &output_render_elements,
+                    [0.6, 0.6, 0.6, 1.0],
+                    time.into(),
+                    &self.loop_handle,
+                );
+
                 // Send frames to the cursor surface so it updates correctly
                 if let CursorImageStatus::Surface(surf) = &self.cursor_status {
                     if let Some(op) = self.focus_state.focused_output.as_ref() {
@@ -388,6 +449,7 @@ impl State {
                     &self.space,
                     None,
                     time.into(),
+                    self.background_throttle,
                 );
 
                 if has_rendered {
@@ -411,5 +473,10 @@ impl State {
                 tracing::warn!("{}", err);
             }
         }
+
+        // Keep rendering until damage flashes fade out
+        if self.debug_overlay.is_flashing_damage() {
+            self.schedule_render(output);
+        }
     }
 }
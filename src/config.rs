@@ -1,13 +1,30 @@
+pub mod reload;
+
 use crate::{
-    api::{msg::ModifierMask, PinnacleSocketSource},
+    api::{
+        limits::ClientLimits, msg::ModifierMask, stats::DEFAULT_SLOW_REQUEST_THRESHOLD,
+        PinnacleSocketSource,
+    },
+    focus::FollowPolicy,
+    grab::snap::DEFAULT_SNAP_THRESHOLD,
+    input::{
+        bind_layer::BindLayerState, cursor_warp::CursorWarpState, double_click::DoubleClickState,
+        gesture::GestureState, media_keys::MediaKeys, pointer_barrier::PointerBarrier,
+        scroll::ScrollState,
+    },
     output::OutputName,
+    render::text::{DEFAULT_FONT_FAMILY, DEFAULT_FONT_SIZE},
     tag::Tag,
-    window::rules::{WindowRule, WindowRuleCondition},
+    window::{
+        insert_position::InsertPosition,
+        rules::{FloatingOrTiled, WindowRule, WindowRuleCondition},
+        state_request::StateRequestHandler,
+        throttle::BackgroundThrottle,
+    },
 };
 use std::{
     collections::HashMap,
     path::{Path, PathBuf},
-    sync::{Arc, Mutex},
 };
 
 use anyhow::Context;
@@ -17,6 +34,8 @@ use smithay::{
 };
 use toml::Table;
 
+use self::reload::ReloadState;
+
 use crate::api::msg::{CallbackId, Modifier};
 use xkbcommon::xkb::Keysym;
 
@@ -36,6 +55,27 @@ pub struct Metaconfig {
     pub reload_keybind: Keybind,
     pub kill_keybind: Keybind,
     pub socket_dir: Option<String>,
+    #[serde(default)]
+    pub client_limits: ClientLimits,
+    /// Whether to advertise `zwlr_input_inhibit_manager_v1` for older screen lockers.
+    #[serde(default = "default_input_inhibitor")]
+    pub input_inhibitor: bool,
+    #[serde(default)]
+    pub x11_keyboard_grab: X11KeyboardGrab,
+}
+
+fn default_input_inhibitor() -> bool {
+    true
+}
+
+/// What keybinds do while an X11 client has grabbed the keyboard.
+#[derive(serde::Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct X11KeyboardGrab {
+    /// Keep every keybind working instead of just the reserved ones.
+    pub keep_keybinds: bool,
+    /// The keybind that breaks the grab of the focused window.
+    pub break_keybind: Option<Keybind>,
 }
 
 #[derive(serde::Deserialize, Debug)]
@@ -124,10 +164,87 @@ pub enum Key {
 pub struct Config {
     /// Window rules and conditions on when those rules should apply
     pub window_rules: Vec<(WindowRuleCondition, WindowRule)>,
+    /// Callbacks that decide on client fullscreen, maximize, and minimize requests
+    pub state_request_handlers: Vec<StateRequestHandler>,
     /// All callbacks that should be run when outputs are connected
     pub output_callback_ids: Vec<CallbackId>,
+    /// Callbacks that will be run once startup completes
+    pub startup_callback_ids: Vec<CallbackId>,
+    /// Callbacks that will be run when an output is plugged in
+    pub output_connected_callback_ids: Vec<CallbackId>,
+    /// Callbacks that will be run when an output is unplugged
+    pub output_disconnected_callback_ids: Vec<CallbackId>,
+    /// Callbacks that will be run when an output's mode changes
+    pub output_mode_changed_callback_ids: Vec<CallbackId>,
+    /// Callbacks that will be run when a tag is activated or deactivated
+    pub tag_active_callback_ids: Vec<CallbackId>,
+    /// Callbacks that will be run when the order of tags on an output changes
+    pub tag_order_callback_ids: Vec<CallbackId>,
+    /// Callbacks that will be run when do not disturb is turned on or off
+    pub do_not_disturb_callback_ids: Vec<CallbackId>,
+    /// Callbacks that will be run when the window with keyboard focus changes
+    pub keyboard_focus_callback_ids: Vec<CallbackId>,
+    /// Callbacks that will be run when the window with pointer focus changes
+    pub pointer_focus_callback_ids: Vec<CallbackId>,
+    /// Callbacks that will be run when a window's title changes
+    pub window_title_callback_ids: Vec<CallbackId>,
+    /// Callbacks that will be run when a window's class changes
+    pub window_class_callback_ids: Vec<CallbackId>,
+    /// Callbacks that will be run when a window opens
+    pub window_opened_callback_ids: Vec<CallbackId>,
+    /// Callbacks that will be run when a window closes
+    pub window_closed_callback_ids: Vec<CallbackId>,
+    /// Callbacks that will be run when an input device is added
+    pub device_added_callback_ids: Vec<CallbackId>,
+    /// Callbacks that will be run when a client with windows disconnects
+    pub client_disconnected_callback_ids: Vec<CallbackId>,
+    /// Callbacks that will be run when the active keybind layer changes
+    pub bind_layer_callback_ids: Vec<CallbackId>,
+    /// Tag names in the order they were last arranged on each output.
+    ///
+    /// This is kept across config reloads so reordered tags keep their order.
+    pub tag_orders: HashMap<OutputName, Vec<String>>,
+    /// Whether new windows float or tile on tags that override their output, by tag name.
+    ///
+    /// This is kept across config reloads so re-added tags keep their defaults.
+    pub tag_default_modes: HashMap<OutputName, HashMap<String, FloatingOrTiled>>,
+    /// The tags each output had when the config was last restarted, by output name.
+    ///
+    /// These are sent when replaying outputs to the new config so it can put back what was active.
+    pub previous_tags: HashMap<OutputName, PreviousTags>,
     /// Saved states when outputs are disconnected
     pub connector_saved_states: HashMap<OutputName, ConnectorSavedState>,
+    /// The state of the config reload in progress, if any
+    pub reload: ReloadState,
+}
+
+/// The tags an output had before a config reload.
+#[derive(Debug, Default, Clone)]
+pub struct PreviousTags {
+    /// The names of all tags, in order
+    pub names: Vec<String>,
+    /// The names of the tags that were active
+    pub active: Vec<String>,
+}
+
+impl PreviousTags {
+    /// Remember the names of `tags` and which are active.
+    ///
+    /// Returns `None` if there are no tags, since the config never set up the output.
+    fn of(tags: &[Tag]) -> Option<Self> {
+        if tags.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            names: tags.iter().map(|tag| tag.name()).collect(),
+            active: tags
+                .iter()
+                .filter(|tag| tag.active())
+                .map(|tag| tag.name())
+                .collect(),
+        })
+    }
 }
 
 /// State saved when an output is disconnected. When the output is reconnected to the same
@@ -168,19 +285,89 @@ impl State {
         let config_dir = config_dir.as_ref();
 
         tracing::info!("Starting config");
+        self.begin_reload();
+
         tracing::debug!("Clearing tags");
 
+        // Windows are orphaned until the config adds their tags back.
+        // Remember where they were so they can go back there if it doesn't.
+        for win in self.windows.iter() {
+            if let Some(home) = self.window_home(win) {
+                win.with_state(|state| state.orphaned_on = Some(home));
+            }
+        }
+
+        self.config.previous_tags.clear();
+
+        // Tags on disconnected outputs are kept, as the config only adds tags to an output
+        // when it first connects
         for output in self.space.outputs() {
+            if let Some(previous_tags) = output.with_state(|state| PreviousTags::of(&state.tags)) {
+                self.config
+                    .previous_tags
+                    .insert(OutputName(output.name()), previous_tags);
+            }
+
             output.with_state(|state| state.tags.clear());
+            if let Some(saved_state) = self
+                .config
+                .connector_saved_states
+                .get_mut(&OutputName(output.name()))
+            {
+                saved_state.tags.clear();
+            }
         }
 
         TagId::reset();
 
         tracing::debug!("Clearing mouse and keybinds");
         self.input_state.keybinds.clear();
+        self.api_state.client_usages.forget_binds();
+        self.stop_keybind_repeat();
+        self.input_state.bind_layer = BindLayerState::default();
         self.input_state.mousebinds.clear();
+        self.input_state.scrollbinds.clear();
         self.input_state.libinput_settings.clear();
+        self.input_state.media_keys = MediaKeys::new();
+        self.input_state.pointer_barrier = PointerBarrier::default();
+        self.input_state.scroll = ScrollState::default();
+        self.input_state.double_click = DoubleClickState::default();
+        self.input_state.cursor_warp = CursorWarpState::default();
+        self.input_state.gesture = GestureState::default();
+        self.set_grab_hints(true);
+        self.set_ui_font(DEFAULT_FONT_FAMILY.to_string(), DEFAULT_FONT_SIZE);
+        self.set_flash_on_focus(false);
+        self.move_override = None;
+        self.api_state.stats.slow_request_threshold = DEFAULT_SLOW_REQUEST_THRESHOLD;
+        self.snap_threshold = DEFAULT_SNAP_THRESHOLD;
+        self.focus_state.follow_moved_window = FollowPolicy::default();
+        self.activity_state.count_api_focus = true;
+        self.stop_auto_tidy();
+        self.placement_memory.enabled = false;
+        self.insert_position = InsertPosition::default();
+        self.background_throttle = BackgroundThrottle::default();
+        self.raise_on_click = true;
         self.config.window_rules.clear();
+        self.config.state_request_handlers.clear();
+        self.config.output_callback_ids.clear();
+        self.config.startup_callback_ids.clear();
+        self.config.output_connected_callback_ids.clear();
+        self.config.output_disconnected_callback_ids.clear();
+        self.config.output_mode_changed_callback_ids.clear();
+        self.config.tag_active_callback_ids.clear();
+        self.config.tag_order_callback_ids.clear();
+        self.config.do_not_disturb_callback_ids.clear();
+        self.config.keyboard_focus_callback_ids.clear();
+        self.config.pointer_focus_callback_ids.clear();
+        self.config.window_title_callback_ids.clear();
+        self.config.window_class_callback_ids.clear();
+        self.config.window_opened_callback_ids.clear();
+        self.config.window_closed_callback_ids.clear();
+        self.config.device_added_callback_ids.clear();
+        self.config.client_disconnected_callback_ids.clear();
+        self.config.bind_layer_callback_ids.clear();
+        self.allow_pending_state_requests();
+        self.do_not_disturb.held_namespaces.clear();
 
         if let Some(channel) = self.api_state.kill_channel.as_ref() {
             tracing::debug!("Killing old config");
@@ -239,9 +426,22 @@ impl State {
         let socket_source = PinnacleSocketSource::new(tx_channel, &socket_dir)
             .context("Failed to create socket source")?;
 
+        self.api_state.client_limits = metaconfig.client_limits;
+        self.set_input_inhibitor_enabled(metaconfig.input_inhibitor);
+
         let reload_keybind = metaconfig.reload_keybind;
         let kill_keybind = metaconfig.kill_keybind;
 
+        let x11_keyboard_grab = metaconfig.x11_keyboard_grab;
+        self.xwayland_keyboard_grab_state.keep_keybinds = x11_keyboard_grab.keep_keybinds;
+        self.xwayland_keyboard_grab_state.break_keybind =
+            x11_keyboard_grab.break_keybind.map(|keybind| {
+                (
+                    ModifierMask::from(keybind.modifiers),
+                    Keysym::from(keybind.key as u32),
+                )
+            });
+
         let mut command = metaconfig.command.iter();
 
         let arg0 = command
@@ -288,6 +488,7 @@ impl State {
             .context("failed to spawn config")?;
 
         tracing::info!("Started config with {:?}", metaconfig.command);
+        self.wait_for_config();
 
         let reload_mask = ModifierMask::from(reload_keybind.modifiers);
         let kill_mask = ModifierMask::from(kill_keybind.modifiers);
@@ -295,22 +496,10 @@ impl State {
         let reload_keybind = (reload_mask, Keysym::from(reload_keybind.key as u32));
         let kill_keybind = (kill_mask, Keysym::from(kill_keybind.key as u32));
 
+        // Clients are picked up in `handle_client_msg` once they send something
         let socket_token = self
             .loop_handle
-            .insert_source(socket_source, |stream, _, data| {
-                if let Some(old_stream) = data
-                    .state
-                    .api_state
-                    .stream
-                    .replace(Arc::new(Mutex::new(stream)))
-                {
-                    old_stream
-                        .lock()
-                        .expect("Couldn't lock old stream")
-                        .shutdown(std::net::Shutdown::Both)
-                        .expect("Couldn't shutdown old stream");
-                }
-            })?;
+            .insert_source(socket_source, |_, _, _| {})?;
 
         self.input_state.reload_keybind = Some(reload_keybind);
         self.input_state.kill_keybind = Some(kill_keybind);
@@ -358,3 +547,31 @@ impl State {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(active: &str) -> Vec<Tag> {
+        ["1", "2", "3", "4", "5"]
+            .into_iter()
+            .map(|name| {
+                let tag = Tag::new(name.to_string());
+                tag.set_active(name == active);
+                tag
+            })
+            .collect()
+    }
+
+    #[test]
+    fn active_tag_is_remembered_across_reload() {
+        let previous_tags = PreviousTags::of(&tags("4")).unwrap();
+        assert_eq!(previous_tags.names, ["1", "2", "3", "4", "5"]);
+        assert_eq!(previous_tags.active, ["4"]);
+    }
+
+    #[test]
+    fn outputs_without_tags_arent_replayed() {
+        assert!(PreviousTags::of(&[]).is_none());
+    }
+}
@@ -0,0 +1,353 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Sequencing config reloads.
+//!
+//! A reload goes through these phases:
+//!
+//! 1. **Draining**: The old config's stream is shut down and set aside. Anything the old config
+//!    sent that hasn't been handled yet is dropped, so its keybinds and callbacks can't come
+//!    back after its registrations are cleared. Nothing is sent to it anymore.
+//! 2. **Waiting for the config**: The new config process is running and registering things.
+//!    Keybinds pressed now are queued and replayed once it's ready.
+//! 3. **Idle**: The new config sent [`Msg::ConfigReady`](crate::api::msg::Msg::ConfigReady),
+//!    or it took longer than [`RELOAD_TIMEOUT`] and Pinnacle stopped waiting.
+//!
+//! Clients connected with [`Msg::ConnectReload`](crate::api::msg::Msg::ConnectReload) are told
+//! when a reload starts and finishes. One-off clients like bars can use this to refresh
+//! what they show.
+//!
+//! The first config Pinnacle starts goes through the same phases.
+
+use std::{
+    os::unix::net::UnixStream,
+    sync::{atomic::AtomicU64, Arc, Mutex, Weak},
+    time::{Duration, Instant},
+};
+
+use smithay::reexports::calloop::{
+    timer::{TimeoutAction, Timer},
+    RegistrationToken,
+};
+
+use crate::{
+    api::msg::{Args, CallbackId, OutgoingMsg},
+    state::State,
+};
+
+/// How long to wait for a new config to say it's ready before giving up on it.
+const RELOAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Default)]
+pub struct ReloadState {
+    phase: ReloadPhase,
+    /// The streams of replaced configs, whose messages are dropped.
+    ///
+    /// These are gone once their client threads have stopped and their last messages
+    /// were handled.
+    draining_streams: Vec<Weak<Mutex<UnixStream>>>,
+    /// Clients that want to know when reloads start and finish.
+    subscribers: Vec<ReloadSubscriber>,
+    /// The timer that ends the reload if the new config never says it's ready.
+    timeout_token: Option<RegistrationToken>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum ReloadPhase {
+    #[default]
+    Idle,
+    Draining,
+    WaitingForConfig {
+        since: Instant,
+    },
+}
+
+#[derive(Debug)]
+struct ReloadSubscriber {
+    stream: Arc<Mutex<UnixStream>>,
+    callback_id: CallbackId,
+    /// The callback serial of a one-off client. The config uses the shared one in `ApiState`.
+    serial: AtomicU64,
+}
+
+/// Something that happened to a config reload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ReloadEvent {
+    /// The old config was stopped and a new one is starting.
+    Started,
+    /// The new config is ready.
+    Finished,
+    /// The new config didn't say it was ready in time.
+    TimedOut,
+}
+
+impl ReloadState {
+    /// Start draining `old_stream`, the stream of the config being replaced, so its messages
+    /// are dropped and it isn't told about reloads anymore.
+    fn drain(&mut self, old_stream: Option<&Arc<Mutex<UnixStream>>>) {
+        self.phase = ReloadPhase::Draining;
+
+        let Some(old_stream) = old_stream else {
+            return;
+        };
+
+        self.subscribers
+            .retain(|subscriber| !Arc::ptr_eq(&subscriber.stream, old_stream));
+        self.draining_streams
+            .retain(|stream| stream.strong_count() > 0);
+        self.draining_streams.push(Arc::downgrade(old_stream));
+    }
+
+    fn is_draining(&self, stream: &Arc<Mutex<UnixStream>>) -> bool {
+        self.draining_streams
+            .iter()
+            .any(|draining| std::ptr::eq(draining.as_ptr(), Arc::as_ptr(stream)))
+    }
+
+    fn wait(&mut self, now: Instant) {
+        self.phase = ReloadPhase::WaitingForConfig { since: now };
+    }
+
+    fn is_waiting(&self) -> bool {
+        matches!(self.phase, ReloadPhase::WaitingForConfig { .. })
+    }
+
+    /// Go back to idle, returning when the new config started.
+    ///
+    /// Returns `None` if no new config was being waited for, like when the reload
+    /// already finished or timed out.
+    fn finish(&mut self) -> Option<Instant> {
+        let ReloadPhase::WaitingForConfig { since } = self.phase else {
+            return None;
+        };
+        self.phase = ReloadPhase::Idle;
+        Some(since)
+    }
+}
+
+impl State {
+    /// Start a reload, draining the current config.
+    ///
+    /// Call this before clearing the config's registrations.
+    pub fn begin_reload(&mut self) {
+        tracing::info!("Config reload started");
+
+        self.cancel_reload_timeout();
+        self.drop_queued_reload_keybinds();
+
+        let old_stream = self.api_state.stream.take();
+        if let Some(old_stream) = old_stream.as_ref() {
+            if let Err(err) = old_stream
+                .lock()
+                .expect("Couldn't lock old stream")
+                .shutdown(std::net::Shutdown::Both)
+            {
+                tracing::debug!("Failed to shut down old config stream: {err}");
+            }
+            self.clear_window_waits_from(old_stream);
+        }
+
+        self.config.reload.drain(old_stream.as_ref());
+        self.signal_reload(ReloadEvent::Started);
+    }
+
+    /// Wait for the newly spawned config to say it's ready.
+    pub fn wait_for_config(&mut self) {
+        self.config.reload.wait(Instant::now());
+
+        let timeout_token =
+            self.loop_handle
+                .insert_source(Timer::from_duration(RELOAD_TIMEOUT), |_, _, data| {
+                    data.state.config.reload.timeout_token = None;
+                    tracing::warn!(
+                        "Config didn't say it was ready within {RELOAD_TIMEOUT:?}, \
+                    finishing the reload anyway"
+                    );
+                    data.state.finish_reload(ReloadEvent::TimedOut);
+                    TimeoutAction::Drop
+                });
+
+        match timeout_token {
+            Ok(token) => self.config.reload.timeout_token = Some(token),
+            Err(err) => tracing::error!("Failed to insert reload timeout timer: {err}"),
+        }
+    }
+
+    /// Finish the reload because the config on `stream` said it's ready.
+    ///
+    /// This does nothing if `stream` isn't the config or no reload is in progress.
+    pub fn config_ready(&mut self, stream: &Arc<Mutex<UnixStream>>) {
+        let is_config = self
+            .api_state
+            .stream
+            .as_ref()
+            .is_some_and(|config_stream| Arc::ptr_eq(config_stream, stream));
+
+        if !is_config {
+            return;
+        }
+
+        self.cancel_reload_timeout();
+        self.finish_reload(ReloadEvent::Finished);
+    }
+
+    /// Whether a new config is starting and hasn't said it's ready yet.
+    pub fn is_waiting_for_config(&self) -> bool {
+        self.config.reload.is_waiting()
+    }
+
+    /// Whether `stream` belongs to a config that was replaced.
+    pub fn is_draining_stream(&self, stream: &Arc<Mutex<UnixStream>>) -> bool {
+        self.config.reload.is_draining(stream)
+    }
+
+    /// Tell the client on `stream` about reloads by calling the callback with `callback_id`.
+    pub fn connect_reload(&mut self, stream: Arc<Mutex<UnixStream>>, callback_id: CallbackId) {
+        self.config.reload.subscribers.push(ReloadSubscriber {
+            stream,
+            callback_id,
+            serial: AtomicU64::new(0),
+        });
+    }
+
+    fn finish_reload(&mut self, event: ReloadEvent) {
+        let Some(since) = self.config.reload.finish() else {
+            return;
+        };
+
+        match event {
+            ReloadEvent::Finished => {
+                tracing::info!("Config reload finished in {:?}", since.elapsed());
+                self.replay_reload_keybinds();
+            }
+            _ => self.drop_queued_reload_keybinds(),
+        }
+
+        self.signal_reload(event);
+    }
+
+    fn cancel_reload_timeout(&mut self) {
+        if let Some(token) = self.config.reload.timeout_token.take() {
+            self.loop_handle.remove(token);
+        }
+    }
+
+    /// Tell subscribers about `event`, forgetting the ones that went away.
+    fn signal_reload(&mut self, event: ReloadEvent) {
+        let config_stream = self.api_state.stream.as_ref();
+        let config_serial = &self.api_state.callback_serial;
+
+        self.config.reload.subscribers.retain(|subscriber| {
+            let serial = match config_stream {
+                Some(stream) if Arc::ptr_eq(stream, &subscriber.stream) => &**config_serial,
+                _ => &subscriber.serial,
+            };
+
+            let sent = crate::api::send_to_client(
+                &mut subscriber.stream.lock().expect("couldn't lock stream"),
+                &OutgoingMsg::call_callback(
+                    subscriber.callback_id,
+                    Some(Args::ReloadEvent { event }),
+                    serial,
+                ),
+            );
+
+            if let Err(err) = &sent {
+                tracing::debug!("Dropping reload subscriber: {err}");
+            }
+            sent.is_ok()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream() -> Arc<Mutex<UnixStream>> {
+        let (stream, _) = UnixStream::pair().unwrap();
+        Arc::new(Mutex::new(stream))
+    }
+
+    fn subscriber(stream: &Arc<Mutex<UnixStream>>) -> ReloadSubscriber {
+        ReloadSubscriber {
+            stream: stream.clone(),
+            callback_id: CallbackId(0),
+            serial: AtomicU64::new(0),
+        }
+    }
+
+    #[test]
+    fn replaced_config_messages_are_dropped() {
+        let mut reload = ReloadState::default();
+        let old_config = stream();
+        let new_config = stream();
+
+        reload.drain(Some(&old_config));
+        assert!(reload.is_draining(&old_config));
+        assert!(!reload.is_draining(&new_config));
+    }
+
+    #[test]
+    fn replaced_config_isnt_told_about_reloads() {
+        let mut reload = ReloadState::default();
+        let old_config = stream();
+        let bar = stream();
+        reload.subscribers = vec![subscriber(&old_config), subscriber(&bar)];
+
+        reload.drain(Some(&old_config));
+        assert_eq!(reload.subscribers.len(), 1);
+        assert!(Arc::ptr_eq(&reload.subscribers[0].stream, &bar));
+    }
+
+    #[test]
+    fn stopped_configs_are_forgotten() {
+        let mut reload = ReloadState::default();
+        let first = stream();
+        reload.drain(Some(&first));
+        drop(first);
+
+        let second = stream();
+        reload.drain(Some(&second));
+        assert_eq!(reload.draining_streams.len(), 1);
+        assert!(reload.is_draining(&second));
+    }
+
+    #[test]
+    fn slow_config_finishes_once() {
+        let mut reload = ReloadState::default();
+        let start = Instant::now();
+        reload.drain(Some(&stream()));
+        reload.wait(start);
+        assert!(reload.is_waiting());
+
+        // The timeout fires before the slow config says it's ready
+        assert_eq!(reload.finish(), Some(start));
+        assert!(!reload.is_waiting());
+
+        // Its late ConfigReady doesn't finish the reload again,
+        // so nothing is signaled or replayed twice
+        assert_eq!(reload.finish(), None);
+    }
+
+    #[test]
+    fn finishing_without_a_reload_does_nothing() {
+        let mut reload = ReloadState::default();
+        assert_eq!(reload.finish(), None);
+
+        reload.drain(Some(&stream()));
+        assert_eq!(reload.finish(), None);
+    }
+
+    #[test]
+    fn new_reload_while_waiting_restarts_the_wait() {
+        let mut reload = ReloadState::default();
+        let first = Instant::now();
+        reload.wait(first);
+
+        let second = first + Duration::from_secs(1);
+        reload.drain(Some(&stream()));
+        reload.wait(second);
+        assert_eq!(reload.finish(), Some(second));
+        assert_eq!(reload.finish(), None);
+    }
+}
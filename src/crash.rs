@@ -0,0 +1,305 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Crash reports.
+//!
+//! When Pinnacle panics on the udev backend, the session goes with it and the backtrace
+//! scrolls away on the tty. The panic hook installed here writes a report to
+//! `$XDG_STATE_HOME/pinnacle/crash-<timestamp>.txt` first, with Pinnacle's version and
+//! backend, the panic, a backtrace, and the most recent log lines.
+//!
+//! A panic in an API client's thread only disconnects that client. A panic on the main thread
+//! unwinds out of the event loop to [`abort_after_panic`], which adds the same state dump as
+//! `Request::DumpState` to the report, drops the state so the session gives the VT back, and
+//! aborts. The state may be half updated by then, so both are best effort, and a panic in
+//! either is caught and skipped.
+
+use std::{
+    backtrace::Backtrace,
+    collections::VecDeque,
+    fmt::Write as _,
+    io::{self, Write},
+    panic::{AssertUnwindSafe, PanicInfo},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, OnceLock, TryLockError,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use tracing_subscriber::fmt::MakeWriter;
+
+use crate::{
+    state::{CalloopData, State, WithState},
+    XDG_BASE_DIRS,
+};
+
+/// How many log lines are kept for crash reports.
+const RECENT_LOG_LINES: usize = 200;
+
+static RECENT_LOGS: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+static BACKEND: OnceLock<&'static str> = OnceLock::new();
+
+/// Whether a report is being written right now.
+static WRITING_REPORT: AtomicBool = AtomicBool::new(false);
+
+/// The last report that was written, which the state dump is added to.
+static LAST_REPORT: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// A log writer that keeps the most recent log lines for crash reports.
+#[derive(Debug, Clone, Copy)]
+pub struct RecentLogWriter;
+
+impl Write for RecentLogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Losing log lines is better than failing to log
+        let Ok(mut logs) = RECENT_LOGS.lock() else {
+            return Ok(buf.len());
+        };
+
+        for line in String::from_utf8_lossy(buf).lines() {
+            if logs.len() == RECENT_LOG_LINES {
+                logs.pop_front();
+            }
+            logs.push_back(line.to_string());
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for RecentLogWriter {
+    type Writer = Self;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        *self
+    }
+}
+
+/// Remember which backend Pinnacle is running on for crash reports.
+pub fn set_backend(backend: &'static str) {
+    let _ = BACKEND.set(backend);
+}
+
+/// Write a crash report whenever something panics, then run the default panic hook.
+pub fn install_panic_hook() {
+    set_report_hook(|file_name| XDG_BASE_DIRS.place_state_file(file_name));
+}
+
+/// Write a crash report to the path `place_report` gives for its file name whenever
+/// something panics, then run the panic hook that was set before.
+fn set_report_hook(place_report: impl Fn(&str) -> io::Result<PathBuf> + Send + Sync + 'static) {
+    let previous_hook = std::panic::take_hook();
+
+    std::panic::set_hook(Box::new(move |info| {
+        // Another thread may be writing a report already, or writing one may have panicked
+        if !WRITING_REPORT.swap(true, Ordering::SeqCst) {
+            match write_crash_report(&place_report, info) {
+                Ok(path) => {
+                    eprintln!("Wrote crash report to {}", path.display());
+                    if let Ok(mut last_report) = LAST_REPORT.lock() {
+                        *last_report = Some(path);
+                    }
+                }
+                Err(err) => eprintln!("Failed to write crash report: {err}"),
+            }
+            WRITING_REPORT.store(false, Ordering::SeqCst);
+        }
+
+        previous_hook(info);
+    }));
+}
+
+/// Finish the crash report of a panic that unwound out of the event loop, then abort.
+///
+/// The state is dumped into the report, then dropped, which closes the session and gives the
+/// VT back.
+pub fn abort_after_panic(data: CalloopData) -> ! {
+    // Panics from here on are part of this crash, so they shouldn't replace its report
+    WRITING_REPORT.store(true, Ordering::SeqCst);
+
+    let dump = std::panic::catch_unwind(AssertUnwindSafe(|| data.state.dump_state()))
+        .unwrap_or_else(|_| "<unavailable, dumping the state panicked>\n".to_string());
+    if let Err(err) = append_state_dump(&dump) {
+        eprintln!("Failed to add the state dump to the crash report: {err}");
+    }
+
+    if std::panic::catch_unwind(AssertUnwindSafe(move || drop(data))).is_err() {
+        eprintln!("Failed to restore the VT, dropping the state panicked");
+    }
+
+    std::process::abort()
+}
+
+fn append_state_dump(dump: &str) -> io::Result<()> {
+    let path = LAST_REPORT
+        .lock()
+        .ok()
+        .and_then(|last_report| last_report.clone())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no crash report was written"))?;
+
+    let mut file = std::fs::OpenOptions::new().append(true).open(path)?;
+    writeln!(file)?;
+    writeln!(file, "State:")?;
+    write!(file, "{dump}")?;
+    file.flush()
+}
+
+fn write_crash_report(
+    place_report: &dyn Fn(&str) -> io::Result<PathBuf>,
+    info: &PanicInfo<'_>,
+) -> io::Result<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let path = place_report(&format!("crash-{timestamp}.txt"))?;
+    let mut file = std::fs::File::create(&path)?;
+
+    let thread = std::thread::current();
+
+    writeln!(file, "Pinnacle crash report")?;
+    writeln!(file, "Version: {}", env!("CARGO_PKG_VERSION"))?;
+    writeln!(file, "Backend: {}", BACKEND.get().unwrap_or(&"unknown"))?;
+    writeln!(file, "Thread: {}", thread.name().unwrap_or("<unnamed>"))?;
+    writeln!(file, "Time: {timestamp}")?;
+    writeln!(file)?;
+    writeln!(file, "{info}")?;
+    writeln!(file)?;
+    writeln!(file, "Backtrace:")?;
+    writeln!(file, "{}", Backtrace::force_capture())?;
+    writeln!(file)?;
+    writeln!(file, "Recent logs:")?;
+
+    // The panic may have happened while logging, so don't wait for the lock
+    match RECENT_LOGS.try_lock() {
+        Ok(logs) => write_logs(&mut file, &logs)?,
+        Err(TryLockError::Poisoned(poisoned)) => write_logs(&mut file, &poisoned.into_inner())?,
+        Err(TryLockError::WouldBlock) => writeln!(file, "<unavailable>")?,
+    }
+
+    file.flush()?;
+
+    Ok(path)
+}
+
+fn write_logs(file: &mut std::fs::File, logs: &VecDeque<String>) -> io::Result<()> {
+    for line in logs {
+        writeln!(file, "{line}")?;
+    }
+    Ok(())
+}
+
+impl State {
+    /// Describe the outputs, tags, and windows in plain text, for debugging.
+    ///
+    /// RefCell Safety: This uses RefCells on every output and window.
+    pub fn dump_state(&self) -> String {
+        let mut dump = String::new();
+
+        // Writing to a String can't fail
+        let _ = writeln!(
+            dump,
+            "Focused output: {:?}",
+            self.focus_state
+                .focused_output
+                .as_ref()
+                .map(|output| output.name())
+        );
+        let _ = writeln!(
+            dump,
+            "Focused window: {:?}",
+            self.focus_state
+                .focus_stack
+                .last()
+                .map(|window| window.with_state(|state| state.id))
+        );
+
+        let _ = writeln!(dump, "Outputs:");
+        for output in self.space.outputs() {
+            let _ = writeln!(
+                dump,
+                "  {}: {:?}, scale {}",
+                output.name(),
+                self.space.output_geometry(output),
+                output.current_scale().fractional_scale()
+            );
+
+            for tag in output.with_state(|state| state.tags.clone()) {
+                let _ = writeln!(
+                    dump,
+                    "    Tag {:?} {:?}: active {}, layout {:?}",
+                    tag.id(),
+                    tag.name(),
+                    tag.active(),
+                    tag.layout()
+                );
+            }
+        }
+
+        let _ = writeln!(dump, "Windows:");
+        for window in self.windows.iter() {
+            let line = window.with_state(|state| {
+                format!(
+                    "  {:?} {:?} {:?}: tags {:?}, {:?}, {:?}",
+                    state.id,
+                    state.class,
+                    state.title,
+                    state.tags.iter().map(|tag| tag.name()).collect::<Vec<_>>(),
+                    state.floating_or_tiled,
+                    state.fullscreen_or_maximized
+                )
+            });
+            let _ = writeln!(dump, "{line}, at {:?}", self.space.element_geometry(window));
+        }
+
+        dump
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Read, os::unix::net::UnixStream};
+
+    use super::*;
+
+    #[test]
+    fn panics_in_client_threads_write_a_report_and_disconnect() {
+        let dir = std::env::temp_dir().join(format!("pinnacle-crash-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let report_dir = dir.clone();
+        set_report_hook(move |file_name| Ok(report_dir.join(file_name)));
+
+        let (stream, mut client) = UnixStream::pair().unwrap();
+        let (disconnected_tx, disconnected_rx) = std::sync::mpsc::channel();
+        crate::api::spawn_client_thread(
+            stream,
+            |_| panic!("bad message from the client"),
+            move || disconnected_tx.send(()).unwrap(),
+        )
+        .unwrap()
+        .join()
+        .unwrap();
+
+        assert!(disconnected_rx.try_recv().is_ok());
+        assert_eq!(client.read(&mut [0; 1]).unwrap(), 0);
+
+        let report = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+            .find(|report| report.contains("bad message from the client"))
+            .expect("no crash report was written");
+        assert!(report.contains(concat!("Version: ", env!("CARGO_PKG_VERSION"))));
+        assert!(report.contains("Backend: "));
+        assert!(report.contains("Thread: api client"));
+        assert!(report.contains("Backtrace:"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
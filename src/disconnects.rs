@@ -0,0 +1,165 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Remembering why Wayland clients disconnected, so a window that suddenly disappears
+//! can be traced back to a crash or a protocol error Pinnacle sent.
+//!
+//! Only clients that opened a window or were sent a protocol error are remembered.
+//! Everything else, like clipboard tools, comes and goes too often to be useful.
+
+use std::{collections::VecDeque, time::Instant};
+
+use smithay::reexports::wayland_server::{
+    backend::DisconnectReason, protocol::wl_surface::WlSurface, Resource,
+};
+
+use crate::{
+    api::msg::{Args, ClientDisconnectProps, ClientDisconnectReason, OutgoingMsg},
+    state::{ClientState, State},
+    window::window_state::WindowId,
+};
+
+/// How many disconnects are kept around.
+const MAX_RECENT_DISCONNECTS: usize = 32;
+
+/// The process behind a client.
+#[derive(Debug, Clone)]
+pub struct ClientProcess {
+    pub pid: i32,
+    /// The process's name from `/proc`, read when the client connected.
+    pub name: Option<String>,
+}
+
+impl ClientProcess {
+    pub fn new(pid: i32) -> Self {
+        let name = std::fs::read_to_string(format!("/proc/{pid}/comm"))
+            .ok()
+            .map(|name| name.trim_end().to_string());
+        Self { pid, name }
+    }
+}
+
+/// Run `func` with the state of the client that owns `surface`, if it's still connected.
+pub fn with_client_state(surface: &WlSurface, func: impl FnOnce(&ClientState)) {
+    if let Some(client) = surface.client() {
+        if let Some(client_state) = client.get_data::<ClientState>() {
+            func(client_state);
+        }
+    }
+}
+
+/// A client that disconnected, sent from its client data to be recorded.
+#[derive(Debug)]
+pub struct PendingDisconnect {
+    pub process: Option<ClientProcess>,
+    pub reason: DisconnectReason,
+    /// The client's windows that were still open when it disconnected.
+    pub window_ids: Vec<WindowId>,
+    pub time: Instant,
+}
+
+#[derive(Debug)]
+struct ClientDisconnect {
+    process: Option<ClientProcess>,
+    reason: ClientDisconnectReason,
+    window_ids: Vec<WindowId>,
+    time: Instant,
+}
+
+impl ClientDisconnect {
+    fn props(&self) -> ClientDisconnectProps {
+        ClientDisconnectProps {
+            pid: self.process.as_ref().map(|process| process.pid),
+            process_name: self
+                .process
+                .as_ref()
+                .and_then(|process| process.name.clone()),
+            reason: self.reason.clone(),
+            window_ids: self.window_ids.clone(),
+            seconds_ago: self.time.elapsed().as_secs(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DisconnectState {
+    /// Recent disconnects, oldest first.
+    recent: VecDeque<ClientDisconnect>,
+}
+
+impl State {
+    /// Remember a client that disconnected and tell the config about it.
+    pub fn record_client_disconnect(&mut self, pending: PendingDisconnect) {
+        let reason = match pending.reason {
+            DisconnectReason::ProtocolError(err) => ClientDisconnectReason::ProtocolError {
+                interface: err.object_interface,
+                object_id: err.object_id,
+                code: err.code,
+                message: err.message,
+            },
+            // A client that exits normally usually destroys its windows first
+            DisconnectReason::ConnectionClosed if pending.window_ids.is_empty() => {
+                ClientDisconnectReason::Clean
+            }
+            DisconnectReason::ConnectionClosed => ClientDisconnectReason::Crashed,
+        };
+
+        let name = pending
+            .process
+            .as_ref()
+            .and_then(|process| process.name.as_deref())
+            .unwrap_or("unknown");
+        match &reason {
+            ClientDisconnectReason::Clean => tracing::debug!("Client {name} disconnected"),
+            ClientDisconnectReason::Crashed => {
+                tracing::warn!("Client {name} disconnected with windows still open")
+            }
+            ClientDisconnectReason::ProtocolError {
+                interface,
+                object_id,
+                code,
+                message,
+            } => tracing::warn!(
+                "Client {name} was disconnected for a protocol error on {interface}@{object_id} \
+                (code {code}): {message}"
+            ),
+        }
+
+        let disconnect = ClientDisconnect {
+            process: pending.process,
+            reason,
+            window_ids: pending.window_ids,
+            time: pending.time,
+        };
+
+        if let Some(stream) = self.api_state.stream.as_ref() {
+            for callback_id in self.config.client_disconnected_callback_ids.iter() {
+                crate::api::send_to_client(
+                    &mut stream.lock().expect("couldn't lock stream"),
+                    &OutgoingMsg::call_callback(
+                        *callback_id,
+                        Some(Args::ClientDisconnected {
+                            disconnect: disconnect.props(),
+                        }),
+                        &self.api_state.callback_serial,
+                    ),
+                )
+                .expect("Send to client failed");
+            }
+        }
+
+        let recent = &mut self.disconnects.recent;
+        if recent.len() == MAX_RECENT_DISCONNECTS {
+            recent.pop_front();
+        }
+        recent.push_back(disconnect);
+    }
+
+    /// Get recent disconnects, oldest first.
+    pub fn recent_disconnects(&self) -> Vec<ClientDisconnectProps> {
+        self.disconnects
+            .recent
+            .iter()
+            .map(ClientDisconnect::props)
+            .collect()
+    }
+}
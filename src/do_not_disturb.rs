@@ -0,0 +1,128 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Do not disturb mode.
+//!
+//! While enabled, new windows don't steal keyboard focus, and new layer surfaces in the
+//! `top` and `overlay` layers whose namespace matches one of the held namespaces aren't mapped.
+//!
+//! Held layer surfaces don't receive their initial configure until do not disturb is disabled.
+//! Clients are required to wait for that configure before attaching a buffer, so from their
+//! point of view the surface simply hasn't been configured yet.
+//!
+//! Pinnacle doesn't implement `xdg_activation_v1`, so clients have no way to ask for focus that
+//! would need to be held back too.
+
+use smithay::{
+    desktop::{layer_map_for_output, LayerSurface},
+    output::Output,
+    utils::IsAlive,
+    wayland::shell::wlr_layer::{self, LayerSurfaceData},
+};
+
+use crate::{
+    api::msg::{Args, OutgoingMsg},
+    state::State,
+};
+
+#[derive(Debug, Default)]
+pub struct DoNotDisturbState {
+    /// Whether or not do not disturb is on.
+    pub enabled: bool,
+    /// The namespaces of top and overlay layer surfaces that will be held while enabled.
+    pub held_namespaces: Vec<String>,
+    /// Layer surfaces that have been held back, along with the output they will be mapped on.
+    pub held_layers: Vec<(LayerSurface, Output)>,
+}
+
+impl DoNotDisturbState {
+    /// Returns whether a newly created layer surface in `layer` with `namespace` should be held.
+    pub fn should_hold_layer(&self, layer: wlr_layer::Layer, namespace: &str) -> bool {
+        self.enabled
+            && matches!(layer, wlr_layer::Layer::Top | wlr_layer::Layer::Overlay)
+            && self.held_namespaces.iter().any(|ns| ns == namespace)
+    }
+}
+
+impl State {
+    /// Turn do not disturb on or off.
+    ///
+    /// Turning it off maps all held layer surfaces.
+    pub fn set_do_not_disturb(&mut self, enabled: bool) {
+        if self.do_not_disturb.enabled == enabled {
+            return;
+        }
+
+        tracing::info!("Setting do not disturb to {enabled}");
+        self.do_not_disturb.enabled = enabled;
+
+        if !enabled {
+            self.release_held_layers();
+        }
+
+        self.signal_do_not_disturb_changed();
+    }
+
+    /// Tell the config that do not disturb was turned on or off.
+    fn signal_do_not_disturb_changed(&self) {
+        let Some(stream) = self.api_state.stream.as_ref() else {
+            return;
+        };
+
+        for callback_id in self.config.do_not_disturb_callback_ids.iter() {
+            if let Err(err) = crate::api::send_to_client(
+                &mut stream.lock().expect("couldn't lock stream"),
+                &OutgoingMsg::call_callback(
+                    *callback_id,
+                    Some(Args::DoNotDisturbChanged {
+                        enabled: self.do_not_disturb.enabled,
+                    }),
+                    &self.api_state.callback_serial,
+                ),
+            ) {
+                tracing::warn!("Failed to send callback to client: {err}");
+            }
+        }
+    }
+
+    /// Map every held layer surface that is still alive and send it its initial configure.
+    fn release_held_layers(&mut self) {
+        let held_layers = std::mem::take(&mut self.do_not_disturb.held_layers);
+
+        for (layer, output) in held_layers.into_iter().filter(|(layer, _)| layer.alive()) {
+            // The output may have been unplugged in the meantime
+            let output = if self.space.outputs().any(|op| op == &output) {
+                output
+            } else if let Some(output) = self.space.outputs().next() {
+                output.clone()
+            } else {
+                continue;
+            };
+
+            let mut map = layer_map_for_output(&output);
+            if let Err(err) = map.map_layer(&layer) {
+                tracing::error!("Failed to map held layer surface: {err}");
+                continue;
+            }
+            map.arrange();
+
+            let initial_configure_sent =
+                smithay::wayland::compositor::with_states(layer.wl_surface(), |states| {
+                    states
+                        .data_map
+                        .get::<LayerSurfaceData>()
+                        .expect("no LayerSurfaceData")
+                        .lock()
+                        .expect("failed to lock data")
+                        .initial_configure_sent
+                });
+
+            if !initial_configure_sent {
+                layer.layer_surface().send_configure();
+            }
+            drop(map);
+
+            self.update_windows(&output);
+            self.schedule_render(&output);
+        }
+    }
+}
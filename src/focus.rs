@@ -1,7 +1,10 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+pub mod exclusive_layer;
+pub mod mru;
+
 use smithay::{
-    desktop::{LayerSurface, PopupKind, Space},
+    desktop::{find_popup_root_surface, layer_map_for_output, LayerSurface, PopupKind},
     input::{
         keyboard::KeyboardTarget,
         pointer::{MotionEvent, PointerTarget},
@@ -10,20 +13,61 @@ use smithay::{
     output::Output,
     reexports::wayland_server::{protocol::wl_surface::WlSurface, Resource},
     utils::{IsAlive, SERIAL_COUNTER},
-    wayland::seat::WaylandFocus,
+    wayland::{keyboard_shortcuts_inhibit::KeyboardShortcutsInhibitor, seat::WaylandFocus},
 };
 
 use crate::{
+    api::msg::{Args, CallbackId, OutgoingMsg},
     state::{State, WithState},
+    tag::Tag,
     window::WindowElement,
 };
 
+use self::{exclusive_layer::is_layer_mapped, mru::MruCycle};
+
 #[derive(Default)]
 pub struct FocusState {
     /// The ordering of window focus
     pub focus_stack: Vec<WindowElement>,
     /// The focused output, currently defined to be the one the pointer is on.
     pub focused_output: Option<Output>,
+    /// The window that is currently activated, if any.
+    pub activated: Option<WindowElement>,
+    /// What to do with focus when the focused window is moved to another tag.
+    pub follow_moved_window: FollowPolicy,
+    /// Layer surfaces that took keyboard focus, most recent last.
+    ///
+    /// While one of these is still mapped and wants keyboard input, it keeps keyboard focus
+    /// when focus is updated, like when switching tags. The stack is cleared when
+    /// something else is clicked.
+    pub layer_focus_stack: Vec<LayerSurface>,
+    /// The window that has pointer focus, if any.
+    ///
+    /// This follows where the seat delivers pointer enter and leave events, so during a grab
+    /// it's whatever the grab sends pointer events to. Move and resize grabs don't send them
+    /// to any window, so this is `None` while they're active.
+    pub pointer_focus: Option<WindowElement>,
+    /// The keyboard shortcuts inhibitor of the surface with keyboard focus, if it has one.
+    /// This is the only inhibitor that is active.
+    pub shortcuts_inhibitor: Option<KeyboardShortcutsInhibitor>,
+    /// The pointer focus the config was last told about.
+    signaled_pointer_focus: Option<WindowElement>,
+    /// Whether telling the config about a pointer focus change is already scheduled.
+    pointer_focus_signal_pending: bool,
+    /// The cycle through recently focused windows, if one is going on.
+    pub mru_cycle: Option<MruCycle>,
+}
+
+/// What to do with focus when the focused window is moved to another tag.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum FollowPolicy {
+    /// Leave focus alone.
+    #[default]
+    Stay,
+    /// Switch to the window's new tag and keep the window focused.
+    SwitchTag,
+    /// Stay on the current tag and focus the next window on it.
+    FocusNext,
 }
 
 impl State {
@@ -45,28 +89,216 @@ impl State {
         windows.find(|win| !win.is_x11_override_redirect()).cloned()
     }
 
+    /// Get the layer surface that should keep keyboard focus over windows, if any.
+    ///
+    /// This is a layer surface that wants exclusive keyboard focus, or else the last layer
+    /// surface that took keyboard focus that is still mapped and wants keyboard input.
+    fn focused_layer(&mut self) -> Option<LayerSurface> {
+        if let Some(layer) = self.exclusive_layer() {
+            return Some(layer);
+        }
+
+        let outputs = self.space.outputs().cloned().collect::<Vec<_>>();
+        self.focus_state.layer_focus_stack.retain(|layer| {
+            layer.alive()
+                && layer.can_receive_keyboard_focus()
+                && is_layer_mapped(layer)
+                && outputs
+                    .iter()
+                    .any(|op| layer_map_for_output(op).layers().any(|l| l == layer))
+        });
+
+        self.focus_state.layer_focus_stack.last().cloned()
+    }
+
     /// Update the focus. This will raise the current focus and activate it,
     /// as well as setting the keyboard focus to it.
+    ///
+    /// If a layer surface that took keyboard focus is still around, it gets
+    /// keyboard focus instead.
     pub fn update_focus(&mut self, output: &Output) {
         let current_focus = self.focused_window(output);
 
         if let Some(win) = &current_focus {
             assert!(!win.is_x11_override_redirect());
 
-            self.space.raise_element(win, true);
-            if let WindowElement::Wayland(w) = win {
-                w.toplevel().send_configure();
-            }
+            self.raise_window(win);
         }
 
+        let keyboard_focus = match self.focused_layer() {
+            Some(layer) => Some(FocusTarget::LayerSurface(layer)),
+            None => current_focus.map(|win| win.into()),
+        };
+
         self.seat.get_keyboard().expect("no keyboard").set_focus(
             self,
-            current_focus.map(|win| win.into()),
+            keyboard_focus,
             SERIAL_COUNTER.next_serial(),
         );
 
         // TODO: if there already is a visible focused window, don't do anything
     }
+
+    /// Fix focus layering for all windows in the `focus_stack`.
+    ///
+    /// This raises every window's group from back to front to correct their z locations.
+    pub fn fix_up_focus(&mut self) {
+        for win in self.focus_state.focus_stack.clone() {
+            self.raise_window_in_space(&win);
+        }
+    }
+
+    /// Focus the window after the currently focused one on `output`, or the one before it
+    /// if `forward` is false, wrapping around.
+    ///
+    /// Windows are cycled through in the order they are laid out.
+    pub fn cycle_focus(&mut self, output: &Output, forward: bool) {
+        let windows = self
+            .windows
+            .iter()
+            .filter(|win| !win.is_x11_override_redirect() && win.is_on_active_tag([output]))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if windows.is_empty() {
+            return;
+        }
+
+        let next_index = match self
+            .focused_window(output)
+            .and_then(|focused| windows.iter().position(|win| win == &focused))
+        {
+            Some(index) if forward => (index + 1) % windows.len(),
+            Some(index) => (index + windows.len() - 1) % windows.len(),
+            None => 0,
+        };
+
+        let window = windows[next_index].clone();
+        self.focus_state.set_focus(window.clone());
+        self.update_focus(output);
+        tracing::debug!("Cycled focus to {:?}", window.class());
+    }
+
+    /// Update focus after the focused `window` was moved to `tag` from `old_output`,
+    /// according to [`FocusState::follow_moved_window`].
+    pub fn follow_moved_window(
+        &mut self,
+        window: &WindowElement,
+        tag: &Tag,
+        old_output: Option<&Output>,
+    ) {
+        match self.focus_state.follow_moved_window {
+            FollowPolicy::Stay => (),
+            FollowPolicy::SwitchTag => {
+                let Some(output) = tag.output(self) else { return };
+                if !tag.active() {
+                    self.switch_to_tag(tag);
+                }
+                self.focus_state.set_focus(window.clone());
+                self.update_focus(&output);
+            }
+            FollowPolicy::FocusNext => {
+                if let Some(old_output) = old_output {
+                    self.update_focus(old_output);
+                }
+            }
+        }
+    }
+
+    /// Get the window `target` belongs to, if any.
+    ///
+    /// Popups belong to the window they were opened from. Override redirect windows and
+    /// layer surfaces don't count as windows here.
+    pub fn window_for_focus_target(&self, target: &FocusTarget) -> Option<WindowElement> {
+        match target {
+            FocusTarget::Window(win) if !win.is_x11_override_redirect() => Some(win.clone()),
+            FocusTarget::Popup(popup) => find_popup_root_surface(popup)
+                .ok()
+                .and_then(|root| self.window_for_surface(&root)),
+            _ => None,
+        }
+    }
+
+    /// Get the window that has pointer focus, if any.
+    pub fn pointer_focused_window(&self) -> Option<WindowElement> {
+        self.focus_state
+            .pointer_focus
+            .clone()
+            .filter(|win| win.alive())
+    }
+
+    /// Set the window that has pointer focus.
+    ///
+    /// The config is told once the current event is done being handled, so moving between
+    /// a window and its popups doesn't tell it about the pointer briefly leaving the window.
+    pub fn set_pointer_focused_window(&mut self, window: Option<WindowElement>) {
+        self.focus_state.pointer_focus = window;
+
+        if self.focus_state.pointer_focus_signal_pending {
+            return;
+        }
+        self.focus_state.pointer_focus_signal_pending = true;
+
+        self.loop_handle.insert_idle(|data| {
+            let state = &mut data.state;
+            state.focus_state.pointer_focus_signal_pending = false;
+
+            let focus = state.pointer_focused_window();
+            if focus == state.focus_state.signaled_pointer_focus {
+                return;
+            }
+            state.focus_state.signaled_pointer_focus = focus.clone();
+
+            let callback_ids = state.config.pointer_focus_callback_ids.clone();
+            state.signal_focus_changed(&callback_ids, focus.as_ref());
+        });
+    }
+
+    /// Tell the config that the window with keyboard or pointer focus changed to `window`.
+    fn signal_focus_changed(&self, callback_ids: &[CallbackId], window: Option<&WindowElement>) {
+        let Some(stream) = self.api_state.stream.as_ref() else {
+            return;
+        };
+
+        let window_id = window.map(|win| win.with_state(|state| state.id));
+
+        for callback_id in callback_ids {
+            crate::api::send_to_client(
+                &mut stream.lock().expect("couldn't lock stream"),
+                &OutgoingMsg::call_callback(
+                    *callback_id,
+                    Some(Args::WindowFocusChanged { window_id }),
+                    &self.api_state.callback_serial,
+                ),
+            )
+            .expect("Send to client failed");
+        }
+    }
+
+    /// Activate `window` and deactivate the previously activated window.
+    ///
+    /// Only the windows whose activated state changes get configured,
+    /// so this is cheap to call with the already activated window.
+    pub fn set_activated_window(&mut self, window: Option<WindowElement>) {
+        if self.focus_state.activated == window {
+            return;
+        }
+
+        let prev = self
+            .focus_state
+            .activated
+            .take()
+            .filter(|prev| prev.alive());
+
+        for (win, activated) in activation_changes(prev, window.clone()) {
+            win.set_activated(activated);
+            self.mark_window_focus_activity(&win);
+        }
+
+        self.signal_focus_changed(&self.config.keyboard_focus_callback_ids, window.as_ref());
+
+        self.focus_state.activated = window;
+    }
 }
 
 impl FocusState {
@@ -80,14 +312,10 @@ impl FocusState {
         self.focus_stack.push(window);
     }
 
-    /// Fix focus layering for all windows in the `focus_stack`.
-    ///
-    /// This will call `space.raise_element` on all windows from back
-    /// to front to correct their z locations.
-    pub fn fix_up_focus(&self, space: &mut Space<WindowElement>) {
-        for win in self.focus_stack.iter() {
-            space.raise_element(win, false);
-        }
+    /// Remember that `layer` took keyboard focus so it can keep it when focus is updated.
+    pub fn push_layer_focus(&mut self, layer: LayerSurface) {
+        self.layer_focus_stack.retain(|l| l != &layer);
+        self.layer_focus_stack.push(layer);
     }
 }
 
@@ -127,6 +355,9 @@ impl PointerTarget<State> for FocusTarget {
     }
 
     fn enter(&self, seat: &Seat<State>, data: &mut State, event: &MotionEvent) {
+        data.debug_overlay_pointer_focus(self);
+        data.set_pointer_focused_window(data.window_for_focus_target(self));
+
         match self {
             FocusTarget::Window(window) => PointerTarget::enter(window, seat, data, event),
             FocusTarget::Popup(popup) => {
@@ -200,6 +431,11 @@ impl PointerTarget<State> for FocusTarget {
         serial: smithay::utils::Serial,
         time: u32,
     ) {
+        let window = data.window_for_focus_target(self);
+        if window.is_some() && data.focus_state.pointer_focus == window {
+            data.set_pointer_focused_window(None);
+        }
+
         match self {
             FocusTarget::Window(window) => PointerTarget::leave(window, seat, data, serial, time),
             FocusTarget::Popup(popup) => {
@@ -211,74 +447,154 @@ impl PointerTarget<State> for FocusTarget {
 
     fn gesture_swipe_begin(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GestureSwipeBeginEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GestureSwipeBeginEvent,
     ) {
-        todo!()
+        match self {
+            FocusTarget::Window(window) => {
+                PointerTarget::gesture_swipe_begin(window, seat, data, event)
+            }
+            FocusTarget::Popup(popup) => {
+                PointerTarget::gesture_swipe_begin(popup.wl_surface(), seat, data, event);
+            }
+            FocusTarget::LayerSurface(surf) => {
+                PointerTarget::gesture_swipe_begin(surf, seat, data, event);
+            }
+        }
     }
 
     fn gesture_swipe_update(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GestureSwipeUpdateEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GestureSwipeUpdateEvent,
     ) {
-        todo!()
+        match self {
+            FocusTarget::Window(window) => {
+                PointerTarget::gesture_swipe_update(window, seat, data, event)
+            }
+            FocusTarget::Popup(popup) => {
+                PointerTarget::gesture_swipe_update(popup.wl_surface(), seat, data, event);
+            }
+            FocusTarget::LayerSurface(surf) => {
+                PointerTarget::gesture_swipe_update(surf, seat, data, event);
+            }
+        }
     }
 
     fn gesture_swipe_end(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GestureSwipeEndEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GestureSwipeEndEvent,
     ) {
-        todo!()
+        match self {
+            FocusTarget::Window(window) => {
+                PointerTarget::gesture_swipe_end(window, seat, data, event)
+            }
+            FocusTarget::Popup(popup) => {
+                PointerTarget::gesture_swipe_end(popup.wl_surface(), seat, data, event);
+            }
+            FocusTarget::LayerSurface(surf) => {
+                PointerTarget::gesture_swipe_end(surf, seat, data, event);
+            }
+        }
     }
 
     fn gesture_pinch_begin(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GesturePinchBeginEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GesturePinchBeginEvent,
     ) {
-        todo!()
+        match self {
+            FocusTarget::Window(window) => {
+                PointerTarget::gesture_pinch_begin(window, seat, data, event)
+            }
+            FocusTarget::Popup(popup) => {
+                PointerTarget::gesture_pinch_begin(popup.wl_surface(), seat, data, event);
+            }
+            FocusTarget::LayerSurface(surf) => {
+                PointerTarget::gesture_pinch_begin(surf, seat, data, event);
+            }
+        }
     }
 
     fn gesture_pinch_update(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GesturePinchUpdateEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GesturePinchUpdateEvent,
     ) {
-        todo!()
+        match self {
+            FocusTarget::Window(window) => {
+                PointerTarget::gesture_pinch_update(window, seat, data, event)
+            }
+            FocusTarget::Popup(popup) => {
+                PointerTarget::gesture_pinch_update(popup.wl_surface(), seat, data, event);
+            }
+            FocusTarget::LayerSurface(surf) => {
+                PointerTarget::gesture_pinch_update(surf, seat, data, event);
+            }
+        }
     }
 
     fn gesture_pinch_end(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GesturePinchEndEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GesturePinchEndEvent,
     ) {
-        todo!()
+        match self {
+            FocusTarget::Window(window) => {
+                PointerTarget::gesture_pinch_end(window, seat, data, event)
+            }
+            FocusTarget::Popup(popup) => {
+                PointerTarget::gesture_pinch_end(popup.wl_surface(), seat, data, event);
+            }
+            FocusTarget::LayerSurface(surf) => {
+                PointerTarget::gesture_pinch_end(surf, seat, data, event);
+            }
+        }
     }
 
     fn gesture_hold_begin(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GestureHoldBeginEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GestureHoldBeginEvent,
     ) {
-        todo!()
+        match self {
+            FocusTarget::Window(window) => {
+                PointerTarget::gesture_hold_begin(window, seat, data, event)
+            }
+            FocusTarget::Popup(popup) => {
+                PointerTarget::gesture_hold_begin(popup.wl_surface(), seat, data, event);
+            }
+            FocusTarget::LayerSurface(surf) => {
+                PointerTarget::gesture_hold_begin(surf, seat, data, event);
+            }
+        }
     }
 
     fn gesture_hold_end(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GestureHoldEndEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GestureHoldEndEvent,
     ) {
-        todo!()
+        match self {
+            FocusTarget::Window(window) => {
+                PointerTarget::gesture_hold_end(window, seat, data, event)
+            }
+            FocusTarget::Popup(popup) => {
+                PointerTarget::gesture_hold_end(popup.wl_surface(), seat, data, event);
+            }
+            FocusTarget::LayerSurface(surf) => {
+                PointerTarget::gesture_hold_end(surf, seat, data, event);
+            }
+        }
     }
 }
 
@@ -395,3 +711,72 @@ impl From<LayerSurface> for FocusTarget {
         FocusTarget::LayerSurface(value)
     }
 }
+
+/// Get the windows to configure when `next` is activated in place of `prev`,
+/// along with whether each one is now activated.
+///
+/// This is at most the window losing activation and the window gaining it.
+fn activation_changes<W: PartialEq>(prev: Option<W>, next: Option<W>) -> Vec<(W, bool)> {
+    if prev == next {
+        return Vec::new();
+    }
+
+    prev.map(|prev| (prev, false))
+        .into_iter()
+        .chain(next.map(|next| (next, true)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Click on each window in `clicks`, or on empty space for `None`, and
+    /// check that every click configures at most two windows.
+    fn click_through(windows: usize, clicks: &[Option<usize>]) {
+        let mut activated = vec![false; windows];
+        let mut current = None;
+
+        for &click in clicks {
+            let changes = activation_changes(current, click);
+            assert!(
+                changes.len() <= 2,
+                "click on {click:?} configured {changes:?}"
+            );
+
+            for (window, now_activated) in changes {
+                assert_ne!(activated[window], now_activated);
+                activated[window] = now_activated;
+            }
+            current = click;
+
+            let expected = (0..windows).map(|window| Some(window) == click);
+            assert!(activated.iter().copied().eq(expected));
+        }
+    }
+
+    #[test]
+    fn clicks_configure_at_most_two_windows() {
+        click_through(
+            10,
+            &[Some(0), Some(3), Some(9), None, Some(9), Some(2), Some(7)],
+        );
+    }
+
+    #[test]
+    fn clicking_the_activated_window_configures_nothing() {
+        assert!(activation_changes(Some(1), Some(1)).is_empty());
+        assert!(activation_changes::<usize>(None, None).is_empty());
+        click_through(3, &[Some(1), Some(1), Some(1), None, None]);
+    }
+
+    #[test]
+    fn clicking_empty_space_only_deactivates() {
+        assert_eq!(activation_changes(Some(4), None), [(4, false)]);
+        assert_eq!(activation_changes(None, Some(4)), [(4, true)]);
+        assert_eq!(
+            activation_changes(Some(4), Some(5)),
+            [(4, false), (5, true)]
+        );
+    }
+}
@@ -0,0 +1,112 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Layer surfaces that want exclusive keyboard focus, like launchers and screen lockers.
+//!
+//! A mapped layer surface on the top or overlay layer with exclusive keyboard interactivity
+//! gets keyboard focus as soon as it maps and keeps it until it unmaps, stops asking for it,
+//! or is destroyed. Focus then goes back to what had it before.
+//!
+//! While it has focus, clicking other things doesn't move keyboard focus away from it, and
+//! keybinds other than the kill and reload keybinds and VT switching don't run, so typing
+//! into a launcher can't trigger them.
+//!
+//! Layer surfaces with on-demand keyboard interactivity are focused when clicked instead.
+
+use smithay::{
+    backend::renderer::utils::with_renderer_surface_state,
+    desktop::{layer_map_for_output, LayerSurface, WindowSurfaceType},
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    utils::{IsAlive, SERIAL_COUNTER},
+    wayland::shell::wlr_layer::{KeyboardInteractivity, Layer},
+};
+
+use crate::state::State;
+
+use super::FocusTarget;
+
+/// Whether `layer` has a buffer attached, so it's actually shown.
+pub fn is_layer_mapped(layer: &LayerSurface) -> bool {
+    with_renderer_surface_state(layer.wl_surface(), |state| state.buffer().is_some())
+        .unwrap_or(false)
+}
+
+/// Whether `layer` wants keyboard focus for as long as it's mapped.
+fn wants_exclusive_focus(layer: &LayerSurface) -> bool {
+    layer.alive()
+        && layer.cached_state().keyboard_interactivity == KeyboardInteractivity::Exclusive
+        && is_layer_mapped(layer)
+}
+
+impl State {
+    /// Get the layer surface that should have exclusive keyboard focus, if any.
+    ///
+    /// Overlay surfaces come before top surfaces, and surfaces on the focused output
+    /// come before ones on other outputs.
+    pub fn exclusive_layer(&self) -> Option<LayerSurface> {
+        let focused_output = self.focus_state.focused_output.clone();
+        let outputs = focused_output.iter().chain(
+            self.space
+                .outputs()
+                .filter(|op| focused_output.as_ref() != Some(*op)),
+        );
+
+        for output in outputs {
+            let map = layer_map_for_output(output);
+            let layer = [Layer::Overlay, Layer::Top].into_iter().find_map(|layer| {
+                map.layers_on(layer)
+                    .find(|layer| wants_exclusive_focus(layer))
+                    .cloned()
+            });
+
+            if layer.is_some() {
+                return layer;
+            }
+        }
+
+        None
+    }
+
+    /// Whether a layer surface with exclusive keyboard interactivity has keyboard focus.
+    pub fn exclusive_layer_has_keyboard_focus(&self) -> bool {
+        self.seat
+            .get_keyboard()
+            .and_then(|keyboard| keyboard.current_focus())
+            .is_some_and(|focus| match focus {
+                FocusTarget::LayerSurface(layer) => wants_exclusive_focus(&layer),
+                _ => false,
+            })
+    }
+
+    /// Update keyboard focus after the layer surface `surface` committed.
+    ///
+    /// This focuses a layer surface that now wants exclusive keyboard focus, and gives focus
+    /// back once the focused one unmaps or stops wanting keyboard input.
+    pub fn update_layer_keyboard_focus(&mut self, surface: &WlSurface) {
+        let Some((layer, output)) = self.space.outputs().find_map(|op| {
+            layer_map_for_output(op)
+                .layer_for_surface(surface, WindowSurfaceType::TOPLEVEL)
+                .cloned()
+                .map(|layer| (layer, op.clone()))
+        }) else {
+            return;
+        };
+
+        let keyboard = self.seat.get_keyboard().expect("Seat has no keyboard");
+        let current_focus = keyboard.current_focus();
+
+        if let Some(exclusive) = self.exclusive_layer() {
+            let target = FocusTarget::LayerSurface(exclusive);
+            if current_focus.as_ref() != Some(&target) && self.input_inhibitor_state.allows(&target)
+            {
+                tracing::debug!("Giving exclusive keyboard focus to layer surface");
+                keyboard.set_focus(self, Some(target), SERIAL_COUNTER.next_serial());
+            }
+            return;
+        }
+
+        let had_focus = current_focus == Some(FocusTarget::LayerSurface(layer.clone()));
+        if had_focus && (!is_layer_mapped(&layer) || !layer.can_receive_keyboard_focus()) {
+            self.update_focus(&output);
+        }
+    }
+}
@@ -0,0 +1,198 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Alt-Tab style cycling through windows in the order they were last focused.
+//!
+//! Starting a cycle takes a snapshot of the focus stack, most recent first, with only windows
+//! on active tags. Each step focuses and raises the next window in the snapshot. Focusing a
+//! window moves it to the top of the focus stack, so the stack from when the cycle started is
+//! kept and put back when the cycle is committed, with only the chosen window moved to the top.
+//! Windows passed over on the way keep their place.
+//!
+//! A cycle is committed through the API, or once the modifiers held when it started, other
+//! than Shift, are all released. If no such modifiers were held, only the API commits it.
+//! Focusing some other window ends the cycle where it is.
+
+use smithay::utils::IsAlive;
+
+use crate::{
+    api::msg::{Modifier, ModifierMask},
+    state::State,
+    window::WindowElement,
+};
+
+/// Which way to cycle through windows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum CycleDirection {
+    /// To less recently focused windows.
+    Forward,
+    /// To more recently focused windows.
+    Backward,
+}
+
+/// A cycle through recently focused windows that hasn't been committed yet.
+#[derive(Debug)]
+pub struct MruCycle {
+    /// The focus stack when the cycle started, most recent last
+    stack: Vec<WindowElement>,
+    /// The windows being cycled through, most recent first
+    candidates: Vec<WindowElement>,
+    /// The index of the window in `candidates` that is focused
+    index: usize,
+    /// Modifiers that commit the cycle once none of them are held
+    commit_modifiers: Vec<Modifier>,
+}
+
+impl MruCycle {
+    fn current(&self) -> Option<&WindowElement> {
+        self.candidates.get(self.index)
+    }
+}
+
+impl State {
+    /// Focus the next window in the cycle through recently focused windows,
+    /// starting a cycle if there isn't one.
+    pub fn cycle_mru_focus(&mut self, direction: CycleDirection) {
+        let mut cycle = match self.focus_state.mru_cycle.take() {
+            Some(cycle) => cycle,
+            None => match self.start_mru_cycle() {
+                Some(cycle) => cycle,
+                None => return,
+            },
+        };
+
+        // Windows that closed during the cycle are skipped from now on
+        let current = cycle.current().cloned();
+        cycle.candidates.retain(|win| win.alive());
+        cycle.stack.retain(|win| win.alive());
+        if cycle.candidates.is_empty() {
+            return;
+        }
+
+        let len = cycle.candidates.len();
+        let index = current
+            .and_then(|current| cycle.candidates.iter().position(|win| win == &current))
+            .unwrap_or(0);
+        cycle.index = match direction {
+            CycleDirection::Forward => (index + 1) % len,
+            CycleDirection::Backward => (index + len - 1) % len,
+        };
+
+        let window = cycle.candidates[cycle.index].clone();
+        // The cycle has to be in place before focus changes so it isn't ended
+        self.focus_state.mru_cycle = Some(cycle);
+
+        let Some(output) = window.output(self) else {
+            return;
+        };
+        tracing::debug!("Cycling focus to {:?}", window.class());
+        self.focus_state.set_focus(window);
+        self.update_focus(&output);
+    }
+
+    fn start_mru_cycle(&mut self) -> Option<MruCycle> {
+        self.focus_state.focus_stack.retain(|win| win.alive());
+
+        let outputs = self.space.outputs().cloned().collect::<Vec<_>>();
+        let candidates = self
+            .focus_state
+            .focus_stack
+            .iter()
+            .rev()
+            .filter(|win| !win.is_x11_override_redirect() && win.is_on_active_tag(outputs.iter()))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if candidates.len() < 2 {
+            return None;
+        }
+
+        let held = self
+            .seat
+            .get_keyboard()
+            .map(|keyboard| ModifierMask::from(keyboard.modifier_state()).values())
+            .unwrap_or_default();
+
+        Some(MruCycle {
+            stack: self.focus_state.focus_stack.clone(),
+            candidates,
+            index: 0,
+            commit_modifiers: held
+                .into_iter()
+                .filter(|modifier| *modifier != Modifier::Shift)
+                .collect(),
+        })
+    }
+
+    /// Commit the cycle through recently focused windows, moving the focused window
+    /// to the top of the focus stack and putting the rest back where they were.
+    pub fn commit_mru_cycle(&mut self) {
+        let Some(cycle) = self.focus_state.mru_cycle.take() else {
+            return;
+        };
+        let Some(chosen) = cycle.current().filter(|win| win.alive()).cloned() else {
+            return;
+        };
+
+        let mut stack = cycle.stack;
+        stack.retain(|win| win.alive() && win != &chosen);
+
+        // Windows that opened during the cycle go under the rest
+        let new_windows = self
+            .focus_state
+            .focus_stack
+            .iter()
+            .filter(|win| win.alive() && win != &&chosen && !stack.contains(win))
+            .cloned()
+            .collect::<Vec<_>>();
+        stack.splice(0..0, new_windows);
+
+        stack.push(chosen);
+        self.focus_state.focus_stack = stack;
+    }
+
+    /// Commit the cycle through recently focused windows if none of the modifiers that were
+    /// held when it started are held anymore.
+    pub fn commit_mru_cycle_on_release(&mut self, held: ModifierMask) {
+        let Some(cycle) = self.focus_state.mru_cycle.as_ref() else {
+            return;
+        };
+        let held = held.values();
+        if cycle.commit_modifiers.is_empty()
+            || cycle
+                .commit_modifiers
+                .iter()
+                .any(|modifier| held.contains(modifier))
+        {
+            return;
+        }
+
+        self.commit_mru_cycle();
+    }
+
+    /// End the cycle through recently focused windows if `window` was focused by something else.
+    pub fn end_mru_cycle_unless_current(&mut self, window: &WindowElement) {
+        let is_current = self
+            .focus_state
+            .mru_cycle
+            .as_ref()
+            .is_some_and(|cycle| cycle.current() == Some(window));
+
+        if !is_current {
+            self.focus_state.mru_cycle = None;
+        }
+    }
+
+    /// Get the window that was focused before the focused one on the active tags, if any.
+    pub fn previous_focused_window(&mut self) -> Option<WindowElement> {
+        self.focus_state.focus_stack.retain(|win| win.alive());
+
+        let outputs = self.space.outputs().cloned().collect::<Vec<_>>();
+        self.focus_state
+            .focus_stack
+            .iter()
+            .rev()
+            .filter(|win| !win.is_x11_override_redirect() && win.is_on_active_tag(outputs.iter()))
+            .nth(1)
+            .cloned()
+    }
+}
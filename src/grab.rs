@@ -1,7 +1,12 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+pub mod cancel;
+pub mod edge_tile;
+pub mod layout_resize;
 pub mod move_grab;
+pub mod popup_grab;
 pub mod resize_grab;
+pub mod snap;
 
 use smithay::{
     input::{
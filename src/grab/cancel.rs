@@ -0,0 +1,122 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Cancelling window moves and resizes with Escape.
+//!
+//! When a move or resize grab starts, what the window was like before it is kept here.
+//! Pressing Escape while the grab is going ends it without the button being released and puts
+//! the window back: its location, size, floating geometry, and whether it was floating all go
+//! back to what they were.
+//!
+//! The grab is remembered by the serial it was set with, so Escape doesn't cancel a grab that
+//! replaced it, like a popup grab.
+
+use std::time::Duration;
+
+use smithay::{
+    reexports::wayland_protocols::xdg::shell::server::xdg_toplevel,
+    utils::{IsAlive, Logical, Rectangle, Serial, SERIAL_COUNTER},
+};
+
+use crate::{
+    grab::resize_grab::ResizeSurfaceState,
+    state::{State, WithState},
+    window::{window_state::FloatingOrTiled, WindowElement},
+};
+
+/// What a window was like before a move or resize grab started.
+#[derive(Debug)]
+pub struct GrabRestore {
+    /// The serial the grab was set with.
+    serial: Serial,
+    window: WindowElement,
+    floating_or_tiled: FloatingOrTiled,
+    edge_tile_restore_geo: Option<Rectangle<i32, Logical>>,
+}
+
+impl GrabRestore {
+    /// Remember what `window` is like now, before a grab changes it.
+    ///
+    /// Call [`GrabRestore::set`] once the grab is set.
+    pub fn new(window: &WindowElement) -> Self {
+        let (floating_or_tiled, edge_tile_restore_geo) =
+            window.with_state(|state| (state.floating_or_tiled, state.edge_tile_restore_geo));
+
+        Self {
+            serial: Serial::from(0),
+            window: window.clone(),
+            floating_or_tiled,
+            edge_tile_restore_geo,
+        }
+    }
+
+    /// Make this the grab Escape cancels.
+    pub fn set(mut self, state: &mut State, serial: Serial) {
+        self.serial = serial;
+        state.grab_restore = Some(self);
+    }
+}
+
+impl State {
+    /// Whether the pointer is in a move or resize grab that Escape can cancel.
+    pub fn can_cancel_grab(&self) -> bool {
+        let Some(restore) = self.grab_restore.as_ref() else {
+            return false;
+        };
+
+        self.seat
+            .get_pointer()
+            .is_some_and(|pointer| pointer.has_grab(restore.serial))
+    }
+
+    /// End the current move or resize grab and put the window back where it was.
+    pub fn cancel_grab(&mut self) {
+        if !self.can_cancel_grab() {
+            return;
+        }
+        let Some(restore) = self.grab_restore.take() else {
+            return;
+        };
+        let Some(pointer) = self.seat.get_pointer() else {
+            return;
+        };
+
+        tracing::debug!("Cancelling grab");
+
+        pointer.unset_grab(
+            self,
+            SERIAL_COUNTER.next_serial(),
+            Duration::from(self.clock.now()).as_millis() as u32,
+        );
+        self.hide_grab_hint();
+
+        let window = restore.window;
+        if !window.alive() {
+            return;
+        }
+
+        window.with_state(|state| {
+            state.floating_or_tiled = restore.floating_or_tiled;
+            state.edge_tile_restore_geo = restore.edge_tile_restore_geo;
+        });
+
+        if let Some(surface) = window.wl_surface() {
+            surface.with_state(|state| state.resize_state = ResizeSurfaceState::Idle);
+        }
+        if let WindowElement::Wayland(win) = &window {
+            win.toplevel().with_pending_state(|state| {
+                state.states.unset(xdg_toplevel::State::Resizing);
+            });
+        }
+
+        // Laying the window out again sends it its old size and maps it where it was
+        if let Some(output) = window.output(self) {
+            self.update_windows(&output);
+            self.schedule_render(&output);
+        }
+
+        // The size may not have changed, but the window still needs to know it isn't resizing
+        if let WindowElement::Wayland(win) = &window {
+            win.toplevel().send_pending_configure();
+        }
+    }
+}
@@ -0,0 +1,187 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Maximizing and tiling floating windows by dragging them to an output's edge.
+//!
+//! While a floating window is moved, the pointer hitting the top edge of an output maximizes
+//! the window when it's dropped, and hitting the left or right edge makes it float over that
+//! half of the area layer surfaces' exclusive zones leave free. Edges shared with another
+//! output don't count, so the pointer can still cross between outputs.
+//!
+//! The window's floating geometry from before it was dragged there is kept. Unmaximizing goes
+//! back to it, and so does dragging a half tiled window away.
+
+use smithay::{
+    desktop::layer_map_for_output,
+    output::Output,
+    utils::{Logical, Point, Rectangle, Size},
+};
+
+use crate::{
+    state::{State, WithState},
+    window::{placement::MoveOrigin, window_state::FloatingOrTiled, WindowElement},
+};
+
+/// How close the pointer has to be to an output's edge to be on it, in logical pixels.
+const EDGE_ZONE_SIZE: f64 = 2.0;
+
+/// What a window dropped on an output's edge turns into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeTile {
+    Maximized,
+    LeftHalf,
+    RightHalf,
+}
+
+impl State {
+    /// Get the edge of an output `loc` is on and what a window dropped there turns into.
+    pub fn edge_tile_at(&self, loc: Point<f64, Logical>) -> Option<(Output, EdgeTile)> {
+        let output = self.space.output_under(loc).next()?.clone();
+        let output_geo = self.space.output_geometry(&output)?.to_f64();
+
+        // Only edges without another output past them count
+        let is_outer_edge =
+            |past_edge: Point<f64, Logical>| self.space.output_under(past_edge).next().is_none();
+
+        let left = output_geo.loc.x;
+        let right = output_geo.loc.x + output_geo.size.w;
+        let top = output_geo.loc.y;
+
+        let edge_tile = if loc.y < top + EDGE_ZONE_SIZE && is_outer_edge((loc.x, top - 1.0).into())
+        {
+            EdgeTile::Maximized
+        } else if loc.x < left + EDGE_ZONE_SIZE && is_outer_edge((left - 1.0, loc.y).into()) {
+            EdgeTile::LeftHalf
+        } else if loc.x >= right - EDGE_ZONE_SIZE && is_outer_edge((right + 1.0, loc.y).into()) {
+            EdgeTile::RightHalf
+        } else {
+            return None;
+        };
+
+        Some((output, edge_tile))
+    }
+
+    /// Maximize or half tile the floating `window` dropped on an edge of `output`.
+    ///
+    /// `restore_geo` is the floating geometry the window goes back to when it's unmaximized or
+    /// dragged away.
+    pub fn edge_tile_window(
+        &mut self,
+        window: &WindowElement,
+        output: &Output,
+        edge_tile: EdgeTile,
+        restore_geo: Rectangle<i32, Logical>,
+    ) {
+        let Some(area) = self.usable_area(output) else {
+            return;
+        };
+
+        tracing::debug!("Dropped window on an output edge, {edge_tile:?}");
+
+        match edge_tile {
+            EdgeTile::Maximized => {
+                window.with_state(|state| {
+                    state.floating_or_tiled = FloatingOrTiled::Floating(restore_geo);
+                    state.edge_tile_restore_geo = None;
+                });
+                window.set_maximized(true);
+            }
+            EdgeTile::LeftHalf | EdgeTile::RightHalf => {
+                let width = area.size.w / 2;
+                let x = match edge_tile {
+                    EdgeTile::LeftHalf => area.loc.x,
+                    _ => area.loc.x + area.size.w - width,
+                };
+                let geo = Rectangle::from_loc_and_size((x, area.loc.y), (width, area.size.h));
+
+                window.with_state(|state| {
+                    state.floating_or_tiled = FloatingOrTiled::Floating(geo);
+                    state.edge_tile_restore_geo = Some(restore_geo);
+                });
+            }
+        }
+
+        let old_output = window.output(self);
+        if old_output.as_ref() != Some(output) {
+            window.place_on_output(output);
+            self.window_moved(window, MoveOrigin::User);
+        }
+
+        for output in old_output.into_iter().chain([output.clone()]) {
+            self.update_windows(&output);
+            self.schedule_render(&output);
+        }
+    }
+
+    /// Give a half tiled `window` that's starting to be dragged its old size back.
+    ///
+    /// The window is resized around `pointer_loc` so the pointer stays on the same part of it.
+    /// Returns the window's new location, or `None` if it wasn't half tiled.
+    pub fn restore_edge_tiled_window(
+        &mut self,
+        window: &WindowElement,
+        pointer_loc: Point<f64, Logical>,
+    ) -> Option<Point<i32, Logical>> {
+        let restore_geo = window.with_state(|state| {
+            state
+                .floating_or_tiled
+                .is_floating()
+                .then(|| state.edge_tile_restore_geo.take())
+                .flatten()
+        })?;
+        let geo = self.space.element_geometry(window)?;
+
+        let scale = |offset: f64, old: i32, new: i32| offset * new as f64 / old.max(1) as f64;
+        let offset = pointer_loc - geo.loc.to_f64();
+        let new_offset = Point::from((
+            scale(offset.x, geo.size.w, restore_geo.size.w),
+            scale(offset.y, geo.size.h, restore_geo.size.h),
+        ));
+        let new_loc = (pointer_loc - new_offset).to_i32_round();
+
+        window.with_state(|state| {
+            state.floating_or_tiled =
+                FloatingOrTiled::Floating(Rectangle::from_loc_and_size(new_loc, restore_geo.size));
+        });
+        request_size(window, restore_geo.size, new_loc);
+
+        Some(new_loc)
+    }
+
+    /// Get the part of `output` that layer surfaces' exclusive zones leave free.
+    pub fn usable_area(&self, output: &Output) -> Option<Rectangle<i32, Logical>> {
+        let output_geo = self.space.output_geometry(output)?;
+
+        let map = layer_map_for_output(output);
+        if map.layers().next().is_none() {
+            // The zone is sometimes wrong when there are no layer surfaces, see `tile_windows`
+            return Some(output_geo);
+        }
+
+        let zone = map.non_exclusive_zone();
+        Some(Rectangle::from_loc_and_size(
+            output_geo.loc + zone.loc,
+            zone.size,
+        ))
+    }
+}
+
+/// Ask `window` to be `size` while it's being dragged.
+///
+/// Unlike [`WindowElement::change_geometry`], this doesn't map the window anywhere once it
+/// commits, since the move grab keeps mapping it under the pointer.
+fn request_size(window: &WindowElement, size: Size<i32, Logical>, loc: Point<i32, Logical>) {
+    match window {
+        WindowElement::Wayland(window) => {
+            window.toplevel().with_pending_state(|state| {
+                state.size = Some(size);
+            });
+            window.toplevel().send_pending_configure();
+        }
+        WindowElement::X11(surface) => {
+            if let Err(err) = surface.configure(Rectangle::from_loc_and_size(loc, size)) {
+                tracing::error!("Failed to configure X11 window: {err}");
+            }
+        }
+        WindowElement::X11OverrideRedirect(_) => (),
+    }
+}
@@ -0,0 +1,294 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Resizing tiled windows by dragging the split between the master window and the rest.
+//!
+//! Tiled windows can't be resized on their own, so dragging the master window's right edge, or
+//! the left edge of a window right next to it, changes the master factor of the output's focused
+//! tag instead. The layout runs again as the pointer moves. The factor is kept on the tag, so it
+//! stays when windows open or close. Other edges, and layouts without a master window, aren't
+//! resized.
+//!
+//! Resizing a tiled window through the API moves the same split.
+
+use smithay::{
+    input::{
+        pointer::{
+            AxisFrame, ButtonEvent, Focus, GrabStartData, MotionEvent, PointerGrab,
+            PointerInnerHandle, RelativeMotionEvent,
+        },
+        SeatHandler,
+    },
+    output::Output,
+    reexports::wayland_protocols::xdg::shell::server::xdg_toplevel,
+    utils::{Logical, Point, Serial},
+};
+
+use crate::{
+    grab::resize_grab::ResizeEdge,
+    state::{State, WithState},
+    tag::Tag,
+    window::WindowElement,
+};
+
+/// How far a window's left edge can be from the master window's right edge and still count
+/// as the split, since snapping to resize increments moves edges a bit.
+const SPLIT_TOLERANCE: i32 = 16;
+
+/// Data for dragging the split between the master window and the rest.
+pub struct LayoutResizeGrab {
+    start_data: GrabStartData<State>,
+    /// The tag whose master factor is changing
+    tag: Tag,
+    output: Output,
+    initial_master_factor: f64,
+    /// The width of the area tiled windows are laid out in
+    tiling_width: i32,
+    /// Which button initiated the grab
+    button_used: u32,
+}
+
+impl PointerGrab<State> for LayoutResizeGrab {
+    fn motion(
+        &mut self,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        _focus: Option<(<State as SeatHandler>::PointerFocus, Point<i32, Logical>)>,
+        event: &MotionEvent,
+    ) {
+        handle.motion(data, None, event);
+
+        let delta = event.location.x - self.start_data.location.x;
+        self.tag
+            .set_master_factor(self.initial_master_factor + delta / self.tiling_width as f64);
+
+        data.show_grab_hint(factor_hint_text(self.tag.master_factor()));
+        data.update_windows(&self.output);
+        data.schedule_render(&self.output);
+    }
+
+    fn relative_motion(
+        &mut self,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        focus: Option<(<State as SeatHandler>::PointerFocus, Point<i32, Logical>)>,
+        event: &RelativeMotionEvent,
+    ) {
+        handle.relative_motion(data, focus, event);
+    }
+
+    fn button(
+        &mut self,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &ButtonEvent,
+    ) {
+        handle.button(data, event);
+
+        if !handle.current_pressed().contains(&self.button_used) {
+            handle.unset_grab(data, event.serial, event.time);
+            data.hide_grab_hint();
+        }
+    }
+
+    fn axis(
+        &mut self,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        details: AxisFrame,
+    ) {
+        handle.axis(data, details);
+    }
+
+    fn frame(&mut self, data: &mut State, handle: &mut PointerInnerHandle<'_, State>) {
+        handle.frame(data);
+    }
+
+    fn start_data(&self) -> &GrabStartData<State> {
+        &self.start_data
+    }
+
+    fn gesture_swipe_begin(
+        &mut self,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &smithay::input::pointer::GestureSwipeBeginEvent,
+    ) {
+        handle.gesture_swipe_begin(data, event);
+    }
+
+    fn gesture_swipe_update(
+        &mut self,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &smithay::input::pointer::GestureSwipeUpdateEvent,
+    ) {
+        handle.gesture_swipe_update(data, event);
+    }
+
+    fn gesture_swipe_end(
+        &mut self,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &smithay::input::pointer::GestureSwipeEndEvent,
+    ) {
+        handle.gesture_swipe_end(data, event);
+    }
+
+    fn gesture_pinch_begin(
+        &mut self,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &smithay::input::pointer::GesturePinchBeginEvent,
+    ) {
+        handle.gesture_pinch_begin(data, event);
+    }
+
+    fn gesture_pinch_update(
+        &mut self,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &smithay::input::pointer::GesturePinchUpdateEvent,
+    ) {
+        handle.gesture_pinch_update(data, event);
+    }
+
+    fn gesture_pinch_end(
+        &mut self,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &smithay::input::pointer::GesturePinchEndEvent,
+    ) {
+        handle.gesture_pinch_end(data, event);
+    }
+
+    fn gesture_hold_begin(
+        &mut self,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &smithay::input::pointer::GestureHoldBeginEvent,
+    ) {
+        handle.gesture_hold_begin(data, event);
+    }
+
+    fn gesture_hold_end(
+        &mut self,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &smithay::input::pointer::GestureHoldEndEvent,
+    ) {
+        handle.gesture_hold_end(data, event);
+    }
+}
+
+/// Start dragging the split between the master window and the rest if the tiled `window` is
+/// being resized from an edge on it.
+///
+/// `start_data` must have the pointer's location when the resize started.
+pub fn layout_resize_request(
+    state: &mut State,
+    window: &WindowElement,
+    edges: ResizeEdge,
+    start_data: GrabStartData<State>,
+    serial: Serial,
+    button_used: u32,
+) {
+    let Some(pointer) = state.seat.get_pointer() else {
+        return;
+    };
+    let Some(output) = window.output(state) else {
+        return;
+    };
+    let Some((tag, master)) = master_window(state, &output) else {
+        return;
+    };
+    let Some(tiling_area) = state.tiling_area(&output) else {
+        return;
+    };
+    let (Some(master_geo), Some(window_geo)) = (
+        state.space.element_geometry(&master),
+        state.space.element_geometry(window),
+    ) else {
+        return;
+    };
+
+    let split_x = master_geo.loc.x + master_geo.size.w;
+    let on_split = if window == &master {
+        matches!(
+            edges.0,
+            xdg_toplevel::ResizeEdge::Right
+                | xdg_toplevel::ResizeEdge::TopRight
+                | xdg_toplevel::ResizeEdge::BottomRight
+        )
+    } else {
+        matches!(
+            edges.0,
+            xdg_toplevel::ResizeEdge::Left
+                | xdg_toplevel::ResizeEdge::TopLeft
+                | xdg_toplevel::ResizeEdge::BottomLeft
+        ) && (window_geo.loc.x - split_x).abs() <= SPLIT_TOLERANCE
+    };
+    if !on_split {
+        return;
+    }
+
+    tracing::debug!("Starting layout resize of tag {}", tag.name());
+
+    let initial_master_factor = tag.master_factor();
+    let grab = LayoutResizeGrab {
+        start_data,
+        tag,
+        output,
+        initial_master_factor,
+        tiling_width: tiling_area.size.w.max(1),
+        button_used,
+    };
+
+    pointer.set_grab(state, grab, serial, Focus::Clear);
+    state.show_grab_hint(factor_hint_text(initial_master_factor));
+}
+
+/// Move the split between the master window and the rest by `dx` logical pixels, growing the
+/// tiled `window` if it's the master and shrinking the master if it isn't.
+pub fn resize_split_by(state: &mut State, window: &WindowElement, dx: i32) {
+    let Some(output) = window.output(state) else {
+        return;
+    };
+    let Some((tag, master)) = master_window(state, &output) else {
+        return;
+    };
+    let Some(tiling_area) = state.tiling_area(&output) else {
+        return;
+    };
+
+    let dx = if window == &master { dx } else { -dx };
+    tag.set_master_factor(tag.master_factor() + dx as f64 / tiling_area.size.w.max(1) as f64);
+
+    state.update_windows(&output);
+    state.schedule_render(&output);
+}
+
+/// Get the first focused tag on `output` and its master window, if its layout has one.
+fn master_window(state: &State, output: &Output) -> Option<(Tag, WindowElement)> {
+    let focused_tags = output.with_state(|state| state.focused_tags().cloned().collect::<Vec<_>>());
+    let tag = focused_tags.first().cloned()?;
+    if !tag.layout().has_master() {
+        return None;
+    }
+
+    // The master window is the first tiled one, like in `update_windows`
+    let master = state.windows.iter().find(|win| {
+        !win.is_x11_override_redirect()
+            && win.with_state(|win_state| {
+                win_state.tags.iter().any(|tg| focused_tags.contains(tg))
+                    && win_state.floating_or_tiled.is_tiled()
+                    && win_state.fullscreen_or_maximized.is_neither()
+            })
+    })?;
+
+    Some((tag, master.clone()))
+}
+
+/// The text the grab hint shows for a master factor.
+fn factor_hint_text(master_factor: f64) -> String {
+    format!("{:.0}%", master_factor * 100.0)
+}
@@ -1,7 +1,6 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use smithay::{
-    desktop::space::SpaceElement,
     // NOTE: maybe alias this to PointerGrabStartData because there's another GrabStartData in
     // |     input::keyboard
     input::{
@@ -12,16 +11,17 @@ use smithay::{
         pointer::{Focus, PointerGrab},
         Seat, SeatHandler,
     },
+    output::Output,
     reexports::wayland_server::protocol::wl_surface::WlSurface,
-    utils::{IsAlive, Logical, Point, Rectangle},
+    utils::{IsAlive, Logical, Point, Rectangle, Size},
 };
 
 use crate::{
+    api::msg::{ModifierMask, MoveOverrideDrop},
+    focus::FocusTarget,
+    grab::{cancel::GrabRestore, edge_tile::EdgeTile},
     state::{State, WithState},
-    window::{
-        window_state::{FloatingOrTiled, LocationRequestState},
-        WindowElement,
-    },
+    window::{placement::MoveOrigin, window_state::FloatingOrTiled, WindowElement},
 };
 
 /// Data for moving a window.
@@ -32,6 +32,207 @@ pub struct MoveSurfaceGrab {
     pub initial_window_loc: Point<i32, Logical>,
     /// Which button initiated the grab
     pub button_used: u32,
+    /// Set if the window is tiled but floating for this grab because of the move override
+    pub overridden_tile: Option<OverriddenTile>,
+    /// The window's floating geometry when the grab started, if it can be maximized or half
+    /// tiled by dropping it on an output's edge
+    pub edge_tile_restore_geo: Option<Rectangle<i32, Logical>>,
+    /// The output edge the pointer is on and what dropping the window there does
+    pub edge_tile: Option<(Output, EdgeTile)>,
+    /// The size a half tiled window is going back to, since it won't have it until it commits
+    pub restored_size: Option<Size<i32, Logical>>,
+    /// What happens to a tiled window if it's dropped where the pointer is now
+    pub tile_drop: Option<TileDrop>,
+}
+
+/// Where a tiled window being dragged can be dropped.
+#[derive(Debug)]
+pub enum TileDrop {
+    /// Swap places in the layout with this tiled window.
+    Swap(WindowElement),
+    /// Move to this output's focused tags.
+    Output(Output),
+}
+
+/// Modifiers that, when held as a move grab starts, let a tiled window be dragged freely.
+#[derive(Debug, Clone, Copy)]
+pub struct MoveOverride {
+    pub modifiers: ModifierMask,
+    pub on_drop: MoveOverrideDrop,
+}
+
+/// What a tiled window was like before the move override made it float.
+#[derive(Debug)]
+pub struct OverriddenTile {
+    floating_or_tiled: FloatingOrTiled,
+    output: Option<Output>,
+    on_drop: MoveOverrideDrop,
+}
+
+impl MoveSurfaceGrab {
+    /// Put a window dragged with the move override back in its tile,
+    /// or leave it floating if it was dropped on another output and the config wants that.
+    fn end_move_override(&mut self, state: &mut State) {
+        let Some(overridden) = self.overridden_tile.take() else {
+            return;
+        };
+
+        let mut outputs = Vec::from_iter(overridden.output.clone());
+
+        if self.window.alive() {
+            let drop_output = state
+                .primary_output_for_window(&self.window)
+                .filter(|output| Some(output) != overridden.output.as_ref());
+
+            match (overridden.on_drop, drop_output) {
+                (MoveOverrideDrop::FloatOnOtherOutput, Some(drop_output)) => {
+                    tracing::debug!("Leaving window floating on {}", drop_output.name());
+                    self.window.place_on_output(&drop_output);
+                    state.window_moved(&self.window, MoveOrigin::User);
+                    outputs.push(drop_output);
+                }
+                _ => {
+                    self.window.with_state(|state| {
+                        state.floating_or_tiled = overridden.floating_or_tiled;
+                    });
+                }
+            }
+        }
+
+        for output in outputs {
+            state.update_windows(&output);
+            state.schedule_render(&output);
+        }
+    }
+
+    /// Get what dropping the tiled window at `loc` does.
+    fn tile_drop_at(&self, state: &State, loc: Point<f64, Logical>) -> Option<TileDrop> {
+        let output = state.space.output_under(loc).next()?.clone();
+        if self.window.output(state).as_ref() != Some(&output) {
+            return Some(TileDrop::Output(output));
+        }
+
+        let (FocusTarget::Window(window_under), _) =
+            state.surface_under_except(loc, Some(&self.window))?
+        else {
+            return None;
+        };
+
+        let is_tiled = window_under.with_state(|state| {
+            state.floating_or_tiled.is_tiled() && state.fullscreen_or_maximized.is_neither()
+        });
+
+        is_tiled.then_some(TileDrop::Swap(window_under))
+    }
+
+    /// Swap the tiled window with the one it was dropped on, or move it to the output it was
+    /// dropped on.
+    fn end_tile_drop(&mut self, state: &mut State) {
+        let Some(tile_drop) = self.tile_drop.take() else {
+            return;
+        };
+
+        let is_tiled = self
+            .window
+            .with_state(|state| state.floating_or_tiled.is_tiled());
+        if !self.window.alive() || !is_tiled {
+            return;
+        }
+
+        match tile_drop {
+            TileDrop::Swap(window) => {
+                if !window.alive() {
+                    return;
+                }
+
+                tracing::debug!("Swapping window positions");
+                state.swap_window_positions(&self.window, &window);
+                if let Some(output) = self.window.output(state) {
+                    state.schedule_render(&output);
+                }
+            }
+            TileDrop::Output(output) => {
+                tracing::debug!("Moving window to {}", output.name());
+                let old_output = self.window.output(state);
+                self.window.place_on_output(&output);
+                state.window_moved(&self.window, MoveOrigin::User);
+
+                for output in old_output.into_iter().chain([output]) {
+                    state.update_windows(&output);
+                    state.schedule_render(&output);
+                }
+            }
+        }
+    }
+
+    /// Maximize or half tile the window if it was dropped on an output's edge.
+    fn end_edge_tile(&mut self, state: &mut State) {
+        let (Some((output, edge_tile)), Some(restore_geo)) =
+            (self.edge_tile.take(), self.edge_tile_restore_geo)
+        else {
+            return;
+        };
+
+        let is_floating = self
+            .window
+            .with_state(|state| state.floating_or_tiled.is_floating());
+        if self.window.alive() && is_floating {
+            state.edge_tile_window(&self.window, &output, edge_tile, restore_geo);
+        }
+    }
+}
+
+/// Get ready to maximize or half tile a floating `window` that's about to be dragged.
+///
+/// A half tiled window gets its old size back around `pointer_loc`. Returns its new location
+/// if that happened, along with the geometry it goes back to if it's dropped on an edge again.
+fn start_edge_tile(
+    state: &mut State,
+    window: &WindowElement,
+    pointer_loc: Point<f64, Logical>,
+) -> Option<(Option<Point<i32, Logical>>, Rectangle<i32, Logical>)> {
+    let is_neither = window.with_state(|state| state.fullscreen_or_maximized.is_neither());
+    if !is_neither {
+        return None;
+    }
+
+    let restored_loc = state.restore_edge_tiled_window(window, pointer_loc);
+
+    window.with_state(|state| match state.floating_or_tiled {
+        FloatingOrTiled::Floating(geo) => Some((restored_loc, geo)),
+        FloatingOrTiled::Tiled(_) => None,
+    })
+}
+
+/// Float `window` for a move grab if it's tiled and the move override's modifiers are held.
+///
+/// The window's tile is left empty until the grab ends so it can go back there.
+fn start_move_override(state: &mut State, window: &WindowElement) -> Option<OverriddenTile> {
+    let move_override = state.move_override?;
+
+    let keyboard = state.seat.get_keyboard()?;
+    if ModifierMask::from(keyboard.modifier_state()) != move_override.modifiers {
+        return None;
+    }
+
+    let (floating_or_tiled, is_neither) = window.with_state(|state| {
+        (
+            state.floating_or_tiled,
+            state.fullscreen_or_maximized.is_neither(),
+        )
+    });
+    if !floating_or_tiled.is_tiled() || !is_neither {
+        return None;
+    }
+
+    let geo = state.space.element_geometry(window)?;
+    window.with_state(|state| state.floating_or_tiled = FloatingOrTiled::Floating(geo));
+
+    Some(OverriddenTile {
+        floating_or_tiled,
+        output: window.output(state),
+        on_drop: move_override.on_drop,
+    })
 }
 
 impl PointerGrab<State> for MoveSurfaceGrab {
@@ -50,87 +251,40 @@ impl PointerGrab<State> for MoveSurfaceGrab {
 
         if !self.window.alive() {
             handle.unset_grab(state, event.serial, event.time);
+            state.hide_grab_hint();
+            state.grab_restore = None;
+            self.end_move_override(state);
             return;
         }
 
-        state.space.raise_element(&self.window, false);
-        if let WindowElement::X11(surface) = &self.window {
-            state
-                .xwm
-                .as_mut()
-                .expect("no xwm")
-                .raise_window(surface)
-                .expect("failed to raise x11 win");
-        }
+        state.raise_window(&self.window);
 
         let is_tiled = self
             .window
             .with_state(|state| state.floating_or_tiled.is_tiled());
 
         if is_tiled {
-            // INFO: this is being used instead of space.element_under(event.location) because that
-            // |     uses the bounding box, which is different from the actual geometry
-            let window_under = state
-                .space
-                .elements()
-                .rev()
-                .find(|&win| {
-                    if let Some(loc) = state.space.element_location(win) {
-                        let size = win.geometry().size;
-                        let rect = Rectangle { size, loc };
-                        rect.contains(event.location.to_i32_round())
-                    } else {
-                        false
-                    }
-                })
-                .cloned();
-
-            if let Some(window_under) = window_under {
-                if window_under == self.window {
-                    return;
-                }
-
-                if state
+            // Tiled windows stay in their tile and are swapped or moved when they're dropped
+            self.tile_drop = self.tile_drop_at(state, event.location);
+        } else {
+            let size = self.restored_size.unwrap_or_else(|| {
+                state
                     .space
                     .element_geometry(&self.window)
-                    .is_some_and(|geo| {
-                        state
-                            .space
-                            .element_geometry(&window_under)
-                            .is_some_and(|geo2| geo.overlaps(geo2))
-                    })
-                {
-                    return;
-                }
-
-                let is_floating =
-                    window_under.with_state(|state| state.floating_or_tiled.is_floating());
-
-                if is_floating {
-                    return;
-                }
-
-                let has_pending_resize = window_under.with_state(|state| {
-                    !matches!(state.loc_request_state, LocationRequestState::Idle)
-                });
-
-                if has_pending_resize {
-                    return;
-                }
+                    .expect("window wasn't mapped")
+                    .size
+            });
 
-                tracing::debug!("Swapping window positions");
-                state.swap_window_positions(&self.window, &window_under);
-            }
-        } else {
             let delta = event.location - self.start_data.location;
             let new_loc = (self.initial_window_loc.to_f64() + delta).to_i32_round();
-            state.space.map_element(self.window.clone(), new_loc, true);
+            let new_loc = state.snap_window_loc(&self.window, new_loc, size);
+            state.space.map_element(self.window.clone(), new_loc, false);
+            state.show_grab_hint(loc_hint_text(new_loc));
+            state.update_window_outputs([&self.window]);
 
-            let size = state
-                .space
-                .element_geometry(&self.window)
-                .expect("window wasn't mapped")
-                .size;
+            if self.edge_tile_restore_geo.is_some() {
+                self.edge_tile = state.edge_tile_at(event.location);
+            }
 
             self.window.with_state(|state| {
                 if state.floating_or_tiled.is_floating() {
@@ -140,8 +294,7 @@ impl PointerGrab<State> for MoveSurfaceGrab {
             });
 
             if let WindowElement::X11(surface) = &self.window {
-                let geo = surface.geometry();
-                let new_geo = Rectangle::from_loc_and_size(new_loc, geo.size);
+                let new_geo = Rectangle::from_loc_and_size(new_loc, size);
                 surface
                     .configure(new_geo)
                     .expect("failed to configure x11 win");
@@ -169,6 +322,11 @@ impl PointerGrab<State> for MoveSurfaceGrab {
 
         if !handle.current_pressed().contains(&self.button_used) {
             handle.unset_grab(data, event.serial, event.time);
+            data.hide_grab_hint();
+            data.grab_restore = None;
+            self.end_move_override(data);
+            self.end_edge_tile(data);
+            self.end_tile_drop(data);
         }
     }
 
@@ -187,74 +345,74 @@ impl PointerGrab<State> for MoveSurfaceGrab {
 
     fn gesture_swipe_begin(
         &mut self,
-        _data: &mut State,
-        _handle: &mut PointerInnerHandle<'_, State>,
-        _event: &smithay::input::pointer::GestureSwipeBeginEvent,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &smithay::input::pointer::GestureSwipeBeginEvent,
     ) {
-        todo!()
+        handle.gesture_swipe_begin(data, event);
     }
 
     fn gesture_swipe_update(
         &mut self,
-        _data: &mut State,
-        _handle: &mut PointerInnerHandle<'_, State>,
-        _event: &smithay::input::pointer::GestureSwipeUpdateEvent,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &smithay::input::pointer::GestureSwipeUpdateEvent,
     ) {
-        todo!()
+        handle.gesture_swipe_update(data, event);
     }
 
     fn gesture_swipe_end(
         &mut self,
-        _data: &mut State,
-        _handle: &mut PointerInnerHandle<'_, State>,
-        _event: &smithay::input::pointer::GestureSwipeEndEvent,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &smithay::input::pointer::GestureSwipeEndEvent,
     ) {
-        todo!()
+        handle.gesture_swipe_end(data, event);
     }
 
     fn gesture_pinch_begin(
         &mut self,
-        _data: &mut State,
-        _handle: &mut PointerInnerHandle<'_, State>,
-        _event: &smithay::input::pointer::GesturePinchBeginEvent,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &smithay::input::pointer::GesturePinchBeginEvent,
     ) {
-        todo!()
+        handle.gesture_pinch_begin(data, event);
     }
 
     fn gesture_pinch_update(
         &mut self,
-        _data: &mut State,
-        _handle: &mut PointerInnerHandle<'_, State>,
-        _event: &smithay::input::pointer::GesturePinchUpdateEvent,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &smithay::input::pointer::GesturePinchUpdateEvent,
     ) {
-        todo!()
+        handle.gesture_pinch_update(data, event);
     }
 
     fn gesture_pinch_end(
         &mut self,
-        _data: &mut State,
-        _handle: &mut PointerInnerHandle<'_, State>,
-        _event: &smithay::input::pointer::GesturePinchEndEvent,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &smithay::input::pointer::GesturePinchEndEvent,
     ) {
-        todo!()
+        handle.gesture_pinch_end(data, event);
     }
 
     fn gesture_hold_begin(
         &mut self,
-        _data: &mut State,
-        _handle: &mut PointerInnerHandle<'_, State>,
-        _event: &smithay::input::pointer::GestureHoldBeginEvent,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &smithay::input::pointer::GestureHoldBeginEvent,
     ) {
-        todo!()
+        handle.gesture_hold_begin(data, event);
     }
 
     fn gesture_hold_end(
         &mut self,
-        _data: &mut State,
-        _handle: &mut PointerInnerHandle<'_, State>,
-        _event: &smithay::input::pointer::GestureHoldEndEvent,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &smithay::input::pointer::GestureHoldEndEvent,
     ) {
-        todo!()
+        handle.gesture_hold_end(data, event);
     }
 }
 
@@ -273,19 +431,47 @@ pub fn move_request_client(
             return;
         };
 
-        let initial_window_loc = state
+        let mut initial_window_loc = state
             .space
             .element_location(&window)
             .expect("move request was called on an unmapped window");
 
+        let restore = GrabRestore::new(&window);
+        let overridden_tile = start_move_override(state, &window);
+        let is_floating = window.with_state(|state| state.floating_or_tiled.is_floating());
+
+        let mut edge_tile_restore_geo = None;
+        let mut restored_size = None;
+        if overridden_tile.is_none() {
+            if let Some((restored_loc, geo)) = start_edge_tile(state, &window, start_data.location)
+            {
+                if let Some(restored_loc) = restored_loc {
+                    initial_window_loc = restored_loc;
+                    restored_size = Some(geo.size);
+                }
+                edge_tile_restore_geo = Some(geo);
+            }
+        }
+
         let grab = MoveSurfaceGrab {
             start_data,
             window,
             initial_window_loc,
             button_used,
+            overridden_tile,
+            edge_tile_restore_geo,
+            edge_tile: None,
+            restored_size,
+            tile_drop: None,
         };
 
         pointer.set_grab(state, grab, serial, Focus::Clear);
+        restore.set(state, serial);
+
+        // Tiled windows swap places instead of moving freely, so they don't get a hint
+        if is_floating {
+            state.show_grab_hint(loc_hint_text(initial_window_loc));
+        }
     } else {
         tracing::warn!("no grab start data");
     }
@@ -305,7 +491,7 @@ pub fn move_request_server(
         return;
     };
 
-    let initial_window_loc = state
+    let mut initial_window_loc = state
         .space
         .element_location(&window)
         .expect("move request was called on an unmapped window");
@@ -318,12 +504,43 @@ pub fn move_request_server(
         location: pointer.current_location(),
     };
 
+    let restore = GrabRestore::new(&window);
+    let overridden_tile = start_move_override(state, &window);
+    let is_floating = window.with_state(|state| state.floating_or_tiled.is_floating());
+
+    let mut edge_tile_restore_geo = None;
+    let mut restored_size = None;
+    if overridden_tile.is_none() {
+        if let Some((restored_loc, geo)) = start_edge_tile(state, &window, start_data.location) {
+            if let Some(restored_loc) = restored_loc {
+                initial_window_loc = restored_loc;
+                restored_size = Some(geo.size);
+            }
+            edge_tile_restore_geo = Some(geo);
+        }
+    }
+
     let grab = MoveSurfaceGrab {
         start_data,
         window,
         initial_window_loc,
         button_used,
+        overridden_tile,
+        edge_tile_restore_geo,
+        edge_tile: None,
+        restored_size,
+        tile_drop: None,
     };
 
     pointer.set_grab(state, grab, serial, Focus::Clear);
+    restore.set(state, serial);
+
+    if is_floating {
+        state.show_grab_hint(loc_hint_text(initial_window_loc));
+    }
+}
+
+/// The text the grab hint shows for a window at `loc`.
+fn loc_hint_text(loc: Point<i32, Logical>) -> String {
+    format!("{}, {}", loc.x, loc.y)
 }
@@ -0,0 +1,168 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Dismissing grabbing popups, like menus, when the user clicks outside them.
+//!
+//! Smithay's popup grabs send keyboard input to the topmost popup and only dismiss the popups
+//! when a click lands on another client. Pinnacle's pointer focus is the window or layer
+//! surface under the pointer rather than the exact surface, so clicking the popups' own window
+//! outside them used to leave them open. Pinnacle now dismisses the whole chain on any press
+//! outside it, and neither the press nor its release reaches the window underneath.
+//!
+//! Keybinds and mousebinds still trigger while a popup has a grab.
+
+use std::time::Duration;
+
+use smithay::{
+    backend::input::ButtonState,
+    desktop::{
+        layer_map_for_output, space::SpaceElement, PopupGrab, PopupUngrabStrategy,
+        WindowSurfaceType,
+    },
+    utils::{IsAlive, Logical, Point, SERIAL_COUNTER},
+};
+
+use crate::{focus::FocusTarget, state::State, window::WindowElement};
+
+#[derive(Default)]
+pub struct PopupGrabState {
+    grab: Option<ActivePopupGrab>,
+    /// The button whose press dismissed popups, so its release isn't sent either.
+    dismissing_button: Option<u32>,
+}
+
+struct ActivePopupGrab {
+    grab: PopupGrab<State>,
+    /// The window or layer surface the popups belong to.
+    root: FocusTarget,
+}
+
+impl State {
+    /// Keep track of a popup grab that just started, replacing any earlier one.
+    pub fn start_popup_grab(&mut self, grab: PopupGrab<State>, root: FocusTarget) {
+        self.popup_grab.grab = Some(ActivePopupGrab { grab, root });
+    }
+
+    /// Dismiss the popups if `button` was pressed at `loc` outside a popup grab.
+    ///
+    /// Returns whether the button event should be swallowed. That's the case for the press
+    /// that dismissed the popups and for its release.
+    pub fn popup_grab_button(
+        &mut self,
+        button: u32,
+        button_state: ButtonState,
+        loc: Point<f64, Logical>,
+    ) -> bool {
+        if button_state == ButtonState::Released {
+            if self.popup_grab.dismissing_button == Some(button) {
+                self.popup_grab.dismissing_button = None;
+                return true;
+            }
+            return false;
+        }
+
+        let Some(active) = self.popup_grab.grab.as_ref() else {
+            return false;
+        };
+
+        if active.grab.has_ended() {
+            self.popup_grab.grab = None;
+            return false;
+        }
+
+        if self.is_over_popups(&active.root, loc) {
+            return false;
+        }
+
+        tracing::debug!("Dismissing popups after a click outside them");
+        self.dismiss_popup_grab();
+        self.popup_grab.dismissing_button = Some(button);
+
+        true
+    }
+
+    /// Check on the popup grab after a popup is destroyed.
+    ///
+    /// Keyboard focus moves to the popup that has the grab now, or back to the popups' window
+    /// or layer surface once none are left.
+    pub fn update_popup_grab(&mut self) {
+        let Some(active) = self.popup_grab.grab.as_ref() else {
+            return;
+        };
+        let Some(keyboard) = self.seat.get_keyboard() else {
+            return;
+        };
+
+        let focus = if active.grab.has_ended() {
+            let root = active.root.clone();
+            self.popup_grab.grab = None;
+            root.alive().then_some(root)
+        } else {
+            active.grab.current_grab()
+        };
+
+        let focus_is_dead = keyboard
+            .current_focus()
+            .map_or(true, |current| !current.alive());
+
+        if focus_is_dead {
+            keyboard.set_focus(self, focus, SERIAL_COUNTER.next_serial());
+        }
+    }
+
+    /// End the popup grab, dismissing every popup in it, and give keyboard focus back to the
+    /// popups' window or layer surface.
+    fn dismiss_popup_grab(&mut self) {
+        let Some(mut active) = self.popup_grab.grab.take() else {
+            return;
+        };
+
+        let focus = active.grab.ungrab(PopupUngrabStrategy::All);
+        let serial = SERIAL_COUNTER.next_serial();
+
+        if let Some(pointer) = self.seat.get_pointer() {
+            if pointer.is_grabbed() {
+                pointer.unset_grab(
+                    self,
+                    serial,
+                    Duration::from(self.clock.now()).as_millis() as u32,
+                );
+            }
+        }
+
+        // The keyboard grab ends itself when it sees the popups are gone
+        if let Some(keyboard) = self.seat.get_keyboard() {
+            keyboard.set_focus(self, focus.or(Some(active.root)), serial);
+        }
+    }
+
+    /// Returns whether `loc` is over one of the popups of `root`.
+    fn is_over_popups(&self, root: &FocusTarget, loc: Point<f64, Logical>) -> bool {
+        // Popups and their subsurfaces, but not the root's own surfaces
+        let surface_type = WindowSurfaceType::POPUP | WindowSurfaceType::SUBSURFACE;
+
+        match root {
+            FocusTarget::Window(window @ WindowElement::Wayland(win)) => {
+                let Some(window_loc) = self.space.element_location(window) else {
+                    return false;
+                };
+                let surface_loc = window_loc - window.geometry().loc;
+                win.surface_under(loc - surface_loc.to_f64(), surface_type)
+                    .is_some()
+            }
+            FocusTarget::LayerSurface(layer) => self.space.outputs().any(|output| {
+                let map = layer_map_for_output(output);
+                let (Some(layer_geo), Some(output_geo)) = (
+                    map.layer_geometry(layer),
+                    self.space.output_geometry(output),
+                ) else {
+                    return false;
+                };
+                let surface_loc = output_geo.loc + layer_geo.loc;
+                layer
+                    .surface_under(loc - surface_loc.to_f64(), surface_type)
+                    .is_some()
+            }),
+            _ => false,
+        }
+    }
+}
@@ -11,11 +11,11 @@ use smithay::{
         wayland_server::protocol::wl_surface::WlSurface,
     },
     utils::{IsAlive, Logical, Point, Rectangle, Size},
-    wayland::{compositor, shell::xdg::SurfaceCachedState},
     xwayland,
 };
 
 use crate::{
+    grab::cancel::GrabRestore,
     state::{State, WithState},
     window::{window_state::FloatingOrTiled, WindowElement},
 };
@@ -44,6 +44,34 @@ impl From<xdg_toplevel::ResizeEdge> for ResizeEdge {
     }
 }
 
+impl ResizeEdge {
+    /// Whether resizing from these edges changes the window's width.
+    fn changes_width(&self) -> bool {
+        matches!(
+            self.0,
+            xdg_toplevel::ResizeEdge::Left
+                | xdg_toplevel::ResizeEdge::Right
+                | xdg_toplevel::ResizeEdge::TopLeft
+                | xdg_toplevel::ResizeEdge::TopRight
+                | xdg_toplevel::ResizeEdge::BottomLeft
+                | xdg_toplevel::ResizeEdge::BottomRight
+        )
+    }
+
+    /// Whether resizing from these edges changes the window's height.
+    fn changes_height(&self) -> bool {
+        matches!(
+            self.0,
+            xdg_toplevel::ResizeEdge::Top
+                | xdg_toplevel::ResizeEdge::Bottom
+                | xdg_toplevel::ResizeEdge::TopLeft
+                | xdg_toplevel::ResizeEdge::TopRight
+                | xdg_toplevel::ResizeEdge::BottomLeft
+                | xdg_toplevel::ResizeEdge::BottomRight
+        )
+    }
+}
+
 pub struct ResizeSurfaceGrab {
     start_data: GrabStartData<State>,
     window: WindowElement,
@@ -68,9 +96,13 @@ impl ResizeSurfaceGrab {
             state.resize_state = ResizeSurfaceState::Resizing {
                 edges,
                 initial_window_rect,
+                centered: false,
             };
         });
 
+        // A half tiled window that's resized keeps its new size when it's dragged away
+        window.with_state(|state| state.edge_tile_restore_geo = None);
+
         Some(Self {
             start_data,
             window,
@@ -98,10 +130,25 @@ impl PointerGrab<State> for ResizeSurfaceGrab {
 
         if !self.window.alive() {
             handle.unset_grab(data, event.serial, event.time);
+            data.hide_grab_hint();
+            data.grab_restore = None;
             return;
         }
 
-        let delta = (event.location - self.start_data.location).to_i32_round::<i32>();
+        // Holding Shift keeps the window's aspect ratio, and holding Ctrl resizes it
+        // around its center
+        let modifiers = data
+            .seat
+            .get_keyboard()
+            .map(|keyboard| keyboard.modifier_state());
+        let keep_aspect_ratio = modifiers.is_some_and(|modifiers| modifiers.shift);
+        let centered = modifiers.is_some_and(|modifiers| modifiers.ctrl);
+
+        let mut delta = (event.location - self.start_data.location).to_i32_round::<i32>();
+        if centered {
+            // Both sides move, so the size changes twice as much
+            delta = Point::from((delta.x * 2, delta.y * 2));
+        }
 
         let mut new_window_width = self.initial_window_rect.size.w;
         let mut new_window_height = self.initial_window_rect.size.h;
@@ -131,32 +178,45 @@ impl PointerGrab<State> for ResizeSurfaceGrab {
             new_window_height = self.initial_window_rect.size.h + delta.y;
         }
 
-        let (min_size, max_size) = match self.window.wl_surface() {
-            Some(wl_surface) => compositor::with_states(&wl_surface, |states| {
-                let data = states.cached_state.current::<SurfaceCachedState>();
-                (data.min_size, data.max_size)
-            }),
-            None => ((0, 0).into(), (0, 0).into()),
-        };
+        let initial_size = self.initial_window_rect.size;
+        if keep_aspect_ratio && initial_size.w > 0 && initial_size.h > 0 {
+            let ratio = initial_size.w as f64 / initial_size.h as f64;
+            let width_scale = new_window_width as f64 / initial_size.w as f64;
+            let height_scale = new_window_height as f64 / initial_size.h as f64;
+
+            // Corners follow whichever side the pointer moved further
+            let follow_width = match (self.edges.changes_width(), self.edges.changes_height()) {
+                (true, false) => true,
+                (false, true) => false,
+                _ => width_scale >= height_scale,
+            };
 
-        // HACK: Here I set the min height to be self.window.geometry().loc.y.abs() because if it's
-        // |     lower then the compositor crashes trying to create a size with height -1 if you make the
-        // |     window height too small.
-        // |     However I don't know if the loc.y from window.geometry will always be the negative
-        // |     of the csd height.
-        let min_width = i32::max(1, min_size.w);
-        let min_height = i32::max(
-            i32::max(0, self.window.geometry().loc.y.abs()) + 1,
-            min_size.h,
-        );
+            if follow_width {
+                new_window_height = (new_window_width as f64 / ratio).round() as i32;
+            } else {
+                new_window_width = (new_window_height as f64 * ratio).round() as i32;
+            }
+        }
+
+        if let Some(surface) = self.window.wl_surface() {
+            surface.with_state(|state| {
+                if let ResizeSurfaceState::Resizing {
+                    centered: resize_centered,
+                    ..
+                } = &mut state.resize_state
+                {
+                    *resize_centered = centered;
+                }
+            });
+        }
 
-        let max_width = if max_size.w != 0 { max_size.w } else { i32::MAX };
-        let max_height = if max_size.h != 0 { max_size.h } else { i32::MAX };
+        let new_window_size = self
+            .window
+            .clamp_size(Size::from((new_window_width, new_window_height)));
 
-        self.last_window_size = Size::from((
-            new_window_width.clamp(min_width, max_width),
-            new_window_height.clamp(min_height, max_height),
-        ));
+        // Snap to the window's resize increments (e.g. terminal cells) if it has any.
+        self.last_window_size = self.window.snap_size_to_increments(new_window_size);
+        data.show_grab_hint(size_hint_text(self.last_window_size));
 
         match &self.window {
             WindowElement::Wayland(window) => {
@@ -202,6 +262,8 @@ impl PointerGrab<State> for ResizeSurfaceGrab {
 
         if !handle.current_pressed().contains(&self.button_used) {
             handle.unset_grab(data, event.serial, event.time);
+            data.hide_grab_hint();
+            data.grab_restore = None;
 
             if !self.window.alive() {
                 return;
@@ -222,6 +284,7 @@ impl PointerGrab<State> for ResizeSurfaceGrab {
                         state.resize_state = ResizeSurfaceState::WaitingForLastCommit {
                             edges: self.edges,
                             initial_window_rect: self.initial_window_rect,
+                            centered: state.resize_state.is_centered(),
                         };
                     });
                 }
@@ -231,6 +294,7 @@ impl PointerGrab<State> for ResizeSurfaceGrab {
                         state.resize_state = ResizeSurfaceState::WaitingForLastCommit {
                             edges: self.edges,
                             initial_window_rect: self.initial_window_rect,
+                            centered: state.resize_state.is_centered(),
                         };
                     });
                 }
@@ -254,74 +318,74 @@ impl PointerGrab<State> for ResizeSurfaceGrab {
 
     fn gesture_swipe_begin(
         &mut self,
-        _data: &mut State,
-        _handle: &mut PointerInnerHandle<'_, State>,
-        _event: &smithay::input::pointer::GestureSwipeBeginEvent,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &smithay::input::pointer::GestureSwipeBeginEvent,
     ) {
-        todo!()
+        handle.gesture_swipe_begin(data, event);
     }
 
     fn gesture_swipe_update(
         &mut self,
-        _data: &mut State,
-        _handle: &mut PointerInnerHandle<'_, State>,
-        _event: &smithay::input::pointer::GestureSwipeUpdateEvent,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &smithay::input::pointer::GestureSwipeUpdateEvent,
     ) {
-        todo!()
+        handle.gesture_swipe_update(data, event);
     }
 
     fn gesture_swipe_end(
         &mut self,
-        _data: &mut State,
-        _handle: &mut PointerInnerHandle<'_, State>,
-        _event: &smithay::input::pointer::GestureSwipeEndEvent,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &smithay::input::pointer::GestureSwipeEndEvent,
     ) {
-        todo!()
+        handle.gesture_swipe_end(data, event);
     }
 
     fn gesture_pinch_begin(
         &mut self,
-        _data: &mut State,
-        _handle: &mut PointerInnerHandle<'_, State>,
-        _event: &smithay::input::pointer::GesturePinchBeginEvent,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &smithay::input::pointer::GesturePinchBeginEvent,
     ) {
-        todo!()
+        handle.gesture_pinch_begin(data, event);
     }
 
     fn gesture_pinch_update(
         &mut self,
-        _data: &mut State,
-        _handle: &mut PointerInnerHandle<'_, State>,
-        _event: &smithay::input::pointer::GesturePinchUpdateEvent,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &smithay::input::pointer::GesturePinchUpdateEvent,
     ) {
-        todo!()
+        handle.gesture_pinch_update(data, event);
     }
 
     fn gesture_pinch_end(
         &mut self,
-        _data: &mut State,
-        _handle: &mut PointerInnerHandle<'_, State>,
-        _event: &smithay::input::pointer::GesturePinchEndEvent,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &smithay::input::pointer::GesturePinchEndEvent,
     ) {
-        todo!()
+        handle.gesture_pinch_end(data, event);
     }
 
     fn gesture_hold_begin(
         &mut self,
-        _data: &mut State,
-        _handle: &mut PointerInnerHandle<'_, State>,
-        _event: &smithay::input::pointer::GestureHoldBeginEvent,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &smithay::input::pointer::GestureHoldBeginEvent,
     ) {
-        todo!()
+        handle.gesture_hold_begin(data, event);
     }
 
     fn gesture_hold_end(
         &mut self,
-        _data: &mut State,
-        _handle: &mut PointerInnerHandle<'_, State>,
-        _event: &smithay::input::pointer::GestureHoldEndEvent,
+        data: &mut State,
+        handle: &mut PointerInnerHandle<'_, State>,
+        event: &smithay::input::pointer::GestureHoldEndEvent,
     ) {
-        todo!()
+        handle.gesture_hold_end(data, event);
     }
 }
 
@@ -332,27 +396,42 @@ pub enum ResizeSurfaceState {
     Resizing {
         edges: ResizeEdge,
         initial_window_rect: Rectangle<i32, Logical>,
+        /// Whether the window is being resized around its center
+        centered: bool,
     },
     WaitingForLastCommit {
         edges: ResizeEdge,
         initial_window_rect: Rectangle<i32, Logical>,
+        /// Whether the window is being resized around its center
+        centered: bool,
     },
 }
 
 impl ResizeSurfaceState {
-    fn commit(&mut self) -> Option<(ResizeEdge, Rectangle<i32, Logical>)> {
+    fn commit(&mut self) -> Option<(ResizeEdge, Rectangle<i32, Logical>, bool)> {
         match *self {
             Self::Idle => None,
             Self::Resizing {
                 edges,
                 initial_window_rect,
-            } => Some((edges, initial_window_rect)),
+                centered,
+            } => Some((edges, initial_window_rect, centered)),
             Self::WaitingForLastCommit {
                 edges,
                 initial_window_rect,
+                centered,
             } => {
                 *self = Self::Idle;
-                Some((edges, initial_window_rect))
+                Some((edges, initial_window_rect, centered))
+            }
+        }
+    }
+
+    fn is_centered(&self) -> bool {
+        match *self {
+            Self::Idle => false,
+            Self::Resizing { centered, .. } | Self::WaitingForLastCommit { centered, .. } => {
+                centered
             }
         }
     }
@@ -367,9 +446,27 @@ pub fn handle_commit(state: &mut State, surface: &WlSurface) -> Option<()> {
         state
             .resize_state
             .commit()
-            .map(|(edges, initial_window_rect)| {
+            .map(|(edges, initial_window_rect, centered)| {
                 let mut new_x: Option<i32> = None;
                 let mut new_y: Option<i32> = None;
+
+                // Centered resizes keep the window's center where it was
+                if centered {
+                    if edges.changes_width() {
+                        new_x = Some(
+                            initial_window_rect.loc.x
+                                + (initial_window_rect.size.w - geometry.size.w) / 2,
+                        );
+                    }
+                    if edges.changes_height() {
+                        new_y = Some(
+                            initial_window_rect.loc.y
+                                + (initial_window_rect.size.h - geometry.size.h) / 2,
+                        );
+                    }
+                    return (new_x, new_y);
+                }
+
                 if let xdg_toplevel::ResizeEdge::Left
                 | xdg_toplevel::ResizeEdge::TopLeft
                 | xdg_toplevel::ResizeEdge::BottomLeft = edges.0
@@ -402,6 +499,7 @@ pub fn handle_commit(state: &mut State, surface: &WlSurface) -> Option<()> {
 
     if new_loc.x.is_some() || new_loc.y.is_some() {
         state.space.map_element(window.clone(), window_loc, false);
+        state.update_window_outputs([&window]);
         let size = state
             .space
             .element_geometry(&window)
@@ -446,6 +544,14 @@ pub fn resize_request_client(
 
         // TODO: check for fullscreen/maximized (probably shouldn't matter)
         if window.with_state(|state| state.floating_or_tiled.is_tiled()) {
+            crate::grab::layout_resize::layout_resize_request(
+                state,
+                &window,
+                edges,
+                start_data,
+                serial,
+                button_used,
+            );
             return;
         }
 
@@ -463,6 +569,7 @@ pub fn resize_request_client(
             window.toplevel().send_pending_configure();
         }
 
+        let restore = GrabRestore::new(&window);
         let grab = ResizeSurfaceGrab::start(
             start_data,
             window,
@@ -473,6 +580,8 @@ pub fn resize_request_client(
 
         if let Some(grab) = grab {
             pointer.set_grab(state, grab, serial, Focus::Clear);
+            restore.set(state, serial);
+            state.show_grab_hint(size_hint_text(initial_window_size));
         }
     }
 }
@@ -493,24 +602,12 @@ pub fn resize_request_server(
         return;
     };
 
-    if window.with_state(|state| state.floating_or_tiled.is_tiled()) {
-        return;
-    }
-
     let initial_window_loc = state
         .space
         .element_location(&window)
         .expect("resize request called on unmapped window");
     let initial_window_size = window.geometry().size;
 
-    if let Some(WindowElement::Wayland(window)) = state.window_for_surface(surface) {
-        window.toplevel().with_pending_state(|state| {
-            state.states.set(xdg_toplevel::State::Resizing);
-        });
-
-        window.toplevel().send_pending_configure();
-    }
-
     let start_data = smithay::input::pointer::GrabStartData {
         focus: pointer
             .current_focus()
@@ -519,6 +616,27 @@ pub fn resize_request_server(
         location: pointer.current_location(),
     };
 
+    if window.with_state(|state| state.floating_or_tiled.is_tiled()) {
+        crate::grab::layout_resize::layout_resize_request(
+            state,
+            &window,
+            edges,
+            start_data,
+            serial,
+            button_used,
+        );
+        return;
+    }
+
+    if let Some(WindowElement::Wayland(window)) = state.window_for_surface(surface) {
+        window.toplevel().with_pending_state(|state| {
+            state.states.set(xdg_toplevel::State::Resizing);
+        });
+
+        window.toplevel().send_pending_configure();
+    }
+
+    let restore = GrabRestore::new(&window);
     let grab = ResizeSurfaceGrab::start(
         start_data,
         window,
@@ -529,5 +647,12 @@ pub fn resize_request_server(
 
     if let Some(grab) = grab {
         pointer.set_grab(state, grab, serial, Focus::Clear);
+        restore.set(state, serial);
+        state.show_grab_hint(size_hint_text(initial_window_size));
     }
 }
+
+/// The text the grab hint shows for a window of `size`.
+fn size_hint_text(size: Size<i32, Logical>) -> String {
+    format!("{}×{}", size.w, size.h)
+}
@@ -0,0 +1,133 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Snapping floating windows to nearby edges while they're moved.
+//!
+//! When an edge of a window being moved comes within the snap threshold of another edge, the
+//! window is pulled over so they line up. Windows snap to the edges of outputs, the edges of
+//! the area layer surfaces' exclusive zones leave free, and the edges of other floating windows.
+//! An edge is only snapped to if it's beside the window, so a window doesn't snap to the
+//! edge of an output or window it isn't anywhere near.
+//!
+//! Snapping always starts from where the pointer would put the window, not from where it
+//! was last snapped to, so moving past the threshold pulls the window off the edge again.
+//! This keeps it from getting stuck where two outputs meet.
+
+use smithay::{
+    desktop::layer_map_for_output,
+    utils::{Logical, Point, Rectangle, Size},
+};
+
+use crate::{
+    state::{State, WithState},
+    window::WindowElement,
+};
+
+/// How close edges have to be to snap together, in logical pixels, unless the config changes it.
+pub const DEFAULT_SNAP_THRESHOLD: u32 = 10;
+
+/// An edge a window can snap to.
+///
+/// For vertical edges, `pos` is the x coordinate and `start` and `end` are where the edge
+/// starts and ends on the y axis. Horizontal edges are the other way around.
+#[derive(Debug, Clone, Copy)]
+struct SnapEdge {
+    pos: i32,
+    start: i32,
+    end: i32,
+}
+
+impl State {
+    /// Snap `window`, which the pointer would move to `loc`, to nearby edges.
+    ///
+    /// Returns where the window should go.
+    pub fn snap_window_loc(
+        &self,
+        window: &WindowElement,
+        loc: Point<i32, Logical>,
+        size: Size<i32, Logical>,
+    ) -> Point<i32, Logical> {
+        let threshold = self.snap_threshold as i32;
+        if threshold == 0 {
+            return loc;
+        }
+
+        let mut rects = Vec::new();
+
+        for output in self.space.outputs() {
+            let Some(op_geo) = self.space.output_geometry(output) else {
+                continue;
+            };
+            rects.push(op_geo);
+
+            // The zone is only right when there are layer surfaces, see `tile_windows`
+            let map = layer_map_for_output(output);
+            if map.layers().next().is_some() {
+                let zone = map.non_exclusive_zone();
+                let zone = Rectangle::from_loc_and_size(op_geo.loc + zone.loc, zone.size);
+                if zone != op_geo {
+                    rects.push(zone);
+                }
+            }
+        }
+
+        rects.extend(
+            self.space
+                .elements()
+                .filter(|win| *win != window && !win.is_x11_override_redirect())
+                .filter(|win| win.with_state(|state| state.floating_or_tiled.is_floating()))
+                .filter_map(|win| self.space.element_geometry(win)),
+        );
+
+        let mut vertical_edges = Vec::new();
+        let mut horizontal_edges = Vec::new();
+
+        for rect in rects {
+            let (left, right) = (rect.loc.x, rect.loc.x + rect.size.w);
+            let (top, bottom) = (rect.loc.y, rect.loc.y + rect.size.h);
+
+            for pos in [left, right] {
+                vertical_edges.push(SnapEdge {
+                    pos,
+                    start: top,
+                    end: bottom,
+                });
+            }
+            for pos in [top, bottom] {
+                horizontal_edges.push(SnapEdge {
+                    pos,
+                    start: left,
+                    end: right,
+                });
+            }
+        }
+
+        let dx = snap_offset(
+            &vertical_edges,
+            (loc.x, loc.x + size.w),
+            (loc.y, loc.y + size.h),
+            threshold,
+        );
+        let dy = snap_offset(
+            &horizontal_edges,
+            (loc.y, loc.y + size.h),
+            (loc.x, loc.x + size.w),
+            threshold,
+        );
+
+        loc + Point::from((dx, dy))
+    }
+}
+
+/// Get how far to move a window to snap one of its edges to the closest edge in `edges`.
+///
+/// `sides` are the positions of the window's two edges parallel to `edges`, and `span` is where
+/// the window starts and ends along them. Returns 0 if no edge is within `threshold`.
+fn snap_offset(edges: &[SnapEdge], sides: (i32, i32), span: (i32, i32), threshold: i32) -> i32 {
+    edges
+        .iter()
+        .filter(|edge| edge.start <= span.1 + threshold && span.0 - threshold <= edge.end)
+        .flat_map(|edge| [edge.pos - sides.0, edge.pos - sides.1])
+        .filter(|offset| offset.abs() <= threshold)
+        .min_by_key(|offset| offset.abs())
+        .unwrap_or(0)
+}
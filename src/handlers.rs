@@ -1,15 +1,23 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+pub mod foreign_toplevel;
+pub mod gamma_control;
+pub mod input_inhibitor;
+mod input_method;
+mod keyboard_shortcuts_inhibit;
+pub mod screencopy;
+pub mod security_context;
 mod xdg_shell;
 mod xwayland;
+pub mod xwayland_keyboard_grab;
 
 use std::os::fd::OwnedFd;
 
 use smithay::{
     backend::renderer::utils,
     delegate_compositor, delegate_data_device, delegate_fractional_scale, delegate_layer_shell,
-    delegate_output, delegate_presentation, delegate_primary_selection, delegate_relative_pointer,
-    delegate_seat, delegate_shm, delegate_viewporter,
+    delegate_output, delegate_pointer_gestures, delegate_presentation, delegate_primary_selection,
+    delegate_relative_pointer, delegate_seat, delegate_shm, delegate_viewporter,
     desktop::{self, find_popup_root_surface, layer_map_for_output, PopupKind, WindowSurfaceType},
     input::{pointer::CursorImageStatus, Seat, SeatHandler, SeatState},
     output::Output,
@@ -54,7 +62,7 @@ use smithay::{
 use crate::{
     focus::FocusTarget,
     state::{CalloopData, ClientState, State, WithState},
-    window::{window_state::LocationRequestState, WindowElement},
+    window::WindowElement,
 };
 
 impl BufferHandler for State {
@@ -103,6 +111,8 @@ impl CompositorHandler for State {
 
         X11Wm::commit_hook::<CalloopData>(surface);
 
+        self.record_debug_damage(surface);
+
         utils::on_commit_buffer_handler::<Self>(surface);
         self.backend.early_import(surface);
 
@@ -114,20 +124,33 @@ impl CompositorHandler for State {
         if !compositor::is_sync_subsurface(surface) {
             if let Some(win @ WindowElement::Wayland(window)) = &self.window_for_surface(&root) {
                 window.on_commit();
-                win.with_state(|state| {
-                    if let LocationRequestState::Acknowledged(new_pos) = state.loc_request_state {
+                let mapped = win.with_state(|state| {
+                    if let Some(new_pos) = state.loc_request_state.take_acknowledged() {
                         tracing::debug!("Mapping Acknowledged window");
-                        state.loc_request_state = LocationRequestState::Idle;
                         self.space.map_element(win.clone(), new_pos, false);
+                        true
+                    } else {
+                        false
                     }
                 });
+
+                if mapped {
+                    self.update_window_outputs([win]);
+                }
             }
         };
 
+        // Titles can change on any commit, even for windows that aren't shown
+        if let Some(window) = self.window_for_surface(surface) {
+            self.update_window_title_and_class(&window);
+        }
+
         self.popup_manager.commit(surface);
 
         ensure_initial_configure(surface, self);
 
+        self.update_layer_keyboard_focus(surface);
+
         crate::grab::resize_grab::handle_commit(self, surface);
 
         let output = if let Some(output) = self
@@ -326,11 +349,19 @@ impl SeatHandler for State {
         if let Some(win) =
             focused.and_then(|focused| self.window_for_surface(&focused.wl_surface()?))
         {
-            if let WindowElement::Wayland(win) = &win {
-                win.set_activated(true);
-            }
-            self.focus_state.set_focus(win);
+            self.end_mru_cycle_unless_current(&win);
+            self.focus_state.set_focus(win.clone());
+            self.flash_window_on_focus(&win);
+            self.warp_pointer_on_focus(&win);
         }
+
+        if let Some(FocusTarget::LayerSurface(layer)) = focused {
+            self.focus_state.push_layer_focus(layer.clone());
+        }
+
+        // Popups keep the window they belong to activated
+        let activated = focused.and_then(|focused| self.window_for_focus_target(focused));
+        self.set_activated_window(activated);
         let focus_client = focused.and_then(|foc_target| {
             self.display_handle
                 .get_client(foc_target.wl_surface()?.id())
@@ -338,6 +369,11 @@ impl SeatHandler for State {
         });
         set_data_device_focus(&self.display_handle, seat, focus_client.clone());
         set_primary_focus(&self.display_handle, seat, focus_client);
+
+        self.update_keyboard_shortcuts_inhibitor(focused);
+
+        // A repeating keybind shouldn't keep acting on whatever gets focused next
+        self.stop_keybind_repeat();
     }
 }
 delegate_seat!(State);
@@ -405,6 +441,8 @@ delegate_fractional_scale!(State);
 
 delegate_relative_pointer!(State);
 
+delegate_pointer_gestures!(State);
+
 delegate_presentation!(State);
 
 impl WlrLayerShellHandler for State {
@@ -416,7 +454,7 @@ impl WlrLayerShellHandler for State {
         &mut self,
         surface: wlr_layer::LayerSurface,
         output: Option<WlOutput>,
-        _layer: Layer,
+        layer: Layer,
         namespace: String,
     ) {
         tracing::debug!("New layer surface");
@@ -430,6 +468,14 @@ impl WlrLayerShellHandler for State {
             return;
         };
 
+        if self.do_not_disturb.should_hold_layer(layer, &namespace) {
+            tracing::debug!("Holding layer surface {namespace} until do not disturb ends");
+            self.do_not_disturb
+                .held_layers
+                .push((desktop::LayerSurface::new(surface, namespace), output));
+            return;
+        }
+
         let mut map = layer_map_for_output(&output);
         map.map_layer(&desktop::LayerSurface::new(surface, namespace))
             .expect("failed to map layer surface");
@@ -441,6 +487,10 @@ impl WlrLayerShellHandler for State {
     }
 
     fn layer_destroyed(&mut self, surface: wlr_layer::LayerSurface) {
+        self.do_not_disturb
+            .held_layers
+            .retain(|(layer, _)| layer.layer_surface() != &surface);
+
         let mut output: Option<Output> = None;
         if let Some((mut map, layer, op)) = self.space.outputs().find_map(|o| {
             let map = layer_map_for_output(o);
@@ -454,9 +504,26 @@ impl WlrLayerShellHandler for State {
             output = Some(op.clone());
         }
 
+        self.focus_state
+            .layer_focus_stack
+            .retain(|layer| layer.layer_surface() != &surface);
+
+        let had_keyboard_focus = self
+            .seat
+            .get_keyboard()
+            .and_then(|kb| kb.current_focus())
+            .is_some_and(|focus| match focus {
+                FocusTarget::LayerSurface(layer) => layer.layer_surface() == &surface,
+                _ => false,
+            });
+
         if let Some(output) = output {
             self.loop_handle.insert_idle(move |data| {
                 data.state.update_windows(&output);
+                // Give keyboard focus back to what had it before the layer surface took it
+                if had_keyboard_focus {
+                    data.state.update_focus(&output);
+                }
             });
         }
     }
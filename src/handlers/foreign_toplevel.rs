@@ -0,0 +1,375 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! `zwlr_foreign_toplevel_manager_v1`, for taskbars and window switchers.
+//!
+//! Every mapped window that isn't an X11 override-redirect window gets a handle with its title,
+//! app id, whether it's maximized, fullscreen, or activated, and which outputs it's on.
+//! Handles are refreshed once per event loop iteration, and only what changed is sent.
+//! When a window closes or an X11 window unmaps, its handles are closed.
+//!
+//! Clients can activate, close, maximize, and fullscreen windows the same way the config can.
+//! Minimizing isn't supported, so those requests are ignored.
+//!
+//! Sandboxed clients only see this if the sandbox policy allows it.
+
+use smithay::{
+    backend::renderer::utils::with_renderer_surface_state,
+    output::Output,
+    reexports::{
+        wayland_protocols_wlr::foreign_toplevel::v1::server::{
+            zwlr_foreign_toplevel_handle_v1::{self, ZwlrForeignToplevelHandleV1},
+            zwlr_foreign_toplevel_manager_v1::{self, ZwlrForeignToplevelManagerV1},
+        },
+        wayland_server::{
+            backend::ClientId, Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New,
+            Resource,
+        },
+    },
+    utils::IsAlive,
+};
+
+use crate::{
+    handlers::security_context::{self, Capability},
+    state::{State, WithState},
+    window::{window_state::WindowId, WindowElement},
+};
+
+const VERSION: u32 = 3;
+
+#[derive(Debug)]
+pub struct ForeignToplevelState {
+    /// Managers that haven't been stopped
+    managers: Vec<ZwlrForeignToplevelManagerV1>,
+    /// Every advertised window with what its handles were last told
+    toplevels: Vec<Toplevel>,
+}
+
+#[derive(Debug)]
+struct Toplevel {
+    window: WindowElement,
+    handles: Vec<ZwlrForeignToplevelHandleV1>,
+    props: ToplevelProps,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct ToplevelProps {
+    title: Option<String>,
+    app_id: Option<String>,
+    states: Vec<zwlr_foreign_toplevel_handle_v1::State>,
+    outputs: Vec<Output>,
+}
+
+impl ForeignToplevelState {
+    /// Advertise `zwlr_foreign_toplevel_manager_v1`.
+    pub fn new(display_handle: &DisplayHandle) -> Self {
+        display_handle.create_global::<State, ZwlrForeignToplevelManagerV1, _>(VERSION, ());
+
+        Self {
+            managers: Vec::new(),
+            toplevels: Vec::new(),
+        }
+    }
+}
+
+impl ToplevelProps {
+    fn new(state: &State, window: &WindowElement, activated: bool) -> Self {
+        let mut states = Vec::new();
+        window.with_state(|win_state| {
+            if win_state.fullscreen_or_maximized.is_maximized() {
+                states.push(zwlr_foreign_toplevel_handle_v1::State::Maximized);
+            }
+            if win_state.fullscreen_or_maximized.is_fullscreen() {
+                states.push(zwlr_foreign_toplevel_handle_v1::State::Fullscreen);
+            }
+        });
+        if activated {
+            states.push(zwlr_foreign_toplevel_handle_v1::State::Activated);
+        }
+
+        Self {
+            title: window.title(),
+            app_id: window.class(),
+            states,
+            outputs: state.space.outputs_for_element(window),
+        }
+    }
+
+    /// Send everything that differs from `old` to `handle`, or everything if there is no `old`.
+    fn send(&self, handle: &ZwlrForeignToplevelHandleV1, old: Option<&ToplevelProps>) {
+        let mut changed = false;
+
+        if old.map(|old| &old.title) != Some(&self.title) {
+            handle.title(self.title.clone().unwrap_or_default());
+            changed = true;
+        }
+        if old.map(|old| &old.app_id) != Some(&self.app_id) {
+            handle.app_id(self.app_id.clone().unwrap_or_default());
+            changed = true;
+        }
+        if old.map(|old| &old.states) != Some(&self.states) {
+            let states = self
+                .states
+                .iter()
+                .flat_map(|state| (*state as u32).to_ne_bytes())
+                .collect();
+            handle.state(states);
+            changed = true;
+        }
+
+        if let Some(client) = handle.client() {
+            let old_outputs = old.map(|old| old.outputs.as_slice()).unwrap_or_default();
+
+            for output in old_outputs.iter().filter(|op| !self.outputs.contains(op)) {
+                for wl_output in output.client_outputs(&client) {
+                    handle.output_leave(&wl_output);
+                }
+                changed = true;
+            }
+            for output in self.outputs.iter().filter(|op| !old_outputs.contains(op)) {
+                for wl_output in output.client_outputs(&client) {
+                    handle.output_enter(&wl_output);
+                }
+                changed = true;
+            }
+        }
+
+        if changed {
+            handle.done();
+        }
+    }
+}
+
+impl State {
+    /// Create, update, and close foreign toplevel handles so they match the current windows.
+    pub fn refresh_foreign_toplevels(&mut self) {
+        if self.foreign_toplevel_state.managers.is_empty()
+            && self.foreign_toplevel_state.toplevels.is_empty()
+        {
+            return;
+        }
+
+        let mut toplevels = std::mem::take(&mut self.foreign_toplevel_state.toplevels);
+
+        toplevels.retain(|toplevel| {
+            let keep = is_advertised(&toplevel.window);
+            if !keep {
+                for handle in toplevel.handles.iter() {
+                    handle.closed();
+                }
+            }
+            keep
+        });
+
+        let activated = self.focus_state.activated.clone();
+
+        for window in self.windows.iter().filter(|win| is_advertised(win)) {
+            let props = ToplevelProps::new(self, window, activated.as_ref() == Some(window));
+
+            match toplevels
+                .iter_mut()
+                .find(|toplevel| &toplevel.window == window)
+            {
+                Some(toplevel) => {
+                    if toplevel.props == props {
+                        continue;
+                    }
+                    toplevel.handles.retain(|handle| handle.is_alive());
+                    for handle in toplevel.handles.iter() {
+                        props.send(handle, Some(&toplevel.props));
+                    }
+                    toplevel.props = props;
+                }
+                None => {
+                    let window_id = window.with_state(|state| state.id);
+                    let handles = self
+                        .foreign_toplevel_state
+                        .managers
+                        .iter()
+                        .filter_map(|manager| {
+                            new_handle(&self.display_handle, manager, window_id, &props)
+                        })
+                        .collect();
+                    toplevels.push(Toplevel {
+                        window: window.clone(),
+                        handles,
+                        props,
+                    });
+                }
+            }
+        }
+
+        self.foreign_toplevel_state.toplevels = toplevels;
+    }
+}
+
+/// Whether `window` should have foreign toplevel handles.
+fn is_advertised(window: &WindowElement) -> bool {
+    window.alive()
+        && match window {
+            WindowElement::Wayland(window) => {
+                with_renderer_surface_state(window.toplevel().wl_surface(), |state| {
+                    state.buffer().is_some()
+                })
+                .unwrap_or(false)
+            }
+            WindowElement::X11(surface) => surface.is_mapped(),
+            WindowElement::X11OverrideRedirect(_) => false,
+        }
+}
+
+/// Create a handle for `manager`'s client, announce it, and send it `props`.
+fn new_handle(
+    display_handle: &DisplayHandle,
+    manager: &ZwlrForeignToplevelManagerV1,
+    window_id: WindowId,
+    props: &ToplevelProps,
+) -> Option<ZwlrForeignToplevelHandleV1> {
+    let client = manager.client()?;
+    let handle = client
+        .create_resource::<ZwlrForeignToplevelHandleV1, _, State>(
+            display_handle,
+            manager.version(),
+            window_id,
+        )
+        .ok()?;
+
+    manager.toplevel(&handle);
+    props.send(&handle, None);
+
+    Some(handle)
+}
+
+impl GlobalDispatch<ZwlrForeignToplevelManagerV1, ()> for State {
+    fn bind(
+        state: &mut Self,
+        handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwlrForeignToplevelManagerV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let manager = data_init.init(resource, ());
+
+        // Tell the new manager about windows that are already open
+        for toplevel in state.foreign_toplevel_state.toplevels.iter_mut() {
+            let window_id = toplevel.window.with_state(|state| state.id);
+            if let Some(new) = new_handle(handle, &manager, window_id, &toplevel.props) {
+                toplevel.handles.push(new);
+            }
+        }
+
+        state.foreign_toplevel_state.managers.push(manager);
+    }
+
+    fn can_view(client: Client, _global_data: &()) -> bool {
+        security_context::client_allows(&client, Capability::ForeignToplevel)
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelManagerV1, ()> for State {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        manager: &ZwlrForeignToplevelManagerV1,
+        request: zwlr_foreign_toplevel_manager_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            zwlr_foreign_toplevel_manager_v1::Request::Stop => {
+                state
+                    .foreign_toplevel_state
+                    .managers
+                    .retain(|other| other != manager);
+                manager.finished();
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut Self,
+        _client: ClientId,
+        manager: &ZwlrForeignToplevelManagerV1,
+        _data: &(),
+    ) {
+        state
+            .foreign_toplevel_state
+            .managers
+            .retain(|other| other != manager);
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, WindowId> for State {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        _handle: &ZwlrForeignToplevelHandleV1,
+        request: zwlr_foreign_toplevel_handle_v1::Request,
+        window_id: &WindowId,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        let Some(window) = window_id.window(state) else {
+            return;
+        };
+
+        match request {
+            zwlr_foreign_toplevel_handle_v1::Request::Activate { .. } => {
+                let Some(output) = window.output(state) else { return };
+
+                state.focus_state.set_focus(window);
+                state.update_focus(&output);
+                state.schedule_render(&output);
+            }
+            zwlr_foreign_toplevel_handle_v1::Request::Close => window.close(),
+            zwlr_foreign_toplevel_handle_v1::Request::SetMaximized => {
+                state.set_foreign_toplevel_maximized(&window, true);
+            }
+            zwlr_foreign_toplevel_handle_v1::Request::UnsetMaximized => {
+                state.set_foreign_toplevel_maximized(&window, false);
+            }
+            zwlr_foreign_toplevel_handle_v1::Request::SetFullscreen { .. } => {
+                state.set_foreign_toplevel_fullscreen(&window, true);
+            }
+            zwlr_foreign_toplevel_handle_v1::Request::UnsetFullscreen => {
+                state.set_foreign_toplevel_fullscreen(&window, false);
+            }
+            zwlr_foreign_toplevel_handle_v1::Request::SetMinimized
+            | zwlr_foreign_toplevel_handle_v1::Request::UnsetMinimized
+            | zwlr_foreign_toplevel_handle_v1::Request::SetRectangle { .. }
+            | zwlr_foreign_toplevel_handle_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut Self,
+        _client: ClientId,
+        handle: &ZwlrForeignToplevelHandleV1,
+        _data: &WindowId,
+    ) {
+        for toplevel in state.foreign_toplevel_state.toplevels.iter_mut() {
+            toplevel.handles.retain(|other| other != handle);
+        }
+    }
+}
+
+impl State {
+    fn set_foreign_toplevel_maximized(&mut self, window: &WindowElement, maximized: bool) {
+        window.set_maximized(maximized);
+
+        let Some(output) = window.output(self) else { return };
+        self.update_windows(&output);
+        self.schedule_render(&output);
+    }
+
+    fn set_foreign_toplevel_fullscreen(&mut self, window: &WindowElement, fullscreen: bool) {
+        window.set_fullscreen(fullscreen);
+
+        let Some(output) = window.output(self) else { return };
+        self.update_windows(&output);
+        self.schedule_render(&output);
+    }
+}
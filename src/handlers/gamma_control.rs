@@ -0,0 +1,232 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! `zwlr_gamma_control_manager_v1`, for night light tools like gammastep and redshift.
+//!
+//! Clients set the gamma ramps of an output's CRTC. Only one client can control an output at
+//! a time, so asking for a second control of an output fails right away. When a control is
+//! destroyed, including when its client disconnects, the output gets back the ramps it had
+//! before the control was made.
+//!
+//! This is only advertised on the udev backend, since a winit window has no CRTC to set.
+//! Sandboxed clients only see it if the sandbox policy allows gamma control.
+
+use std::{fs::File, os::unix::fs::FileExt};
+
+use smithay::{
+    output::Output,
+    reexports::{
+        wayland_protocols_wlr::gamma_control::v1::server::{
+            zwlr_gamma_control_manager_v1::{self, ZwlrGammaControlManagerV1},
+            zwlr_gamma_control_v1::{self, ZwlrGammaControlV1},
+        },
+        wayland_server::{
+            backend::ClientId, Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New,
+            Resource,
+        },
+    },
+};
+
+use crate::{
+    backend::Backend,
+    handlers::security_context::{self, Capability},
+    state::State,
+};
+
+const VERSION: u32 = 1;
+
+#[derive(Debug, Default)]
+pub struct GammaControlState {
+    /// Controls that haven't failed, at most one per output
+    controls: Vec<GammaControl>,
+}
+
+#[derive(Debug)]
+struct GammaControl {
+    control: ZwlrGammaControlV1,
+    output: Output,
+    /// The ramps the output had before the control was made
+    original: Vec<u16>,
+}
+
+impl GammaControlState {
+    /// Advertise `zwlr_gamma_control_manager_v1`.
+    pub fn create_global(display_handle: &DisplayHandle) {
+        display_handle.create_global::<State, ZwlrGammaControlManagerV1, _>(VERSION, ());
+    }
+
+    /// Fail the control of `output` now that it's gone.
+    pub fn output_removed(&mut self, output: &Output) {
+        self.controls.retain(|control| {
+            let keep = &control.output != output;
+            if !keep {
+                control.control.failed();
+            }
+            keep
+        });
+    }
+}
+
+impl State {
+    /// Stop `control` from controlling its output, putting back the output's original ramps
+    /// if `restore` is true.
+    fn remove_gamma_control(&mut self, control: &ZwlrGammaControlV1, restore: bool) {
+        let Some(index) = self
+            .gamma_control_state
+            .controls
+            .iter()
+            .position(|other| &other.control == control)
+        else {
+            return;
+        };
+        let removed = self.gamma_control_state.controls.remove(index);
+
+        if let (true, Backend::Udev(udev)) = (restore, &self.backend) {
+            if let Err(err) = udev.set_gamma(&removed.output, &removed.original) {
+                tracing::warn!(
+                    "Failed to restore gamma of {}: {err}",
+                    removed.output.name()
+                );
+            }
+        }
+    }
+}
+
+impl GlobalDispatch<ZwlrGammaControlManagerV1, ()> for State {
+    fn bind(
+        _state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwlrGammaControlManagerV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+
+    fn can_view(client: Client, _global_data: &()) -> bool {
+        security_context::client_allows(&client, Capability::GammaControl)
+    }
+}
+
+impl Dispatch<ZwlrGammaControlManagerV1, ()> for State {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        _manager: &ZwlrGammaControlManagerV1,
+        request: zwlr_gamma_control_manager_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            zwlr_gamma_control_manager_v1::Request::GetGammaControl { id, output } => {
+                let control = data_init.init(id, ());
+
+                let Some(output) = Output::from_resource(&output) else {
+                    control.failed();
+                    return;
+                };
+                let Backend::Udev(udev) = &state.backend else {
+                    control.failed();
+                    return;
+                };
+
+                let taken = state
+                    .gamma_control_state
+                    .controls
+                    .iter()
+                    .any(|other| other.output == output);
+                if taken {
+                    tracing::debug!("{} already has a gamma control", output.name());
+                    control.failed();
+                    return;
+                }
+
+                let Some(size) = udev.gamma_size(&output) else {
+                    control.failed();
+                    return;
+                };
+                let original = match udev.gamma(&output) {
+                    Ok(original) => original,
+                    Err(err) => {
+                        tracing::warn!("Failed to get gamma of {}: {err}", output.name());
+                        control.failed();
+                        return;
+                    }
+                };
+
+                control.gamma_size(size);
+                state.gamma_control_state.controls.push(GammaControl {
+                    control,
+                    output,
+                    original,
+                });
+            }
+            zwlr_gamma_control_manager_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Dispatch<ZwlrGammaControlV1, ()> for State {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        control: &ZwlrGammaControlV1,
+        request: zwlr_gamma_control_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            zwlr_gamma_control_v1::Request::SetGamma { fd } => {
+                // Failed controls are ignored until the client destroys them
+                let Some(output) = state
+                    .gamma_control_state
+                    .controls
+                    .iter()
+                    .find(|other| &other.control == control)
+                    .map(|control| control.output.clone())
+                else {
+                    return;
+                };
+                let Backend::Udev(udev) = &state.backend else {
+                    return;
+                };
+                let Some(size) = udev.gamma_size(&output) else {
+                    control.failed();
+                    state.remove_gamma_control(control, false);
+                    return;
+                };
+
+                // Ramps are read from the start of the file like wlroots does,
+                // since clients write them before sending the fd
+                let mut bytes = vec![0; size as usize * 3 * 2];
+                if let Err(err) = File::from(fd).read_exact_at(&mut bytes, 0) {
+                    control.post_error(
+                        zwlr_gamma_control_v1::Error::InvalidGamma,
+                        format!("failed to read {} bytes of gamma ramps: {err}", bytes.len()),
+                    );
+                    return;
+                }
+
+                let ramps = bytes
+                    .chunks_exact(2)
+                    .map(|entry| u16::from_ne_bytes([entry[0], entry[1]]))
+                    .collect::<Vec<_>>();
+
+                if let Err(err) = udev.set_gamma(&output, &ramps) {
+                    tracing::warn!("Failed to set gamma of {}: {err}", output.name());
+                    control.failed();
+                    state.remove_gamma_control(control, true);
+                }
+            }
+            zwlr_gamma_control_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(state: &mut Self, _client: ClientId, control: &ZwlrGammaControlV1, _data: &()) {
+        state.remove_gamma_control(control, true);
+    }
+}
@@ -0,0 +1,231 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! `zwlr_input_inhibit_manager_v1`, for screen lockers that don't speak ext-session-lock yet.
+//!
+//! While a client holds the inhibitor, only its surfaces get keyboard and pointer input
+//! and only the kill and reload keybinds and VT switching still work.
+//! One client can hold it at a time, and it's released when that client disconnects.
+//!
+//! This can be turned off with `input_inhibitor = false` in `metaconfig.toml`. Sandboxed clients
+//! only see it if the sandbox policy allows it.
+
+use std::time::Duration;
+
+use smithay::{
+    desktop::layer_map_for_output,
+    input::pointer::MotionEvent,
+    reexports::{
+        wayland_protocols_wlr::input_inhibitor::v1::server::{
+            zwlr_input_inhibit_manager_v1::{self, ZwlrInputInhibitManagerV1},
+            zwlr_input_inhibitor_v1::{self, ZwlrInputInhibitorV1},
+        },
+        wayland_server::{
+            backend::{ClientId, GlobalId},
+            Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+        },
+    },
+    utils::SERIAL_COUNTER,
+    wayland::{seat::WaylandFocus, shell::wlr_layer::KeyboardInteractivity},
+};
+
+use crate::{
+    focus::FocusTarget,
+    handlers::security_context::{self, Capability},
+    state::State,
+};
+
+const VERSION: u32 = 1;
+
+#[derive(Debug, Default)]
+pub struct InputInhibitorState {
+    global: Option<GlobalId>,
+    /// The inhibitor that is held, if any
+    inhibitor: Option<ZwlrInputInhibitorV1>,
+}
+
+impl InputInhibitorState {
+    /// Get the client holding the inhibitor, if any.
+    pub fn client(&self) -> Option<Client> {
+        self.inhibitor
+            .as_ref()
+            .filter(|inhibitor| inhibitor.is_alive())
+            .and_then(|inhibitor| inhibitor.client())
+    }
+
+    /// Whether `focus` can get input right now.
+    pub fn allows(&self, focus: &FocusTarget) -> bool {
+        let Some(client) = self.client() else {
+            return true;
+        };
+
+        focus
+            .wl_surface()
+            .and_then(|surface| surface.client())
+            .is_some_and(|focus_client| focus_client == client)
+    }
+}
+
+impl State {
+    /// Advertise `zwlr_input_inhibit_manager_v1`, or stop advertising it.
+    ///
+    /// Turning it off doesn't release an inhibitor that is already held.
+    pub fn set_input_inhibitor_enabled(&mut self, enabled: bool) {
+        let global = &mut self.input_inhibitor_state.global;
+        match (enabled, global.take()) {
+            (true, None) => {
+                *global = Some(
+                    self.display_handle
+                        .create_global::<State, ZwlrInputInhibitManagerV1, _>(VERSION, ()),
+                );
+            }
+            (false, Some(old)) => self.display_handle.remove_global::<State>(old),
+            (_, old) => *global = old,
+        }
+    }
+
+    /// Give keyboard focus to a surface of the client holding the inhibitor
+    /// if something else has it.
+    pub fn focus_input_inhibitor(&mut self) {
+        let Some(client) = self.input_inhibitor_state.client() else {
+            return;
+        };
+
+        let keyboard = self.seat.get_keyboard().expect("Seat has no keyboard");
+
+        if keyboard
+            .current_focus()
+            .is_some_and(|focus| self.input_inhibitor_state.allows(&focus))
+        {
+            return;
+        }
+
+        // Lockers put a layer surface on every output, so any of them will do
+        let layer = self.space.outputs().find_map(|output| {
+            layer_map_for_output(output)
+                .layers()
+                .find(|layer| {
+                    layer.wl_surface().client().as_ref() == Some(&client)
+                        && layer.cached_state().keyboard_interactivity
+                            != KeyboardInteractivity::None
+                })
+                .cloned()
+        });
+
+        keyboard.set_focus(
+            self,
+            layer.map(FocusTarget::from),
+            SERIAL_COUNTER.next_serial(),
+        );
+    }
+
+    /// Send the pointer to whatever is under it now that a different client may be allowed
+    /// to get pointer input.
+    fn refresh_pointer_focus(&mut self) {
+        let Some(pointer) = self.seat.get_pointer() else {
+            return;
+        };
+
+        pointer.motion(
+            self,
+            self.surface_under(self.pointer_location),
+            &MotionEvent {
+                location: self.pointer_location,
+                serial: SERIAL_COUNTER.next_serial(),
+                time: Duration::from(self.clock.now()).as_millis() as u32,
+            },
+        );
+        pointer.frame(self);
+    }
+
+    /// Give input back to everything else after the inhibitor is released.
+    fn release_input_inhibitor(&mut self) {
+        self.input_inhibitor_state.inhibitor = None;
+        tracing::info!("Input inhibitor released");
+
+        if let Some(output) = self.focus_state.focused_output.clone() {
+            self.update_focus(&output);
+        }
+
+        // Refresh pointer focus for whatever is under the pointer now
+        self.refresh_pointer_focus();
+    }
+}
+
+impl GlobalDispatch<ZwlrInputInhibitManagerV1, ()> for State {
+    fn bind(
+        _state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwlrInputInhibitManagerV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+
+    fn can_view(client: Client, _global_data: &()) -> bool {
+        security_context::client_allows(&client, Capability::InputInhibitor)
+    }
+}
+
+impl Dispatch<ZwlrInputInhibitManagerV1, ()> for State {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        manager: &ZwlrInputInhibitManagerV1,
+        request: zwlr_input_inhibit_manager_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            zwlr_input_inhibit_manager_v1::Request::GetInhibitor { id } => {
+                if state.input_inhibitor_state.client().is_some() {
+                    manager.post_error(
+                        zwlr_input_inhibit_manager_v1::Error::AlreadyInhibited,
+                        "input is already inhibited by another client",
+                    );
+                    return;
+                }
+
+                let inhibitor = data_init.init(id, ());
+                state.input_inhibitor_state.inhibitor = Some(inhibitor);
+                tracing::info!("Input inhibited");
+
+                // Take pointer focus away from other clients right away
+                state.refresh_pointer_focus();
+                state.focus_input_inhibitor();
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Dispatch<ZwlrInputInhibitorV1, ()> for State {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _inhibitor: &ZwlrInputInhibitorV1,
+        request: zwlr_input_inhibitor_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            // Releasing happens in `destroyed`, which is also called when the client disconnects
+            zwlr_input_inhibitor_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut Self,
+        _client: ClientId,
+        inhibitor: &ZwlrInputInhibitorV1,
+        _data: &(),
+    ) {
+        if state.input_inhibitor_state.inhibitor.as_ref() == Some(inhibitor) {
+            state.release_input_inhibitor();
+        }
+    }
+}
@@ -0,0 +1,51 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Input methods, text input, and virtual keyboards.
+//!
+//! Text input focus follows keyboard focus: Smithay enables and disables text input on
+//! surfaces as they gain and lose keyboard focus, so nothing needs to be done here for that.
+//!
+//! Input method popups are tracked by the popup manager under the surface that has
+//! text input focus. They get rendered with that surface's popups at the cursor rectangle
+//! the client reported.
+//!
+//! An input method grabs the keyboard while text input is enabled, but that grab only gets the
+//! keys the keyboard filter forwards. Keybinds, media keys, and bind layers take their keys
+//! before the input method sees them, even while it's composing. Keybinds set with
+//! `passthrough` are forwarded to it too.
+
+use smithay::{
+    delegate_input_method_manager, delegate_text_input_manager, delegate_virtual_keyboard_manager,
+    desktop::{space::SpaceElement, PopupKind, PopupManager},
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    utils::{Logical, Rectangle},
+    wayland::input_method::{InputMethodHandler, PopupSurface},
+};
+
+use crate::state::State;
+
+impl InputMethodHandler for State {
+    fn new_popup(&mut self, surface: PopupSurface) {
+        if let Err(err) = self.popup_manager.track_popup(PopupKind::from(surface)) {
+            tracing::warn!("Failed to track input method popup: {err}");
+        }
+    }
+
+    fn dismiss_popup(&mut self, surface: PopupSurface) {
+        if let Some(parent) = surface.get_parent().map(|parent| parent.surface.clone()) {
+            let _ = PopupManager::dismiss_popup(&parent, &PopupKind::from(surface));
+        }
+    }
+
+    fn parent_geometry(&self, parent: &WlSurface) -> Rectangle<i32, Logical> {
+        self.window_for_surface(parent)
+            .map(|window| window.geometry())
+            .unwrap_or_default()
+    }
+}
+
+delegate_input_method_manager!(State);
+
+delegate_text_input_manager!(State);
+
+delegate_virtual_keyboard_manager!(State);
@@ -0,0 +1,79 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Keyboard shortcuts inhibition, for VM viewers and remote desktop clients that want
+//! keys like Super to go to them instead of triggering keybinds.
+//!
+//! Only the inhibitor of the surface with keyboard focus is active. While it is, keybinds
+//! not set with `force` don't trigger and keys go straight to the surface. VT switching
+//! always works. X11 keyboard grabs suppress keybinds the same way, see
+//! [`KeybindSuppression`][crate::input::suppression::KeybindSuppression].
+
+use smithay::{
+    delegate_keyboard_shortcuts_inhibit,
+    wayland::{
+        keyboard_shortcuts_inhibit::{
+            KeyboardShortcutsInhibitHandler, KeyboardShortcutsInhibitState,
+            KeyboardShortcutsInhibitor, KeyboardShortcutsInhibitorSeat,
+        },
+        seat::WaylandFocus,
+    },
+};
+
+use crate::{focus::FocusTarget, state::State};
+
+impl KeyboardShortcutsInhibitHandler for State {
+    fn keyboard_shortcuts_inhibit_state(&mut self) -> &mut KeyboardShortcutsInhibitState {
+        &mut self.keyboard_shortcuts_inhibit_state
+    }
+
+    fn new_inhibitor(&mut self, _inhibitor: KeyboardShortcutsInhibitor) {
+        // Don't activate it right away like the default does, its surface may not be focused
+        let focus = self.seat.get_keyboard().and_then(|kb| kb.current_focus());
+        self.update_keyboard_shortcuts_inhibitor(focus.as_ref());
+    }
+
+    fn inhibitor_destroyed(&mut self, inhibitor: KeyboardShortcutsInhibitor) {
+        if self
+            .focus_state
+            .shortcuts_inhibitor
+            .as_ref()
+            .is_some_and(|active| active.wl_surface() == inhibitor.wl_surface())
+        {
+            self.focus_state.shortcuts_inhibitor = None;
+        }
+    }
+}
+delegate_keyboard_shortcuts_inhibit!(State);
+
+impl State {
+    /// Activate the keyboard shortcuts inhibitor of `focused`, if it has one,
+    /// and deactivate the one that was active before.
+    pub fn update_keyboard_shortcuts_inhibitor(&mut self, focused: Option<&FocusTarget>) {
+        let inhibitor = focused
+            .and_then(|focused| focused.wl_surface())
+            .and_then(|surface| self.seat.keyboard_shortcuts_inhibitor_for_surface(&surface));
+
+        let active = &mut self.focus_state.shortcuts_inhibitor;
+        if active.as_ref().map(|active| active.wl_surface())
+            == inhibitor.as_ref().map(|inhibitor| inhibitor.wl_surface())
+        {
+            return;
+        }
+
+        if let Some(old) = active.take() {
+            old.inactivate();
+        }
+        if let Some(inhibitor) = inhibitor.as_ref() {
+            inhibitor.activate();
+        }
+        *active = inhibitor;
+    }
+
+    /// Whether the focused surface is inhibiting keybinds.
+    pub fn keyboard_shortcuts_inhibited(&self) -> bool {
+        self.focus_state
+            .shortcuts_inhibitor
+            .as_ref()
+            .is_some_and(|inhibitor| inhibitor.is_active())
+    }
+}
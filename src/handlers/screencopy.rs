@@ -0,0 +1,689 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! `zwlr_screencopy_manager_v1`, for screenshot tools like grim and recorders like wf-recorder.
+//!
+//! A client asks for a frame of an output or a region of one and is told what buffer to make
+//! for it. Once it hands one over, the copy waits for the output's next render, which draws
+//! the output again into an offscreen buffer and copies that into the client's buffer.
+//!
+//! Every client gets its own offscreen buffer per output, so damage is tracked between the
+//! frames it copied. Copies that ask for damage wait until something on the output changed,
+//! so recorders only get a frame when there's something new and never hold up rendering.
+//!
+//! Shm buffers work for whole outputs and regions. Dmabufs are offered for whole outputs,
+//! and the copy fails if the renderer can't draw into the one the client made.
+//! Frames are copied top to bottom, so they're never y-inverted. A dmabuf frame is only ready
+//! once the GPU is done drawing into it, which the event loop waits for instead of the render.
+//!
+//! Sandboxed clients only see this if the sandbox policy allows screen capture.
+
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use smithay::{
+    backend::{
+        allocator::{dmabuf::Dmabuf, Buffer as _, Fourcc},
+        renderer::{
+            buffer_type, damage::OutputDamageTracker, element::RenderElement, gles::GlesTexture,
+            sync::SyncPoint, Bind, BufferType, ExportMem, ImportAll, ImportMem, Offscreen,
+            Renderer,
+        },
+    },
+    output::Output,
+    reexports::{
+        calloop::{
+            generic::Generic,
+            timer::{TimeoutAction, Timer},
+            Interest, LoopHandle, Mode, PostAction,
+        },
+        wayland_protocols_wlr::screencopy::v1::server::{
+            zwlr_screencopy_frame_v1::{self, ZwlrScreencopyFrameV1},
+            zwlr_screencopy_manager_v1::{self, ZwlrScreencopyManagerV1},
+        },
+        wayland_server::{
+            backend::ClientId,
+            protocol::{wl_buffer::WlBuffer, wl_output::WlOutput, wl_shm},
+            Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+        },
+    },
+    utils::{Buffer as BufferCoords, Logical, Physical, Rectangle, Size, Transform},
+    wayland::{dmabuf::get_dmabuf, shm},
+};
+
+use crate::{
+    handlers::security_context::{self, Capability},
+    render::OutputRenderElements,
+    state::{CalloopData, State},
+};
+
+const VERSION: u32 = 3;
+
+/// The only shm format offered, since every client supports it.
+const SHM_FORMAT: wl_shm::Format = wl_shm::Format::Xrgb8888;
+const FOURCC: Fourcc = Fourcc::Xrgb8888;
+
+#[derive(Debug)]
+pub struct ScreencopyState {
+    /// Copies waiting for their output to render
+    pending: Vec<PendingCopy>,
+    /// The offscreen buffers outputs are drawn into for each client
+    targets: Vec<CaptureTarget>,
+}
+
+/// What a frame is a capture of.
+#[derive(Debug)]
+pub struct FrameData {
+    manager: ZwlrScreencopyManagerV1,
+    /// `None` if the output was gone when the frame was made
+    output: Option<Output>,
+    /// The captured region in the output's buffer coordinates, or `None` if it's empty
+    region: Option<Rectangle<i32, BufferCoords>>,
+    overlay_cursor: bool,
+    /// Whether the client already asked for the frame to be copied
+    copied: AtomicBool,
+}
+
+#[derive(Debug)]
+struct PendingCopy {
+    frame: ZwlrScreencopyFrameV1,
+    buffer: WlBuffer,
+    manager: ZwlrScreencopyManagerV1,
+    output: Output,
+    region: Rectangle<i32, BufferCoords>,
+    overlay_cursor: bool,
+    /// Whether the client wants damage, and so only wants the frame once something changed
+    with_damage: bool,
+}
+
+#[derive(Debug)]
+struct CaptureTarget {
+    manager: ZwlrScreencopyManagerV1,
+    output: Output,
+    overlay_cursor: bool,
+    texture: GlesTexture,
+    damage_tracker: OutputDamageTracker,
+    /// The size of the output's mode the texture was made for
+    size: Size<i32, Physical>,
+    /// The age of the texture's contents, which is 0 until it has been rendered to
+    age: usize,
+}
+
+impl ScreencopyState {
+    /// Advertise `zwlr_screencopy_manager_v1`.
+    pub fn new(display_handle: &DisplayHandle) -> Self {
+        display_handle.create_global::<State, ZwlrScreencopyManagerV1, _>(VERSION, ());
+
+        Self {
+            pending: Vec::new(),
+            targets: Vec::new(),
+        }
+    }
+
+    /// Fail copies waiting on `output` and drop its offscreen buffers now that it's gone.
+    pub fn output_removed(&mut self, output: &Output) {
+        self.pending.retain(|copy| {
+            let keep = &copy.output != output;
+            if !keep {
+                copy.frame.failed();
+            }
+            keep
+        });
+        self.targets.retain(|target| &target.output != output);
+    }
+
+    /// Copy what `output` shows into the buffers of the copies waiting on it,
+    /// with `elements` being what was just rendered to it.
+    ///
+    /// Copies that want damage but have nothing new keep waiting.
+    /// Copies into dmabufs are sent to the client through `loop_handle` once they're drawn.
+    pub fn render_pending<R, E>(
+        &mut self,
+        renderer: &mut R,
+        output: &Output,
+        elements: &[OutputRenderElements<R, E>],
+        clear_color: [f32; 4],
+        time: Duration,
+        loop_handle: &LoopHandle<'static, CalloopData>,
+    ) where
+        R: Renderer
+            + ImportAll
+            + ImportMem
+            + Offscreen<GlesTexture>
+            + Bind<GlesTexture>
+            + Bind<Dmabuf>
+            + ExportMem,
+        <R as Renderer>::TextureId: 'static,
+        E: RenderElement<R>,
+        OutputRenderElements<R, E>: RenderElement<R>,
+    {
+        self.pending.retain(|copy| copy.frame.is_alive());
+
+        let (copies, rest) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition::<Vec<_>, _>(|copy| &copy.output == output);
+        self.pending = rest;
+
+        if copies.is_empty() {
+            return;
+        }
+
+        // Pointer elements, the cursor and the drag and drop icon, come first,
+        // so they're left out by skipping them
+        let cursor_len = elements
+            .iter()
+            .take_while(|element| matches!(element, OutputRenderElements::Pointer(_)))
+            .count();
+
+        for copy in copies {
+            let elements = if copy.overlay_cursor {
+                elements
+            } else {
+                &elements[cursor_len..]
+            };
+
+            match self.copy(renderer, &copy, elements, clear_color) {
+                Ok(Some((damage, None))) => send_ready(&copy, &damage, time),
+                Ok(Some((damage, Some(sync)))) => {
+                    send_ready_when_reached(loop_handle, copy, damage, time, sync)
+                }
+                Ok(None) => self.pending.push(copy),
+                Err(err) => {
+                    tracing::warn!("Failed to copy {} for screencopy: {err}", output.name());
+                    copy.frame.failed();
+                }
+            }
+        }
+    }
+
+    /// Draw `elements` into the client's offscreen buffer for the output and copy them into
+    /// the client's buffer.
+    ///
+    /// Returns the damage since the client's last copy in buffer coordinates relative to the
+    /// region, or `None` if the client wants damage and there is none. Copies into dmabufs
+    /// also return the sync point the GPU reaches once it's done drawing into the dmabuf.
+    #[allow(clippy::type_complexity)]
+    fn copy<R, E>(
+        &mut self,
+        renderer: &mut R,
+        copy: &PendingCopy,
+        elements: &[E],
+        clear_color: [f32; 4],
+    ) -> anyhow::Result<Option<(Vec<Rectangle<i32, BufferCoords>>, Option<SyncPoint>)>>
+    where
+        R: Renderer + Offscreen<GlesTexture> + Bind<GlesTexture> + Bind<Dmabuf> + ExportMem,
+        <R as Renderer>::TextureId: 'static,
+        E: RenderElement<R>,
+    {
+        let output = &copy.output;
+        let size = output
+            .current_mode()
+            .ok_or_else(|| anyhow!("output has no mode"))?
+            .size;
+        let transform = output.current_transform();
+
+        let target = self.target(renderer, copy, size)?;
+
+        renderer
+            .bind(target.texture.clone())
+            .map_err(|err| anyhow!("failed to bind offscreen buffer: {err}"))?;
+        let result = target
+            .damage_tracker
+            .render_output(renderer, target.age, elements, clear_color)
+            .map_err(|err| anyhow!("failed to render: {err:?}"))?;
+        target.age = 1;
+
+        let damage = result
+            .damage
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|rect| {
+                let rect = to_buffer_rect(rect, transform, size);
+                let rect = rect.intersection(copy.region)?;
+                Some(Rectangle::from_loc_and_size(
+                    rect.loc - copy.region.loc,
+                    rect.size,
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        if copy.with_damage && damage.is_empty() {
+            return Ok(None);
+        }
+
+        let sync = match buffer_type(&copy.buffer) {
+            Some(BufferType::Shm) => {
+                let mapping = renderer
+                    .copy_framebuffer(copy.region, FOURCC)
+                    .map_err(|err| anyhow!("failed to read back frame: {err}"))?;
+                let pixels = renderer
+                    .map_texture(&mapping)
+                    .map_err(|err| anyhow!("failed to map frame: {err}"))?;
+                copy_to_shm(&copy.buffer, pixels, copy.region.size)?;
+                None
+            }
+            Some(BufferType::Dma) => {
+                let dmabuf = get_dmabuf(&copy.buffer)
+                    .map(|dmabuf| dmabuf.clone())
+                    .map_err(|err| anyhow!("buffer isn't a dmabuf: {err}"))?;
+                renderer
+                    .bind(dmabuf)
+                    .map_err(|err| anyhow!("failed to bind dmabuf: {err}"))?;
+
+                // The client's dmabuf has none of the offscreen buffer's contents
+                let mut damage_tracker = OutputDamageTracker::from_output(output);
+                let result = damage_tracker
+                    .render_output(renderer, 0, elements, clear_color)
+                    .map_err(|err| anyhow!("failed to render to dmabuf: {err:?}"))?;
+                Some(result.sync)
+            }
+            _ => return Err(anyhow!("unsupported buffer type")),
+        };
+
+        Ok(Some((damage, sync)))
+    }
+
+    /// Get the offscreen buffer `copy`'s client has for its output, making one if it
+    /// doesn't have one or the output's mode changed.
+    fn target<R>(
+        &mut self,
+        renderer: &mut R,
+        copy: &PendingCopy,
+        size: Size<i32, Physical>,
+    ) -> anyhow::Result<&mut CaptureTarget>
+    where
+        R: Renderer + Offscreen<GlesTexture>,
+    {
+        let index = self.targets.iter().position(|target| {
+            target.manager == copy.manager
+                && target.output == copy.output
+                && target.overlay_cursor == copy.overlay_cursor
+        });
+
+        if let Some(index) = index {
+            if self.targets[index].size == size {
+                return Ok(&mut self.targets[index]);
+            }
+            self.targets.remove(index);
+        }
+
+        let texture = Offscreen::<GlesTexture>::create_buffer(
+            renderer,
+            Fourcc::Abgr8888,
+            (size.w, size.h).into(),
+        )
+        .map_err(|err| anyhow!("failed to create offscreen buffer: {err}"))?;
+
+        self.targets.push(CaptureTarget {
+            manager: copy.manager.clone(),
+            output: copy.output.clone(),
+            overlay_cursor: copy.overlay_cursor,
+            texture,
+            damage_tracker: OutputDamageTracker::from_output(&copy.output),
+            size,
+            age: 0,
+        });
+
+        Ok(self.targets.last_mut().expect("target was just pushed"))
+    }
+}
+
+impl State {
+    /// Queue `frame` to be copied into `buffer` on its output's next render.
+    fn queue_screencopy(
+        &mut self,
+        frame: &ZwlrScreencopyFrameV1,
+        data: &FrameData,
+        buffer: WlBuffer,
+        with_damage: bool,
+    ) {
+        if data.copied.swap(true, Ordering::Relaxed) {
+            frame.post_error(
+                zwlr_screencopy_frame_v1::Error::AlreadyUsed,
+                "the frame was already copied",
+            );
+            return;
+        }
+
+        let (Some(output), Some(region)) = (data.output.clone(), data.region) else {
+            frame.failed();
+            return;
+        };
+        if !self.space.outputs().any(|op| op == &output) {
+            frame.failed();
+            return;
+        }
+
+        let whole_output = output.current_mode().is_some_and(|mode| {
+            region.loc == (0, 0).into() && region.size == (mode.size.w, mode.size.h).into()
+        });
+
+        let valid = match buffer_type(&buffer) {
+            Some(BufferType::Shm) => shm::with_buffer_contents(&buffer, |_, _, info| {
+                info.format == SHM_FORMAT
+                    && info.width == region.size.w
+                    && info.height == region.size.h
+                    && info.stride == region.size.w * 4
+            })
+            .unwrap_or(false),
+            Some(BufferType::Dma) => {
+                whole_output
+                    && get_dmabuf(&buffer).is_ok_and(|dmabuf| {
+                        dmabuf.format().code == FOURCC
+                            && dmabuf.width() == region.size.w as u32
+                            && dmabuf.height() == region.size.h as u32
+                    })
+            }
+            _ => false,
+        };
+
+        if !valid {
+            frame.post_error(
+                zwlr_screencopy_frame_v1::Error::InvalidBuffer,
+                "the buffer doesn't match the frame's buffer events",
+            );
+            return;
+        }
+
+        self.screencopy_state.pending.push(PendingCopy {
+            frame: frame.clone(),
+            buffer,
+            manager: data.manager.clone(),
+            output: output.clone(),
+            region,
+            overlay_cursor: data.overlay_cursor,
+            with_damage,
+        });
+        self.schedule_render(&output);
+    }
+}
+
+/// Get `region` of `output`, or all of it if `None`, in the output's buffer coordinates.
+///
+/// Returns `None` if the region is empty or entirely outside of the output.
+fn buffer_region(
+    output: &Output,
+    region: Option<Rectangle<i32, Logical>>,
+) -> Option<Rectangle<i32, BufferCoords>> {
+    let size = output.current_mode()?.size;
+    let transform = output.current_transform();
+
+    let Some(region) = region else {
+        return Some(Rectangle::from_loc_and_size((0, 0), (size.w, size.h)));
+    };
+
+    let output_rect = Rectangle::from_loc_and_size((0, 0), transform.transform_size(size));
+    let rect = region
+        .to_physical_precise_round(output.current_scale().fractional_scale())
+        .intersection(output_rect)
+        .filter(|rect| !rect.is_empty())?;
+
+    Some(to_buffer_rect(rect, transform, size))
+}
+
+/// Turn `rect` on an output with `transform` into the output's buffer coordinates, where
+/// `size` is the size of the output's mode.
+fn to_buffer_rect(
+    rect: Rectangle<i32, Physical>,
+    transform: Transform,
+    size: Size<i32, Physical>,
+) -> Rectangle<i32, BufferCoords> {
+    let rect = transform
+        .invert()
+        .transform_rect_in(rect, &transform.transform_size(size));
+    Rectangle::from_loc_and_size((rect.loc.x, rect.loc.y), (rect.size.w, rect.size.h))
+}
+
+/// Copy tightly packed rows of `pixels` that make up an image of `size` into the shm `buffer`.
+fn copy_to_shm(
+    buffer: &WlBuffer,
+    pixels: &[u8],
+    size: Size<i32, BufferCoords>,
+) -> anyhow::Result<()> {
+    let row_len = size.w as usize * 4;
+    let rows = size.h as usize;
+
+    shm::with_buffer_contents_mut(buffer, |ptr, len, info| {
+        let offset = info.offset as usize;
+        let stride = info.stride as usize;
+
+        if pixels.len() < row_len * rows || offset + stride * (rows - 1) + row_len > len {
+            return Err(anyhow!("shm buffer is too small"));
+        }
+
+        for row in 0..rows {
+            let src = &pixels[row * row_len..][..row_len];
+            // SAFETY: the destination row was checked to be within the buffer above
+            unsafe {
+                std::ptr::copy_nonoverlapping(
+                    src.as_ptr(),
+                    ptr.add(offset + row * stride),
+                    row_len,
+                );
+            }
+        }
+
+        Ok(())
+    })
+    .map_err(|err| anyhow!("failed to access shm buffer: {err}"))?
+}
+
+/// Tell the client its copy is done.
+fn send_ready(copy: &PendingCopy, damage: &[Rectangle<i32, BufferCoords>], time: Duration) {
+    copy.frame.flags(zwlr_screencopy_frame_v1::Flags::empty());
+
+    if copy.with_damage {
+        for rect in damage {
+            copy.frame.damage(
+                rect.loc.x as u32,
+                rect.loc.y as u32,
+                rect.size.w as u32,
+                rect.size.h as u32,
+            );
+        }
+    }
+
+    let secs = time.as_secs();
+    copy.frame
+        .ready((secs >> 32) as u32, secs as u32, time.subsec_nanos());
+}
+
+/// Send `ready` for `copy` once the GPU reached `sync`, so the client doesn't read its dmabuf
+/// before it's drawn.
+///
+/// The event loop waits on the sync point's fence if it can be exported, and otherwise
+/// checks the sync point every millisecond.
+fn send_ready_when_reached(
+    loop_handle: &LoopHandle<'static, CalloopData>,
+    copy: PendingCopy,
+    damage: Vec<Rectangle<i32, BufferCoords>>,
+    time: Duration,
+    sync: SyncPoint,
+) {
+    if sync.is_reached() {
+        send_ready(&copy, &damage, time);
+        return;
+    }
+
+    let result = if let Some(fence) = sync.export() {
+        let source = Generic::new(fence, Interest::READ, Mode::OneShot);
+        loop_handle
+            .insert_source(source, move |_, _, _| {
+                if copy.frame.is_alive() {
+                    send_ready(&copy, &damage, time);
+                }
+                Ok(PostAction::Remove)
+            })
+            .map(|_| ())
+            .map_err(|err| err.error)
+    } else {
+        loop_handle
+            .insert_source(Timer::immediate(), move |_, _, _| {
+                if !sync.is_reached() {
+                    return TimeoutAction::ToDuration(Duration::from_millis(1));
+                }
+                if copy.frame.is_alive() {
+                    send_ready(&copy, &damage, time);
+                }
+                TimeoutAction::Drop
+            })
+            .map(|_| ())
+            .map_err(|err| err.error)
+    };
+
+    if let Err(err) = result {
+        tracing::warn!("Failed to wait for screencopy to finish: {err}");
+    }
+}
+
+/// Make `frame` a capture of `region` of the output, or all of it if `None`, and tell the
+/// client what buffer to make for it.
+fn init_frame(
+    data_init: &mut DataInit<'_, State>,
+    manager: &ZwlrScreencopyManagerV1,
+    frame: New<ZwlrScreencopyFrameV1>,
+    overlay_cursor: i32,
+    wl_output: &WlOutput,
+    region: Option<Rectangle<i32, Logical>>,
+) {
+    let output = Output::from_resource(wl_output);
+    let buffer_region = output
+        .as_ref()
+        .and_then(|output| buffer_region(output, region));
+
+    let frame = data_init.init(
+        frame,
+        FrameData {
+            manager: manager.clone(),
+            output,
+            region: buffer_region,
+            overlay_cursor: overlay_cursor != 0,
+            copied: AtomicBool::new(false),
+        },
+    );
+
+    let Some(buffer_region) = buffer_region else {
+        frame.failed();
+        return;
+    };
+
+    let (width, height) = (buffer_region.size.w as u32, buffer_region.size.h as u32);
+    frame.buffer(SHM_FORMAT, width, height, width * 4);
+
+    if frame.version() >= 3 {
+        // Dmabufs can't be drawn into at an offset, so they're only offered for whole outputs
+        if region.is_none() {
+            frame.linux_dmabuf(FOURCC as u32, width, height);
+        }
+        frame.buffer_done();
+    }
+}
+
+impl GlobalDispatch<ZwlrScreencopyManagerV1, ()> for State {
+    fn bind(
+        _state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwlrScreencopyManagerV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+
+    fn can_view(client: Client, _global_data: &()) -> bool {
+        security_context::client_allows(&client, Capability::ScreenCapture)
+    }
+}
+
+impl Dispatch<ZwlrScreencopyManagerV1, ()> for State {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        manager: &ZwlrScreencopyManagerV1,
+        request: zwlr_screencopy_manager_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            zwlr_screencopy_manager_v1::Request::CaptureOutput {
+                frame,
+                overlay_cursor,
+                output,
+            } => {
+                init_frame(data_init, manager, frame, overlay_cursor, &output, None);
+            }
+            zwlr_screencopy_manager_v1::Request::CaptureOutputRegion {
+                frame,
+                overlay_cursor,
+                output,
+                x,
+                y,
+                width,
+                height,
+            } => {
+                let region = Rectangle::from_loc_and_size((x, y), (width, height));
+                init_frame(
+                    data_init,
+                    manager,
+                    frame,
+                    overlay_cursor,
+                    &output,
+                    Some(region),
+                );
+            }
+            zwlr_screencopy_manager_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut Self,
+        _client: ClientId,
+        manager: &ZwlrScreencopyManagerV1,
+        _data: &(),
+    ) {
+        state
+            .screencopy_state
+            .targets
+            .retain(|target| &target.manager != manager);
+    }
+}
+
+impl Dispatch<ZwlrScreencopyFrameV1, FrameData> for State {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        frame: &ZwlrScreencopyFrameV1,
+        request: zwlr_screencopy_frame_v1::Request,
+        data: &FrameData,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            zwlr_screencopy_frame_v1::Request::Copy { buffer } => {
+                state.queue_screencopy(frame, data, buffer, false);
+            }
+            zwlr_screencopy_frame_v1::Request::CopyWithDamage { buffer } => {
+                state.queue_screencopy(frame, data, buffer, true);
+            }
+            zwlr_screencopy_frame_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut Self,
+        _client: ClientId,
+        frame: &ZwlrScreencopyFrameV1,
+        _data: &FrameData,
+    ) {
+        state
+            .screencopy_state
+            .pending
+            .retain(|copy| &copy.frame != frame);
+    }
+}
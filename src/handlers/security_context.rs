@@ -0,0 +1,218 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! `wp_security_context_manager_v1`, for sandboxes like Flatpak.
+//!
+//! A sandbox engine makes a listening socket for the app it's running and attaches a
+//! security context to it, naming itself and the app. Clients that connect through that
+//! socket are tagged with the context, so privileged globals can be hidden from them and
+//! window rules can match the app id the sandbox vouches for instead of the one the client
+//! says it has.
+//!
+//! Which privileged globals sandboxed clients see is set with the [`SandboxPolicy`].
+//! Sandboxed clients never see the security context manager itself.
+
+use std::{os::unix::net::UnixStream, sync::Mutex};
+
+use smithay::{
+    delegate_security_context,
+    reexports::wayland_server::{Client, Resource},
+    wayland::{
+        seat::WaylandFocus,
+        security_context::{
+            SecurityContext, SecurityContextHandler, SecurityContextListenerSource,
+        },
+    },
+};
+
+use crate::{
+    state::{ClientState, State},
+    window::WindowElement,
+};
+
+/// The policy globals are checked against when sandboxed clients look for them.
+///
+/// Globals only check this when they're advertised, so clients that already bound
+/// a global keep it when the policy changes.
+static SANDBOX_POLICY: Mutex<SandboxPolicy> = Mutex::new(SandboxPolicy::DEFAULT);
+
+/// Which privileged protocols sandboxed clients are allowed to use.
+///
+/// By default, sandboxed clients can't capture the screen, read and set the clipboard
+/// through data control, or set the gamma of outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SandboxPolicy {
+    /// Capturing the contents of outputs with screencopy.
+    pub screen_capture: bool,
+    /// Reading and setting the clipboard without focus with data control.
+    pub data_control: bool,
+    /// Listing and controlling other clients' windows with foreign toplevel management.
+    pub foreign_toplevel: bool,
+    /// Typing into other clients with virtual keyboards.
+    pub virtual_keyboard: bool,
+    /// Acting as an input method for other clients.
+    pub input_method: bool,
+    /// Taking all input away from other clients with the input inhibitor.
+    pub input_inhibitor: bool,
+    /// Setting the gamma ramps of outputs with gamma control.
+    pub gamma_control: bool,
+}
+
+impl SandboxPolicy {
+    const DEFAULT: Self = Self {
+        screen_capture: false,
+        data_control: false,
+        foreign_toplevel: true,
+        virtual_keyboard: true,
+        input_method: true,
+        input_inhibitor: true,
+        gamma_control: false,
+    };
+
+    /// Whether this policy lets sandboxed clients use `capability`.
+    pub fn allows(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::ScreenCapture => self.screen_capture,
+            Capability::DataControl => self.data_control,
+            Capability::ForeignToplevel => self.foreign_toplevel,
+            Capability::VirtualKeyboard => self.virtual_keyboard,
+            Capability::InputMethod => self.input_method,
+            Capability::InputInhibitor => self.input_inhibitor,
+            Capability::GammaControl => self.gamma_control,
+        }
+    }
+}
+
+impl Default for SandboxPolicy {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// A privileged protocol that the [`SandboxPolicy`] can keep from sandboxed clients.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    ScreenCapture,
+    DataControl,
+    ForeignToplevel,
+    VirtualKeyboard,
+    InputMethod,
+    InputInhibitor,
+    GammaControl,
+}
+
+/// Set which privileged protocols sandboxed clients can use from now on.
+pub fn set_sandbox_policy(policy: SandboxPolicy) {
+    tracing::info!("Setting sandbox policy to {policy:?}");
+    *SANDBOX_POLICY.lock().expect("couldn't lock sandbox policy") = policy;
+}
+
+/// Get the security context `client` connected with, if it's sandboxed.
+pub fn client_security_context(client: &Client) -> Option<&SecurityContext> {
+    client
+        .get_data::<ClientState>()
+        .and_then(|client_state| client_state.security_context.as_ref())
+}
+
+/// Whether `client` can use `capability`.
+///
+/// Clients that aren't sandboxed can use everything.
+pub fn client_allows(client: &Client, capability: Capability) -> bool {
+    policy_allows(
+        &SANDBOX_POLICY.lock().expect("couldn't lock sandbox policy"),
+        client_security_context(client).is_some(),
+        capability,
+    )
+}
+
+/// Whether `policy` lets a client that is `sandboxed` or not use `capability`.
+fn policy_allows(policy: &SandboxPolicy, sandboxed: bool, capability: Capability) -> bool {
+    !sandboxed || policy.allows(capability)
+}
+
+impl State {
+    /// Get the app id the sandbox that `window`'s client is in gave it, if it's sandboxed.
+    pub fn window_sandbox_app_id(&self, window: &WindowElement) -> Option<String> {
+        let client = window.wl_surface()?.client()?;
+        client_security_context(&client)?.app_id.clone()
+    }
+}
+
+impl SecurityContextHandler for State {
+    fn context_created(
+        &mut self,
+        source: SecurityContextListenerSource,
+        security_context: SecurityContext,
+    ) {
+        tracing::info!(
+            "Sandbox {} created a security context for {}",
+            security_context
+                .sandbox_engine
+                .as_deref()
+                .unwrap_or("<unknown>"),
+            security_context.app_id.as_deref().unwrap_or("<unknown>"),
+        );
+
+        let res = self
+            .loop_handle
+            .insert_source(source, move |stream: UnixStream, _, data| {
+                data.state
+                    .insert_client(stream, Some(security_context.clone()));
+            });
+
+        if let Err(err) = res {
+            tracing::error!("Failed to insert security context listener into loop: {err}");
+        }
+    }
+}
+delegate_security_context!(State);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Capability; 7] = [
+        Capability::ScreenCapture,
+        Capability::DataControl,
+        Capability::ForeignToplevel,
+        Capability::VirtualKeyboard,
+        Capability::InputMethod,
+        Capability::InputInhibitor,
+        Capability::GammaControl,
+    ];
+
+    #[test]
+    fn sandboxed_clients_cant_capture_by_default() {
+        let policy = SandboxPolicy::default();
+        assert!(!policy_allows(&policy, true, Capability::ScreenCapture));
+        assert!(!policy_allows(&policy, true, Capability::DataControl));
+        assert!(!policy_allows(&policy, true, Capability::GammaControl));
+        assert!(policy_allows(&policy, true, Capability::ForeignToplevel));
+    }
+
+    #[test]
+    fn unsandboxed_clients_can_use_everything() {
+        let policy = SandboxPolicy {
+            screen_capture: false,
+            data_control: false,
+            foreign_toplevel: false,
+            virtual_keyboard: false,
+            input_method: false,
+            input_inhibitor: false,
+            gamma_control: false,
+        };
+        for capability in ALL {
+            assert!(policy_allows(&policy, false, capability), "{capability:?}");
+        }
+    }
+
+    #[test]
+    fn policy_can_allow_capture() {
+        let policy = SandboxPolicy {
+            screen_capture: true,
+            ..Default::default()
+        };
+        assert!(policy_allows(&policy, true, Capability::ScreenCapture));
+        assert!(!policy_allows(&policy, true, Capability::DataControl));
+    }
+}
@@ -5,16 +5,12 @@ use smithay::{
         PopupPointerGrab, PopupUngrabStrategy, Window, WindowSurfaceType,
     },
     input::{pointer::Focus, Seat},
-    output::Output,
     reexports::{
         wayland_protocols::xdg::shell::server::{
             xdg_positioner::{Anchor, ConstraintAdjustment, Gravity},
             xdg_toplevel::{self, ResizeEdge},
         },
-        wayland_server::{
-            protocol::{wl_output::WlOutput, wl_seat::WlSeat, wl_surface::WlSurface},
-            Resource,
-        },
+        wayland_server::protocol::{wl_output::WlOutput, wl_seat::WlSeat, wl_surface::WlSurface},
     },
     utils::{Logical, Point, Rectangle, Serial, SERIAL_COUNTER},
     wayland::{
@@ -27,9 +23,10 @@ use smithay::{
 };
 
 use crate::{
+    disconnects::with_client_state,
     focus::FocusTarget,
     state::{State, WithState},
-    window::{window_state::LocationRequestState, WindowElement},
+    window::{state_request::RequestedState, WindowElement},
 };
 
 impl XdgShellHandler for State {
@@ -47,6 +44,11 @@ impl XdgShellHandler for State {
 
         let window = WindowElement::Wayland(Window::new(surface.clone()));
 
+        let window_id = window.with_state(|state| state.id);
+        with_client_state(surface.wl_surface(), |client_state| {
+            client_state.window_opened(window_id);
+        });
+
         if let (Some(output), _) | (None, Some(output)) = (
             &self.focus_state.focused_output,
             self.space.outputs().next(),
@@ -58,7 +60,7 @@ impl XdgShellHandler for State {
         // TODO: fix it so that reordering this doesn't break stuff
         self.windows.push(window.clone());
 
-        self.space.map_element(window.clone(), (0, 0), true);
+        self.space.map_element(window.clone(), (0, 0), false);
 
         let win_clone = window.clone();
 
@@ -84,10 +86,25 @@ impl XdgShellHandler for State {
             },
             |data| {
                 data.state.apply_window_rules(&window);
+                data.state.resolve_window_wait(&window);
+                data.state.signal_window_opened(&window);
 
-                if let Some(focused_output) = data.state.focus_state.focused_output.clone() {
-                    data.state.update_windows(&focused_output);
+                let focused_output = data.state.focus_state.focused_output.clone();
+                if let Some(focused_output) = focused_output.as_ref() {
+                    data.state.update_windows(focused_output);
                 }
+                // Rules and remembered placements can put the window on another output
+                if let Some(output) = window
+                    .output(&data.state)
+                    .filter(|output| Some(output) != focused_output.as_ref())
+                {
+                    data.state.update_windows(&output);
+                }
+                // Don't steal focus while in do not disturb mode
+                if data.state.do_not_disturb.enabled {
+                    return;
+                }
+
                 data.state.loop_handle.insert_idle(move |data| {
                     data.state
                         .seat
@@ -105,6 +122,14 @@ impl XdgShellHandler for State {
 
     fn toplevel_destroyed(&mut self, surface: ToplevelSurface) {
         tracing::debug!("toplevel destroyed");
+
+        if let Some(window) = self.window_for_surface(surface.wl_surface()) {
+            let window_id = window.with_state(|state| state.id);
+            with_client_state(surface.wl_surface(), |client_state| {
+                client_state.window_closed(window_id);
+            });
+            self.signal_window_closed(&window);
+        }
         self.windows.retain(|window| {
             window
                 .wl_surface()
@@ -125,10 +150,7 @@ impl XdgShellHandler for State {
             let focus = self.focused_window(&output).map(FocusTarget::Window);
             if let Some(FocusTarget::Window(win)) = &focus {
                 tracing::debug!("Focusing on prev win");
-                self.space.raise_element(win, true);
-                if let WindowElement::Wayland(win) = &win {
-                    win.toplevel().send_configure();
-                }
+                self.raise_window(win);
             }
             self.seat
                 .get_keyboard()
@@ -661,6 +683,12 @@ impl XdgShellHandler for State {
         );
     }
 
+    fn popup_destroyed(&mut self, _surface: PopupSurface) {
+        // The popup is still alive here, so check on the grab once it's gone
+        self.loop_handle
+            .insert_idle(|data| data.state.update_popup_grab());
+    }
+
     fn reposition_request(
         &mut self,
         surface: PopupSurface,
@@ -691,9 +719,9 @@ impl XdgShellHandler for State {
                     })
                 })
         }) {
-            if let Ok(mut grab) = self
-                .popup_manager
-                .grab_popup(root, popup_kind, &seat, serial)
+            if let Ok(mut grab) =
+                self.popup_manager
+                    .grab_popup(root.clone(), popup_kind, &seat, serial)
             {
                 if let Some(keyboard) = seat.get_keyboard() {
                     if keyboard.is_grabbed()
@@ -718,32 +746,27 @@ impl XdgShellHandler for State {
                     }
                     pointer.set_grab(self, PopupPointerGrab::new(&grab), serial, Focus::Keep);
                 }
+
+                self.start_popup_grab(grab, root);
             }
         }
     }
 
     fn ack_configure(&mut self, surface: WlSurface, configure: Configure) {
+        let Configure::Toplevel(configure) = configure else {
+            return;
+        };
+
         if let Some(window) = self.window_for_surface(&surface) {
-            if let LocationRequestState::Requested(serial, new_loc) =
-                window.with_state(|state| state.loc_request_state.clone())
-            {
-                match &configure {
-                    Configure::Toplevel(configure) => {
-                        if configure.serial >= serial {
-                            tracing::debug!("acked configure, new loc is {:?}", new_loc);
-                            window.with_state(|state| {
-                                state.loc_request_state =
-                                    LocationRequestState::Acknowledged(new_loc);
-                            });
-                        }
-                    }
-                    Configure::Popup(_) => todo!(),
+            window.with_state(|state| {
+                if let Some(new_loc) = state.loc_request_state.ack(configure.serial) {
+                    tracing::debug!("acked configure, new loc is {:?}", new_loc);
                 }
-            }
+            });
         }
     }
 
-    fn fullscreen_request(&mut self, surface: ToplevelSurface, mut wl_output: Option<WlOutput>) {
+    fn fullscreen_request(&mut self, surface: ToplevelSurface, wl_output: Option<WlOutput>) {
         if !surface
             .current_state()
             .capabilities
@@ -752,46 +775,13 @@ impl XdgShellHandler for State {
             return;
         }
 
-        let wl_surface = surface.wl_surface();
-        let output = wl_output
-            .as_ref()
-            .and_then(Output::from_resource)
-            .or_else(|| {
-                self.window_for_surface(wl_surface)
-                    .and_then(|window| self.space.outputs_for_element(&window).get(0).cloned())
-            });
-
-        if let Some(output) = output {
-            let Some(geometry) = self.space.output_geometry(&output) else {
-                surface.send_configure();
-                return;
-            };
-
-            let client = self
-                .display_handle
-                .get_client(wl_surface.id())
-                .expect("wl_surface had no client");
-            for output in output.client_outputs(&client) {
-                wl_output = Some(output);
-            }
-
-            surface.with_pending_state(|state| {
-                state.states.set(xdg_toplevel::State::Fullscreen);
-                state.size = Some(geometry.size);
-                state.fullscreen_output = wl_output;
-            });
-
-            let Some(window) = self.window_for_surface(wl_surface) else {
-                tracing::error!("wl_surface had no window");
-                return;
-            };
-
-            if !window.with_state(|state| state.fullscreen_or_maximized.is_fullscreen()) {
-                window.toggle_fullscreen();
-            }
-        }
+        let Some(window) = self.window_for_surface(surface.wl_surface()) else {
+            tracing::error!("wl_surface had no window");
+            surface.send_configure();
+            return;
+        };
 
-        surface.send_configure();
+        self.request_window_state(&window, RequestedState::Fullscreen, wl_output);
     }
 
     fn unfullscreen_request(&mut self, surface: ToplevelSurface) {
@@ -816,9 +806,7 @@ impl XdgShellHandler for State {
             return;
         };
 
-        if window.with_state(|state| state.fullscreen_or_maximized.is_fullscreen()) {
-            window.toggle_fullscreen();
-        }
+        window.set_fullscreen(false);
     }
 
     fn maximize_request(&mut self, surface: ToplevelSurface) {
@@ -826,12 +814,7 @@ impl XdgShellHandler for State {
             return;
         };
 
-        if !window.with_state(|state| state.fullscreen_or_maximized.is_maximized()) {
-            window.toggle_maximized();
-        }
-
-        let Some(output) = window.output(self) else { return };
-        self.update_windows(&output);
+        self.request_window_state(&window, RequestedState::Maximized, None);
     }
 
     fn unmaximize_request(&mut self, surface: ToplevelSurface) {
@@ -839,19 +822,17 @@ impl XdgShellHandler for State {
             return;
         };
 
-        if window.with_state(|state| state.fullscreen_or_maximized.is_maximized()) {
-            window.toggle_maximized();
-        }
+        window.set_maximized(false);
 
         let Some(output) = window.output(self) else { return };
         self.update_windows(&output);
     }
 
-    fn minimize_request(&mut self, _surface: ToplevelSurface) {
-        // TODO:
-        // if let Some(window) = self.window_for_surface(surface.wl_surface()) {
-        //     self.space.unmap_elem(&window);
-        // }
+    fn minimize_request(&mut self, surface: ToplevelSurface) {
+        // TODO: actually minimize; for now this only lets the config react to the request
+        if let Some(window) = self.window_for_surface(surface.wl_surface()) {
+            self.request_window_state(&window, RequestedState::Minimized, None);
+        }
     }
 
     // TODO: impl the rest of the fns in XdgShellHandler
@@ -24,7 +24,7 @@ use smithay::{
 use crate::{
     focus::FocusTarget,
     state::{CalloopData, WithState},
-    window::{window_state::FloatingOrTiled, WindowElement},
+    window::{state_request::RequestedState, window_state::FloatingOrTiled, WindowElement},
 };
 
 impl XwmHandler for CalloopData {
@@ -42,7 +42,10 @@ impl XwmHandler for CalloopData {
         assert!(!window.is_override_redirect());
 
         let window = WindowElement::X11(window);
-        self.state.space.map_element(window.clone(), (0, 0), true);
+        // Window rules need the class right away
+        self.state.update_window_title_and_class(&window);
+        self.state.watch_x11_shape(&window);
+        self.state.space.map_element(window.clone(), (0, 0), false);
         let bbox = self
             .state
             .space
@@ -79,7 +82,7 @@ impl XwmHandler for CalloopData {
             unreachable!()
         };
 
-        self.state.space.map_element(window.clone(), loc, true);
+        self.state.space.map_element(window.clone(), loc, false);
         surface.set_mapped(true).expect("failed to map x11 window");
 
         let bbox = Rectangle::from_loc_and_size(loc, bbox.size);
@@ -105,14 +108,27 @@ impl XwmHandler for CalloopData {
 
         self.state.windows.push(window.clone());
 
-        self.state.focus_state.set_focus(window.clone());
+        // Don't steal focus while in do not disturb mode
+        let steal_focus = !self.state.do_not_disturb.enabled;
+
+        if steal_focus {
+            self.state.focus_state.set_focus(window.clone());
+        } else {
+            self.state.focus_state.focus_stack.insert(0, window.clone());
+        }
 
         self.state.apply_window_rules(&window);
+        self.state.resolve_window_wait(&window);
+        self.state.signal_window_opened(&window);
 
         if let Some(output) = window.output(&self.state) {
             self.state.update_windows(&output);
         }
 
+        if !steal_focus {
+            return;
+        }
+
         self.state.loop_handle.insert_idle(move |data| {
             data.state
                     .seat
@@ -134,6 +150,8 @@ impl XwmHandler for CalloopData {
         let loc = window.geometry().loc;
 
         let window = WindowElement::X11OverrideRedirect(window);
+        self.state.update_window_title_and_class(&window);
+        self.state.watch_x11_shape(&window);
         self.state.windows.push(window.clone());
 
         if let (Some(output), _) | (None, Some(output)) = (
@@ -143,7 +161,7 @@ impl XwmHandler for CalloopData {
             window.place_on_output(output);
         }
 
-        self.state.space.map_element(window.clone(), loc, true);
+        self.state.space.map_element(window.clone(), loc, false);
         self.state.focus_state.set_focus(window);
     }
 
@@ -169,10 +187,7 @@ impl XwmHandler for CalloopData {
                 let focus = self.state.focused_window(&output).map(FocusTarget::Window);
 
                 if let Some(FocusTarget::Window(win)) = &focus {
-                    self.state.space.raise_element(win, true);
-                    if let WindowElement::Wayland(win) = &win {
-                        win.toplevel().send_configure();
-                    }
+                    self.state.raise_window(win);
                 }
 
                 self.state
@@ -214,6 +229,8 @@ impl XwmHandler for CalloopData {
         if let Some(win) = win {
             tracing::debug!("removing x11 window from windows");
 
+            self.state.signal_window_closed(&win);
+
             // INFO: comparing the windows doesn't work so wlsurface it is
             // self.state.windows.retain(|elem| &win != elem);
             self.state
@@ -226,10 +243,7 @@ impl XwmHandler for CalloopData {
                 let focus = self.state.focused_window(&output).map(FocusTarget::Window);
 
                 if let Some(FocusTarget::Window(win)) = &focus {
-                    self.state.space.raise_element(win, true);
-                    if let WindowElement::Wayland(win) = &win {
-                        win.toplevel().send_configure();
-                    }
+                    self.state.raise_window(win);
                 }
 
                 self.state
@@ -284,14 +298,10 @@ impl XwmHandler for CalloopData {
             return;
         };
 
-        self.state.space.map_element(win, geometry.loc, true);
+        self.state.space.map_element(win, geometry.loc, false);
     }
 
     fn maximize_request(&mut self, _xwm: XwmId, window: X11Surface) {
-        window
-            .set_maximized(true)
-            .expect("failed to set x11 win to maximized");
-
         let Some(window) = window
             .wl_surface()
             .and_then(|surf| self.state.window_for_surface(&surf))
@@ -299,9 +309,8 @@ impl XwmHandler for CalloopData {
             return;
         };
 
-        if !window.with_state(|state| state.fullscreen_or_maximized.is_maximized()) {
-            window.toggle_maximized();
-        }
+        self.state
+            .request_window_state(&window, RequestedState::Maximized, None);
     }
 
     fn unmaximize_request(&mut self, _xwm: XwmId, window: X11Surface) {
@@ -316,16 +325,10 @@ impl XwmHandler for CalloopData {
             return;
         };
 
-        if window.with_state(|state| state.fullscreen_or_maximized.is_maximized()) {
-            window.toggle_maximized();
-        }
+        window.set_maximized(false);
     }
 
     fn fullscreen_request(&mut self, _xwm: XwmId, window: X11Surface) {
-        window
-            .set_fullscreen(true)
-            .expect("failed to set x11 win to fullscreen");
-
         let Some(window) = window
             .wl_surface()
             .and_then(|surf| self.state.window_for_surface(&surf))
@@ -333,9 +336,8 @@ impl XwmHandler for CalloopData {
             return;
         };
 
-        if !window.with_state(|state| state.fullscreen_or_maximized.is_fullscreen()) {
-            window.toggle_fullscreen();
-        }
+        self.state
+            .request_window_state(&window, RequestedState::Fullscreen, None);
     }
 
     fn unfullscreen_request(&mut self, _xwm: XwmId, window: X11Surface) {
@@ -350,9 +352,7 @@ impl XwmHandler for CalloopData {
             return;
         };
 
-        if window.with_state(|state| state.fullscreen_or_maximized.is_fullscreen()) {
-            window.toggle_fullscreen();
-        }
+        window.set_fullscreen(false);
     }
 
     fn resize_request(
@@ -0,0 +1,195 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! `zwp_xwayland_keyboard_grab_manager_v1`, which Xwayland uses to tell Pinnacle that an X11
+//! client like a game or VirtualBox grabbed the keyboard.
+//!
+//! A grab is honored while its surface has keyboard focus. Keys still go through the keyboard
+//! filter before they're forwarded to Xwayland, but only the reserved keybinds work, see
+//! [`KeybindSuppression`][crate::input::suppression::KeybindSuppression]. Under
+//! `[x11_keyboard_grab]` in `metaconfig.toml`, `keep_keybinds = true` keeps every keybind working
+//! instead, and `break_keybind` breaks the grab until the X11 client grabs the keyboard again.
+//!
+//! Only Xwayland sees this global, and only grabs of surfaces of the xwm's windows are honored.
+
+use smithay::{
+    reexports::{
+        wayland_protocols::xwayland::keyboard_grab::zv1::server::{
+            zwp_xwayland_keyboard_grab_manager_v1::{self, ZwpXwaylandKeyboardGrabManagerV1},
+            zwp_xwayland_keyboard_grab_v1::{self, ZwpXwaylandKeyboardGrabV1},
+        },
+        wayland_server::{
+            backend::ClientId, protocol::wl_surface::WlSurface, Client, DataInit, Dispatch,
+            DisplayHandle, GlobalDispatch, New,
+        },
+    },
+    wayland::seat::WaylandFocus,
+    xwayland::XWaylandClientData,
+};
+use xkbcommon::xkb::Keysym;
+
+use crate::{api::msg::ModifierMask, state::State, window::WindowElement};
+
+const VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub struct XwaylandKeyboardGrabState {
+    /// Grabs Xwayland holds
+    grabs: Vec<XwaylandKeyboardGrab>,
+    /// Whether every keybind keeps working during a grab, not just the reserved ones
+    pub keep_keybinds: bool,
+    /// The keybind that breaks the grab of the focused window
+    pub break_keybind: Option<(ModifierMask, Keysym)>,
+}
+
+#[derive(Debug)]
+struct XwaylandKeyboardGrab {
+    grab: ZwpXwaylandKeyboardGrabV1,
+    surface: WlSurface,
+    /// Whether the break keybind was pressed during this grab
+    broken: bool,
+}
+
+impl XwaylandKeyboardGrabState {
+    /// Advertise `zwp_xwayland_keyboard_grab_manager_v1` to Xwayland.
+    pub fn new(display_handle: &DisplayHandle) -> Self {
+        display_handle.create_global::<State, ZwpXwaylandKeyboardGrabManagerV1, _>(VERSION, ());
+
+        Self {
+            grabs: Vec::new(),
+            keep_keybinds: false,
+            break_keybind: None,
+        }
+    }
+}
+
+impl State {
+    fn keyboard_focus_surface(&self) -> Option<WlSurface> {
+        self.seat.get_keyboard()?.current_focus()?.wl_surface()
+    }
+
+    /// Whether an X11 client has grabbed the keyboard and its window has keyboard focus.
+    ///
+    /// Broken grabs don't count.
+    pub fn xwayland_keyboard_grabbed(&self) -> bool {
+        let Some(focus) = self.keyboard_focus_surface() else {
+            return false;
+        };
+
+        self.xwayland_keyboard_grab_state
+            .grabs
+            .iter()
+            .any(|grab| grab.surface == focus && !grab.broken)
+    }
+
+    /// Break the grab of the window with keyboard focus, so keybinds work again
+    /// until its client grabs the keyboard again.
+    pub fn break_xwayland_keyboard_grab(&mut self) {
+        let Some(focus) = self.keyboard_focus_surface() else {
+            return;
+        };
+
+        for grab in self.xwayland_keyboard_grab_state.grabs.iter_mut() {
+            if grab.surface == focus && !grab.broken {
+                tracing::info!("Breaking X11 keyboard grab");
+                grab.broken = true;
+            }
+        }
+    }
+
+    fn remove_xwayland_keyboard_grab(&mut self, grab: &ZwpXwaylandKeyboardGrabV1) {
+        self.xwayland_keyboard_grab_state
+            .grabs
+            .retain(|other| &other.grab != grab);
+    }
+}
+
+impl GlobalDispatch<ZwpXwaylandKeyboardGrabManagerV1, ()> for State {
+    fn bind(
+        _state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwpXwaylandKeyboardGrabManagerV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+
+    fn can_view(client: Client, _global_data: &()) -> bool {
+        client.get_data::<XWaylandClientData>().is_some()
+    }
+}
+
+impl Dispatch<ZwpXwaylandKeyboardGrabManagerV1, ()> for State {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        _manager: &ZwpXwaylandKeyboardGrabManagerV1,
+        request: zwp_xwayland_keyboard_grab_manager_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            zwp_xwayland_keyboard_grab_manager_v1::Request::GrabKeyboard {
+                id, surface, ..
+            } => {
+                let grab = data_init.init(id, ());
+
+                // Xwayland only grabs for its own surfaces, but the window also needs to be
+                // one the xwm manages for the grab to mean anything
+                let Some(WindowElement::X11(x11_surface)) = state.window_for_surface(&surface)
+                else {
+                    tracing::debug!("Ignoring X11 keyboard grab of an unmanaged surface");
+                    return;
+                };
+
+                tracing::info!("X11 window {:?} grabbed the keyboard", x11_surface.class());
+
+                // Xwayland makes a new grab each time the client grabs, so a grab the break
+                // keybind broke only lasts until then
+                state
+                    .xwayland_keyboard_grab_state
+                    .grabs
+                    .retain(|other| other.surface != surface);
+                state
+                    .xwayland_keyboard_grab_state
+                    .grabs
+                    .push(XwaylandKeyboardGrab {
+                        grab,
+                        surface,
+                        broken: false,
+                    });
+            }
+            zwp_xwayland_keyboard_grab_manager_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Dispatch<ZwpXwaylandKeyboardGrabV1, ()> for State {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _grab: &ZwpXwaylandKeyboardGrabV1,
+        request: zwp_xwayland_keyboard_grab_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            zwp_xwayland_keyboard_grab_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut Self,
+        _client: ClientId,
+        grab: &ZwpXwaylandKeyboardGrabV1,
+        _data: &(),
+    ) {
+        tracing::info!("X11 keyboard grab released");
+        state.remove_xwayland_keyboard_grab(grab);
+    }
+}
@@ -1,19 +1,32 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+pub mod bind_layer;
+pub mod cursor_warp;
+pub mod double_click;
+pub mod gesture;
 pub mod libinput;
+pub mod media_keys;
+pub mod pointer_barrier;
+pub mod prompt;
+pub mod reload_queue;
+pub mod repeat;
+pub mod scroll;
+pub mod suppression;
+pub mod touch;
 
 use std::collections::HashMap;
 
 use crate::{
-    api::msg::{CallbackId, Modifier, ModifierMask, MouseEdge, OutgoingMsg},
+    api::msg::{Args, CallbackId, KeyEdge, ModifierMask, MouseEdge, OutgoingMsg, ScrollDirection},
     focus::FocusTarget,
     state::WithState,
     window::WindowElement,
 };
 use smithay::{
     backend::input::{
-        AbsolutePositionEvent, Axis, AxisSource, ButtonState, Event, InputBackend, InputEvent,
-        KeyState, KeyboardKeyEvent, PointerAxisEvent, PointerButtonEvent, PointerMotionEvent,
+        AbsolutePositionEvent, Axis, AxisSource, ButtonState, Device, Event, InputBackend,
+        InputEvent, KeyState, KeyboardKeyEvent, PointerAxisEvent, PointerButtonEvent,
+        PointerMotionEvent,
     },
     desktop::{layer_map_for_output, space::SpaceElement},
     input::{
@@ -21,42 +34,160 @@ use smithay::{
         pointer::{AxisFrame, ButtonEvent, MotionEvent, RelativeMotionEvent},
     },
     reexports::input::{self, Led},
-    utils::{Logical, Point, SERIAL_COUNTER},
+    utils::{Logical, Point, Rectangle, SERIAL_COUNTER},
     wayland::{seat::WaylandFocus, shell::wlr_layer},
 };
 use xkbcommon::xkb::Keysym;
 
 use crate::state::State;
 
-use self::libinput::LibinputSetting;
+use self::{
+    bind_layer::BindLayerState,
+    cursor_warp::CursorWarpState,
+    double_click::DoubleClickState,
+    gesture::GestureState,
+    libinput::DeviceSetting,
+    media_keys::MediaKeys,
+    pointer_barrier::PointerBarrier,
+    prompt::PromptState,
+    reload_queue::{QueuedKeybind, ReloadKeybindQueue},
+    repeat::KeyRepeatState,
+    scroll::ScrollState,
+    touch::TouchState,
+};
 
 #[derive(Default, Debug)]
 pub struct InputState {
-    /// A hashmap of modifier keys and keycodes to callback IDs
-    pub keybinds: HashMap<(ModifierMask, Keysym), CallbackId>,
+    /// A hashmap of keybind layers, modifier keys, keycodes, and key edges to keybinds
+    pub keybinds: HashMap<KeybindKey, Keybind>,
+    /// The active keybind layer
+    pub bind_layer: BindLayerState,
     /// A hashmap of modifier keys and mouse button codes to callback IDs
     pub mousebinds: HashMap<(ModifierMask, u32, MouseEdge), CallbackId>,
+    /// A hashmap of modifier keys and scroll directions to callback IDs
+    pub scrollbinds: HashMap<(ModifierMask, ScrollDirection), CallbackId>,
     pub reload_keybind: Option<(ModifierMask, Keysym)>,
     pub kill_keybind: Option<(ModifierMask, Keysym)>,
-    /// User defined libinput settings that will be applied
-    pub libinput_settings: Vec<LibinputSetting>,
+    /// User defined libinput settings that will be applied, along with the devices they apply to
+    pub libinput_settings: Vec<DeviceSetting>,
     /// All libinput devices that have been connected
     pub libinput_devices: Vec<input::Device>,
+    /// Built-in media key handlers, used when the config doesn't bind the key itself
+    pub media_keys: MediaKeys,
+    /// Output locking and edge resistance for the pointer
+    pub pointer_barrier: PointerBarrier,
+    /// Whether the pointer warps to windows the config focuses
+    pub cursor_warp: CursorWarpState,
+    /// Scroll speed and inversion
+    pub scroll: ScrollState,
+    /// The double-click interval and the last button press
+    pub double_click: DoubleClickState,
+    /// Touchpad gesture binds and the gesture in progress
+    pub gesture: GestureState,
+    /// Touchscreen long-press settings and the fingers that are down
+    pub touch: TouchState,
+    /// The keyboard's repeat settings and the repeating keybind being held
+    pub key_repeat: KeyRepeatState,
+    /// Keybinds pressed while the config reloads
+    pub reload_queue: ReloadKeybindQueue,
+    /// The prompt taking key presses, if one is open
+    pub prompt: PromptState,
+    /// The lock leds last sent to keyboards
+    leds: Option<Led>,
 }
 
 impl InputState {
     pub fn new() -> Self {
-        Default::default()
+        Self::default()
     }
 }
 
+/// What identifies a keybind: its layer, or `None` for the default layer,
+/// its modifiers, its keysym, and its edge.
+pub type KeybindKey = (Option<String>, ModifierMask, Keysym, KeyEdge);
+
+/// A keybind set by the config.
+#[derive(Debug)]
+pub struct Keybind {
+    pub callback_id: CallbackId,
+    /// What kind of keybind this is, for listing keybinds.
+    pub group: Option<String>,
+    /// What this keybind does, for listing keybinds.
+    pub description: Option<String>,
+    /// Whether the key is still sent to the focused window after calling the callback
+    pub passthrough: bool,
+    /// Whether this keybind triggers even while the focused surface inhibits shortcuts
+    /// or has grabbed the keyboard
+    pub force: bool,
+    /// Whether the callback is called again while the key is held
+    pub repeats: bool,
+    /// Data the config attached to the keybind, sent back with its callback
+    pub data: Option<Vec<u8>>,
+}
+
 #[derive(Debug)]
 enum KeyAction {
-    /// Call a callback from a config process
-    CallCallback(CallbackId),
+    /// Call a callback from a config process with the keybind's data
+    CallCallback(CallbackId, Option<Vec<u8>>),
+    /// Call a callback from a config process and keep calling it while the key is held
+    CallRepeatingCallback(CallbackId, Option<Vec<u8>>),
     Quit,
     SwitchVt(i32),
     ReloadConfig,
+    /// Break the X11 keyboard grab of the focused window
+    BreakX11Grab,
+    /// Spawn a built-in media key command
+    SpawnMediaCommand(Vec<String>),
+    /// Hold a keybind press until the new config is ready
+    QueueForReload(QueuedKeybind),
+    /// Cancel the window move or resize going on
+    CancelGrab,
+    /// Type a key into the open prompt, with whether it can type a character
+    PromptKey(Keysym, bool),
+    /// Don't send the key to the client
+    Suppress,
+}
+
+impl InputState {
+    /// Get the keybind in the active layer with `modifier_mask` and `keysym`
+    /// that triggers on `edge`, including keybinds that trigger on both edges.
+    fn keybind(
+        &self,
+        modifier_mask: ModifierMask,
+        keysym: Keysym,
+        edge: KeyEdge,
+    ) -> Option<&Keybind> {
+        let layer = &self.bind_layer.active;
+        self.keybinds
+            .get(&(layer.clone(), modifier_mask, keysym, edge))
+            .or_else(|| {
+                self.keybinds
+                    .get(&(layer.clone(), modifier_mask, keysym, KeyEdge::Both))
+            })
+    }
+
+    /// Get the built-in media key command a key press runs.
+    ///
+    /// Media keys come after keybinds so the config can override them. A key bound in the
+    /// active layer on either edge, with or without its modifiers applied, has no command.
+    fn media_command(
+        &self,
+        modifier_mask: ModifierMask,
+        mod_sym: Keysym,
+        raw_sym: Option<Keysym>,
+    ) -> Option<&Vec<String>> {
+        let bound = [KeyEdge::Press, KeyEdge::Release].into_iter().any(|edge| {
+            self.keybind(modifier_mask, mod_sym, edge).is_some()
+                || raw_sym
+                    .is_some_and(|raw_sym| self.keybind(modifier_mask, raw_sym, edge).is_some())
+        });
+
+        if bound {
+            return None;
+        }
+
+        self.media_keys.command_for_press(mod_sym, raw_sym)
+    }
 }
 
 impl State {
@@ -64,13 +195,26 @@ impl State {
         match event {
             // TODO: rest of input events
 
-            // InputEvent::DeviceAdded { device } => todo!(),
-            // InputEvent::DeviceRemoved { device } => todo!(),
+            // Devices are only tracked on the udev backend, where this needs the libinput
+            // device, so added and removed devices are handled in `handle_libinput_device_event`.
+            InputEvent::DeviceAdded { .. } | InputEvent::DeviceRemoved { .. } => (),
             InputEvent::Keyboard { event } => self.keyboard::<B>(event),
             InputEvent::PointerMotion { event } => self.pointer_motion::<B>(event),
             InputEvent::PointerMotionAbsolute { event } => self.pointer_motion_absolute::<B>(event),
             InputEvent::PointerButton { event } => self.pointer_button::<B>(event),
             InputEvent::PointerAxis { event } => self.pointer_axis::<B>(event),
+            InputEvent::GestureSwipeBegin { event } => self.gesture_swipe_begin::<B>(event),
+            InputEvent::GestureSwipeUpdate { event } => self.gesture_swipe_update::<B>(event),
+            InputEvent::GestureSwipeEnd { event } => self.gesture_swipe_end::<B>(event),
+            InputEvent::GesturePinchBegin { event } => self.gesture_pinch_begin::<B>(event),
+            InputEvent::GesturePinchUpdate { event } => self.gesture_pinch_update::<B>(event),
+            InputEvent::GesturePinchEnd { event } => self.gesture_pinch_end::<B>(event),
+            InputEvent::GestureHoldBegin { event } => self.gesture_hold_begin::<B>(event),
+            InputEvent::GestureHoldEnd { event } => self.gesture_hold_end::<B>(event),
+            InputEvent::TouchDown { event } => self.touch_down::<B>(event),
+            InputEvent::TouchMotion { event } => self.touch_motion::<B>(event),
+            InputEvent::TouchUp { event } => self.touch_up::<B>(event),
+            InputEvent::TouchCancel { event } => self.touch_cancel::<B>(event),
 
             _ => (),
         }
@@ -78,6 +222,18 @@ impl State {
 
     /// Get the [`FocusTarget`] under `point`.
     pub fn surface_under<P>(&self, point: P) -> Option<(FocusTarget, Point<i32, Logical>)>
+    where
+        P: Into<Point<f64, Logical>>,
+    {
+        self.surface_under_except(point, None)
+    }
+
+    /// Get the [`FocusTarget`] under `point`, looking through `except` as if it weren't there.
+    pub fn surface_under_except<P>(
+        &self,
+        point: P,
+        except: Option<&WindowElement>,
+    ) -> Option<(FocusTarget, Point<i32, Logical>)>
     where
         P: Into<Point<f64, Logical>>,
     {
@@ -97,60 +253,74 @@ impl State {
 
         let layers = layer_map_for_output(output);
 
+        // Only the screen locker holding the input inhibitor gets the pointer. Lockers cover
+        // outputs with layer surfaces, which need it even over fullscreen windows.
+        if self.input_inhibitor_state.client().is_some() {
+            return [
+                wlr_layer::Layer::Overlay,
+                wlr_layer::Layer::Top,
+                wlr_layer::Layer::Bottom,
+                wlr_layer::Layer::Background,
+            ]
+            .into_iter()
+            .filter_map(|layer| layers.layer_under(layer, point))
+            .map(|layer| FocusTarget::from(layer.clone()))
+            .find(|focus| self.input_inhibitor_state.allows(focus))
+            .and_then(|focus| {
+                let FocusTarget::LayerSurface(layer) = &focus else {
+                    unreachable!()
+                };
+                let layer_loc = layers.layer_geometry(layer)?.loc;
+                Some((focus, output_geo.loc + layer_loc))
+            });
+        }
+
         let top_fullscreen_window = self.focus_state.focus_stack.iter().rev().find(|win| {
-            win.with_state(|state| {
-                state.fullscreen_or_maximized.is_fullscreen()
-                    && state.tags.iter().any(|tag| tag.active())
-            })
+            Some(*win) != except
+                && win.with_state(|state| {
+                    state.fullscreen_or_maximized.is_fullscreen()
+                        && state.tags.iter().any(|tag| tag.active())
+                })
         });
 
+        // A fullscreen window is drawn over every layer, so it gets everything
         if let Some(window) = top_fullscreen_window {
-            Some((FocusTarget::from(window.clone()), output_geo.loc))
-        } else if let (Some(layer), _) | (None, Some(layer)) = (
-            layers.layer_under(wlr_layer::Layer::Overlay, point),
-            layers.layer_under(wlr_layer::Layer::Top, point),
-        ) {
-            let layer_loc = layers.layer_geometry(layer).expect("no layer geo").loc;
-            Some((FocusTarget::from(layer.clone()), output_geo.loc + layer_loc))
-        } else if let Some(ret) = self
-            .space
-            .elements()
-            .rev()
-            .filter(|win| win.is_on_active_tag(self.space.outputs()))
-            .find_map(|win| {
-                let loc = self
-                    .space
-                    .element_location(win)
-                    .expect("called elem loc on unmapped win")
-                    - win.geometry().loc;
-
-                win.is_in_input_region(&(point - loc.to_f64()))
-                    .then(|| (win.clone().into(), loc))
-            })
-        {
-            Some(ret)
-        } else if let (Some(layer), _) | (None, Some(layer)) = (
-            layers.layer_under(wlr_layer::Layer::Overlay, point),
-            layers.layer_under(wlr_layer::Layer::Top, point),
-        ) {
-            let layer_loc = layers.layer_geometry(layer).expect("no layer geo").loc;
-            Some((FocusTarget::from(layer.clone()), output_geo.loc + layer_loc))
-        } else {
-            None
+            return Some((FocusTarget::from(window.clone()), output_geo.loc));
         }
-    }
 
-    fn keyboard<I: InputBackend>(&mut self, event: I::KeyboardKeyEvent) {
-        let serial = SERIAL_COUNTER.next_serial();
-        let time = event.time_msec();
-        let press_state = event.state();
+        let layer_under = |layer: wlr_layer::Layer| {
+            let layer = layers.layer_under(layer, point)?;
+            let layer_loc = layers.layer_geometry(layer)?.loc;
+            Some((FocusTarget::from(layer.clone()), output_geo.loc + layer_loc))
+        };
 
-        let reload_keybind = self.input_state.reload_keybind;
-        let kill_keybind = self.input_state.kill_keybind;
+        let window_under = || {
+            self.space
+                .elements()
+                .rev()
+                .filter(|win| Some(*win) != except)
+                .filter(|win| win.is_on_active_tag(self.space.outputs()))
+                .find_map(|win| {
+                    let loc = self
+                        .space
+                        .element_location(win)
+                        .expect("called elem loc on unmapped win")
+                        - win.geometry().loc;
+
+                    win.is_in_input_region(&(point - loc.to_f64()))
+                        .then(|| (win.clone().into(), loc))
+                })
+        };
 
-        let keyboard = self.seat.get_keyboard().expect("Seat has no keyboard");
+        hit_test(|stratum| match stratum {
+            Stratum::Layer(layer) => layer_under(layer),
+            Stratum::Windows => window_under(),
+        })
+    }
 
-        let modifiers = keyboard.modifier_state();
+    /// Get the leds that should be lit for the keyboard's current lock state.
+    fn keyboard_leds(&self) -> Option<Led> {
+        let modifiers = self.seat.get_keyboard()?.modifier_state();
 
         let mut leds = Led::empty();
         if modifiers.num_lock {
@@ -159,81 +329,218 @@ impl State {
         if modifiers.caps_lock {
             leds |= Led::CAPSLOCK;
         }
+        Some(leds)
+    }
+
+    /// Light up the lock leds on every keyboard if the lock state changed.
+    fn update_keyboard_leds(&mut self) {
+        let Some(leds) = self.keyboard_leds() else {
+            return;
+        };
+        if self.input_state.leds == Some(leds) {
+            return;
+        }
+        self.input_state.leds = Some(leds);
 
-        // FIXME: Leds only update once another key is pressed.
         for device in self.input_state.libinput_devices.iter_mut() {
             device.led_update(leds);
         }
+    }
+
+    fn keyboard<I: InputBackend>(&mut self, event: I::KeyboardKeyEvent) {
+        let serial = SERIAL_COUNTER.next_serial();
+        let time = event.time_msec();
+        let press_state = event.state();
+        let keycode = event.key_code();
+
+        let reload_keybind = self.input_state.reload_keybind;
+        let kill_keybind = self.input_state.kill_keybind;
+        let break_x11_grab_keybind = self.xwayland_keyboard_grab_state.break_keybind;
+
+        self.focus_input_inhibitor();
+
+        let exclusive_layer_focused = self.exclusive_layer_has_keyboard_focus();
+
+        // While the focused surface inhibits shortcuts or has grabbed the keyboard,
+        // only some keybinds work. These look at the keyboard focus, so they're checked
+        // here instead of in the filter, which holds the keyboard's lock.
+        let suppression = self.keybind_suppression();
+        let x11_grabbed = self.xwayland_keyboard_grabbed();
 
+        let keyboard = self.seat.get_keyboard().expect("Seat has no keyboard");
+
+        // This filter runs before the key reaches any keyboard grab or client, including
+        // Xwayland. Clients that want keys to themselves, with a shortcuts inhibitor or an X11
+        // keyboard grab, can only suppress keybinds here.
         let action = keyboard.input(
             self,
-            event.key_code(),
+            keycode,
             press_state,
             serial,
             time,
             |state, modifiers, keysym| {
-                if press_state == KeyState::Pressed {
-                    let mut modifier_mask = Vec::<Modifier>::new();
-                    if modifiers.alt {
-                        modifier_mask.push(Modifier::Alt);
+                let modifier_mask = ModifierMask::from(*modifiers);
+
+                let raw_sym = keysym.raw_syms().iter().next().copied();
+                let mod_sym = keysym.modified_sym();
+
+                // While a screen locker holds the input inhibitor or a layer surface like
+                // a launcher has exclusive keyboard focus, only the kill and reload keybinds
+                // and VT switching work
+                let inhibited =
+                    state.input_inhibitor_state.client().is_some() || exclusive_layer_focused;
+
+                let keybind = |edge| {
+                    if inhibited {
+                        return None;
                     }
-                    if modifiers.shift {
-                        modifier_mask.push(Modifier::Shift);
+                    state
+                        .input_state
+                        .keybind(modifier_mask, mod_sym, edge)
+                        .or_else(|| {
+                            raw_sym.and_then(|raw_sym| {
+                                state.input_state.keybind(modifier_mask, raw_sym, edge)
+                            })
+                        })
+                        .filter(|keybind| keybind.force || !suppression.keybinds())
+                };
+
+                // The kill, reload, and X11 grab break keybinds and VT switching work even while
+                // a prompt is open
+                let reserved = || {
+                    if !suppression.reserved() && kill_keybind == Some((modifier_mask, mod_sym)) {
+                        Some(KeyAction::Quit)
+                    } else if !suppression.reserved()
+                        && reload_keybind == Some((modifier_mask, mod_sym))
+                    {
+                        Some(KeyAction::ReloadConfig)
+                    } else if x11_grabbed
+                        && break_x11_grab_keybind == Some((modifier_mask, mod_sym))
+                    {
+                        Some(KeyAction::BreakX11Grab)
+                    } else if let mut vt @ keysyms::KEY_XF86Switch_VT_1
+                        ..=keysyms::KEY_XF86Switch_VT_12 = keysym.modified_sym().raw()
+                    {
+                        vt = vt - keysyms::KEY_XF86Switch_VT_1 + 1;
+                        tracing::info!("Switching to vt {vt}");
+                        Some(KeyAction::SwitchVt(vt as i32))
+                    } else {
+                        None
+                    }
+                };
+
+                // Escape cancels a move or resize before anything else gets it
+                if press_state == KeyState::Pressed
+                    && mod_sym.raw() == keysyms::KEY_Escape
+                    && state.can_cancel_grab()
+                {
+                    return FilterResult::Intercept(KeyAction::CancelGrab);
+                }
+
+                // An open prompt takes every press, but releases go through so keys held
+                // when it opened don't get stuck
+                if !inhibited && state.input_state.prompt.is_open() {
+                    if press_state == KeyState::Released {
+                        return FilterResult::Forward;
                     }
-                    if modifiers.ctrl {
-                        modifier_mask.push(Modifier::Ctrl);
+                    let action = reserved().unwrap_or(KeyAction::PromptKey(
+                        mod_sym,
+                        !(modifiers.alt || modifiers.ctrl || modifiers.logo),
+                    ));
+                    return FilterResult::Intercept(action);
+                }
+
+                // Passthrough keybinds call their callback here because only intercepted
+                // keys return an action.
+                let edge = match press_state {
+                    KeyState::Pressed => KeyEdge::Press,
+                    KeyState::Released => KeyEdge::Release,
+                };
+                if let Some(keybind) = keybind(edge) {
+                    let (callback_id, data) = (keybind.callback_id, keybind.data.clone());
+                    if keybind.passthrough {
+                        state.call_keybind_callback(callback_id, data);
+                        return FilterResult::Forward;
                     }
-                    if modifiers.logo {
-                        modifier_mask.push(Modifier::Super);
+                    if keybind.repeats && press_state == KeyState::Pressed {
+                        return FilterResult::Intercept(KeyAction::CallRepeatingCallback(
+                            callback_id,
+                            data,
+                        ));
                     }
-                    let modifier_mask = ModifierMask::from(modifier_mask);
+                    return FilterResult::Intercept(KeyAction::CallCallback(callback_id, data));
+                }
 
-                    let raw_sym = keysym.raw_syms().iter().next();
-                    let mod_sym = keysym.modified_sym();
+                if press_state == KeyState::Pressed {
+                    // Release keybinds also take the press so the client doesn't see half
+                    // of a key press.
+                    if keybind(KeyEdge::Release).is_some_and(|keybind| !keybind.passthrough) {
+                        return FilterResult::Intercept(KeyAction::Suppress);
+                    }
 
-                    let cb_id_mod = state.input_state.keybinds.get(&(modifier_mask, mod_sym));
+                    // Only keys the config doesn't bind can be media keys
+                    if let Some(command) = state
+                        .input_state
+                        .media_command(modifier_mask, mod_sym, raw_sym)
+                        .filter(|_| !inhibited && !suppression.keybinds())
+                    {
+                        return FilterResult::Intercept(KeyAction::SpawnMediaCommand(
+                            command.clone(),
+                        ));
+                    }
 
-                    let cb_id_raw = raw_sym.and_then(|raw_sym| {
-                        state.input_state.keybinds.get(&(modifier_mask, *raw_sym))
-                    });
+                    if let Some(action) = reserved() {
+                        return FilterResult::Intercept(action);
+                    }
 
-                    match (cb_id_mod, cb_id_raw) {
-                        (Some(cb_id), _) | (None, Some(cb_id)) => {
-                            return FilterResult::Intercept(KeyAction::CallCallback(*cb_id));
-                        }
-                        (None, None) => (),
+                    // The new config hasn't registered its keybinds yet.
+                    // Shift alone is just typing, so that still goes to the focused window,
+                    // as do the modifier keys themselves.
+                    if !inhibited
+                        && !suppression.keybinds()
+                        && (modifiers.alt || modifiers.ctrl || modifiers.logo)
+                        && !reload_queue::is_modifier_key(mod_sym)
+                        && state.is_waiting_for_config()
+                    {
+                        return FilterResult::Intercept(KeyAction::QueueForReload(QueuedKeybind {
+                            modifier_mask,
+                            mod_sym,
+                            raw_sym,
+                        }));
                     }
 
-                    if kill_keybind == Some((modifier_mask, mod_sym)) {
-                        return FilterResult::Intercept(KeyAction::Quit);
-                    } else if reload_keybind == Some((modifier_mask, mod_sym)) {
-                        return FilterResult::Intercept(KeyAction::ReloadConfig);
-                    } else if let mut vt @ keysyms::KEY_XF86Switch_VT_1
-                        ..=keysyms::KEY_XF86Switch_VT_12 = keysym.modified_sym().raw()
+                    // Only presses are swallowed, so keys held when the layer was entered
+                    // still get released in the focused window
+                    if !inhibited
+                        && !suppression.keybinds()
+                        && state.input_state.bind_layer.swallows_unbound()
                     {
-                        vt = vt - keysyms::KEY_XF86Switch_VT_1 + 1;
-                        tracing::info!("Switching to vt {vt}");
-                        return FilterResult::Intercept(KeyAction::SwitchVt(vt as i32));
+                        return FilterResult::Intercept(KeyAction::Suppress);
                     }
                 }
 
+                // An input method's keyboard grab only sees forwarded keys, so keybinds
+                // still trigger in the middle of composing text
                 FilterResult::Forward
             },
         );
 
+        // The lock state was updated with this key, so the leds can change right away,
+        // even if the key was intercepted above
+        self.update_keyboard_leds();
+
+        // Pressing another key or letting go of a held repeating keybind stops it repeating
+        let modifier_mask = ModifierMask::from(keyboard.modifier_state());
+        self.update_keybind_repeat(keycode, press_state, modifier_mask);
+        self.commit_mru_cycle_on_release(modifier_mask);
+
         match action {
-            Some(KeyAction::CallCallback(callback_id)) => {
-                if let Some(stream) = self.api_state.stream.as_ref() {
-                    if let Err(err) = crate::api::send_to_client(
-                        &mut stream.lock().expect("Could not lock stream mutex"),
-                        &OutgoingMsg::CallCallback {
-                            callback_id,
-                            args: None,
-                        },
-                    ) {
-                        tracing::error!("error sending msg to client: {err}");
-                    }
-                }
+            Some(KeyAction::CallCallback(callback_id, data)) => {
+                self.call_keybind_callback(callback_id, data);
+            }
+            Some(KeyAction::CallRepeatingCallback(callback_id, data)) => {
+                self.call_keybind_callback(callback_id, data.clone());
+                self.start_keybind_repeat(keycode, modifier_mask, callback_id, data);
             }
             Some(KeyAction::SwitchVt(vt)) => {
                 self.switch_vt(vt);
@@ -246,20 +553,51 @@ impl State {
                 self.start_config(crate::config::get_config_dir())
                     .expect("failed to restart config");
             }
-            None => (),
+            Some(KeyAction::BreakX11Grab) => self.break_xwayland_keyboard_grab(),
+            Some(KeyAction::SpawnMediaCommand(command)) => {
+                self.handle_spawn(command, None, None);
+            }
+            Some(KeyAction::QueueForReload(press)) => {
+                self.queue_keybind_for_reload(press);
+            }
+            Some(KeyAction::CancelGrab) => {
+                self.cancel_grab();
+            }
+            Some(KeyAction::PromptKey(keysym, typing)) => {
+                self.prompt_key(keysym, typing);
+            }
+            Some(KeyAction::Suppress) | None => (),
+        }
+    }
+
+    /// Call a keybind's callback, sending back the `data` the config attached to it.
+    fn call_keybind_callback(&mut self, callback_id: CallbackId, data: Option<Vec<u8>>) {
+        self.window_flash_keybind_triggered();
+        self.cursor_warp_keybind_triggered();
+
+        let args = data.map(|data| Args::KeybindData { data });
+
+        if let Some(stream) = self.api_state.stream.as_ref() {
+            if let Err(err) = crate::api::send_to_client(
+                &mut stream.lock().expect("Could not lock stream mutex"),
+                &OutgoingMsg::call_callback(callback_id, args, &self.api_state.callback_serial),
+            ) {
+                tracing::error!("error sending msg to client: {err}");
+            }
         }
     }
 
     fn pointer_button<I: InputBackend>(&mut self, event: I::PointerButtonEvent) {
+        self.press_pointer_button(event.button_code(), event.state(), event.time_msec());
+    }
+
+    /// Press or release `button`, as if a pointer device did.
+    fn press_pointer_button(&mut self, button: u32, button_state: ButtonState, time_msec: u32) {
         let pointer = self.seat.get_pointer().expect("Seat has no pointer"); // FIXME: handle err
         let keyboard = self.seat.get_keyboard().expect("Seat has no keyboard"); // FIXME: handle err
 
         let serial = SERIAL_COUNTER.next_serial();
 
-        let button = event.button_code();
-
-        let button_state = event.state();
-
         let pointer_loc = pointer.current_location();
 
         let mouse_edge = match button_state {
@@ -268,41 +606,68 @@ impl State {
         };
         let modifier_mask = ModifierMask::from(keyboard.modifier_state());
 
-        // If any mousebinds are detected, call the config's callback and return.
-        if let Some(&callback_id) =
+        let inhibited = self.input_inhibitor_state.client().is_some();
+
+        if button_state == ButtonState::Pressed {
+            self.window_flash_pointer_pressed();
+            self.cursor_warp_pointer_used();
+        }
+
+        let is_double_click = button_state == ButtonState::Pressed && {
+            let window = match self.surface_under(pointer_loc) {
+                Some((FocusTarget::Window(window), _)) => Some(window.with_state(|state| state.id)),
+                _ => None,
+            };
             self.input_state
-                .mousebinds
-                .get(&(modifier_mask, button, mouse_edge))
+                .double_click
+                .press(button, time_msec, pointer_loc, window)
+        };
+
+        // If any mousebinds are detected, call the config's callback and return.
+        if let Some(callback_id) = find_mousebind(
+            &self.input_state.mousebinds,
+            modifier_mask,
+            button,
+            mouse_edge,
+            is_double_click,
+        )
+        .filter(|_| !inhibited)
         {
             if let Some(stream) = self.api_state.stream.as_ref() {
-                crate::api::send_to_client(
+                if let Err(err) = crate::api::send_to_client(
                     &mut stream.lock().expect("failed to lock api stream"),
-                    &OutgoingMsg::CallCallback {
+                    &OutgoingMsg::call_callback(
                         callback_id,
-                        args: None,
-                    },
-                )
-                .expect("failed to call callback");
+                        Some(Args::MousebindTriggered {
+                            modifiers: modifier_mask.values(),
+                        }),
+                        &self.api_state.callback_serial,
+                    ),
+                ) {
+                    tracing::error!("error sending msg to client: {err}");
+                }
             }
             return;
         }
 
+        // A click outside a grabbing popup only dismisses it
+        if self.popup_grab_button(button, button_state, pointer_loc) {
+            return;
+        }
+
+        // A layer surface with exclusive keyboard focus keeps it until it goes away
+        let exclusive_layer_focused = self.exclusive_layer_has_keyboard_focus();
+
         // If the button was clicked, focus on the window below if exists, else
         // unfocus on windows.
         if button_state == ButtonState::Pressed {
             if let Some((focus, _)) = self.surface_under(pointer_loc) {
-                // Move window to top of stack.
+                // Move window to top of stack, unless the config turned that off.
+                // This also skips restacking X11 windows in the xwm.
                 if let FocusTarget::Window(window) = &focus {
-                    self.space.raise_element(window, true);
-                    if let WindowElement::X11(surface) = &window {
-                        self.xwm
-                            .as_mut()
-                            .expect("no xwm")
-                            .raise_window(surface)
-                            .expect("failed to raise x11 win");
-                        surface
-                            .set_activated(true)
-                            .expect("failed to set x11 win to activated");
+                    self.mark_window_activity(window);
+                    if self.raise_on_click {
+                        self.raise_window(window);
                     }
                 }
 
@@ -314,36 +679,29 @@ impl State {
 
                 // TODO: use update_keyboard_focus from anvil
 
-                if !matches!(
-                    &focus,
-                    FocusTarget::Window(WindowElement::X11OverrideRedirect(_))
-                ) {
+                // Layer surfaces that don't want keyboard input, like bars and wallpapers,
+                // leave keyboard focus where it is.
+                let takes_keyboard_focus = !exclusive_layer_focused
+                    && match &focus {
+                        FocusTarget::Window(WindowElement::X11OverrideRedirect(_)) => false,
+                        FocusTarget::LayerSurface(layer) => layer.can_receive_keyboard_focus(),
+                        _ => true,
+                    };
+
+                if takes_keyboard_focus {
+                    // Clicking something else means layer surfaces don't get focus back
+                    if !matches!(&focus, FocusTarget::LayerSurface(_)) {
+                        self.focus_state.layer_focus_stack.clear();
+                    }
+                    // Activation is updated in `focus_changed`
                     keyboard.set_focus(self, Some(focus.clone()), serial);
                 }
 
-                self.space.elements().for_each(|window| {
-                    if let WindowElement::Wayland(window) = window {
-                        window.toplevel().send_configure();
-                    }
-                });
-
                 if let FocusTarget::Window(window) = &focus {
                     tracing::debug!("setting keyboard focus to {:?}", window.class());
                 }
-            } else {
-                self.space.elements().for_each(|window| match window {
-                    WindowElement::Wayland(window) => {
-                        window.set_activated(false);
-                        window.toplevel().send_configure();
-                    }
-                    WindowElement::X11(surface) => {
-                        surface
-                            .set_activated(false)
-                            .expect("failed to deactivate x11 win");
-                        // INFO: do i need to configure this?
-                    }
-                    WindowElement::X11OverrideRedirect(_) => (),
-                });
+            } else if !inhibited && !exclusive_layer_focused {
+                self.focus_state.layer_focus_stack.clear();
                 keyboard.set_focus(self, None, serial);
             }
         };
@@ -354,7 +712,7 @@ impl State {
                 button,
                 state: button_state,
                 serial,
-                time: event.time_msec(),
+                time: time_msec,
             },
         );
         pointer.frame(self);
@@ -363,23 +721,56 @@ impl State {
     fn pointer_axis<I: InputBackend>(&mut self, event: I::PointerAxisEvent) {
         let source = event.source();
 
+        let device_name = event.device().name();
+        let scroll = &mut self.input_state.scroll;
+        let factor = scroll.factor(&device_name);
+        let horizontal_factor = if scroll.horizontal_invert(&device_name) {
+            -factor
+        } else {
+            factor
+        };
+
         let horizontal_amount = event
             .amount(Axis::Horizontal)
-            .unwrap_or_else(|| event.amount_discrete(Axis::Horizontal).unwrap_or(0.0) * 3.0);
+            .unwrap_or_else(|| event.amount_discrete(Axis::Horizontal).unwrap_or(0.0) * 3.0)
+            * horizontal_factor;
 
         let vertical_amount = event
             .amount(Axis::Vertical)
-            .unwrap_or_else(|| event.amount_discrete(Axis::Vertical).unwrap_or(0.0) * 3.0);
+            .unwrap_or_else(|| event.amount_discrete(Axis::Vertical).unwrap_or(0.0) * 3.0)
+            * factor;
+
+        let horizontal_scroll = (
+            horizontal_amount,
+            event
+                .amount_discrete(Axis::Horizontal)
+                .map(|discrete| discrete * horizontal_factor),
+        );
+        let vertical_scroll = (
+            vertical_amount,
+            event
+                .amount_discrete(Axis::Vertical)
+                .map(|discrete| discrete * factor),
+        );
+        if self.trigger_scrollbinds(horizontal_scroll, vertical_scroll) {
+            self.cursor_warp_pointer_used();
+            return;
+        }
 
-        let horizontal_amount_discrete = event.amount_discrete(Axis::Horizontal);
-        let vertical_amount_discrete = event.amount_discrete(Axis::Vertical);
+        let scroll = &mut self.input_state.scroll;
+        let horizontal_amount_discrete = event.amount_discrete(Axis::Horizontal).map(|discrete| {
+            scroll.accumulate_discrete(Axis::Horizontal, discrete * horizontal_factor)
+        });
+        let vertical_amount_discrete = event
+            .amount_discrete(Axis::Vertical)
+            .map(|discrete| scroll.accumulate_discrete(Axis::Vertical, discrete * factor));
 
         let mut frame = AxisFrame::new(event.time_msec()).source(source);
 
         if horizontal_amount != 0.0 {
             frame = frame.value(Axis::Horizontal, horizontal_amount);
-            if let Some(discrete) = horizontal_amount_discrete {
-                frame = frame.discrete(Axis::Horizontal, discrete as i32);
+            if let Some(discrete) = horizontal_amount_discrete.filter(|discrete| *discrete != 0) {
+                frame = frame.discrete(Axis::Horizontal, discrete);
             }
         } else if source == AxisSource::Finger {
             frame = frame.stop(Axis::Horizontal);
@@ -387,8 +778,8 @@ impl State {
 
         if vertical_amount != 0.0 {
             frame = frame.value(Axis::Vertical, vertical_amount);
-            if let Some(discrete) = vertical_amount_discrete {
-                frame = frame.discrete(Axis::Vertical, discrete as i32);
+            if let Some(discrete) = vertical_amount_discrete.filter(|discrete| *discrete != 0) {
+                frame = frame.discrete(Axis::Vertical, discrete);
             }
         } else if source == AxisSource::Finger {
             frame = frame.stop(Axis::Vertical);
@@ -400,68 +791,127 @@ impl State {
         pointer.frame(self);
     }
 
-    /// Clamp pointer coordinates inside outputs.
+    /// Call the callbacks of scrollbinds that match a scroll, returning whether any matched.
     ///
-    /// This returns the nearest point inside an output.
-    fn clamp_coords(&self, pos: Point<f64, Logical>) -> Point<f64, Logical> {
-        if self.space.outputs().next().is_none() {
-            return pos;
+    /// `horizontal` and `vertical` are the scaled continuous and discrete scroll amounts
+    /// on each axis.
+    fn trigger_scrollbinds(
+        &mut self,
+        horizontal: (f64, Option<f64>),
+        vertical: (f64, Option<f64>),
+    ) -> bool {
+        let keyboard = self.seat.get_keyboard().expect("Seat has no keyboard");
+        let modifier_mask = ModifierMask::from(keyboard.modifier_state());
+
+        if self.input_inhibitor_state.client().is_some() {
+            return false;
         }
 
-        let (pos_x, pos_y) = pos.into();
+        let mut matched = false;
 
-        let nearest_points = self.space.outputs().map(|op| {
-            let size = self
-                .space
-                .output_geometry(op)
-                .expect("called output_geometry on unmapped output")
-                .size;
-            let loc = op.current_location();
-            let pos_x = pos_x.clamp(loc.x as f64, (loc.x + size.w) as f64);
-            let pos_y = pos_y.clamp(loc.y as f64, (loc.y + size.h) as f64);
-            (pos_x, pos_y)
-        });
+        for (axis, (amount, discrete)) in
+            [(Axis::Horizontal, horizontal), (Axis::Vertical, vertical)]
+        {
+            if amount == 0.0 {
+                // Scrolling stopped, so start counting from zero next time
+                self.input_state.scroll.reset_bind_remainder(axis);
+                continue;
+            }
 
-        let nearest_point = nearest_points.min_by(|(x1, y1), (x2, y2)| {
-            f64::total_cmp(
-                &((pos_x - x1).powi(2) + (pos_y - y1).powi(2)).sqrt(),
-                &((pos_x - x2).powi(2) + (pos_y - y2).powi(2)).sqrt(),
-            )
-        });
+            let direction = match (axis, amount > 0.0) {
+                (Axis::Horizontal, true) => ScrollDirection::Right,
+                (Axis::Horizontal, false) => ScrollDirection::Left,
+                (Axis::Vertical, true) => ScrollDirection::Down,
+                (Axis::Vertical, false) => ScrollDirection::Up,
+            };
+
+            let Some(&callback_id) = self
+                .input_state
+                .scrollbinds
+                .get(&(modifier_mask, direction))
+            else {
+                continue;
+            };
+
+            matched = true;
+
+            let steps = self
+                .input_state
+                .scroll
+                .accumulate_bind(axis, amount, discrete);
+
+            if let Some(stream) = self.api_state.stream.as_ref() {
+                for _ in 0..steps {
+                    crate::api::send_to_client(
+                        &mut stream.lock().expect("failed to lock api stream"),
+                        &OutgoingMsg::call_callback(
+                            callback_id,
+                            None,
+                            &self.api_state.callback_serial,
+                        ),
+                    )
+                    .expect("failed to call callback");
+                }
+            }
+        }
+
+        matched
+    }
+
+    /// Clamp pointer coordinates inside outputs.
+    ///
+    /// This returns the nearest point inside an output.
+    fn clamp_coords(&self, pos: Point<f64, Logical>) -> Point<f64, Logical> {
+        clamp_to_outputs(
+            pos,
+            self.space.outputs().map(|op| {
+                self.space
+                    .output_geometry(op)
+                    .expect("called output_geometry on unmapped output")
+            }),
+        )
+    }
 
-        nearest_point.map(|point| point.into()).unwrap_or(pos)
+    /// Get the area that absolute pointer events from the device with `device_id` are spread
+    /// over. This is the output the device is mapped to, or every output together if it isn't
+    /// mapped or that output isn't connected.
+    ///
+    /// Returns `None` if there are no outputs.
+    fn absolute_pointer_area(&self, device_id: &str) -> Option<Rectangle<i32, Logical>> {
+        let mapped_geo = self
+            .mapped_output(device_id)
+            .and_then(|output_name| output_name.output(self))
+            .and_then(|output| self.space.output_geometry(&output));
+
+        absolute_pointer_area(
+            mapped_geo,
+            self.space
+                .outputs()
+                .filter_map(|output| self.space.output_geometry(output)),
+        )
     }
 
     fn pointer_motion_absolute<I: InputBackend>(&mut self, event: I::PointerMotionAbsoluteEvent) {
-        let Some(output) = self.space.outputs().next() else {
+        let Some(area) = self.absolute_pointer_area(&event.device().id()) else {
             return;
         };
 
-        let output_geo = self
-            .space
-            .output_geometry(output)
-            .expect("Output geometry doesn't exist");
-        let pointer_loc = event.position_transformed(output_geo.size) + output_geo.loc.to_f64();
+        let position = (event.x_transformed(1), event.y_transformed(1)).into();
+        let pointer_loc = map_absolute_position(position, area);
+        let pointer_loc = self.constrain_pointer_motion(self.pointer_location, pointer_loc);
         let serial = SERIAL_COUNTER.next_serial();
         let pointer = self.seat.get_pointer().expect("Seat has no pointer"); // FIXME: handle err
 
         self.pointer_location = pointer_loc;
 
-        match self.focus_state.focused_output {
-            Some(_) => {
-                if let Some(output) = self
-                    .space
-                    .output_under(self.pointer_location)
-                    .next()
-                    .cloned()
-                {
-                    self.focus_state.focused_output = Some(output);
-                }
-            }
-            None => {
-                self.focus_state.focused_output = self.space.outputs().next().cloned();
-            }
-        }
+        self.focus_state.focused_output = focused_output_after_motion(
+            self.focus_state.focused_output.take(),
+            self.space
+                .output_under(self.pointer_location)
+                .next()
+                .cloned(),
+            self.space.outputs().next().cloned(),
+        );
 
         pointer.motion(
             self,
@@ -474,30 +924,27 @@ impl State {
         );
 
         pointer.frame(self);
+
+        if let Some(output) = self.focus_state.focused_output.clone() {
+            self.schedule_render(&output);
+        }
     }
 
     fn pointer_motion<I: InputBackend>(&mut self, event: I::PointerMotionEvent) {
         let serial = SERIAL_COUNTER.next_serial();
-        self.pointer_location += event.delta();
 
         // clamp to screen limits
         // this event is never generated by winit
-        self.pointer_location = self.clamp_coords(self.pointer_location);
-        match self.focus_state.focused_output {
-            Some(_) => {
-                if let Some(output) = self
-                    .space
-                    .output_under(self.pointer_location)
-                    .next()
-                    .cloned()
-                {
-                    self.focus_state.focused_output = Some(output);
-                }
-            }
-            None => {
-                self.focus_state.focused_output = self.space.outputs().next().cloned();
-            }
-        }
+        self.pointer_location = self
+            .constrain_pointer_motion(self.pointer_location, self.pointer_location + event.delta());
+        self.focus_state.focused_output = focused_output_after_motion(
+            self.focus_state.focused_output.take(),
+            self.space
+                .output_under(self.pointer_location)
+                .next()
+                .cloned(),
+            self.space.outputs().next().cloned(),
+        );
 
         let surface_under = self.surface_under(self.pointer_location);
 
@@ -524,13 +971,400 @@ impl State {
 
             pointer.frame(self);
 
-            self.schedule_render(
-                &self
-                    .focus_state
-                    .focused_output
-                    .clone()
-                    .expect("no focused output"),
+            // There may be no outputs if they were all unplugged
+            if let Some(output) = self.focus_state.focused_output.clone() {
+                self.schedule_render(&output);
+            }
+        }
+    }
+}
+
+/// Get the mousebind for `button` with `modifiers`.
+///
+/// A double click's bind replaces the second click's press bind.
+fn find_mousebind(
+    mousebinds: &HashMap<(ModifierMask, u32, MouseEdge), CallbackId>,
+    modifiers: ModifierMask,
+    button: u32,
+    edge: MouseEdge,
+    is_double_click: bool,
+) -> Option<CallbackId> {
+    is_double_click
+        .then(|| mousebinds.get(&(modifiers, button, MouseEdge::DoubleClick)))
+        .flatten()
+        .or_else(|| mousebinds.get(&(modifiers, button, edge)))
+        .copied()
+}
+
+/// Something the pointer can be over, layer surfaces of one layer or windows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stratum {
+    Layer(wlr_layer::Layer),
+    Windows,
+}
+
+/// Find the topmost thing `under` finds in any stratum, going through them in the same order
+/// they're drawn in, from top to bottom.
+fn hit_test<T>(under: impl FnMut(Stratum) -> Option<T>) -> Option<T> {
+    [
+        Stratum::Layer(wlr_layer::Layer::Overlay),
+        Stratum::Layer(wlr_layer::Layer::Top),
+        Stratum::Windows,
+        Stratum::Layer(wlr_layer::Layer::Bottom),
+        Stratum::Layer(wlr_layer::Layer::Background),
+    ]
+    .into_iter()
+    .find_map(under)
+}
+
+/// Clamp `pos` to whichever of the outputs with `output_geos` is nearest to it.
+///
+/// Without outputs, `pos` is left alone.
+fn clamp_to_outputs(
+    pos: Point<f64, Logical>,
+    output_geos: impl IntoIterator<Item = Rectangle<i32, Logical>>,
+) -> Point<f64, Logical> {
+    let (pos_x, pos_y) = pos.into();
+
+    let nearest_points = output_geos.into_iter().map(|geo| {
+        let pos_x = pos_x.clamp(geo.loc.x as f64, (geo.loc.x + geo.size.w) as f64);
+        let pos_y = pos_y.clamp(geo.loc.y as f64, (geo.loc.y + geo.size.h) as f64);
+        (pos_x, pos_y)
+    });
+
+    let nearest_point = nearest_points.min_by(|(x1, y1), (x2, y2)| {
+        f64::total_cmp(
+            &((pos_x - x1).powi(2) + (pos_y - y1).powi(2)).sqrt(),
+            &((pos_x - x2).powi(2) + (pos_y - y2).powi(2)).sqrt(),
+        )
+    });
+
+    nearest_point.map(|point| point.into()).unwrap_or(pos)
+}
+
+/// Get the output that has focus after the pointer moves onto `output_under`.
+///
+/// If no output had focus, the first output gets it, and without outputs none does.
+fn focused_output_after_motion<O>(
+    focused: Option<O>,
+    output_under: Option<O>,
+    first_output: Option<O>,
+) -> Option<O> {
+    match focused {
+        Some(focused) => Some(output_under.unwrap_or(focused)),
+        None => first_output,
+    }
+}
+
+/// Get the area that absolute pointer events are spread over: `mapped_geo`, the geometry of the
+/// output the device is mapped to, or the bounding box of `output_geos` if there isn't one.
+fn absolute_pointer_area(
+    mapped_geo: Option<Rectangle<i32, Logical>>,
+    output_geos: impl IntoIterator<Item = Rectangle<i32, Logical>>,
+) -> Option<Rectangle<i32, Logical>> {
+    mapped_geo.or_else(|| {
+        output_geos
+            .into_iter()
+            .reduce(|area, output_geo| area.merge(output_geo))
+    })
+}
+
+/// Map an absolute pointer position, from 0 to 1 across each axis of the device, onto `area`.
+fn map_absolute_position(
+    position: Point<f64, Logical>,
+    area: Rectangle<i32, Logical>,
+) -> Point<f64, Logical> {
+    Point::from((
+        position.x * area.size.w as f64,
+        position.y * area.size.h as f64,
+    )) + area.loc.to_f64()
+}
+
+#[cfg(test)]
+mod tests {
+    use smithay::input::keyboard::ModifiersState;
+
+    use crate::api::msg::Modifier;
+
+    use super::*;
+
+    const BTN_LEFT: u32 = 0x110;
+
+    /// Press `BTN_LEFT` with Super held and Caps Lock on, with a Super+click bind set the way
+    /// the config sets it.
+    fn super_click(edge: MouseEdge, is_double_click: bool) -> Option<u32> {
+        let mut mousebinds = HashMap::new();
+        mousebinds.insert(
+            (vec![Modifier::Super].into(), BTN_LEFT, MouseEdge::Press),
+            CallbackId(1),
+        );
+        mousebinds.insert(
+            (
+                vec![Modifier::Super].into(),
+                BTN_LEFT,
+                MouseEdge::DoubleClick,
+            ),
+            CallbackId(2),
+        );
+
+        // Super held and Caps Lock locked, with the core modifier indices
+        let mut keyboard = ModifiersState::default();
+        keyboard.serialized.depressed = 1 << 6;
+        keyboard.serialized.locked = 1 << 1;
+        let modifiers = ModifierMask::from(keyboard);
+        assert_eq!(modifiers.values(), [Modifier::Super]);
+
+        find_mousebind(&mousebinds, modifiers, BTN_LEFT, edge, is_double_click).map(|id| id.0)
+    }
+
+    #[test]
+    fn mousebind_fires_with_caps_lock_on() {
+        assert_eq!(super_click(MouseEdge::Press, false), Some(1));
+        assert_eq!(super_click(MouseEdge::Release, false), None);
+    }
+
+    #[test]
+    fn double_click_bind_replaces_press_bind() {
+        assert_eq!(super_click(MouseEdge::Press, true), Some(2));
+    }
+
+    fn rect(x: i32, y: i32, w: i32, h: i32) -> Rectangle<i32, Logical> {
+        Rectangle::from_loc_and_size((x, y), (w, h))
+    }
+
+    fn map(x: f64, y: f64, area: Rectangle<i32, Logical>) -> (f64, f64) {
+        let point = map_absolute_position((x, y).into(), area);
+        (point.x, point.y)
+    }
+
+    #[test]
+    fn side_by_side_outputs_of_different_sizes() {
+        let area = absolute_pointer_area(None, [rect(0, 0, 1920, 1080), rect(1920, 0, 2560, 1440)])
+            .unwrap();
+        assert_eq!(area, rect(0, 0, 4480, 1440));
+
+        assert_eq!(map(0.0, 0.0, area), (0.0, 0.0));
+        assert_eq!(map(0.5, 0.5, area), (2240.0, 720.0));
+        assert_eq!(map(1.0, 1.0, area), (4480.0, 1440.0));
+    }
+
+    #[test]
+    fn scaled_outputs_use_their_logical_size() {
+        // A 3840x2160 output at scale 2 takes up 1920x1080 in the space
+        let area = absolute_pointer_area(None, [rect(0, 0, 1920, 1080), rect(1920, 0, 1920, 1080)])
+            .unwrap();
+        assert_eq!(area, rect(0, 0, 3840, 1080));
+        assert_eq!(map(0.75, 0.5, area), (2880.0, 540.0));
+    }
+
+    #[test]
+    fn offset_outputs() {
+        // The second output is to the left of the first and lower down
+        let area =
+            absolute_pointer_area(None, [rect(0, 0, 1920, 1080), rect(-1280, 200, 1280, 1024)])
+                .unwrap();
+        assert_eq!(area, rect(-1280, 0, 3200, 1224));
+
+        assert_eq!(map(0.0, 0.0, area), (-1280.0, 0.0));
+        assert_eq!(map(0.2, 0.5, area), (-640.0, 612.0));
+        assert_eq!(map(1.0, 1.0, area), (1920.0, 1224.0));
+    }
+
+    #[test]
+    fn mapped_output_is_used_alone() {
+        let mapped = rect(1920, 0, 1280, 720);
+        let area = absolute_pointer_area(Some(mapped), [rect(0, 0, 1920, 1080), mapped]).unwrap();
+        assert_eq!(area, mapped);
+
+        assert_eq!(map(0.0, 0.0, area), (1920.0, 0.0));
+        assert_eq!(map(0.5, 0.5, area), (2560.0, 360.0));
+    }
+
+    #[test]
+    fn no_outputs() {
+        assert_eq!(absolute_pointer_area(None, []), None);
+    }
+
+    /// Hit test a point that's over everything in `strata`.
+    fn hit(strata: &[Stratum]) -> Option<Stratum> {
+        hit_test(|stratum| strata.contains(&stratum).then_some(stratum))
+    }
+
+    #[test]
+    fn bottom_layer_gets_clicks_without_windows_over_it() {
+        let bottom = Stratum::Layer(wlr_layer::Layer::Bottom);
+        let background = Stratum::Layer(wlr_layer::Layer::Background);
+        assert_eq!(hit(&[bottom]), Some(bottom));
+        assert_eq!(hit(&[bottom, background]), Some(bottom));
+        assert_eq!(hit(&[background]), Some(background));
+    }
+
+    #[test]
+    fn windows_cover_bottom_and_background_layers() {
+        let strata = [
+            Stratum::Windows,
+            Stratum::Layer(wlr_layer::Layer::Bottom),
+            Stratum::Layer(wlr_layer::Layer::Background),
+        ];
+        assert_eq!(hit(&strata), Some(Stratum::Windows));
+    }
+
+    #[test]
+    fn overlay_and_top_layers_cover_windows() {
+        let top = Stratum::Layer(wlr_layer::Layer::Top);
+        let overlay = Stratum::Layer(wlr_layer::Layer::Overlay);
+        assert_eq!(hit(&[Stratum::Windows, top]), Some(top));
+        assert_eq!(hit(&[Stratum::Windows, top, overlay]), Some(overlay));
+    }
+
+    #[test]
+    fn nothing_under_the_pointer() {
+        assert_eq!(hit(&[]), None);
+    }
+
+    #[test]
+    fn motion_without_outputs() {
+        let mut pos: Point<f64, Logical> = (100.0, 100.0).into();
+        let mut focused_output: Option<&str> = None;
+
+        for delta in [(50.0, 0.0), (-500.0, 20.0), (10_000.0, -10_000.0)] {
+            pos = clamp_to_outputs(pos + delta.into(), []);
+            focused_output = focused_output_after_motion(focused_output, None, None);
+        }
+
+        // The pointer moves freely and nothing gets focus, so no render is scheduled
+        assert_eq!(pos, (9_650.0, -9_880.0).into());
+        assert_eq!(focused_output, None);
+
+        // Once an output connects, the pointer is clamped onto it
+        let output = rect(0, 0, 1920, 1080);
+        pos = clamp_to_outputs(pos, [output]);
+        assert_eq!(pos, (1920.0, 0.0).into());
+        focused_output = focused_output_after_motion(focused_output, Some("DP-1"), Some("DP-1"));
+        assert_eq!(focused_output, Some("DP-1"));
+    }
+
+    #[test]
+    fn focus_stays_when_moving_off_outputs() {
+        assert_eq!(
+            focused_output_after_motion(Some("DP-1"), None, Some("DP-1")),
+            Some("DP-1")
+        );
+        assert_eq!(
+            focused_output_after_motion(Some("DP-1"), Some("HDMI-A-1"), Some("DP-1")),
+            Some("HDMI-A-1")
+        );
+    }
+
+    fn media_input_state() -> InputState {
+        let mut input_state = InputState::default();
+        input_state.media_keys.enabled = true;
+        input_state.media_keys.commands = HashMap::from([(
+            Keysym::from(keysyms::KEY_XF86AudioMute),
+            vec!["mute".to_string()],
+        )]);
+        input_state
+    }
+
+    fn bind(
+        input_state: &mut InputState,
+        layer: Option<&str>,
+        modifiers: &[Modifier],
+        keysym: u32,
+        edge: KeyEdge,
+    ) {
+        input_state.keybinds.insert(
+            (
+                layer.map(str::to_string),
+                ModifierMask::from(modifiers),
+                Keysym::from(keysym),
+                edge,
+            ),
+            Keybind {
+                callback_id: CallbackId(0),
+                group: None,
+                description: None,
+                passthrough: true,
+                force: false,
+                repeats: false,
+                data: None,
+            },
+        );
+    }
+
+    fn mute_command(input_state: &InputState, raw_sym: Option<u32>) -> Option<&Vec<String>> {
+        input_state.media_command(
+            ModifierMask::from(&[][..]),
+            Keysym::from(keysyms::KEY_XF86AudioMute),
+            raw_sym.map(Keysym::from),
+        )
+    }
+
+    #[test]
+    fn unbound_media_keys_run_commands() {
+        let input_state = media_input_state();
+        assert_eq!(
+            mute_command(&input_state, None),
+            Some(&vec!["mute".to_string()])
+        );
+    }
+
+    #[test]
+    fn config_binds_override_media_keys() {
+        for edge in [KeyEdge::Press, KeyEdge::Release, KeyEdge::Both] {
+            let mut input_state = media_input_state();
+            bind(
+                &mut input_state,
+                None,
+                &[],
+                keysyms::KEY_XF86AudioMute,
+                edge,
             );
+            assert_eq!(mute_command(&input_state, None), None, "{edge:?}");
         }
     }
+
+    #[test]
+    fn config_binds_on_the_raw_keysym_override_media_keys() {
+        let mut input_state = media_input_state();
+        bind(
+            &mut input_state,
+            None,
+            &[],
+            keysyms::KEY_XF86AudioPlay,
+            KeyEdge::Press,
+        );
+        assert_eq!(
+            mute_command(&input_state, Some(keysyms::KEY_XF86AudioPlay)),
+            None
+        );
+    }
+
+    #[test]
+    fn other_binds_dont_override_media_keys() {
+        let mut input_state = media_input_state();
+        // Different modifiers
+        bind(
+            &mut input_state,
+            None,
+            &[Modifier::Super],
+            keysyms::KEY_XF86AudioMute,
+            KeyEdge::Press,
+        );
+        // A layer that isn't active
+        bind(
+            &mut input_state,
+            Some("resize"),
+            &[],
+            keysyms::KEY_XF86AudioMute,
+            KeyEdge::Press,
+        );
+        assert_eq!(
+            mute_command(&input_state, None),
+            Some(&vec!["mute".to_string()])
+        );
+
+        input_state.bind_layer.active = Some("resize".to_string());
+        assert_eq!(mute_command(&input_state, None), None);
+    }
 }
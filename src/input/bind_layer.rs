@@ -0,0 +1,63 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Keybind layers, for modes like a resize mode where plain keys do something different.
+//!
+//! Only keybinds in the active layer trigger. Keybinds without a layer are in the
+//! default layer, which is active when no other layer is entered.
+
+use std::collections::HashSet;
+
+use crate::{
+    api::msg::{Args, OutgoingMsg},
+    state::State,
+};
+
+#[derive(Debug, Default)]
+pub struct BindLayerState {
+    /// The entered layer, or `None` for the default layer
+    pub active: Option<String>,
+    /// Layers that send key presses they have no keybind for to the focused window.
+    /// Other layers swallow them.
+    pub forward_unbound: HashSet<String>,
+}
+
+impl BindLayerState {
+    /// Whether a key press with no keybind in the active layer should be swallowed.
+    pub fn swallows_unbound(&self) -> bool {
+        self.active
+            .as_ref()
+            .is_some_and(|layer| !self.forward_unbound.contains(layer))
+    }
+}
+
+impl State {
+    /// Make `layer` the active keybind layer, or the default layer if `None`,
+    /// and tell the config if it changed.
+    pub fn enter_bind_layer(&mut self, layer: Option<String>) {
+        if self.input_state.bind_layer.active == layer {
+            return;
+        }
+
+        let previous = std::mem::replace(&mut self.input_state.bind_layer.active, layer.clone());
+        tracing::debug!("Keybind layer changed from {previous:?} to {layer:?}");
+
+        let Some(stream) = self.api_state.stream.as_ref() else {
+            return;
+        };
+
+        for callback_id in self.config.bind_layer_callback_ids.iter() {
+            crate::api::send_to_client(
+                &mut stream.lock().expect("couldn't lock stream"),
+                &OutgoingMsg::call_callback(
+                    *callback_id,
+                    Some(Args::BindLayerChanged {
+                        layer: layer.clone(),
+                        previous: previous.clone(),
+                    }),
+                    &self.api_state.callback_serial,
+                ),
+            )
+            .expect("Send to client failed");
+        }
+    }
+}
@@ -0,0 +1,143 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Warping the pointer to windows that the config focuses.
+//!
+//! On multiple outputs, focusing a window with a keybind leaves the pointer behind, so the next
+//! scroll goes to whatever is under it. With warping on, the pointer jumps to the center of the
+//! window when focus changes through the API, like from focusing a window or switching tags.
+//!
+//! Focus changes that come from clicks and scrollbinds don't warp, since the pointer is
+//! already where the user wants it. Neither do ones where the pointer is already over the window.
+
+use std::time::{Duration, Instant};
+
+use smithay::{
+    input::pointer::MotionEvent,
+    utils::{IsAlive, Logical, Point, SERIAL_COUNTER},
+};
+
+use crate::{
+    state::{State, WithState},
+    window::WindowElement,
+};
+
+/// How long after a click or scrollbind a focus change still counts as coming from it.
+///
+/// Bind callbacks run in the config, so the focus change comes in a bit later.
+const POINTER_FOCUS_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Where the pointer goes when the config focuses a window.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum CursorWarp {
+    /// Leave the pointer alone.
+    #[default]
+    None,
+    /// Move the pointer to the center of the focused window.
+    CenterOnFocus,
+}
+
+#[derive(Debug, Default)]
+pub struct CursorWarpState {
+    pub warp: CursorWarp,
+    /// When a pointer button was last pressed or a scrollbind last triggered,
+    /// if no keybind has triggered since.
+    last_pointer_use: Option<Instant>,
+}
+
+impl State {
+    /// Warp the pointer to `window` if warping is on and the API just focused it.
+    pub fn warp_pointer_on_focus(&mut self, window: &WindowElement) {
+        let cursor_warp = &self.input_state.cursor_warp;
+
+        let from_pointer = cursor_warp
+            .last_pointer_use
+            .is_some_and(|time| time.elapsed() < POINTER_FOCUS_TIMEOUT);
+
+        if cursor_warp.warp != CursorWarp::CenterOnFocus
+            || !self.activity_state.handling_api_msg
+            || from_pointer
+        {
+            return;
+        }
+
+        // The window may not be in its new spot yet, like after switching tags
+        let win = window.clone();
+        let window = window.clone();
+        self.schedule(
+            move |_| !win.alive() || win.with_state(|state| state.loc_request_state.is_idle()),
+            move |data| data.state.warp_pointer_to_window(&window),
+        );
+    }
+
+    /// Remember that a pointer button was pressed or a scrollbind triggered, so the focus change
+    /// it causes doesn't warp the pointer.
+    pub fn cursor_warp_pointer_used(&mut self) {
+        self.input_state.cursor_warp.last_pointer_use = Some(Instant::now());
+    }
+
+    /// Forget the last pointer use, so focus changes from keybinds warp the pointer.
+    pub fn cursor_warp_keybind_triggered(&mut self) {
+        self.input_state.cursor_warp.last_pointer_use = None;
+    }
+
+    /// Move the pointer to the center of `window`, if it still has keyboard focus and the pointer
+    /// isn't already over it.
+    ///
+    /// The pointer isn't moved while it's locked to an output or grabbed.
+    fn warp_pointer_to_window(&mut self, window: &WindowElement) {
+        let Some(pointer) = self.seat.get_pointer() else {
+            return;
+        };
+
+        if pointer.is_grabbed() || self.input_state.pointer_barrier.locked_output.is_some() {
+            return;
+        }
+
+        if self.focus_state.activated.as_ref() != Some(window) {
+            return;
+        }
+
+        let Some(geo) = self.space.element_geometry(window).map(|geo| geo.to_f64()) else {
+            return;
+        };
+
+        if geo.contains(self.pointer_location) {
+            return;
+        }
+
+        let center = Point::<f64, Logical>::from((
+            geo.loc.x + geo.size.w / 2.0,
+            geo.loc.y + geo.size.h / 2.0,
+        ));
+        let new_loc = self.clamp_coords(center);
+        let old_output = self
+            .space
+            .output_under(self.pointer_location)
+            .next()
+            .cloned();
+
+        tracing::debug!("Warping pointer to {new_loc:?}");
+        self.pointer_location = new_loc;
+
+        pointer.motion(
+            self,
+            self.surface_under(new_loc),
+            &MotionEvent {
+                location: new_loc,
+                serial: SERIAL_COUNTER.next_serial(),
+                time: Duration::from(self.clock.now()).as_millis() as u32,
+            },
+        );
+        pointer.frame(self);
+
+        let new_output = self.space.output_under(new_loc).next().cloned();
+        if let Some(output) = new_output.as_ref() {
+            self.focus_state.focused_output = Some(output.clone());
+        }
+
+        // Redraw the pointer on both outputs
+        for output in old_output.into_iter().chain(new_output) {
+            self.schedule_render(&output);
+        }
+    }
+}
@@ -0,0 +1,156 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Double-click mousebinds.
+//!
+//! A press is the second click of a double click when it's the same button as the last press,
+//! it comes within the double-click interval, it's on the same window, and the pointer hasn't
+//! moved more than a few pixels since. A third quick click starts a new double click instead of making another one.
+//!
+//! The first click triggers `Press` binds like any other press. The second click triggers the
+//! `DoubleClick` bind instead of the `Press` bind if there is one for the same modifiers and
+//! button. Otherwise it's an ordinary press. `Release` binds trigger on both releases.
+
+use std::time::Duration;
+
+use smithay::utils::{Logical, Point};
+
+use crate::window::window_state::WindowId;
+
+/// The double-click interval the pointer starts with.
+pub const DEFAULT_DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+/// How far the pointer can move between the clicks of a double click.
+const DOUBLE_CLICK_RADIUS: f64 = 4.0;
+
+#[derive(Debug)]
+pub struct DoubleClickState {
+    /// The longest time between the clicks of a double click
+    pub interval: Duration,
+    last_press: Option<LastPress>,
+}
+
+impl Default for DoubleClickState {
+    fn default() -> Self {
+        Self {
+            interval: DEFAULT_DOUBLE_CLICK_INTERVAL,
+            last_press: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LastPress {
+    button: u32,
+    /// The press's event time, in milliseconds
+    time_msec: u32,
+    loc: Point<f64, Logical>,
+    /// The window the press was on, if any
+    window: Option<WindowId>,
+}
+
+impl DoubleClickState {
+    /// Record a press of `button` at `loc` on `window` and return whether it's the second click
+    /// of a double click.
+    pub fn press(
+        &mut self,
+        button: u32,
+        time_msec: u32,
+        loc: Point<f64, Logical>,
+        window: Option<WindowId>,
+    ) -> bool {
+        let is_double_click = self.last_press.as_ref().is_some_and(|last| {
+            let elapsed = Duration::from_millis(time_msec.wrapping_sub(last.time_msec) as u64);
+            let moved = loc - last.loc;
+
+            last.button == button
+                && last.window == window
+                && elapsed <= self.interval
+                && moved.x.hypot(moved.y) <= DOUBLE_CLICK_RADIUS
+        });
+
+        self.last_press = (!is_double_click).then_some(LastPress {
+            button,
+            time_msec,
+            loc,
+            window,
+        });
+
+        is_double_click
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEFT: u32 = 0x110;
+    const RIGHT: u32 = 0x111;
+    const WINDOW: Option<WindowId> = Some(WindowId::Some(0));
+
+    fn loc(x: f64, y: f64) -> Point<f64, Logical> {
+        (x, y).into()
+    }
+
+    #[test]
+    fn quick_second_click_is_a_double_click() {
+        let mut state = DoubleClickState::default();
+        assert!(!state.press(LEFT, 1000, loc(10.0, 10.0), WINDOW));
+        assert!(state.press(LEFT, 1200, loc(11.0, 12.0), WINDOW));
+    }
+
+    #[test]
+    fn slow_second_click_is_not_a_double_click() {
+        let mut state = DoubleClickState::default();
+        assert!(!state.press(LEFT, 1000, loc(10.0, 10.0), WINDOW));
+        assert!(!state.press(LEFT, 1401, loc(10.0, 10.0), WINDOW));
+        // The slow click starts a new double click
+        assert!(state.press(LEFT, 1500, loc(10.0, 10.0), WINDOW));
+    }
+
+    #[test]
+    fn interval_is_configurable() {
+        let mut state = DoubleClickState {
+            interval: Duration::from_millis(100),
+            ..Default::default()
+        };
+        assert!(!state.press(LEFT, 1000, loc(10.0, 10.0), WINDOW));
+        assert!(!state.press(LEFT, 1200, loc(10.0, 10.0), WINDOW));
+        assert!(state.press(LEFT, 1300, loc(10.0, 10.0), WINDOW));
+    }
+
+    #[test]
+    fn click_on_another_window_resets() {
+        let other = Some(WindowId::Some(1));
+        let mut state = DoubleClickState::default();
+        assert!(!state.press(LEFT, 1000, loc(10.0, 10.0), WINDOW));
+        assert!(!state.press(LEFT, 1100, loc(10.0, 10.0), other));
+        assert!(state.press(LEFT, 1200, loc(10.0, 10.0), other));
+        // Clicking empty space is a different target too
+        assert!(!state.press(LEFT, 1300, loc(10.0, 10.0), None));
+    }
+
+    #[test]
+    fn other_button_or_moving_away_resets() {
+        let mut state = DoubleClickState::default();
+        assert!(!state.press(LEFT, 1000, loc(10.0, 10.0), WINDOW));
+        assert!(!state.press(RIGHT, 1100, loc(10.0, 10.0), WINDOW));
+        assert!(!state.press(LEFT, 1200, loc(10.0, 10.0), WINDOW));
+        assert!(!state.press(LEFT, 1300, loc(20.0, 10.0), WINDOW));
+    }
+
+    #[test]
+    fn third_quick_click_starts_over() {
+        let mut state = DoubleClickState::default();
+        assert!(!state.press(LEFT, 1000, loc(10.0, 10.0), WINDOW));
+        assert!(state.press(LEFT, 1100, loc(10.0, 10.0), WINDOW));
+        assert!(!state.press(LEFT, 1200, loc(10.0, 10.0), WINDOW));
+        assert!(state.press(LEFT, 1300, loc(10.0, 10.0), WINDOW));
+    }
+
+    #[test]
+    fn event_time_wraparound() {
+        let mut state = DoubleClickState::default();
+        assert!(!state.press(LEFT, u32::MAX - 50, loc(10.0, 10.0), WINDOW));
+        assert!(state.press(LEFT, 50, loc(10.0, 10.0), WINDOW));
+    }
+}
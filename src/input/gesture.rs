@@ -0,0 +1,268 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Touchpad gestures.
+//!
+//! A gesture with a finger count that has a bind is kept from clients and decided
+//! when it ends: swipes by the direction they moved furthest in, and pinches by how far
+//! their scale moved from 1.0. Every other gesture is sent to clients through
+//! the seat's pointer.
+
+use std::collections::HashMap;
+
+use smithay::{
+    backend::input::{
+        Event, GestureBeginEvent, GestureEndEvent, GesturePinchUpdateEvent as _,
+        GestureSwipeUpdateEvent as _, InputBackend,
+    },
+    input::pointer::{
+        GestureHoldBeginEvent, GestureHoldEndEvent, GesturePinchBeginEvent, GesturePinchEndEvent,
+        GesturePinchUpdateEvent, GestureSwipeBeginEvent, GestureSwipeEndEvent,
+        GestureSwipeUpdateEvent,
+    },
+    utils::{Logical, Point, SERIAL_COUNTER},
+};
+
+use crate::{
+    api::msg::{CallbackId, OutgoingMsg, PinchDirection, SwipeDirection},
+    state::State,
+};
+
+/// How far a swipe needs to move for a swipe bind to trigger.
+const MIN_SWIPE_DISTANCE: f64 = 20.0;
+
+#[derive(Debug, Default)]
+pub struct GestureState {
+    /// A hashmap of finger counts and swipe directions to callback IDs
+    pub swipebinds: HashMap<(u32, SwipeDirection), CallbackId>,
+    /// A hashmap of finger counts and pinch directions to pinchbinds
+    pub pinchbinds: HashMap<(u32, PinchDirection), Pinchbind>,
+    /// The bound gesture in progress. Its events aren't sent to clients.
+    bound: Option<BoundGesture>,
+}
+
+/// A pinchbind set by the config.
+#[derive(Debug, Clone, Copy)]
+pub struct Pinchbind {
+    pub callback_id: CallbackId,
+    /// How far the scale needs to move away from 1.0 for this to trigger.
+    pub threshold: f64,
+}
+
+#[derive(Debug)]
+enum BoundGesture {
+    Swipe {
+        fingers: u32,
+        delta: Point<f64, Logical>,
+    },
+    Pinch {
+        fingers: u32,
+        scale: f64,
+    },
+}
+
+impl GestureState {
+    /// Get the direction of a swipe that moved `delta`, if it moved far enough.
+    fn swipe_direction(delta: Point<f64, Logical>) -> Option<SwipeDirection> {
+        if delta.x.abs().max(delta.y.abs()) < MIN_SWIPE_DISTANCE {
+            return None;
+        }
+
+        let direction = if delta.x.abs() >= delta.y.abs() {
+            if delta.x > 0.0 {
+                SwipeDirection::Right
+            } else {
+                SwipeDirection::Left
+            }
+        } else if delta.y > 0.0 {
+            SwipeDirection::Down
+        } else {
+            SwipeDirection::Up
+        };
+
+        Some(direction)
+    }
+
+    /// Get the callback of the pinchbind that a pinch ending at `scale` triggers, if any.
+    fn pinch_callback(&self, fingers: u32, scale: f64) -> Option<CallbackId> {
+        let direction = if scale < 1.0 {
+            PinchDirection::In
+        } else {
+            PinchDirection::Out
+        };
+
+        self.pinchbinds
+            .get(&(fingers, direction))
+            .filter(|bind| (scale - 1.0).abs() >= bind.threshold)
+            .map(|bind| bind.callback_id)
+    }
+}
+
+impl State {
+    pub fn gesture_swipe_begin<I: InputBackend>(&mut self, event: I::GestureSwipeBeginEvent) {
+        let fingers = event.fingers();
+        let inhibited = self.input_inhibitor_state.client().is_some();
+        let gesture = &mut self.input_state.gesture;
+        if !inhibited && gesture.swipebinds.keys().any(|(f, _)| *f == fingers) {
+            gesture.bound = Some(BoundGesture::Swipe {
+                fingers,
+                delta: Point::default(),
+            });
+            return;
+        }
+        gesture.bound = None;
+
+        let Some(pointer) = self.seat.get_pointer() else { return };
+        pointer.gesture_swipe_begin(
+            self,
+            &GestureSwipeBeginEvent {
+                serial: SERIAL_COUNTER.next_serial(),
+                time: event.time_msec(),
+                fingers,
+            },
+        );
+    }
+
+    pub fn gesture_swipe_update<I: InputBackend>(&mut self, event: I::GestureSwipeUpdateEvent) {
+        if let Some(BoundGesture::Swipe { delta, .. }) = self.input_state.gesture.bound.as_mut() {
+            *delta += event.delta();
+            return;
+        }
+
+        let Some(pointer) = self.seat.get_pointer() else { return };
+        pointer.gesture_swipe_update(
+            self,
+            &GestureSwipeUpdateEvent {
+                time: event.time_msec(),
+                delta: event.delta(),
+            },
+        );
+    }
+
+    pub fn gesture_swipe_end<I: InputBackend>(&mut self, event: I::GestureSwipeEndEvent) {
+        let gesture = &mut self.input_state.gesture;
+        if let Some(BoundGesture::Swipe { fingers, delta }) = gesture.bound {
+            gesture.bound = None;
+            if !event.cancelled() {
+                let callback_id = GestureState::swipe_direction(delta)
+                    .and_then(|direction| gesture.swipebinds.get(&(fingers, direction)))
+                    .copied();
+                if let Some(callback_id) = callback_id {
+                    self.call_gesture_callback(callback_id);
+                }
+            }
+            return;
+        }
+
+        let Some(pointer) = self.seat.get_pointer() else { return };
+        pointer.gesture_swipe_end(
+            self,
+            &GestureSwipeEndEvent {
+                serial: SERIAL_COUNTER.next_serial(),
+                time: event.time_msec(),
+                cancelled: event.cancelled(),
+            },
+        );
+    }
+
+    pub fn gesture_pinch_begin<I: InputBackend>(&mut self, event: I::GesturePinchBeginEvent) {
+        let fingers = event.fingers();
+        let inhibited = self.input_inhibitor_state.client().is_some();
+        let gesture = &mut self.input_state.gesture;
+        if !inhibited && gesture.pinchbinds.keys().any(|(f, _)| *f == fingers) {
+            gesture.bound = Some(BoundGesture::Pinch {
+                fingers,
+                scale: 1.0,
+            });
+            return;
+        }
+        gesture.bound = None;
+
+        let Some(pointer) = self.seat.get_pointer() else { return };
+        pointer.gesture_pinch_begin(
+            self,
+            &GesturePinchBeginEvent {
+                serial: SERIAL_COUNTER.next_serial(),
+                time: event.time_msec(),
+                fingers,
+            },
+        );
+    }
+
+    pub fn gesture_pinch_update<I: InputBackend>(&mut self, event: I::GesturePinchUpdateEvent) {
+        if let Some(BoundGesture::Pinch { scale, .. }) = self.input_state.gesture.bound.as_mut() {
+            // The scale is relative to where the pinch began, not the last update
+            *scale = event.scale();
+            return;
+        }
+
+        let Some(pointer) = self.seat.get_pointer() else { return };
+        pointer.gesture_pinch_update(
+            self,
+            &GesturePinchUpdateEvent {
+                time: event.time_msec(),
+                delta: event.delta(),
+                scale: event.scale(),
+                rotation: event.rotation(),
+            },
+        );
+    }
+
+    pub fn gesture_pinch_end<I: InputBackend>(&mut self, event: I::GesturePinchEndEvent) {
+        let gesture = &mut self.input_state.gesture;
+        if let Some(BoundGesture::Pinch { fingers, scale }) = gesture.bound {
+            gesture.bound = None;
+            if !event.cancelled() {
+                if let Some(callback_id) = gesture.pinch_callback(fingers, scale) {
+                    self.call_gesture_callback(callback_id);
+                }
+            }
+            return;
+        }
+
+        let Some(pointer) = self.seat.get_pointer() else { return };
+        pointer.gesture_pinch_end(
+            self,
+            &GesturePinchEndEvent {
+                serial: SERIAL_COUNTER.next_serial(),
+                time: event.time_msec(),
+                cancelled: event.cancelled(),
+            },
+        );
+    }
+
+    pub fn gesture_hold_begin<I: InputBackend>(&mut self, event: I::GestureHoldBeginEvent) {
+        let Some(pointer) = self.seat.get_pointer() else { return };
+        pointer.gesture_hold_begin(
+            self,
+            &GestureHoldBeginEvent {
+                serial: SERIAL_COUNTER.next_serial(),
+                time: event.time_msec(),
+                fingers: event.fingers(),
+            },
+        );
+    }
+
+    pub fn gesture_hold_end<I: InputBackend>(&mut self, event: I::GestureHoldEndEvent) {
+        let Some(pointer) = self.seat.get_pointer() else { return };
+        pointer.gesture_hold_end(
+            self,
+            &GestureHoldEndEvent {
+                serial: SERIAL_COUNTER.next_serial(),
+                time: event.time_msec(),
+                cancelled: event.cancelled(),
+            },
+        );
+    }
+
+    fn call_gesture_callback(&self, callback_id: CallbackId) {
+        let Some(stream) = self.api_state.stream.as_ref() else {
+            return;
+        };
+
+        crate::api::send_to_client(
+            &mut stream.lock().expect("failed to lock api stream"),
+            &OutgoingMsg::call_callback(callback_id, None, &self.api_state.callback_serial),
+        )
+        .expect("failed to call callback");
+    }
+}
@@ -1,9 +1,18 @@
 use smithay::{
-    backend::{input::InputEvent, libinput::LibinputInputBackend},
-    reexports::input::{self, AccelProfile, ClickMethod, ScrollMethod, TapButtonMap},
+    backend::{
+        input::{Device, InputEvent},
+        libinput::LibinputInputBackend,
+    },
+    reexports::input::{
+        self, AccelProfile, ClickMethod, DeviceCapability, ScrollMethod, TapButtonMap,
+    },
 };
 
-use crate::state::State;
+use crate::{
+    api::msg::{Args, OutgoingMsg},
+    output::OutputName,
+    state::State,
+};
 
 #[derive(Debug, serde::Deserialize)]
 #[serde(remote = "AccelProfile")]
@@ -35,7 +44,7 @@ enum TapButtonMapDef {
     LeftMiddleRight,
 }
 
-#[derive(Debug, PartialEq, Copy, Clone, serde::Deserialize)]
+#[derive(Debug, PartialEq, Clone, serde::Deserialize)]
 pub enum LibinputSetting {
     #[serde(with = "AccelProfileDef")]
     AccelProfile(AccelProfile),
@@ -56,6 +65,11 @@ pub enum LibinputSetting {
     TapDragEnabled(bool),
     TapDragLockEnabled(bool),
     TapEnabled(bool),
+    /// Spread absolute pointer events from the device over this output instead of every output.
+    ///
+    /// This isn't a libinput setting, but it's matched to devices like one.
+    #[serde(skip_deserializing)]
+    MapToOutput(Option<OutputName>),
 }
 
 impl LibinputSetting {
@@ -88,10 +102,123 @@ impl LibinputSetting {
                 device.config_tap_set_drag_lock_enabled(*enabled)
             }
             LibinputSetting::TapEnabled(enabled) => device.config_tap_set_enabled(*enabled),
+            LibinputSetting::MapToOutput(_) => Ok(()),
         };
     }
 }
 
+/// Which devices a libinput setting applies to.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+pub enum DeviceMatcher {
+    /// The device with exactly this name.
+    Name(String),
+    /// Devices whose name contains this.
+    NameContains(String),
+    /// Devices of this type.
+    Type(DeviceType),
+}
+
+/// The kind of an input device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum DeviceType {
+    Keyboard,
+    /// Mice, trackballs, and other pointer devices that aren't touchpads.
+    Pointer,
+    Touchpad,
+    Touch,
+    Tablet,
+    Switch,
+}
+
+impl DeviceType {
+    const ALL: [DeviceType; 6] = [
+        DeviceType::Keyboard,
+        DeviceType::Pointer,
+        DeviceType::Touchpad,
+        DeviceType::Touch,
+        DeviceType::Tablet,
+        DeviceType::Switch,
+    ];
+
+    /// Get every type `device` is. Some devices, like keyboards with a touchpad,
+    /// are more than one.
+    pub fn of(device: &input::Device) -> Vec<DeviceType> {
+        Self::ALL
+            .into_iter()
+            .filter(|device_type| device_type.matches(device))
+            .collect()
+    }
+
+    fn matches(&self, device: &input::Device) -> bool {
+        // Only touchpads support tapping
+        let is_touchpad = device.config_tap_finger_count() > 0;
+        match self {
+            DeviceType::Keyboard => device.has_capability(DeviceCapability::Keyboard),
+            DeviceType::Pointer => device.has_capability(DeviceCapability::Pointer) && !is_touchpad,
+            DeviceType::Touchpad => is_touchpad,
+            DeviceType::Touch => device.has_capability(DeviceCapability::Touch),
+            DeviceType::Tablet => device.has_capability(DeviceCapability::TabletTool),
+            DeviceType::Switch => device.has_capability(DeviceCapability::Switch),
+        }
+    }
+}
+
+impl DeviceMatcher {
+    fn matches(&self, device: &input::Device) -> bool {
+        match self {
+            DeviceMatcher::Name(name) => device.name() == name,
+            DeviceMatcher::NameContains(name) => device.name().contains(name.as_str()),
+            DeviceMatcher::Type(device_type) => device_type.matches(device),
+        }
+    }
+
+    /// How specific this matcher is. Settings for more specific matchers
+    /// win over ones for less specific matchers.
+    fn specificity(&self) -> u8 {
+        match self {
+            DeviceMatcher::Type(_) => 1,
+            DeviceMatcher::NameContains(_) => 2,
+            DeviceMatcher::Name(_) => 3,
+        }
+    }
+}
+
+/// A libinput setting along with the devices it applies to, or all devices if `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceSetting {
+    pub device: Option<DeviceMatcher>,
+    pub setting: LibinputSetting,
+}
+
+impl DeviceSetting {
+    fn matches(&self, device: &input::Device) -> bool {
+        match &self.device {
+            Some(matcher) => matcher.matches(device),
+            None => true,
+        }
+    }
+
+    fn specificity(&self) -> u8 {
+        self.device.as_ref().map_or(0, DeviceMatcher::specificity)
+    }
+}
+
+/// Apply the settings in `settings` that match `device`, with more specific
+/// settings applied last so they win.
+fn apply_settings_to_device(settings: &[DeviceSetting], device: &mut input::Device) {
+    let mut matching = settings
+        .iter()
+        .filter(|setting| setting.matches(device))
+        .collect::<Vec<_>>();
+    // This is stable, so settings for the same matcher keep the order they were set in
+    matching.sort_by_key(|setting| setting.specificity());
+
+    for setting in matching {
+        // We're just gonna indiscriminately apply everything and ignore errors
+        setting.setting.apply_to_device(device);
+    }
+}
+
 // We want to completely replace old settings, so we hash only the discriminant.
 impl std::hash::Hash for LibinputSetting {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
@@ -100,8 +227,59 @@ impl std::hash::Hash for LibinputSetting {
 }
 
 impl State {
-    /// Apply current libinput settings to new devices.
-    pub fn apply_libinput_settings(&mut self, event: &InputEvent<LibinputInputBackend>) {
+    /// Set a libinput setting for the devices `device` matches, or all devices if `None`.
+    ///
+    /// This replaces the same kind of setting for the same devices. Settings are kept
+    /// for devices that aren't connected and applied when they are.
+    pub fn set_libinput_setting(
+        &mut self,
+        device: Option<DeviceMatcher>,
+        setting: LibinputSetting,
+    ) {
+        let settings = &mut self.input_state.libinput_settings;
+        settings.retain(|old| {
+            old.device != device
+                || std::mem::discriminant(&old.setting) != std::mem::discriminant(&setting)
+        });
+        settings.push(DeviceSetting { device, setting });
+
+        // Reapply everything so a new general setting doesn't override a more specific one
+        for dev in self.input_state.libinput_devices.iter_mut() {
+            apply_settings_to_device(&self.input_state.libinput_settings, dev);
+        }
+    }
+
+    /// Get the name of the output that the device with `device_id` is mapped to, if any.
+    ///
+    /// `device_id` is the id smithay gives the device.
+    pub fn mapped_output(&self, device_id: &str) -> Option<OutputName> {
+        let device = self
+            .input_state
+            .libinput_devices
+            .iter()
+            .find(|dev| Device::id(*dev) == device_id)?;
+
+        // The last of the most specific settings wins, like when applying them
+        self.input_state
+            .libinput_settings
+            .iter()
+            .filter(|setting| setting.matches(device))
+            .filter_map(|setting| match &setting.setting {
+                LibinputSetting::MapToOutput(output_name) => {
+                    Some((setting.specificity(), output_name))
+                }
+                _ => None,
+            })
+            .max_by_key(|(specificity, _)| *specificity)
+            .and_then(|(_, output_name)| output_name.clone())
+    }
+
+    /// Keep track of added and removed devices.
+    ///
+    /// New devices get the current libinput settings and keyboard leds, and the config
+    /// is told about them. A device that is unplugged and plugged back in gets its settings
+    /// applied the same way again, since removed devices are forgotten.
+    pub fn handle_libinput_device_event(&mut self, event: &InputEvent<LibinputInputBackend>) {
         let mut device = match event {
             InputEvent::DeviceAdded { device } => device.clone(),
             InputEvent::DeviceRemoved { device } => {
@@ -117,10 +295,36 @@ impl State {
             return;
         }
 
-        for setting in self.input_state.libinput_settings.iter() {
-            setting.apply_to_device(&mut device);
+        apply_settings_to_device(&self.input_state.libinput_settings, &mut device);
+
+        if let Some(leds) = self.keyboard_leds() {
+            device.led_update(leds);
         }
 
+        self.signal_device_added(&device);
+
         self.input_state.libinput_devices.push(device);
     }
+
+    /// Run the config's device added callbacks with `device`.
+    fn signal_device_added(&self, device: &input::Device) {
+        let Some(stream) = self.api_state.stream.as_ref() else {
+            return;
+        };
+
+        for callback_id in self.config.device_added_callback_ids.iter() {
+            crate::api::send_to_client(
+                &mut stream.lock().expect("couldn't lock stream"),
+                &OutgoingMsg::call_callback(
+                    *callback_id,
+                    Some(Args::DeviceAdded {
+                        name: device.name().to_string(),
+                        device_types: DeviceType::of(device),
+                    }),
+                    &self.api_state.callback_serial,
+                ),
+            )
+            .expect("Send to client failed");
+        }
+    }
 }
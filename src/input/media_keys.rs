@@ -0,0 +1,185 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Built-in handling for common XF86 media keys.
+//!
+//! These are consulted after user keybinds, so anything the config binds takes precedence.
+
+use std::{collections::HashMap, path::Path};
+
+use xkbcommon::xkb::{keysyms, Keysym};
+
+#[derive(Debug)]
+pub struct MediaKeys {
+    /// Whether or not the compositor should respond to media keys at all.
+    pub enabled: bool,
+    /// The commands that will be spawned when the corresponding key is pressed.
+    pub commands: HashMap<Keysym, Vec<String>>,
+}
+
+impl MediaKeys {
+    /// Create a new, disabled set of media keys populated with default commands
+    /// for whatever programs were found in `$PATH`.
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            commands: default_commands(),
+        }
+    }
+
+    /// Get the command for `keysym` if media keys are enabled.
+    pub fn command_for(&self, keysym: Keysym) -> Option<&Vec<String>> {
+        if !self.enabled {
+            return None;
+        }
+
+        self.commands.get(&keysym)
+    }
+
+    /// Get the command for a key press if media keys are enabled.
+    ///
+    /// The press's keysym with modifiers applied, `mod_sym`, is looked up first, then
+    /// `raw_sym` without them.
+    pub fn command_for_press(
+        &self,
+        mod_sym: Keysym,
+        raw_sym: Option<Keysym>,
+    ) -> Option<&Vec<String>> {
+        self.command_for(mod_sym)
+            .or_else(|| raw_sym.and_then(|raw_sym| self.command_for(raw_sym)))
+    }
+}
+
+impl Default for MediaKeys {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Build the default media key commands from programs that exist in `$PATH`.
+fn default_commands() -> HashMap<Keysym, Vec<String>> {
+    let mut commands = HashMap::new();
+
+    let mut insert = |raw: u32, command: &[&str]| {
+        commands.insert(
+            Keysym::from(raw),
+            command.iter().map(|s| s.to_string()).collect::<Vec<_>>(),
+        );
+    };
+
+    if program_in_path("wpctl") {
+        insert(
+            keysyms::KEY_XF86AudioRaiseVolume,
+            &[
+                "wpctl",
+                "set-volume",
+                "-l",
+                "1.0",
+                "@DEFAULT_AUDIO_SINK@",
+                "5%+",
+            ],
+        );
+        insert(
+            keysyms::KEY_XF86AudioLowerVolume,
+            &["wpctl", "set-volume", "@DEFAULT_AUDIO_SINK@", "5%-"],
+        );
+        insert(
+            keysyms::KEY_XF86AudioMute,
+            &["wpctl", "set-mute", "@DEFAULT_AUDIO_SINK@", "toggle"],
+        );
+        insert(
+            keysyms::KEY_XF86AudioMicMute,
+            &["wpctl", "set-mute", "@DEFAULT_AUDIO_SOURCE@", "toggle"],
+        );
+    }
+
+    if program_in_path("brightnessctl") {
+        insert(
+            keysyms::KEY_XF86MonBrightnessUp,
+            &["brightnessctl", "set", "5%+"],
+        );
+        insert(
+            keysyms::KEY_XF86MonBrightnessDown,
+            &["brightnessctl", "set", "5%-"],
+        );
+    }
+
+    if program_in_path("playerctl") {
+        insert(keysyms::KEY_XF86AudioPlay, &["playerctl", "play-pause"]);
+        insert(keysyms::KEY_XF86AudioPause, &["playerctl", "pause"]);
+        insert(keysyms::KEY_XF86AudioNext, &["playerctl", "next"]);
+        insert(keysyms::KEY_XF86AudioPrev, &["playerctl", "previous"]);
+        insert(keysyms::KEY_XF86AudioStop, &["playerctl", "stop"]);
+    }
+
+    commands
+}
+
+/// Check if `program` is an existing file in any directory in `$PATH`.
+fn program_in_path(program: &str) -> bool {
+    let Some(path) = std::env::var_os("PATH") else {
+        return false;
+    };
+
+    std::env::split_paths(&path).any(|dir| Path::new(&dir).join(program).is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn media_keys() -> MediaKeys {
+        MediaKeys {
+            enabled: true,
+            commands: HashMap::from([(
+                Keysym::from(keysyms::KEY_XF86AudioMute),
+                vec!["mute".to_string()],
+            )]),
+        }
+    }
+
+    #[test]
+    fn looks_up_modified_keysym() {
+        let media_keys = media_keys();
+        assert_eq!(
+            media_keys.command_for_press(Keysym::from(keysyms::KEY_XF86AudioMute), None),
+            Some(&vec!["mute".to_string()])
+        );
+    }
+
+    #[test]
+    fn falls_back_to_raw_keysym() {
+        // E.g. a layout where a modifier changes what the key produces
+        let media_keys = media_keys();
+        assert_eq!(
+            media_keys.command_for_press(
+                Keysym::from(keysyms::KEY_a),
+                Some(Keysym::from(keysyms::KEY_XF86AudioMute))
+            ),
+            Some(&vec!["mute".to_string()])
+        );
+    }
+
+    #[test]
+    fn unknown_keys_have_no_command() {
+        let media_keys = media_keys();
+        assert_eq!(
+            media_keys.command_for_press(
+                Keysym::from(keysyms::KEY_XF86AudioPlay),
+                Some(Keysym::from(keysyms::KEY_a))
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn disabled_media_keys_have_no_command() {
+        let media_keys = MediaKeys {
+            enabled: false,
+            ..media_keys()
+        };
+        assert_eq!(
+            media_keys.command_for_press(Keysym::from(keysyms::KEY_XF86AudioMute), None),
+            None
+        );
+    }
+}
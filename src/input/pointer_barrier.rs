@@ -0,0 +1,219 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Keeping the pointer on an output.
+//!
+//! The pointer can either be locked to one output, or made to stick to output edges so that it
+//! only crosses to another output after being pushed some distance past the edge.
+
+use std::time::Duration;
+
+use smithay::{
+    input::pointer::MotionEvent,
+    utils::{Logical, Point, Rectangle, SERIAL_COUNTER},
+};
+
+use crate::{output::OutputName, state::State};
+
+#[derive(Debug, Default)]
+pub struct PointerBarrier {
+    /// The output the pointer is locked to, if any.
+    pub locked_output: Option<OutputName>,
+    /// How far past an output's edge the pointer needs to be pushed before it crosses over.
+    pub edge_resistance: f64,
+    /// How far past the current output's edge the pointer has been pushed so far.
+    overshoot: f64,
+}
+
+impl State {
+    /// Move the pointer from `from` to `to`, applying the output lock and edge resistance.
+    ///
+    /// Returns where the pointer should end up.
+    pub fn constrain_pointer_motion(
+        &mut self,
+        from: Point<f64, Logical>,
+        to: Point<f64, Logical>,
+    ) -> Point<f64, Logical> {
+        if let Some(geo) = self.locked_output_geometry() {
+            self.input_state.pointer_barrier.overshoot = 0.0;
+            return clamp_to_rect(to, geo);
+        }
+
+        let to = self.clamp_coords(to);
+
+        let resistance = self.input_state.pointer_barrier.edge_resistance;
+        if resistance <= 0.0 {
+            return to;
+        }
+
+        let Some(geo) = self
+            .space
+            .output_under(from)
+            .next()
+            .and_then(|op| self.space.output_geometry(op))
+        else {
+            return to;
+        };
+
+        if geo.to_f64().contains(to) {
+            self.input_state.pointer_barrier.overshoot = 0.0;
+            return to;
+        }
+
+        // The pointer is trying to cross onto another output
+        let clamped = clamp_to_rect(to, geo);
+        let overshoot = to - clamped;
+        let barrier = &mut self.input_state.pointer_barrier;
+        barrier.overshoot += (overshoot.x.powi(2) + overshoot.y.powi(2)).sqrt();
+
+        if barrier.overshoot >= resistance {
+            barrier.overshoot = 0.0;
+            to
+        } else {
+            clamped
+        }
+    }
+
+    /// Lock the pointer to the output with `output_name`, or unlock it if `None`.
+    pub fn lock_pointer_to_output(&mut self, output_name: Option<OutputName>) {
+        tracing::info!("Locking pointer to output {output_name:?}");
+        self.input_state.pointer_barrier.locked_output = output_name;
+        self.reclamp_pointer();
+    }
+
+    /// Move the pointer back onto the locked output, or onto the nearest output if it isn't
+    /// locked.
+    ///
+    /// This should be called whenever the output layout changes. If the locked output is gone,
+    /// the pointer gets unlocked. If there are no outputs, the pointer is left where it is.
+    pub fn reclamp_pointer(&mut self) {
+        let clamped = match self.locked_output_geometry() {
+            Some(geo) => clamp_to_rect(self.pointer_location, geo),
+            None => {
+                if let Some(output_name) = self.input_state.pointer_barrier.locked_output.take() {
+                    tracing::info!("Locked output {} is gone, unlocking pointer", output_name.0);
+                }
+
+                let output_geos = self
+                    .space
+                    .outputs()
+                    .filter_map(|output| self.space.output_geometry(output));
+                let Some(clamped) = clamp_to_nearest_rect(self.pointer_location, output_geos)
+                else {
+                    return;
+                };
+                clamped
+            }
+        };
+
+        if clamped == self.pointer_location {
+            return;
+        }
+
+        self.pointer_location = clamped;
+
+        let Some(pointer) = self.seat.get_pointer() else {
+            return;
+        };
+
+        pointer.motion(
+            self,
+            self.surface_under(clamped),
+            &MotionEvent {
+                location: clamped,
+                serial: SERIAL_COUNTER.next_serial(),
+                time: Duration::from(self.clock.now()).as_millis() as u32,
+            },
+        );
+        pointer.frame(self);
+
+        if let Some(output) = self.space.output_under(clamped).next().cloned() {
+            self.focus_state.focused_output = Some(output.clone());
+            self.schedule_render(&output);
+        }
+    }
+
+    fn locked_output_geometry(&self) -> Option<Rectangle<i32, Logical>> {
+        let output = self
+            .input_state
+            .pointer_barrier
+            .locked_output
+            .as_ref()?
+            .output(self)?;
+        self.space.output_geometry(&output)
+    }
+}
+
+/// Clamp `point` so it lies within `rect`.
+fn clamp_to_rect(point: Point<f64, Logical>, rect: Rectangle<i32, Logical>) -> Point<f64, Logical> {
+    let rect = rect.to_f64();
+    // Subtract one so the pointer doesn't end up on a neighboring output
+    let x = point.x.clamp(rect.loc.x, rect.loc.x + rect.size.w - 1.0);
+    let y = point.y.clamp(rect.loc.y, rect.loc.y + rect.size.h - 1.0);
+    (x, y).into()
+}
+
+/// Clamp `point` so it lies within whichever of `rects` is nearest to it.
+///
+/// Returns `None` if there are no rects.
+fn clamp_to_nearest_rect(
+    point: Point<f64, Logical>,
+    rects: impl IntoIterator<Item = Rectangle<i32, Logical>>,
+) -> Option<Point<f64, Logical>> {
+    let distance = |other: &Point<f64, Logical>| (point.x - other.x).hypot(point.y - other.y);
+
+    rects
+        .into_iter()
+        .map(|rect| clamp_to_rect(point, rect))
+        .min_by(|a, b| f64::total_cmp(&distance(a), &distance(b)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: i32, y: i32, w: i32, h: i32) -> Rectangle<i32, Logical> {
+        Rectangle::from_loc_and_size((x, y), (w, h))
+    }
+
+    fn is_on(point: Point<f64, Logical>, rect: Rectangle<i32, Logical>) -> bool {
+        rect.to_f64().contains(point)
+    }
+
+    #[test]
+    fn pointer_on_unplugged_output_moves_onto_remaining_one() {
+        // The output at x = 1920 was unplugged with the pointer on it
+        let remaining = rect(0, 0, 1920, 1080);
+        let clamped = clamp_to_nearest_rect((2500.0, 500.0).into(), [remaining]).unwrap();
+
+        assert_eq!(clamped, (1919.0, 500.0).into());
+        assert!(is_on(clamped, remaining));
+    }
+
+    #[test]
+    fn pointer_moves_onto_nearest_output() {
+        let left = rect(0, 0, 1920, 1080);
+        let below = rect(1920, 1080, 1280, 1024);
+
+        let clamped = clamp_to_nearest_rect((2200.0, 900.0).into(), [left, below]).unwrap();
+        assert_eq!(clamped, (2200.0, 1080.0).into());
+        assert!(is_on(clamped, below));
+
+        let clamped = clamp_to_nearest_rect((2000.0, 100.0).into(), [left, below]).unwrap();
+        assert_eq!(clamped, (1919.0, 100.0).into());
+        assert!(is_on(clamped, left));
+    }
+
+    #[test]
+    fn pointer_on_an_output_stays_put() {
+        let point = (800.0, 600.0).into();
+        assert_eq!(
+            clamp_to_nearest_rect(point, [rect(0, 0, 1920, 1080), rect(1920, 0, 1920, 1080)]),
+            Some(point)
+        );
+    }
+
+    #[test]
+    fn no_outputs_leave_pointer_alone() {
+        assert_eq!(clamp_to_nearest_rect((10.0, 10.0).into(), []), None);
+    }
+}
@@ -0,0 +1,403 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Asking the user for a line of text without an external launcher.
+//!
+//! While a prompt is open, key presses go to it instead of keybinds or the focused window,
+//! except for the kill and reload keybinds and VT switching. Releases still go through,
+//! so keys that were held when the prompt opened don't get stuck in the focused window.
+//!
+//! Enter finishes the prompt and Escape cancels it. Backspace deletes the last character,
+//! or cancels a compose sequence in progress. Dead keys and the compose key go through the
+//! compose table of the locale Pinnacle was started in.
+//!
+//! Only one prompt can be open at a time; asking for another while one is open cancels the new
+//! one right away. A prompt is cancelled if the client that opened it disconnects.
+
+use std::{
+    ffi::OsString,
+    fmt,
+    os::unix::net::UnixStream,
+    sync::{atomic::AtomicU64, Arc, Mutex},
+};
+
+use xkbcommon::xkb::{self, compose, keysyms, Keysym};
+
+use crate::{
+    api::msg::{Args, CallbackId, OutgoingMsg},
+    state::State,
+};
+
+#[derive(Debug, Default)]
+pub struct PromptState {
+    /// The prompt that is open, if any
+    open: Option<OpenPrompt>,
+}
+
+impl PromptState {
+    /// Whether a prompt is open and taking key presses.
+    pub fn is_open(&self) -> bool {
+        self.open.is_some()
+    }
+
+    /// Close the open prompt without responding if the client on `stream` opened it,
+    /// returning whether it did.
+    fn close_opened_by(&mut self, stream: &Arc<Mutex<UnixStream>>) -> bool {
+        let opened_by_stream = self
+            .open
+            .as_ref()
+            .is_some_and(|prompt| Arc::ptr_eq(&prompt.stream, stream));
+
+        if opened_by_stream {
+            self.open = None;
+        }
+        opened_by_stream
+    }
+}
+
+/// What a key press did to an open prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PromptKey {
+    Submit,
+    Cancel,
+    /// Text was typed or backspaced
+    Edit,
+    Ignored,
+}
+
+struct OpenPrompt {
+    stream: Arc<Mutex<UnixStream>>,
+    callback_id: CallbackId,
+    /// The callback serial of a one-off client. The config uses the shared one in `ApiState`.
+    serial: AtomicU64,
+    text: String,
+    /// `None` if the locale has no compose table
+    compose: Option<compose::State>,
+}
+
+impl fmt::Debug for OpenPrompt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OpenPrompt")
+            .field("callback_id", &self.callback_id)
+            .field("text", &self.text)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OpenPrompt {
+    /// Type `keysym`, going through the compose table if there is one.
+    fn key(&mut self, keysym: Keysym, typing: bool) -> PromptKey {
+        match keysym.raw() {
+            keysyms::KEY_Escape => PromptKey::Cancel,
+            keysyms::KEY_Return | keysyms::KEY_KP_Enter => PromptKey::Submit,
+            keysyms::KEY_BackSpace => {
+                match self.compose.as_mut() {
+                    Some(compose) if compose.status() == compose::Status::Composing => {
+                        compose.reset();
+                    }
+                    _ => {
+                        self.text.pop();
+                    }
+                }
+                PromptKey::Edit
+            }
+            _ if typing => {
+                let typed = match self.compose.as_mut() {
+                    Some(compose) => match compose.feed(keysym) {
+                        compose::FeedResult::Accepted => match compose.status() {
+                            compose::Status::Composing => None,
+                            compose::Status::Composed => {
+                                let composed = compose.utf8();
+                                compose.reset();
+                                composed
+                            }
+                            compose::Status::Cancelled => {
+                                compose.reset();
+                                None
+                            }
+                            compose::Status::Nothing => keysym_text(keysym),
+                        },
+                        compose::FeedResult::Ignored => keysym_text(keysym),
+                    },
+                    None => keysym_text(keysym),
+                };
+
+                match typed {
+                    Some(typed) => {
+                        self.text.push_str(&typed);
+                        PromptKey::Edit
+                    }
+                    None => PromptKey::Ignored,
+                }
+            }
+            _ => PromptKey::Ignored,
+        }
+    }
+}
+
+impl State {
+    /// Open a prompt with `title` on the focused output, calling the callback with
+    /// `callback_id` on `stream` with the text once it's finished or cancelled.
+    pub fn start_prompt(
+        &mut self,
+        title: String,
+        callback_id: CallbackId,
+        stream: Arc<Mutex<UnixStream>>,
+    ) {
+        let mut prompt = OpenPrompt {
+            stream,
+            callback_id,
+            serial: AtomicU64::new(0),
+            text: String::new(),
+            compose: None,
+        };
+
+        if self.input_state.prompt.is_open() {
+            tracing::warn!("A prompt is already open, cancelling {title:?}");
+            self.send_prompt_result(&prompt, None);
+            return;
+        }
+
+        let Some(output) = self.focus_state.focused_output.clone() else {
+            self.send_prompt_result(&prompt, None);
+            return;
+        };
+
+        prompt.compose = new_compose_state();
+        self.input_state.prompt.open = Some(prompt);
+        self.show_prompt_box(&output, title);
+    }
+
+    /// Type `keysym` into the open prompt, finishing or cancelling it on Enter or Escape.
+    ///
+    /// Characters are only typed if `typing` is true, so keys held with modifiers like Ctrl
+    /// don't add anything.
+    pub fn prompt_key(&mut self, keysym: Keysym, typing: bool) {
+        let Some(prompt) = self.input_state.prompt.open.as_mut() else {
+            return;
+        };
+
+        match prompt.key(keysym, typing) {
+            PromptKey::Submit => self.finish_prompt(true),
+            PromptKey::Cancel => self.finish_prompt(false),
+            PromptKey::Edit => {
+                let text = prompt.text.clone();
+                self.set_prompt_box_text(&text);
+            }
+            PromptKey::Ignored => (),
+        }
+    }
+
+    /// Cancel the open prompt if the client on `stream` opened it.
+    pub fn cancel_prompt_from(&mut self, stream: &Arc<Mutex<UnixStream>>) {
+        // The client is gone, so there's no one to tell
+        if self.input_state.prompt.close_opened_by(stream) {
+            self.hide_prompt_box();
+        }
+    }
+
+    /// Close the open prompt, sending back what was typed if `submit` is true.
+    fn finish_prompt(&mut self, submit: bool) {
+        let Some(prompt) = self.input_state.prompt.open.take() else {
+            return;
+        };
+        self.hide_prompt_box();
+
+        let text = submit.then(|| prompt.text.clone());
+        self.send_prompt_result(&prompt, text);
+    }
+
+    fn send_prompt_result(&self, prompt: &OpenPrompt, text: Option<String>) {
+        let serial = match self.api_state.stream.as_ref() {
+            Some(stream) if Arc::ptr_eq(stream, &prompt.stream) => &*self.api_state.callback_serial,
+            _ => &prompt.serial,
+        };
+
+        if let Err(err) = crate::api::send_to_client(
+            &mut prompt.stream.lock().expect("couldn't lock stream"),
+            &OutgoingMsg::call_callback(
+                prompt.callback_id,
+                Some(Args::PromptFinished { text }),
+                serial,
+            ),
+        ) {
+            tracing::warn!("Failed to send prompt result: {err}");
+        }
+    }
+}
+
+/// Get the text `keysym` types on its own, if it types any.
+fn keysym_text(keysym: Keysym) -> Option<String> {
+    char::from_u32(xkb::keysym_to_utf32(keysym))
+        .filter(|c| !c.is_control())
+        .map(String::from)
+}
+
+/// Load the compose table for the locale Pinnacle was started in.
+fn new_compose_state() -> Option<compose::State> {
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .into_iter()
+        .find_map(|var| std::env::var_os(var).filter(|value| !value.is_empty()))
+        .unwrap_or_else(|| OsString::from("C"));
+
+    let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+    let table = compose::Table::new_from_locale(&context, &locale, compose::COMPILE_NO_FLAGS)
+        .map_err(|()| tracing::debug!("No compose table for locale {locale:?}"))
+        .ok()?;
+
+    Some(compose::State::new(&table, compose::STATE_NO_FLAGS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMPOSE: &str = r#"
+<dead_acute> <e> : "é" eacute
+<Multi_key> <a> <e> : "æ" ae
+"#;
+
+    fn open_prompt(compose: bool) -> OpenPrompt {
+        let compose = compose.then(|| {
+            let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+            let table = compose::Table::new_from_buffer(
+                &context,
+                COMPOSE,
+                "C",
+                compose::FORMAT_TEXT_V1,
+                compose::COMPILE_NO_FLAGS,
+            )
+            .expect("compose table should parse");
+            compose::State::new(&table, compose::STATE_NO_FLAGS)
+        });
+
+        let (stream, _) = UnixStream::pair().unwrap();
+        OpenPrompt {
+            stream: Arc::new(Mutex::new(stream)),
+            callback_id: CallbackId(0),
+            serial: AtomicU64::new(0),
+            text: String::new(),
+            compose,
+        }
+    }
+
+    fn press(prompt: &mut OpenPrompt, keysyms: &[u32]) -> Vec<PromptKey> {
+        keysyms
+            .iter()
+            .map(|keysym| prompt.key(Keysym::from(*keysym), true))
+            .collect()
+    }
+
+    #[test]
+    fn punctuation_and_non_ascii_are_typed() {
+        let mut prompt = open_prompt(false);
+
+        press(
+            &mut prompt,
+            &[
+                keysyms::KEY_semicolon,
+                keysyms::KEY_space,
+                keysyms::KEY_asciitilde,
+                keysyms::KEY_ssharp,
+                keysyms::KEY_Cyrillic_zhe,
+            ],
+        );
+
+        assert_eq!(prompt.text, "; ~ßж");
+    }
+
+    #[test]
+    fn keys_held_with_modifiers_dont_type() {
+        let mut prompt = open_prompt(false);
+
+        assert_eq!(
+            prompt.key(Keysym::from(keysyms::KEY_a), false),
+            PromptKey::Ignored
+        );
+        assert_eq!(
+            prompt.key(Keysym::from(keysyms::KEY_Return), false),
+            PromptKey::Submit
+        );
+        assert_eq!(prompt.text, "");
+    }
+
+    #[test]
+    fn enter_submits_and_escape_cancels() {
+        let mut prompt = open_prompt(false);
+
+        assert_eq!(
+            press(
+                &mut prompt,
+                &[
+                    keysyms::KEY_Return,
+                    keysyms::KEY_KP_Enter,
+                    keysyms::KEY_Escape
+                ]
+            ),
+            [PromptKey::Submit, PromptKey::Submit, PromptKey::Cancel]
+        );
+    }
+
+    #[test]
+    fn dead_keys_and_the_compose_key_compose() {
+        let mut prompt = open_prompt(true);
+
+        let keys = press(&mut prompt, &[keysyms::KEY_dead_acute, keysyms::KEY_e]);
+        assert_eq!(keys, [PromptKey::Ignored, PromptKey::Edit]);
+        assert_eq!(prompt.text, "é");
+
+        press(
+            &mut prompt,
+            &[keysyms::KEY_Multi_key, keysyms::KEY_a, keysyms::KEY_e],
+        );
+        assert_eq!(prompt.text, "éæ");
+    }
+
+    #[test]
+    fn backspace_cancels_a_compose_sequence_before_deleting() {
+        let mut prompt = open_prompt(true);
+
+        press(
+            &mut prompt,
+            &[
+                keysyms::KEY_x,
+                keysyms::KEY_Multi_key,
+                keysyms::KEY_a,
+                keysyms::KEY_BackSpace,
+            ],
+        );
+        assert_eq!(prompt.text, "x");
+
+        press(&mut prompt, &[keysyms::KEY_e, keysyms::KEY_BackSpace]);
+        assert_eq!(prompt.text, "x");
+    }
+
+    #[test]
+    fn keys_that_break_a_compose_sequence_type_nothing() {
+        let mut prompt = open_prompt(true);
+
+        press(
+            &mut prompt,
+            &[keysyms::KEY_dead_acute, keysyms::KEY_q, keysyms::KEY_q],
+        );
+
+        assert_eq!(prompt.text, "q");
+    }
+
+    #[test]
+    fn prompt_is_closed_when_the_client_that_opened_it_disconnects() {
+        let prompt = open_prompt(false);
+        let opener = prompt.stream.clone();
+        let (other, _) = UnixStream::pair().unwrap();
+        let other = Arc::new(Mutex::new(other));
+
+        let mut state = PromptState { open: Some(prompt) };
+
+        assert!(!state.close_opened_by(&other));
+        assert!(state.is_open());
+
+        assert!(state.close_opened_by(&opener));
+        assert!(!state.is_open());
+
+        assert!(!state.close_opened_by(&opener));
+    }
+}
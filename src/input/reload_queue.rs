@@ -0,0 +1,168 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Keybinds pressed while the config reloads.
+//!
+//! Between the old config's keybinds being cleared and the new config registering its own,
+//! there are no keybinds, so presses would go to the focused window instead. Presses with
+//! modifiers other than Shift are held back instead. Once the new config says it's ready,
+//! they're looked up in its keybinds and called in order. Presses it didn't bind are dropped.
+//!
+//! Only a few presses are kept. If the reload times out, they're all dropped.
+
+use smithay::input::keyboard::keysyms;
+use xkbcommon::xkb::Keysym;
+
+use crate::{
+    api::msg::{KeyEdge, ModifierMask},
+    state::State,
+};
+
+/// The most keybind presses kept during a reload.
+const MAX_QUEUED_KEYBINDS: usize = 16;
+
+#[derive(Debug, Default)]
+pub struct ReloadKeybindQueue {
+    presses: Vec<QueuedKeybind>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct QueuedKeybind {
+    pub modifier_mask: ModifierMask,
+    pub mod_sym: Keysym,
+    pub raw_sym: Option<Keysym>,
+}
+
+/// Whether `keysym` is a modifier key itself, like `Control_L`.
+pub fn is_modifier_key(keysym: Keysym) -> bool {
+    matches!(
+        keysym.raw(),
+        keysyms::KEY_Shift_L..=keysyms::KEY_Hyper_R
+            | keysyms::KEY_ISO_Lock..=keysyms::KEY_ISO_Level5_Lock
+            | keysyms::KEY_Mode_switch
+            | keysyms::KEY_Num_Lock
+    )
+}
+
+impl ReloadKeybindQueue {
+    /// Queue `press`, returning whether there was room for it.
+    fn push(&mut self, press: QueuedKeybind) -> bool {
+        if self.presses.len() >= MAX_QUEUED_KEYBINDS {
+            return false;
+        }
+        self.presses.push(press);
+        true
+    }
+
+    /// Take every queued press, oldest first.
+    fn take(&mut self) -> Vec<QueuedKeybind> {
+        std::mem::take(&mut self.presses)
+    }
+}
+
+impl State {
+    /// Hold on to a keybind press until the new config is ready.
+    pub fn queue_keybind_for_reload(&mut self, press: QueuedKeybind) {
+        if self.input_state.reload_queue.push(press) {
+            tracing::debug!("Queueing keybind pressed during config reload");
+        } else {
+            tracing::warn!(
+                "Dropping keybind pressed during config reload, \
+                {MAX_QUEUED_KEYBINDS} are already queued"
+            );
+        }
+    }
+
+    /// Call the new config's keybinds for the presses queued during the reload.
+    pub fn replay_reload_keybinds(&mut self) {
+        let presses = self.input_state.reload_queue.take();
+
+        for press in presses {
+            let QueuedKeybind {
+                modifier_mask,
+                mod_sym,
+                raw_sym,
+            } = press;
+
+            let keybind = self
+                .input_state
+                .keybind(modifier_mask, mod_sym, KeyEdge::Press)
+                .or_else(|| {
+                    raw_sym.and_then(|raw_sym| {
+                        self.input_state
+                            .keybind(modifier_mask, raw_sym, KeyEdge::Press)
+                    })
+                })
+                .map(|keybind| (keybind.callback_id, keybind.data.clone()));
+
+            match keybind {
+                Some((callback_id, data)) => self.call_keybind_callback(callback_id, data),
+                None => tracing::debug!(
+                    "Dropping queued keybind {mod_sym:?}, the new config didn't bind it"
+                ),
+            }
+        }
+    }
+
+    /// Drop the presses queued during the reload without calling anything.
+    pub fn drop_queued_reload_keybinds(&mut self) {
+        let presses = self.input_state.reload_queue.take();
+
+        if !presses.is_empty() {
+            tracing::warn!(
+                "Dropping {} keybind(s) pressed during config reload",
+                presses.len()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(keysym: u32) -> QueuedKeybind {
+        QueuedKeybind {
+            modifier_mask: ModifierMask::from(&[][..]),
+            mod_sym: Keysym::from(keysym),
+            raw_sym: None,
+        }
+    }
+
+    fn keysyms(presses: &[QueuedKeybind]) -> Vec<u32> {
+        presses.iter().map(|press| press.mod_sym.raw()).collect()
+    }
+
+    #[test]
+    fn presses_are_replayed_once_in_order() {
+        let mut queue = ReloadKeybindQueue::default();
+        assert!(queue.push(press(keysyms::KEY_a)));
+        assert!(queue.push(press(keysyms::KEY_b)));
+
+        assert_eq!(keysyms(&queue.take()), vec![keysyms::KEY_a, keysyms::KEY_b]);
+        // A config that says it's ready twice doesn't get the presses twice
+        assert!(queue.take().is_empty());
+    }
+
+    #[test]
+    fn presses_past_the_limit_are_dropped() {
+        let mut queue = ReloadKeybindQueue::default();
+        for _ in 0..MAX_QUEUED_KEYBINDS {
+            assert!(queue.push(press(keysyms::KEY_a)));
+        }
+        assert!(!queue.push(press(keysyms::KEY_b)));
+
+        let presses = queue.take();
+        assert_eq!(presses.len(), MAX_QUEUED_KEYBINDS);
+        assert!(!keysyms(&presses).contains(&keysyms::KEY_b));
+
+        // There's room again for the next reload
+        assert!(queue.push(press(keysyms::KEY_b)));
+    }
+
+    #[test]
+    fn modifier_keys_arent_keybinds() {
+        assert!(is_modifier_key(Keysym::from(keysyms::KEY_Control_L)));
+        assert!(is_modifier_key(Keysym::from(keysyms::KEY_Super_R)));
+        assert!(!is_modifier_key(Keysym::from(keysyms::KEY_a)));
+    }
+}
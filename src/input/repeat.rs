@@ -0,0 +1,177 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Repeating keybinds, which call their callback again while their key is held.
+//!
+//! Keys a keybind intercepts never reach a client, so clients can't repeat them.
+//! Instead, a timer calls the callback at the keyboard's repeat rate after its repeat delay.
+//! Repeats are scheduled from when the key was pressed rather than from when the last repeat
+//! ran, so slow callbacks or a busy event loop don't make them drift below the repeat rate.
+//! Repeating stops when the key or one of the keybind's modifiers is released, when another
+//! key is pressed, or when keyboard focus changes.
+
+use std::time::{Duration, Instant};
+
+use smithay::{
+    backend::input::KeyState,
+    reexports::calloop::{
+        timer::{TimeoutAction, Timer},
+        RegistrationToken,
+    },
+};
+
+use crate::{
+    api::msg::{CallbackId, ModifierMask},
+    state::State,
+};
+
+/// The repeat rate keyboards start with, in repeats per second.
+pub const DEFAULT_REPEAT_RATE: i32 = 25;
+/// The repeat delay keyboards start with, in milliseconds.
+pub const DEFAULT_REPEAT_DELAY: i32 = 500;
+
+#[derive(Debug)]
+pub struct KeyRepeatState {
+    /// The keyboard's repeat rate, in repeats per second
+    pub rate: i32,
+    /// The keyboard's repeat delay, in milliseconds
+    pub delay: i32,
+    /// The repeating keybind being held, if any
+    held: Option<HeldKeybind>,
+}
+
+impl Default for KeyRepeatState {
+    fn default() -> Self {
+        Self {
+            rate: DEFAULT_REPEAT_RATE,
+            delay: DEFAULT_REPEAT_DELAY,
+            held: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct HeldKeybind {
+    keycode: u32,
+    modifier_mask: ModifierMask,
+    timer_token: RegistrationToken,
+}
+
+impl State {
+    /// Start calling `callback_id` with `data` repeatedly until the key with `keycode` is
+    /// released or `modifier_mask` stops being held.
+    pub fn start_keybind_repeat(
+        &mut self,
+        keycode: u32,
+        modifier_mask: ModifierMask,
+        callback_id: CallbackId,
+        data: Option<Vec<u8>>,
+    ) {
+        self.stop_keybind_repeat();
+
+        let repeat = &self.input_state.key_repeat;
+        let delay = Duration::from_millis(repeat.delay as u64);
+        let interval = Duration::from_secs_f64(1.0 / repeat.rate as f64);
+
+        let mut next_repeat = Instant::now() + delay;
+        let keybind_data = data;
+
+        let timer_token =
+            self.loop_handle
+                .insert_source(Timer::from_deadline(next_repeat), move |_, _, data| {
+                    data.state
+                        .call_keybind_callback(callback_id, keybind_data.clone());
+
+                    next_repeat = following_repeat(next_repeat, interval, Instant::now());
+                    TimeoutAction::ToInstant(next_repeat)
+                });
+
+        match timer_token {
+            Ok(timer_token) => {
+                self.input_state.key_repeat.held = Some(HeldKeybind {
+                    keycode,
+                    modifier_mask,
+                    timer_token,
+                });
+            }
+            Err(err) => tracing::error!("Failed to insert keybind repeat timer: {err}"),
+        }
+    }
+
+    /// Stop repeating the held keybind, if any.
+    pub fn stop_keybind_repeat(&mut self) {
+        if let Some(held) = self.input_state.key_repeat.held.take() {
+            self.loop_handle.remove(held.timer_token);
+        }
+    }
+
+    /// Stop repeating the held keybind if this key event ends it.
+    ///
+    /// `modifier_mask` is the modifiers held after the key event.
+    pub fn update_keybind_repeat(
+        &mut self,
+        keycode: u32,
+        press_state: KeyState,
+        modifier_mask: ModifierMask,
+    ) {
+        let Some(held) = self.input_state.key_repeat.held.as_ref() else {
+            return;
+        };
+
+        let stop = match press_state {
+            KeyState::Pressed => true,
+            KeyState::Released => keycode == held.keycode || modifier_mask != held.modifier_mask,
+        };
+
+        if stop {
+            self.stop_keybind_repeat();
+        }
+    }
+}
+
+/// Get when the repeat after the one due at `repeat` is due, at `interval` after it.
+///
+/// Repeats that were missed by `now` are skipped instead of being called all at once.
+fn following_repeat(repeat: Instant, interval: Duration, now: Instant) -> Instant {
+    let mut next = repeat + interval;
+    while next <= now {
+        next += interval;
+    }
+    next
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_millis(40);
+
+    #[test]
+    fn repeats_keep_the_rate_despite_slow_callbacks() {
+        let pressed = Instant::now();
+        let first = pressed + Duration::from_millis(200);
+
+        // The callback took 15ms, which doesn't push the next repeat back
+        let second = following_repeat(first, INTERVAL, first + Duration::from_millis(15));
+        assert_eq!(second, first + INTERVAL);
+    }
+
+    #[test]
+    fn missed_repeats_are_skipped() {
+        let first = Instant::now();
+
+        // The event loop was busy for 130ms, past three repeats
+        let next = following_repeat(first, INTERVAL, first + Duration::from_millis(130));
+        assert_eq!(next, first + INTERVAL * 4);
+    }
+
+    #[test]
+    fn repeats_land_on_the_configured_rate() {
+        // 25 repeats a second after a delay, like xset r rate 200 25
+        let first = Instant::now() + Duration::from_millis(200);
+        let mut repeat = first;
+        for _ in 0..25 {
+            repeat = following_repeat(repeat, INTERVAL, repeat);
+        }
+        assert_eq!(repeat - first, Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,134 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Scaling and inverting scroll events.
+//!
+//! Settings can be set for every device or for devices with a given name, which take precedence.
+//! Unlike libinput settings, these work with every backend.
+//!
+//! This also counts how many times scrollbinds should trigger for a scroll.
+
+use std::collections::HashMap;
+
+use smithay::backend::input::Axis;
+
+/// How far continuous scrolling, like from a touchpad, needs to go to trigger a scrollbind once.
+const SCROLLBIND_DISTANCE: f64 = 100.0;
+
+#[derive(Debug)]
+pub struct ScrollState {
+    /// How much to multiply scroll amounts by for every device.
+    pub factor: f64,
+    /// Whether to flip horizontal scrolling for every device.
+    pub horizontal_invert: bool,
+    /// Settings for devices with a given name, overriding the above.
+    pub devices: HashMap<String, DeviceScrollSettings>,
+    /// Scaled discrete steps that haven't been sent yet, as `[horizontal, vertical]`.
+    discrete_remainder: [f64; 2],
+    /// Scrolling that hasn't triggered a scrollbind yet, in steps, as `[horizontal, vertical]`.
+    bind_remainder: [f64; 2],
+}
+
+impl Default for ScrollState {
+    fn default() -> Self {
+        Self {
+            factor: 1.0,
+            horizontal_invert: false,
+            devices: HashMap::new(),
+            discrete_remainder: [0.0; 2],
+            bind_remainder: [0.0; 2],
+        }
+    }
+}
+
+/// Scroll settings for one device. `None` fields fall back to the settings for every device.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DeviceScrollSettings {
+    pub factor: Option<f64>,
+    pub horizontal_invert: Option<bool>,
+}
+
+impl ScrollState {
+    /// Get the scroll factor for the device with `device_name`.
+    pub fn factor(&self, device_name: &str) -> f64 {
+        self.devices
+            .get(device_name)
+            .and_then(|settings| settings.factor)
+            .unwrap_or(self.factor)
+    }
+
+    /// Get whether horizontal scrolling is flipped for the device with `device_name`.
+    pub fn horizontal_invert(&self, device_name: &str) -> bool {
+        self.devices
+            .get(device_name)
+            .and_then(|settings| settings.horizontal_invert)
+            .unwrap_or(self.horizontal_invert)
+    }
+
+    /// Set the scroll factor for the device with `device_name`, or every device if `None`.
+    pub fn set_factor(&mut self, factor: f64, device_name: Option<String>) {
+        match device_name {
+            Some(name) => self.devices.entry(name).or_default().factor = Some(factor),
+            None => self.factor = factor,
+        }
+    }
+
+    /// Set whether horizontal scrolling is flipped for the device with `device_name`,
+    /// or every device if `None`.
+    pub fn set_horizontal_invert(&mut self, invert: bool, device_name: Option<String>) {
+        match device_name {
+            Some(name) => self.devices.entry(name).or_default().horizontal_invert = Some(invert),
+            None => self.horizontal_invert = invert,
+        }
+    }
+
+    /// Turn a scaled discrete amount into whole steps.
+    ///
+    /// Clients expect discrete steps to be integers, so fractions are held back
+    /// and added to the next event on the same axis.
+    pub fn accumulate_discrete(&mut self, axis: Axis, amount: f64) -> i32 {
+        let remainder = match axis {
+            Axis::Horizontal => &mut self.discrete_remainder[0],
+            Axis::Vertical => &mut self.discrete_remainder[1],
+        };
+
+        // Don't let leftovers from scrolling one way count toward scrolling the other
+        if remainder.signum() != amount.signum() {
+            *remainder = 0.0;
+        }
+
+        *remainder += amount;
+        let steps = remainder.trunc();
+        *remainder -= steps;
+
+        steps as i32
+    }
+
+    /// Get how many times a scrollbind on `axis` should trigger for a scaled scroll `amount`.
+    ///
+    /// Wheel clicks, given as `discrete`, trigger once each. Continuous scrolling triggers once
+    /// every [`SCROLLBIND_DISTANCE`] so a touchpad flick doesn't trigger it dozens of times.
+    pub fn accumulate_bind(&mut self, axis: Axis, amount: f64, discrete: Option<f64>) -> u32 {
+        let remainder = match axis {
+            Axis::Horizontal => &mut self.bind_remainder[0],
+            Axis::Vertical => &mut self.bind_remainder[1],
+        };
+
+        if remainder.signum() != amount.signum() {
+            *remainder = 0.0;
+        }
+
+        *remainder += discrete.unwrap_or(amount / SCROLLBIND_DISTANCE);
+        let steps = remainder.trunc();
+        *remainder -= steps;
+
+        steps.abs() as u32
+    }
+
+    /// Forget scrolling on `axis` that hasn't triggered a scrollbind yet.
+    pub fn reset_bind_remainder(&mut self, axis: Axis) {
+        match axis {
+            Axis::Horizontal => self.bind_remainder[0] = 0.0,
+            Axis::Vertical => self.bind_remainder[1] = 0.0,
+        }
+    }
+}
@@ -0,0 +1,92 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Which keybinds still work while a client wants keys to itself.
+//!
+//! A Wayland client can inhibit shortcuts on its surface, and an X11 client can grab the
+//! keyboard, which Xwayland passes on as an Xwayland keyboard grab. Both go through
+//! [`State::keybind_suppression`] so the keyboard filter treats them the same way.
+
+use crate::state::State;
+
+/// Which keybinds are kept from triggering.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeybindSuppression {
+    /// Every keybind works.
+    #[default]
+    None,
+    /// Only forced keybinds, the kill, reload, and X11 grab break keybinds,
+    /// and VT switching work.
+    AllButReserved,
+    /// Only forced keybinds and VT switching work.
+    AllButForced,
+}
+
+impl KeybindSuppression {
+    /// Get the suppression for a focused surface that may be inhibiting shortcuts or
+    /// have grabbed the keyboard.
+    ///
+    /// A shortcuts inhibitor wins over an X11 grab, since it asks for more.
+    /// With `keep_keybinds`, X11 grabs don't suppress anything.
+    pub fn new(shortcuts_inhibited: bool, x11_grabbed: bool, keep_keybinds: bool) -> Self {
+        if shortcuts_inhibited {
+            Self::AllButForced
+        } else if x11_grabbed && !keep_keybinds {
+            Self::AllButReserved
+        } else {
+            Self::None
+        }
+    }
+
+    /// Whether keybinds not set with `force` are suppressed, along with media keys,
+    /// queueing keybinds during a reload, and bind layers swallowing keys.
+    pub fn keybinds(self) -> bool {
+        self != Self::None
+    }
+
+    /// Whether the kill and reload keybinds are suppressed.
+    pub fn reserved(self) -> bool {
+        self == Self::AllButForced
+    }
+}
+
+impl State {
+    /// Get which keybinds are kept from triggering by the surface with keyboard focus.
+    pub fn keybind_suppression(&self) -> KeybindSuppression {
+        KeybindSuppression::new(
+            self.keyboard_shortcuts_inhibited(),
+            self.xwayland_keyboard_grabbed(),
+            self.xwayland_keyboard_grab_state.keep_keybinds,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn x11_grabs_keep_reserved_keybinds() {
+        let suppression = KeybindSuppression::new(false, true, false);
+        assert_eq!(suppression, KeybindSuppression::AllButReserved);
+        assert!(suppression.keybinds());
+        assert!(!suppression.reserved());
+    }
+
+    #[test]
+    fn keep_keybinds_ignores_x11_grabs() {
+        let suppression = KeybindSuppression::new(false, true, true);
+        assert_eq!(suppression, KeybindSuppression::None);
+        assert!(!suppression.keybinds());
+        assert!(!suppression.reserved());
+    }
+
+    #[test]
+    fn shortcuts_inhibitors_win_over_x11_grabs() {
+        for (x11_grabbed, keep_keybinds) in [(false, false), (true, false), (true, true)] {
+            let suppression = KeybindSuppression::new(true, x11_grabbed, keep_keybinds);
+            assert_eq!(suppression, KeybindSuppression::AllButForced);
+            assert!(suppression.keybinds());
+            assert!(suppression.reserved());
+        }
+    }
+}
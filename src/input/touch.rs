@@ -0,0 +1,296 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Long-press touch gestures.
+//!
+//! Touchscreens can't right click or drag windows around, so holding fingers still does that
+//! instead. Holding one finger for the long-press duration right clicks what's under it, or
+//! starts moving the window under it if the finger is on the window's edge. Holding two fingers
+//! starts moving the window under the first one. The finger that started a move drives the
+//! pointer while it goes on, and lifting it drops the window.
+//!
+//! Each finger is tracked by its slot. A gesture is cancelled if a finger lifts or moves further
+//! than the tolerance before the long press, or if a third finger comes down. A finger coming
+//! down during a move ends the move. Once cancelled, nothing happens until every finger lifts.
+//!
+//! Other touch input isn't sent to clients.
+
+use std::time::Duration;
+
+use smithay::{
+    backend::input::{
+        AbsolutePositionEvent, ButtonState, Device, Event, InputBackend, TouchEvent, TouchSlot,
+    },
+    input::pointer::MotionEvent,
+    reexports::calloop::{
+        timer::{TimeoutAction, Timer},
+        RegistrationToken,
+    },
+    utils::{Logical, Point, SERIAL_COUNTER},
+    wayland::seat::WaylandFocus,
+};
+
+use crate::{focus::FocusTarget, state::State};
+
+use super::map_absolute_position;
+
+/// The left mouse button, which touch window moves act as if they were started with.
+const BUTTON_LEFT: u32 = 0x110;
+/// The right mouse button, which a long press clicks.
+const BUTTON_RIGHT: u32 = 0x111;
+
+/// How far inside a window's edge a long press starts a move instead of right clicking.
+const EDGE_REGION: f64 = 24.0;
+
+/// Which long-press gestures are on and how they're recognized.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TouchGestures {
+    /// Whether long presses do anything.
+    pub enabled: bool,
+    /// How long fingers need to be held, in milliseconds.
+    pub long_press_ms: u64,
+    /// How far a finger can move before it's no longer a long press.
+    pub tolerance: f64,
+}
+
+impl Default for TouchGestures {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            long_press_ms: 500,
+            tolerance: 8.0,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TouchState {
+    pub gestures: TouchGestures,
+    /// The fingers that are down, in the order they came down
+    fingers: Vec<Finger>,
+    gesture: TouchGesture,
+}
+
+#[derive(Debug)]
+struct Finger {
+    slot: TouchSlot,
+    /// Where the finger came down
+    start: Point<f64, Logical>,
+    loc: Point<f64, Logical>,
+}
+
+#[derive(Debug, Default)]
+enum TouchGesture {
+    /// No fingers are down.
+    #[default]
+    Idle,
+    /// Fingers are down and being held still.
+    Pending { timer_token: RegistrationToken },
+    /// The finger in `slot` is moving a window.
+    Moving { slot: TouchSlot },
+    /// The gesture happened or was cancelled, and the fingers haven't all lifted yet.
+    Done,
+}
+
+impl State {
+    pub fn touch_down<I: InputBackend>(&mut self, event: I::TouchDownEvent) {
+        let Some(area) = self.absolute_pointer_area(&event.device().id()) else {
+            return;
+        };
+        let loc = map_absolute_position(
+            (event.x_transformed(1), event.y_transformed(1)).into(),
+            area,
+        );
+
+        let touch = &mut self.input_state.touch;
+        touch.fingers.push(Finger {
+            slot: event.slot(),
+            start: loc,
+            loc,
+        });
+        let finger_count = touch.fingers.len();
+
+        match std::mem::take(&mut touch.gesture) {
+            TouchGesture::Idle if touch.gestures.enabled => self.start_long_press_timer(),
+            // A second finger turns the long press into a two-finger one, which starts over
+            TouchGesture::Pending { timer_token } if finger_count == 2 => {
+                self.loop_handle.remove(timer_token);
+                self.start_long_press_timer();
+            }
+            TouchGesture::Pending { timer_token } => {
+                self.loop_handle.remove(timer_token);
+                self.input_state.touch.gesture = TouchGesture::Done;
+            }
+            TouchGesture::Moving { .. } => {
+                self.end_touch_move(event.time_msec());
+                self.input_state.touch.gesture = TouchGesture::Done;
+            }
+            TouchGesture::Idle | TouchGesture::Done => {
+                self.input_state.touch.gesture = TouchGesture::Done;
+            }
+        }
+    }
+
+    pub fn touch_motion<I: InputBackend>(&mut self, event: I::TouchMotionEvent) {
+        let Some(area) = self.absolute_pointer_area(&event.device().id()) else {
+            return;
+        };
+        let loc = map_absolute_position(
+            (event.x_transformed(1), event.y_transformed(1)).into(),
+            area,
+        );
+        let slot = event.slot();
+
+        let touch = &mut self.input_state.touch;
+        let Some(finger) = touch.fingers.iter_mut().find(|finger| finger.slot == slot) else {
+            return;
+        };
+        finger.loc = loc;
+        let moved = finger.loc - finger.start;
+        let tolerance = touch.gestures.tolerance;
+
+        match touch.gesture {
+            TouchGesture::Pending { timer_token } if moved.x.hypot(moved.y) > tolerance => {
+                self.loop_handle.remove(timer_token);
+                self.input_state.touch.gesture = TouchGesture::Done;
+            }
+            TouchGesture::Moving { slot: moving_slot } if moving_slot == slot => {
+                self.move_pointer_to_touch(loc, event.time_msec());
+            }
+            _ => (),
+        }
+    }
+
+    pub fn touch_up<I: InputBackend>(&mut self, event: I::TouchUpEvent) {
+        self.lift_finger(event.slot(), event.time_msec());
+    }
+
+    pub fn touch_cancel<I: InputBackend>(&mut self, event: I::TouchCancelEvent) {
+        self.lift_finger(event.slot(), event.time_msec());
+    }
+
+    /// Forget the finger in `slot`, cancelling the long press or ending the move it was part of.
+    fn lift_finger(&mut self, slot: TouchSlot, time_msec: u32) {
+        let touch = &mut self.input_state.touch;
+        touch.fingers.retain(|finger| finger.slot != slot);
+
+        match std::mem::take(&mut touch.gesture) {
+            TouchGesture::Pending { timer_token } => {
+                self.loop_handle.remove(timer_token);
+                self.input_state.touch.gesture = TouchGesture::Done;
+            }
+            TouchGesture::Moving { slot: moving_slot } if moving_slot == slot => {
+                self.end_touch_move(time_msec);
+                self.input_state.touch.gesture = TouchGesture::Done;
+            }
+            gesture => self.input_state.touch.gesture = gesture,
+        }
+
+        if self.input_state.touch.fingers.is_empty() {
+            self.input_state.touch.gesture = TouchGesture::Idle;
+        }
+    }
+
+    fn start_long_press_timer(&mut self) {
+        let duration = Duration::from_millis(self.input_state.touch.gestures.long_press_ms);
+
+        let timer_token =
+            self.loop_handle
+                .insert_source(Timer::from_duration(duration), |_, _, data| {
+                    data.state.long_press();
+                    TimeoutAction::Drop
+                });
+
+        match timer_token {
+            Ok(timer_token) => {
+                self.input_state.touch.gesture = TouchGesture::Pending { timer_token };
+            }
+            Err(err) => {
+                tracing::error!("Failed to insert long press timer: {err}");
+                self.input_state.touch.gesture = TouchGesture::Done;
+            }
+        }
+    }
+
+    /// The fingers were held long enough, so right click or start moving a window.
+    fn long_press(&mut self) {
+        let touch = &mut self.input_state.touch;
+        touch.gesture = TouchGesture::Done;
+
+        let Some(first) = touch.fingers.first() else {
+            return;
+        };
+        let (slot, loc) = (first.slot, first.loc);
+        let two_fingers = touch.fingers.len() == 2;
+        let time_msec = Duration::from(self.clock.now()).as_millis() as u32;
+
+        self.move_pointer_to_touch(loc, time_msec);
+
+        let window = match self.surface_under(loc) {
+            Some((FocusTarget::Window(window), _)) => Some(window),
+            _ => None,
+        };
+        let on_edge = window.as_ref().is_some_and(|window| {
+            self.space.element_geometry(window).is_some_and(|geo| {
+                let geo = geo.to_f64();
+                loc.x - geo.loc.x < EDGE_REGION
+                    || loc.y - geo.loc.y < EDGE_REGION
+                    || geo.loc.x + geo.size.w - loc.x < EDGE_REGION
+                    || geo.loc.y + geo.size.h - loc.y < EDGE_REGION
+            })
+        });
+
+        match window.and_then(|window| window.wl_surface()) {
+            Some(surface) if two_fingers || on_edge => {
+                tracing::debug!("Long press starting window move");
+                let seat = self.seat.clone();
+                crate::grab::move_grab::move_request_server(
+                    self,
+                    &surface,
+                    &seat,
+                    SERIAL_COUNTER.next_serial(),
+                    BUTTON_LEFT,
+                );
+                self.input_state.touch.gesture = TouchGesture::Moving { slot };
+            }
+            _ if two_fingers => (),
+            _ => {
+                tracing::debug!("Long press right clicking");
+                self.press_pointer_button(BUTTON_RIGHT, ButtonState::Pressed, time_msec);
+                self.press_pointer_button(BUTTON_RIGHT, ButtonState::Released, time_msec);
+            }
+        }
+    }
+
+    /// Drop the window being moved by touch.
+    fn end_touch_move(&mut self, time_msec: u32) {
+        // The move grab ends once the button it was started with isn't held
+        self.press_pointer_button(BUTTON_LEFT, ButtonState::Released, time_msec);
+    }
+
+    /// Move the pointer to where a finger is.
+    fn move_pointer_to_touch(&mut self, loc: Point<f64, Logical>, time_msec: u32) {
+        let Some(pointer) = self.seat.get_pointer() else {
+            return;
+        };
+
+        self.pointer_location = loc;
+        if let Some(output) = self.space.output_under(loc).next().cloned() {
+            self.focus_state.focused_output = Some(output);
+        }
+
+        pointer.motion(
+            self,
+            self.surface_under(loc),
+            &MotionEvent {
+                location: loc,
+                serial: SERIAL_COUNTER.next_serial(),
+                time: time_msec,
+            },
+        );
+        pointer.frame(self);
+
+        if let Some(output) = self.focus_state.focused_output.clone() {
+            self.schedule_render(&output);
+        }
+    }
+}
@@ -1,5 +1,7 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+pub mod applied;
+
 use smithay::{
     desktop::layer_map_for_output,
     output::Output,
@@ -10,15 +12,15 @@ use crate::{
     state::{State, WithState},
     window::{
         window_state::{FloatingOrTiled, FullscreenOrMaximized, LocationRequestState},
-        WindowElement,
+        ResizeIncrements, WindowElement,
     },
 };
 
 impl State {
-    /// Compute the positions and sizes of tiled windows on
-    /// `output` according to the provided [`Layout`].
-    fn tile_windows(&self, output: &Output, windows: Vec<WindowElement>, layout: Layout) {
-        let Some(rect) = self.space.output_geometry(output).map(|op_geo| {
+    /// Get the area of `output` that tiled windows are laid out in,
+    /// which is what layer surfaces' exclusive zones leave over.
+    pub fn tiling_area(&self, output: &Output) -> Option<Rectangle<i32, Logical>> {
+        self.space.output_geometry(output).map(|op_geo| {
             let map = layer_map_for_output(output);
             if map.layers().peekable().peek().is_none() {
                 // INFO: Sometimes the exclusive zone is some weird number that doesn't match the
@@ -30,21 +32,42 @@ impl State {
                 tracing::debug!("non_exclusive_zone is {zone:?}");
                 Rectangle::from_loc_and_size(op_geo.loc + zone.loc, zone.size)
             }
-        }) else {
+        })
+    }
+
+    /// Compute the positions and sizes of tiled windows on
+    /// `output` according to the provided [`Layout`].
+    ///
+    /// `master_factor` is how much of the width the first window takes up
+    /// in layouts that split it off from the rest.
+    fn tile_windows(
+        &self,
+        output: &Output,
+        windows: Vec<WindowElement>,
+        layout: Layout,
+        master_factor: f64,
+    ) {
+        let Some(rect) = self.tiling_area(output) else {
             // TODO: maybe default to something like 800x800 like in anvil so people still see
             // |     windows open
             tracing::error!("Failed to get output geometry");
             return;
         };
 
-        match layout {
-            Layout::MasterStack => master_stack(windows, rect),
-            Layout::Dwindle => dwindle(windows, rect),
-            Layout::Spiral => spiral(windows, rect),
+        let mut geos = match layout {
+            Layout::MasterStack => master_stack(windows, rect, master_factor),
+            Layout::Dwindle => dwindle(windows, rect, master_factor),
+            Layout::Spiral => spiral(windows, rect, master_factor),
             layout @ (Layout::CornerTopLeft
             | Layout::CornerTopRight
             | Layout::CornerBottomLeft
             | Layout::CornerBottomRight) => corner(&layout, windows, rect),
+        };
+
+        snap_geometries_to_increments(&mut geos);
+
+        for (window, geo) in geos {
+            window.change_geometry(geo);
         }
     }
 
@@ -52,12 +75,18 @@ impl State {
     /// and send configures and that cool stuff.
     pub fn update_windows(&mut self, output: &Output) {
         tracing::debug!("Updating windows");
-        let Some(layout) =
-            output.with_state(|state| state.focused_tags().next().map(|tag| tag.layout()))
-        else {
+        let Some((layout, master_factor)) = output.with_state(|state| {
+            state
+                .focused_tags()
+                .next()
+                .map(|tag| (tag.layout(), tag.master_factor()))
+        }) else {
             return;
         };
 
+        // The active tags may have changed, so windows following them need to catch up
+        self.retag_following_windows(output);
+
         let (windows_on_foc_tags, mut windows_not_on_foc_tags): (Vec<_>, _) =
             output.with_state(|state| {
                 let focused_tags = state.focused_tags().collect::<Vec<_>>();
@@ -72,8 +101,12 @@ impl State {
                     })
             });
 
-        // Don't unmap windows that aren't on `output` (that would clear all other monitors)
-        windows_not_on_foc_tags.retain(|win| win.output(self) == Some(output.clone()));
+        // Don't unmap windows that are on other outputs (that would clear all other monitors).
+        // Windows on no output, like orphaned ones, can't be shown anywhere.
+        windows_not_on_foc_tags.retain(|win| {
+            win.output(self)
+                .map_or(true, |win_output| &win_output == output)
+        });
 
         let tiled_windows = windows_on_foc_tags
             .iter()
@@ -85,7 +118,7 @@ impl State {
             .cloned()
             .collect::<Vec<_>>();
 
-        self.tile_windows(output, tiled_windows, layout);
+        self.tile_windows(output, tiled_windows, layout, master_factor);
 
         let output_geo = self.space.output_geometry(output).expect("no output geo");
         for window in windows_on_foc_tags.iter() {
@@ -117,67 +150,87 @@ impl State {
             }
         }
 
-        let mut pending_wins = Vec::<(Point<_, _>, WindowElement)>::new();
-        let mut non_pending_wins = Vec::<(Point<_, _>, WindowElement)>::new();
+        // Windows that are hidden get a new geometry when they're shown again,
+        // so drop whatever they were waiting on.
+        for window in windows_not_on_foc_tags.iter() {
+            window.with_state(|state| {
+                state.loc_request_state = LocationRequestState::Idle;
+            });
+        }
+
+        let mut pending_wins = Vec::<WindowElement>::new();
+        let mut ready_wins = Vec::<WindowElement>::new();
+        let mut x11_wins = Vec::<(Point<_, _>, WindowElement)>::new();
 
-        // TODO: completely refactor how tracking state changes works
         for window in windows_on_foc_tags.iter() {
-            window.with_state(|state| {
-                if let LocationRequestState::Sent(loc) = state.loc_request_state {
-                    match &window {
-                        WindowElement::Wayland(win) => {
-                            // If the above didn't cause any change to size or other state, simply
-                            // map the window.
-                            let current_state = win.toplevel().current_state();
-                            let is_pending = win
-                                .toplevel()
-                                .with_pending_state(|state| state.size != current_state.size);
-
-                            if !is_pending {
-                                tracing::debug!("No pending changes, mapping window");
-                                // TODO: map win here, not down there
-                                state.loc_request_state = LocationRequestState::Idle;
-                                non_pending_wins.push((loc, window.clone()));
-                            } else {
-                                tracing::debug!("Pending changes, requesting commit");
-                                let serial = win.toplevel().send_configure();
-                                state.loc_request_state =
-                                    LocationRequestState::Requested(serial, loc);
-                                pending_wins.push((loc, window.clone()));
-                            }
-                        }
-                        WindowElement::X11(surface) => {
-                            // already configured, just need to map
-                            // maybe wait for all wayland windows to commit before mapping
-                            // self.space.map_element(window.clone(), loc, false);
-                            surface
-                                .set_mapped(true)
-                                .expect("failed to set x11 win to mapped");
-                            state.loc_request_state = LocationRequestState::Idle;
-                            non_pending_wins.push((loc, window.clone()));
-                        }
-                        WindowElement::X11OverrideRedirect(_) => {
-                            // filtered out up there somewhere
-                            unreachable!();
-                        }
+            window.with_state(|state| match (&window, state.loc_request_state.clone()) {
+                (WindowElement::Wayland(win), LocationRequestState::Sent(_)) => {
+                    // If the above didn't cause any change to size or other state, the window
+                    // only needs to be moved.
+                    let current_state = win.toplevel().current_state();
+                    let is_pending = win
+                        .toplevel()
+                        .with_pending_state(|state| state.size != current_state.size);
+
+                    if !is_pending {
+                        tracing::debug!("No pending changes, mapping window");
+                        state.loc_request_state.configured(None);
+                        ready_wins.push(window.clone());
+                    } else {
+                        tracing::debug!("Pending changes, requesting commit");
+                        let serial = win.toplevel().send_configure();
+                        state.loc_request_state.configured(Some(serial));
+                        pending_wins.push(window.clone());
                     }
                 }
+                (WindowElement::X11(surface), LocationRequestState::Acknowledged(loc)) => {
+                    // Already configured in `change_geometry`, so it can be moved right away
+                    surface
+                        .set_mapped(true)
+                        .expect("failed to set x11 win to mapped");
+                    state.loc_request_state = LocationRequestState::Idle;
+                    x11_wins.push((loc, window.clone()));
+                }
+                _ => (),
             });
         }
 
+        if !x11_wins.is_empty() {
+            for (loc, win) in x11_wins.iter() {
+                self.space.map_element(win.clone(), *loc, false);
+            }
+            self.update_window_outputs(x11_wins.iter().map(|(_, win)| win));
+        }
+
+        if ready_wins.is_empty() {
+            self.signal_layout_applied(output);
+            return;
+        }
+
+        let output = output.clone();
         self.schedule(
             move |_dt| {
-                let all_idle = pending_wins
+                pending_wins
                     .iter()
-                    .filter(|(_, win)| win.alive())
-                    .all(|(_, win)| win.with_state(|state| state.loc_request_state.is_idle()));
-
-                all_idle
+                    .filter(|win| win.alive())
+                    .all(|win| win.with_state(|state| state.loc_request_state.is_idle()))
             },
             move |dt| {
-                for (loc, win) in non_pending_wins {
-                    dt.state.space.map_element(win, loc, false);
-                }
+                // Only move windows that are still waiting. If a window got a newer geometry in
+                // the meantime, that one is applied instead of this stale location.
+                let mapped_wins = ready_wins
+                    .into_iter()
+                    .filter(|win| win.alive())
+                    .filter_map(|win| {
+                        let loc =
+                            win.with_state(|state| state.loc_request_state.take_acknowledged())?;
+                        dt.state.space.map_element(win.clone(), loc, false);
+                        Some(win)
+                    })
+                    .collect::<Vec<_>>();
+
+                dt.state.update_window_outputs(mapped_wins.iter());
+                dt.state.signal_layout_applied(&output);
             },
         );
     }
@@ -196,24 +249,41 @@ pub enum Layout {
     CornerBottomRight,
 }
 
-fn master_stack(windows: Vec<WindowElement>, rect: Rectangle<i32, Logical>) {
+impl Layout {
+    /// Whether this layout splits the first window off from the rest by the master factor.
+    pub fn has_master(&self) -> bool {
+        matches!(self, Layout::MasterStack | Layout::Dwindle | Layout::Spiral)
+    }
+}
+
+fn master_stack(
+    windows: Vec<WindowElement>,
+    rect: Rectangle<i32, Logical>,
+    master_factor: f64,
+) -> Vec<(WindowElement, Rectangle<i32, Logical>)> {
+    let mut geos = Vec::new();
+
     let size = rect.size;
     let loc = rect.loc;
 
     let master = windows.first();
     let stack = windows.iter().skip(1);
 
-    let Some(master) = master else { return };
+    let Some(master) = master else { return geos };
 
     let stack_count = stack.clone().count();
 
     if stack_count == 0 {
         // one window
-        master.change_geometry(Rectangle::from_loc_and_size(loc, size));
+        geos.push((master.clone(), Rectangle::from_loc_and_size(loc, size)));
     } else {
         let loc: Point<i32, Logical> = (loc.x, loc.y).into();
-        let new_master_size: Size<i32, Logical> = (size.w / 2, size.h).into();
-        master.change_geometry(Rectangle::from_loc_and_size(loc, new_master_size));
+        let master_width = (size.w as f64 * master_factor).round() as i32;
+        let new_master_size: Size<i32, Logical> = (master_width, size.h).into();
+        geos.push((
+            master.clone(),
+            Rectangle::from_loc_and_size(loc, new_master_size),
+        ));
 
         let stack_count = stack_count;
 
@@ -229,15 +299,26 @@ fn master_stack(windows: Vec<WindowElement>, rect: Rectangle<i32, Logical>) {
             .collect::<Vec<_>>();
 
         for (i, win) in stack.enumerate() {
-            win.change_geometry(Rectangle::from_loc_and_size(
-                Point::from((size.w / 2 + loc.x, y_s[i] + loc.y)),
-                Size::from((size.w / 2, i32::max(heights[i], 40))),
+            geos.push((
+                win.clone(),
+                Rectangle::from_loc_and_size(
+                    Point::from((master_width + loc.x, y_s[i] + loc.y)),
+                    Size::from((size.w - master_width, i32::max(heights[i], 40))),
+                ),
             ));
         }
     }
+
+    geos
 }
 
-fn dwindle(windows: Vec<WindowElement>, rect: Rectangle<i32, Logical>) {
+fn dwindle(
+    windows: Vec<WindowElement>,
+    rect: Rectangle<i32, Logical>,
+    master_factor: f64,
+) -> Vec<(WindowElement, Rectangle<i32, Logical>)> {
+    let mut geos = Vec::new();
+
     let size = rect.size;
     let loc = rect.loc;
 
@@ -245,7 +326,7 @@ fn dwindle(windows: Vec<WindowElement>, rect: Rectangle<i32, Logical>) {
 
     if iter.peek().is_none() {
         if let Some(window) = windows.first() {
-            window.change_geometry(Rectangle::from_loc_and_size(loc, size));
+            geos.push((window.clone(), Rectangle::from_loc_and_size(loc, size)));
         }
     } else {
         let mut win1_size = size;
@@ -263,37 +344,62 @@ fn dwindle(windows: Vec<WindowElement>, rect: Rectangle<i32, Logical>) {
 
             match slice {
                 Slice::Right => {
-                    let width_partition = win1_size.w / 2;
+                    // The first split leaves the master window its share of the width
+                    let width_partition = if i == 0 {
+                        win1_size.w - (win1_size.w as f64 * master_factor).round() as i32
+                    } else {
+                        win1_size.w / 2
+                    };
 
-                    win1.change_geometry(Rectangle::from_loc_and_size(
-                        win1_loc,
-                        Size::from((win1_size.w - width_partition, i32::max(win1_size.h, 40))),
+                    geos.push((
+                        win1.clone(),
+                        Rectangle::from_loc_and_size(
+                            win1_loc,
+                            Size::from((win1_size.w - width_partition, i32::max(win1_size.h, 40))),
+                        ),
                     ));
 
                     win1_loc = (win1_loc.x + (win1_size.w - width_partition), win1_loc.y).into();
                     win1_size = (width_partition, i32::max(win1_size.h, 40)).into();
 
-                    win2.change_geometry(Rectangle::from_loc_and_size(win1_loc, win1_size));
+                    geos.push((
+                        win2.clone(),
+                        Rectangle::from_loc_and_size(win1_loc, win1_size),
+                    ));
                 }
                 Slice::Below => {
                     let height_partition = win1_size.h / 2;
 
-                    win1.change_geometry(Rectangle::from_loc_and_size(
-                        win1_loc,
-                        Size::from((win1_size.w, i32::max(win1_size.h - height_partition, 40))),
+                    geos.push((
+                        win1.clone(),
+                        Rectangle::from_loc_and_size(
+                            win1_loc,
+                            Size::from((win1_size.w, i32::max(win1_size.h - height_partition, 40))),
+                        ),
                     ));
 
                     win1_loc = (win1_loc.x, win1_loc.y + (win1_size.h - height_partition)).into();
                     win1_size = (win1_size.w, i32::max(height_partition, 40)).into();
 
-                    win2.change_geometry(Rectangle::from_loc_and_size(win1_loc, win1_size));
+                    geos.push((
+                        win2.clone(),
+                        Rectangle::from_loc_and_size(win1_loc, win1_size),
+                    ));
                 }
             }
         }
     }
+
+    geos
 }
 
-fn spiral(windows: Vec<WindowElement>, rect: Rectangle<i32, Logical>) {
+fn spiral(
+    windows: Vec<WindowElement>,
+    rect: Rectangle<i32, Logical>,
+    master_factor: f64,
+) -> Vec<(WindowElement, Rectangle<i32, Logical>)> {
+    let mut geos = Vec::new();
+
     let size = rect.size;
     let loc = rect.loc;
 
@@ -301,7 +407,7 @@ fn spiral(windows: Vec<WindowElement>, rect: Rectangle<i32, Logical>) {
 
     if window_pairs.peek().is_none() {
         if let Some(window) = windows.first() {
-            window.change_geometry(Rectangle::from_loc_and_size(loc, size));
+            geos.push((window.clone(), Rectangle::from_loc_and_size(loc, size)));
         }
     } else {
         let mut win1_loc = loc;
@@ -330,72 +436,112 @@ fn spiral(windows: Vec<WindowElement>, rect: Rectangle<i32, Logical>) {
                 Slice::Above => {
                     let height_partition = win1_size.h / 2;
 
-                    win1.change_geometry(Rectangle::from_loc_and_size(
-                        Point::from((win1_loc.x, win1_loc.y + height_partition)),
-                        Size::from((win1_size.w, i32::max(win1_size.h - height_partition, 40))),
+                    geos.push((
+                        win1.clone(),
+                        Rectangle::from_loc_and_size(
+                            Point::from((win1_loc.x, win1_loc.y + height_partition)),
+                            Size::from((win1_size.w, i32::max(win1_size.h - height_partition, 40))),
+                        ),
                     ));
 
                     win1_size = (win1_size.w, i32::max(height_partition, 40)).into();
-                    win2.change_geometry(Rectangle::from_loc_and_size(win1_loc, win1_size));
+                    geos.push((
+                        win2.clone(),
+                        Rectangle::from_loc_and_size(win1_loc, win1_size),
+                    ));
                 }
                 Slice::Below => {
                     let height_partition = win1_size.h / 2;
 
-                    win1.change_geometry(Rectangle::from_loc_and_size(
-                        win1_loc,
-                        Size::from((win1_size.w, win1_size.h - i32::max(height_partition, 40))),
+                    geos.push((
+                        win1.clone(),
+                        Rectangle::from_loc_and_size(
+                            win1_loc,
+                            Size::from((win1_size.w, win1_size.h - i32::max(height_partition, 40))),
+                        ),
                     ));
 
                     win1_loc = (win1_loc.x, win1_loc.y + (win1_size.h - height_partition)).into();
                     win1_size = (win1_size.w, i32::max(height_partition, 40)).into();
-                    win2.change_geometry(Rectangle::from_loc_and_size(win1_loc, win1_size));
+                    geos.push((
+                        win2.clone(),
+                        Rectangle::from_loc_and_size(win1_loc, win1_size),
+                    ));
                 }
                 Slice::Left => {
                     let width_partition = win1_size.w / 2;
 
-                    win1.change_geometry(Rectangle::from_loc_and_size(
-                        Point::from((win1_loc.x + width_partition, win1_loc.y)),
-                        Size::from((win1_size.w - width_partition, i32::max(win1_size.h, 40))),
+                    geos.push((
+                        win1.clone(),
+                        Rectangle::from_loc_and_size(
+                            Point::from((win1_loc.x + width_partition, win1_loc.y)),
+                            Size::from((win1_size.w - width_partition, i32::max(win1_size.h, 40))),
+                        ),
                     ));
 
                     win1_size = (width_partition, i32::max(win1_size.h, 40)).into();
-                    win2.change_geometry(Rectangle::from_loc_and_size(win1_loc, win1_size));
+                    geos.push((
+                        win2.clone(),
+                        Rectangle::from_loc_and_size(win1_loc, win1_size),
+                    ));
                 }
                 Slice::Right => {
-                    let width_partition = win1_size.w / 2;
+                    // The first split leaves the master window its share of the width
+                    let width_partition = if i == 0 {
+                        win1_size.w - (win1_size.w as f64 * master_factor).round() as i32
+                    } else {
+                        win1_size.w / 2
+                    };
 
-                    win1.change_geometry(Rectangle::from_loc_and_size(
-                        win1_loc,
-                        Size::from((win1_size.w - width_partition, i32::max(win1_size.h, 40))),
+                    geos.push((
+                        win1.clone(),
+                        Rectangle::from_loc_and_size(
+                            win1_loc,
+                            Size::from((win1_size.w - width_partition, i32::max(win1_size.h, 40))),
+                        ),
                     ));
 
                     win1_loc = (win1_loc.x + (win1_size.w - width_partition), win1_loc.y).into();
                     win1_size = (width_partition, i32::max(win1_size.h, 40)).into();
-                    win2.change_geometry(Rectangle::from_loc_and_size(win1_loc, win1_size));
+                    geos.push((
+                        win2.clone(),
+                        Rectangle::from_loc_and_size(win1_loc, win1_size),
+                    ));
                 }
             }
         }
     }
+
+    geos
 }
 
-fn corner(layout: &Layout, windows: Vec<WindowElement>, rect: Rectangle<i32, Logical>) {
+fn corner(
+    layout: &Layout,
+    windows: Vec<WindowElement>,
+    rect: Rectangle<i32, Logical>,
+) -> Vec<(WindowElement, Rectangle<i32, Logical>)> {
+    let mut geos = Vec::new();
+
     let size = rect.size;
     let loc = rect.loc;
 
     match windows.len() {
         0 => (),
         1 => {
-            windows[0].change_geometry(rect);
+            geos.push((windows[0].clone(), rect));
         }
         2 => {
-            windows[0].change_geometry(Rectangle::from_loc_and_size(
-                loc,
-                Size::from((size.w / 2, size.h)),
+            geos.push((
+                windows[0].clone(),
+                Rectangle::from_loc_and_size(loc, Size::from((size.w / 2, size.h))),
             ));
 
-            windows[1].change_geometry(Rectangle::from_loc_and_size(
-                Point::from((loc.x + size.w / 2, loc.y)),
-                Size::from((size.w / 2, size.h)),
+            geos.push((
+                windows[1].clone(),
+                Rectangle::from_loc_and_size(
+                    Point::from((loc.x + size.w / 2, loc.y)),
+                    Size::from((size.w / 2, size.h)),
+                ),
             ));
         }
         _ => {
@@ -415,18 +561,21 @@ fn corner(layout: &Layout, windows: Vec<WindowElement>, rect: Rectangle<i32, Log
 
             let div_factor = 2;
 
-            corner.change_geometry(Rectangle::from_loc_and_size(
-                Point::from(match layout {
-                    Layout::CornerTopLeft => (loc.x, loc.y),
-                    Layout::CornerTopRight => (loc.x + size.w - size.w / div_factor, loc.y),
-                    Layout::CornerBottomLeft => (loc.x, loc.y + size.h - size.h / div_factor),
-                    Layout::CornerBottomRight => (
-                        loc.x + size.w - size.w / div_factor,
-                        loc.y + size.h - size.h / div_factor,
-                    ),
-                    _ => unreachable!(),
-                }),
-                Size::from((size.w / div_factor, size.h / div_factor)),
+            geos.push((
+                corner.clone(),
+                Rectangle::from_loc_and_size(
+                    Point::from(match layout {
+                        Layout::CornerTopLeft => (loc.x, loc.y),
+                        Layout::CornerTopRight => (loc.x + size.w - size.w / div_factor, loc.y),
+                        Layout::CornerBottomLeft => (loc.x, loc.y + size.h - size.h / div_factor),
+                        Layout::CornerBottomRight => (
+                            loc.x + size.w - size.w / div_factor,
+                            loc.y + size.h - size.h / div_factor,
+                        ),
+                        _ => unreachable!(),
+                    }),
+                    Size::from((size.w / div_factor, size.h / div_factor)),
+                ),
             ));
 
             let vert_stack_count = vert_stack.len();
@@ -443,16 +592,21 @@ fn corner(layout: &Layout, windows: Vec<WindowElement>, rect: Rectangle<i32, Log
                 .collect::<Vec<_>>();
 
             for (i, win) in vert_stack.iter().enumerate() {
-                win.change_geometry(Rectangle::from_loc_and_size(
-                    Point::from((
-                        match layout {
-                            Layout::CornerTopLeft | Layout::CornerBottomLeft => size.w / 2 + loc.x,
-                            Layout::CornerTopRight | Layout::CornerBottomRight => loc.x,
-                            _ => unreachable!(),
-                        },
-                        y_s[i] + loc.y,
-                    )),
-                    Size::from((size.w / 2, i32::max(heights[i], 40))),
+                geos.push((
+                    win.clone(),
+                    Rectangle::from_loc_and_size(
+                        Point::from((
+                            match layout {
+                                Layout::CornerTopLeft | Layout::CornerBottomLeft => {
+                                    size.w / 2 + loc.x
+                                }
+                                Layout::CornerTopRight | Layout::CornerBottomRight => loc.x,
+                                _ => unreachable!(),
+                            },
+                            y_s[i] + loc.y,
+                        )),
+                        Size::from((size.w / 2, i32::max(heights[i], 40))),
+                    ),
                 ));
             }
 
@@ -470,19 +624,157 @@ fn corner(layout: &Layout, windows: Vec<WindowElement>, rect: Rectangle<i32, Log
                 .collect::<Vec<_>>();
 
             for (i, win) in horiz_stack.iter().enumerate() {
-                win.change_geometry(Rectangle::from_loc_and_size(
-                    Point::from(match layout {
-                        Layout::CornerTopLeft => (x_s[i] + loc.x, loc.y + size.h / 2),
-                        Layout::CornerTopRight => (x_s[i] + loc.x + size.w / 2, loc.y + size.h / 2),
-                        Layout::CornerBottomLeft => (x_s[i] + loc.x, loc.y),
-                        Layout::CornerBottomRight => (x_s[i] + loc.x + size.w / 2, loc.y),
-                        _ => unreachable!(),
-                    }),
-                    Size::from((i32::max(widths[i], 1), size.h / 2)),
+                geos.push((
+                    win.clone(),
+                    Rectangle::from_loc_and_size(
+                        Point::from(match layout {
+                            Layout::CornerTopLeft => (x_s[i] + loc.x, loc.y + size.h / 2),
+                            Layout::CornerTopRight => {
+                                (x_s[i] + loc.x + size.w / 2, loc.y + size.h / 2)
+                            }
+                            Layout::CornerBottomLeft => (x_s[i] + loc.x, loc.y),
+                            Layout::CornerBottomRight => (x_s[i] + loc.x + size.w / 2, loc.y),
+                            _ => unreachable!(),
+                        }),
+                        Size::from((i32::max(widths[i], 1), size.h / 2)),
+                    ),
                 ));
             }
         }
     }
+
+    geos
+}
+
+/// Shrink the geometries of windows that want to snap to their resize increments,
+/// giving the leftover space to a neighboring window.
+///
+/// RefCell Safety: This uses RefCells on every window in `geos`.
+fn snap_geometries_to_increments(geos: &mut [(WindowElement, Rectangle<i32, Logical>)]) {
+    let increments = geos
+        .iter()
+        .map(|(window, _)| {
+            window
+                .with_state(|state| state.snap_to_increments)
+                .then(|| window.resize_increments())
+                .flatten()
+        })
+        .collect::<Vec<_>>();
+
+    let mut rects = geos.iter().map(|(_, geo)| *geo).collect::<Vec<_>>();
+    snap_rects_to_increments(&mut rects, &increments);
+
+    for ((_, geo), rect) in geos.iter_mut().zip(rects) {
+        *geo = rect;
+    }
+}
+
+/// Snap every rect that has increments to its grid, giving the slack to its neighbors.
+///
+/// The slack goes to the rects on the right of or below the snapped rect, or failing that,
+/// the ones on the left or above. Those neighbors can't stick out past the snapped rect along
+/// the shared edge, or growing them would overlap whatever else is next to it. Neighbors with
+/// increments that take slack are snapped again afterwards and pass their own slack on.
+/// Rects that have already been snapped never take slack, so they stay on their grid.
+/// If no neighbors can take the slack, it's left as a gap.
+fn snap_rects_to_increments(
+    rects: &mut [Rectangle<i32, Logical>],
+    increments: &[Option<ResizeIncrements>],
+) {
+    for i in 0..rects.len() {
+        let Some(incs) = increments[i] else {
+            continue;
+        };
+
+        let geo = rects[i];
+        let snapped_size = incs.snap(geo.size);
+        let slack_w = geo.size.w - snapped_size.w;
+        let slack_h = geo.size.h - snapped_size.h;
+
+        if slack_w == 0 && slack_h == 0 {
+            continue;
+        }
+
+        rects[i].size = snapped_size;
+
+        let overlaps_vertically = |other: &Rectangle<i32, Logical>| {
+            other.loc.y < geo.loc.y + geo.size.h && geo.loc.y < other.loc.y + other.size.h
+        };
+        let overlaps_horizontally = |other: &Rectangle<i32, Logical>| {
+            other.loc.x < geo.loc.x + geo.size.w && geo.loc.x < other.loc.x + other.size.w
+        };
+        let within_vertically = |other: &Rectangle<i32, Logical>| {
+            other.loc.y >= geo.loc.y && other.loc.y + other.size.h <= geo.loc.y + geo.size.h
+        };
+        let within_horizontally = |other: &Rectangle<i32, Logical>| {
+            other.loc.x >= geo.loc.x && other.loc.x + other.size.w <= geo.loc.x + geo.size.w
+        };
+
+        // Rects before this one with increments have already been snapped.
+        let can_take_slack = |j: usize| increments[j].is_none() || j > i;
+
+        let neighbors =
+            |rects: &[Rectangle<i32, Logical>],
+             is_neighbor: &dyn Fn(&Rectangle<i32, Logical>) -> bool,
+             is_within: &dyn Fn(&Rectangle<i32, Logical>) -> bool| {
+                let neighbors = (0..rects.len())
+                    .filter(|&j| j != i && is_neighbor(&rects[j]))
+                    .collect::<Vec<_>>();
+                let can_grow = !neighbors.is_empty()
+                    && neighbors
+                        .iter()
+                        .all(|&j| can_take_slack(j) && is_within(&rects[j]));
+                can_grow.then_some(neighbors)
+            };
+
+        if slack_w > 0 {
+            let right_edge = geo.loc.x + geo.size.w;
+            if let Some(right) = neighbors(
+                rects,
+                &|other| other.loc.x == right_edge && overlaps_vertically(other),
+                &within_vertically,
+            ) {
+                // Grow the windows to the right leftwards into the slack.
+                for j in right {
+                    rects[j].loc.x -= slack_w;
+                    rects[j].size.w += slack_w;
+                }
+            } else if let Some(left) = neighbors(
+                rects,
+                &|other| other.loc.x + other.size.w == geo.loc.x && overlaps_vertically(other),
+                &within_vertically,
+            ) {
+                // Otherwise, push this window right and grow the windows to the left.
+                for j in left {
+                    rects[j].size.w += slack_w;
+                }
+                rects[i].loc.x += slack_w;
+            }
+        }
+
+        if slack_h > 0 {
+            let bottom_edge = geo.loc.y + geo.size.h;
+            if let Some(below) = neighbors(
+                rects,
+                &|other| other.loc.y == bottom_edge && overlaps_horizontally(other),
+                &within_horizontally,
+            ) {
+                for j in below {
+                    rects[j].loc.y -= slack_h;
+                    rects[j].size.h += slack_h;
+                }
+            } else if let Some(above) = neighbors(
+                rects,
+                &|other| other.loc.y + other.size.h == geo.loc.y && overlaps_horizontally(other),
+                &within_horizontally,
+            ) {
+                for j in above {
+                    rects[j].size.h += slack_h;
+                }
+                rects[i].loc.y += slack_h;
+            }
+        }
+    }
 }
 
 impl State {
@@ -499,3 +791,143 @@ impl State {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: i32, y: i32, w: i32, h: i32) -> Rectangle<i32, Logical> {
+        Rectangle::from_loc_and_size((x, y), (w, h))
+    }
+
+    fn incs(base: i32, inc: i32, min: i32) -> ResizeIncrements {
+        ResizeIncrements {
+            base: (base, base).into(),
+            inc: (inc, inc).into(),
+            min: (min, min).into(),
+        }
+    }
+
+    #[test]
+    fn snap_rounds_down_from_base() {
+        let incs = incs(4, 10, 0);
+        assert_eq!(incs.snap((4, 4).into()), (4, 4).into());
+        assert_eq!(incs.snap((13, 14).into()), (4, 14).into());
+        assert_eq!(incs.snap((99, 100).into()), (94, 94).into());
+    }
+
+    #[test]
+    fn snap_leaves_sizes_below_base_alone() {
+        assert_eq!(incs(20, 10, 0).snap((15, 5).into()), (15, 5).into());
+    }
+
+    #[test]
+    fn snap_never_goes_below_min_size() {
+        // Rounding 25 down gives 17, so the next grid size up is used.
+        let incs = incs(10, 7, 20);
+        assert_eq!(incs.snap((25, 25).into()), (24, 24).into());
+        // There's no grid size between the min size and 22.
+        assert_eq!(incs.snap((22, 22).into()), (22, 22).into());
+        // Already below the min size.
+        assert_eq!(incs.snap((15, 15).into()), (15, 15).into());
+    }
+
+    #[test]
+    fn windows_without_increments_are_unaffected() {
+        let mut rects = [rect(0, 0, 503, 1000), rect(503, 0, 497, 1000)];
+        snap_rects_to_increments(&mut rects, &[None, None]);
+        assert_eq!(rects, [rect(0, 0, 503, 1000), rect(503, 0, 497, 1000)]);
+    }
+
+    #[test]
+    fn slack_goes_to_the_right_neighbor() {
+        let mut rects = [rect(0, 0, 505, 1000), rect(505, 0, 495, 1000)];
+        snap_rects_to_increments(&mut rects, &[Some(incs(0, 10, 0)), None]);
+        assert_eq!(rects, [rect(0, 0, 500, 1000), rect(500, 0, 500, 1000)]);
+    }
+
+    #[test]
+    fn slack_goes_to_the_left_neighbor_if_there_is_no_right_one() {
+        let mut rects = [rect(0, 0, 495, 1000), rect(495, 0, 505, 1000)];
+        snap_rects_to_increments(&mut rects, &[None, Some(incs(0, 10, 0))]);
+        assert_eq!(rects, [rect(0, 0, 500, 1000), rect(500, 0, 500, 1000)]);
+    }
+
+    #[test]
+    fn slack_goes_below_on_vertical_splits() {
+        let mut rects = [rect(0, 0, 1000, 505), rect(0, 505, 1000, 495)];
+        snap_rects_to_increments(&mut rects, &[Some(incs(0, 10, 0)), None]);
+        assert_eq!(rects, [rect(0, 0, 1000, 500), rect(0, 500, 1000, 500)]);
+    }
+
+    #[test]
+    fn slack_goes_to_every_window_along_the_edge() {
+        // Master on the left, two stacked windows on the right
+        let mut rects = [
+            rect(0, 0, 505, 1000),
+            rect(505, 0, 495, 500),
+            rect(505, 500, 495, 500),
+        ];
+        snap_rects_to_increments(&mut rects, &[Some(incs(0, 10, 0)), None, None]);
+        assert_eq!(
+            rects,
+            [
+                rect(0, 0, 500, 1000),
+                rect(500, 0, 500, 500),
+                rect(500, 500, 500, 500),
+            ]
+        );
+    }
+
+    #[test]
+    fn neighbors_that_stick_out_dont_take_slack() {
+        // The master sticks out below the top stack window, so growing it would
+        // overlap the bottom one.
+        let mut rects = [
+            rect(0, 0, 500, 1000),
+            rect(500, 0, 505, 500),
+            rect(500, 500, 505, 500),
+        ];
+        snap_rects_to_increments(&mut rects, &[None, Some(incs(0, 10, 0)), None]);
+        assert_eq!(
+            rects,
+            [
+                rect(0, 0, 500, 1000),
+                rect(500, 0, 500, 500),
+                rect(500, 500, 505, 500),
+            ]
+        );
+    }
+
+    #[test]
+    fn neighbor_with_increments_is_resnapped_after_taking_slack() {
+        let mut rects = [
+            rect(0, 0, 333, 1000),
+            rect(333, 0, 333, 1000),
+            rect(666, 0, 334, 1000),
+        ];
+        snap_rects_to_increments(
+            &mut rects,
+            &[Some(incs(0, 10, 0)), Some(incs(0, 10, 0)), None],
+        );
+        // The first window's 3 pixels go to the second, which snaps 336 down to 330
+        // and passes 6 on to the third.
+        assert_eq!(
+            rects,
+            [
+                rect(0, 0, 330, 1000),
+                rect(330, 0, 330, 1000),
+                rect(660, 0, 340, 1000),
+            ]
+        );
+    }
+
+    #[test]
+    fn snapped_windows_never_take_slack() {
+        let mut rects = [rect(0, 0, 505, 990), rect(505, 0, 495, 990)];
+        snap_rects_to_increments(&mut rects, &[Some(incs(0, 10, 0)), Some(incs(0, 9, 0))]);
+        // The second window snaps 500 down to 495 and can't give its slack back
+        // to the first, so it's left as a gap.
+        assert_eq!(rects, [rect(0, 0, 500, 990), rect(500, 0, 495, 990)]);
+    }
+}
@@ -0,0 +1,120 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Telling clients where windows ended up after a layout.
+//!
+//! Clients that connect to applied layouts are sent the geometry of every window on the
+//! active tags of an output each time the output is laid out. This is sent once windows
+//! that had to be configured have committed and been moved, so the geometries are what's
+//! on screen, and only once per layout of the output.
+//!
+//! Every layout gets a generation one higher than the last, so a client that falls behind
+//! can tell which layouts are stale.
+//!
+//! Nothing is gathered while no client is connected. One-off clients can connect too, like
+//! tools that draw hints over windows.
+
+use std::{
+    os::unix::net::UnixStream,
+    sync::{atomic::AtomicU64, Arc, Mutex},
+};
+
+use smithay::output::Output;
+
+use crate::{
+    api::msg::{Args, CallbackId, OutgoingMsg},
+    state::{State, WithState},
+    window::window_state::WindowId,
+};
+
+/// A window and the geometry it was laid out at, as `(window_id, x, y, width, height)`.
+pub type LaidOutWindow = (WindowId, i32, i32, i32, i32);
+
+#[derive(Debug, Default)]
+pub struct LayoutAppliedState {
+    /// Clients that want to know where windows were laid out.
+    subscribers: Vec<LayoutSubscriber>,
+    /// The generation of the last layout that was sent
+    generation: u64,
+}
+
+#[derive(Debug)]
+struct LayoutSubscriber {
+    stream: Arc<Mutex<UnixStream>>,
+    callback_id: CallbackId,
+    /// The callback serial of a one-off client. The config uses the shared one in `ApiState`.
+    serial: AtomicU64,
+}
+
+impl State {
+    /// Tell the client on `stream` about applied layouts by calling the callback with
+    /// `callback_id`.
+    pub fn connect_layout_applied(
+        &mut self,
+        stream: Arc<Mutex<UnixStream>>,
+        callback_id: CallbackId,
+    ) {
+        self.layout_applied.subscribers.push(LayoutSubscriber {
+            stream,
+            callback_id,
+            serial: AtomicU64::new(0),
+        });
+    }
+
+    /// Tell subscribers where the windows on the active tags of `output` are now,
+    /// forgetting the ones that went away.
+    pub fn signal_layout_applied(&mut self, output: &Output) {
+        if self.layout_applied.subscribers.is_empty() {
+            return;
+        }
+
+        let Some(tag_id) =
+            output.with_state(|state| state.focused_tags().next().map(|tag| tag.id()))
+        else {
+            return;
+        };
+
+        let windows = self
+            .windows
+            .iter()
+            .filter(|win| !win.is_x11_override_redirect() && win.is_on_active_tag([output]))
+            .filter_map(|win| {
+                let geo = self.space.element_geometry(win)?;
+                let window_id = win.with_state(|state| state.id);
+                Some((window_id, geo.loc.x, geo.loc.y, geo.size.w, geo.size.h))
+            })
+            .collect::<Vec<LaidOutWindow>>();
+
+        self.layout_applied.generation += 1;
+        let generation = self.layout_applied.generation;
+        let output_name = output.name();
+
+        let config_stream = self.api_state.stream.as_ref();
+        let config_serial = &self.api_state.callback_serial;
+
+        self.layout_applied.subscribers.retain(|subscriber| {
+            let serial = match config_stream {
+                Some(stream) if Arc::ptr_eq(stream, &subscriber.stream) => &**config_serial,
+                _ => &subscriber.serial,
+            };
+
+            let sent = crate::api::send_to_client(
+                &mut subscriber.stream.lock().expect("couldn't lock stream"),
+                &OutgoingMsg::call_callback(
+                    subscriber.callback_id,
+                    Some(Args::LayoutApplied {
+                        output_name: output_name.clone(),
+                        tag_id,
+                        generation,
+                        windows: windows.clone(),
+                    }),
+                    serial,
+                ),
+            );
+
+            if let Err(err) = &sent {
+                tracing::debug!("Dropping layout subscriber: {err}");
+            }
+            sent.is_ok()
+        });
+    }
+}
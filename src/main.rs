@@ -11,7 +11,10 @@
 // #![deny(unused_imports)] // gonna force myself to keep stuff clean
 #![warn(clippy::unwrap_used)]
 
+use std::os::fd::{FromRawFd, OwnedFd, RawFd};
+
 use clap::Parser;
+use smithay::reexports::nix::fcntl::{fcntl, FcntlArg, FdFlag};
 use tracing_appender::rolling::Rotation;
 use tracing_subscriber::{fmt::writer::MakeWriterExt, EnvFilter};
 use xdg::BaseDirectories;
@@ -19,7 +22,10 @@ use xdg::BaseDirectories;
 mod api;
 mod backend;
 mod config;
+mod crash;
 mod cursor;
+mod disconnects;
+mod do_not_disturb;
 mod focus;
 mod grab;
 mod handlers;
@@ -27,6 +33,8 @@ mod input;
 mod layout;
 mod output;
 mod render;
+mod session;
+mod startup;
 mod state;
 mod tag;
 mod window;
@@ -58,9 +66,20 @@ struct Args {
     #[arg(long, requires = "backend")]
     /// Force Pinnacle to run with the provided backend
     force: bool,
+    #[arg(long, value_name = "FD")]
+    /// Write a newline to this file descriptor and close it once Pinnacle is ready
+    ready_fd: Option<RawFd>,
+    #[arg(long)]
+    /// Don't limit how many messages configs can send or what they can register
+    no_client_limits: bool,
+    #[arg(long)]
+    /// Render in software instead of on the GPU when running from a tty
+    force_software_renderer: bool,
 }
 
 fn main() -> anyhow::Result<()> {
+    crash::install_panic_hook();
+
     let xdg_state_dir = XDG_BASE_DIRS.get_state_home();
 
     let appender = tracing_appender::rolling::Builder::new()
@@ -71,7 +90,7 @@ fn main() -> anyhow::Result<()> {
         .expect("failed to build file logger");
 
     let (appender, _guard) = tracing_appender::non_blocking(appender);
-    let writer = appender.and(std::io::stdout);
+    let writer = appender.and(std::io::stdout).and(crash::RecentLogWriter);
 
     let env_filter = EnvFilter::try_from_default_env().unwrap_or(EnvFilter::new("debug"));
 
@@ -88,6 +107,23 @@ fn main() -> anyhow::Result<()> {
         return Ok(());
     }
 
+    if args.no_client_limits {
+        api::limits::disable_limits();
+    }
+
+    let ready_fd = match args.ready_fd {
+        Some(fd) => {
+            // Make sure the fd is valid and that spawned processes don't inherit it
+            if let Err(err) = fcntl(fd, FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC)) {
+                println!("--ready-fd {fd} is not a valid file descriptor: {err}");
+                return Ok(());
+            }
+            // Safety: the fd was checked to be open above, and nothing else in Pinnacle uses it
+            Some(unsafe { OwnedFd::from_raw_fd(fd) })
+        }
+        None => None,
+    };
+
     let in_graphical_env =
         std::env::var("WAYLAND_DISPLAY").is_ok() || std::env::var("DISPLAY").is_ok();
 
@@ -95,17 +131,17 @@ fn main() -> anyhow::Result<()> {
         (false, false, _) => {
             if in_graphical_env {
                 tracing::info!("Starting winit backend");
-                crate::backend::winit::run_winit()?;
+                crate::backend::winit::run_winit(ready_fd)?;
             } else {
                 tracing::info!("Starting udev backend");
-                crate::backend::udev::run_udev()?;
+                crate::backend::udev::run_udev(ready_fd, args.force_software_renderer)?;
             }
         }
         (true, false, force) => {
             if !in_graphical_env {
                 if force {
                     tracing::warn!("Starting winit backend with no detected graphical environment");
-                    crate::backend::winit::run_winit()?;
+                    crate::backend::winit::run_winit(ready_fd)?;
                 } else {
                     println!("Both WAYLAND_DISPLAY and DISPLAY are not set.");
                     println!("If you are trying to run the winit backend in a tty, it won't work.");
@@ -113,14 +149,14 @@ fn main() -> anyhow::Result<()> {
                 }
             } else {
                 tracing::info!("Starting winit backend");
-                crate::backend::winit::run_winit()?;
+                crate::backend::winit::run_winit(ready_fd)?;
             }
         }
         (false, true, force) => {
             if in_graphical_env {
                 if force {
                     tracing::warn!("Starting udev backend with a detected graphical environment");
-                    crate::backend::udev::run_udev()?;
+                    crate::backend::udev::run_udev(ready_fd, args.force_software_renderer)?;
                 } else {
                     println!("WAYLAND_DISPLAY and/or DISPLAY are set.");
                     println!(
@@ -131,7 +167,7 @@ fn main() -> anyhow::Result<()> {
                 }
             } else {
                 tracing::info!("Starting udev backend");
-                crate::backend::udev::run_udev()?;
+                crate::backend::udev::run_udev(ready_fd, args.force_software_renderer)?;
             }
         }
         _ => unreachable!(),
@@ -1,12 +1,22 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use std::cell::RefCell;
+pub mod signals;
 
-use smithay::output::Output;
+use std::{cell::RefCell, time::Duration};
+
+use smithay::{
+    output::Output, reexports::wayland_protocols::xdg::shell::server::xdg_toplevel,
+    utils::SERIAL_COUNTER,
+};
+
+use self::signals::OutputSignal;
 
 use crate::{
+    focus::FocusTarget,
+    grab::resize_grab::ResizeSurfaceState,
     state::{State, WithState},
     tag::Tag,
+    window::{rules::FloatingOrTiled, WindowElement},
 };
 
 /// A unique identifier for an output.
@@ -27,10 +37,28 @@ impl OutputName {
     }
 }
 
+/// The lowest render scale an output can have.
+pub const MIN_RENDER_SCALE: f64 = 0.5;
+
 /// The state of an output
-#[derive(Default)]
 pub struct OutputState {
     pub tags: Vec<Tag>,
+    /// The fraction of the output's resolution it is rendered at before being scaled up.
+    ///
+    /// This only changes how many pixels get rendered, so clients and input are unaffected.
+    pub render_scale: f64,
+    /// Whether new windows on this output float or tile when their tags don't say.
+    pub default_floating_or_tiled: Option<FloatingOrTiled>,
+}
+
+impl Default for OutputState {
+    fn default() -> Self {
+        Self {
+            tags: Vec::new(),
+            render_scale: 1.0,
+            default_floating_or_tiled: None,
+        }
+    }
 }
 
 impl WithState for Output {
@@ -53,3 +81,131 @@ impl OutputState {
         self.tags.iter().filter(|tag| tag.active())
     }
 }
+
+impl State {
+    /// Set the fraction of `output`'s resolution it is rendered at.
+    ///
+    /// This is clamped between [`MIN_RENDER_SCALE`] and 1.0.
+    /// It currently only has an effect on the udev backend.
+    pub fn set_output_render_scale(&mut self, output: &Output, render_scale: f64) {
+        let render_scale = if render_scale.is_nan() {
+            1.0
+        } else {
+            render_scale.clamp(MIN_RENDER_SCALE, 1.0)
+        };
+
+        tracing::debug!(
+            "Setting render scale of output {} to {render_scale}",
+            output.name()
+        );
+        output.with_state(|state| state.render_scale = render_scale);
+        self.schedule_render(output);
+    }
+
+    /// Unmap `output` and move everything that depended on it elsewhere.
+    ///
+    /// Any pointer grab on a window on `output` is cancelled, fullscreen windows on it are moved
+    /// to the focused tag of a remaining output, and the focused output and pointer are moved
+    /// onto a remaining output. If no outputs remain, these are left alone until one is
+    /// connected again.
+    ///
+    /// Other windows stay on `output`'s tags.
+    pub fn remove_output(&mut self, output: &Output) {
+        self.signal_output(output, OutputSignal::Disconnected);
+        self.screencopy_state.output_removed(output);
+        self.gamma_control_state.output_removed(output);
+
+        let output_tags = output.with_state(|state| state.tags.clone());
+        let windows_on_output = self
+            .windows
+            .iter()
+            .filter(|win| {
+                win.with_state(|state| state.tags.iter().any(|tag| output_tags.contains(tag)))
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+
+        self.cancel_grab_on_windows(&windows_on_output);
+
+        self.space.unmap_output(output);
+
+        if self.focus_state.focused_output.as_ref() == Some(output) {
+            self.focus_state.focused_output = self.space.outputs().next().cloned();
+        }
+
+        if let Some(new_output) = self.focus_state.focused_output.clone() {
+            let new_tag = new_output.with_state(|state| {
+                state
+                    .focused_tags()
+                    .next()
+                    .or_else(|| state.tags.first())
+                    .cloned()
+            });
+
+            let fullscreen_windows = windows_on_output
+                .iter()
+                .filter(|win| win.with_state(|state| state.fullscreen_or_maximized.is_fullscreen()))
+                .collect::<Vec<_>>();
+
+            if let Some(new_tag) = new_tag {
+                // These stay fullscreen and get laid out on the new output
+                for win in fullscreen_windows.iter() {
+                    win.with_state(|state| state.tags = vec![new_tag.clone()]);
+                }
+
+                if !fullscreen_windows.is_empty() {
+                    self.update_windows(&new_output);
+                }
+            }
+        }
+
+        // Windows that were on the unplugged output need to leave it and get the scale
+        // of whatever output they're on now.
+        let windows = self.windows.clone();
+        self.update_window_outputs(windows.iter());
+
+        self.reclamp_pointer();
+    }
+
+    /// Cancel the current pointer grab if it is on one of `windows`.
+    fn cancel_grab_on_windows(&mut self, windows: &[WindowElement]) {
+        let Some(pointer) = self.seat.get_pointer() else {
+            return;
+        };
+
+        let Some(FocusTarget::Window(window)) = pointer
+            .grab_start_data()
+            .and_then(|start_data| start_data.focus)
+            .map(|(focus, _)| focus)
+        else {
+            return;
+        };
+
+        if !windows.contains(&window) {
+            return;
+        }
+
+        tracing::debug!("Cancelling pointer grab on window on removed output");
+        self.grab_restore = None;
+
+        pointer.unset_grab(
+            self,
+            SERIAL_COUNTER.next_serial(),
+            Duration::from(self.clock.now()).as_millis() as u32,
+        );
+        self.hide_grab_hint();
+
+        // Cancelling skips the grab's own cleanup, so stop any resize here.
+        if let WindowElement::Wayland(win) = &window {
+            let toplevel = win.toplevel();
+            toplevel.with_pending_state(|state| {
+                state.states.unset(xdg_toplevel::State::Resizing);
+            });
+            toplevel.send_pending_configure();
+        }
+
+        if let Some(surface) = window.wl_surface() {
+            surface.with_state(|state| state.resize_state = ResizeSurfaceState::Idle);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Telling the config when outputs connect, disconnect, or change modes.
+//!
+//! Unlike `connect_for_all`, connects are signaled every time an output is plugged in, even to a
+//! connector that had one before. Each signal carries the output's logical geometry. For
+//! disconnects this is where it was before it was removed.
+
+use smithay::output::Output;
+
+use crate::{
+    api::msg::{Args, OutgoingMsg},
+    state::State,
+};
+
+/// Something that happened to an output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputSignal {
+    Connected,
+    Disconnected,
+    ModeChanged,
+}
+
+impl State {
+    /// Tell the config that `output` connected, disconnected, or changed modes.
+    ///
+    /// Call this for disconnects before the output is unmapped so its geometry is known.
+    pub fn signal_output(&self, output: &Output, signal: OutputSignal) {
+        let Some(stream) = self.api_state.stream.as_ref() else {
+            return;
+        };
+
+        let callback_ids = match signal {
+            OutputSignal::Connected => &self.config.output_connected_callback_ids,
+            OutputSignal::Disconnected => &self.config.output_disconnected_callback_ids,
+            OutputSignal::ModeChanged => &self.config.output_mode_changed_callback_ids,
+        };
+
+        let Some(geo) = self.space.output_geometry(output) else {
+            return;
+        };
+
+        tracing::debug!("Output {} signal: {signal:?}", output.name());
+
+        for callback_id in callback_ids.iter() {
+            crate::api::send_to_client(
+                &mut stream.lock().expect("couldn't lock stream"),
+                &OutgoingMsg::call_callback(
+                    *callback_id,
+                    Some(Args::OutputGeometry {
+                        output_name: output.name(),
+                        x: geo.loc.x,
+                        y: geo.loc.y,
+                        width: geo.size.w,
+                        height: geo.size.h,
+                    }),
+                    &self.api_state.callback_serial,
+                ),
+            )
+            .expect("Send to client failed");
+        }
+    }
+}
@@ -5,8 +5,9 @@ use std::sync::Mutex;
 use smithay::{
     backend::renderer::{
         element::{
+            solid::SolidColorRenderElement,
             surface::WaylandSurfaceRenderElement,
-            texture::TextureBuffer,
+            texture::{TextureBuffer, TextureRenderElement},
             utils::{CropRenderElement, RelocateRenderElement, RescaleRenderElement},
             AsRenderElements, RenderElementStates, Wrap,
         },
@@ -38,9 +39,20 @@ use crate::{
     window::WindowElement,
 };
 
-use self::pointer::{PointerElement, PointerRenderElement};
+use self::{
+    debug_overlay::DebugOverlay,
+    grab_hint::GrabHint,
+    pointer::{PointerElement, PointerRenderElement},
+    prompt_box::PromptBox,
+    window_flash::WindowFlashState,
+};
 
+pub mod debug_overlay;
+pub mod grab_hint;
 pub mod pointer;
+pub mod prompt_box;
+pub mod text;
+pub mod window_flash;
 
 render_elements! {
     pub TransformRenderElement<R, E>;
@@ -55,6 +67,8 @@ render_elements! {
     Surface = WaylandSurfaceRenderElement<R>,
     Pointer = PointerRenderElement<R>,
     Transform = TransformRenderElement<R, E>,
+    SolidColor = SolidColorRenderElement,
+    Text = TextureRenderElement<<R as Renderer>::TextureId>,
 }
 
 impl<R> AsRenderElements<R> for WindowElement
@@ -193,6 +207,10 @@ pub fn generate_render_elements<R, T>(
     // input_method: &InputMethodHandle,
     pointer_element: &mut PointerElement<T>,
     pointer_image: Option<&TextureBuffer<T>>,
+    debug_overlay: &DebugOverlay,
+    grab_hint: &GrabHint,
+    prompt_box: &PromptBox,
+    window_flash: &WindowFlashState,
 ) -> Vec<OutputRenderElements<R, WaylandSurfaceRenderElement<R>>>
 where
     R: Renderer<TextureId = T> + ImportAll + ImportMem,
@@ -268,17 +286,50 @@ where
             1.0,
         ));
 
+        // The drag and drop icon is a pointer element too, so screencopy leaves it out
+        // along with the cursor
         if let Some(dnd_icon) = dnd_icon {
-            output_render_elements.extend(AsRenderElements::render_elements(
-                &smithay::desktop::space::SurfaceTree::from_surface(dnd_icon),
-                renderer,
-                cursor_pos_scaled,
-                scale,
-                1.0,
-            ));
+            output_render_elements.extend(
+                AsRenderElements::<R>::render_elements::<PointerRenderElement<R>>(
+                    &smithay::desktop::space::SurfaceTree::from_surface(dnd_icon),
+                    renderer,
+                    cursor_pos_scaled,
+                    scale,
+                    1.0,
+                )
+                .into_iter()
+                .map(OutputRenderElements::Pointer),
+            );
         }
     }
 
+    // The grab hint goes above the window being grabbed
+    output_render_elements.extend(
+        grab_hint
+            .render_element(renderer, output, space)
+            .map(OutputRenderElements::from),
+    );
+
+    output_render_elements.extend(
+        prompt_box
+            .render_element(renderer, output, space)
+            .map(OutputRenderElements::from),
+    );
+
+    output_render_elements.extend(
+        window_flash
+            .render_elements(output, space)
+            .into_iter()
+            .map(OutputRenderElements::from),
+    );
+
+    output_render_elements.extend(
+        debug_overlay
+            .render_elements(output, space, &windows)
+            .into_iter()
+            .map(OutputRenderElements::from),
+    );
+
     let o_r_elements = override_redirect_windows.iter().flat_map(|surf| {
         surf.render_elements::<WaylandSurfaceRenderElement<R>>(
             renderer,
@@ -391,10 +442,13 @@ pub fn take_presentation_feedback(
 }
 
 impl State {
-    /// Schedule a new render. This does nothing on the winit backend.
+    /// Schedule a new render.
+    ///
+    /// On winit, the window is rendered on the next poll of winit's events.
     pub fn schedule_render(&mut self, output: &Output) {
-        if let Backend::Udev(udev) = &mut self.backend {
-            udev.schedule_render(&self.loop_handle, output);
+        match &mut self.backend {
+            Backend::Udev(udev) => udev.schedule_render(&self.loop_handle, output),
+            Backend::Winit(winit) => winit.render_scheduled = true,
         }
     }
 }
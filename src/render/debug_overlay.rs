@@ -0,0 +1,426 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! An overlay for debugging surface geometry, input and opaque regions, and damage.
+//!
+//! Annotations are drawn as translucent outlines on top of everything else. While no flags
+//! are set, nothing is collected on commit and no render elements are generated.
+//!
+//! The surface with pointer focus gets a highlighted outline. There is no text rendering yet,
+//! so its name is logged whenever pointer focus changes instead of being drawn.
+
+use std::time::{Duration, Instant};
+
+use smithay::{
+    backend::renderer::{
+        element::{solid::SolidColorRenderElement, Id, Kind},
+        utils::{CommitCounter, RendererSurfaceStateUserData},
+    },
+    desktop::{layer_map_for_output, space::SpaceElement, LayerSurface, Space, WindowSurfaceType},
+    output::Output,
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    utils::{Logical, Physical, Point, Rectangle, Scale},
+    wayland::compositor::{
+        self, Damage, RectangleKind, SubsurfaceCachedState, SurfaceAttributes, TraversalAction,
+    },
+};
+
+use crate::{focus::FocusTarget, state::State, window::WindowElement};
+
+/// How long damaged rectangles stay visible.
+const DAMAGE_FLASH_DURATION: Duration = Duration::from_millis(300);
+
+/// The width of outlines in physical pixels.
+const OUTLINE_WIDTH: i32 = 2;
+
+const GEOMETRY_COLOR: [f32; 4] = [0.0, 0.6, 0.0, 0.6];
+const INPUT_REGION_COLOR: [f32; 4] = [0.0, 0.3, 0.8, 0.6];
+const INPUT_REGION_SUBTRACT_COLOR: [f32; 4] = [0.3, 0.3, 0.3, 0.6];
+const OPAQUE_REGION_COLOR: [f32; 4] = [0.8, 0.0, 0.0, 0.6];
+const DAMAGE_COLOR: [f32; 4] = [0.5, 0.5, 0.0, 0.5];
+const POINTER_FOCUS_COLOR: [f32; 4] = [0.8, 0.0, 0.8, 0.8];
+
+/// Which annotations the debug overlay draws.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DebugOverlayFlags(u32);
+
+impl DebugOverlayFlags {
+    /// Outline the geometry of windows and layer surfaces.
+    pub const GEOMETRY: Self = Self(1);
+    /// Outline the input region of every surface.
+    pub const INPUT_REGION: Self = Self(1 << 1);
+    /// Outline the opaque region of every surface.
+    pub const OPAQUE_REGION: Self = Self(1 << 2);
+    /// Flash the rectangles that surfaces damage when they commit.
+    pub const DAMAGE: Self = Self(1 << 3);
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DebugOverlay {
+    pub flags: DebugOverlayFlags,
+    /// Damaged rectangles in global coordinates, along with when they were damaged.
+    damage: Vec<(Rectangle<i32, Logical>, Instant)>,
+    /// The current pointer focus.
+    pointer_focus: Option<FocusTarget>,
+}
+
+impl DebugOverlay {
+    /// Returns whether any damage is still being flashed.
+    ///
+    /// Outputs need to keep rendering while this is true so the flashes fade out.
+    pub fn is_flashing_damage(&self) -> bool {
+        self.damage
+            .iter()
+            .any(|(_, time)| time.elapsed() < DAMAGE_FLASH_DURATION)
+    }
+
+    /// Generate the overlay's render elements for `output`.
+    ///
+    /// `windows` should be in the same order they're rendered in.
+    pub fn render_elements(
+        &self,
+        output: &Output,
+        space: &Space<WindowElement>,
+        windows: &[WindowElement],
+    ) -> Vec<SolidColorRenderElement> {
+        if self.flags.is_empty() {
+            return Vec::new();
+        }
+
+        let Some(output_geo) = space.output_geometry(output) else {
+            return Vec::new();
+        };
+        let scale = Scale::from(output.current_scale().fractional_scale());
+
+        // Rectangles in global coordinates, along with their color and whether to fill them
+        let mut rects: Vec<(Rectangle<i32, Logical>, [f32; 4], bool)> = Vec::new();
+
+        if let Some(geo) = self
+            .pointer_focus
+            .as_ref()
+            .and_then(|focus| focus_geometry(focus, space))
+        {
+            rects.push((geo, POINTER_FOCUS_COLOR, false));
+        }
+
+        for win in windows
+            .iter()
+            .filter(|win| win.is_on_active_tag(space.outputs()))
+        {
+            let Some(geo) = space.element_geometry(win) else {
+                continue;
+            };
+
+            if !geo.overlaps(output_geo) {
+                continue;
+            }
+
+            if self.flags.contains(DebugOverlayFlags::GEOMETRY) {
+                rects.push((geo, GEOMETRY_COLOR, false));
+            }
+
+            if let Some(surface) = win.wl_surface() {
+                let origin = geo.loc - win.geometry().loc;
+                self.collect_surface_regions(&surface, origin, &mut rects);
+            }
+        }
+
+        {
+            let layer_map = layer_map_for_output(output);
+            for layer in layer_map.layers() {
+                let Some(geo) = layer_map.layer_geometry(layer) else {
+                    continue;
+                };
+                let geo = Rectangle::from_loc_and_size(geo.loc + output_geo.loc, geo.size);
+
+                if self.flags.contains(DebugOverlayFlags::GEOMETRY) {
+                    rects.push((geo, GEOMETRY_COLOR, false));
+                }
+
+                self.collect_surface_regions(layer.wl_surface(), geo.loc, &mut rects);
+            }
+        }
+
+        if self.flags.contains(DebugOverlayFlags::DAMAGE) {
+            for (rect, time) in self.damage.iter() {
+                let elapsed = time.elapsed();
+                if elapsed >= DAMAGE_FLASH_DURATION {
+                    continue;
+                }
+
+                let fade = 1.0 - elapsed.as_secs_f32() / DAMAGE_FLASH_DURATION.as_secs_f32();
+                rects.push((*rect, DAMAGE_COLOR.map(|c| c * fade), true));
+            }
+        }
+
+        rects
+            .into_iter()
+            .filter_map(|(rect, color, fill)| {
+                let rect = rect.intersection(output_geo)?;
+                let rect = Rectangle::from_loc_and_size(rect.loc - output_geo.loc, rect.size)
+                    .to_physical_precise_round(scale);
+                Some((rect, color, fill))
+            })
+            .flat_map(|(rect, color, fill)| {
+                let rects = if fill { vec![rect] } else { outline(rect) };
+
+                rects.into_iter().map(move |rect| {
+                    SolidColorRenderElement::new(
+                        Id::new(),
+                        rect,
+                        CommitCounter::default(),
+                        color,
+                        Kind::Unspecified,
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Collect the input and opaque regions of `surface` and its subsurfaces, with `surface`
+    /// at `origin` in global coordinates.
+    fn collect_surface_regions(
+        &self,
+        surface: &WlSurface,
+        origin: Point<i32, Logical>,
+        rects: &mut Vec<(Rectangle<i32, Logical>, [f32; 4], bool)>,
+    ) {
+        let input_region = self.flags.contains(DebugOverlayFlags::INPUT_REGION);
+        let opaque_region = self.flags.contains(DebugOverlayFlags::OPAQUE_REGION);
+
+        if !input_region && !opaque_region {
+            return;
+        }
+
+        compositor::with_surface_tree_downward(
+            surface,
+            origin,
+            |_, states, loc| {
+                let mut loc = *loc;
+                if states.role == Some("subsurface") {
+                    loc += states
+                        .cached_state
+                        .current::<SubsurfaceCachedState>()
+                        .location;
+                }
+                TraversalAction::DoChildren(loc)
+            },
+            |_, states, loc| {
+                let mut loc = *loc;
+                if states.role == Some("subsurface") {
+                    loc += states
+                        .cached_state
+                        .current::<SubsurfaceCachedState>()
+                        .location;
+                }
+
+                // Surfaces without a buffer aren't visible
+                let Some(size) = states
+                    .data_map
+                    .get::<RendererSurfaceStateUserData>()
+                    .and_then(|data| data.borrow().surface_size())
+                else {
+                    return;
+                };
+
+                let bounds = Rectangle::from_loc_and_size(loc, size);
+                let attrs = states.cached_state.current::<SurfaceAttributes>();
+
+                if input_region {
+                    match attrs.input_region.as_ref() {
+                        // No input region means the whole surface accepts input
+                        None => rects.push((bounds, INPUT_REGION_COLOR, false)),
+                        Some(region) => {
+                            for (kind, rect) in region.rects.iter() {
+                                let color = match kind {
+                                    RectangleKind::Add => INPUT_REGION_COLOR,
+                                    RectangleKind::Subtract => INPUT_REGION_SUBTRACT_COLOR,
+                                };
+                                let rect = Rectangle::from_loc_and_size(rect.loc + loc, rect.size);
+                                if let Some(rect) = rect.intersection(bounds) {
+                                    rects.push((rect, color, false));
+                                }
+                            }
+                        }
+                    }
+                }
+
+                if opaque_region {
+                    if let Some(region) = attrs.opaque_region.as_ref() {
+                        for (kind, rect) in region.rects.iter() {
+                            if *kind == RectangleKind::Subtract {
+                                continue;
+                            }
+                            let rect = Rectangle::from_loc_and_size(rect.loc + loc, rect.size);
+                            if let Some(rect) = rect.intersection(bounds) {
+                                rects.push((rect, OPAQUE_REGION_COLOR, false));
+                            }
+                        }
+                    }
+                }
+            },
+            |_, _, _| true,
+        );
+    }
+}
+
+impl State {
+    /// Set which annotations the debug overlay draws. Empty flags turn it off.
+    pub fn set_debug_overlay(&mut self, flags: DebugOverlayFlags) {
+        tracing::info!("Setting debug overlay flags to {flags:?}");
+        self.debug_overlay.flags = flags;
+
+        if flags.is_empty() {
+            self.debug_overlay.damage.clear();
+            self.debug_overlay.pointer_focus = None;
+        }
+
+        for output in self.space.outputs().cloned().collect::<Vec<_>>() {
+            self.schedule_render(&output);
+        }
+    }
+
+    /// Track pointer focus for the debug overlay, logging the new focus.
+    pub fn debug_overlay_pointer_focus(&mut self, focus: &FocusTarget) {
+        if self.debug_overlay.flags.is_empty() {
+            return;
+        }
+
+        tracing::info!("Pointer focus: {}", focus_name(focus));
+        self.debug_overlay.pointer_focus = Some(focus.clone());
+    }
+
+    /// Record the damage `surface` is about to commit.
+    ///
+    /// This must be called before the commit is handled, as handling it consumes the damage.
+    pub fn record_debug_damage(&mut self, surface: &WlSurface) {
+        if !self.debug_overlay.flags.contains(DebugOverlayFlags::DAMAGE) {
+            return;
+        }
+
+        let Some(loc) = self.surface_global_location(surface) else {
+            return;
+        };
+
+        let damage = compositor::with_states(surface, |states| {
+            let attrs = states.cached_state.current::<SurfaceAttributes>();
+            let buffer_scale = attrs.buffer_scale.max(1);
+            attrs
+                .damage
+                .iter()
+                .map(|damage| match damage {
+                    Damage::Surface(rect) => *rect,
+                    // Buffer transforms are ignored, this is only for debugging
+                    Damage::Buffer(rect) => Rectangle::from_loc_and_size(
+                        (rect.loc.x / buffer_scale, rect.loc.y / buffer_scale),
+                        (rect.size.w / buffer_scale, rect.size.h / buffer_scale),
+                    ),
+                })
+                .collect::<Vec<_>>()
+        });
+
+        if damage.is_empty() {
+            return;
+        }
+
+        let now = Instant::now();
+        let overlay = &mut self.debug_overlay;
+        overlay
+            .damage
+            .retain(|(_, time)| time.elapsed() < DAMAGE_FLASH_DURATION);
+        overlay.damage.extend(
+            damage
+                .into_iter()
+                .map(|rect| (Rectangle::from_loc_and_size(rect.loc + loc, rect.size), now)),
+        );
+    }
+
+    /// Get the location of `surface` in global coordinates, if it belongs to a window or layer
+    /// surface.
+    fn surface_global_location(&self, surface: &WlSurface) -> Option<Point<i32, Logical>> {
+        let mut loc = Point::from((0, 0));
+        let mut root = surface.clone();
+        while let Some(parent) = compositor::get_parent(&root) {
+            loc += compositor::with_states(&root, |states| {
+                states
+                    .cached_state
+                    .current::<SubsurfaceCachedState>()
+                    .location
+            });
+            root = parent;
+        }
+
+        if let Some(win) = self.window_for_surface(&root) {
+            let win_loc = self.space.element_location(&win)?;
+            return Some(loc + win_loc - win.geometry().loc);
+        }
+
+        self.space.outputs().find_map(|output| {
+            let layer_map = layer_map_for_output(output);
+            let layer = layer_map.layer_for_surface(&root, WindowSurfaceType::TOPLEVEL)?;
+            let layer_geo = layer_map.layer_geometry(layer)?;
+            let output_geo = self.space.output_geometry(output)?;
+            Some(loc + layer_geo.loc + output_geo.loc)
+        })
+    }
+}
+
+/// Get the geometry of `focus` in global coordinates.
+fn focus_geometry(
+    focus: &FocusTarget,
+    space: &Space<WindowElement>,
+) -> Option<Rectangle<i32, Logical>> {
+    match focus {
+        FocusTarget::Window(win) => space.element_geometry(win),
+        FocusTarget::LayerSurface(layer) => layer_geometry(layer, space),
+        // TODO: popups
+        FocusTarget::Popup(_) => None,
+    }
+}
+
+fn layer_geometry(
+    layer: &LayerSurface,
+    space: &Space<WindowElement>,
+) -> Option<Rectangle<i32, Logical>> {
+    space.outputs().find_map(|output| {
+        let layer_map = layer_map_for_output(output);
+        let geo = layer_map.layer_geometry(layer)?;
+        let output_geo = space.output_geometry(output)?;
+        Some(Rectangle::from_loc_and_size(
+            geo.loc + output_geo.loc,
+            geo.size,
+        ))
+    })
+}
+
+fn focus_name(focus: &FocusTarget) -> String {
+    match focus {
+        FocusTarget::Window(win) => format!(
+            "window {} ({})",
+            win.class().unwrap_or("None".to_string()),
+            win.title().unwrap_or("None".to_string())
+        ),
+        FocusTarget::Popup(_) => "popup".to_string(),
+        FocusTarget::LayerSurface(layer) => format!("layer surface {}", layer.namespace()),
+    }
+}
+
+/// Split `rect` into four rectangles that outline it.
+fn outline(rect: Rectangle<i32, Physical>) -> Vec<Rectangle<i32, Physical>> {
+    let Rectangle { loc, size } = rect;
+    let width = OUTLINE_WIDTH.min(size.w).min(size.h);
+
+    vec![
+        Rectangle::from_loc_and_size(loc, (size.w, width)),
+        Rectangle::from_loc_and_size((loc.x, loc.y + size.h - width), (size.w, width)),
+        Rectangle::from_loc_and_size(loc, (width, size.h)),
+        Rectangle::from_loc_and_size((loc.x + size.w - width, loc.y), (width, size.h)),
+    ]
+}
@@ -0,0 +1,142 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! A small label next to the pointer that shows a window's size while it's being resized
+//! and its location while it's being moved.
+//!
+//! The label is a [`TextBox`] in the UI font.
+
+use smithay::{
+    backend::renderer::{element::texture::TextureRenderElement, ImportMem, Renderer},
+    desktop::Space,
+    output::Output,
+    utils::{Logical, Point, Rectangle, Scale},
+};
+
+use crate::{state::State, window::WindowElement};
+
+use super::text::{TextBox, UiFont};
+
+/// How far the label is from the pointer.
+const POINTER_OFFSET: (i32, i32) = (16, 16);
+
+/// The space between the text and the edge of the label, as a fraction of the font size.
+const PADDING: f64 = 0.4;
+
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const BACKGROUND_COLOR: [f32; 4] = [0.1, 0.1, 0.1, 0.85];
+
+#[derive(Debug)]
+pub struct GrabHint {
+    /// Whether to show the label during grabs.
+    pub enabled: bool,
+    shown: bool,
+    /// The top left corner of the label in global coordinates.
+    loc: Point<i32, Logical>,
+    label: TextBox,
+}
+
+impl Default for GrabHint {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            shown: false,
+            loc: (0, 0).into(),
+            label: TextBox::new(PADDING, 0, BACKGROUND_COLOR),
+        }
+    }
+}
+
+impl GrabHint {
+    /// Show `text` next to `pointer_loc`, or update it if it's already shown.
+    fn show(&mut self, text: String, pointer_loc: Point<f64, Logical>, font: &UiFont) {
+        self.loc = pointer_loc.to_i32_round() + Point::from(POINTER_OFFSET);
+        self.shown = true;
+        self.label.set_spans(vec![(text, TEXT_COLOR)], font);
+    }
+
+    /// Get the label's geometry in global coordinates, if it's shown.
+    fn geometry(&self) -> Option<Rectangle<i32, Logical>> {
+        self.shown
+            .then(|| Rectangle::from_loc_and_size(self.loc, self.label.size()))
+    }
+
+    /// Generate the label's render element for `output`.
+    pub fn render_element<R>(
+        &self,
+        renderer: &mut R,
+        output: &Output,
+        space: &Space<WindowElement>,
+    ) -> Option<TextureRenderElement<<R as Renderer>::TextureId>>
+    where
+        R: Renderer + ImportMem,
+        <R as Renderer>::TextureId: Clone + 'static,
+    {
+        let (Some(geo), Some(output_geo)) = (self.geometry(), space.output_geometry(output)) else {
+            return None;
+        };
+        if !geo.overlaps(output_geo) {
+            return None;
+        }
+
+        let scale = Scale::from(output.current_scale().fractional_scale());
+        self.label
+            .render_element(renderer, geo.loc - output_geo.loc, scale)
+    }
+}
+
+impl State {
+    /// Show `text` in the grab hint next to the pointer.
+    pub fn show_grab_hint(&mut self, text: String) {
+        if !self.grab_hint.enabled {
+            return;
+        }
+
+        let old_geo = self.grab_hint.geometry();
+        self.grab_hint
+            .show(text, self.pointer_location, &self.ui_font);
+        self.schedule_grab_hint_render(old_geo);
+    }
+
+    /// Redraw the grab hint after the UI font changed.
+    pub fn update_grab_hint_font(&mut self) {
+        let old_geo = self.grab_hint.geometry();
+        self.grab_hint.label.relayout(&self.ui_font);
+        self.schedule_grab_hint_render(old_geo);
+    }
+
+    /// Hide the grab hint. This should be called when a grab ends.
+    pub fn hide_grab_hint(&mut self) {
+        let old_geo = self.grab_hint.geometry();
+        self.grab_hint.shown = false;
+        self.schedule_grab_hint_render(old_geo);
+    }
+
+    /// Turn grab hints on or off.
+    pub fn set_grab_hints(&mut self, enabled: bool) {
+        self.grab_hint.enabled = enabled;
+        if !enabled {
+            self.hide_grab_hint();
+        }
+    }
+
+    /// Render the outputs the grab hint was on and is on now.
+    fn schedule_grab_hint_render(&mut self, old_geo: Option<Rectangle<i32, Logical>>) {
+        let geos = [old_geo, self.grab_hint.geometry()];
+        let outputs = self
+            .space
+            .outputs()
+            .filter(|output| {
+                self.space
+                    .output_geometry(output)
+                    .is_some_and(|output_geo| {
+                        geos.iter().flatten().any(|geo| geo.overlaps(output_geo))
+                    })
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for output in outputs {
+            self.schedule_render(&output);
+        }
+    }
+}
@@ -0,0 +1,128 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! The box a prompt is typed into, centered near the top of the output it was opened on.
+//!
+//! The box shows the prompt's title followed by what has been typed so far and a cursor.
+//! Like the grab hint, it's a [`TextBox`] in the UI font.
+
+use smithay::{
+    backend::renderer::{element::texture::TextureRenderElement, ImportMem, Renderer},
+    desktop::Space,
+    output::Output,
+    utils::{Point, Scale},
+};
+
+use crate::{state::State, window::WindowElement};
+
+use super::text::{TextBox, UiFont};
+
+/// The space between the text and the edge of the box, as a fraction of the font size.
+const PADDING: f64 = 0.6;
+
+/// The narrowest the box gets, in characters, so it doesn't grow with every key early on.
+const MIN_WIDTH_CHARS: usize = 32;
+
+const TITLE_COLOR: [f32; 4] = [0.6, 0.6, 0.6, 1.0];
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const BACKGROUND_COLOR: [f32; 4] = [0.1, 0.1, 0.1, 0.9];
+
+#[derive(Debug)]
+pub struct PromptBox {
+    /// The output the box is shown on, if it's shown
+    output: Option<Output>,
+    title: String,
+    text: String,
+    text_box: TextBox,
+}
+
+impl Default for PromptBox {
+    fn default() -> Self {
+        Self {
+            output: None,
+            title: String::new(),
+            text: String::new(),
+            text_box: TextBox::new(PADDING, MIN_WIDTH_CHARS, BACKGROUND_COLOR),
+        }
+    }
+}
+
+impl PromptBox {
+    /// Show the title and text in `font`.
+    fn relayout(&mut self, font: &UiFont) {
+        if self.output.is_none() {
+            return;
+        }
+
+        let spans = vec![
+            (format!("{} ", self.title), TITLE_COLOR),
+            (format!("{}_", self.text), TEXT_COLOR),
+        ];
+        self.text_box.set_spans(spans, font);
+    }
+
+    /// Generate the box's render element for `output`.
+    pub fn render_element<R>(
+        &self,
+        renderer: &mut R,
+        output: &Output,
+        space: &Space<WindowElement>,
+    ) -> Option<TextureRenderElement<<R as Renderer>::TextureId>>
+    where
+        R: Renderer + ImportMem,
+        <R as Renderer>::TextureId: Clone + 'static,
+    {
+        if self.output.as_ref() != Some(output) {
+            return None;
+        }
+
+        let output_geo = space.output_geometry(output)?;
+        let scale = Scale::from(output.current_scale().fractional_scale());
+
+        // Relative to the output, so the box stays put if the output moves
+        let loc = Point::from((
+            (output_geo.size.w - self.text_box.size().w) / 2,
+            output_geo.size.h / 3,
+        ));
+
+        self.text_box.render_element(renderer, loc, scale)
+    }
+}
+
+impl State {
+    /// Show the prompt box with `title` and nothing typed on `output`.
+    pub fn show_prompt_box(&mut self, output: &Output, title: String) {
+        self.hide_prompt_box();
+
+        self.prompt_box.output = Some(output.clone());
+        self.prompt_box.title = title;
+        self.prompt_box.text.clear();
+        self.prompt_box.relayout(&self.ui_font);
+        self.schedule_render(output);
+    }
+
+    /// Show `text` as what has been typed in the prompt box.
+    pub fn set_prompt_box_text(&mut self, text: &str) {
+        let Some(output) = self.prompt_box.output.clone() else {
+            return;
+        };
+
+        self.prompt_box.text = text.to_string();
+        self.prompt_box.relayout(&self.ui_font);
+        self.schedule_render(&output);
+    }
+
+    /// Redraw the prompt box after the UI font changed.
+    pub fn update_prompt_box_font(&mut self) {
+        self.prompt_box.text_box.relayout(&self.ui_font);
+        if let Some(output) = self.prompt_box.output.clone() {
+            self.schedule_render(&output);
+        }
+    }
+
+    /// Hide the prompt box.
+    pub fn hide_prompt_box(&mut self) {
+        if let Some(output) = self.prompt_box.output.take() {
+            self.schedule_render(&output);
+        }
+    }
+}
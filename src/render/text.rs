@@ -0,0 +1,732 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Text drawn by Pinnacle itself, like the grab hint's label and the prompt box.
+//!
+//! Text is drawn with one of the system's fonts, found through fontconfig's font directories.
+//! Families that aren't installed fall back to the system's sans-serif font. If the system has
+//! no fonts at all, a tiny built-in bitmap font is used instead, which can also be picked by
+//! name. It has every printable ASCII character and draws anything else as a box. Its pixels are
+//! drawn as squares, so its text heights are rounded to a multiple of the font's height.
+//!
+//! Text is shown in a [`TextBox`], a line of text on a background. The box is rasterized into a
+//! texture for each scale it's drawn at, and the textures are kept until the text or font
+//! changes, so moving the box only damages where it was and where it is now. Measuring text uses
+//! the same layout as drawing it, so configs get the size text is actually drawn at.
+
+use std::{
+    any::Any,
+    cell::RefCell,
+    fmt,
+    sync::{Arc, OnceLock},
+};
+
+use smithay::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            element::{
+                texture::{TextureBuffer, TextureRenderElement},
+                Kind,
+            },
+            ImportMem, Renderer,
+        },
+    },
+    utils::{Logical, Physical, Point, Scale, Size, Transform},
+};
+
+use crate::state::State;
+
+/// The name of the built-in bitmap font.
+pub const BUILTIN_FONT: &str = "builtin";
+
+/// The family the UI font has until a config sets it.
+pub const DEFAULT_FONT_FAMILY: &str = "sans-serif";
+/// The size the UI font has until a config sets it.
+pub const DEFAULT_FONT_SIZE: i32 = 13;
+
+const GLYPH_WIDTH: i32 = 3;
+const GLYPH_HEIGHT: i32 = 5;
+
+/// The font used by Pinnacle's own UI.
+#[derive(Debug, Clone)]
+pub struct UiFont {
+    /// The font family that was asked for
+    pub family: String,
+    /// The size of the font in logical pixels
+    pub size: i32,
+    /// The face text is drawn with, which may be a fallback for `family`
+    face: Arc<Face>,
+}
+
+impl PartialEq for UiFont {
+    fn eq(&self, other: &Self) -> bool {
+        self.family == other.family && self.size == other.size
+    }
+}
+
+impl Default for UiFont {
+    fn default() -> Self {
+        Self::new(DEFAULT_FONT_FAMILY, DEFAULT_FONT_SIZE)
+    }
+}
+
+impl UiFont {
+    /// Load `family` at `size` logical pixels, falling back to another font if it isn't installed.
+    pub fn new(family: &str, size: i32) -> Self {
+        Self {
+            family: family.to_string(),
+            size: size.max(1),
+            face: Arc::new(Face::load(family)),
+        }
+    }
+
+    /// Get this font at another size without loading it again.
+    pub fn with_size(&self, size: i32) -> Self {
+        Self {
+            size: size.max(1),
+            ..self.clone()
+        }
+    }
+
+    /// Get the size of `text` drawn at `size` logical pixels.
+    ///
+    /// Empty text has no width, but still has the height of a line.
+    pub fn measure(&self, text: &str, size: i32) -> Size<i32, Logical> {
+        let line = self.face.layout(text.chars(), size.max(1) as f32);
+        (line.width.ceil() as i32, line.height.ceil() as i32).into()
+    }
+}
+
+/// Get the fonts installed on the system, loading them the first time.
+fn font_database() -> &'static fontdb::Database {
+    static DATABASE: OnceLock<fontdb::Database> = OnceLock::new();
+    DATABASE.get_or_init(|| {
+        let mut database = fontdb::Database::new();
+        database.load_system_fonts();
+        tracing::debug!("Loaded {} font faces", database.len());
+
+        // fontdb's generic families default to fonts that usually aren't installed on Linux,
+        // so point them at the first common one that is
+        if let Some(family) = first_installed(
+            &database,
+            &["Noto Sans", "DejaVu Sans", "Liberation Sans", "Cantarell"],
+        ) {
+            database.set_sans_serif_family(family);
+        }
+        if let Some(family) = first_installed(
+            &database,
+            &["Noto Serif", "DejaVu Serif", "Liberation Serif"],
+        ) {
+            database.set_serif_family(family);
+        }
+        if let Some(family) = first_installed(
+            &database,
+            &["Noto Sans Mono", "DejaVu Sans Mono", "Liberation Mono"],
+        ) {
+            database.set_monospace_family(family);
+        }
+
+        database
+    })
+}
+
+/// Get the first of `families` that has a face in `database`.
+fn first_installed(database: &fontdb::Database, families: &[&str]) -> Option<String> {
+    families
+        .iter()
+        .find(|family| {
+            database
+                .faces()
+                .any(|face| face.families.iter().any(|(name, _)| name == *family))
+        })
+        .map(|family| family.to_string())
+}
+
+/// A font face that text can be laid out and drawn with.
+enum Face {
+    System(fontdue::Font),
+    Builtin,
+}
+
+impl fmt::Debug for Face {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::System(_) => write!(f, "System"),
+            Self::Builtin => write!(f, "Builtin"),
+        }
+    }
+}
+
+/// A laid out line of text.
+#[derive(Debug, Default)]
+struct Line {
+    /// Each character with the x coordinate of its pen position
+    glyphs: Vec<(char, f32)>,
+    width: f32,
+    /// The distance from the top of the line to the baseline
+    ascent: f32,
+    height: f32,
+}
+
+impl Face {
+    /// Load the face for `family`.
+    ///
+    /// `"sans-serif"`, `"serif"`, and `"monospace"` get the system's font for those families.
+    fn load(family: &str) -> Self {
+        if family == BUILTIN_FONT {
+            return Self::Builtin;
+        }
+
+        let database = font_database();
+        let query = |family: fontdb::Family<'_>| {
+            database.query(&fontdb::Query {
+                families: &[family],
+                ..Default::default()
+            })
+        };
+
+        let requested = match family {
+            "sans-serif" => fontdb::Family::SansSerif,
+            "serif" => fontdb::Family::Serif,
+            "monospace" => fontdb::Family::Monospace,
+            name => fontdb::Family::Name(name),
+        };
+
+        let id = query(requested)
+            .or_else(|| {
+                tracing::warn!(
+                    "Font family {family:?} isn't installed, falling back to sans-serif"
+                );
+                query(fontdb::Family::SansSerif)
+            })
+            .or_else(|| database.faces().next().map(|face| face.id));
+
+        let Some(id) = id else {
+            tracing::warn!("No fonts are installed, falling back to the built-in font");
+            return Self::Builtin;
+        };
+
+        let font = database.with_face_data(id, |data, index| {
+            fontdue::Font::from_bytes(
+                data,
+                fontdue::FontSettings {
+                    collection_index: index,
+                    ..Default::default()
+                },
+            )
+        });
+
+        match font {
+            Some(Ok(font)) => Self::System(font),
+            Some(Err(err)) => {
+                tracing::warn!(
+                    "Failed to load font {family:?}, falling back to the built-in font: {err}"
+                );
+                Self::Builtin
+            }
+            None => {
+                tracing::warn!("Failed to read font {family:?}, falling back to the built-in font");
+                Self::Builtin
+            }
+        }
+    }
+
+    /// Lay out `text` in one line at `px` pixels.
+    fn layout(&self, text: impl Iterator<Item = char>, px: f32) -> Line {
+        match self {
+            Self::System(font) => {
+                let (ascent, height) = match font.horizontal_line_metrics(px) {
+                    Some(metrics) => (metrics.ascent, metrics.ascent - metrics.descent),
+                    None => (px, px),
+                };
+
+                let mut line = Line {
+                    ascent,
+                    height,
+                    ..Default::default()
+                };
+                let mut prev = None;
+                for c in text {
+                    if let Some(prev) = prev {
+                        line.width += font.horizontal_kern(prev, c, px).unwrap_or(0.0);
+                    }
+                    line.glyphs.push((c, line.width));
+                    line.width += font.metrics(c, px).advance_width;
+                    prev = Some(c);
+                }
+
+                line
+            }
+            Self::Builtin => {
+                let pixel_size = builtin_pixel_size(px) as f32;
+                let glyphs = text
+                    .enumerate()
+                    .map(|(i, c)| (c, i as f32 * (GLYPH_WIDTH + 1) as f32 * pixel_size))
+                    .collect::<Vec<_>>();
+
+                // The last character doesn't have a gap after it
+                let width = (glyphs.len() as f32 * (GLYPH_WIDTH + 1) as f32 - 1.0).max(0.0);
+
+                Line {
+                    glyphs,
+                    width: width * pixel_size,
+                    ascent: GLYPH_HEIGHT as f32 * pixel_size,
+                    height: GLYPH_HEIGHT as f32 * pixel_size,
+                }
+            }
+        }
+    }
+
+    /// Draw `c` at `px` pixels onto `canvas` with its pen position at `pen_x` on the `baseline`.
+    fn draw(
+        &self,
+        canvas: &mut Canvas,
+        c: char,
+        px: f32,
+        pen_x: f32,
+        baseline: i32,
+        color: [f32; 4],
+    ) {
+        match self {
+            Self::System(font) => {
+                let (metrics, coverage) = font.rasterize(c, px);
+                let left = pen_x.round() as i32 + metrics.xmin;
+                let top = baseline - metrics.ymin - metrics.height as i32;
+
+                for (i, coverage) in coverage.into_iter().enumerate() {
+                    let x = left + (i % metrics.width.max(1)) as i32;
+                    let y = top + (i / metrics.width.max(1)) as i32;
+                    canvas.blend(x, y, coverage as f32 / 255.0, color);
+                }
+            }
+            Self::Builtin => {
+                let pixel_size = builtin_pixel_size(px);
+                let left = pen_x.round() as i32;
+                let top = baseline - GLYPH_HEIGHT * pixel_size;
+
+                for (row_y, row) in glyph(c).iter().enumerate() {
+                    for col_x in 0..GLYPH_WIDTH {
+                        if row & (1 << (GLYPH_WIDTH - 1 - col_x)) == 0 {
+                            continue;
+                        }
+
+                        for y in 0..pixel_size {
+                            for x in 0..pixel_size {
+                                canvas.blend(
+                                    left + col_x * pixel_size + x,
+                                    top + row_y as i32 * pixel_size + y,
+                                    1.0,
+                                    color,
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Get the size of one pixel of the built-in font at a text height of `px`.
+fn builtin_pixel_size(px: f32) -> i32 {
+    ((px / GLYPH_HEIGHT as f32).round() as i32).max(1)
+}
+
+/// Premultiplied RGBA pixels that text is drawn onto.
+struct Canvas {
+    pixels: Vec<u8>,
+    size: Size<i32, Physical>,
+}
+
+impl Canvas {
+    /// Create a canvas of `size` filled with `color`.
+    fn new(size: Size<i32, Physical>, color: [f32; 4]) -> Self {
+        let [r, g, b, a] = color;
+        let pixel = [r * a, g * a, b * a, a].map(|channel| (channel * 255.0).round() as u8);
+
+        Self {
+            pixels: pixel.repeat((size.w * size.h) as usize),
+            size,
+        }
+    }
+
+    /// Draw `color` over the pixel at `(x, y)` with `coverage` of it covered.
+    ///
+    /// Pixels outside the canvas are ignored.
+    fn blend(&mut self, x: i32, y: i32, coverage: f32, color: [f32; 4]) {
+        if x < 0 || y < 0 || x >= self.size.w || y >= self.size.h || coverage <= 0.0 {
+            return;
+        }
+
+        let [r, g, b, a] = color;
+        let alpha = a * coverage;
+        let index = ((y * self.size.w + x) * 4) as usize;
+        let dst = &mut self.pixels[index..index + 4];
+
+        for (dst, src) in dst.iter_mut().zip([r * alpha, g * alpha, b * alpha, alpha]) {
+            let blended = src * 255.0 + *dst as f32 * (1.0 - alpha);
+            *dst = blended.round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+impl State {
+    /// Set the font used by Pinnacle's own UI, redrawing anything that shows text.
+    ///
+    /// Families that aren't installed fall back to the system's sans-serif font.
+    pub fn set_ui_font(&mut self, family: String, size: i32) {
+        let font = if self.ui_font.family == family {
+            self.ui_font.with_size(size)
+        } else {
+            UiFont::new(&family, size)
+        };
+
+        if self.ui_font == font {
+            return;
+        }
+
+        self.ui_font = font;
+        self.update_grab_hint_font();
+        self.update_prompt_box_font();
+    }
+}
+
+/// A line of text in the UI font on a background.
+pub struct TextBox {
+    /// The pieces of text on the line, left to right, with their colors
+    spans: Vec<(String, [f32; 4])>,
+    /// The space between the text and the edge of the box, as a fraction of the font size
+    padding: f64,
+    /// The narrowest the box gets, in characters
+    min_width_chars: usize,
+    background: [f32; 4],
+    /// The font the text was laid out in, or `None` if it hasn't been yet
+    font: Option<UiFont>,
+    size: Size<i32, Logical>,
+    /// The box rasterized at each scale it has been drawn at since the text last changed,
+    /// as `TextureBuffer`s of the renderer that drew it
+    textures: RefCell<Vec<(f64, Box<dyn Any>)>>,
+}
+
+impl fmt::Debug for TextBox {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TextBox")
+            .field("spans", &self.spans)
+            .field("font", &self.font)
+            .field("size", &self.size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TextBox {
+    /// Create an empty box with `padding` times the font size around the text that's at least
+    /// `min_width_chars` characters wide.
+    pub fn new(padding: f64, min_width_chars: usize, background: [f32; 4]) -> Self {
+        Self {
+            spans: Vec::new(),
+            padding,
+            min_width_chars,
+            background,
+            font: None,
+            size: Size::default(),
+            textures: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Show `spans` of text in `font`, left to right.
+    ///
+    /// Nothing is laid out again if the text didn't change, so call [`TextBox::relayout`]
+    /// when the font does.
+    pub fn set_spans(&mut self, spans: Vec<(String, [f32; 4])>, font: &UiFont) {
+        if self.spans == spans && self.font.is_some() {
+            return;
+        }
+
+        self.spans = spans;
+        self.relayout(font);
+    }
+
+    /// Lay out the text again in `font`.
+    pub fn relayout(&mut self, font: &UiFont) {
+        let line = self
+            .spans
+            .iter()
+            .map(|(text, _)| text.as_str())
+            .collect::<String>();
+        let text_size = font.measure(&line, font.size);
+        let min_width = font.measure(&"_".repeat(self.min_width_chars), font.size).w;
+        let padding = self.logical_padding(font) as i32;
+
+        self.size = (
+            text_size.w.max(min_width) + padding * 2,
+            text_size.h + padding * 2,
+        )
+            .into();
+        self.font = Some(font.clone());
+        self.textures.borrow_mut().clear();
+    }
+
+    /// Get the size of the box.
+    pub fn size(&self) -> Size<i32, Logical> {
+        self.size
+    }
+
+    /// Get the space between the text and the edge of the box in `font`, in logical pixels.
+    fn logical_padding(&self, font: &UiFont) -> f64 {
+        (self.padding * font.size as f64).round()
+    }
+
+    /// Rasterize the box at `scale`, returning its premultiplied RGBA pixels and their size.
+    fn rasterize(&self, scale: f64) -> Option<(Vec<u8>, Size<i32, Physical>)> {
+        let font = self.font.as_ref()?;
+        let size = self.size.to_physical_precise_round(scale);
+        if size.w <= 0 || size.h <= 0 {
+            return None;
+        }
+
+        let px = (font.size as f64 * scale) as f32;
+        let padding = (self.logical_padding(font) * scale) as f32;
+
+        let colors = self
+            .spans
+            .iter()
+            .flat_map(|(text, color)| text.chars().map(|_| *color));
+        let line = font
+            .face
+            .layout(self.spans.iter().flat_map(|(text, _)| text.chars()), px);
+
+        let mut canvas = Canvas::new(size, self.background);
+        let baseline = (padding + line.ascent).round() as i32;
+        for ((c, pen_x), color) in line.glyphs.into_iter().zip(colors) {
+            font.face
+                .draw(&mut canvas, c, px, padding + pen_x, baseline, color);
+        }
+
+        Some((canvas.pixels, size))
+    }
+
+    /// Generate the box's render element with its top left corner at `loc`,
+    /// relative to the output it's drawn on.
+    pub fn render_element<R>(
+        &self,
+        renderer: &mut R,
+        loc: Point<i32, Logical>,
+        scale: Scale<f64>,
+    ) -> Option<TextureRenderElement<<R as Renderer>::TextureId>>
+    where
+        R: Renderer + ImportMem,
+        <R as Renderer>::TextureId: Clone + 'static,
+    {
+        let mut textures = self.textures.borrow_mut();
+        let cached = textures
+            .iter()
+            .find(|(texture_scale, _)| *texture_scale == scale.x)
+            .and_then(|(_, texture)| {
+                texture.downcast_ref::<TextureBuffer<<R as Renderer>::TextureId>>()
+            })
+            .cloned();
+
+        let texture = match cached {
+            Some(texture) => texture,
+            None => {
+                let (pixels, size) = self.rasterize(scale.x)?;
+                let texture = match TextureBuffer::from_memory(
+                    renderer,
+                    &pixels,
+                    Fourcc::Abgr8888,
+                    (size.w, size.h),
+                    false,
+                    1,
+                    Transform::Normal,
+                    None,
+                ) {
+                    Ok(texture) => texture,
+                    Err(err) => {
+                        tracing::warn!("Failed to import text texture: {err}");
+                        return None;
+                    }
+                };
+
+                textures.retain(|(texture_scale, _)| *texture_scale != scale.x);
+                textures.push((scale.x, Box::new(texture.clone())));
+                texture
+            }
+        };
+
+        Some(TextureRenderElement::from_texture_buffer(
+            loc.to_physical_precise_round(scale).to_f64(),
+            &texture,
+            None,
+            None,
+            Some(self.size),
+            Kind::Unspecified,
+        ))
+    }
+}
+
+/// Get the rows of `c`'s glyph, top to bottom, with the leftmost pixel in the highest bit.
+///
+/// Whitespace is blank, and other characters without a glyph are drawn as a filled box.
+fn glyph(c: char) -> [i32; GLYPH_HEIGHT as usize] {
+    match c {
+        _ if c.is_whitespace() => [0; GLYPH_HEIGHT as usize],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        '×' | 'x' => [0b000, 0b101, 0b010, 0b101, 0b000],
+        'A' | 'a' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' | 'b' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' | 'c' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' | 'd' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' | 'e' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' | 'f' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' | 'g' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' | 'h' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' | 'i' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' | 'j' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' | 'k' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' | 'l' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' | 'm' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' | 'n' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' | 'o' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' | 'p' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' | 'q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' | 'r' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' | 's' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' | 't' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' | 'u' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' | 'v' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' | 'w' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' | 'y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' | 'z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '?' => [0b111, 0b001, 0b010, 0b000, 0b010],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '"' => [0b101, 0b101, 0b000, 0b000, 0b000],
+        '#' => [0b101, 0b111, 0b101, 0b111, 0b101],
+        '$' => [0b011, 0b110, 0b010, 0b011, 0b110],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '&' => [0b010, 0b101, 0b010, 0b101, 0b011],
+        '\'' => [0b010, 0b010, 0b000, 0b000, 0b000],
+        '(' => [0b001, 0b010, 0b010, 0b010, 0b001],
+        ')' => [0b100, 0b010, 0b010, 0b010, 0b100],
+        '*' => [0b101, 0b010, 0b111, 0b010, 0b101],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        ';' => [0b000, 0b010, 0b000, 0b010, 0b100],
+        '<' => [0b001, 0b010, 0b100, 0b010, 0b001],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '>' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        '@' => [0b111, 0b101, 0b111, 0b100, 0b011],
+        '[' => [0b011, 0b010, 0b010, 0b010, 0b011],
+        '\\' => [0b100, 0b100, 0b010, 0b001, 0b001],
+        ']' => [0b110, 0b010, 0b010, 0b010, 0b110],
+        '^' => [0b010, 0b101, 0b000, 0b000, 0b000],
+        '`' => [0b100, 0b010, 0b000, 0b000, 0b000],
+        '{' => [0b011, 0b010, 0b110, 0b010, 0b011],
+        '|' => [0b010, 0b010, 0b010, 0b010, 0b010],
+        '}' => [0b110, 0b010, 0b011, 0b010, 0b110],
+        '~' => [0b000, 0b011, 0b110, 0b000, 0b000],
+        _ => [0b111; GLYPH_HEIGHT as usize],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+    const CLEAR: [f32; 4] = [0.0, 0.0, 0.0, 0.0];
+
+    fn pixel(pixels: &[u8], size: Size<i32, Physical>, x: i32, y: i32) -> [u8; 4] {
+        let index = ((y * size.w + x) * 4) as usize;
+        pixels[index..index + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn builtin_font_draws_every_printable_character() {
+        for c in (' '..='~').filter(|c| !c.is_whitespace()) {
+            assert_ne!(glyph(c), [0; GLYPH_HEIGHT as usize], "{c:?} is blank");
+        }
+
+        assert_eq!(glyph(' '), [0; GLYPH_HEIGHT as usize]);
+        assert_eq!(glyph('é'), [0b111; GLYPH_HEIGHT as usize]);
+        assert_eq!(glyph('ж'), [0b111; GLYPH_HEIGHT as usize]);
+    }
+
+    #[test]
+    fn builtin_text_is_measured_in_font_pixels() {
+        let font = UiFont::new(BUILTIN_FONT, 10);
+
+        assert_eq!(font.measure("AB", 10), (14, 10).into());
+        assert_eq!(font.measure("AB", 20), (28, 20).into());
+        assert_eq!(font.measure("", 10), (0, 10).into());
+    }
+
+    #[test]
+    fn text_box_is_padded_and_kept_to_its_min_width() {
+        let font = UiFont::new(BUILTIN_FONT, 10);
+        let mut text_box = TextBox::new(0.5, 4, CLEAR);
+
+        text_box.set_spans(vec![("1".to_string(), WHITE)], &font);
+        assert_eq!(text_box.size(), (40, 20).into());
+
+        text_box.set_spans(vec![("123456".to_string(), WHITE)], &font);
+        assert_eq!(text_box.size(), (56, 20).into());
+    }
+
+    #[test]
+    fn text_is_rasterized_at_the_output_scale() {
+        let font = UiFont::new(BUILTIN_FONT, 10);
+        let mut text_box = TextBox::new(0.0, 0, CLEAR);
+        text_box.set_spans(vec![("1".to_string(), WHITE)], &font);
+
+        let (pixels, size) = text_box.rasterize(1.0).unwrap();
+        assert_eq!(size, (6, 10).into());
+        assert_eq!(pixel(&pixels, size, 2, 0), [255; 4]);
+        assert_eq!(pixel(&pixels, size, 0, 0), [0; 4]);
+
+        let (pixels, size) = text_box.rasterize(2.0).unwrap();
+        assert_eq!(size, (12, 20).into());
+        assert_eq!(pixel(&pixels, size, 4, 0), [255; 4]);
+        assert_eq!(pixel(&pixels, size, 3, 0), [0; 4]);
+    }
+
+    #[test]
+    fn text_is_blended_over_a_premultiplied_background() {
+        let font = UiFont::new(BUILTIN_FONT, 10);
+        let mut text_box = TextBox::new(0.0, 0, [1.0, 0.5, 0.0, 0.5]);
+        text_box.set_spans(vec![("1".to_string(), [0.0, 0.0, 1.0, 0.5])], &font);
+
+        let (pixels, size) = text_box.rasterize(1.0).unwrap();
+        assert_eq!(pixel(&pixels, size, 0, 0), [128, 64, 0, 128]);
+        assert_eq!(pixel(&pixels, size, 2, 0), [64, 32, 128, 192]);
+    }
+
+    #[test]
+    fn spans_keep_their_colors() {
+        let font = UiFont::new(BUILTIN_FONT, 5);
+        let mut text_box = TextBox::new(0.0, 0, CLEAR);
+        let red = [1.0, 0.0, 0.0, 1.0];
+        text_box.set_spans(
+            vec![("1".to_string(), WHITE), ("1".to_string(), red)],
+            &font,
+        );
+
+        let (pixels, size) = text_box.rasterize(1.0).unwrap();
+        assert_eq!(pixel(&pixels, size, 1, 0), [255; 4]);
+        assert_eq!(pixel(&pixels, size, 5, 0), [255, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,429 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! A ring that flashes around a window so it's easy to find, like after a keybind moves
+//! focus to it.
+//!
+//! The ring fades out over the flash's duration. Each output with a ring on it gets a timer
+//! that steps the fade at the output's refresh rate and only renders that output, so a fast
+//! output gets a smooth fade without slower ones being redrawn more than they can show.
+//! How far along the fade is comes from how long the flash has been running, so it takes the
+//! same time on every output no matter how often it's stepped.
+//!
+//! Rings follow their window every step, and a flash ends early if its window closes or
+//! stops being shown.
+//!
+//! Rings are drawn above every window so they show even when their window is covered.
+
+use std::time::{Duration, Instant};
+
+use smithay::{
+    backend::renderer::{
+        element::{solid::SolidColorRenderElement, Id, Kind},
+        utils::CommitCounter,
+    },
+    desktop::Space,
+    output::Output,
+    reexports::calloop::{
+        timer::{TimeoutAction, Timer},
+        RegistrationToken,
+    },
+    utils::{IsAlive, Logical, Rectangle, Scale},
+};
+
+use crate::{state::State, window::WindowElement};
+
+/// The width of the ring.
+const RING_WIDTH: i32 = 4;
+
+/// How often the fade is stepped on outputs that don't have a refresh rate.
+const FALLBACK_FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// How long windows flash when a keybind focuses them.
+const FOCUS_FLASH_DURATION: Duration = Duration::from_millis(400);
+const FOCUS_FLASH_COLOR: [f32; 4] = [0.4, 0.6, 1.0, 1.0];
+
+/// How long after a keybind triggers a focus change still counts as coming from it.
+///
+/// Keybind callbacks run in the config, so the focus change comes in a bit later.
+const KEYBIND_FOCUS_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Default)]
+pub struct WindowFlashState {
+    /// Whether to flash windows when a keybind moves keyboard focus to them.
+    pub flash_on_focus: bool,
+    /// When a keybind last triggered, if no pointer button has been pressed since.
+    last_keybind: Option<Instant>,
+    /// The window that last got keyboard focus, so focusing it again doesn't flash it.
+    last_focused: Option<WindowElement>,
+    flashes: Vec<WindowFlash>,
+    /// The timers stepping the fade, one for each output with a ring on it.
+    drivers: Vec<FlashDriver>,
+}
+
+#[derive(Debug)]
+struct FlashDriver {
+    output: Output,
+    timer_token: RegistrationToken,
+}
+
+#[derive(Debug)]
+struct WindowFlash {
+    window: WindowElement,
+    /// The color the ring starts at.
+    base_color: [f32; 4],
+    /// The color the ring is at now.
+    color: [f32; 4],
+    start: Instant,
+    duration: Duration,
+    ids: [Id; 4],
+    commit: CommitCounter,
+    /// The window's geometry at the last step, so the ring gets damaged once it moves or ends.
+    geo: Option<Rectangle<i32, Logical>>,
+}
+
+impl WindowFlashState {
+    /// Generate the rings' render elements for `output`.
+    pub fn render_elements(
+        &self,
+        output: &Output,
+        space: &Space<WindowElement>,
+    ) -> Vec<SolidColorRenderElement> {
+        if self.flashes.is_empty() {
+            return Vec::new();
+        }
+
+        let Some(output_geo) = space.output_geometry(output) else {
+            return Vec::new();
+        };
+        let scale = Scale::from(output.current_scale().fractional_scale());
+
+        self.flashes
+            .iter()
+            .flat_map(|flash| {
+                let rects = space
+                    .element_geometry(&flash.window)
+                    .map(ring)
+                    .unwrap_or_default();
+
+                rects
+                    .into_iter()
+                    .zip(flash.ids.iter())
+                    .filter_map(|(rect, id)| {
+                        let rect = rect.intersection(output_geo)?;
+                        let rect =
+                            Rectangle::from_loc_and_size(rect.loc - output_geo.loc, rect.size)
+                                .to_physical_precise_round(scale);
+
+                        Some(SolidColorRenderElement::new(
+                            id.clone(),
+                            rect,
+                            flash.commit,
+                            flash.color,
+                            Kind::Unspecified,
+                        ))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+impl State {
+    /// Flash a ring of `color` around `window` that fades out over `duration`.
+    ///
+    /// Flashing a window that's already flashing starts its flash over.
+    pub fn flash_window(&mut self, window: &WindowElement, duration: Duration, color: [f32; 4]) {
+        if duration.is_zero() {
+            return;
+        }
+
+        let old_geo = self
+            .window_flash
+            .flashes
+            .iter()
+            .find(|flash| &flash.window == window)
+            .and_then(|flash| flash.geo);
+        self.window_flash
+            .flashes
+            .retain(|flash| &flash.window != window);
+
+        let geo = self.space.element_geometry(window);
+        self.window_flash.flashes.push(WindowFlash {
+            window: window.clone(),
+            base_color: color,
+            color,
+            start: Instant::now(),
+            duration,
+            ids: std::array::from_fn(|_| Id::new()),
+            commit: CommitCounter::default(),
+            geo,
+        });
+
+        self.schedule_window_flash_render([old_geo, geo].into_iter().flatten());
+        self.update_window_flash_drivers(None);
+    }
+
+    /// Flash `window` if flashing on focus is on and a keybind just moved focus to it.
+    pub fn flash_window_on_focus(&mut self, window: &WindowElement) {
+        if self.window_flash.last_focused.as_ref() == Some(window) {
+            return;
+        }
+        self.window_flash.last_focused = Some(window.clone());
+
+        let from_keybind = self
+            .window_flash
+            .last_keybind
+            .is_some_and(|time| time.elapsed() < KEYBIND_FOCUS_TIMEOUT);
+
+        if self.window_flash.flash_on_focus && from_keybind {
+            self.flash_window(window, FOCUS_FLASH_DURATION, FOCUS_FLASH_COLOR);
+        }
+    }
+
+    /// Remember that a keybind triggered, so the focus change it causes flashes the window.
+    pub fn window_flash_keybind_triggered(&mut self) {
+        self.window_flash.last_keybind = Some(Instant::now());
+    }
+
+    /// Forget the last keybind, so focus changes from the pointer don't flash windows.
+    pub fn window_flash_pointer_pressed(&mut self) {
+        self.window_flash.last_keybind = None;
+    }
+
+    /// Turn flashing windows when a keybind focuses them on or off.
+    pub fn set_flash_on_focus(&mut self, enabled: bool) {
+        self.window_flash.flash_on_focus = enabled;
+    }
+
+    /// Step every flash's fade for a frame on `output`, ending flashes that are done or whose
+    /// window closed or is no longer shown.
+    ///
+    /// Only `output` is rendered for the fade itself. Outputs a ring moved off of or ended on
+    /// are rendered too so they don't keep showing it.
+    fn step_window_flashes(&mut self, output: &Output) -> TimeoutAction {
+        let now = Instant::now();
+        let space = &self.space;
+        let mut damaged = Vec::new();
+
+        self.window_flash.flashes.retain_mut(|flash| {
+            let progress = fade_progress(flash.start, flash.duration, now);
+            let old_geo = flash.geo;
+            flash.geo = space.element_geometry(&flash.window);
+            let running = progress < 1.0 && flash.window.alive() && flash.geo.is_some();
+
+            if !running {
+                flash.geo = None;
+            }
+            if flash.geo != old_geo {
+                damaged.extend(old_geo);
+                damaged.extend(flash.geo);
+            }
+
+            if running {
+                flash.color[3] = flash.base_color[3] * (1.0 - progress);
+                flash.commit.increment();
+            }
+
+            running
+        });
+
+        self.schedule_window_flash_render(damaged);
+        self.schedule_render(output);
+
+        if self.update_window_flash_drivers(Some(output)) {
+            TimeoutAction::ToDuration(frame_interval(output))
+        } else {
+            TimeoutAction::Drop
+        }
+    }
+
+    /// Start stepping the fade on outputs that now have a ring on them, and stop on ones
+    /// that don't.
+    ///
+    /// Flashes whose ring isn't on any output are ended, since nothing would step them.
+    ///
+    /// `stepping` is the output whose timer is running right now, if any. Its timer is left
+    /// for the caller to drop, and this returns whether it should keep going.
+    fn update_window_flash_drivers(&mut self, stepping: Option<&Output>) -> bool {
+        let output_geos = self
+            .space
+            .outputs()
+            .filter_map(|output| Some((output.clone(), self.space.output_geometry(output)?)))
+            .collect::<Vec<_>>();
+
+        self.window_flash.flashes.retain(|flash| {
+            flash.geo.is_some_and(|geo| {
+                output_geos
+                    .iter()
+                    .any(|(_, output_geo)| geo.overlaps(*output_geo))
+            })
+        });
+
+        let outputs = output_geos
+            .into_iter()
+            .filter(|(_, output_geo)| {
+                self.window_flash
+                    .flashes
+                    .iter()
+                    .any(|flash| flash.geo.is_some_and(|geo| geo.overlaps(*output_geo)))
+            })
+            .map(|(output, _)| output)
+            .collect::<Vec<_>>();
+
+        let mut keep_stepping = false;
+        let loop_handle = self.loop_handle.clone();
+        self.window_flash.drivers.retain(|driver| {
+            let keep = outputs.contains(&driver.output);
+            if stepping == Some(&driver.output) {
+                keep_stepping = keep;
+            } else if !keep {
+                loop_handle.remove(driver.timer_token);
+            }
+            keep
+        });
+
+        for output in outputs {
+            if self
+                .window_flash
+                .drivers
+                .iter()
+                .any(|driver| driver.output == output)
+            {
+                continue;
+            }
+
+            let driven_output = output.clone();
+            let timer_token = self.loop_handle.insert_source(
+                Timer::from_duration(frame_interval(&output)),
+                move |_, _, data| data.state.step_window_flashes(&driven_output),
+            );
+
+            match timer_token {
+                Ok(timer_token) => self.window_flash.drivers.push(FlashDriver {
+                    output,
+                    timer_token,
+                }),
+                Err(err) => tracing::error!("Failed to insert window flash timer: {err}"),
+            }
+        }
+
+        keep_stepping
+    }
+
+    /// Render the outputs that overlap any of `geos`.
+    fn schedule_window_flash_render(
+        &mut self,
+        geos: impl IntoIterator<Item = Rectangle<i32, Logical>>,
+    ) {
+        let geos = geos.into_iter().collect::<Vec<_>>();
+        let outputs = self
+            .space
+            .outputs()
+            .filter(|output| {
+                self.space
+                    .output_geometry(output)
+                    .is_some_and(|output_geo| geos.iter().any(|geo| geo.overlaps(output_geo)))
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for output in outputs {
+            self.schedule_render(&output);
+        }
+    }
+}
+
+/// How long a frame on `output` lasts at its refresh rate.
+fn frame_interval(output: &Output) -> Duration {
+    refresh_interval(output.current_mode().map(|mode| mode.refresh))
+}
+
+/// How long a frame lasts at `refresh`, in millihertz.
+fn refresh_interval(refresh: Option<i32>) -> Duration {
+    refresh
+        .filter(|refresh| *refresh > 0)
+        .map(|refresh| Duration::from_secs_f64(1000.0 / refresh as f64))
+        .unwrap_or(FALLBACK_FRAME_INTERVAL)
+}
+
+/// How far along a flash that started at `start` and lasts `duration` is at `now`,
+/// where 1.0 or more means it's done.
+fn fade_progress(start: Instant, duration: Duration, now: Instant) -> f32 {
+    now.duration_since(start).as_secs_f32() / duration.as_secs_f32()
+}
+
+/// The ring's four sides, just inside `geo` so they show even when the window fills
+/// its output.
+fn ring(geo: Rectangle<i32, Logical>) -> Vec<Rectangle<i32, Logical>> {
+    let width = RING_WIDTH.min(geo.size.w / 2).min(geo.size.h / 2);
+    if width <= 0 {
+        return Vec::new();
+    }
+
+    let (x, y, w, h) = (geo.loc.x, geo.loc.y, geo.size.w, geo.size.h);
+    vec![
+        Rectangle::from_loc_and_size((x, y), (w, width)),
+        Rectangle::from_loc_and_size((x, y + h - width), (w, width)),
+        Rectangle::from_loc_and_size((x, y + width), (width, h - 2 * width)),
+        Rectangle::from_loc_and_size((x + w - width, y + width), (width, h - 2 * width)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Step a flash that lasts `duration` on an output refreshing at `refresh` millihertz,
+    /// returning how many frames it got and when it ended.
+    fn run_flash(refresh: i32, duration: Duration) -> (u32, Duration) {
+        let interval = refresh_interval(Some(refresh));
+        let start = Instant::now();
+        let mut now = start;
+        let mut frames = 0;
+
+        loop {
+            now += interval;
+            frames += 1;
+            if fade_progress(start, duration, now) >= 1.0 {
+                return (frames, now - start);
+            }
+        }
+    }
+
+    #[test]
+    fn faster_outputs_step_more_often_for_the_same_time() {
+        let duration = FOCUS_FLASH_DURATION;
+        let (frames_60, ended_60) = run_flash(60_000, duration);
+        let (frames_144, ended_144) = run_flash(144_000, duration);
+
+        assert_eq!(frames_60, 24);
+        assert_eq!(frames_144, 58);
+
+        // Each ends within a frame of the flash's duration
+        assert!(ended_60 >= duration && ended_60 - duration < refresh_interval(Some(60_000)));
+        assert!(ended_144 >= duration && ended_144 - duration < refresh_interval(Some(144_000)));
+    }
+
+    #[test]
+    fn progress_comes_from_elapsed_time() {
+        let start = Instant::now();
+        let duration = Duration::from_millis(400);
+        assert_eq!(fade_progress(start, duration, start), 0.0);
+        assert_eq!(
+            fade_progress(start, duration, start + Duration::from_millis(100)),
+            0.25
+        );
+        // A late step doesn't slow the fade down
+        assert_eq!(
+            fade_progress(start, duration, start + Duration::from_millis(300)),
+            0.75
+        );
+    }
+
+    #[test]
+    fn outputs_without_a_refresh_rate_use_the_fallback() {
+        assert_eq!(refresh_interval(None), FALLBACK_FRAME_INTERVAL);
+        assert_eq!(refresh_interval(Some(0)), FALLBACK_FRAME_INTERVAL);
+        assert_eq!(refresh_interval(Some(-1)), FALLBACK_FRAME_INTERVAL);
+    }
+}
@@ -0,0 +1,305 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Best-effort session restore across restarts.
+//!
+//! When Pinnacle shuts down, it saves each output's tags and every window's class, title,
+//! tags, floating geometry, and place in the stack to `$XDG_STATE_HOME/pinnacle/session.toml`.
+//! The next time it starts, the config can get that with `Request::GetPreviousSession` and
+//! respawn its apps.
+//!
+//! Pinnacle can't restart apps itself, but `Msg::ApplyPreviousSession` makes windows that
+//! reappear go back where they were. For a short while, each new window is matched by class
+//! to a window from the previous session, preferring one with the same title, and gets its
+//! tags and floating geometry. Each saved window is only used once. Stacking order is saved
+//! but not restored, since windows come back in whatever order their apps start in.
+//!
+//! The file is versioned, and missing fields are filled in with defaults, so sessions saved by
+//! older versions still load.
+
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use smithay::utils::Rectangle;
+
+use crate::{
+    layout::Layout,
+    output::OutputName,
+    state::{State, WithState},
+    window::{window_state::FloatingOrTiled, WindowElement},
+    XDG_BASE_DIRS,
+};
+
+const SESSION_FILE: &str = "session.toml";
+
+/// The version of the session format this Pinnacle writes.
+///
+/// Sessions with a newer version are ignored.
+pub const SESSION_VERSION: u32 = 1;
+
+/// How long windows are put back where they were after the config applies the previous session.
+const RESTORE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Everything saved about a session.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub version: u32,
+    pub outputs: Vec<SessionOutput>,
+    /// Windows from the bottom of the stack to the top.
+    ///
+    /// Windows that weren't shown, like ones on inactive tags, come first.
+    pub windows: Vec<SessionWindow>,
+}
+
+/// An output and its tags.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionOutput {
+    /// The output's connector name.
+    pub name: String,
+    pub tags: Vec<SessionTag>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionTag {
+    pub name: String,
+    pub active: bool,
+    pub layout: Option<Layout>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionWindow {
+    pub class: Option<String>,
+    pub title: Option<String>,
+    /// The output the window's tags are on.
+    pub output_name: Option<String>,
+    /// The names of the window's tags.
+    pub tags: Vec<String>,
+    pub floating: bool,
+    /// Where the window floats, or would float if it's tiled.
+    pub geometry: Option<SessionGeometry>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+#[derive(Debug, Default)]
+pub struct SessionState {
+    /// The session saved when Pinnacle last shut down, if there is one.
+    pub previous: Option<Session>,
+    /// Windows from the previous session that haven't come back yet.
+    restoring: Vec<SessionWindow>,
+    /// When windows stop being put back where they were.
+    restore_until: Option<Instant>,
+}
+
+impl SessionState {
+    /// Load the session saved when Pinnacle last shut down.
+    pub fn load() -> Self {
+        let previous = XDG_BASE_DIRS
+            .find_state_file(SESSION_FILE)
+            .and_then(|path| {
+                std::fs::read_to_string(path)
+                    .context("Failed to read previous session")
+                    .and_then(|session| {
+                        toml::from_str::<Session>(&session)
+                            .context("Failed to deserialize previous session")
+                    })
+                    .map_err(|err| tracing::warn!("{err:?}"))
+                    .ok()
+            })
+            .filter(|session| {
+                let supported = session.version <= SESSION_VERSION;
+                if !supported {
+                    tracing::warn!(
+                        "Ignoring previous session from a newer version of Pinnacle (version {})",
+                        session.version
+                    );
+                }
+                supported
+            });
+
+        Self {
+            previous,
+            ..Default::default()
+        }
+    }
+}
+
+impl State {
+    /// Get everything that's saved about the current session.
+    pub fn session(&self) -> Session {
+        let outputs = self
+            .space
+            .outputs()
+            .map(|output| SessionOutput {
+                name: output.name(),
+                tags: output.with_state(|state| {
+                    state
+                        .tags
+                        .iter()
+                        .map(|tag| SessionTag {
+                            name: tag.name(),
+                            active: tag.active(),
+                            layout: Some(tag.layout()),
+                        })
+                        .collect()
+                }),
+            })
+            .collect();
+
+        let stacked = self
+            .space
+            .elements()
+            .filter(|win| !win.is_x11_override_redirect());
+        let windows = self
+            .windows
+            .iter()
+            .filter(|win| !win.is_x11_override_redirect())
+            .filter(|win| self.space.element_location(win).is_none())
+            .chain(stacked)
+            .map(|win| self.session_window(win))
+            .collect();
+
+        Session {
+            version: SESSION_VERSION,
+            outputs,
+            windows,
+        }
+    }
+
+    fn session_window(&self, window: &WindowElement) -> SessionWindow {
+        let (tags, floating_or_tiled) =
+            window.with_state(|state| (state.tags.clone(), state.floating_or_tiled));
+
+        let (floating, geometry) = match floating_or_tiled {
+            FloatingOrTiled::Floating(geo) => (true, Some(geo)),
+            FloatingOrTiled::Tiled(geo) => (false, geo),
+        };
+
+        SessionWindow {
+            class: window.class(),
+            title: window.title(),
+            output_name: tags
+                .first()
+                .and_then(|tag| tag.output(self))
+                .map(|output| output.name()),
+            tags: tags.iter().map(|tag| tag.name()).collect(),
+            floating,
+            geometry: geometry.map(|geo| SessionGeometry {
+                x: geo.loc.x,
+                y: geo.loc.y,
+                width: geo.size.w,
+                height: geo.size.h,
+            }),
+        }
+    }
+
+    /// Save the current session so it can be restored after Pinnacle restarts.
+    pub fn save_session(&self) {
+        let result = XDG_BASE_DIRS
+            .place_state_file(SESSION_FILE)
+            .context("Failed to create state dir")
+            .and_then(|path| {
+                let session =
+                    toml::to_string(&self.session()).context("Failed to serialize session")?;
+                std::fs::write(path, session).context("Failed to write session")
+            });
+
+        match result {
+            Ok(()) => tracing::info!("Saved session"),
+            Err(err) => tracing::error!("{err:?}"),
+        }
+    }
+
+    /// Put windows from the previous session back where they were as they reappear.
+    ///
+    /// This replaces any windows still waiting from an earlier call.
+    pub fn apply_previous_session(&mut self) {
+        let Some(previous) = self.session_state.previous.as_ref() else {
+            tracing::debug!("No previous session to apply");
+            return;
+        };
+
+        self.session_state.restoring = previous
+            .windows
+            .iter()
+            .filter(|win| win.class.is_some())
+            .cloned()
+            .collect();
+        self.session_state.restore_until = Some(Instant::now() + RESTORE_TIMEOUT);
+    }
+
+    /// Put a new `window` where a window of its class was in the previous session,
+    /// if the config applied it recently.
+    ///
+    /// Returns whether the window was put back.
+    pub fn apply_session_restore(&mut self, window: &WindowElement) -> bool {
+        let session_state = &mut self.session_state;
+        if session_state
+            .restore_until
+            .is_some_and(|until| Instant::now() > until)
+        {
+            session_state.restoring.clear();
+            session_state.restore_until = None;
+        }
+
+        let Some(class) = window.class() else {
+            return false;
+        };
+        let title = window.title();
+
+        let matches_class = |win: &SessionWindow| win.class.as_ref() == Some(&class);
+        let Some(index) = session_state
+            .restoring
+            .iter()
+            .position(|win| matches_class(win) && win.title == title)
+            .or_else(|| session_state.restoring.iter().position(matches_class))
+        else {
+            return false;
+        };
+        let saved = session_state.restoring.remove(index);
+
+        tracing::debug!("Putting {class} back where it was in the previous session");
+
+        if let Some(output) = saved
+            .output_name
+            .and_then(|name| OutputName(name).output(self))
+        {
+            let tags = output.with_state(|state| {
+                state
+                    .tags
+                    .iter()
+                    .filter(|tag| saved.tags.contains(&tag.name()))
+                    .cloned()
+                    .collect::<Vec<_>>()
+            });
+
+            if !tags.is_empty() {
+                window.with_state(|state| state.tags = tags);
+            }
+        }
+
+        if let Some(geo) = saved.geometry {
+            let geo = Rectangle::from_loc_and_size((geo.x, geo.y), (geo.width, geo.height));
+            window.with_state(|state| {
+                state.floating_or_tiled = match state.floating_or_tiled {
+                    FloatingOrTiled::Floating(_) => FloatingOrTiled::Floating(geo),
+                    FloatingOrTiled::Tiled(_) => FloatingOrTiled::Tiled(Some(geo)),
+                };
+            });
+        }
+
+        window.set_floating(saved.floating);
+        true
+    }
+}
@@ -0,0 +1,151 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Signaling when Pinnacle has finished starting up.
+//!
+//! Pinnacle is ready once all of the following are true:
+//! - The Wayland socket is bound. This happens in [`State::init`], so it is always true here.
+//! - The backend has set up the outputs that were connected at startup, and at least one
+//!   output is mapped.
+//! - XWayland has started, or failed to.
+//!
+//! The udev backend sets up the outputs connected at startup before the event loop runs,
+//! but if none are connected then Pinnacle waits until one is plugged in.
+//!
+//! When ready, Pinnacle tells any supervisor that is listening: systemd through
+//! `$NOTIFY_SOCKET` with the `systemd` feature, and others through the fd passed with
+//! `--ready-fd`. It then runs any callbacks the config registered to run on startup completion.
+
+use std::{io::Write, os::fd::OwnedFd};
+
+use crate::{
+    api::msg::{CallbackId, OutgoingMsg},
+    state::State,
+};
+
+#[derive(Debug, Default)]
+pub struct StartupState {
+    /// Whether the backend has set up the outputs that were connected at startup.
+    pub outputs_initialized: bool,
+    /// Whether XWayland has started, or won't.
+    pub xwayland_done: bool,
+    /// Whether startup has completed.
+    pub complete: bool,
+    /// The fd to write to when ready, from `--ready-fd`.
+    pub ready_fd: Option<OwnedFd>,
+    /// The socket systemd is listening on for readiness notifications.
+    #[cfg(feature = "systemd")]
+    notify_socket: Option<std::ffi::OsString>,
+}
+
+impl StartupState {
+    pub fn new() -> Self {
+        // Unset this so that spawned processes don't think they need to notify systemd.
+        #[cfg(feature = "systemd")]
+        let notify_socket = std::env::var_os("NOTIFY_SOCKET").map(|socket| {
+            std::env::remove_var("NOTIFY_SOCKET");
+            socket
+        });
+
+        Self {
+            #[cfg(feature = "systemd")]
+            notify_socket,
+            ..Default::default()
+        }
+    }
+}
+
+impl State {
+    /// Complete startup if everything is ready. This does nothing if startup already completed.
+    ///
+    /// This should be called whenever something that startup waits on becomes ready.
+    pub fn check_startup_complete(&mut self) {
+        let startup = &self.startup;
+        if startup.complete
+            || !startup.outputs_initialized
+            || !startup.xwayland_done
+            || self.space.outputs().next().is_none()
+        {
+            return;
+        }
+
+        tracing::info!("Startup complete");
+        self.startup.complete = true;
+
+        #[cfg(feature = "systemd")]
+        if let Some(socket) = self.startup.notify_socket.take() {
+            if let Err(err) = notify_systemd(&socket) {
+                tracing::error!("Failed to notify systemd: {err}");
+            }
+        }
+
+        if let Some(fd) = self.startup.ready_fd.take() {
+            let mut file = std::fs::File::from(fd);
+            if let Err(err) = file.write_all(b"\n") {
+                tracing::error!("Failed to write to ready fd: {err}");
+            }
+        }
+
+        let callback_ids = std::mem::take(&mut self.config.startup_callback_ids);
+        for callback_id in callback_ids {
+            self.call_startup_callback(callback_id);
+        }
+    }
+
+    /// Run the config callback with `callback_id` once startup completes, or now if it already has.
+    pub fn on_startup_complete(&mut self, callback_id: CallbackId) {
+        if self.startup.complete {
+            self.call_startup_callback(callback_id);
+        } else {
+            self.config.startup_callback_ids.push(callback_id);
+        }
+    }
+
+    fn call_startup_callback(&self, callback_id: CallbackId) {
+        // Output connection callbacks are scheduled the same way, so scheduling this
+        // makes it run after the callbacks for outputs that were just connected.
+        self.schedule(
+            |dt| dt.state.api_state.stream.is_some(),
+            move |dt| {
+                let stream = dt
+                    .state
+                    .api_state
+                    .stream
+                    .as_ref()
+                    .expect("stream doesn't exist");
+
+                crate::api::send_to_client(
+                    &mut stream.lock().expect("couldn't lock stream"),
+                    &OutgoingMsg::call_callback(
+                        callback_id,
+                        None,
+                        &dt.state.api_state.callback_serial,
+                    ),
+                )
+                .expect("Send to client failed");
+            },
+        );
+    }
+}
+
+/// Send `READY=1` to systemd's notification socket at `socket`.
+///
+/// Socket paths starting with `@` are in the abstract namespace.
+#[cfg(feature = "systemd")]
+fn notify_systemd(socket: &std::ffi::OsStr) -> std::io::Result<()> {
+    use std::os::{
+        linux::net::SocketAddrExt,
+        unix::{
+            ffi::OsStrExt,
+            net::{SocketAddr, UnixDatagram},
+        },
+    };
+
+    let addr = match socket.as_bytes().strip_prefix(b"@") {
+        Some(name) => SocketAddr::from_abstract_name(name)?,
+        None => SocketAddr::from_pathname(socket)?,
+    };
+
+    UnixDatagram::unbound()?.send_to_addr(b"READY=1", &addr)?;
+
+    Ok(())
+}
@@ -1,15 +1,50 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use std::{cell::RefCell, sync::Arc, time::Duration};
+use std::{
+    cell::RefCell,
+    os::unix::net::UnixStream,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    time::{Duration, Instant},
+};
 
 use crate::{
-    api::{msg::Msg, ApiState},
+    api::{
+        limits::{ClientLimits, ClientUsages},
+        stats::ApiStats,
+        ApiState, ClientMsg,
+    },
     backend::Backend,
     config::Config,
     cursor::Cursor,
+    disconnects::{ClientProcess, DisconnectState, PendingDisconnect},
+    do_not_disturb::DoNotDisturbState,
     focus::FocusState,
-    grab::resize_grab::ResizeSurfaceState,
-    window::WindowElement,
+    grab::{
+        cancel::GrabRestore, move_grab::MoveOverride, popup_grab::PopupGrabState,
+        resize_grab::ResizeSurfaceState, snap::DEFAULT_SNAP_THRESHOLD,
+    },
+    handlers::{
+        foreign_toplevel::ForeignToplevelState,
+        gamma_control::GammaControlState,
+        input_inhibitor::InputInhibitorState,
+        screencopy::ScreencopyState,
+        security_context::{self, Capability},
+        xwayland_keyboard_grab::XwaylandKeyboardGrabState,
+    },
+    layout::applied::LayoutAppliedState,
+    render::{
+        debug_overlay::DebugOverlay, grab_hint::GrabHint, prompt_box::PromptBox, text::UiFont,
+        window_flash::WindowFlashState,
+    },
+    session::SessionState,
+    startup::StartupState,
+    window::{
+        activity::ActivityState, insert_position::InsertPosition, placement::PlacementMemory,
+        shape::X11ShapeState, throttle::BackgroundThrottle, window_state::WindowId, WindowElement,
+    },
 };
 use calloop::futures::Scheduler;
 use smithay::{
@@ -17,8 +52,10 @@ use smithay::{
     input::{keyboard::XkbConfig, pointer::CursorImageStatus, Seat, SeatState},
     reexports::{
         calloop::{
-            self, channel::Event, generic::Generic, Interest, LoopHandle, LoopSignal, Mode,
-            PostAction,
+            self,
+            channel::{Event, Sender},
+            generic::Generic,
+            Interest, LoopHandle, LoopSignal, Mode, PostAction,
         },
         wayland_server::{
             backend::{ClientData, ClientId, DisconnectReason},
@@ -31,18 +68,27 @@ use smithay::{
         compositor::{self, CompositorClientState, CompositorState},
         dmabuf::DmabufFeedback,
         fractional_scale::FractionalScaleManagerState,
+        input_method::InputMethodManagerState,
+        keyboard_shortcuts_inhibit::KeyboardShortcutsInhibitState,
         output::OutputManagerState,
+        pointer_gestures::PointerGesturesState,
+        security_context::{SecurityContext, SecurityContextState},
         selection::data_device::DataDeviceState,
         selection::primary_selection::PrimarySelectionState,
         shell::{wlr_layer::WlrLayerShellState, xdg::XdgShellState},
         shm::ShmState,
         socket::ListeningSocketSource,
+        text_input::TextInputManagerState,
         viewporter::ViewporterState,
+        virtual_keyboard::VirtualKeyboardManagerState,
     },
     xwayland::{X11Wm, XWayland, XWaylandEvent},
 };
 
-use crate::input::InputState;
+use crate::input::{
+    repeat::{DEFAULT_REPEAT_DELAY, DEFAULT_REPEAT_RATE},
+    InputState,
+};
 
 /// The main state of the application.
 pub struct State {
@@ -72,6 +118,17 @@ pub struct State {
     pub fractional_scale_manager_state: FractionalScaleManagerState,
     pub primary_selection_state: PrimarySelectionState,
     pub layer_shell_state: WlrLayerShellState,
+    pub text_input_manager_state: TextInputManagerState,
+    pub input_method_manager_state: InputMethodManagerState,
+    pub virtual_keyboard_manager_state: VirtualKeyboardManagerState,
+    pub pointer_gestures_state: PointerGesturesState,
+    pub input_inhibitor_state: InputInhibitorState,
+    pub foreign_toplevel_state: ForeignToplevelState,
+    pub screencopy_state: ScreencopyState,
+    pub gamma_control_state: GammaControlState,
+    pub keyboard_shortcuts_inhibit_state: KeyboardShortcutsInhibitState,
+    pub security_context_state: SecurityContextState,
+    pub xwayland_keyboard_grab_state: XwaylandKeyboardGrabState,
 
     /// The state of key and mousebinds along with libinput settings
     pub input_state: InputState,
@@ -79,8 +136,52 @@ pub struct State {
     pub api_state: ApiState,
     /// Keeps track of the focus stack and focused output
     pub focus_state: FocusState,
+    /// Do not disturb mode and any layer surfaces it's holding back
+    pub do_not_disturb: DoNotDisturbState,
+    /// How window activity is tracked and whether inactive windows are tidied away
+    pub activity_state: ActivityState,
+    /// Where windows of each class were last moved to
+    pub placement_memory: PlacementMemory,
+    /// The previous session and the windows from it still waiting to be put back
+    pub session_state: SessionState,
+    /// Where new windows go in the layout, unless a window rule says otherwise
+    pub insert_position: InsertPosition,
+    /// How frame callbacks are throttled for windows that can't be seen
+    pub background_throttle: BackgroundThrottle,
+    /// Clients that want to know where windows were laid out
+    pub layout_applied: LayoutAppliedState,
+    /// Whether clicking a window raises it as well as focusing it
+    pub raise_on_click: bool,
+    /// Why clients disconnected recently
+    pub disconnects: DisconnectState,
+    /// Where clients send why they disconnected, to be put in `disconnects`
+    pub disconnect_tx: Sender<PendingDisconnect>,
+    /// What startup is waiting on before Pinnacle is ready
+    pub startup: StartupState,
+    /// Annotations drawn over surfaces for debugging
+    pub debug_overlay: DebugOverlay,
+    pub grab_hint: GrabHint,
+    pub prompt_box: PromptBox,
+    /// The font used by Pinnacle's own UI, like the grab hint
+    pub ui_font: UiFont,
+    /// Rings flashing around windows to help find them
+    pub window_flash: WindowFlashState,
+    /// The modifiers that let a tiled window be dragged freely for one move
+    pub move_override: Option<MoveOverride>,
+    /// How close a moved window's edges have to be to other edges to snap to them,
+    /// or 0 to not snap
+    pub snap_threshold: u32,
+    /// What the window being moved or resized was like before, so Escape can put it back
+    pub grab_restore: Option<GrabRestore>,
+    /// How many times the event loop has woken up.
+    ///
+    /// This shouldn't go up while nothing is happening, so it's reported in the
+    /// compositor info to catch things that wake Pinnacle up for no reason.
+    pub event_loop_wakeups: u64,
 
     pub popup_manager: PopupManager,
+    /// The current popup grab, which clicks outside of dismiss
+    pub popup_grab: PopupGrabState,
 
     pub cursor_status: CursorImageStatus,
     pub pointer_location: Point<f64, Logical>,
@@ -98,6 +199,8 @@ pub struct State {
     pub xwayland: XWayland,
     pub xwm: Option<X11Wm>,
     pub xdisplay: Option<u32>,
+    /// Pinnacle's own connection to Xwayland for window shapes, if it's running
+    pub x11_shape: Option<X11ShapeState>,
 }
 
 impl State {
@@ -108,6 +211,8 @@ impl State {
         loop_signal: LoopSignal,
         loop_handle: LoopHandle<'static, CalloopData>,
     ) -> anyhow::Result<Self> {
+        crate::crash::set_backend(if backend.is_winit() { "winit" } else { "udev" });
+
         let socket = ListeningSocketSource::new_auto()?;
         let socket_name = socket.socket_name().to_os_string();
 
@@ -134,10 +239,18 @@ impl State {
             tracing::info!("Fd raise success!");
         }
 
+        let (disconnect_tx, disconnect_rx) = calloop::channel::channel::<PendingDisconnect>();
+
+        loop_handle
+            .insert_source(disconnect_rx, |event, _, data| {
+                if let Event::Msg(pending) = event {
+                    data.state.record_client_disconnect(pending);
+                }
+            })
+            .expect("failed to insert disconnect channel into loop");
+
         loop_handle.insert_source(socket, |stream, _metadata, data| {
-            data.display_handle
-                .insert_client(stream, Arc::new(ClientState::default()))
-                .expect("Could not insert client into loop handle");
+            data.state.insert_client(stream, None);
         })?;
 
         let display_handle = display.handle();
@@ -156,7 +269,7 @@ impl State {
             },
         )?;
 
-        let (tx_channel, rx_channel) = calloop::channel::channel::<Msg>();
+        let (tx_channel, rx_channel) = calloop::channel::channel::<ClientMsg>();
 
         loop_handle.insert_idle(|data| {
             if let Err(err) = data.state.start_config(crate::config::get_config_dir()) {
@@ -176,13 +289,17 @@ impl State {
         seat.add_pointer();
 
         // TODO: update from config
-        seat.add_keyboard(XkbConfig::default(), 500, 25)?;
+        seat.add_keyboard(
+            XkbConfig::default(),
+            DEFAULT_REPEAT_DELAY,
+            DEFAULT_REPEAT_RATE,
+        )?;
 
         loop_handle.insert_idle(|data| {
             data.state
                 .loop_handle
                 .insert_source(rx_channel, |msg, _, data| match msg {
-                    Event::Msg(msg) => data.state.handle_msg(msg),
+                    Event::Msg(msg) => data.state.handle_client_msg(msg),
                     Event::Closed => todo!(),
                 })
                 .expect("failed to insert rx_channel into loop");
@@ -220,9 +337,18 @@ impl State {
 
                     data.state.xwm = Some(wm);
                     data.state.xdisplay = Some(display);
+                    data.state.connect_x11_shape(display);
+
+                    data.state.startup.xwayland_done = true;
+                    data.state.check_startup_complete();
                 }
                 XWaylandEvent::Exited => {
                     data.state.xwm.take();
+                    data.state.disconnect_x11_shape();
+
+                    // Don't hold up startup if XWayland exited before becoming ready
+                    data.state.startup.xwayland_done = true;
+                    data.state.check_startup_complete();
                 }
             });
             if let Err(err) = res {
@@ -238,7 +364,7 @@ impl State {
             loop_handle,
             display_handle: display_handle.clone(),
             clock: Clock::<Monotonic>::new()?,
-            compositor_state: CompositorState::new::<Self>(&display_handle),
+            compositor_state: CompositorState::new_v6::<Self>(&display_handle),
             data_device_state: DataDeviceState::new::<Self>(&display_handle),
             seat_state,
             pointer_location: (0.0, 0.0).into(),
@@ -253,6 +379,30 @@ impl State {
             ),
             primary_selection_state: PrimarySelectionState::new::<Self>(&display_handle),
             layer_shell_state: WlrLayerShellState::new::<Self>(&display_handle),
+            text_input_manager_state: TextInputManagerState::new::<Self>(&display_handle),
+            input_method_manager_state: InputMethodManagerState::new::<Self, _>(
+                &display_handle,
+                |client| security_context::client_allows(client, Capability::InputMethod),
+            ),
+            virtual_keyboard_manager_state: VirtualKeyboardManagerState::new::<Self, _>(
+                &display_handle,
+                |client| security_context::client_allows(client, Capability::VirtualKeyboard),
+            ),
+            pointer_gestures_state: PointerGesturesState::new::<Self>(&display_handle),
+            input_inhibitor_state: InputInhibitorState::default(),
+            foreign_toplevel_state: ForeignToplevelState::new(&display_handle),
+            screencopy_state: ScreencopyState::new(&display_handle),
+            // Only advertised by the udev backend
+            gamma_control_state: GammaControlState::default(),
+            keyboard_shortcuts_inhibit_state: KeyboardShortcutsInhibitState::new::<Self>(
+                &display_handle,
+            ),
+            // Sandboxed clients can't make their own sandboxes
+            security_context_state: SecurityContextState::new::<Self, _>(
+                &display_handle,
+                |client| security_context::client_security_context(client).is_none(),
+            ),
+            xwayland_keyboard_grab_state: XwaylandKeyboardGrabState::new(&display_handle),
 
             input_state: InputState::new(),
             api_state: ApiState {
@@ -261,8 +411,35 @@ impl State {
                 tx_channel,
                 kill_channel: None,
                 future_channel: None,
+                window_waits: Vec::new(),
+                pending_state_requests: Vec::new(),
+                next_state_request_token: 0,
+                callback_serial: Arc::new(AtomicU64::new(0)),
+                client_limits: ClientLimits::default(),
+                client_usages: ClientUsages::new(),
+                stats: ApiStats::default(),
             },
             focus_state: FocusState::new(),
+            do_not_disturb: DoNotDisturbState::default(),
+            activity_state: ActivityState::default(),
+            placement_memory: PlacementMemory::load(),
+            session_state: SessionState::load(),
+            insert_position: InsertPosition::default(),
+            background_throttle: BackgroundThrottle::default(),
+            layout_applied: LayoutAppliedState::default(),
+            raise_on_click: true,
+            disconnects: DisconnectState::default(),
+            disconnect_tx,
+            startup: StartupState::new(),
+            debug_overlay: DebugOverlay::default(),
+            grab_hint: GrabHint::default(),
+            prompt_box: PromptBox::default(),
+            ui_font: UiFont::default(),
+            window_flash: WindowFlashState::default(),
+            move_override: None,
+            snap_threshold: DEFAULT_SNAP_THRESHOLD,
+            grab_restore: None,
+            event_loop_wakeups: 0,
 
             config: Config::default(),
 
@@ -273,6 +450,7 @@ impl State {
             socket_name: socket_name.to_string_lossy().to_string(),
 
             popup_manager: PopupManager::default(),
+            popup_grab: PopupGrabState::default(),
 
             async_scheduler: sched,
 
@@ -281,6 +459,7 @@ impl State {
             xwayland,
             xwm: None,
             xdisplay: None,
+            x11_shape: None,
         })
     }
 
@@ -312,6 +491,33 @@ impl State {
 
         run(data);
     }
+
+    /// Add a client that connected through `stream`.
+    ///
+    /// `security_context` is the sandbox the client is in if it connected
+    /// through a security context's socket.
+    pub fn insert_client(&mut self, stream: UnixStream, security_context: Option<SecurityContext>) {
+        let client_state = ClientState::new(self.disconnect_tx.clone(), security_context);
+        let client = match self
+            .display_handle
+            .insert_client(stream, Arc::new(client_state))
+        {
+            Ok(client) => client,
+            Err(err) => {
+                tracing::error!("Failed to insert client: {err}");
+                return;
+            }
+        };
+
+        if let (Ok(credentials), Some(client_state)) = (
+            client.get_credentials(&self.display_handle),
+            client.get_data::<ClientState>(),
+        ) {
+            let _ = client_state
+                .process
+                .set(ClientProcess::new(credentials.pid));
+        }
+    }
 }
 
 pub struct CalloopData {
@@ -319,15 +525,85 @@ pub struct CalloopData {
     pub state: State,
 }
 
-#[derive(Default)]
 pub struct ClientState {
     pub compositor_state: CompositorClientState,
+    /// The process behind this client, if it could be found
+    pub process: OnceLock<ClientProcess>,
+    /// This client's windows that are open
+    open_window_ids: Mutex<Vec<WindowId>>,
+    /// Whether this client ever opened a window
+    opened_window: AtomicBool,
+    disconnect_tx: Mutex<Sender<PendingDisconnect>>,
+    /// The sandbox this client is in, if it connected through a security context's socket
+    pub security_context: Option<SecurityContext>,
+}
+
+impl ClientState {
+    pub fn new(
+        disconnect_tx: Sender<PendingDisconnect>,
+        security_context: Option<SecurityContext>,
+    ) -> Self {
+        Self {
+            compositor_state: CompositorClientState::default(),
+            process: OnceLock::new(),
+            open_window_ids: Mutex::new(Vec::new()),
+            opened_window: AtomicBool::new(false),
+            disconnect_tx: Mutex::new(disconnect_tx),
+            security_context,
+        }
+    }
+
+    /// Remember that this client opened the window with `id`.
+    pub fn window_opened(&self, id: WindowId) {
+        self.opened_window.store(true, Ordering::Relaxed);
+        self.open_window_ids
+            .lock()
+            .expect("couldn't lock open_window_ids")
+            .push(id);
+    }
+
+    /// Forget the window with `id` after this client closed it.
+    pub fn window_closed(&self, id: WindowId) {
+        self.open_window_ids
+            .lock()
+            .expect("couldn't lock open_window_ids")
+            .retain(|open_id| *open_id != id);
+    }
 }
 
 impl ClientData for ClientState {
     fn initialized(&self, _client_id: ClientId) {}
 
-    fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
+    fn disconnected(&self, _client_id: ClientId, reason: DisconnectReason) {
+        if !self.opened_window.load(Ordering::Relaxed)
+            && !matches!(reason, DisconnectReason::ProtocolError(_))
+        {
+            return;
+        }
+
+        // Windows are only forgotten while the client is alive, so these
+        // are the windows that were open when it disconnected
+        let window_ids = std::mem::take(
+            &mut *self
+                .open_window_ids
+                .lock()
+                .expect("couldn't lock open_window_ids"),
+        );
+
+        let pending = PendingDisconnect {
+            process: self.process.get().cloned(),
+            reason,
+            window_ids,
+            time: Instant::now(),
+        };
+
+        // The compositor is shutting down if this fails
+        let _ = self
+            .disconnect_tx
+            .lock()
+            .expect("couldn't lock disconnect_tx")
+            .send(pending);
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
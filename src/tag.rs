@@ -10,12 +10,21 @@ use std::{
 use smithay::output::Output;
 
 use crate::{
+    api::msg::{Args, OutgoingMsg},
+    config::ConnectorSavedState,
     layout::Layout,
+    output::OutputName,
     state::{State, WithState},
+    window::rules::FloatingOrTiled,
 };
 
 static TAG_ID_COUNTER: AtomicU32 = AtomicU32::new(0);
 
+/// The master factor tags start with.
+pub const DEFAULT_MASTER_FACTOR: f64 = 0.5;
+/// The smallest master factor, and how far the largest is from 1.0.
+const MIN_MASTER_FACTOR: f64 = 0.1;
+
 /// A unique id for a [`Tag`].
 #[derive(Debug, Hash, PartialEq, Eq, Clone, Copy, serde::Serialize, serde::Deserialize)]
 pub enum TagId {
@@ -59,6 +68,10 @@ struct TagInner {
     active: bool,
     /// What layout this tag has.
     layout: Layout,
+    /// How much of the width the master window takes up in layouts that have one.
+    master_factor: f64,
+    /// Whether new windows on this tag float or tile, if it overrides its output.
+    default_floating_or_tiled: Option<FloatingOrTiled>,
 }
 
 impl PartialEq for TagInner {
@@ -101,6 +114,24 @@ impl Tag {
     pub fn set_layout(&self, layout: Layout) {
         self.0.borrow_mut().layout = layout;
     }
+
+    pub fn master_factor(&self) -> f64 {
+        self.0.borrow().master_factor
+    }
+
+    /// Set the master factor, clamped so neither side of the layout disappears.
+    pub fn set_master_factor(&self, master_factor: f64) {
+        self.0.borrow_mut().master_factor =
+            master_factor.clamp(MIN_MASTER_FACTOR, 1.0 - MIN_MASTER_FACTOR);
+    }
+
+    pub fn default_floating_or_tiled(&self) -> Option<FloatingOrTiled> {
+        self.0.borrow().default_floating_or_tiled
+    }
+
+    pub fn set_default_floating_or_tiled(&self, floating_or_tiled: Option<FloatingOrTiled>) {
+        self.0.borrow_mut().default_floating_or_tiled = floating_or_tiled;
+    }
 }
 
 impl Tag {
@@ -110,6 +141,8 @@ impl Tag {
             name,
             active: false,
             layout: Layout::MasterStack, // TODO: get from config
+            master_factor: DEFAULT_MASTER_FACTOR,
+            default_floating_or_tiled: None,
         })))
     }
 
@@ -124,3 +157,180 @@ impl Tag {
             .cloned()
     }
 }
+
+impl State {
+    /// Add tags named `tag_names` to the output named `output_name`, returning them.
+    ///
+    /// If the output isn't connected, the tags are saved and added when it is.
+    pub fn add_tags(&mut self, output_name: OutputName, tag_names: Vec<String>) -> Vec<Tag> {
+        let new_tags = tag_names.into_iter().map(Tag::new).collect::<Vec<_>>();
+        self.apply_saved_tag_default_modes(&output_name, &new_tags);
+        if let Some(saved_state) = self.config.connector_saved_states.get_mut(&output_name) {
+            let mut tags = saved_state.tags.clone();
+            tags.extend(new_tags.clone());
+            saved_state.tags = tags;
+        } else {
+            self.config.connector_saved_states.insert(
+                output_name.clone(),
+                ConnectorSavedState {
+                    tags: new_tags.clone(),
+                    ..Default::default()
+                },
+            );
+        }
+
+        if let Some(output) = self
+            .space
+            .outputs()
+            .find(|output| output.name() == output_name.0)
+            .cloned()
+        {
+            output.with_state(|state| {
+                state.tags.extend(new_tags.clone());
+                tracing::debug!("tags added, are now {:?}", state.tags);
+            });
+            self.apply_saved_tag_order(&output);
+
+            // replace tags that windows have that are the same id
+            // (this should only happen on config reload)
+            for tag in new_tags.iter() {
+                for window in self.windows.iter() {
+                    window.with_state(|state| {
+                        for win_tag in state.tags.iter_mut() {
+                            if win_tag.id() == tag.id() {
+                                *win_tag = tag.clone();
+                            }
+                        }
+                    });
+                }
+            }
+
+            self.adopt_orphans(&output);
+        }
+
+        new_tags
+    }
+
+    /// Switch to `tag`, deactivating all other tags on its output.
+    pub fn switch_to_tag(&mut self, tag: &Tag) {
+        let Some(output) = tag.output(self) else { return };
+        let changed = output.with_state(|state| {
+            let was_active = state.tags.iter().map(Tag::active).collect::<Vec<_>>();
+            for op_tag in state.tags.iter_mut() {
+                op_tag.set_active(false);
+            }
+            tag.set_active(true);
+
+            state
+                .tags
+                .iter()
+                .zip(was_active)
+                .filter(|(op_tag, was_active)| op_tag.active() != *was_active)
+                .map(|(op_tag, _)| op_tag.clone())
+                .collect::<Vec<_>>()
+        });
+        for changed in changed.iter() {
+            self.signal_tag_active_changed(changed);
+        }
+        self.update_windows(&output);
+        self.update_focus(&output);
+        self.schedule_render(&output);
+    }
+
+    /// Move `tag` to `index` in its output's tag order, clamping `index` to the last position.
+    ///
+    /// The new order is remembered across config reloads.
+    pub fn set_tag_index(&mut self, tag: &Tag, index: usize) {
+        let Some(output) = tag.output(self) else { return };
+
+        let changed = output.with_state(|state| {
+            let Some(current) = state.tags.iter().position(|tg| tg == tag) else {
+                return false;
+            };
+            let index = index.min(state.tags.len() - 1);
+            if current == index {
+                return false;
+            }
+
+            let tag = state.tags.remove(current);
+            state.tags.insert(index, tag);
+            true
+        });
+
+        if !changed {
+            return;
+        }
+
+        let tag_names = output.with_state(|state| state.tags.iter().map(Tag::name).collect());
+        self.config
+            .tag_orders
+            .insert(OutputName(output.name()), tag_names);
+
+        self.signal_tag_order_changed(&output);
+    }
+
+    /// Reorder the tags on `output` to follow the order saved from before a config reload.
+    ///
+    /// Tags that weren't in the saved order go after those that were.
+    pub fn apply_saved_tag_order(&mut self, output: &Output) {
+        let Some(saved_order) = self.config.tag_orders.get(&OutputName(output.name())) else {
+            return;
+        };
+
+        output.with_state(|state| {
+            state.tags.sort_by_key(|tag| {
+                saved_order
+                    .iter()
+                    .position(|name| name == &tag.name())
+                    .unwrap_or(usize::MAX)
+            });
+        });
+    }
+
+    /// Tell the config that `tag` was activated or deactivated.
+    pub fn signal_tag_active_changed(&self, tag: &Tag) {
+        let Some(stream) = self.api_state.stream.as_ref() else {
+            return;
+        };
+
+        let output_name = tag.output(self).map(|output| output.name());
+
+        for callback_id in self.config.tag_active_callback_ids.iter() {
+            crate::api::send_to_client(
+                &mut stream.lock().expect("couldn't lock stream"),
+                &OutgoingMsg::call_callback(
+                    *callback_id,
+                    Some(Args::TagActiveChanged {
+                        tag_id: tag.id(),
+                        name: tag.name(),
+                        output_name: output_name.clone(),
+                        active: tag.active(),
+                    }),
+                    &self.api_state.callback_serial,
+                ),
+            )
+            .expect("Send to client failed");
+        }
+    }
+
+    /// Tell the config that the order of tags on `output` changed.
+    fn signal_tag_order_changed(&self, output: &Output) {
+        let Some(stream) = self.api_state.stream.as_ref() else {
+            return;
+        };
+
+        for callback_id in self.config.tag_order_callback_ids.iter() {
+            crate::api::send_to_client(
+                &mut stream.lock().expect("couldn't lock stream"),
+                &OutgoingMsg::call_callback(
+                    *callback_id,
+                    Some(Args::TagOrderChanged {
+                        output_name: output.name(),
+                    }),
+                    &self.api_state.callback_serial,
+                ),
+            )
+            .expect("Send to client failed");
+        }
+    }
+}
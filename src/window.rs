@@ -1,6 +1,20 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+pub mod activity;
+pub mod default_mode;
+pub mod follow;
+pub mod group;
+pub mod insert_position;
+pub mod lifecycle;
+pub mod orphan;
+pub mod placement;
 pub mod rules;
+pub mod shape;
+pub mod state_request;
+pub mod swap;
+pub mod throttle;
+pub mod title;
+pub mod wait;
 
 use std::{cell::RefCell, time::Duration};
 
@@ -25,19 +39,23 @@ use smithay::{
         wayland_protocols::wp::presentation_time::server::wp_presentation_feedback,
         wayland_server::protocol::wl_surface::WlSurface,
     },
-    utils::{user_data::UserDataMap, IsAlive, Logical, Point, Rectangle, Serial},
+    utils::{user_data::UserDataMap, IsAlive, Logical, Point, Rectangle, Serial, Size},
     wayland::{
         compositor::{self, SurfaceData},
         dmabuf::DmabufFeedback,
+        fractional_scale::with_fractional_scale,
         seat::WaylandFocus,
-        shell::xdg::XdgToplevelSurfaceData,
+        shell::xdg::SurfaceCachedState,
     },
     xwayland::X11Surface,
 };
 
-use crate::state::{State, WithState};
+use crate::{
+    output::OutputName,
+    state::{State, WithState},
+};
 
-use self::window_state::{LocationRequestState, WindowElementState};
+use self::window_state::{FloatingOrTiled, LocationRequestState, WindowElementState};
 
 pub mod window_state;
 
@@ -52,6 +70,51 @@ pub enum WindowElement {
     X11OverrideRedirect(X11Surface),
 }
 
+/// The grid a window wants to be resized along, like the cells of a terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResizeIncrements {
+    /// The size the increments are counted from
+    pub base: Size<i32, Logical>,
+    /// The size of one step of the grid
+    pub inc: Size<i32, Logical>,
+    /// The smallest size the window wants
+    pub min: Size<i32, Logical>,
+}
+
+impl ResizeIncrements {
+    /// Round `size` down to the grid, relative to the base size.
+    ///
+    /// The result is never below the min size: if rounding down would go below it, the
+    /// smallest grid size at or above it is used instead. Lengths that are already at or below
+    /// the base or min size, or that have no grid size between the min size and themselves,
+    /// are left as they are.
+    pub fn snap(&self, size: Size<i32, Logical>) -> Size<i32, Logical> {
+        let snap = |len: i32, base: i32, inc: i32, min: i32| {
+            if len <= base || len <= min {
+                return len;
+            }
+
+            let snapped = base + (len - base) / inc * inc;
+            if snapped >= min {
+                return snapped;
+            }
+
+            let above_min = base + (min - base + inc - 1) / inc * inc;
+            if above_min <= len {
+                above_min
+            } else {
+                len
+            }
+        };
+
+        (
+            snap(size.w, self.base.w, self.inc.w, self.min.w),
+            snap(size.h, self.base.h, self.inc.h, self.min.h),
+        )
+            .into()
+    }
+}
+
 impl WindowElement {
     pub fn with_surfaces<F>(&self, processor: F)
     where
@@ -197,50 +260,122 @@ impl WindowElement {
             }
         }
         self.with_state(|state| {
-            state.loc_request_state = LocationRequestState::Sent(new_geo.loc);
+            state.loc_request_state = match self {
+                WindowElement::Wayland(_) => LocationRequestState::Sent(new_geo.loc),
+                // X11 windows were just configured, so they only need to be moved
+                WindowElement::X11(_) => LocationRequestState::Acknowledged(new_geo.loc),
+                WindowElement::X11OverrideRedirect(_) => LocationRequestState::Idle,
+            };
         });
     }
 
-    pub fn class(&self) -> Option<String> {
+    /// Ask the window to close.
+    pub fn close(&self) {
         match self {
-            WindowElement::Wayland(window) => {
-                compositor::with_states(window.toplevel().wl_surface(), |states| {
-                    states
-                        .data_map
-                        .get::<XdgToplevelSurfaceData>()
-                        .expect("XdgToplevelSurfaceData wasn't in surface's data map")
-                        .lock()
-                        .expect("Failed to lock Mutex<XdgToplevelSurfaceData>")
-                        .app_id
-                        .clone()
-                })
-            }
-            WindowElement::X11(surface) | WindowElement::X11OverrideRedirect(surface) => {
-                Some(surface.class())
+            WindowElement::Wayland(window) => window.toplevel().send_close(),
+            WindowElement::X11(surface) => {
+                surface.close().expect("failed to close x11 win");
             }
+            WindowElement::X11OverrideRedirect(_) => (),
         }
     }
 
+    /// Get the window's class, which is its app id on Wayland.
+    ///
+    /// This is cached from the last commit, so it doesn't touch the window's surface.
+    pub fn class(&self) -> Option<String> {
+        self.with_state(|state| state.class.clone())
+    }
+
+    /// Get the window's title.
+    ///
+    /// This is cached from the last commit, so it doesn't touch the window's surface.
     pub fn title(&self) -> Option<String> {
+        self.with_state(|state| state.title.clone())
+    }
+
+    /// Set the preferred scale and transform of every surface of this window to `output`'s.
+    ///
+    /// This sets the fractional scale and sends the integer `wl_surface.preferred_buffer_scale`
+    /// for clients that don't use fractional scaling. Both are only sent when they change.
+    pub fn send_preferred_scale(&self, output: &Output) {
+        let fractional_scale = output.current_scale().fractional_scale();
+        let integer_scale = output.current_scale().integer_scale();
+        let transform = output.current_transform();
+        self.with_surfaces(move |surface, states| {
+            with_fractional_scale(states, |fractional| {
+                fractional.set_preferred_scale(fractional_scale);
+            });
+            compositor::send_surface_state(surface, states, integer_scale, transform);
+        });
+    }
+
+    /// Get the resize increments of this window, if it has any.
+    ///
+    /// These are only read from the `WM_NORMAL_HINTS` of Xwayland windows. Wayland has no way
+    /// for a client to advertise increments, so Wayland windows never have any and are never
+    /// snapped. Windows with an increment of 1 in both directions are treated as having none.
+    pub fn resize_increments(&self) -> Option<ResizeIncrements> {
         match self {
-            WindowElement::Wayland(window) => {
-                compositor::with_states(window.toplevel().wl_surface(), |states| {
-                    states
-                        .data_map
-                        .get::<XdgToplevelSurfaceData>()
-                        .expect("XdgToplevelSurfaceData wasn't in surface's data map")
-                        .lock()
-                        .expect("Failed to lock Mutex<XdgToplevelSurfaceData>")
-                        .title
-                        .clone()
+            WindowElement::Wayland(_) | WindowElement::X11OverrideRedirect(_) => None,
+            WindowElement::X11(surface) => {
+                let hints = surface.size_hints()?;
+                let (inc_w, inc_h) = hints.size_increment?;
+                if inc_w <= 1 && inc_h <= 1 {
+                    return None;
+                }
+
+                // ICCCM says to fall back to the min size if there is no base size.
+                let (base_w, base_h) = hints.base_size.or(hints.min_size).unwrap_or((0, 0));
+                let (min_w, min_h) = hints.min_size.unwrap_or((0, 0));
+
+                Some(ResizeIncrements {
+                    base: (base_w.max(0), base_h.max(0)).into(),
+                    inc: (inc_w.max(1), inc_h.max(1)).into(),
+                    min: (min_w.max(0), min_h.max(0)).into(),
                 })
             }
-            WindowElement::X11(surface) | WindowElement::X11OverrideRedirect(surface) => {
-                Some(surface.title())
-            }
         }
     }
 
+    /// Round `size` down to this window's resize increment grid.
+    ///
+    /// If the window has no resize increments, `size` is returned unchanged.
+    /// See [`ResizeIncrements::snap`].
+    pub fn snap_size_to_increments(&self, size: Size<i32, Logical>) -> Size<i32, Logical> {
+        match self.resize_increments() {
+            Some(increments) => increments.snap(size),
+            None => size,
+        }
+    }
+
+    /// Clamp `size` to the smallest and largest sizes this window supports.
+    pub fn clamp_size(&self, size: Size<i32, Logical>) -> Size<i32, Logical> {
+        let (min_size, max_size) = match self.wl_surface() {
+            Some(wl_surface) => compositor::with_states(&wl_surface, |states| {
+                let data = states.cached_state.current::<SurfaceCachedState>();
+                (data.min_size, data.max_size)
+            }),
+            None => ((0, 0).into(), (0, 0).into()),
+        };
+
+        // HACK: Here I set the min height to be self.geometry().loc.y.abs() because if it's
+        // |     lower then the compositor crashes trying to create a size with height -1 if you make the
+        // |     window height too small.
+        // |     However I don't know if the loc.y from window.geometry will always be the negative
+        // |     of the csd height.
+        let min_width = i32::max(1, min_size.w);
+        let min_height = i32::max(i32::max(0, self.geometry().loc.y.abs()) + 1, min_size.h);
+
+        let max_width = if max_size.w != 0 { max_size.w } else { i32::MAX };
+        let max_height = if max_size.h != 0 { max_size.h } else { i32::MAX };
+
+        Size::from((
+            size.w.clamp(min_width, max_width),
+            size.h.clamp(min_height, max_height),
+        ))
+    }
+
     /// Get the output this window is on.
     ///
     /// This method gets the first tag the window has and returns its output.
@@ -267,21 +402,19 @@ impl WindowElement {
         })
     }
 
-    /// Place this window on the given output, giving it the output's focused tags.
+    /// Place this window on the given output, giving it the output's focused tags,
+    /// or its first tag if none are focused.
+    ///
+    /// If the output has no tags, the window is orphaned on it until it gets some.
     ///
     /// RefCell Safety: Uses refcells on both the window and the output.
     pub fn place_on_output(&self, output: &Output) {
         self.with_state(|state| {
-            state.tags = output.with_state(|state| {
-                let output_tags = state.focused_tags().cloned().collect::<Vec<_>>();
-                if !output_tags.is_empty() {
-                    output_tags
-                } else if let Some(first_tag) = state.tags.first() {
-                    vec![first_tag.clone()]
-                } else {
-                    vec![]
-                }
-            });
+            state.tags = output.with_state(|state| orphan::placement_tags(&state.tags));
+
+            if state.tags.is_empty() {
+                state.orphaned_on = Some(OutputName(output.name()));
+            }
 
             tracing::debug!(
                 "Placed window on {} with tags {:?}",
@@ -314,6 +447,25 @@ impl WindowElement {
     pub fn is_x11_override_redirect(&self) -> bool {
         matches!(self, Self::X11OverrideRedirect(..))
     }
+
+    /// Set this window's activated state, configuring it only if the state changed.
+    ///
+    /// This does nothing for override redirect windows.
+    pub fn set_activated(&self, activated: bool) {
+        match self {
+            WindowElement::Wayland(window) => {
+                if window.set_activated(activated) {
+                    window.toplevel().send_configure();
+                }
+            }
+            WindowElement::X11(surface) => {
+                if let Err(err) = surface.set_activated(activated) {
+                    tracing::error!("Failed to set x11 window activated state: {err}");
+                }
+            }
+            WindowElement::X11OverrideRedirect(_) => (),
+        }
+    }
 }
 
 impl IsAlive for WindowElement {
@@ -413,74 +565,130 @@ impl PointerTarget<State> for WindowElement {
 
     fn gesture_swipe_begin(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GestureSwipeBeginEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GestureSwipeBeginEvent,
     ) {
-        todo!()
+        match self {
+            WindowElement::Wayland(window) => {
+                PointerTarget::gesture_swipe_begin(window, seat, data, event);
+            }
+            WindowElement::X11(surface) | WindowElement::X11OverrideRedirect(surface) => {
+                PointerTarget::gesture_swipe_begin(surface, seat, data, event);
+            }
+        }
     }
 
     fn gesture_swipe_update(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GestureSwipeUpdateEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GestureSwipeUpdateEvent,
     ) {
-        todo!()
+        match self {
+            WindowElement::Wayland(window) => {
+                PointerTarget::gesture_swipe_update(window, seat, data, event);
+            }
+            WindowElement::X11(surface) | WindowElement::X11OverrideRedirect(surface) => {
+                PointerTarget::gesture_swipe_update(surface, seat, data, event);
+            }
+        }
     }
 
     fn gesture_swipe_end(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GestureSwipeEndEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GestureSwipeEndEvent,
     ) {
-        todo!()
+        match self {
+            WindowElement::Wayland(window) => {
+                PointerTarget::gesture_swipe_end(window, seat, data, event);
+            }
+            WindowElement::X11(surface) | WindowElement::X11OverrideRedirect(surface) => {
+                PointerTarget::gesture_swipe_end(surface, seat, data, event);
+            }
+        }
     }
 
     fn gesture_pinch_begin(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GesturePinchBeginEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GesturePinchBeginEvent,
     ) {
-        todo!()
+        match self {
+            WindowElement::Wayland(window) => {
+                PointerTarget::gesture_pinch_begin(window, seat, data, event);
+            }
+            WindowElement::X11(surface) | WindowElement::X11OverrideRedirect(surface) => {
+                PointerTarget::gesture_pinch_begin(surface, seat, data, event);
+            }
+        }
     }
 
     fn gesture_pinch_update(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GesturePinchUpdateEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GesturePinchUpdateEvent,
     ) {
-        todo!()
+        match self {
+            WindowElement::Wayland(window) => {
+                PointerTarget::gesture_pinch_update(window, seat, data, event);
+            }
+            WindowElement::X11(surface) | WindowElement::X11OverrideRedirect(surface) => {
+                PointerTarget::gesture_pinch_update(surface, seat, data, event);
+            }
+        }
     }
 
     fn gesture_pinch_end(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GesturePinchEndEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GesturePinchEndEvent,
     ) {
-        todo!()
+        match self {
+            WindowElement::Wayland(window) => {
+                PointerTarget::gesture_pinch_end(window, seat, data, event);
+            }
+            WindowElement::X11(surface) | WindowElement::X11OverrideRedirect(surface) => {
+                PointerTarget::gesture_pinch_end(surface, seat, data, event);
+            }
+        }
     }
 
     fn gesture_hold_begin(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GestureHoldBeginEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GestureHoldBeginEvent,
     ) {
-        todo!()
+        match self {
+            WindowElement::Wayland(window) => {
+                PointerTarget::gesture_hold_begin(window, seat, data, event);
+            }
+            WindowElement::X11(surface) | WindowElement::X11OverrideRedirect(surface) => {
+                PointerTarget::gesture_hold_begin(surface, seat, data, event);
+            }
+        }
     }
 
     fn gesture_hold_end(
         &self,
-        _seat: &Seat<State>,
-        _data: &mut State,
-        _event: &smithay::input::pointer::GestureHoldEndEvent,
+        seat: &Seat<State>,
+        data: &mut State,
+        event: &smithay::input::pointer::GestureHoldEndEvent,
     ) {
-        todo!()
+        match self {
+            WindowElement::Wayland(window) => {
+                PointerTarget::gesture_hold_end(window, seat, data, event);
+            }
+            WindowElement::X11(surface) | WindowElement::X11OverrideRedirect(surface) => {
+                PointerTarget::gesture_hold_end(surface, seat, data, event);
+            }
+        }
     }
 }
 
@@ -575,6 +783,7 @@ impl SpaceElement for WindowElement {
             WindowElement::Wayland(window) => SpaceElement::is_in_input_region(window, point),
             WindowElement::X11(surface) | WindowElement::X11OverrideRedirect(surface) => {
                 SpaceElement::is_in_input_region(surface, point)
+                    && self.with_state(|state| state.x11_shape.takes_input_at(*point))
             }
         }
     }
@@ -654,4 +863,178 @@ impl State {
                     .cloned()
             })
     }
+
+    /// Get the geometry `window` has while floating, or will have once it floats.
+    pub fn window_floating_geometry(&self, window: &WindowElement) -> Rectangle<i32, Logical> {
+        match window.with_state(|state| state.floating_or_tiled) {
+            FloatingOrTiled::Floating(rect) | FloatingOrTiled::Tiled(Some(rect)) => rect,
+            FloatingOrTiled::Tiled(None) => Rectangle::from_loc_and_size(
+                self.space.element_location(window).unwrap_or_default(),
+                window.geometry().size,
+            ),
+        }
+    }
+
+    /// Set the geometry `window` has while floating, clamped to the sizes it supports.
+    ///
+    /// Tiled windows keep the geometry for when they're floating again.
+    pub fn set_window_floating_geometry(
+        &mut self,
+        window: &WindowElement,
+        geo: Rectangle<i32, Logical>,
+    ) {
+        let geo = Rectangle::from_loc_and_size(geo.loc, window.clamp_size(geo.size));
+        window.with_state(|state| {
+            state.floating_or_tiled = match state.floating_or_tiled {
+                FloatingOrTiled::Floating(_) => FloatingOrTiled::Floating(geo),
+                FloatingOrTiled::Tiled(_) => FloatingOrTiled::Tiled(Some(geo)),
+            }
+        });
+
+        if let Some(output) = window.output(self) {
+            self.update_windows(&output);
+            self.schedule_render(&output);
+        }
+    }
+
+    /// Get the output `window` overlaps the most, or `None` if it isn't mapped
+    /// or on any output.
+    pub fn primary_output_for_window(&self, window: &WindowElement) -> Option<Output> {
+        let window_loc = self.space.element_location(window)?;
+        let window_geo = Rectangle::from_loc_and_size(window_loc, window.geometry().size);
+
+        self.space
+            .outputs_for_element(window)
+            .into_iter()
+            .filter_map(|output| {
+                let output_geo = self.space.output_geometry(&output)?;
+                let overlap = output_geo.intersection(window_geo)?;
+                Some((output, overlap.size.w * overlap.size.h))
+            })
+            .max_by_key(|(_, area)| *area)
+            .map(|(output, _)| output)
+    }
+
+    /// Recompute which outputs the given windows overlap and update their preferred scales.
+    ///
+    /// This sends `wl_surface.enter` and `leave` events to windows that moved onto or off of
+    /// outputs, then sets each window's preferred scale to that of its primary output.
+    /// Call this after anything that changes a window's location or the output layout.
+    ///
+    /// Space::refresh is what actually sends enter and leave, and it's only called when a window
+    /// crossed an output boundary. Otherwise the refresh every frame is enough.
+    pub fn update_window_outputs<'a>(
+        &mut self,
+        windows: impl IntoIterator<Item = &'a WindowElement>,
+    ) {
+        let windows = windows.into_iter().collect::<Vec<_>>();
+
+        let output_geos = self
+            .space
+            .outputs()
+            .filter_map(|output| Some((output.clone(), self.space.output_geometry(output)?)))
+            .collect::<Vec<_>>();
+
+        let crossed_boundary = windows.iter().any(|window| {
+            self.space.element_bbox(window).is_some_and(|bbox| {
+                overlapped_outputs_changed(
+                    &self.space.outputs_for_element(window),
+                    bbox,
+                    output_geos.iter().cloned(),
+                )
+            })
+        });
+
+        if crossed_boundary {
+            self.space.refresh();
+        }
+
+        for window in windows {
+            if let Some(output) = self.primary_output_for_window(window) {
+                window.send_preferred_scale(&output);
+            }
+        }
+    }
+}
+
+/// Whether something with `bbox` overlaps different outputs than the `current` ones it entered.
+fn overlapped_outputs_changed<O: PartialEq>(
+    current: &[O],
+    bbox: Rectangle<i32, Logical>,
+    output_geos: impl IntoIterator<Item = (O, Rectangle<i32, Logical>)>,
+) -> bool {
+    let mut overlapped = 0;
+
+    for (output, geo) in output_geos {
+        if geo.intersection(bbox).is_some() {
+            if !current.contains(&output) {
+                return true;
+            }
+            overlapped += 1;
+        }
+    }
+
+    overlapped != current.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: i32, y: i32, w: i32, h: i32) -> Rectangle<i32, Logical> {
+        Rectangle::from_loc_and_size((x, y), (w, h))
+    }
+
+    fn outputs() -> [(&'static str, Rectangle<i32, Logical>); 2] {
+        [
+            ("left", rect(0, 0, 1920, 1080)),
+            ("right", rect(1920, 0, 1920, 1080)),
+        ]
+    }
+
+    #[test]
+    fn moving_within_an_output_changes_nothing() {
+        assert!(!overlapped_outputs_changed(
+            &["left"],
+            rect(100, 100, 500, 500),
+            outputs()
+        ));
+        assert!(!overlapped_outputs_changed(
+            &["left", "right"],
+            rect(1800, 100, 500, 500),
+            outputs()
+        ));
+    }
+
+    #[test]
+    fn crossing_onto_an_output_changes_outputs() {
+        assert!(overlapped_outputs_changed(
+            &["left"],
+            rect(1800, 100, 500, 500),
+            outputs()
+        ));
+    }
+
+    #[test]
+    fn leaving_an_output_changes_outputs() {
+        assert!(overlapped_outputs_changed(
+            &["left", "right"],
+            rect(2000, 100, 500, 500),
+            outputs()
+        ));
+        assert!(overlapped_outputs_changed(
+            &["left"],
+            rect(-1000, -1000, 500, 500),
+            outputs()
+        ));
+    }
+
+    #[test]
+    fn newly_mapped_windows_change_outputs() {
+        assert!(overlapped_outputs_changed(
+            &[],
+            rect(100, 100, 500, 500),
+            outputs()
+        ));
+    }
 }
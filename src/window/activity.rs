@@ -0,0 +1,263 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Tracking when windows were last used, and tidying away ones that haven't been.
+//!
+//! A window counts as used when it gains or loses keyboard focus or is clicked,
+//! and the window with keyboard focus is always in use. Pointer motion doesn't count,
+//! so tracking this stays cheap.
+//!
+//! Auto tidy periodically moves windows that haven't been used for a while to a tag
+//! on their output.
+
+use std::time::{Duration, Instant};
+
+use smithay::reexports::calloop::{
+    timer::{TimeoutAction, Timer},
+    RegistrationToken,
+};
+
+use crate::{
+    state::{State, WithState},
+    tag::Tag,
+    window::{placement::MoveOrigin, WindowElement},
+};
+
+/// The longest auto tidy waits between checks for inactive windows.
+const AUTO_TIDY_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+pub struct ActivityState {
+    /// Whether focus changes made through the API count as activity.
+    pub count_api_focus: bool,
+    /// Whether a message from a client is being handled right now,
+    /// so focus changes it makes can be told apart from the user's.
+    pub handling_api_msg: bool,
+    auto_tidy: Option<AutoTidy>,
+}
+
+impl Default for ActivityState {
+    fn default() -> Self {
+        Self {
+            count_api_focus: true,
+            handling_api_msg: false,
+            auto_tidy: None,
+        }
+    }
+}
+
+impl ActivityState {
+    /// Whether a keyboard focus change happening now counts as activity.
+    ///
+    /// Changes made through the API only count if [`Self::count_api_focus`] is on.
+    pub fn counts_focus_change(&self) -> bool {
+        !self.handling_api_msg || self.count_api_focus
+    }
+}
+
+#[derive(Debug)]
+struct AutoTidy {
+    /// How long a window needs to be inactive before it's moved.
+    after: Duration,
+    /// The name of the tag inactive windows are moved to.
+    tag_name: String,
+    timer_token: RegistrationToken,
+}
+
+impl State {
+    /// Mark `window` as used just now.
+    pub fn mark_window_activity(&self, window: &WindowElement) {
+        window.with_state(|state| state.last_activity = Instant::now());
+    }
+
+    /// Mark `window` as used because its keyboard focus changed.
+    ///
+    /// This doesn't count if the change came from the API and
+    /// [`ActivityState::count_api_focus`] is off.
+    pub fn mark_window_focus_activity(&self, window: &WindowElement) {
+        if self.activity_state.counts_focus_change() {
+            self.mark_window_activity(window);
+        }
+    }
+
+    /// Get how long it's been since `window` was last used.
+    pub fn window_inactive_duration(&self, window: &WindowElement) -> Duration {
+        let activated = self.focus_state.activated.as_ref() == Some(window);
+        let last_activity = window.with_state(|state| state.last_activity);
+        inactive_duration(last_activity, activated, Instant::now())
+    }
+
+    /// Get all windows that haven't been used for at least `duration`.
+    pub fn windows_inactive_for(&self, duration: Duration) -> Vec<WindowElement> {
+        self.windows
+            .iter()
+            .filter(|win| !win.is_x11_override_redirect())
+            .filter(|win| self.window_inactive_duration(win) >= duration)
+            .cloned()
+            .collect()
+    }
+
+    /// Start moving windows that haven't been used for `after` to the tag named `tag_name`
+    /// on their output, or stop if `enabled` is false.
+    ///
+    /// This replaces any auto tidy that was already running.
+    pub fn set_auto_tidy(&mut self, after: Duration, tag_name: String, enabled: bool) {
+        self.stop_auto_tidy();
+
+        if !enabled {
+            return;
+        }
+
+        // Check often enough that windows don't stay around much longer than `after`
+        let interval = after.clamp(Duration::from_secs(1), AUTO_TIDY_INTERVAL);
+
+        let timer_token =
+            self.loop_handle
+                .insert_source(Timer::from_duration(interval), move |_, _, data| {
+                    data.state.tidy_inactive_windows();
+                    TimeoutAction::ToDuration(interval)
+                });
+
+        match timer_token {
+            Ok(timer_token) => {
+                self.activity_state.auto_tidy = Some(AutoTidy {
+                    after,
+                    tag_name,
+                    timer_token,
+                });
+            }
+            Err(err) => tracing::error!("Failed to insert auto tidy timer: {err}"),
+        }
+    }
+
+    /// Stop auto tidy if it's running.
+    pub fn stop_auto_tidy(&mut self) {
+        if let Some(auto_tidy) = self.activity_state.auto_tidy.take() {
+            self.loop_handle.remove(auto_tidy.timer_token);
+        }
+    }
+
+    /// Move every window that has been inactive long enough to the auto tidy tag.
+    fn tidy_inactive_windows(&mut self) {
+        let Some(auto_tidy) = self.activity_state.auto_tidy.as_ref() else {
+            return;
+        };
+        let tag_name = auto_tidy.tag_name.clone();
+
+        let mut outputs = Vec::new();
+
+        for window in self.windows_inactive_for(auto_tidy.after) {
+            let Some(output) = window.output(self) else { continue };
+            let Some(tag) = output.with_state(|state| {
+                window.with_state(|win_state| tidy_tag(&state.tags, &tag_name, &win_state.tags))
+            }) else {
+                continue;
+            };
+
+            tracing::debug!("Auto tidy is moving {:?} to tag {tag_name}", window.class());
+            window.with_state(|state| state.tags = vec![tag]);
+            self.window_moved(&window, MoveOrigin::Layout);
+
+            if !outputs.contains(&output) {
+                outputs.push(output);
+            }
+        }
+
+        for output in outputs {
+            self.update_windows(&output);
+            self.schedule_render(&output);
+        }
+    }
+}
+
+/// Get how long it's been since `last_activity` at `now`.
+/// Activated windows are in use, so they're never inactive.
+fn inactive_duration(last_activity: Instant, activated: bool, now: Instant) -> Duration {
+    if activated {
+        Duration::ZERO
+    } else {
+        now.saturating_duration_since(last_activity)
+    }
+}
+
+/// Get the tag named `tag_name` in `output_tags` that a window with `window_tags` should be
+/// moved to, or `None` if there's no such tag or the window is only on it already.
+fn tidy_tag(output_tags: &[Tag], tag_name: &str, window_tags: &[Tag]) -> Option<Tag> {
+    let tag = output_tags.iter().find(|tag| tag.name() == tag_name)?;
+    (window_tags != [tag.clone()]).then(|| tag.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_focus_changes_always_count() {
+        for count_api_focus in [true, false] {
+            let state = ActivityState {
+                count_api_focus,
+                ..Default::default()
+            };
+            assert!(state.counts_focus_change());
+        }
+    }
+
+    #[test]
+    fn api_focus_changes_count_when_enabled() {
+        let state = ActivityState {
+            handling_api_msg: true,
+            ..Default::default()
+        };
+        assert!(state.count_api_focus);
+        assert!(state.counts_focus_change());
+    }
+
+    #[test]
+    fn api_focus_changes_dont_count_when_disabled() {
+        let state = ActivityState {
+            count_api_focus: false,
+            handling_api_msg: true,
+            ..Default::default()
+        };
+        assert!(!state.counts_focus_change());
+    }
+
+    #[test]
+    fn inactive_duration_is_time_since_last_activity() {
+        let last_activity = Instant::now();
+        let now = last_activity + Duration::from_secs(90);
+        assert_eq!(
+            inactive_duration(last_activity, false, now),
+            Duration::from_secs(90)
+        );
+    }
+
+    #[test]
+    fn activated_windows_are_never_inactive() {
+        let last_activity = Instant::now();
+        let now = last_activity + Duration::from_secs(3600);
+        assert_eq!(inactive_duration(last_activity, true, now), Duration::ZERO);
+    }
+
+    #[test]
+    fn tidy_moves_windows_to_the_named_tag() {
+        let work = Tag::new("work".to_string());
+        let stale = Tag::new("stale".to_string());
+        let output_tags = [work.clone(), stale.clone()];
+
+        assert_eq!(
+            tidy_tag(&output_tags, "stale", &[work.clone(), stale.clone()]),
+            Some(stale.clone())
+        );
+        assert_eq!(tidy_tag(&output_tags, "stale", &[work]), Some(stale));
+    }
+
+    #[test]
+    fn tidy_skips_tidied_windows_and_missing_tags() {
+        let work = Tag::new("work".to_string());
+        let stale = Tag::new("stale".to_string());
+        let output_tags = [work.clone(), stale.clone()];
+
+        assert_eq!(tidy_tag(&output_tags, "stale", &[stale]), None);
+        assert_eq!(tidy_tag(&output_tags, "old", &[work]), None);
+    }
+}
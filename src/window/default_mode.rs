@@ -0,0 +1,182 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Whether new windows float or tile.
+//!
+//! Outputs and tags can each set whether new windows on them float or tile. When a window is
+//! mapped, the first of these that's set decides:
+//!
+//! 1. Window rules
+//! 2. The first of the window's tags with a default
+//! 3. The window's output
+//! 4. Tiled
+//!
+//! Windows that already float or tile for a reason of their own, like X11 dialogs or windows
+//! put back from the previous session, only go by window rules.
+//!
+//! Tag defaults are kept by output and tag name, so they come back when the config adds the
+//! tag again after a reload.
+
+use smithay::output::Output;
+
+use crate::{
+    output::OutputName,
+    state::{State, WithState},
+    tag::Tag,
+};
+
+use super::{rules::FloatingOrTiled, WindowElement};
+
+/// Decide whether a new window floats or tiles from what window rules, its tag, and its
+/// output want, in that order, falling back to `fallback`.
+pub fn decide_floating_or_tiled(
+    rule: Option<FloatingOrTiled>,
+    tag: Option<FloatingOrTiled>,
+    output: Option<FloatingOrTiled>,
+    fallback: FloatingOrTiled,
+) -> FloatingOrTiled {
+    rule.or(tag).or(output).unwrap_or(fallback)
+}
+
+impl State {
+    /// Float or tile the newly mapped `window` after window rules were applied.
+    ///
+    /// `rule` is what the window rules decided, if any did. If `keep` is true, the window
+    /// already floats or tiles for a reason of its own and tag and output defaults are skipped.
+    pub fn apply_default_floating_or_tiled(
+        &self,
+        window: &WindowElement,
+        rule: Option<FloatingOrTiled>,
+        keep: bool,
+    ) {
+        let (tag_default, output_default, fallback) = if keep {
+            let current = if window.with_state(|state| state.floating_or_tiled.is_floating()) {
+                FloatingOrTiled::Floating
+            } else {
+                FloatingOrTiled::Tiled
+            };
+            (None, None, current)
+        } else {
+            let tag_default = window.with_state(|state| {
+                state
+                    .tags
+                    .iter()
+                    .find_map(|tag| tag.default_floating_or_tiled())
+            });
+            let output_default = window
+                .output(self)
+                .and_then(|output| output.with_state(|state| state.default_floating_or_tiled));
+            (tag_default, output_default, FloatingOrTiled::Tiled)
+        };
+
+        let floating_or_tiled =
+            decide_floating_or_tiled(rule, tag_default, output_default, fallback);
+        window.set_floating(floating_or_tiled == FloatingOrTiled::Floating);
+    }
+
+    /// Set whether new windows on `output` float or tile when their tags don't say.
+    pub fn set_output_default_floating_or_tiled(
+        &mut self,
+        output: &Output,
+        floating_or_tiled: Option<FloatingOrTiled>,
+    ) {
+        output.with_state(|state| state.default_floating_or_tiled = floating_or_tiled);
+    }
+
+    /// Set whether new windows on `tag` float or tile, overriding its output.
+    pub fn set_tag_default_floating_or_tiled(
+        &mut self,
+        tag: &Tag,
+        floating_or_tiled: Option<FloatingOrTiled>,
+    ) {
+        tag.set_default_floating_or_tiled(floating_or_tiled);
+
+        let Some(output) = tag.output(self) else { return };
+        let saved = self
+            .config
+            .tag_default_modes
+            .entry(OutputName(output.name()))
+            .or_default();
+        match floating_or_tiled {
+            Some(floating_or_tiled) => {
+                saved.insert(tag.name(), floating_or_tiled);
+            }
+            None => {
+                saved.remove(&tag.name());
+            }
+        }
+    }
+
+    /// Give `tags` on the output named `output_name` the defaults tags with their names had
+    /// before a config reload.
+    pub fn apply_saved_tag_default_modes(&self, output_name: &OutputName, tags: &[Tag]) {
+        let Some(saved) = self.config.tag_default_modes.get(output_name) else {
+            return;
+        };
+
+        for tag in tags {
+            if let Some(floating_or_tiled) = saved.get(&tag.name()) {
+                tag.set_default_floating_or_tiled(Some(*floating_or_tiled));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use FloatingOrTiled::{Floating, Tiled};
+
+    #[test]
+    fn rule_wins() {
+        assert_eq!(
+            decide_floating_or_tiled(Some(Floating), Some(Tiled), Some(Tiled), Tiled),
+            Floating
+        );
+        assert_eq!(
+            decide_floating_or_tiled(Some(Tiled), Some(Floating), Some(Floating), Floating),
+            Tiled
+        );
+    }
+
+    #[test]
+    fn tag_default_beats_output_default() {
+        assert_eq!(
+            decide_floating_or_tiled(None, Some(Floating), Some(Tiled), Tiled),
+            Floating
+        );
+        assert_eq!(
+            decide_floating_or_tiled(None, Some(Tiled), Some(Floating), Floating),
+            Tiled
+        );
+    }
+
+    #[test]
+    fn output_default_beats_fallback() {
+        assert_eq!(
+            decide_floating_or_tiled(None, None, Some(Floating), Tiled),
+            Floating
+        );
+        assert_eq!(
+            decide_floating_or_tiled(None, None, Some(Tiled), Floating),
+            Tiled
+        );
+    }
+
+    #[test]
+    fn fallback_when_nothing_is_set() {
+        assert_eq!(decide_floating_or_tiled(None, None, None, Tiled), Tiled);
+        assert_eq!(
+            decide_floating_or_tiled(None, None, None, Floating),
+            Floating
+        );
+    }
+
+    #[test]
+    fn rule_without_defaults() {
+        assert_eq!(
+            decide_floating_or_tiled(Some(Floating), None, None, Tiled),
+            Floating
+        );
+    }
+}
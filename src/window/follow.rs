@@ -0,0 +1,48 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Windows that follow the active tags on their output.
+//!
+//! Whenever the active tags on an output change, windows following them are moved onto the
+//! newly active tags before the layout runs, so they're tiled on whatever tag is being viewed.
+//! Fullscreen windows stay where they are until they aren't fullscreen anymore.
+//!
+//! Moving a window to a tag or toggling a tag on it stops it from following, since it was
+//! put somewhere on purpose.
+
+use smithay::output::Output;
+
+use crate::state::{State, WithState};
+
+use super::WindowElement;
+
+impl State {
+    /// Make `window` follow the active tags on its output, or stop it from doing so.
+    pub fn set_follow_active_tag(&mut self, window: &WindowElement, follow: bool) {
+        window.with_state(|state| state.follow_active_tag = follow);
+
+        if let Some(output) = window.output(self) {
+            self.update_windows(&output);
+            self.schedule_render(&output);
+        }
+    }
+
+    /// Move windows following the active tags on `output` onto them.
+    pub fn retag_following_windows(&self, output: &Output) {
+        let active_tags =
+            output.with_state(|state| state.focused_tags().cloned().collect::<Vec<_>>());
+        if active_tags.is_empty() {
+            return;
+        }
+
+        for window in self.windows.iter() {
+            let follows = window.with_state(|state| {
+                state.follow_active_tag && !state.fullscreen_or_maximized.is_fullscreen()
+            });
+            if !follows || window.output(self).as_ref() != Some(output) {
+                continue;
+            }
+
+            window.with_state(|state| state.tags = active_tags.clone());
+        }
+    }
+}
@@ -0,0 +1,169 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Window groups, which keep things like tool palettes above their main window.
+//!
+//! Windows that share an X11 window group, an X11 transient-for chain, or an xdg toplevel
+//! parent chain form a group. Raising any window in a group raises the whole group and keeps
+//! its stacking order. Child toplevels and transient, dialog, utility, and toolbar windows
+//! always stack above the group's main windows.
+//!
+//! Groups are worked out from the clients' hints whenever they're needed, so they follow
+//! hint changes and never hold on to closed windows.
+
+use smithay::{
+    reexports::wayland_server::protocol::wl_surface::WlSurface, xwayland::xwm::WmWindowType,
+};
+
+use crate::state::{State, WithState};
+
+use super::{window_state::WindowId, WindowElement};
+
+/// How far parent chains are followed, in case a client makes a cycle.
+const MAX_PARENT_DEPTH: usize = 32;
+
+/// What every window in a group has in common.
+#[derive(Debug, Clone, PartialEq)]
+enum GroupLeader {
+    /// The X11 window group leader, or the root of a transient-for chain.
+    ///
+    /// The leader is often an unmapped window that only exists to hold the group together.
+    X11(u32),
+    /// The root toplevel of an xdg parent chain.
+    Wayland(WlSurface),
+}
+
+impl WindowElement {
+    /// Whether this window stacks above the main windows of its group.
+    fn stacks_above_group(&self) -> bool {
+        match self {
+            WindowElement::Wayland(window) => window.toplevel().parent().is_some(),
+            WindowElement::X11(surface) => {
+                surface.is_transient_for().is_some()
+                    || surface.window_type().is_some_and(|typ| {
+                        matches!(
+                            typ,
+                            WmWindowType::Dialog | WmWindowType::Utility | WmWindowType::Toolbar
+                        )
+                    })
+            }
+            WindowElement::X11OverrideRedirect(_) => false,
+        }
+    }
+}
+
+impl State {
+    fn group_leader(&self, window: &WindowElement) -> Option<GroupLeader> {
+        match window {
+            WindowElement::Wayland(window) => {
+                let mut root = window.toplevel().wl_surface().clone();
+                for _ in 0..MAX_PARENT_DEPTH {
+                    let parent = match self.window_for_surface(&root) {
+                        Some(WindowElement::Wayland(window)) => window.toplevel().parent(),
+                        _ => None,
+                    };
+                    let Some(parent) = parent else {
+                        break;
+                    };
+                    root = parent;
+                }
+                Some(GroupLeader::Wayland(root))
+            }
+            WindowElement::X11(surface) => {
+                let mut surface = surface.clone();
+                for _ in 0..MAX_PARENT_DEPTH {
+                    if let Some(leader) = surface.hints().and_then(|hints| hints.window_group) {
+                        return Some(GroupLeader::X11(leader));
+                    }
+                    let Some(parent_id) = surface.is_transient_for() else {
+                        break;
+                    };
+                    let parent = self.windows.iter().find_map(|win| match win {
+                        WindowElement::X11(parent) if parent.window_id() == parent_id => {
+                            Some(parent.clone())
+                        }
+                        _ => None,
+                    });
+                    match parent {
+                        Some(parent) => surface = parent,
+                        None => return Some(GroupLeader::X11(parent_id)),
+                    }
+                }
+                Some(GroupLeader::X11(surface.window_id()))
+            }
+            WindowElement::X11OverrideRedirect(_) => None,
+        }
+    }
+
+    /// Get every window in `window`'s group, including `window`, in the order they opened.
+    pub fn window_group(&self, window: &WindowElement) -> Vec<WindowElement> {
+        let Some(leader) = self.group_leader(window) else {
+            return vec![window.clone()];
+        };
+
+        let mut group = self
+            .windows
+            .iter()
+            .filter(|win| self.group_leader(win).as_ref() == Some(&leader))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if !group.contains(window) {
+            group.push(window.clone());
+        }
+
+        group
+    }
+
+    /// Get the id of `window`'s group, which is the id of the first window that opened in it.
+    pub fn window_group_id(&self, window: &WindowElement) -> Option<u32> {
+        let first = self.window_group(window).into_iter().next()?;
+        match first.with_state(|state| state.id) {
+            WindowId::Some(id) => Some(id),
+            WindowId::None => None,
+        }
+    }
+
+    /// Raise `window` and the rest of its group to the top of the stack.
+    ///
+    /// The group keeps its stacking order, except that `window` goes on top of the windows
+    /// it stacks with.
+    pub fn raise_window(&mut self, window: &WindowElement) {
+        for win in self.raise_window_in_space(window) {
+            if let WindowElement::X11(surface) = win {
+                self.xwm
+                    .as_mut()
+                    .expect("no xwm")
+                    .raise_window(&surface)
+                    .expect("failed to raise x11 win");
+            }
+        }
+    }
+
+    /// Like [`State::raise_window`], but without restacking X11 windows in the xwm.
+    ///
+    /// Returns the raised windows from bottom to top.
+    pub fn raise_window_in_space(&mut self, window: &WindowElement) -> Vec<WindowElement> {
+        let group = self.window_group(window);
+
+        let (mut main_windows, mut above_windows): (Vec<_>, Vec<_>) = self
+            .space
+            .elements()
+            .filter(|win| *win != window && group.contains(win))
+            .cloned()
+            .partition(|win| !win.stacks_above_group());
+
+        if window.stacks_above_group() {
+            above_windows.push(window.clone());
+        } else {
+            main_windows.push(window.clone());
+        }
+
+        main_windows.append(&mut above_windows);
+
+        for win in main_windows.iter() {
+            self.space.raise_element(win, false);
+        }
+
+        main_windows
+    }
+}
@@ -0,0 +1,177 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Where new windows go in the layout.
+//!
+//! Tiled layouts lay windows out in the order they are in [`State::windows`], so the first
+//! window on a tag is the master. New windows get added to the end by default. They can also
+//! go first, like dwm, or right before or after the focused window.
+//!
+//! Window rules can override this for specific windows.
+
+use smithay::utils::IsAlive;
+
+use crate::state::{State, WithState};
+
+use super::WindowElement;
+
+/// Where new windows are inserted into the layout.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum InsertPosition {
+    /// After every other window.
+    #[default]
+    Append,
+    /// Before every other window, making the new window the master.
+    Prepend,
+    /// Right after the focused window.
+    AfterFocused,
+    /// Right before the focused window, taking its place.
+    BeforeFocused,
+}
+
+impl InsertPosition {
+    /// Get the index a new window at `index` should be moved to, counted after it has been
+    /// taken out, or `None` if it should stay where it is.
+    ///
+    /// `focused_index` is where the focused window is, if there is one.
+    fn new_index(self, index: usize, focused_index: Option<usize>) -> Option<usize> {
+        match self {
+            InsertPosition::Append => None,
+            InsertPosition::Prepend => Some(0),
+            InsertPosition::AfterFocused | InsertPosition::BeforeFocused => {
+                let focused_index = focused_index?;
+
+                // Taking the window out first moves everything after it back by one
+                let focused_index = if focused_index > index {
+                    focused_index - 1
+                } else {
+                    focused_index
+                };
+
+                match self {
+                    InsertPosition::AfterFocused => Some(focused_index + 1),
+                    _ => Some(focused_index),
+                }
+            }
+        }
+    }
+}
+
+impl State {
+    /// Move the newly mapped `window` to `position` in the layout order.
+    ///
+    /// Call this after window rules have set its tags. Positions relative to the focused window
+    /// fall back to appending when no other window on its tags has focus.
+    pub fn insert_new_window(&mut self, window: &WindowElement, position: InsertPosition) {
+        let Some(index) = self.windows.iter().position(|win| win == window) else {
+            return;
+        };
+
+        let focused_index = match position {
+            InsertPosition::Append | InsertPosition::Prepend => None,
+            InsertPosition::AfterFocused | InsertPosition::BeforeFocused => self
+                .focused_window_near(window)
+                .and_then(|focused| self.windows.iter().position(|win| win == &focused)),
+        };
+
+        let Some(new_index) = position.new_index(index, focused_index) else {
+            return;
+        };
+
+        let window = self.windows.remove(index);
+        self.windows.insert(new_index, window);
+    }
+
+    /// Get the most recently focused window other than `window` that shares a tag with it.
+    ///
+    /// The new window may already be at the top of the focus stack, so it's skipped.
+    fn focused_window_near(&self, window: &WindowElement) -> Option<WindowElement> {
+        let tags = window.with_state(|state| state.tags.clone());
+
+        self.focus_state
+            .focus_stack
+            .iter()
+            .rev()
+            .filter(|win| *win != window && win.alive() && !win.is_x11_override_redirect())
+            .find(|win| win.with_state(|state| state.tags.iter().any(|tag| tags.contains(tag))))
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Insert the new window `n` that was pushed to the end of `windows`, with `focused` focused.
+    fn insert(position: InsertPosition, windows: &str, focused: Option<char>) -> String {
+        let mut windows = windows.chars().chain(['n']).collect::<Vec<_>>();
+        let index = windows.len() - 1;
+        let focused_index = focused.and_then(|focused| windows.iter().position(|&w| w == focused));
+
+        if let Some(new_index) = position.new_index(index, focused_index) {
+            let window = windows.remove(index);
+            windows.insert(new_index, window);
+        }
+
+        windows.into_iter().collect()
+    }
+
+    #[test]
+    fn append() {
+        assert_eq!(insert(InsertPosition::Append, "abc", Some('b')), "abcn");
+        assert_eq!(insert(InsertPosition::Append, "", None), "n");
+    }
+
+    #[test]
+    fn prepend() {
+        assert_eq!(insert(InsertPosition::Prepend, "abc", Some('b')), "nabc");
+        assert_eq!(insert(InsertPosition::Prepend, "", None), "n");
+    }
+
+    #[test]
+    fn after_focused() {
+        assert_eq!(
+            insert(InsertPosition::AfterFocused, "abc", Some('a')),
+            "anbc"
+        );
+        assert_eq!(
+            insert(InsertPosition::AfterFocused, "abc", Some('b')),
+            "abnc"
+        );
+        assert_eq!(
+            insert(InsertPosition::AfterFocused, "abc", Some('c')),
+            "abcn"
+        );
+    }
+
+    #[test]
+    fn before_focused() {
+        assert_eq!(
+            insert(InsertPosition::BeforeFocused, "abc", Some('a')),
+            "nabc"
+        );
+        assert_eq!(
+            insert(InsertPosition::BeforeFocused, "abc", Some('b')),
+            "anbc"
+        );
+        assert_eq!(
+            insert(InsertPosition::BeforeFocused, "abc", Some('c')),
+            "abnc"
+        );
+    }
+
+    #[test]
+    fn relative_positions_append_without_focus() {
+        assert_eq!(insert(InsertPosition::AfterFocused, "abc", None), "abcn");
+        assert_eq!(insert(InsertPosition::BeforeFocused, "abc", None), "abcn");
+    }
+
+    #[test]
+    fn new_window_before_focused_window() {
+        // The new window isn't always last, e.g. when another window was mapped after it
+        // but before its rules were applied.
+        let new_index = InsertPosition::AfterFocused.new_index(0, Some(2));
+        assert_eq!(new_index, Some(2));
+        let new_index = InsertPosition::BeforeFocused.new_index(0, Some(2));
+        assert_eq!(new_index, Some(1));
+    }
+}
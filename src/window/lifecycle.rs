@@ -0,0 +1,57 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Telling the config when windows open and close.
+//!
+//! A window counts as opened once window rules have been applied to it, so the config sees
+//! the tags and output the rules gave it. It counts as closed when its toplevel or X11 window
+//! is destroyed. Unmapping an X11 window only hides it, so that isn't a close.
+//!
+//! Both carry the window's class and title. For closed windows these are the last ones it had,
+//! since it can't be asked anymore.
+
+use crate::{
+    api::msg::Args,
+    state::{State, WithState},
+};
+
+use super::WindowElement;
+
+impl State {
+    /// Tell the config that `window` opened.
+    pub fn signal_window_opened(&self, window: &WindowElement) {
+        if window.is_x11_override_redirect() {
+            return;
+        }
+
+        let (window_id, class, title) =
+            window.with_state(|state| (state.id, state.class.clone(), state.title.clone()));
+
+        tracing::debug!("Window {class:?} opened");
+        self.signal_window_props_changed(&self.config.window_opened_callback_ids, || {
+            Args::WindowOpened {
+                window_id,
+                class: class.clone(),
+                title: title.clone(),
+            }
+        });
+    }
+
+    /// Tell the config that `window` closed.
+    pub fn signal_window_closed(&self, window: &WindowElement) {
+        if window.is_x11_override_redirect() {
+            return;
+        }
+
+        let (window_id, class, title) =
+            window.with_state(|state| (state.id, state.class.clone(), state.title.clone()));
+
+        tracing::debug!("Window {class:?} closed");
+        self.signal_window_props_changed(&self.config.window_closed_callback_ids, || {
+            Args::WindowClosed {
+                window_id,
+                class: class.clone(),
+                title: title.clone(),
+            }
+        });
+    }
+}
@@ -0,0 +1,332 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Orphaned windows, which have no live tags.
+//!
+//! A window is only shown on the output its tags are on, so a window without live tags
+//! can't be seen or focused. This happens when a window opens on an output that has no tags
+//! yet, when its last tag is toggled off or removed, and when a config reload doesn't add its
+//! tags back. Tags on a disconnected output are still live, since they come back with it.
+//!
+//! An orphaned window remembers the output it was orphaned on. When tags are added to that
+//! output, the window adopts the output's active tags, or its first tag if none are active.
+//! Until then, orphaned windows can be found with `window::get_orphaned` and moved to a tag
+//! like any other window.
+
+use smithay::output::Output;
+
+use crate::{
+    output::OutputName,
+    state::{State, WithState},
+    tag::Tag,
+};
+
+use super::{placement::MoveOrigin, WindowElement};
+
+/// Get the name of the first output in `live_tags` that has one of `tags`.
+fn home(tags: &[Tag], live_tags: &[(OutputName, Vec<Tag>)]) -> Option<OutputName> {
+    tags.iter().find_map(|tag| {
+        live_tags
+            .iter()
+            .find(|(_, output_tags)| output_tags.contains(tag))
+            .map(|(output_name, _)| output_name.clone())
+    })
+}
+
+/// Get the tags a window placed on an output with `output_tags` gets: the active ones,
+/// or the first one if none are active.
+///
+/// This is empty if the output has no tags, which orphans the window.
+pub fn placement_tags(output_tags: &[Tag]) -> Vec<Tag> {
+    let active = output_tags
+        .iter()
+        .filter(|tag| tag.active())
+        .cloned()
+        .collect::<Vec<_>>();
+
+    if active.is_empty() {
+        output_tags.iter().take(1).cloned().collect()
+    } else {
+        active
+    }
+}
+
+impl State {
+    /// Get the tags of every output, connected or not, along with the output's name.
+    fn live_tags(&self) -> Vec<(OutputName, Vec<Tag>)> {
+        self.space
+            .outputs()
+            .map(|output| {
+                (
+                    OutputName(output.name()),
+                    output.with_state(|state| state.tags.clone()),
+                )
+            })
+            .chain(
+                self.config
+                    .connector_saved_states
+                    .iter()
+                    .map(|(output_name, saved_state)| {
+                        (output_name.clone(), saved_state.tags.clone())
+                    }),
+            )
+            .collect()
+    }
+
+    /// Get the name of the output `tag` is on, whether or not the output is connected.
+    ///
+    /// Returns `None` if the tag was removed.
+    pub fn tag_home(&self, tag: &Tag) -> Option<OutputName> {
+        home(std::slice::from_ref(tag), &self.live_tags())
+    }
+
+    /// Get the name of the output `window`'s tags are on, whether or not the output is
+    /// connected.
+    ///
+    /// Returns `None` if the window is orphaned.
+    pub fn window_home(&self, window: &WindowElement) -> Option<OutputName> {
+        home(
+            &window.with_state(|state| state.tags.clone()),
+            &self.live_tags(),
+        )
+    }
+
+    /// Returns whether `window` has no live tags.
+    ///
+    /// Override redirect windows don't have tags and are never orphaned.
+    pub fn is_orphaned(&self, window: &WindowElement) -> bool {
+        !window.is_x11_override_redirect() && self.window_home(window).is_none()
+    }
+
+    /// Get all orphaned windows.
+    pub fn orphaned_windows(&self) -> Vec<WindowElement> {
+        self.windows
+            .iter()
+            .filter(|win| self.is_orphaned(win))
+            .cloned()
+            .collect()
+    }
+
+    /// If `window` is orphaned, remember that it was orphaned on the output named `output_name`
+    /// so it can adopt that output's tags later.
+    pub fn mark_if_orphaned(&self, window: &WindowElement, output_name: Option<OutputName>) {
+        if self.is_orphaned(window) {
+            window.with_state(|state| state.orphaned_on = output_name);
+        }
+    }
+
+    /// Give windows orphaned on `output` the output's active tags, or its first tag
+    /// if none are active.
+    ///
+    /// This does nothing if the output has no tags.
+    pub fn adopt_orphans(&mut self, output: &Output) {
+        if output.with_state(|state| state.tags.is_empty()) {
+            return;
+        }
+
+        let output_name = OutputName(output.name());
+        let orphans = self
+            .windows
+            .iter()
+            .filter(|win| win.with_state(|state| state.orphaned_on.as_ref() == Some(&output_name)))
+            .filter(|win| self.is_orphaned(win))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if orphans.is_empty() {
+            return;
+        }
+
+        tracing::debug!(
+            "Output {} adopted {} orphaned windows",
+            output_name.0,
+            orphans.len()
+        );
+
+        for win in orphans.iter() {
+            win.place_on_output(output);
+            win.with_state(|state| state.orphaned_on = None);
+            self.window_moved(win, MoveOrigin::Layout);
+        }
+
+        self.update_windows(output);
+        self.schedule_render(output);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(name: &str) -> OutputName {
+        OutputName(name.to_string())
+    }
+
+    fn tag(name: &str, active: bool) -> Tag {
+        let tag = Tag::new(name.to_string());
+        tag.set_active(active);
+        tag
+    }
+
+    /// A window's tags and where it was orphaned, changed the way the compositor does.
+    #[derive(Debug)]
+    struct Window {
+        tags: Vec<Tag>,
+        orphaned_on: Option<OutputName>,
+    }
+
+    impl Window {
+        fn place(output_name: &str, live_tags: &[(OutputName, Vec<Tag>)]) -> Self {
+            let (output_name, output_tags) = live_tags
+                .iter()
+                .find(|(other, _)| other == &name(output_name))
+                .unwrap();
+            let tags = placement_tags(output_tags);
+            let orphaned_on = tags.is_empty().then(|| output_name.clone());
+            Self { tags, orphaned_on }
+        }
+
+        /// Take `tag` off this window, like toggling it off or removing it does.
+        ///
+        /// `home` is where the tag was before it was removed.
+        fn lose_tag(
+            &mut self,
+            tag: &Tag,
+            home: Option<OutputName>,
+            live_tags: &[(OutputName, Vec<Tag>)],
+        ) {
+            self.tags.retain(|tg| tg != tag);
+            if super::home(&self.tags, live_tags).is_none() {
+                self.orphaned_on = home;
+            }
+        }
+
+        fn adopt(&mut self, output_name: &OutputName, live_tags: &[(OutputName, Vec<Tag>)]) {
+            if self.orphaned_on.as_ref() != Some(output_name)
+                || super::home(&self.tags, live_tags).is_some()
+            {
+                return;
+            }
+            let (_, output_tags) = live_tags
+                .iter()
+                .find(|(other, _)| other == output_name)
+                .unwrap();
+            if output_tags.is_empty() {
+                return;
+            }
+            self.tags = placement_tags(output_tags);
+            self.orphaned_on = None;
+        }
+
+        /// Whether this window can be shown, or will be once its output gets tags.
+        fn reachable(&self, live_tags: &[(OutputName, Vec<Tag>)]) -> bool {
+            super::home(&self.tags, live_tags).is_some() || self.orphaned_on.is_some()
+        }
+    }
+
+    #[test]
+    fn placement_prefers_active_tags() {
+        let tags = [tag("1", false), tag("2", true), tag("3", true)];
+        assert_eq!(placement_tags(&tags), tags[1..].to_vec());
+    }
+
+    #[test]
+    fn placement_falls_back_to_first_tag() {
+        let tags = [tag("1", false), tag("2", false)];
+        assert_eq!(placement_tags(&tags), tags[..1].to_vec());
+    }
+
+    #[test]
+    fn placement_on_output_without_tags_orphans() {
+        let mut live_tags = vec![(name("DP-1"), vec![])];
+        let mut win = Window::place("DP-1", &live_tags);
+        assert!(win.tags.is_empty());
+        assert_eq!(win.orphaned_on, Some(name("DP-1")));
+
+        let new_tag = tag("1", true);
+        live_tags[0].1.push(new_tag.clone());
+        win.adopt(&name("DP-1"), &live_tags);
+        assert_eq!(win.tags, vec![new_tag]);
+        assert_eq!(win.orphaned_on, None);
+    }
+
+    #[test]
+    fn tags_on_disconnected_outputs_are_live() {
+        let saved = tag("1", true);
+        let live_tags = [
+            (name("DP-1"), vec![]),
+            (name("HDMI-A-1"), vec![saved.clone()]),
+        ];
+        assert_eq!(home(&[saved], &live_tags), Some(name("HDMI-A-1")));
+    }
+
+    #[test]
+    fn removed_tags_arent_live() {
+        let removed = tag("1", true);
+        let live_tags = [(name("DP-1"), vec![tag("2", true)])];
+        assert_eq!(home(&[removed], &live_tags), None);
+    }
+
+    #[test]
+    fn adoption_only_happens_on_the_orphaning_output() {
+        let live_tags = [
+            (name("DP-1"), vec![tag("1", true)]),
+            (name("HDMI-A-1"), vec![]),
+        ];
+        let mut win = Window::place("HDMI-A-1", &live_tags);
+        win.adopt(&name("DP-1"), &live_tags);
+        assert!(win.tags.is_empty());
+        assert_eq!(win.orphaned_on, Some(name("HDMI-A-1")));
+    }
+
+    #[test]
+    fn no_order_of_tag_removals_makes_a_window_unreachable() {
+        // Every order of taking tags off a window spread over two outputs,
+        // by toggling them off the window or removing them entirely
+        let orders = [
+            [0, 1, 2],
+            [0, 2, 1],
+            [1, 0, 2],
+            [1, 2, 0],
+            [2, 0, 1],
+            [2, 1, 0],
+        ];
+        for order in orders {
+            for remove_from_output in [false, true] {
+                let tags = [tag("1", true), tag("2", false), tag("3", true)];
+                let mut live_tags = vec![
+                    (name("DP-1"), vec![tags[0].clone(), tags[1].clone()]),
+                    (name("HDMI-A-1"), vec![tags[2].clone()]),
+                ];
+                let mut win = Window {
+                    tags: tags.to_vec(),
+                    orphaned_on: None,
+                };
+
+                for i in order {
+                    let removed = &tags[i];
+                    let tag_home = home(std::slice::from_ref(removed), &live_tags);
+                    if remove_from_output {
+                        for (_, output_tags) in live_tags.iter_mut() {
+                            output_tags.retain(|tg| tg != removed);
+                        }
+                    }
+                    win.lose_tag(removed, tag_home, &live_tags);
+                    assert!(win.reachable(&live_tags), "{win:?} after removing {i}");
+                }
+
+                // The window ends up orphaned on the output of the last tag it lost,
+                // and comes back once that output gets a tag
+                let output_name = win.orphaned_on.clone().unwrap();
+                let new_tag = tag("4", false);
+                live_tags
+                    .iter_mut()
+                    .find(|(other, _)| other == &output_name)
+                    .unwrap()
+                    .1
+                    .push(new_tag.clone());
+                win.adopt(&output_name, &live_tags);
+                assert!(home(&win.tags, &live_tags).is_some(), "{win:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,162 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Placement memory, which remembers where you put windows of each class.
+//!
+//! When it's on, moving a window to another tag or output records the window's class along
+//! with the output and tag it ended up on. New windows of that class start there, unless a
+//! window rule says otherwise. Only moves you make count. Window rules placing new windows
+//! and moves Pinnacle makes on its own, like auto tidy, aren't recorded.
+//!
+//! Placements are saved to `$XDG_STATE_HOME/pinnacle/window_placements.toml`, so they're kept
+//! across restarts. Outputs are remembered by connector name.
+
+use std::collections::HashMap;
+
+use anyhow::Context;
+
+use crate::{
+    output::OutputName,
+    state::{State, WithState},
+    XDG_BASE_DIRS,
+};
+
+use super::WindowElement;
+
+const PLACEMENTS_FILE: &str = "window_placements.toml";
+
+/// What moved a window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveOrigin {
+    /// The user moved it, through the API or by dragging it.
+    User,
+    /// Pinnacle moved it on its own, like when tidying or adopting orphaned windows.
+    Layout,
+}
+
+#[derive(Debug, Default)]
+pub struct PlacementMemory {
+    /// Whether moves are recorded and applied to new windows.
+    pub enabled: bool,
+    /// Where windows of each class were last moved to.
+    placements: HashMap<String, Placement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+struct Placement {
+    output_name: String,
+    tag_name: String,
+}
+
+impl PlacementMemory {
+    /// Load saved placements from the state dir.
+    ///
+    /// Placement memory starts off, and starts empty if there's nothing saved.
+    pub fn load() -> Self {
+        let placements = XDG_BASE_DIRS
+            .find_state_file(PLACEMENTS_FILE)
+            .map(|path| {
+                std::fs::read_to_string(path)
+                    .context("Failed to read saved window placements")
+                    .and_then(|placements| {
+                        toml::from_str(&placements)
+                            .context("Failed to deserialize saved window placements")
+                    })
+                    .unwrap_or_else(|err| {
+                        tracing::warn!("{err:?}");
+                        HashMap::new()
+                    })
+            })
+            .unwrap_or_default();
+
+        Self {
+            enabled: false,
+            placements,
+        }
+    }
+
+    fn save(&self) {
+        let result = XDG_BASE_DIRS
+            .place_state_file(PLACEMENTS_FILE)
+            .context("Failed to create state dir")
+            .and_then(|path| {
+                let placements = toml::to_string(&self.placements)
+                    .context("Failed to serialize window placements")?;
+                std::fs::write(path, placements).context("Failed to write window placements")
+            });
+
+        if let Err(err) = result {
+            tracing::error!("{err:?}");
+        }
+    }
+}
+
+impl State {
+    /// Remember where `window` is now if `origin` is the user and placement memory is on.
+    ///
+    /// Call this after changing a window's tags or output.
+    pub fn window_moved(&mut self, window: &WindowElement, origin: MoveOrigin) {
+        if origin != MoveOrigin::User || !self.placement_memory.enabled {
+            return;
+        }
+
+        let Some(class) = window.class() else { return };
+        let Some(tag) = window.with_state(|state| state.tags.first().cloned()) else {
+            return;
+        };
+        let Some(output) = tag.output(self) else { return };
+
+        let placement = Placement {
+            output_name: output.name(),
+            tag_name: tag.name(),
+        };
+
+        if self.placement_memory.placements.get(&class) == Some(&placement) {
+            return;
+        }
+
+        tracing::debug!(
+            "Remembering {class} on tag {} of {}",
+            placement.tag_name,
+            placement.output_name
+        );
+        self.placement_memory.placements.insert(class, placement);
+        self.placement_memory.save();
+    }
+
+    /// Forget where windows of `class` were placed.
+    pub fn forget_window_placement(&mut self, class: &str) {
+        if self.placement_memory.placements.remove(class).is_some() {
+            self.placement_memory.save();
+        }
+    }
+
+    /// Put `window` where windows of its class were last moved to, if placement memory is on.
+    ///
+    /// Nothing happens if that output isn't connected or doesn't have a tag with that name.
+    pub fn apply_remembered_placement(&self, window: &WindowElement) {
+        if !self.placement_memory.enabled {
+            return;
+        }
+
+        let Some(class) = window.class() else { return };
+        let Some(placement) = self.placement_memory.placements.get(&class) else {
+            return;
+        };
+
+        let Some(output) = OutputName(placement.output_name.clone()).output(self) else {
+            return;
+        };
+        let Some(tag) = output.with_state(|state| {
+            state
+                .tags
+                .iter()
+                .find(|tag| tag.name() == placement.tag_name)
+                .cloned()
+        }) else {
+            return;
+        };
+
+        tracing::debug!("Placing {class} where it was last moved");
+        window.with_state(|state| state.tags = vec![tag]);
+    }
+}
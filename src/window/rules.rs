@@ -12,7 +12,11 @@ use super::WindowElement;
 
 use std::num::NonZeroU32;
 
-use crate::{output::OutputName, tag::TagId, window::window_state::FullscreenOrMaximized};
+use crate::{
+    output::OutputName,
+    tag::TagId,
+    window::{insert_position::InsertPosition, window_state::FullscreenOrMaximized},
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct WindowRuleCondition {
@@ -31,6 +35,12 @@ pub struct WindowRuleCondition {
     /// This condition is met when the tag matches.
     #[serde(default)]
     tag: Option<Vec<TagId>>,
+    /// This condition is met when the window is in the group with this id.
+    #[serde(default)]
+    group: Option<Vec<u32>>,
+    /// This condition is met when the window's sandbox gave it this app id.
+    #[serde(default)]
+    sandbox_app_id: Option<Vec<String>>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -54,6 +64,8 @@ impl WindowRuleCondition {
             class,
             title,
             tag,
+            group,
+            sandbox_app_id,
         } = self;
 
         match all_or_any {
@@ -92,9 +104,25 @@ impl WindowRuleCondition {
                 } else {
                     true
                 };
+                let groups = if let Some(groups) = group {
+                    let window_group = state.window_group_id(window);
+                    groups.iter().all(|group| window_group == Some(*group))
+                } else {
+                    true
+                };
+                let sandbox_app_ids = if let Some(app_ids) = sandbox_app_id {
+                    let window_app_id = state.window_sandbox_app_id(window);
+                    app_ids
+                        .iter()
+                        .all(|app_id| window_app_id.as_ref() == Some(app_id))
+                } else {
+                    true
+                };
 
-                tracing::debug!("{cond_all} {cond_any} {classes} {titles} {tags}");
-                cond_all && cond_any && classes && titles && tags
+                tracing::debug!(
+                    "{cond_all} {cond_any} {classes} {titles} {tags} {groups} {sandbox_app_ids}"
+                );
+                cond_all && cond_any && classes && titles && tags && groups && sandbox_app_ids
             }
             AllOrAny::Any => {
                 let cond_any = if let Some(cond_any) = cond_any {
@@ -131,7 +159,21 @@ impl WindowRuleCondition {
                 } else {
                     false
                 };
-                cond_all || cond_any || classes || titles || tags
+                let groups = if let Some(groups) = group {
+                    let window_group = state.window_group_id(window);
+                    groups.iter().any(|group| window_group == Some(*group))
+                } else {
+                    false
+                };
+                let sandbox_app_ids = if let Some(app_ids) = sandbox_app_id {
+                    let window_app_id = state.window_sandbox_app_id(window);
+                    app_ids
+                        .iter()
+                        .any(|app_id| window_app_id.as_ref() == Some(app_id))
+                } else {
+                    false
+                };
+                cond_all || cond_any || classes || titles || tags || groups || sandbox_app_ids
             }
         }
     }
@@ -158,6 +200,21 @@ pub struct WindowRule {
     /// when set to floating.
     #[serde(default)]
     pub location: Option<(i32, i32)>,
+    /// Make tiled layouts round the window's size down to its resize increments.
+    #[serde(default)]
+    pub snap_to_increments: Option<bool>,
+    /// Deny any requests from the client to fullscreen the window.
+    #[serde(default)]
+    pub deny_fullscreen: Option<bool>,
+    /// Make the window follow the active tags on its output.
+    #[serde(default)]
+    pub follow_active_tag: Option<bool>,
+    /// Set where the window goes in the layout.
+    #[serde(default)]
+    pub insert_position: Option<InsertPosition>,
+    /// Exempt the window from the background throttle.
+    #[serde(default)]
+    pub never_throttle: Option<bool>,
 }
 
 // TODO: just skip serializing fields on the other FloatingOrTiled
@@ -170,6 +227,17 @@ pub enum FloatingOrTiled {
 impl State {
     pub fn apply_window_rules(&mut self, window: &WindowElement) {
         tracing::debug!("Applying window rules");
+
+        // Remembered placements and the previous session go under window rules
+        self.apply_remembered_placement(window);
+        let restored = self.apply_session_restore(window);
+        // X11 windows like dialogs float before any rules apply
+        let keep_floating_or_tiled =
+            restored || window.with_state(|state| state.floating_or_tiled.is_floating());
+
+        let mut insert_position = self.insert_position;
+        let mut rule_floating_or_tiled = None;
+
         for (cond, rule) in self.config.window_rules.iter() {
             if cond.is_met(self, window) {
                 let WindowRule {
@@ -179,6 +247,11 @@ impl State {
                     fullscreen_or_maximized,
                     size,
                     location,
+                    snap_to_increments,
+                    deny_fullscreen,
+                    follow_active_tag,
+                    insert_position: rule_insert_position,
+                    never_throttle,
                 } = rule;
 
                 // TODO: If both `output` and `tags` are specified, `tags` will apply over
@@ -186,10 +259,7 @@ impl State {
 
                 if let Some(output_name) = output {
                     if let Some(output) = output_name.output(self) {
-                        let tags = output
-                            .with_state(|state| state.focused_tags().cloned().collect::<Vec<_>>());
-
-                        window.with_state(|state| state.tags = tags.clone());
+                        window.place_on_output(&output);
                     }
                 }
 
@@ -199,26 +269,19 @@ impl State {
                         .filter_map(|tag_id| tag_id.tag(self))
                         .collect::<Vec<_>>();
 
-                    window.with_state(|state| state.tags = tags.clone());
+                    // A rule whose tags were all removed shouldn't orphan the window
+                    if !tags.is_empty() {
+                        window.with_state(|state| state.tags = tags.clone());
+                    }
                 }
 
                 if let Some(floating_or_tiled) = floating_or_tiled {
-                    match floating_or_tiled {
-                        FloatingOrTiled::Floating => {
-                            if window.with_state(|state| state.floating_or_tiled.is_tiled()) {
-                                window.toggle_floating();
-                            }
-                        }
-                        FloatingOrTiled::Tiled => {
-                            if window.with_state(|state| state.floating_or_tiled.is_floating()) {
-                                window.toggle_floating();
-                            }
-                        }
-                    }
+                    window.set_floating(matches!(floating_or_tiled, FloatingOrTiled::Floating));
+                    rule_floating_or_tiled = Some(*floating_or_tiled);
                 }
 
                 if let Some(fs_or_max) = fullscreen_or_maximized {
-                    window.with_state(|state| state.fullscreen_or_maximized = *fs_or_max);
+                    window.set_fullscreen_or_maximized(*fs_or_max);
                 }
 
                 if let Some((w, h)) = size {
@@ -245,6 +308,26 @@ impl State {
                     }
                 }
 
+                if let Some(snap_to_increments) = snap_to_increments {
+                    window.with_state(|state| state.snap_to_increments = *snap_to_increments);
+                }
+
+                if let Some(deny_fullscreen) = deny_fullscreen {
+                    window.with_state(|state| state.deny_fullscreen = *deny_fullscreen);
+                }
+
+                if let Some(follow_active_tag) = follow_active_tag {
+                    window.with_state(|state| state.follow_active_tag = *follow_active_tag);
+                }
+
+                if let Some(never_throttle) = never_throttle {
+                    window.with_state(|state| state.never_throttle = *never_throttle);
+                }
+
+                if let Some(rule_insert_position) = rule_insert_position {
+                    insert_position = *rule_insert_position;
+                }
+
                 if let Some(loc) = location {
                     match window.with_state(|state| state.floating_or_tiled) {
                         window_state::FloatingOrTiled::Floating(mut rect) => {
@@ -272,5 +355,13 @@ impl State {
                 }
             }
         }
+
+        // Rules can change the window's tags and output, so these go after them
+        self.apply_default_floating_or_tiled(
+            window,
+            rule_floating_or_tiled,
+            keep_floating_or_tiled,
+        );
+        self.insert_new_window(window, insert_position);
     }
 }
@@ -0,0 +1,250 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Non-rectangular X11 windows.
+//!
+//! X11 clients like xeyes use the SHAPE extension to cut their windows into other shapes.
+//! Xwayland still gives them rectangular surfaces, so Pinnacle asks the X server for the
+//! shapes itself over its own connection to Xwayland.
+//!
+//! A window's bounding shape is the part of it that exists, and its input shape is the part
+//! that takes pointer input. The pointer only goes to a window where both shapes contain it,
+//! so clicks on cut out parts go to whatever is underneath. The X server sends a
+//! `ShapeNotify` event whenever a window's shape changes, and the shape is queried again.
+//!
+//! Rendering isn't clipped to the bounding shape. Clients with an ARGB visual draw the cut out
+//! parts transparent themselves, but other clients will still show them.
+
+use std::os::fd::AsFd;
+
+use smithay::{
+    reexports::{
+        calloop::{generic::Generic, Interest, Mode, PostAction, RegistrationToken},
+        x11rb::{
+            connection::{Connection, RequestConnection},
+            errors::ReplyError,
+            protocol::{
+                shape::{self, ConnectionExt as _, SK},
+                xproto::{ConnectionExt as _, Window},
+                Event,
+            },
+            rust_connection::RustConnection,
+        },
+    },
+    utils::{Logical, Point, Rectangle},
+};
+
+use crate::state::{State, WithState};
+
+use super::WindowElement;
+
+/// Pinnacle's own connection to Xwayland, used to keep track of window shapes.
+pub struct X11ShapeState {
+    connection: RustConnection,
+    token: RegistrationToken,
+}
+
+/// The shape of an X11 window, relative to its top left corner.
+///
+/// `None` means that shape isn't set, so it's the whole window.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct X11Shape {
+    bounding: Option<Vec<Rectangle<i32, Logical>>>,
+    input: Option<Vec<Rectangle<i32, Logical>>>,
+}
+
+impl X11Shape {
+    /// Whether `point`, relative to the window's top left corner, is in both of the
+    /// window's shapes.
+    pub fn takes_input_at(&self, point: Point<f64, Logical>) -> bool {
+        let contains = |rects: &Option<Vec<Rectangle<i32, Logical>>>| {
+            rects.as_ref().map_or(true, |rects| {
+                rects.iter().any(|rect| rect.to_f64().contains(point))
+            })
+        };
+
+        contains(&self.bounding) && contains(&self.input)
+    }
+}
+
+impl State {
+    /// Connect to Xwayland on `display` to keep track of window shapes.
+    ///
+    /// If this fails, every X11 window is treated as rectangular.
+    pub fn connect_x11_shape(&mut self, display: u32) {
+        let (connection, _) = match RustConnection::connect(Some(&format!(":{display}"))) {
+            Ok(connection) => connection,
+            Err(err) => {
+                tracing::warn!("Failed to connect to Xwayland to track window shapes: {err}");
+                return;
+            }
+        };
+
+        match connection.extension_information(shape::X11_EXTENSION_NAME) {
+            Ok(Some(_)) => (),
+            Ok(None) => {
+                tracing::warn!("Xwayland doesn't have the SHAPE extension");
+                return;
+            }
+            Err(err) => {
+                tracing::warn!("Failed to query Xwayland for the SHAPE extension: {err}");
+                return;
+            }
+        }
+
+        let fd = match connection.stream().as_fd().try_clone_to_owned() {
+            Ok(fd) => fd,
+            Err(err) => {
+                tracing::warn!("Failed to duplicate Xwayland connection fd: {err}");
+                return;
+            }
+        };
+
+        let token = self.loop_handle.insert_source(
+            Generic::new(fd, Interest::READ, Mode::Level),
+            |_, _, data| {
+                data.state.process_x11_shape_events();
+                Ok(PostAction::Continue)
+            },
+        );
+
+        match token {
+            Ok(token) => self.x11_shape = Some(X11ShapeState { connection, token }),
+            Err(err) => tracing::warn!("Failed to insert Xwayland shape source: {err}"),
+        }
+    }
+
+    /// Drop the connection to Xwayland after it exits.
+    pub fn disconnect_x11_shape(&mut self) {
+        if let Some(shape_state) = self.x11_shape.take() {
+            self.loop_handle.remove(shape_state.token);
+        }
+    }
+
+    /// Start keeping track of the shape of the newly mapped X11 `window`.
+    pub fn watch_x11_shape(&mut self, window: &WindowElement) {
+        let (Some(shape_state), Some(window_id)) = (self.x11_shape.as_ref(), x11_window_id(window))
+        else {
+            return;
+        };
+
+        if let Err(err) = shape_state
+            .connection
+            .shape_select_input(window_id, true)
+            .map(|cookie| cookie.ignore_error())
+        {
+            tracing::debug!("Failed to select shape events on X11 window: {err}");
+            return;
+        }
+
+        self.update_x11_shape(window);
+
+        // Waiting for the replies above may have read events that the event source
+        // won't wake up for
+        self.process_x11_shape_events();
+    }
+
+    /// Query the X server for `window`'s shape.
+    fn update_x11_shape(&self, window: &WindowElement) {
+        let (Some(shape_state), Some(window_id)) = (self.x11_shape.as_ref(), x11_window_id(window))
+        else {
+            return;
+        };
+
+        match query_shape(&shape_state.connection, window_id) {
+            Ok(shape) => {
+                window.with_state(|state| {
+                    if state.x11_shape != shape {
+                        tracing::debug!("X11 window shape changed to {shape:?}");
+                        state.x11_shape = shape;
+                    }
+                });
+            }
+            // The window may have been destroyed in the meantime
+            Err(err) => tracing::debug!("Failed to query X11 window shape: {err}"),
+        }
+    }
+
+    fn process_x11_shape_events(&mut self) {
+        loop {
+            let Some(shape_state) = self.x11_shape.as_ref() else {
+                return;
+            };
+
+            let event = match shape_state.connection.poll_for_event() {
+                Ok(Some(event)) => event,
+                Ok(None) => return,
+                Err(err) => {
+                    tracing::warn!("Lost the Xwayland connection for window shapes: {err}");
+                    self.disconnect_x11_shape();
+                    return;
+                }
+            };
+
+            let Event::ShapeNotify(event) = event else {
+                continue;
+            };
+
+            let window = self
+                .windows
+                .iter()
+                .find(|win| x11_window_id(win) == Some(event.affected_window))
+                .cloned();
+
+            if let Some(window) = window {
+                self.update_x11_shape(&window);
+            }
+        }
+    }
+}
+
+fn x11_window_id(window: &WindowElement) -> Option<Window> {
+    match window {
+        WindowElement::Wayland(_) => None,
+        WindowElement::X11(surface) | WindowElement::X11OverrideRedirect(surface) => {
+            Some(surface.window_id())
+        }
+    }
+}
+
+fn query_shape(connection: &RustConnection, window: Window) -> Result<X11Shape, ReplyError> {
+    let extents = connection.shape_query_extents(window)?.reply()?;
+
+    let bounding = if extents.bounding_shaped {
+        Some(shape_rectangles(connection, window, SK::BOUNDING)?)
+    } else {
+        None
+    };
+
+    // Whether the input shape is set isn't in the extents, but an unset one is
+    // a single rectangle covering the whole window
+    let input = shape_rectangles(connection, window, SK::INPUT)?;
+    let geometry = connection
+        .get_geometry(window)?
+        .reply()
+        .map(|geo| Rectangle::from_loc_and_size((0, 0), (geo.width as i32, geo.height as i32)))?;
+    let input = match input.as_slice() {
+        [rect] if rect.contains_rect(geometry) => None,
+        _ => Some(input),
+    };
+
+    Ok(X11Shape { bounding, input })
+}
+
+fn shape_rectangles(
+    connection: &RustConnection,
+    window: Window,
+    kind: SK,
+) -> Result<Vec<Rectangle<i32, Logical>>, ReplyError> {
+    let reply = connection.shape_get_rectangles(window, kind)?.reply()?;
+
+    Ok(reply
+        .rectangles
+        .into_iter()
+        .map(|rect| {
+            Rectangle::from_loc_and_size(
+                (rect.x as i32, rect.y as i32),
+                (rect.width as i32, rect.height as i32),
+            )
+        })
+        .collect())
+}
@@ -0,0 +1,294 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Client requests to fullscreen, maximize, or minimize windows.
+//!
+//! These requests go through the window's `deny_fullscreen` rule and then through any
+//! config callbacks registered with a matching condition. A callback gets a token it
+//! uses to send back its decision. If no decision arrives in time, the request is allowed.
+
+use std::time::Duration;
+
+use smithay::{
+    output::Output,
+    reexports::{
+        calloop::{
+            timer::{TimeoutAction, Timer},
+            RegistrationToken,
+        },
+        wayland_server::protocol::wl_output::WlOutput,
+    },
+    utils::IsAlive,
+};
+
+use crate::{
+    api::msg::{Args, CallbackId, OutgoingMsg},
+    state::{State, WithState},
+};
+
+use super::{rules::WindowRuleCondition, window_state::FullscreenOrMaximized, WindowElement};
+
+/// How long to wait for the config to decide on a request before allowing it.
+const DECISION_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// A state a client requested its window be put in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum RequestedState {
+    Fullscreen,
+    Maximized,
+    Minimized,
+}
+
+/// What the config decided to do with a state request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum StateRequestDecision {
+    /// Put the window in the requested state.
+    Allow,
+    /// Keep the window in its current state.
+    Deny,
+    /// Put the window in this state instead.
+    Modify { state: FullscreenOrMaximized },
+}
+
+/// A config callback that decides on state requests from windows that meet `cond`.
+#[derive(Debug)]
+pub struct StateRequestHandler {
+    pub cond: WindowRuleCondition,
+    pub callback_id: CallbackId,
+}
+
+/// A state request waiting on a decision from the config.
+#[derive(Debug)]
+pub struct PendingStateRequest {
+    /// The token the config will respond with.
+    pub token: u32,
+    pub window: WindowElement,
+    pub requested: RequestedState,
+    /// The output a fullscreen request asked for.
+    pub fullscreen_output: Option<WlOutput>,
+    /// The timer that will allow the request if the config doesn't respond.
+    timeout_token: RegistrationToken,
+}
+
+impl State {
+    /// Handle a client's request to put `window` in the `requested` state.
+    ///
+    /// `fullscreen_output` is the output a Wayland client asked to be fullscreened on.
+    /// If the window ends up fullscreen, it's moved there.
+    pub fn request_window_state(
+        &mut self,
+        window: &WindowElement,
+        requested: RequestedState,
+        fullscreen_output: Option<WlOutput>,
+    ) {
+        if requested == RequestedState::Fullscreen
+            && window.with_state(|state| state.deny_fullscreen)
+        {
+            tracing::debug!("Denying fullscreen request because of window rule");
+            self.apply_state_decision(window, requested, None, StateRequestDecision::Deny);
+            return;
+        }
+
+        let callback_id = self
+            .config
+            .state_request_handlers
+            .iter()
+            .find(|handler| handler.cond.is_met(self, window))
+            .map(|handler| handler.callback_id);
+
+        let (Some(callback_id), Some(stream)) = (callback_id, self.api_state.stream.clone()) else {
+            self.apply_state_decision(
+                window,
+                requested,
+                fullscreen_output,
+                StateRequestDecision::Allow,
+            );
+            return;
+        };
+
+        let token = self.api_state.next_state_request_token;
+        self.api_state.next_state_request_token = token.wrapping_add(1);
+
+        let timeout_token = self.loop_handle.insert_source(
+            Timer::from_duration(DECISION_TIMEOUT),
+            move |_, _, data| {
+                tracing::debug!("State request {token} timed out, allowing");
+                data.state
+                    .decide_state_request(token, StateRequestDecision::Allow);
+                TimeoutAction::Drop
+            },
+        );
+
+        let timeout_token = match timeout_token {
+            Ok(token) => token,
+            Err(err) => {
+                tracing::error!("Failed to insert state request timer: {err}");
+                self.apply_state_decision(
+                    window,
+                    requested,
+                    fullscreen_output,
+                    StateRequestDecision::Allow,
+                );
+                return;
+            }
+        };
+
+        self.api_state
+            .pending_state_requests
+            .push(PendingStateRequest {
+                token,
+                window: window.clone(),
+                requested,
+                fullscreen_output,
+                timeout_token,
+            });
+
+        let window_id = window.with_state(|state| state.id);
+
+        let sent = crate::api::send_to_client(
+            &mut stream.lock().expect("Couldn't lock stream"),
+            &OutgoingMsg::call_callback(
+                callback_id,
+                Some(Args::StateRequest {
+                    window_id,
+                    token,
+                    state: requested,
+                }),
+                &self.api_state.callback_serial,
+            ),
+        );
+
+        // A config that can't be asked doesn't get to decide, so don't leave the client
+        // waiting on the timeout
+        if let Err(err) = sent {
+            tracing::warn!("Failed to send state request to config, allowing: {err}");
+            self.decide_state_request(token, StateRequestDecision::Allow);
+        }
+    }
+
+    /// Apply the config's `decision` on the pending request with `token`.
+    ///
+    /// This does nothing if the request already timed out.
+    pub fn decide_state_request(&mut self, token: u32, decision: StateRequestDecision) {
+        let Some(index) = self
+            .api_state
+            .pending_state_requests
+            .iter()
+            .position(|req| req.token == token)
+        else {
+            return;
+        };
+
+        let pending = self.api_state.pending_state_requests.remove(index);
+        self.loop_handle.remove(pending.timeout_token);
+
+        if pending.window.alive() {
+            self.apply_state_decision(
+                &pending.window,
+                pending.requested,
+                pending.fullscreen_output,
+                decision,
+            );
+        }
+    }
+
+    /// Allow all pending requests.
+    ///
+    /// This should be called when the config goes away so no request is left hanging.
+    pub fn allow_pending_state_requests(&mut self) {
+        let pending = std::mem::take(&mut self.api_state.pending_state_requests);
+        for req in pending {
+            self.loop_handle.remove(req.timeout_token);
+            if req.window.alive() {
+                self.apply_state_decision(
+                    &req.window,
+                    req.requested,
+                    req.fullscreen_output,
+                    StateRequestDecision::Allow,
+                );
+            }
+        }
+    }
+
+    fn apply_state_decision(
+        &mut self,
+        window: &WindowElement,
+        requested: RequestedState,
+        fullscreen_output: Option<WlOutput>,
+        decision: StateRequestDecision,
+    ) {
+        let new_state = match (decision, requested) {
+            (StateRequestDecision::Allow, RequestedState::Fullscreen) => {
+                Some(FullscreenOrMaximized::Fullscreen)
+            }
+            (StateRequestDecision::Allow, RequestedState::Maximized) => {
+                Some(FullscreenOrMaximized::Maximized)
+            }
+            // TODO: minimization
+            (StateRequestDecision::Allow, RequestedState::Minimized) => None,
+            (StateRequestDecision::Deny, _) => None,
+            (StateRequestDecision::Modify { state }, _) => Some(state),
+        };
+
+        let current_state = window.with_state(|state| state.fullscreen_or_maximized);
+
+        match new_state {
+            Some(new_state) if new_state != current_state => {
+                let old_output = window.output(self);
+
+                window.set_fullscreen_or_maximized(new_state);
+
+                if new_state.is_fullscreen() {
+                    if let Some(wl_output) = fullscreen_output {
+                        self.fullscreen_on_output(window, wl_output);
+                    }
+                }
+
+                let new_output = window.output(self);
+                if let Some(output) = old_output
+                    .as_ref()
+                    .filter(|&old| Some(old) != new_output.as_ref())
+                {
+                    self.update_windows(output);
+                }
+                if let Some(output) = new_output {
+                    self.update_windows(&output);
+                }
+            }
+            _ => {
+                // Clients may wait for a configure after a request, so confirm the current state.
+                match window {
+                    WindowElement::Wayland(win) => {
+                        win.toplevel().send_configure();
+                    }
+                    WindowElement::X11(surface) => {
+                        let result = surface
+                            .set_fullscreen(current_state.is_fullscreen())
+                            .and_then(|_| surface.set_maximized(current_state.is_maximized()));
+                        if let Err(err) = result {
+                            tracing::error!("Failed to confirm x11 window state: {err}");
+                        }
+                    }
+                    WindowElement::X11OverrideRedirect(_) => (),
+                }
+            }
+        }
+    }
+
+    /// Move a window that was just fullscreened to the output its client asked for.
+    fn fullscreen_on_output(&mut self, window: &WindowElement, wl_output: WlOutput) {
+        let Some(output) = Output::from_resource(&wl_output) else {
+            return;
+        };
+
+        if window.output(self).as_ref() != Some(&output) {
+            tracing::debug!("Fullscreening window on requested output {}", output.name());
+            window.place_on_output(&output);
+        }
+
+        if let WindowElement::Wayland(win) = window {
+            win.toplevel().with_pending_state(|state| {
+                state.fullscreen_output = Some(wl_output);
+            });
+        }
+    }
+}
@@ -0,0 +1,148 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Swapping the focused window with its neighbor in a direction.
+//!
+//! The neighbor is the window whose center is closest to the focused window's center in that
+//! direction, with windows further off to the side counting as further away. Windows on the
+//! active tags of every output are considered, so the neighbor can be on another output.
+//! Fullscreen and maximized windows are skipped.
+//!
+//! The two windows trade places in the order windows are laid out in, so tiled windows trade
+//! tiles. If either is floating, they also trade whether they're floating and where, so
+//! floating windows trade geometries. A window swapped with one on another output also trades
+//! tags with it, moving to the focused tag of that output.
+
+use smithay::utils::{Logical, Point, Rectangle};
+
+use crate::state::{State, WithState};
+
+use super::WindowElement;
+
+/// A direction to look for a neighboring window in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl Direction {
+    /// How far `to` is from `from` in this direction and how far it is off to the side.
+    ///
+    /// The first distance is negative if `to` isn't in this direction.
+    fn distances(&self, from: Point<i32, Logical>, to: Point<i32, Logical>) -> (i32, i32) {
+        let delta = to - from;
+        match self {
+            Direction::Left => (-delta.x, delta.y.abs()),
+            Direction::Right => (delta.x, delta.y.abs()),
+            Direction::Up => (-delta.y, delta.x.abs()),
+            Direction::Down => (delta.y, delta.x.abs()),
+        }
+    }
+}
+
+impl State {
+    /// Swap the focused window with its neighbor in `direction`.
+    pub fn swap_in_direction(&mut self, direction: Direction) {
+        let Some(output) = self.focus_state.focused_output.clone() else {
+            return;
+        };
+        let Some(focused) = self.focused_window(&output) else {
+            return;
+        };
+        if !focused.with_state(|state| state.fullscreen_or_maximized.is_neither()) {
+            return;
+        }
+        let Some(neighbor) = self.neighbor_in_direction(&focused, direction) else {
+            return;
+        };
+        let (Some(focused_output), Some(neighbor_output)) =
+            (focused.output(self), neighbor.output(self))
+        else {
+            return;
+        };
+
+        tracing::debug!(
+            "Swapping {:?} with {:?} to the {direction:?}",
+            focused.class(),
+            neighbor.class()
+        );
+
+        if focused_output != neighbor_output {
+            let (Some(focused_tag), Some(neighbor_tag)) = (
+                focused_output.with_state(|state| state.focused_tags().next().cloned()),
+                neighbor_output.with_state(|state| state.focused_tags().next().cloned()),
+            ) else {
+                return;
+            };
+
+            focused.with_state(|state| {
+                state.tags = vec![neighbor_tag];
+                state.follow_active_tag = false;
+            });
+            neighbor.with_state(|state| {
+                state.tags = vec![focused_tag];
+                state.follow_active_tag = false;
+            });
+        }
+
+        let (focused_fot, neighbor_fot) = (
+            focused.with_state(|state| state.floating_or_tiled),
+            neighbor.with_state(|state| state.floating_or_tiled),
+        );
+        if focused_fot.is_floating() || neighbor_fot.is_floating() {
+            focused.with_state(|state| state.floating_or_tiled = neighbor_fot);
+            neighbor.with_state(|state| state.floating_or_tiled = focused_fot);
+        }
+
+        let focused_index = self.windows.iter().position(|win| win == &focused);
+        let neighbor_index = self.windows.iter().position(|win| win == &neighbor);
+        if let (Some(focused_index), Some(neighbor_index)) = (focused_index, neighbor_index) {
+            self.windows.swap(focused_index, neighbor_index);
+        }
+
+        self.update_windows(&focused_output);
+        self.schedule_render(&focused_output);
+
+        if neighbor_output != focused_output {
+            self.update_windows(&neighbor_output);
+            self.schedule_render(&neighbor_output);
+
+            // Keep the swapped window focused on its new output
+            self.focus_state.set_focus(focused.clone());
+            self.update_focus(&neighbor_output);
+        }
+    }
+
+    /// Get the window closest to `window` in `direction` on the active tags of any output.
+    fn neighbor_in_direction(
+        &self,
+        window: &WindowElement,
+        direction: Direction,
+    ) -> Option<WindowElement> {
+        let origin = center(self.space.element_geometry(window)?);
+        let outputs = self.space.outputs().collect::<Vec<_>>();
+
+        self.windows
+            .iter()
+            .filter(|win| {
+                *win != window
+                    && !win.is_x11_override_redirect()
+                    && win.with_state(|state| state.fullscreen_or_maximized.is_neither())
+                    && win.is_on_active_tag(outputs.iter().copied())
+            })
+            .filter_map(|win| {
+                let other_center = center(self.space.element_geometry(win)?);
+                let (ahead, aside) = direction.distances(origin, other_center);
+                // Windows off to the side count double so ones in line are preferred
+                (ahead > 0).then_some((ahead + 2 * aside, win))
+            })
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, win)| win.clone())
+    }
+}
+
+fn center(geo: Rectangle<i32, Logical>) -> Point<i32, Logical> {
+    geo.loc + Point::from((geo.size.w / 2, geo.size.h / 2))
+}
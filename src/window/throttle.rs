@@ -0,0 +1,219 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Sending fewer frame callbacks to windows that can't be seen.
+//!
+//! Clients draw when they get a frame callback, so windows that keep animating in the
+//! background use power even though nothing they draw is shown. What happens to them depends
+//! on the background throttle policy:
+//!
+//! - Hidden windows are on tags that aren't active.
+//! - Occluded windows are on an active tag but completely covered by a single window above them.
+//!   Translucent windows count as covering, so an animation behind a see-through terminal slows
+//!   down too.
+//!
+//! Some clients stop drawing altogether or time out when they don't get frame callbacks for a
+//! while, so suspending hidden windows can break them until they're shown again. Windows can be
+//! exempted with the `never_throttle` window rule.
+
+use std::time::Duration;
+
+use smithay::{
+    desktop::Space,
+    output::Output,
+    reexports::wayland_server::protocol::wl_surface::WlSurface,
+    utils::{Logical, Rectangle},
+    wayland::compositor::SurfaceData,
+};
+
+use crate::state::WithState;
+
+use super::WindowElement;
+
+/// How frame callbacks are throttled for windows that can't be seen.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum BackgroundThrottle {
+    /// Every window gets frame callbacks whenever its output repaints.
+    #[default]
+    None,
+    /// Hidden and occluded windows get at most `fps` frame callbacks a second.
+    ThrottleOccluded { fps: u32 },
+    /// Hidden windows get no frame callbacks. Occluded windows get them as usual.
+    SuspendHidden,
+}
+
+/// How much of a window can be seen, as far as throttling is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Visibility {
+    Visible,
+    /// On an active tag but covered by a window above it
+    Occluded,
+    /// Not on an active tag
+    Hidden,
+}
+
+impl BackgroundThrottle {
+    /// Get the throttle to pass to [`WindowElement::send_frame`] for `window`,
+    /// or `None` if it shouldn't get a frame callback at all.
+    pub fn frame_throttle(
+        &self,
+        window: &WindowElement,
+        space: &Space<WindowElement>,
+    ) -> Option<Duration> {
+        if *self == BackgroundThrottle::None || window.with_state(|state| state.never_throttle) {
+            return Some(Duration::ZERO);
+        }
+
+        let visibility = if !window.is_on_active_tag(space.outputs()) {
+            Visibility::Hidden
+        } else if is_occluded(window, space) {
+            Visibility::Occluded
+        } else {
+            Visibility::Visible
+        };
+
+        self.throttle_for(visibility)
+    }
+
+    /// Get the throttle for a window with `visibility`, or `None` if it shouldn't get a frame
+    /// callback at all.
+    fn throttle_for(&self, visibility: Visibility) -> Option<Duration> {
+        let unthrottled = Some(Duration::ZERO);
+
+        match (self, visibility) {
+            (BackgroundThrottle::None, _) | (_, Visibility::Visible) => unthrottled,
+            (BackgroundThrottle::ThrottleOccluded { fps }, _) => {
+                Some(Duration::from_secs(1) / (*fps).max(1))
+            }
+            (BackgroundThrottle::SuspendHidden, Visibility::Occluded) => unthrottled,
+            (BackgroundThrottle::SuspendHidden, Visibility::Hidden) => None,
+        }
+    }
+}
+
+impl WindowElement {
+    /// Send a frame callback to this window on `output` if `background_throttle` allows it.
+    pub fn send_background_frame<T, F>(
+        &self,
+        output: &Output,
+        time: T,
+        space: &Space<WindowElement>,
+        background_throttle: BackgroundThrottle,
+        primary_scan_out_output: F,
+    ) where
+        T: Into<Duration>,
+        F: FnMut(&WlSurface, &SurfaceData) -> Option<Output> + Copy,
+    {
+        match background_throttle.frame_throttle(self, space) {
+            None => (),
+            Some(Duration::ZERO) => {
+                self.send_frame(output, time, Some(Duration::ZERO), primary_scan_out_output)
+            }
+            // Surfaces on their primary scanout output are never throttled
+            Some(throttle) => self.send_frame(output, time, Some(throttle), |_, _| None),
+        }
+    }
+}
+
+/// Whether a window above `window` covers all of it.
+fn is_occluded(window: &WindowElement, space: &Space<WindowElement>) -> bool {
+    let Some(geo) = space.element_geometry(window) else {
+        return false;
+    };
+
+    // Elements go from bottom to top
+    is_covered(
+        geo,
+        space
+            .elements()
+            .skip_while(|win| *win != window)
+            .skip(1)
+            .filter_map(|above| space.element_geometry(above)),
+    )
+}
+
+/// Whether one of the geometries of the windows above a window with `geo` covers all of it.
+fn is_covered(
+    geo: Rectangle<i32, Logical>,
+    mut above: impl Iterator<Item = Rectangle<i32, Logical>>,
+) -> bool {
+    above.any(|above| above.contains_rect(geo))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICIES: [BackgroundThrottle; 3] = [
+        BackgroundThrottle::None,
+        BackgroundThrottle::ThrottleOccluded { fps: 10 },
+        BackgroundThrottle::SuspendHidden,
+    ];
+
+    /// Count the frame callbacks a client with `visibility` gets in a second of its output
+    /// repainting at 60Hz, sent the way `send_frame` throttles them.
+    fn count_callbacks(policy: BackgroundThrottle, visibility: Visibility) -> u32 {
+        let Some(throttle) = policy.throttle_for(visibility) else {
+            return 0;
+        };
+
+        let frame = Duration::from_nanos(16_666_667);
+        let mut last_sent: Option<Duration> = None;
+        let mut callbacks = 0;
+
+        for i in 0..60 {
+            let time = frame * i;
+            if !last_sent.is_some_and(|last_sent| time - last_sent < throttle) {
+                last_sent = Some(time);
+                callbacks += 1;
+            }
+        }
+
+        callbacks
+    }
+
+    #[test]
+    fn callbacks_under_each_policy() {
+        let counts = POLICIES.map(|policy| {
+            [
+                Visibility::Visible,
+                Visibility::Occluded,
+                Visibility::Hidden,
+            ]
+            .map(|visibility| count_callbacks(policy, visibility))
+        });
+
+        // Visible, occluded, and hidden windows
+        assert_eq!(
+            counts,
+            [
+                [60, 60, 60], // None
+                [60, 10, 10], // ThrottleOccluded { fps: 10 }
+                [60, 60, 0],  // SuspendHidden
+            ]
+        );
+    }
+
+    #[test]
+    fn zero_fps_is_one_fps() {
+        assert_eq!(
+            BackgroundThrottle::ThrottleOccluded { fps: 0 }.throttle_for(Visibility::Occluded),
+            Some(Duration::from_secs(1))
+        );
+    }
+
+    fn rect(x: i32, y: i32, w: i32, h: i32) -> Rectangle<i32, Logical> {
+        Rectangle::from_loc_and_size((x, y), (w, h))
+    }
+
+    #[test]
+    fn only_a_single_window_covering_everything_occludes() {
+        let geo = rect(100, 100, 400, 300);
+        assert!(is_covered(geo, [rect(0, 0, 1920, 1080)].into_iter()));
+        assert!(is_covered(geo, [geo].into_iter()));
+
+        // Two windows that cover it together don't count
+        let halves = [rect(0, 0, 300, 1080), rect(300, 0, 1620, 1080)];
+        assert!(!is_covered(geo, halves.into_iter()));
+        assert!(!is_covered(geo, std::iter::empty()));
+    }
+}
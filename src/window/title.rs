@@ -0,0 +1,101 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Keeping track of window titles and classes.
+//!
+//! Titles and classes are read from the window's surface when it commits and cached in its
+//! [`WindowElementState`](super::window_state::WindowElementState), so getting them doesn't
+//! touch surface state. When one actually changes, the config is told about it.
+//!
+//! This happens for windows on inactive tags too, so bars can show what a hidden terminal
+//! is running. A changed title doesn't damage or relayout anything, since it isn't drawn.
+
+use smithay::wayland::{compositor, shell::xdg::XdgToplevelSurfaceData};
+
+use crate::{
+    api::msg::{Args, CallbackId, OutgoingMsg},
+    state::{State, WithState},
+};
+
+use super::WindowElement;
+
+impl WindowElement {
+    /// Read the window's title and class from its surface.
+    fn surface_title_and_class(&self) -> (Option<String>, Option<String>) {
+        match self {
+            WindowElement::Wayland(window) => {
+                compositor::with_states(window.toplevel().wl_surface(), |states| {
+                    let data = states
+                        .data_map
+                        .get::<XdgToplevelSurfaceData>()
+                        .expect("XdgToplevelSurfaceData wasn't in surface's data map")
+                        .lock()
+                        .expect("Failed to lock Mutex<XdgToplevelSurfaceData>");
+                    (data.title.clone(), data.app_id.clone())
+                })
+            }
+            WindowElement::X11(surface) | WindowElement::X11OverrideRedirect(surface) => {
+                (Some(surface.title()), Some(surface.class()))
+            }
+        }
+    }
+}
+
+impl State {
+    /// Update the cached title and class of `window` from its surface, telling the config
+    /// if either changed.
+    pub fn update_window_title_and_class(&self, window: &WindowElement) {
+        let (title, class) = window.surface_title_and_class();
+
+        let (title_changed, class_changed) = window.with_state(|state| {
+            let title_changed = state.title != title;
+            let class_changed = state.class != class;
+            state.title.clone_from(&title);
+            state.class.clone_from(&class);
+            (title_changed, class_changed)
+        });
+
+        let window_id = window.with_state(|state| state.id);
+
+        if title_changed {
+            tracing::trace!("Window title changed to {title:?}");
+            self.signal_window_props_changed(&self.config.window_title_callback_ids, || {
+                Args::WindowTitleChanged {
+                    window_id,
+                    title: title.clone(),
+                }
+            });
+        }
+
+        if class_changed {
+            tracing::trace!("Window class changed to {class:?}");
+            self.signal_window_props_changed(&self.config.window_class_callback_ids, || {
+                Args::WindowClassChanged {
+                    window_id,
+                    class: class.clone(),
+                }
+            });
+        }
+    }
+
+    pub(super) fn signal_window_props_changed(
+        &self,
+        callback_ids: &[CallbackId],
+        args: impl Fn() -> Args,
+    ) {
+        let Some(stream) = self.api_state.stream.as_ref() else {
+            return;
+        };
+
+        for callback_id in callback_ids {
+            crate::api::send_to_client(
+                &mut stream.lock().expect("couldn't lock stream"),
+                &OutgoingMsg::call_callback(
+                    *callback_id,
+                    Some(args()),
+                    &self.api_state.callback_serial,
+                ),
+            )
+            .expect("Send to client failed");
+        }
+    }
+}
@@ -0,0 +1,230 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! One-shot waits for windows that match a condition.
+//!
+//! A client can ask to be told when a window matching a [`WindowRuleCondition`] opens.
+//! The request is answered when such a window maps or when the timeout elapses,
+//! whichever happens first.
+//!
+//! Request ids are only unique per client, so waits are keyed by the client's stream
+//! along with the request id.
+
+use std::{
+    os::unix::net::UnixStream,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use smithay::reexports::calloop::{
+    timer::{TimeoutAction, Timer},
+    RegistrationToken,
+};
+
+use crate::{
+    api::msg::{OutgoingMsg, RequestId, RequestResponse},
+    state::{State, WithState},
+};
+
+use super::{rules::WindowRuleCondition, window_state::WindowId, WindowElement};
+
+/// A pending wait for a window.
+#[derive(Debug)]
+pub struct WindowWait {
+    /// The request this wait will respond to.
+    pub request_id: RequestId,
+    /// The condition a window needs to meet to resolve this wait.
+    pub condition: WindowRuleCondition,
+    /// The stream of the client that is waiting.
+    stream: Arc<Mutex<UnixStream>>,
+    /// The timer that will resolve this wait with no window.
+    timeout_token: RegistrationToken,
+}
+
+impl State {
+    /// Start waiting for a window that meets `condition`.
+    ///
+    /// If no window meets it within `timeout`, the request will be responded to with no window.
+    /// The response is sent through `stream`.
+    pub fn add_window_wait(
+        &mut self,
+        request_id: RequestId,
+        condition: WindowRuleCondition,
+        timeout: Duration,
+        stream: Arc<Mutex<UnixStream>>,
+    ) {
+        let wait_stream = stream.clone();
+        let timeout_token =
+            self.loop_handle
+                .insert_source(Timer::from_duration(timeout), move |_, _, data| {
+                    let waits = &mut data.state.api_state.window_waits;
+                    if let Some(wait) = take_wait(waits, &wait_stream, request_id) {
+                        tracing::debug!("Window wait for request {request_id:?} timed out");
+                        respond_to_window_wait(&wait.stream, request_id, None);
+                    }
+
+                    TimeoutAction::Drop
+                });
+
+        let timeout_token = match timeout_token {
+            Ok(token) => token,
+            Err(err) => {
+                tracing::error!("Failed to insert window wait timer: {err}");
+                respond_to_window_wait(&stream, request_id, None);
+                return;
+            }
+        };
+
+        self.api_state.window_waits.push(WindowWait {
+            request_id,
+            condition,
+            stream,
+            timeout_token,
+        });
+    }
+
+    /// Resolve the oldest pending wait that `window` meets the condition of, if any.
+    ///
+    /// A window only ever resolves one wait, so that multiple waits for the same kind of window
+    /// each get their own.
+    ///
+    /// RefCell Safety: This method uses RefCells on `window`.
+    pub fn resolve_window_wait(&mut self, window: &WindowElement) {
+        let Some(index) = self
+            .api_state
+            .window_waits
+            .iter()
+            .position(|wait| wait.condition.is_met(self, window))
+        else {
+            return;
+        };
+
+        let wait = self.api_state.window_waits.remove(index);
+        self.loop_handle.remove(wait.timeout_token);
+
+        let window_id = window.with_state(|state| state.id);
+        respond_to_window_wait(&wait.stream, wait.request_id, Some(window_id));
+    }
+
+    /// Drop the pending waits of the client on `stream` without responding to them.
+    ///
+    /// This should be called when that client goes away.
+    pub fn clear_window_waits_from(&mut self, stream: &Arc<Mutex<UnixStream>>) {
+        for wait in take_waits_from(&mut self.api_state.window_waits, stream) {
+            self.loop_handle.remove(wait.timeout_token);
+        }
+    }
+}
+
+impl WindowWait {
+    /// Whether the client on `stream` made this wait.
+    fn is_from(&self, stream: &Arc<Mutex<UnixStream>>) -> bool {
+        Arc::ptr_eq(&self.stream, stream)
+    }
+}
+
+/// Take the wait the client on `stream` made with `request_id` out of `waits`.
+fn take_wait(
+    waits: &mut Vec<WindowWait>,
+    stream: &Arc<Mutex<UnixStream>>,
+    request_id: RequestId,
+) -> Option<WindowWait> {
+    let index = waits
+        .iter()
+        .position(|wait| wait.is_from(stream) && wait.request_id == request_id)?;
+    Some(waits.remove(index))
+}
+
+/// Take every wait the client on `stream` made out of `waits`.
+fn take_waits_from(
+    waits: &mut Vec<WindowWait>,
+    stream: &Arc<Mutex<UnixStream>>,
+) -> Vec<WindowWait> {
+    let (taken, kept) = std::mem::take(waits)
+        .into_iter()
+        .partition(|wait| wait.is_from(stream));
+    *waits = kept;
+    taken
+}
+
+/// Send the response to a wait that has been removed.
+///
+/// If the client that was waiting has disconnected, there's no one to tell, so the wait is
+/// just dropped.
+pub fn respond_to_window_wait(
+    stream: &Mutex<UnixStream>,
+    request_id: RequestId,
+    window_id: Option<WindowId>,
+) {
+    if let Err(err) = crate::api::send_to_client(
+        &mut stream.lock().expect("Couldn't lock stream"),
+        &OutgoingMsg::RequestResponse {
+            request_id,
+            response: RequestResponse::Window { window_id },
+        },
+    ) {
+        tracing::warn!("Dropping window wait for request {request_id:?}: {err}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use smithay::reexports::calloop::EventLoop;
+
+    use super::*;
+
+    fn stream() -> Arc<Mutex<UnixStream>> {
+        let (stream, _) = UnixStream::pair().unwrap();
+        Arc::new(Mutex::new(stream))
+    }
+
+    fn wait(
+        event_loop: &EventLoop<()>,
+        stream: &Arc<Mutex<UnixStream>>,
+        request_id: u32,
+    ) -> WindowWait {
+        let timeout_token = event_loop
+            .handle()
+            .insert_source(Timer::immediate(), |_, _, _| TimeoutAction::Drop)
+            .unwrap();
+
+        WindowWait {
+            request_id: RequestId(request_id),
+            condition: toml::from_str("").unwrap(),
+            stream: stream.clone(),
+            timeout_token,
+        }
+    }
+
+    #[test]
+    fn waits_with_the_same_request_id_from_other_clients_are_kept() {
+        let event_loop = EventLoop::<()>::try_new().unwrap();
+        let (first, second) = (stream(), stream());
+        let mut waits = vec![wait(&event_loop, &first, 1), wait(&event_loop, &second, 1)];
+
+        let taken = take_wait(&mut waits, &second, RequestId(1)).unwrap();
+        assert!(taken.is_from(&second));
+        assert_eq!(waits.len(), 1);
+        assert!(waits[0].is_from(&first));
+
+        assert!(take_wait(&mut waits, &second, RequestId(1)).is_none());
+        assert!(take_wait(&mut waits, &first, RequestId(2)).is_none());
+        assert!(take_wait(&mut waits, &first, RequestId(1)).is_some());
+    }
+
+    #[test]
+    fn only_the_closed_clients_waits_are_taken() {
+        let event_loop = EventLoop::<()>::try_new().unwrap();
+        let (first, second) = (stream(), stream());
+        let mut waits = vec![
+            wait(&event_loop, &first, 1),
+            wait(&event_loop, &second, 1),
+            wait(&event_loop, &first, 2),
+        ];
+
+        let taken = take_waits_from(&mut waits, &first);
+        assert_eq!(taken.len(), 2);
+        assert!(taken.iter().all(|wait| wait.is_from(&first)));
+        assert_eq!(waits.len(), 1);
+        assert!(waits[0].is_from(&second));
+    }
+}
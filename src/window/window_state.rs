@@ -1,6 +1,9 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use std::sync::atomic::{AtomicU32, Ordering};
+use std::{
+    sync::atomic::{AtomicU32, Ordering},
+    time::Instant,
+};
 
 use smithay::{
     desktop::space::SpaceElement,
@@ -9,11 +12,12 @@ use smithay::{
 };
 
 use crate::{
+    output::OutputName,
     state::{State, WithState},
     tag::Tag,
 };
 
-use super::WindowElement;
+use super::{shape::X11Shape, WindowElement};
 
 /// A unique identifier for each window.
 #[derive(Debug, Hash, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -54,22 +58,55 @@ pub struct WindowElementState {
     pub tags: Vec<Tag>,
     pub floating_or_tiled: FloatingOrTiled,
     pub fullscreen_or_maximized: FullscreenOrMaximized,
+    /// Whether or not layouts should round this window's size down to its resize increments.
+    pub snap_to_increments: bool,
+    /// Whether or not client requests to fullscreen this window should be denied.
+    pub deny_fullscreen: bool,
+    /// When the window was last focused, unfocused, or clicked.
+    pub last_activity: Instant,
+    /// The output the window was on when it lost its last live tag, if it's orphaned.
+    pub orphaned_on: Option<OutputName>,
+    /// The window's title as of its last commit.
+    pub title: Option<String>,
+    /// The window's class as of its last commit.
+    pub class: Option<String>,
+    /// The shape of the window if it's a non-rectangular X11 window.
+    pub x11_shape: X11Shape,
+    /// The floating geometry the window had before it was dragged to an output's left or
+    /// right edge, so dragging it away gives it its old size back.
+    pub edge_tile_restore_geo: Option<Rectangle<i32, Logical>>,
+    /// Whether the window moves onto whatever tags become active on its output.
+    pub follow_active_tag: bool,
+    /// Whether the window gets every frame callback regardless of the background throttle.
+    pub never_throttle: bool,
 }
 
 /// The state of a window's resize operation.
+///
+/// A new geometry always replaces the one before it, so only the latest location is ever
+/// moved to, no matter how many geometry changes were made before the window caught up.
 #[derive(Debug, Default, Clone)]
 pub enum LocationRequestState {
     /// The window doesn't need to be moved.
     #[default]
     Idle,
+    /// The Wayland window has a new geometry that hasn't been sent to it yet.
+    /// [`State::update_windows`] sends it.
+    ///
+    /// [`State::update_windows`]: crate::state::State::update_windows
     Sent(Point<i32, Logical>),
     /// The window has received a configure request with a new size. The desired location and the
     /// configure request's serial should be provided here.
     Requested(Serial, Point<i32, Logical>),
-    /// The client has received the configure request and has successfully changed its size. It's
-    /// now safe to move the window in [`CompositorHandler.commit()`] without flickering.
+    /// The window has its new size, or never needed one, and only has to be moved.
+    ///
+    /// For Wayland windows, the client acknowledged the configure request, so it's now safe to
+    /// move the window in [`CompositorHandler.commit()`] without flickering. X11 windows are
+    /// configured right away, so they go straight here and are moved in
+    /// [`State::update_windows`].
     ///
     /// [`CompositorHandler.commit()`]: smithay::wayland::compositor::CompositorHandler#tymethod.commit
+    /// [`State::update_windows`]: crate::state::State::update_windows
     Acknowledged(Point<i32, Logical>),
 }
 
@@ -81,6 +118,47 @@ impl LocationRequestState {
     pub fn is_idle(&self) -> bool {
         matches!(self, Self::Idle)
     }
+
+    /// Record that a [`Sent`] geometry was sent to the window.
+    ///
+    /// If the window needed a new size, `serial` is the serial of the configure asking for it,
+    /// and the window waits for it to be acknowledged. Otherwise it only has to be moved.
+    ///
+    /// [`Sent`]: LocationRequestState::Sent
+    pub fn configured(&mut self, serial: Option<Serial>) {
+        if let Self::Sent(loc) = *self {
+            *self = match serial {
+                Some(serial) => Self::Requested(serial, loc),
+                None => Self::Acknowledged(loc),
+            };
+        }
+    }
+
+    /// Record that the window acknowledged the configure with `serial`, returning the location
+    /// it now has to be moved to if that was the configure it was waiting for or a newer one.
+    pub fn ack(&mut self, serial: Serial) -> Option<Point<i32, Logical>> {
+        match *self {
+            Self::Requested(requested, loc) if serial >= requested => {
+                *self = Self::Acknowledged(loc);
+                Some(loc)
+            }
+            _ => None,
+        }
+    }
+
+    /// Take the location the window has to be moved to if it's ready to be moved,
+    /// leaving it [`Idle`].
+    ///
+    /// [`Idle`]: LocationRequestState::Idle
+    pub fn take_acknowledged(&mut self) -> Option<Point<i32, Logical>> {
+        match *self {
+            Self::Acknowledged(loc) => {
+                *self = Self::Idle;
+                Some(loc)
+            }
+            _ => None,
+        }
+    }
 }
 
 impl WindowElement {
@@ -199,6 +277,55 @@ impl WindowElement {
         }
     }
 
+    /// Make this window floating or tiled. This does nothing if it already is.
+    ///
+    /// RefCell Safety: This method uses a [`RefCell`] on this window.
+    pub fn set_floating(&self, floating: bool) {
+        if self.with_state(|state| state.floating_or_tiled.is_floating()) != floating {
+            self.toggle_floating();
+        }
+    }
+
+    /// Make this window fullscreen or not. This does nothing if it already is.
+    ///
+    /// Making a maximized window fullscreen unmaximizes it. Making a window that isn't
+    /// fullscreen not fullscreen leaves it maximized if it was.
+    ///
+    /// RefCell Safety: This method uses a [`RefCell`] on this window.
+    pub fn set_fullscreen(&self, fullscreen: bool) {
+        let current = self.with_state(|state| state.fullscreen_or_maximized);
+        if current.with_fullscreen(fullscreen) != current {
+            self.toggle_fullscreen();
+        }
+    }
+
+    /// Make this window maximized or not. This does nothing if it already is.
+    ///
+    /// Making a fullscreen window maximized unfullscreens it. Making a window that isn't
+    /// maximized not maximized leaves it fullscreen if it was.
+    ///
+    /// RefCell Safety: This method uses a [`RefCell`] on this window.
+    pub fn set_maximized(&self, maximized: bool) {
+        let current = self.with_state(|state| state.fullscreen_or_maximized);
+        if current.with_maximized(maximized) != current {
+            self.toggle_maximized();
+        }
+    }
+
+    /// Put this window in `fullscreen_or_maximized`. This does nothing if it already is.
+    ///
+    /// RefCell Safety: This method uses a [`RefCell`] on this window.
+    pub fn set_fullscreen_or_maximized(&self, fullscreen_or_maximized: FullscreenOrMaximized) {
+        match fullscreen_or_maximized {
+            FullscreenOrMaximized::Fullscreen => self.set_fullscreen(true),
+            FullscreenOrMaximized::Maximized => self.set_maximized(true),
+            FullscreenOrMaximized::Neither => {
+                self.set_fullscreen(false);
+                self.set_maximized(false);
+            }
+        }
+    }
+
     /// Unsets maximized and fullscreen states for both wayland and xwayland windows
     /// and unsets tiled states for wayland windows.
     fn set_floating_states(&self) {
@@ -313,6 +440,41 @@ impl FullscreenOrMaximized {
     pub fn is_maximized(&self) -> bool {
         matches!(self, Self::Maximized)
     }
+
+    /// Get the state after making a window in this state fullscreen or not.
+    ///
+    /// A window that isn't fullscreen stays as it is when made not fullscreen.
+    #[must_use]
+    pub fn with_fullscreen(self, fullscreen: bool) -> Self {
+        match (self, fullscreen) {
+            (_, true) => Self::Fullscreen,
+            (Self::Fullscreen, false) => Self::Neither,
+            (other, false) => other,
+        }
+    }
+
+    /// Get the state after making a window in this state maximized or not.
+    ///
+    /// A window that isn't maximized stays as it is when made not maximized.
+    #[must_use]
+    pub fn with_maximized(self, maximized: bool) -> Self {
+        match (self, maximized) {
+            (_, true) => Self::Maximized,
+            (Self::Maximized, false) => Self::Neither,
+            (other, false) => other,
+        }
+    }
+
+    /// Get the state after putting a window in this state in `target`, the way
+    /// [`WindowElement::set_fullscreen_or_maximized`] does.
+    #[must_use]
+    pub fn with_target(self, target: Self) -> Self {
+        match target {
+            Self::Fullscreen => self.with_fullscreen(true),
+            Self::Maximized => self.with_maximized(true),
+            Self::Neither => self.with_fullscreen(false).with_maximized(false),
+        }
+    }
 }
 
 impl WindowElementState {
@@ -323,6 +485,179 @@ impl WindowElementState {
             tags: vec![],
             floating_or_tiled: FloatingOrTiled::Tiled(None),
             fullscreen_or_maximized: FullscreenOrMaximized::Neither,
+            snap_to_increments: false,
+            deny_fullscreen: false,
+            last_activity: Instant::now(),
+            orphaned_on: None,
+            title: None,
+            class: None,
+            x11_shape: X11Shape::default(),
+            edge_tile_restore_geo: None,
+            follow_active_tag: false,
+            never_throttle: false,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loc(x: i32, y: i32) -> Point<i32, Logical> {
+        (x, y).into()
+    }
+
+    fn assert_idle(state: &LocationRequestState) {
+        assert!(state.is_idle(), "{state:?} isn't idle");
+    }
+
+    #[test]
+    fn resize_waits_for_ack() {
+        let mut state = LocationRequestState::Sent(loc(10, 20));
+        state.configured(Some(Serial::from(5)));
+        assert!(matches!(state, LocationRequestState::Requested(_, l) if l == loc(10, 20)));
+        assert_eq!(state.take_acknowledged(), None);
+
+        assert_eq!(state.ack(Serial::from(5)), Some(loc(10, 20)));
+        assert_eq!(state.take_acknowledged(), Some(loc(10, 20)));
+        assert_idle(&state);
+    }
+
+    #[test]
+    fn move_without_resize_needs_no_ack() {
+        let mut state = LocationRequestState::Sent(loc(10, 20));
+        state.configured(None);
+        assert_eq!(state.take_acknowledged(), Some(loc(10, 20)));
+        assert_idle(&state);
+    }
+
+    #[test]
+    fn older_acks_are_ignored() {
+        let mut state = LocationRequestState::Sent(loc(10, 20));
+        state.configured(Some(Serial::from(5)));
+        assert_eq!(state.ack(Serial::from(4)), None);
+        assert!(matches!(state, LocationRequestState::Requested(..)));
+    }
+
+    #[test]
+    fn newer_acks_count() {
+        let mut state = LocationRequestState::Sent(loc(10, 20));
+        state.configured(Some(Serial::from(5)));
+        assert_eq!(state.ack(Serial::from(6)), Some(loc(10, 20)));
+    }
+
+    #[test]
+    fn new_geometry_supersedes_acknowledged_location() {
+        // A stale deferred move finds nothing to move once a newer geometry came in
+        let mut state = LocationRequestState::Sent(loc(10, 20));
+        state.configured(None);
+        state = LocationRequestState::Sent(loc(30, 40));
+        assert_eq!(state.take_acknowledged(), None);
+
+        state.configured(None);
+        assert_eq!(state.take_acknowledged(), Some(loc(30, 40)));
+    }
+
+    #[test]
+    fn new_geometry_supersedes_requested_location() {
+        let mut state = LocationRequestState::Sent(loc(10, 20));
+        state.configured(Some(Serial::from(5)));
+        state = LocationRequestState::Sent(loc(30, 40));
+
+        // The ack for the old configure doesn't move the window to the old location
+        assert_eq!(state.ack(Serial::from(5)), None);
+
+        state.configured(Some(Serial::from(6)));
+        assert_eq!(state.ack(Serial::from(6)), Some(loc(30, 40)));
+        assert_eq!(state.take_acknowledged(), Some(loc(30, 40)));
+    }
+
+    #[test]
+    fn only_sent_geometries_get_configured() {
+        let mut state = LocationRequestState::Idle;
+        state.configured(Some(Serial::from(5)));
+        assert_idle(&state);
+
+        let mut state = LocationRequestState::Acknowledged(loc(10, 20));
+        state.configured(Some(Serial::from(5)));
+        assert_eq!(state.take_acknowledged(), Some(loc(10, 20)));
+    }
+
+    #[test]
+    fn idle_has_nothing_to_do() {
+        let mut state = LocationRequestState::Idle;
+        assert_eq!(state.ack(Serial::from(5)), None);
+        assert_eq!(state.take_acknowledged(), None);
+        assert_idle(&state);
+    }
+
+    const ALL: [FullscreenOrMaximized; 3] = [
+        FullscreenOrMaximized::Neither,
+        FullscreenOrMaximized::Fullscreen,
+        FullscreenOrMaximized::Maximized,
+    ];
+
+    /// Apply `targets` the way the setters do, counting the toggles,
+    /// each of which sends a configure.
+    fn apply(
+        mut state: FullscreenOrMaximized,
+        targets: &[FullscreenOrMaximized],
+    ) -> (FullscreenOrMaximized, usize) {
+        let mut toggles = 0;
+        for &target in targets {
+            // `Neither` unsets fullscreen and maximized separately, but at most one of them changes
+            let next = state.with_target(target);
+            if next != state {
+                toggles += 1;
+            }
+            state = next;
+        }
+        (state, toggles)
+    }
+
+    #[test]
+    fn applying_a_state_twice_only_configures_once() {
+        for current in ALL {
+            for target in ALL {
+                let (once, once_toggles) = apply(current, &[target]);
+                let (twice, twice_toggles) = apply(current, &[target, target]);
+                assert_eq!(once, target);
+                assert_eq!(twice, target);
+                assert_eq!(once_toggles, usize::from(current != target));
+                assert_eq!(twice_toggles, once_toggles, "{current:?} -> {target:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn unsetting_leaves_the_other_state_alone() {
+        assert_eq!(
+            FullscreenOrMaximized::Maximized.with_fullscreen(false),
+            FullscreenOrMaximized::Maximized
+        );
+        assert_eq!(
+            FullscreenOrMaximized::Fullscreen.with_maximized(false),
+            FullscreenOrMaximized::Fullscreen
+        );
+        assert_eq!(
+            FullscreenOrMaximized::Fullscreen.with_fullscreen(false),
+            FullscreenOrMaximized::Neither
+        );
+        assert_eq!(
+            FullscreenOrMaximized::Maximized.with_maximized(false),
+            FullscreenOrMaximized::Neither
+        );
+    }
+
+    #[test]
+    fn setting_replaces_the_other_state() {
+        assert_eq!(
+            FullscreenOrMaximized::Maximized.with_fullscreen(true),
+            FullscreenOrMaximized::Fullscreen
+        );
+        assert_eq!(
+            FullscreenOrMaximized::Fullscreen.with_maximized(true),
+            FullscreenOrMaximized::Maximized
+        );
+    }
+}